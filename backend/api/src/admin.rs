@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+
+use run_sous_bpm_core::database::user;
+
+/// Users allowed to call the `/api/admin` operator endpoints
+///
+/// Read from `ADMIN_EMAILS` (comma-separated, case-insensitive). When unset,
+/// nobody is an admin and the admin endpoints always answer 403.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AdminAllowlist {
+    emails: HashSet<String>,
+}
+
+impl AdminAllowlist {
+    /// Builds the allowlist from a list of emails, ignoring blank entries
+    #[must_use]
+    pub fn new<'a>(emails: impl IntoIterator<Item = &'a str>) -> Self {
+        let emails = emails
+            .into_iter()
+            .map(str::trim)
+            .filter(|email| !email.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        Self { emails }
+    }
+
+    /// Reads the allowlist from `ADMIN_EMAILS`
+    #[must_use]
+    pub fn from_env() -> Self {
+        std::env::var("ADMIN_EMAILS")
+            .map(|emails| Self::new(emails.split(',')))
+            .unwrap_or_default()
+    }
+
+    /// Whether any admin is configured
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        !self.emails.is_empty()
+    }
+
+    /// Whether `user` may call admin endpoints
+    #[must_use]
+    pub fn is_admin(&self, user: &user::Model) -> bool {
+        self.emails.contains(&user.email.to_lowercase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use run_sous_bpm_core::test_support::make_user;
+
+    use super::*;
+
+    fn user_with_email(email: &str) -> user::Model {
+        user::Model {
+            email: email.to_string(),
+            ..make_user()
+        }
+    }
+
+    #[test]
+    fn test_listed_email_is_admin_case_insensitively() {
+        let admins = AdminAllowlist::new(" ops@example.com , Root@Example.com".split(','));
+
+        assert!(admins.is_admin(&user_with_email("ops@example.com")));
+        assert!(admins.is_admin(&user_with_email("root@example.com")));
+        assert!(!admins.is_admin(&user_with_email("runner@example.com")));
+    }
+
+    #[test]
+    fn test_empty_allowlist_has_no_admins() {
+        let admins = AdminAllowlist::new(" , ".split(','));
+
+        assert!(!admins.is_enabled());
+        assert!(!admins.is_admin(&user_with_email("ops@example.com")));
+    }
+}
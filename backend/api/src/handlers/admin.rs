@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use axum_login::AuthSession;
+use run_sous_bpm_core::{
+    auth::AuthBackend, crypto::PayloadVersionReport, database::oauth_token_repository,
+    services::reencrypt_all_oauth_tokens,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::{responses::ApiError, AppState};
+
+/// Default number of OAuth tokens inspected by the crypto status check
+const DEFAULT_CRYPTO_SAMPLE_SIZE: u64 = 1000;
+/// Upper bound on the crypto status sample, to keep the check cheap
+const MAX_CRYPTO_SAMPLE_SIZE: u64 = 10_000;
+
+/// Query parameters for the crypto status endpoint
+#[derive(Debug, Deserialize)]
+pub struct CryptoStatusQuery {
+    /// Number of OAuth tokens to inspect (default: 1000, max: 10000)
+    pub sample: Option<u64>,
+}
+
+/// Reports the encryption payload version in use and how many tokens are behind it
+///
+/// Tokens are sampled least recently updated first, so rows missed by a key
+/// rotation show up even in a partial sample.
+///
+/// # Errors
+///
+/// - `401 Unauthorized`: Not logged in
+/// - `403 Forbidden`: User is not listed in `ADMIN_EMAILS`
+/// - `500 Internal Server Error`: Database query failed
+pub async fn get_crypto_status(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Query(params): Query<CryptoStatusQuery>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
+    if !state.admins.is_admin(&user) {
+        return Err(ApiError::admin_required());
+    }
+
+    let sample = params
+        .sample
+        .unwrap_or(DEFAULT_CRYPTO_SAMPLE_SIZE)
+        .clamp(1, MAX_CRYPTO_SAMPLE_SIZE);
+    let payloads =
+        oauth_token_repository::sample_access_token_payloads(&state.db_connection, sample)
+            .await
+            .map_err(ApiError::database)?;
+    let report = PayloadVersionReport::from_payloads(payloads.iter().map(String::as_str));
+
+    info!(
+        user_id = %user.id,
+        sampled = report.sampled,
+        outdated = report.outdated,
+        unreadable = report.unreadable,
+        "Reported crypto payload status"
+    );
+
+    Ok((StatusCode::OK, Json(json!(report))))
+}
+
+/// Re-encrypts every stored OAuth token still on an older payload version
+///
+/// Makes a key rotation operable from the API: run it after deploying the new
+/// key, then check `/api/admin/crypto-status` until `outdated` is zero. Tokens
+/// are migrated in batches, each in its own transaction, so the call can be
+/// repeated safely if it fails halfway.
+///
+/// # Returns
+///
+/// - `200 OK`: `{ scanned, migrated, up_to_date, failed }`
+/// - `401 Unauthorized`: Not logged in
+/// - `403 Forbidden`: User is not listed in `ADMIN_EMAILS`
+/// - `500 Internal Server Error`: Database query failed
+pub async fn reencrypt_tokens(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
+    if !state.admins.is_admin(&user) {
+        return Err(ApiError::admin_required());
+    }
+
+    let report = reencrypt_all_oauth_tokens(&state.db_connection, &*state.encryption_service)
+        .await
+        .map_err(ApiError::database)?;
+
+    info!(
+        user_id = %user.id,
+        migrated = report.migrated,
+        failed = report.failed,
+        "Admin re-encrypted OAuth tokens"
+    );
+
+    Ok((StatusCode::OK, Json(json!(report))))
+}
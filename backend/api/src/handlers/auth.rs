@@ -1,133 +1,210 @@
 use std::sync::Arc;
 
 use axum::{extract::State, http::StatusCode, Json};
-use axum_login::AuthSession;
+use axum_login::{AuthSession, AuthnBackend};
 use run_sous_bpm_core::{
     auth::{hash_password, AuthBackend, Credentials},
     config::OAuthProvider,
-    database::{create_user, get_user_by_email},
-    services::is_oauth_provider_connected,
+    database::{create_user, get_user_by_email, revoke_user_refresh_tokens, user},
+    services::{
+        is_oauth_provider_connected, issue_refresh_token, redeem_refresh_token, IssuedRefreshToken,
+        RefreshTokenError,
+    },
 };
+use serde::Deserialize;
 use serde_json::{json, Value};
 use validator::Validate;
 
-use crate::AppState;
+use crate::{
+    responses::{ApiError, ErrorCode},
+    AppState,
+};
 
 pub async fn register_user(
     State(state): State<AppState>,
     Json(payload): Json<Credentials>,
-) -> (StatusCode, Json<Value>) {
-    if let Err(e) = payload.validate() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "Invalid input",
-                "message": e.to_string()
-            })),
-        );
-    }
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    payload
+        .validate()
+        .map_err(|e| ApiError::bad_request(ErrorCode::InvalidInput, e.to_string()))?;
 
     // Check if email already exists — return generic response to prevent email enumeration
-    match get_user_by_email(&state.db_connection, payload.email.clone()).await {
-        Ok(Some(_)) => {
-            return (
-                StatusCode::CREATED,
-                Json(json!({
-                    "message": "Registration submitted"
-                })),
-            );
-        }
-        Ok(None) => {}
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Database error",
-                    "message": e.to_string()
-                })),
-            );
-        }
-    }
-
-    let Ok(hash) = hash_password(&payload.password) else {
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({
-                "error": "Password hashing failed"
-            })),
-        );
-    };
-    match create_user(&state.db_connection, payload.email, hash).await {
-        Ok(user) => (
+    let existing_user = get_user_by_email(&state.db_connection, payload.email.clone())
+        .await
+        .map_err(ApiError::database)?;
+    if existing_user.is_some() {
+        return Ok((
             StatusCode::CREATED,
             Json(json!({
-                "id": user.id,
-                "email": user.email,
-            })),
-        ),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({
-                "error": "User creation failed",
-                "message": e.to_string()
+                "message": "Registration submitted"
             })),
-        ),
+        ));
     }
+
+    let hash = hash_password(&payload.password)
+        .map_err(|_| ApiError::internal(ErrorCode::InternalError, "Password hashing failed"))?;
+    let user = create_user(&state.db_connection, payload.email, hash)
+        .await
+        .map_err(ApiError::database)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "id": user.id,
+            "email": user.email,
+        })),
+    ))
 }
 
 pub async fn login_user(
+    State(state): State<AppState>,
     mut auth: AuthSession<AuthBackend>,
     Json(payload): Json<Credentials>,
-) -> (StatusCode, Json<Value>) {
-    if let Err(e) = payload.validate() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "Invalid input",
-                "message": e.to_string()
-            })),
-        );
-    }
-    let user = auth.authenticate(payload).await;
-    match user {
-        Ok(Some(user)) => {
-            if let Err(e) = auth.login(&user).await {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({
-                        "error": "Failed to create session",
-                        "message": e.to_string()
-                    })),
-                );
-            }
-            (
-                StatusCode::OK,
-                Json(json!({
-                    "message": "Login successful",
-                    "user": {
-                        "id": user.id,
-                        "email": user.email,
-                    }
-                })),
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    payload
+        .validate()
+        .map_err(|e| ApiError::bad_request(ErrorCode::InvalidInput, e.to_string()))?;
+
+    let remember_me = payload.remember_me;
+    let user = auth
+        .authenticate(payload)
+        .await
+        .map_err(|e| {
+            ApiError::internal(
+                ErrorCode::InternalError,
+                format!("Authentication failed: {e}"),
+            )
+        })?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                ErrorCode::InvalidCredentials,
+                "Invalid credentials",
+            )
+        })?;
+
+    auth.login(&user).await.map_err(|e| {
+        ApiError::internal(
+            ErrorCode::SessionError,
+            format!("Failed to create session: {e}"),
+        )
+    })?;
+
+    let refresh_token = if remember_me {
+        Some(
+            issue_refresh_token(state.db_connection.as_ref(), user.id)
+                .await
+                .map_err(ApiError::database)?,
+        )
+    } else {
+        None
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(session_body(
+            &state,
+            "Login successful",
+            &user,
+            refresh_token,
+        )),
+    ))
+}
+
+/// Request body for refresh token endpoint
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+/// Mints a fresh session from a "remember me" refresh token
+///
+/// The refresh token is rotated: the one presented is revoked and a new one is returned.
+pub async fn refresh_session(
+    State(state): State<AppState>,
+    mut auth: AuthSession<AuthBackend>,
+    Json(payload): Json<RefreshRequest>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let (user_id, refresh_token) =
+        redeem_refresh_token(&state.db_connection, &payload.refresh_token)
+            .await
+            .map_err(|e| match e {
+                RefreshTokenError::Database(e) => ApiError::database(e),
+                e => ApiError::new(
+                    StatusCode::UNAUTHORIZED,
+                    ErrorCode::InvalidRefreshToken,
+                    e.to_string(),
+                ),
+            })?;
+
+    let user = auth
+        .backend
+        .get_user(&user_id)
+        .await
+        .map_err(ApiError::database)?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                ErrorCode::InvalidRefreshToken,
+                "Invalid refresh token",
             )
+        })?;
+
+    auth.login(&user).await.map_err(|e| {
+        ApiError::internal(
+            ErrorCode::SessionError,
+            format!("Failed to create session: {e}"),
+        )
+    })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(session_body(
+            &state,
+            "Session refreshed",
+            &user,
+            Some(refresh_token),
+        )),
+    ))
+}
+
+/// Builds the login/refresh response body with any optional credentials
+fn session_body(
+    state: &AppState,
+    message: &str,
+    user: &user::Model,
+    refresh_token: Option<IssuedRefreshToken>,
+) -> Value {
+    let mut body = json!({
+        "message": message,
+        "user": {
+            "id": user.id,
+            "email": user.email,
         }
-        Ok(None) => (
-            StatusCode::UNAUTHORIZED,
-            Json(json!({
-                "error": "Invalid credentials"
-            })),
-        ),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({
-                "error": "Authentication failed",
-                "message": e.to_string()
-            })),
-        ),
+    });
+    // API clients can use the bearer token instead of the session cookie
+    if let Some(tokens) = &state.bearer_tokens {
+        body["access_token"] = json!(tokens.issue(user.id));
+        body["token_type"] = json!("Bearer");
+        body["expires_in"] = json!(tokens.ttl_seconds());
+    }
+    if let Some(refresh_token) = refresh_token {
+        body["refresh_token"] = json!(refresh_token.token);
+        body["refresh_token_expires_at"] = json!(refresh_token.expires_at);
     }
+    body
 }
 
-pub async fn logout_user(mut auth: AuthSession<AuthBackend>) -> (StatusCode, Json<Value>) {
+/// Ends the session and revokes the user's refresh tokens ("remember me" on all devices)
+pub async fn logout_user(
+    State(state): State<Arc<AppState>>,
+    mut auth: AuthSession<AuthBackend>,
+) -> (StatusCode, Json<Value>) {
+    if let Some(user) = &auth.user {
+        revoke_user_refresh_tokens(&state.db_connection, user.id)
+            .await
+            .ok();
+    }
     auth.logout().await.ok();
     (
         StatusCode::OK,
@@ -140,45 +217,37 @@ pub async fn logout_user(mut auth: AuthSession<AuthBackend>) -> (StatusCode, Jso
 pub async fn get_current_user(
     State(state): State<Arc<AppState>>,
     auth_session: AuthSession<AuthBackend>,
-) -> (StatusCode, Json<Value>) {
-    match auth_session.user {
-        Some(user) => {
-            let (is_connected_strava, is_connected_spotify) = {
-                (
-                    is_oauth_provider_connected(
-                        &state.db_connection,
-                        user.id,
-                        OAuthProvider::Strava,
-                    )
-                    .await
-                    .unwrap_or(false),
-                    is_oauth_provider_connected(
-                        &state.db_connection,
-                        user.id,
-                        OAuthProvider::Spotify,
-                    )
-                    .await
-                    .unwrap_or(false),
-                )
-            };
-            (
-                StatusCode::OK,
-                Json(json!({
-                    "id": user.id,
-                    "email": user.email,
-                    "lastfm_username": user.lastfm_username,
-                    "oauth_connections": {
-                        "strava": is_connected_strava,
-                        "spotify": is_connected_spotify
-                    }
-                })),
-            )
-        }
-        None => (
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user = auth_session.user.ok_or_else(|| {
+        ApiError::new(
             StatusCode::UNAUTHORIZED,
-            Json(json!({
-                "error": "Not authenticated"
-            })),
-        ),
-    }
+            ErrorCode::Unauthorized,
+            "Not authenticated",
+        )
+    })?;
+
+    let (is_connected_strava, is_connected_spotify) = {
+        (
+            is_oauth_provider_connected(&state.db_connection, user.id, OAuthProvider::Strava)
+                .await
+                .unwrap_or(false),
+            is_oauth_provider_connected(&state.db_connection, user.id, OAuthProvider::Spotify)
+                .await
+                .unwrap_or(false),
+        )
+    };
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "id": user.id,
+            "email": user.email,
+            "lastfm_username": user.lastfm_username,
+            "listen_padding_before_seconds": user.listen_padding_before_seconds,
+            "listen_padding_after_seconds": user.listen_padding_after_seconds,
+            "oauth_connections": {
+                "strava": is_connected_strava,
+                "spotify": is_connected_spotify
+            }
+        })),
+    ))
 }
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use axum_login::AuthSession;
+use run_sous_bpm_core::{
+    auth::AuthBackend,
+    services::{SyncEvent, SyncEventBus},
+};
+use sea_orm::prelude::Uuid;
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
+
+use crate::{responses::ApiError, AppState};
+
+/// Events buffered for a client that reads its stream slowly
+const CLIENT_EVENT_BUFFER: usize = 16;
+
+/// Streams the authenticated user's sync events as Server-Sent Events
+///
+/// Each event is named after its type (`activity_synced`, `streams_synced`) and
+/// carries the event as JSON. Only events of the authenticated user are sent.
+///
+/// # Returns
+///
+/// - `200 OK`: `text/event-stream` kept open until the client disconnects
+/// - `401 Unauthorized`: User not authenticated
+///
+/// # Example
+/// GET /api/events
+pub async fn sync_events(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
+
+    Ok(Sse::new(user_event_stream(&state.sync_events, user.id)).keep_alive(KeepAlive::default()))
+}
+
+/// Subscribes to a user's sync events and adapts them to SSE events
+///
+/// A forwarding task owns the subscription and stops as soon as the client
+/// disconnects, even if no further event is published.
+fn user_event_stream(
+    bus: &SyncEventBus,
+    user_id: Uuid,
+) -> impl Stream<Item = Result<Event, axum::Error>> {
+    let mut events = bus.subscribe(user_id);
+    let (sender, receiver) = mpsc::channel(CLIENT_EVENT_BUFFER);
+
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                () = sender.closed() => break,
+                event = events.recv() => match event {
+                    Some(event) => {
+                        if sender.send(event).await.is_err() {
+                            break;
+                        }
+                    }
+                    None => break,
+                },
+            }
+        }
+    });
+
+    ReceiverStream::new(receiver).map(|event| sse_event(&event))
+}
+
+fn sse_event(event: &SyncEvent) -> Result<Event, axum::Error> {
+    Event::default().event(event.name()).json_data(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_published_sync_event_is_received_on_the_stream() {
+        let bus = SyncEventBus::new();
+        let user_id = Uuid::new_v4();
+        let activity_id = Uuid::new_v4();
+        let mut stream = Box::pin(user_event_stream(&bus, user_id));
+
+        bus.publish(Uuid::new_v4(), SyncEvent::ActivitySynced { count: 2 });
+        bus.publish(
+            user_id,
+            SyncEvent::StreamsSynced {
+                activity_id,
+                points: 42,
+            },
+        );
+
+        let event = tokio::time::timeout(Duration::from_secs(1), stream.next())
+            .await
+            .expect("event should be received")
+            .unwrap()
+            .unwrap();
+        let expected = sse_event(&SyncEvent::StreamsSynced {
+            activity_id,
+            points: 42,
+        })
+        .unwrap();
+        assert_eq!(format!("{event:?}"), format!("{expected:?}"));
+    }
+
+    #[tokio::test]
+    async fn test_other_users_events_are_not_streamed() {
+        let bus = SyncEventBus::new();
+        let mut stream = Box::pin(user_event_stream(&bus, Uuid::new_v4()));
+
+        bus.publish(Uuid::new_v4(), SyncEvent::ActivitySynced { count: 2 });
+
+        let next = tokio::time::timeout(Duration::from_millis(100), stream.next()).await;
+        assert!(next.is_err(), "No event should reach another user");
+    }
+}
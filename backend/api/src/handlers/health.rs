@@ -1,13 +1,161 @@
-use axum::{http::StatusCode, response::Json};
+use axum::{extract::State, http::StatusCode, response::Json};
+use run_sous_bpm_integrations::common::{ReachabilityChecker, ReachabilityStatus};
+use sea_orm::DatabaseConnection;
 use serde_json::{json, Value};
 
-pub async fn health() -> (StatusCode, Json<Value>) {
+use crate::AppState;
+
+/// Liveness probe: always `200 OK` while the process is able to serve requests
+///
+/// Does not touch any dependency, so transient database outages never cause
+/// the orchestrator to restart an otherwise healthy process.
+pub async fn health_live() -> (StatusCode, Json<Value>) {
     (
         StatusCode::OK,
         Json(json!({
-            "status": "healthy",
+            "status": "alive",
+            "service": "run-sous-bpm-api",
+            "timestamp": chrono::Utc::now().to_rfc3339()
+        })),
+    )
+}
+
+/// Readiness probe: `200 OK` only when the database answers a ping
+///
+/// Also served on `/health` for backward compatibility.
+///
+/// # Returns
+///
+/// - `200 OK`: All dependencies reachable
+/// - `503 Service Unavailable`: Database unreachable
+pub async fn health_ready(State(state): State<AppState>) -> (StatusCode, Json<Value>) {
+    readiness(&state.db_connection).await
+}
+
+async fn readiness(db: &DatabaseConnection) -> (StatusCode, Json<Value>) {
+    let database_up = db.ping().await.is_ok();
+    let (status, label) = if database_up {
+        (StatusCode::OK, "healthy")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "unhealthy")
+    };
+
+    (
+        status,
+        Json(json!({
+            "status": label,
+            "service": "run-sous-bpm-api",
+            "checks": {
+                "database": if database_up { "up" } else { "down" }
+            },
+            "timestamp": chrono::Utc::now().to_rfc3339()
+        })),
+    )
+}
+
+/// Integration probe: whether the configured third-party providers are reachable
+///
+/// Each provider base URL gets one unauthenticated `HEAD` request with a short
+/// timeout, at most once per `DEFAULT_RESULT_TTL`: calls in between get the
+/// previous results. Disabled unless `HEALTH_CHECK_INTEGRATIONS=true`.
+///
+/// # Returns
+///
+/// - `200 OK`: Every provider reachable
+/// - `404 Not Found`: Integration checks disabled
+/// - `503 Service Unavailable`: At least one provider unreachable
+pub async fn health_integrations(State(state): State<AppState>) -> (StatusCode, Json<Value>) {
+    match &state.integration_health {
+        Some(checker) => integrations_report(checker).await,
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "status": "disabled",
+                "service": "run-sous-bpm-api",
+                "timestamp": chrono::Utc::now().to_rfc3339()
+            })),
+        ),
+    }
+}
+
+async fn integrations_report(checker: &ReachabilityChecker) -> (StatusCode, Json<Value>) {
+    let results = checker.check_all().await;
+    let all_up = results
+        .iter()
+        .all(|result| result.status == ReachabilityStatus::Up);
+    let (status, label) = if all_up {
+        (StatusCode::OK, "healthy")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "degraded")
+    };
+    let checks: serde_json::Map<String, Value> = results
+        .into_iter()
+        .map(|result| (result.name, json!(result.status)))
+        .collect();
+
+    (
+        status,
+        Json(json!({
+            "status": label,
             "service": "run-sous-bpm-api",
+            "checks": checks,
             "timestamp": chrono::Utc::now().to_rfc3339()
         })),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use run_sous_bpm_integrations::common::reachability::DEFAULT_PROBE_TIMEOUT;
+    use run_sous_bpm_integrations::test_support::{
+        closed_port_url, empty_response, spawn_mock_server,
+    };
+
+    async fn spawn_up_server() -> String {
+        spawn_mock_server(|_| empty_response("200 OK")).await
+    }
+
+    #[tokio::test]
+    async fn test_liveness_is_always_ok() {
+        let (status, Json(body)) = health_live().await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "alive");
+    }
+
+    #[tokio::test]
+    async fn test_readiness_fails_when_database_down() {
+        let db = DatabaseConnection::Disconnected;
+        let (status, Json(body)) = readiness(&db).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["checks"]["database"], "down");
+    }
+
+    #[tokio::test]
+    async fn test_integrations_healthy_when_all_providers_up() {
+        let checker = ReachabilityChecker::new(DEFAULT_PROBE_TIMEOUT)
+            .with_provider("strava", spawn_up_server().await)
+            .with_provider("lastfm", spawn_up_server().await);
+
+        let (status, Json(body)) = integrations_report(&checker).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "healthy");
+        assert_eq!(body["checks"]["strava"], "up");
+        assert_eq!(body["checks"]["lastfm"], "up");
+    }
+
+    #[tokio::test]
+    async fn test_integrations_degraded_when_a_provider_is_down() {
+        let checker = ReachabilityChecker::new(DEFAULT_PROBE_TIMEOUT)
+            .with_provider("strava", spawn_up_server().await)
+            .with_provider("lastfm", closed_port_url().await);
+
+        let (status, Json(body)) = integrations_report(&checker).await;
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "degraded");
+        assert_eq!(body["checks"]["strava"], "up");
+        assert_eq!(body["checks"]["lastfm"], "down");
+    }
+}
@@ -1,15 +1,23 @@
+pub mod admin;
 pub mod auth;
+pub mod events;
 pub mod health;
 pub mod music;
 pub mod oauth;
+mod ownership;
 pub mod root;
+pub mod stats;
 pub mod strava;
 pub mod user;
 
+pub use admin::*;
 pub use auth::*;
+pub use events::*;
 pub use health::*;
 pub use music::*;
 pub use oauth::*;
+pub(crate) use ownership::*;
 pub use root::*;
+pub use stats::*;
 pub use strava::*;
 pub use user::*;
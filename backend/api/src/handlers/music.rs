@@ -2,23 +2,43 @@ use std::sync::Arc;
 
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, StatusCode},
+    response::IntoResponse,
     Json,
 };
 use axum_login::AuthSession;
 use run_sous_bpm_core::{
     auth::AuthBackend,
-    database::get_user_by_id,
-    services::{analytics_service, get_lastfm_tracks_raw},
+    config::{lastfm_range_max_seconds, OAuthProvider},
+    database::{
+        activity_repository, get_listen_range_by_user, get_user_by_id, merge_tracks, track, user,
+    },
+    geo::SimplificationAlgorithm,
+    services::{
+        analytics_service::{
+            self, is_valid_density_bucket, is_valid_distance_bucket,
+            DEFAULT_LISTEN_DENSITY_BUCKET_SECONDS, MIN_DISTANCE_BUCKET_METERS,
+            MIN_LISTEN_DENSITY_BUCKET_SECONDS,
+        },
+        enrich_unenriched_tracks, get_lastfm_tracks_raw, get_valid_token, import_lastfm_export,
+        is_oauth_provider_connected, ActivityWindow, LastfmNotConfiguredError, ListenMatchOptions,
+        ListenPadding, SegmentationMode, SimplificationTolerance,
+    },
+    units::{LengthUnit, UnitSystem},
 };
-use sea_orm::prelude::Uuid;
+use sea_orm::{prelude::Uuid, DbErr};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use tracing::info;
 
+use super::{load_listened_track, load_owned_activity};
 use crate::{
     responses::{
-        ActivityMusicResponse, GpsPointResponse, LastFmRangeResponse, LastFmTrackInfo,
-        SegmentResponse, SimplificationStats, TrackInfo,
+        activity_music_csv, ActivityListenDensityResponse, ActivityMusicDistanceResponse,
+        ActivityMusicResponse, ActivityMusicTimelineResponse, ActivityTrackAtResponse, ApiError,
+        DensityBucketResponse, DistanceBucketResponse, ErrorCode, GpsPointResponse,
+        LastFmRangeResponse, LastFmTrackInfo, PointFields, SegmentResponse, SimplificationStats,
+        TimelineSegmentResponse, TrackDetailsResponse, TrackInfo, TrackPlayCountResponse,
     },
     AppState,
 };
@@ -28,108 +48,814 @@ use crate::{
 pub struct SimplificationQuery {
     /// Whether to apply GPS simplification
     pub simplify: Option<bool>,
-    /// Simplification tolerance in meters (default: 10.0)
-    pub tolerance: Option<f64>,
+    /// Simplification tolerance in `tolerance_unit`, or `auto` to scale it with the route length
+    /// (default: 10.0)
+    pub tolerance: Option<SimplificationTolerance>,
+    /// Unit of a numeric `tolerance`: `m` (default) or `ft`
+    pub tolerance_unit: Option<LengthUnit>,
+    /// Named route detail, `high`, `medium` or `low`, used when `tolerance` is not given
+    pub detail: Option<DetailPreset>,
+    /// Simplification algorithm: `rdp` (default) or `vw`
+    pub algorithm: Option<SimplificationAlgorithm>,
+    /// Unit system for the response values (default: metric)
+    pub units: Option<UnitSystem>,
+    /// Segmentation mode: `time` (default) or `distance`
+    pub mode: Option<SegmentationMode>,
+    /// Distance bucket size in meters for `mode=distance` (default: 1000, min: 10)
+    pub bucket: Option<f64>,
+    /// Seconds before the activity in which listens are matched (default: profile setting)
+    pub pad_before: Option<u32>,
+    /// Seconds after the activity in which listens are matched (default: profile setting)
+    pub pad_after: Option<u32>,
+    /// Music segments shorter than this many seconds are merged into the previous one
+    /// (default: 0, keep all)
+    pub min_segment_seconds: Option<u32>,
+    /// Recompute from stored listens only, without syncing Last.fm (default: false)
+    pub no_fetch: Option<bool>,
+    /// Activity duration listens are matched up to: `elapsed` (default) or `moving`
+    pub window: Option<ActivityWindow>,
+    /// Split segments where GPS fixes are more than this many seconds apart
+    /// (default: 0, never split)
+    pub split_gap_seconds: Option<u32>,
+    /// Attribute the Last.fm "now playing" track to the current time when syncing,
+    /// for activities still in progress (default: false)
+    pub include_now_playing: Option<bool>,
+    /// Point fields: `compact` (default) omits sensor values without a reading,
+    /// `full` always includes them
+    pub fields: Option<PointFields>,
+}
+
+/// Named level of route detail, for clients that don't want to pick a tolerance
+///
+/// Each preset maps to a fixed simplification tolerance:
+/// - `high`: 3 m, close to GPS precision, keeps nearly every turn
+/// - `medium`: 10 m, the default tolerance
+/// - `low`: 30 m, outline only, for thumbnails and overviews
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DetailPreset {
+    High,
+    Medium,
+    Low,
+}
+
+impl DetailPreset {
+    /// Simplification tolerance in meters for the preset
+    #[must_use]
+    pub fn tolerance_meters(self) -> f64 {
+        match self {
+            Self::High => 3.0,
+            Self::Medium => 10.0,
+            Self::Low => 30.0,
+        }
+    }
+}
+
+/// Resolves the simplification tolerance: an explicit `tolerance` first, then `detail`
+fn requested_tolerance(params: &SimplificationQuery) -> Option<SimplificationTolerance> {
+    params
+        .tolerance
+        .map(|tolerance| tolerance.in_unit(params.tolerance_unit.unwrap_or_default()))
+        .or_else(|| {
+            params
+                .detail
+                .map(|detail| SimplificationTolerance::Meters(detail.tolerance_meters()))
+        })
+}
+
+/// Resolves the listen padding: query overrides first, then the user's stored default
+fn listen_padding(
+    user: &user::Model,
+    params: &SimplificationQuery,
+) -> Result<ListenPadding, ApiError> {
+    ListenPadding::from_user(user)
+        .with_overrides(params.pad_before, params.pad_after)
+        .map_err(|e| ApiError::bad_request(ErrorCode::InvalidInput, e.to_string()))
 }
 
+/// Default distance bucket size in meters (one kilometer)
+const DEFAULT_DISTANCE_BUCKET_METERS: f64 = 1000.0;
+
 pub async fn get_activity_music(
     State(state): State<Arc<AppState>>,
     auth_session: AuthSession<AuthBackend>,
     Path(activity_id): Path<String>,
     Query(params): Query<SimplificationQuery>,
-) -> (StatusCode, Json<Value>) {
-    let Some(user) = auth_session.user else {
-        return (
-            StatusCode::UNAUTHORIZED,
-            Json(json!({
-                "error": "Unauthorized"
-            })),
-        );
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
+    let activity_id = Uuid::parse_str(&activity_id).map_err(|_| ApiError::invalid_activity_id())?;
+    // Reject foreign activities before triggering a Last.fm sync
+    load_owned_activity(&state.db_connection, user.id, activity_id).await?;
+    let units = params.units.unwrap_or_default();
+    let matching = ListenMatchOptions {
+        padding: listen_padding(&user, &params)?,
+        min_segment_seconds: params.min_segment_seconds.unwrap_or(0),
+        no_fetch: params.no_fetch.unwrap_or(false),
+        window: params.window.unwrap_or_default(),
+        split_gap_seconds: params.split_gap_seconds.unwrap_or(0),
+        include_now_playing: params.include_now_playing.unwrap_or(false),
     };
-    let Ok(activity_id) = Uuid::parse_str(&activity_id) else {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "Invalid activity ID format"
-            })),
-        );
-    };
-    match analytics_service::get_activity_music(
+    if params.mode.unwrap_or_default() == SegmentationMode::Distance {
+        return get_activity_music_by_distance(
+            &state,
+            user.id,
+            activity_id,
+            params.bucket.unwrap_or(DEFAULT_DISTANCE_BUCKET_METERS),
+            units,
+            matching,
+        )
+        .await;
+    }
+    let (segments, simplification_stats) = analytics_service::get_activity_music(
         &state.db_connection,
         user.id,
         activity_id,
         params.simplify.unwrap_or(true),
-        params.tolerance,
+        requested_tolerance(&params),
+        params.algorithm.unwrap_or_default(),
+        matching,
     )
     .await
-    {
-        Ok((segments, simplification_stats)) => {
-            // Convert service layer Segment to API SegmentResponse
-            let segment_responses: Vec<SegmentResponse> = segments
+    .map_err(|e| activity_music_error(e.as_ref()))?;
+    let fields = params.fields.unwrap_or_default();
+
+    // Convert service layer Segment to API SegmentResponse
+    let segment_responses: Vec<SegmentResponse> = segments
+        .into_iter()
+        .map(|segment| {
+            let duration_seconds = segment.duration_seconds();
+            let track = segment.track.map(track_info);
+
+            // Points without coordinates (indoor activities) are kept with null lat/lng
+            let points: Vec<GpsPointResponse> = segment
+                .points
                 .into_iter()
-                .map(|segment| {
-                    let track = segment.track.map(|t| TrackInfo {
-                        id: t.id,
-                        track_name: t.track_name,
-                        artist_name: t.artist_name,
-                        album_name: t.album_name,
-                    });
-
-                    let points: Vec<GpsPointResponse> = segment
-                        .points
-                        .into_iter()
-                        .filter_map(|p| match (p.latitude, p.longitude) {
-                            (Some(lat), Some(lng)) => Some(GpsPointResponse {
-                                time: p.time.with_timezone(&chrono::Utc),
-                                latitude: lat,
-                                longitude: lng,
-                                altitude: p.altitude,
-                                heart_rate: p.heart_rate,
-                                cadence: p.cadence,
-                                watts: p.watts,
-                                velocity: p.velocity,
-                            }),
-                            _ => None,
-                        })
-                        .collect();
-
-                    SegmentResponse {
-                        index: segment.index,
-                        track,
-                        start_time: segment.start_time,
-                        end_time: segment.end_time,
-                        points,
-                    }
+                .map(|p| GpsPointResponse {
+                    time: p.time.with_timezone(&chrono::Utc),
+                    latitude: p.latitude,
+                    longitude: p.longitude,
+                    altitude: p.altitude.map(|a| units.elevation(a)),
+                    heart_rate: p.heart_rate,
+                    cadence: p.cadence,
+                    watts: p.watts,
+                    velocity: p.velocity.map(|v| units.speed(v)),
+                    fields,
                 })
                 .collect();
 
-            let has_gps = segment_responses.iter().any(|s| !s.points.is_empty());
+            SegmentResponse {
+                index: segment.index,
+                track,
+                start_time: segment.start_time,
+                end_time: segment.end_time,
+                duration_seconds,
+                points,
+                avg_temperature: segment.avg_temperature,
+                repeated: segment.repeated,
+                after_gap: segment.after_gap,
+            }
+        })
+        .collect();
 
-            let response = ActivityMusicResponse {
-                activity_id,
-                has_gps,
-                segments: segment_responses,
-                stats: SimplificationStats {
-                    total_segments: simplification_stats.total_segments,
-                    segments_with_music: simplification_stats.segments_with_music,
-                    segments_without_music: simplification_stats.segments_without_music,
-                    original_points: simplification_stats.original_points,
-                    simplified_points: simplification_stats.simplified_points,
-                    reduction_ratio: simplification_stats.reduction_ratio,
-                },
-            };
-
-            (StatusCode::OK, Json(json!(response)))
+    let has_gps = segment_responses
+        .iter()
+        .flat_map(|s| &s.points)
+        .any(|p| p.latitude.is_some() && p.longitude.is_some());
+
+    let response = ActivityMusicResponse {
+        activity_id,
+        has_gps,
+        units,
+        segments: segment_responses,
+        stats: SimplificationStats {
+            total_segments: simplification_stats.total_segments,
+            segments_with_music: simplification_stats.segments_with_music,
+            segments_without_music: simplification_stats.segments_without_music,
+            original_points: simplification_stats.original_points,
+            simplified_points: simplification_stats.simplified_points,
+            reduction_ratio: simplification_stats.reduction_ratio,
+            music_coverage_ratio: simplification_stats.music_coverage_ratio,
+            avg_bpm: simplification_stats.avg_bpm,
+            avg_temperature: simplification_stats.avg_temperature,
+            play_counts: simplification_stats
+                .play_counts
+                .into_iter()
+                .map(|count| TrackPlayCountResponse {
+                    track: track_info(count.track),
+                    play_count: count.play_count,
+                })
+                .collect(),
+        },
+    };
+
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+/// Distance mode of the music endpoint: dominant track per distance bucket
+async fn get_activity_music_by_distance(
+    state: &AppState,
+    user_id: Uuid,
+    activity_id: Uuid,
+    bucket_meters: f64,
+    units: UnitSystem,
+    matching: ListenMatchOptions,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    if !is_valid_distance_bucket(bucket_meters) {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidInput,
+            format!("bucket must be at least {MIN_DISTANCE_BUCKET_METERS} meters"),
+        ));
+    }
+
+    let buckets = analytics_service::get_activity_music_by_distance(
+        &state.db_connection,
+        user_id,
+        activity_id,
+        bucket_meters,
+        matching,
+    )
+    .await
+    .map_err(|e| activity_music_error(e.as_ref()))?;
+
+    let response = ActivityMusicDistanceResponse {
+        activity_id,
+        mode: SegmentationMode::Distance,
+        bucket_meters,
+        units,
+        buckets: buckets
+            .into_iter()
+            .map(|bucket| DistanceBucketResponse {
+                index: bucket.index,
+                start_distance: units.distance(bucket.start_distance),
+                end_distance: units.distance(bucket.end_distance),
+                track: bucket.track.map(track_info),
+                dominant_seconds: bucket.dominant_seconds,
+                total_seconds: bucket.total_seconds,
+            })
+            .collect(),
+    };
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+/// Lightweight track timeline of an activity, without GPS points
+///
+/// Intended for list previews: returns each segment's track and time bounds only,
+/// keeping the payload small compared to the full music endpoint.
+pub async fn get_activity_music_timeline(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Path(activity_id): Path<String>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
+    let activity_id = Uuid::parse_str(&activity_id).map_err(|_| ApiError::invalid_activity_id())?;
+    // Points are discarded, so skip simplification entirely
+    let (segments, stats) = analytics_service::get_activity_music(
+        &state.db_connection,
+        user.id,
+        activity_id,
+        false,
+        None,
+        SimplificationAlgorithm::default(),
+        ListenMatchOptions::with_padding(ListenPadding::from_user(&user)),
+    )
+    .await
+    .map_err(|e| activity_music_error(e.as_ref()))?;
+
+    let response = ActivityMusicTimelineResponse {
+        activity_id,
+        music_coverage_ratio: stats.music_coverage_ratio,
+        segments: segments.into_iter().map(timeline_segment).collect(),
+    };
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+/// Query parameters for the listen density endpoint
+#[derive(Debug, Deserialize)]
+pub struct ListenDensityQuery {
+    /// Bucket size in seconds (default: `DEFAULT_LISTEN_DENSITY_BUCKET_SECONDS`)
+    pub bucket_seconds: Option<u32>,
+}
+
+/// Counts the distinct tracks played in each time bucket of an activity
+///
+/// Meant for a music intensity chart: many tracks in a bucket mean skipping or
+/// variety. Only the activity's listens are looked up, no GPS streams.
+///
+/// # Returns
+///
+/// - `200 OK`: `{ activity_id, bucket_seconds, buckets }`, covering the whole activity
+/// - `400 Bad Request`: Invalid activity ID or a bucket size below `MIN_LISTEN_DENSITY_BUCKET_SECONDS`
+/// - `401 Unauthorized`: User not authenticated
+/// - `404 Not Found`: Activity not found or not owned by the user
+/// - `409 Conflict`: No Last.fm account linked (`lastfm_not_configured`)
+///
+/// # Example
+/// GET /api/activities/{id}/music/density?bucket_seconds=300
+pub async fn get_activity_listen_density(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Path(activity_id): Path<String>,
+    Query(params): Query<ListenDensityQuery>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
+    let activity_id = Uuid::parse_str(&activity_id).map_err(|_| ApiError::invalid_activity_id())?;
+    let bucket_seconds = params
+        .bucket_seconds
+        .unwrap_or(DEFAULT_LISTEN_DENSITY_BUCKET_SECONDS);
+    if !is_valid_density_bucket(bucket_seconds) {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidInput,
+            format!("bucket_seconds must be at least {MIN_LISTEN_DENSITY_BUCKET_SECONDS}"),
+        ));
+    }
+
+    let buckets = analytics_service::get_activity_listen_density(
+        &state.db_connection,
+        user.id,
+        activity_id,
+        bucket_seconds,
+        ListenMatchOptions::with_padding(ListenPadding::from_user(&user)),
+    )
+    .await
+    .map_err(|e| activity_music_error(e.as_ref()))?;
+
+    let response = ActivityListenDensityResponse {
+        activity_id,
+        bucket_seconds,
+        buckets: buckets
+            .into_iter()
+            .map(|bucket| DensityBucketResponse {
+                index: bucket.index,
+                start_time: bucket.start_time,
+                end_time: bucket.end_time,
+                distinct_tracks: bucket.distinct_tracks,
+            })
+            .collect(),
+    };
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+/// Query parameters for the track-at-timestamp endpoint
+#[derive(Debug, Deserialize)]
+pub struct TrackAtQuery {
+    /// Unix timestamp (seconds) within the activity window
+    pub t: i64,
+}
+
+/// Returns the track that was playing and the distance covered at a given moment of an activity
+///
+/// Meant for a map scrubber: only the activity's stored listens and the two distance
+/// readings around the moment are looked up, no full GPS streams and no Last.fm sync.
+///
+/// # Returns
+///
+/// - `200 OK`: `{ activity_id, at, track, distance }`, with `track: null` when no music
+///   was playing and `distance: null` without a distance stream
+/// - `400 Bad Request`: Invalid activity ID or timestamp outside the activity window
+/// - `401 Unauthorized`: User not authenticated
+/// - `404 Not Found`: Activity not found or not owned by the user
+///
+/// # Example
+/// GET /api/activities/{id}/music/at?t=1730298000
+pub async fn get_activity_track_at(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Path(activity_id): Path<String>,
+    Query(params): Query<TrackAtQuery>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
+    let activity_id = Uuid::parse_str(&activity_id).map_err(|_| ApiError::invalid_activity_id())?;
+    let at = chrono::DateTime::from_timestamp(params.t, 0).ok_or_else(|| {
+        ApiError::bad_request(ErrorCode::InvalidInput, "Timestamp is out of range")
+    })?;
+
+    let moment = analytics_service::get_activity_track_at(
+        &state.db_connection,
+        user.id,
+        activity_id,
+        at,
+        ListenPadding::from_user(&user),
+    )
+    .await
+    .map_err(|e| activity_music_error(e.as_ref()))?;
+
+    let response = ActivityTrackAtResponse {
+        activity_id,
+        at,
+        track: moment.track.map(track_info),
+        distance: moment.distance,
+    };
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+/// Request body for the activity time offset endpoint
+#[derive(Debug, Deserialize)]
+pub struct TimeOffsetRequest {
+    /// Seconds added to listen timestamps, positive when the music clock runs behind
+    pub time_offset_seconds: i32,
+}
+
+/// Sets the offset applied to listen timestamps when matching them to an activity
+///
+/// Lets a user nudge the alignment when their music device clock drifted from the
+/// GPS watch, until songs line up with the route.
+///
+/// # Returns
+///
+/// - `200 OK`: `{ activity_id, time_offset_seconds }`
+/// - `400 Bad Request`: Invalid activity ID or offset beyond `MAX_TIME_OFFSET_SECONDS`
+/// - `401 Unauthorized`: User not authenticated
+/// - `404 Not Found`: Activity not found or not owned by the user
+/// - `500 Internal Server Error`: Database operation failed
+///
+/// # Example
+/// PATCH /api/activities/{id}/music/offset with `{"time_offset_seconds": 120}`
+pub async fn set_activity_time_offset(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Path(activity_id): Path<String>,
+    Json(request): Json<TimeOffsetRequest>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
+    let activity_id = Uuid::parse_str(&activity_id).map_err(|_| ApiError::invalid_activity_id())?;
+    let time_offset_seconds = analytics_service::validate_time_offset(request.time_offset_seconds)
+        .map_err(|e| ApiError::bad_request(ErrorCode::InvalidInput, e.to_string()))?;
+
+    load_owned_activity(&state.db_connection, user.id, activity_id).await?;
+
+    let activity = activity_repository::update_activity_time_offset(
+        &state.db_connection,
+        activity_id,
+        time_offset_seconds,
+    )
+    .await
+    .map_err(ApiError::database)?;
+
+    info!(
+        user_id = %user.id,
+        activity_id = %activity_id,
+        time_offset_seconds,
+        "Updated activity listen time offset"
+    );
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "activity_id": activity.id,
+            "time_offset_seconds": activity.time_offset_seconds,
+        })),
+    ))
+}
+
+/// Maps an activity music service error to a structured API error
+///
+/// The analytics service reports failures as plain messages, so the known ones
+/// are recognized by wording to give clients a stable code.
+fn activity_music_error(error: &(dyn std::error::Error + 'static)) -> ApiError {
+    if let Some(api_error) =
+        ApiError::music_not_configured(error).or_else(|| ApiError::lastfm_failure(error))
+    {
+        return api_error;
+    }
+    if error.is::<LastfmNotConfiguredError>() {
+        return ApiError::lastfm_not_configured();
+    }
+
+    let message = error.to_string();
+    match message.as_str() {
+        "Activity not found" | "Activity does not belong to the user" => {
+            ApiError::activity_not_found()
         }
-        Err(e) => (
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": e.to_string()
-            })),
+        "Timestamp is outside the activity window" => {
+            ApiError::bad_request(ErrorCode::InvalidInput, message)
+        }
+        _ => ApiError::bad_request(ErrorCode::ActivityMusicFailed, message),
+    }
+}
+
+/// Exports an activity's music segments as CSV for spreadsheet analysis
+///
+/// One row per segment with track, time bounds, duration, distance, average pace
+/// and average heart rate. Served as a file download.
+///
+/// # Returns
+///
+/// - `200 OK`: `text/csv` attachment
+/// - `400 Bad Request`: Invalid activity ID format or music retrieval failure
+/// - `401 Unauthorized`: User not authenticated
+/// - `404 Not Found`: Activity not found or not owned by the user
+/// - `409 Conflict`: No Last.fm account linked (`lastfm_not_configured`)
+/// - `503 Service Unavailable`: Last.fm integration not configured on the server
+///
+/// # Example
+/// GET /api/activities/{id}/music.csv
+pub async fn export_activity_music_csv(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Path(activity_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
+    let activity_id = Uuid::parse_str(&activity_id).map_err(|_| ApiError::invalid_activity_id())?;
+
+    // Keep every point so distance, pace and heart rate use the full streams
+    let (segments, _) = analytics_service::get_activity_music(
+        &state.db_connection,
+        user.id,
+        activity_id,
+        false,
+        None,
+        SimplificationAlgorithm::default(),
+        ListenMatchOptions::with_padding(ListenPadding::from_user(&user)),
+    )
+    .await
+    .map_err(|e| activity_music_error(e.as_ref()))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"activity-{activity_id}-music.csv\""),
+            ),
+        ],
+        activity_music_csv(&segments),
+    ))
+}
+
+/// Projects a service segment onto the timeline response, dropping its points
+fn timeline_segment(segment: analytics_service::Segment) -> TimelineSegmentResponse {
+    TimelineSegmentResponse {
+        index: segment.index,
+        duration_seconds: segment.duration_seconds(),
+        track: segment.track.map(track_info),
+        start_time: segment.start_time,
+        end_time: segment.end_time,
+    }
+}
+
+fn track_info(track: track::Model) -> TrackInfo {
+    TrackInfo {
+        id: track.id,
+        track_name: track.track_name,
+        artist_name: track.artist_name,
+        album_name: track.album_name,
+        bpm: track.bpm,
+    }
+}
+
+/// Returns a track's full metadata, including its `MusicBrainz` IDs and Last.fm URL
+///
+/// Lets the frontend deep-link a track to `MusicBrainz` or Last.fm.
+///
+/// # Returns
+///
+/// - `200 OK`: Track metadata
+/// - `400 Bad Request`: Invalid track ID format
+/// - `401 Unauthorized`: User not authenticated
+/// - `404 Not Found`: Track missing or never listened to by the user
+/// - `500 Internal Server Error`: Database query failed
+pub async fn get_track(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Path(track_id): Path<String>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
+    let track_id = Uuid::parse_str(&track_id)
+        .map_err(|_| ApiError::bad_request(ErrorCode::InvalidInput, "Invalid track ID format"))?;
+    let track = load_listened_track(&state.db_connection, user.id, track_id).await?;
+
+    let response = TrackDetailsResponse {
+        id: track.id,
+        track_name: track.track_name,
+        artist_name: track.artist_name,
+        album_name: track.album_name,
+        artist_mbid: track.artist_mbid,
+        track_mbid: track.track_mbid,
+        album_mbid: track.album_mbid,
+        lastfm_url: track.lastfm_url,
+        bpm: track.bpm,
+        spotify_id: track.spotify_id,
+    };
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+/// Returns when the user's listening history starts and ends, with its listen count
+///
+/// Meant for the profile page. Both timestamps are `null` when the user has no
+/// listens yet.
+///
+/// # Returns
+///
+/// - `200 OK`: `{ first_listen_at, last_listen_at, total_listens }`
+/// - `401 Unauthorized`: User not authenticated
+/// - `500 Internal Server Error`: Database query failed
+pub async fn get_listen_range(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
+
+    let range = get_listen_range_by_user(&state.db_connection, user.id)
+        .await
+        .map_err(ApiError::database)?;
+
+    Ok((StatusCode::OK, Json(json!(range))))
+}
+
+/// Maximum accepted size of an uploaded Last.fm export
+pub const MAX_LASTFM_EXPORT_BYTES: usize = 20 * 1024 * 1024;
+
+/// Number of skipped rows echoed back in the import report
+const MAX_REPORTED_SKIPPED_ROWS: usize = 100;
+
+/// Backfills listens from an uploaded Last.fm export (CSV or JSON request body)
+///
+/// # Returns
+///
+/// - `200 OK`: Import report with inserted, duplicate and skipped row counts
+/// - `400 Bad Request`: Empty or unparseable export
+/// - `401 Unauthorized`: User not authenticated
+/// - `500 Internal Server Error`: Database insertion failed
+///
+/// # Example
+/// POST /api/music/import with the export file as the raw request body
+pub async fn import_lastfm_listens(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    body: String,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
+
+    if body.trim().is_empty() {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidExport,
+            "Export file is empty",
+        ));
+    }
+
+    let report = import_lastfm_export(user.id, &body, &state.db_connection)
+        .await
+        .map_err(|e| import_error(e.as_ref()))?;
+
+    let skipped_rows: Vec<Value> = report
+        .skipped
+        .iter()
+        .take(MAX_REPORTED_SKIPPED_ROWS)
+        .map(|row| json!({ "row": row.row, "reason": row.reason }))
+        .collect();
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "total_rows": report.total_rows,
+            "inserted": report.inserted,
+            "duplicates": report.duplicates,
+            "skipped": report.skipped.len(),
+            "skipped_rows": skipped_rows
+        })),
+    ))
+}
+
+/// Maps a Last.fm export import failure to a 500 for the database, a 400 for the export
+fn import_error(error: &(dyn std::error::Error + 'static)) -> ApiError {
+    match error.downcast_ref::<DbErr>() {
+        Some(db_error) => ApiError::database(db_error),
+        None => ApiError::bad_request(
+            ErrorCode::InvalidExport,
+            format!("Failed to import Last.fm export: {error}"),
         ),
     }
 }
 
+/// Matches the user's tracks without a Spotify match against Spotify's catalog
+///
+/// Only unenriched tracks are searched, so the call is idempotent. When Spotify keeps
+/// throttling, the run stops early and reports the tracks left as `remaining`;
+/// calling again resumes with them.
+///
+/// # Returns
+///
+/// - `200 OK`: `{ matched, unmatched, remaining }` track counts
+/// - `401 Unauthorized`: User not authenticated
+/// - `403 Forbidden`: Spotify token lacks a required scope, reconnect Spotify
+/// - `409 Conflict`: Spotify is not connected
+/// - `502 Bad Gateway`: Spotify API error
+///
+/// # Example
+/// POST /api/music/enrich
+pub async fn enrich_spotify_tracks(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
+
+    let connected =
+        is_oauth_provider_connected(&state.db_connection, user.id, OAuthProvider::Spotify)
+            .await
+            .map_err(ApiError::database)?;
+    if !connected {
+        return Err(ApiError::spotify_not_connected());
+    }
+
+    let token = get_valid_token(
+        &state.db_connection,
+        user.id,
+        OAuthProvider::Spotify,
+        state.encryption_service.as_ref(),
+    )
+    .await
+    .map_err(|e| spotify_error(e.as_ref(), "get Spotify token"))?;
+
+    let report = enrich_unenriched_tracks(
+        &state.db_connection,
+        &state.spotify_client,
+        token.as_str(),
+        user.id,
+    )
+    .await
+    .map_err(|e| spotify_error(e.as_ref(), "enrich tracks"))?;
+
+    Ok((StatusCode::OK, Json(json!(report))))
+}
+
+fn spotify_error(err: &(dyn std::error::Error + 'static), action: &str) -> ApiError {
+    ApiError::reconnect_required(err).unwrap_or_else(|| {
+        ApiError::bad_gateway(
+            ErrorCode::SpotifyError,
+            format!("Failed to {action}: {err}"),
+        )
+    })
+}
+
+/// Request body for the track merge endpoint
+#[derive(Debug, Deserialize)]
+pub struct MergeTracksRequest {
+    /// Track that remains
+    pub keep_id: Uuid,
+    /// Near-duplicate whose listens move to `keep_id` before it is deleted
+    pub duplicate_id: Uuid,
+}
+
+/// Merges a near-duplicate track into another one
+///
+/// Maintenance endpoint for tracks that Last.fm reported with inconsistent
+/// metadata (e.g. "feat." vs "ft."). Tracks are shared between users and the
+/// merge moves every user's listens, so it is restricted to admins.
+///
+/// # Returns
+///
+/// - `200 OK`: Number of listens repointed to the kept track
+/// - `400 Bad Request`: Both IDs are the same track
+/// - `401 Unauthorized`: User not authenticated
+/// - `403 Forbidden`: User is not listed in `ADMIN_EMAILS`
+/// - `404 Not Found`: Either track does not exist
+/// - `500 Internal Server Error`: Database operation failed
+///
+/// # Example
+/// POST /api/music/tracks/merge with `{"keep_id": "...", "duplicate_id": "..."}`
+pub async fn merge_duplicate_tracks(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Json(request): Json<MergeTracksRequest>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
+    if !state.admins.is_admin(&user) {
+        return Err(ApiError::admin_required());
+    }
+
+    if request.keep_id == request.duplicate_id {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidInput,
+            "Cannot merge a track into itself",
+        ));
+    }
+
+    let repointed = merge_tracks(&state.db_connection, request.keep_id, request.duplicate_id)
+        .await
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => {
+                ApiError::not_found(ErrorCode::TrackNotFound, message)
+            }
+            other => ApiError::database(other),
+        })?;
+
+    info!(
+        user_id = %user.id,
+        keep_id = %request.keep_id,
+        duplicate_id = %request.duplicate_id,
+        repointed,
+        "Merged duplicate track"
+    );
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "keep_id": request.keep_id,
+            "duplicate_id": request.duplicate_id,
+            "listens_repointed": repointed
+        })),
+    ))
+}
+
 /// Query parameters for Last.fm range endpoint
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LastFmRangeQuery {
@@ -139,94 +865,435 @@ pub struct LastFmRangeQuery {
     pub end: i64,
 }
 
+/// Checks that a Last.fm debug range is ordered and no longer than `max_seconds`
+fn validate_lastfm_range(params: &LastFmRangeQuery, max_seconds: i64) -> Result<(), ApiError> {
+    if params.start > params.end {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidInput,
+            "start must not be after end",
+        ));
+    }
+    if params.end.saturating_sub(params.start) > max_seconds {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidInput,
+            format!("Range must not exceed {max_seconds} seconds"),
+        ));
+    }
+    Ok(())
+}
+
 /// Debug endpoint to fetch raw Last.fm data for a time range
 ///
 /// This endpoint helps investigate timestamp boundary behavior and
 /// understand why certain tracks might not be captured during sync.
+/// Only routed when `ENABLE_DEBUG_ENDPOINTS=true`.
 ///
 /// # Query Parameters
 /// - `start`: Unix timestamp (seconds) for start of range
-/// - `end`: Unix timestamp (seconds) for end of range
+/// - `end`: Unix timestamp (seconds) for end of range, at most
+///   `LASTFM_RANGE_MAX_SECONDS` (default: 7 days) after `start`
+///
+/// # Returns
+///
+/// - `200 OK`: Raw Last.fm tracks of the range
+/// - `400 Bad Request`: `start` after `end`, range too long, or Last.fm error
+/// - `401 Unauthorized`: User not authenticated
+/// - `404 Not Found`: User not found
+/// - `409 Conflict`: Last.fm username not configured
+/// - `429 Too Many Requests`: Last.fm rate limit exceeded
+/// - `502 Bad Gateway`: Last.fm unavailable
 ///
 /// # Example
 /// GET /api/music/lastfm/range?start=1730297719&end=1730301319
-#[allow(dead_code)]
 pub async fn get_lastfm_range(
     State(state): State<Arc<AppState>>,
     auth_session: AuthSession<AuthBackend>,
     Query(params): Query<LastFmRangeQuery>,
-) -> (StatusCode, Json<Value>) {
-    let Some(user) = auth_session.user else {
-        return (
-            StatusCode::UNAUTHORIZED,
-            Json(json!({
-                "error": "Unauthorized"
-            })),
-        );
-    };
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
+    validate_lastfm_range(&params, lastfm_range_max_seconds())?;
 
     // Get user's Last.fm username
-    let user_record = match get_user_by_id(&state.db_connection, user.id).await {
-        Ok(Some(u)) => u,
-        Ok(None) => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(json!({
-                    "error": "User not found"
-                })),
-            );
-        }
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": format!("Database error: {}", e)
-                })),
-            );
-        }
-    };
+    let user_record = get_user_by_id(&state.db_connection, user.id)
+        .await
+        .map_err(ApiError::database)?
+        .ok_or_else(|| ApiError::not_found(ErrorCode::UserNotFound, "User not found"))?;
 
-    let Some(lastfm_username) = user_record.lastfm_username else {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "User does not have a Last.fm username configured"
-            })),
-        );
-    };
+    let lastfm_username = user_record
+        .lastfm_username
+        .ok_or_else(ApiError::lastfm_not_configured)?;
 
     // Fetch raw Last.fm tracks
-    match get_lastfm_tracks_raw(&lastfm_username, params.start, params.end).await {
-        Ok(tracks) => {
-            let track_infos: Vec<LastFmTrackInfo> = tracks
-                .into_iter()
-                .map(|track| LastFmTrackInfo {
-                    track_name: track.name.clone(),
-                    artist_name: track.artist.text.clone(),
-                    album_name: if track.album.text.is_empty() {
-                        None
-                    } else {
-                        Some(track.album.text.clone())
-                    },
-                    played_at_timestamp: track.date.as_ref().map(|d| i64::from(d.uts)),
-                    played_at_text: track.date.as_ref().map(|d| d.text.clone()),
+    let tracks = get_lastfm_tracks_raw(&lastfm_username, params.start, params.end)
+        .await
+        .map_err(|e| {
+            ApiError::music_not_configured(e.as_ref())
+                .or_else(|| ApiError::lastfm_failure(e.as_ref()))
+                .unwrap_or_else(|| {
+                    ApiError::bad_request(ErrorCode::LastfmError, format!("Last.fm API error: {e}"))
                 })
-                .collect();
+        })?;
+
+    let track_infos: Vec<LastFmTrackInfo> = tracks
+        .into_iter()
+        .map(|track| LastFmTrackInfo {
+            track_name: track.name.clone(),
+            artist_name: track.artist.text.clone(),
+            album_name: if track.album.text.is_empty() {
+                None
+            } else {
+                Some(track.album.text.clone())
+            },
+            played_at_timestamp: track.date.as_ref().map(|d| i64::from(d.uts)),
+            played_at_text: track.date.as_ref().map(|d| d.text.clone()),
+        })
+        .collect();
+
+    let response = LastFmRangeResponse {
+        total_tracks: track_infos.len(),
+        tracks: track_infos,
+        start_timestamp: params.start,
+        end_timestamp: params.end,
+    };
+
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration, Utc};
+    use run_sous_bpm_core::database::activity_stream;
+    use run_sous_bpm_core::geo::simplify_gps_route;
+    use run_sous_bpm_core::test_support::{make_track, make_user};
+    use run_sous_bpm_integrations::lastfm::require_api_key;
 
-            let response = LastFmRangeResponse {
-                total_tracks: track_infos.len(),
-                tracks: track_infos,
-                start_timestamp: params.start,
-                end_timestamp: params.end,
-            };
+    fn make_segment(
+        index: usize,
+        track: Option<track::Model>,
+        start_time: DateTime<Utc>,
+        seconds: i64,
+    ) -> analytics_service::Segment {
+        let activity_id = Uuid::new_v4();
+        let points = (0..seconds)
+            .step_by(10)
+            .map(|offset| activity_stream::Model {
+                activity_id,
+                time: (start_time + Duration::seconds(offset)).into(),
+                latitude: Some(48.0),
+                longitude: Some(2.0),
+                altitude: None,
+                heart_rate: None,
+                cadence: None,
+                watts: None,
+                velocity: None,
+                distance: None,
+                temperature: None,
+                grade: None,
+                moving: None,
+            })
+            .collect();
 
-            (StatusCode::OK, Json(json!(response)))
+        analytics_service::Segment {
+            index,
+            track,
+            start_time,
+            end_time: start_time + Duration::seconds(seconds),
+            points,
+            avg_temperature: None,
+            repeated: false,
+            after_gap: false,
         }
-        Err(e) => (
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": format!("Last.fm API error: {}", e)
-            })),
-        ),
+    }
+
+    #[test]
+    fn test_timeline_contains_tracks_without_points() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let segments = vec![
+            make_segment(0, None, start, 60),
+            make_segment(
+                1,
+                Some(track::Model {
+                    track_name: "Song A".to_string(),
+                    ..make_track()
+                }),
+                start + Duration::seconds(60),
+                200,
+            ),
+        ];
+
+        let response = ActivityMusicTimelineResponse {
+            activity_id: Uuid::new_v4(),
+            music_coverage_ratio: 0.5,
+            segments: segments.into_iter().map(timeline_segment).collect(),
+        };
+        let json = serde_json::to_value(&response).unwrap();
+
+        let segments = json["segments"].as_array().unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[1]["track"]["track_name"], "Song A");
+        assert_eq!(segments[0]["duration_seconds"], 60);
+        assert_eq!(segments[1]["duration_seconds"], 200);
+        assert!(segments[0].get("track").is_none());
+        assert!(
+            segments.iter().all(|s| s.get("points").is_none()),
+            "Timeline must not include point arrays"
+        );
+    }
+
+    #[test]
+    fn test_missing_activity_maps_to_activity_not_found() {
+        let error: Box<dyn std::error::Error> = "Activity not found".into();
+        let api_error = activity_music_error(error.as_ref());
+
+        assert_eq!(api_error.status, StatusCode::NOT_FOUND);
+        assert_eq!(api_error.code, ErrorCode::ActivityNotFound);
+    }
+
+    #[test]
+    fn test_foreign_activity_is_indistinguishable_from_missing() {
+        let error: Box<dyn std::error::Error> = "Activity does not belong to the user".into();
+        let api_error = activity_music_error(error.as_ref());
+
+        assert_eq!(api_error.status, StatusCode::NOT_FOUND);
+        assert_eq!(api_error.code, ErrorCode::ActivityNotFound);
+        assert_eq!(api_error.message, "Activity not found");
+    }
+
+    #[test]
+    fn test_missing_lastfm_username_maps_to_lastfm_not_configured() {
+        let error: Box<dyn std::error::Error> = Box::new(LastfmNotConfiguredError);
+        let api_error = activity_music_error(error.as_ref());
+
+        assert_eq!(api_error.status, StatusCode::CONFLICT);
+        assert_eq!(api_error.code, ErrorCode::LastfmNotConfigured);
+        assert_eq!(
+            api_error.message,
+            "User does not have a Last.fm username configured"
+        );
+    }
+
+    #[test]
+    fn test_other_music_errors_keep_their_message() {
+        let error: Box<dyn std::error::Error> = "GPS simplification failed".into();
+        let api_error = activity_music_error(error.as_ref());
+
+        assert_eq!(api_error.code, ErrorCode::ActivityMusicFailed);
+        assert_eq!(api_error.message, "GPS simplification failed");
+    }
+
+    #[test]
+    fn test_import_database_failure_is_a_server_error() {
+        let error: Box<dyn std::error::Error> =
+            Box::new(DbErr::Custom("connection reset".to_string()));
+        let api_error = import_error(error.as_ref());
+
+        assert_eq!(api_error.status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(api_error.code, ErrorCode::DatabaseError);
+    }
+
+    #[test]
+    fn test_unparseable_export_is_a_bad_request() {
+        let error: Box<dyn std::error::Error> = "expected value at line 1 column 2".into();
+        let api_error = import_error(error.as_ref());
+
+        assert_eq!(api_error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(api_error.code, ErrorCode::InvalidExport);
+    }
+
+    #[test]
+    fn test_unset_lastfm_api_key_maps_to_service_unavailable() {
+        let error: Box<dyn std::error::Error> = require_api_key(Some("  "))
+            .err()
+            .expect("A blank API key must be rejected")
+            .into();
+        let api_error = activity_music_error(error.as_ref());
+
+        assert_eq!(api_error.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(api_error.code, ErrorCode::MusicIntegrationNotConfigured);
+        assert_eq!(api_error.message, "Music integration not configured");
+    }
+
+    fn padding_query(pad_before: Option<u32>, pad_after: Option<u32>) -> SimplificationQuery {
+        SimplificationQuery {
+            simplify: None,
+            tolerance: None,
+            tolerance_unit: None,
+            detail: None,
+            algorithm: None,
+            units: None,
+            mode: None,
+            bucket: None,
+            pad_before,
+            pad_after,
+            min_segment_seconds: None,
+            no_fetch: None,
+            window: None,
+            split_gap_seconds: None,
+            include_now_playing: None,
+            fields: None,
+        }
+    }
+
+    #[test]
+    fn test_stored_listen_padding_is_applied_without_override() {
+        let padding = listen_padding(
+            &user::Model {
+                listen_padding_before_seconds: 90,
+                listen_padding_after_seconds: 45,
+                ..make_user()
+            },
+            &padding_query(None, None),
+        )
+        .unwrap();
+
+        assert_eq!(padding.before_seconds, 90);
+        assert_eq!(padding.after_seconds, 45);
+    }
+
+    #[test]
+    fn test_query_listen_padding_overrides_stored_default() {
+        let padding = listen_padding(
+            &user::Model {
+                listen_padding_before_seconds: 90,
+                listen_padding_after_seconds: 45,
+                ..make_user()
+            },
+            &padding_query(Some(0), Some(300)),
+        )
+        .unwrap();
+
+        assert_eq!(padding.before_seconds, 0);
+        assert_eq!(padding.after_seconds, 300);
+    }
+
+    #[test]
+    fn test_out_of_bounds_listen_padding_is_rejected() {
+        let error = listen_padding(
+            &user::Model {
+                listen_padding_before_seconds: 0,
+                listen_padding_after_seconds: 0,
+                ..make_user()
+            },
+            &padding_query(Some(86_400), None),
+        )
+        .unwrap_err();
+
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, ErrorCode::InvalidInput);
+    }
+
+    /// Route north in 20 m steps, with sideways spikes of 5, 15 and 50 m
+    fn spiky_route() -> Vec<activity_stream::Model> {
+        let activity_id = Uuid::new_v4();
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        (0..120_i32)
+            .map(|i| {
+                let spike_meters = match i % 6 {
+                    1 => 5.0,
+                    3 => 15.0,
+                    5 => 50.0,
+                    _ => 0.0,
+                };
+                activity_stream::Model {
+                    activity_id,
+                    time: (start + Duration::seconds(i64::from(i))).into(),
+                    latitude: Some(48.0 + f64::from(i) * 20.0 / 111_320.0),
+                    longitude: Some(2.0 + spike_meters / 74_490.0),
+                    altitude: None,
+                    heart_rate: None,
+                    cadence: None,
+                    watts: None,
+                    velocity: None,
+                    distance: None,
+                    temperature: None,
+                    grade: None,
+                    moving: None,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_detail_presets_keep_fewer_points_as_detail_drops() {
+        let route = spiky_route();
+        let kept: Vec<usize> = [DetailPreset::High, DetailPreset::Medium, DetailPreset::Low]
+            .into_iter()
+            .map(|detail| {
+                simplify_gps_route(&route, detail.tolerance_meters())
+                    .unwrap()
+                    .len()
+            })
+            .collect();
+
+        assert!(kept[0] > kept[1] && kept[1] > kept[2], "{kept:?}");
+    }
+
+    #[test]
+    fn test_explicit_tolerance_overrides_detail_preset() {
+        let mut params = padding_query(None, None);
+        params.detail = Some(DetailPreset::Low);
+        assert_eq!(
+            requested_tolerance(&params),
+            Some(SimplificationTolerance::Meters(30.0))
+        );
+
+        params.tolerance = Some(SimplificationTolerance::Auto);
+        assert_eq!(
+            requested_tolerance(&params),
+            Some(SimplificationTolerance::Auto)
+        );
+    }
+
+    #[test]
+    fn test_detail_preset_parses_from_query() {
+        let params: SimplificationQuery =
+            serde_json::from_value(json!({ "detail": "high" })).unwrap();
+        assert_eq!(params.detail, Some(DetailPreset::High));
+        assert!(
+            serde_json::from_value::<SimplificationQuery>(json!({ "detail": "ultra" })).is_err()
+        );
+    }
+
+    const DAY_SECONDS: i64 = 24 * 60 * 60;
+
+    #[test]
+    fn test_lastfm_range_within_limit_is_accepted() {
+        let start = 1_730_297_719;
+        let week = LastFmRangeQuery {
+            start,
+            end: start + 7 * DAY_SECONDS,
+        };
+        let empty = LastFmRangeQuery { start, end: start };
+
+        assert!(validate_lastfm_range(&week, 7 * DAY_SECONDS).is_ok());
+        assert!(validate_lastfm_range(&empty, 7 * DAY_SECONDS).is_ok());
+    }
+
+    #[test]
+    fn test_over_long_lastfm_range_is_rejected() {
+        let start = 1_730_297_719;
+        let decade = LastFmRangeQuery {
+            start,
+            end: start + 3650 * DAY_SECONDS,
+        };
+
+        let error = validate_lastfm_range(&decade, 7 * DAY_SECONDS).unwrap_err();
+
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, ErrorCode::InvalidInput);
+    }
+
+    #[test]
+    fn test_inverted_lastfm_range_is_rejected() {
+        let inverted = LastFmRangeQuery {
+            start: 1_730_301_319,
+            end: 1_730_297_719,
+        };
+
+        let error = validate_lastfm_range(&inverted, 7 * DAY_SECONDS).unwrap_err();
+
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.message, "start must not be after end");
     }
 }
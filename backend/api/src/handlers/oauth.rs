@@ -2,81 +2,116 @@ use std::sync::Arc;
 
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
     response::{Json, Redirect},
 };
 use axum_login::AuthSession;
 use run_sous_bpm_core::{
     auth::AuthBackend,
     config::OAuthProvider,
-    services::{handle_oauth_callback, oauth::start_oauth_flow},
+    services::{handle_oauth_callback, oauth::start_oauth_flow, parse_scopes},
 };
 use serde_json::{json, Value};
 use tracing::info;
 
-use crate::AppState;
+use crate::{
+    responses::{ApiError, ErrorCode},
+    AppState,
+};
+
+/// Query parameters for the authorize endpoint
+#[derive(Debug, serde::Deserialize)]
+pub struct AuthorizeParams {
+    /// Scopes to request on top of the provider defaults, comma- or space-separated
+    scopes: Option<String>,
+}
 
+/// Starts an OAuth flow and returns the provider URL to redirect the user to
+///
+/// The frontend origin making the request (`Origin`, or `Referer` for same-origin
+/// requests) is remembered when allowed, so the callback sends the user back to
+/// the frontend that started the flow.
+///
+/// # Returns
+///
+/// - `200 OK`: `{ auth_url }`
+/// - `400 Bad Request`: Unsupported provider, or a requested scope outside the provider's allowlist
+/// - `401 Unauthorized`: User not authenticated
 pub async fn oauth_callback(
     Path(provider): Path<String>,
     State(app_state): State<Arc<AppState>>,
     auth_session: AuthSession<AuthBackend>,
-) -> (StatusCode, Json<Value>) {
-    let Some(user) = auth_session.user else {
-        return (
-            StatusCode::UNAUTHORIZED,
-            Json(json!({
-                "error": "Unauthorized",
-                "message": "You must be logged in to connect OAuth providers"
-            })),
-        );
-    };
+    headers: HeaderMap,
+    Query(params): Query<AuthorizeParams>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
+    let provider = parse_provider(&provider)?;
+    let return_origin = [header::ORIGIN, header::REFERER]
+        .iter()
+        .filter_map(|name| headers.get(name)?.to_str().ok())
+        .find_map(|url| app_state.redirect_allowlist.allowed_origin(url));
+    let extra_scopes = params
+        .scopes
+        .as_deref()
+        .map(parse_scopes)
+        .unwrap_or_default();
 
-    match provider.parse::<OAuthProvider>() {
-        Ok(provider) => {
-            let auth_url = start_oauth_flow(provider, &app_state.oauth_session_store, user.id);
-            (
-                StatusCode::OK,
-                Json(json!({
-                    "auth_url": auth_url,
-                })),
-            )
-        }
-        Err(_) => (
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "Invalid provider",
-                "message": format!("Provider '{}' is not supported", provider)
-            })),
-        ),
-    }
+    let auth_url = start_oauth_flow(
+        provider,
+        &extra_scopes,
+        &app_state.oauth_session_store,
+        user.id,
+        return_origin,
+    )
+    .map_err(|err| ApiError::bad_request(ErrorCode::InvalidInput, err.to_string()))?;
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "auth_url": auth_url,
+        })),
+    ))
+}
+
+fn parse_provider(provider: &str) -> Result<OAuthProvider, ApiError> {
+    provider.parse::<OAuthProvider>().map_err(|_| {
+        ApiError::bad_request(
+            ErrorCode::InvalidProvider,
+            format!("Provider '{provider}' is not supported"),
+        )
+    })
 }
 
 #[derive(serde::Deserialize)]
 pub struct OAuthCallbackParams {
     code: String,
     state: String,
+    /// Scopes the user accepted, as reported by the provider
+    scope: Option<String>,
 }
 
 pub async fn oauth_process_callback(
     State(app_state): State<AppState>,
     params: Query<OAuthCallbackParams>,
 ) -> Redirect {
-    let frontend_url = std::env::var("FRONTEND_URL").expect("FRONTEND_URL must be set");
+    let default_origin = app_state.redirect_allowlist.default_origin();
 
     let (code, state) = (params.code.clone(), params.state.clone());
     match handle_oauth_callback(
         code.clone(),
         state.clone(),
+        params.scope.as_deref(),
         &app_state.oauth_session_store,
         &app_state.db_connection,
-        &app_state.encryption_service,
+        app_state.encryption_service.as_ref(),
     )
     .await
     {
-        Ok((_token_response, provider)) => {
-            let provider_str = provider.to_string().to_lowercase();
+        Ok(exchanged) => {
+            let frontend_url = exchanged.return_origin.as_deref().unwrap_or(default_origin);
+            let provider_str = exchanged.provider.to_string().to_lowercase();
             let redirect_url =
                 format!("{frontend_url}/oauth/callback?status=success&provider={provider_str}");
+            let redirect_url = app_state.redirect_allowlist.sanitize(&redirect_url);
             info!(
                 "OAuth callback successful for provider: {provider_str}, redirecting to {redirect_url}"
             );
@@ -86,7 +121,8 @@ pub async fn oauth_process_callback(
             let error_string = e.to_string();
             let error_message = urlencoding::encode(&error_string);
             let redirect_url =
-                format!("{frontend_url}/oauth/callback?status=error&error={error_message}");
+                format!("{default_origin}/oauth/callback?status=error&error={error_message}");
+            let redirect_url = app_state.redirect_allowlist.sanitize(&redirect_url);
             info!("OAuth callback failed: {error_string}, redirecting to {redirect_url}");
             Redirect::to(&redirect_url)
         }
@@ -97,49 +133,24 @@ pub async fn remove_oauth_provider(
     State(app_state): State<Arc<AppState>>,
     auth_session: AuthSession<AuthBackend>,
     Path(provider): Path<String>,
-) -> (StatusCode, Json<Value>) {
-    let Some(user) = auth_session.user else {
-        return (
-            StatusCode::UNAUTHORIZED,
-            Json(json!({
-                "error": "Unauthorized",
-                "message": "You must be logged in to disconnect OAuth providers"
-            })),
-        );
-    };
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
 
     info!(user_id = %user.id, provider = %provider, "Removing OAuth provider connection");
 
-    match provider.parse::<OAuthProvider>() {
-        Ok(provider) => {
-            match run_sous_bpm_core::database::repositories::delete_oauth_token(
-                &app_state.db_connection,
-                user.id,
-                provider,
-            )
-            .await
-            {
-                Ok(()) => (
-                    StatusCode::OK,
-                    Json(json!({
-                        "message": format!("Successfully disconnected {provider}"),
-                    })),
-                ),
-                Err(e) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({
-                        "error": "Failed to disconnect provider",
-                        "message": e.to_string(),
-                    })),
-                ),
-            }
-        }
-        Err(_) => (
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "Invalid provider",
-                "message": format!("Provider '{}' is not supported", provider)
-            })),
-        ),
-    }
+    let provider = parse_provider(&provider)?;
+    run_sous_bpm_core::database::repositories::delete_oauth_token(
+        &app_state.db_connection,
+        user.id,
+        provider,
+    )
+    .await
+    .map_err(ApiError::database)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "message": format!("Successfully disconnected {provider}"),
+        })),
+    ))
 }
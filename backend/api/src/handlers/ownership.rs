@@ -0,0 +1,171 @@
+use run_sous_bpm_core::database::{
+    activity, activity_repository, get_track_by_id, has_user_listened_to_track, track,
+};
+use sea_orm::{prelude::Uuid, DatabaseConnection};
+
+use crate::responses::ApiError;
+
+/// Loads an activity and checks it belongs to the user
+///
+/// Activities owned by someone else are reported as not found, so their
+/// existence is not leaked to other users.
+///
+/// # Errors
+///
+/// - `404 Not Found`: Activity missing or owned by another user
+/// - `500 Internal Server Error`: Database query failed
+pub(crate) async fn load_owned_activity(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    activity_id: Uuid,
+) -> Result<activity::Model, ApiError> {
+    let activity = activity_repository::get_activity_by_id(db, activity_id)
+        .await
+        .map_err(ApiError::database)?;
+    owned_activity(activity, user_id)
+}
+
+fn owned_activity(
+    activity: Option<activity::Model>,
+    user_id: Uuid,
+) -> Result<activity::Model, ApiError> {
+    activity
+        .filter(|activity| activity.user_id == user_id)
+        .ok_or_else(ApiError::activity_not_found)
+}
+
+/// Loads a track the user has listened to at least once
+///
+/// Tracks are shared between users; one the user never listened to is reported
+/// as not found, like a foreign activity.
+///
+/// # Errors
+///
+/// - `404 Not Found`: Track missing or never listened to by the user
+/// - `500 Internal Server Error`: Database query failed
+pub(crate) async fn load_listened_track(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    track_id: Uuid,
+) -> Result<track::Model, ApiError> {
+    let track = get_track_by_id(db, track_id)
+        .await
+        .map_err(ApiError::database)?;
+    if track.is_none() {
+        return Err(ApiError::track_not_found());
+    }
+    let listened = has_user_listened_to_track(db, user_id, track_id)
+        .await
+        .map_err(ApiError::database)?;
+    listened_track(track, listened)
+}
+
+fn listened_track(track: Option<track::Model>, listened: bool) -> Result<track::Model, ApiError> {
+    track
+        .filter(|_| listened)
+        .ok_or_else(ApiError::track_not_found)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{http::StatusCode, response::IntoResponse};
+
+    use run_sous_bpm_core::test_support::{make_activity, make_track};
+
+    use super::*;
+
+    #[test]
+    fn test_owned_activity_is_returned() {
+        let user_id = Uuid::new_v4();
+        let activity = activity::Model {
+            user_id,
+            ..make_activity()
+        };
+
+        let result = owned_activity(Some(activity.clone()), user_id);
+
+        assert_eq!(result.unwrap(), activity);
+    }
+
+    #[test]
+    fn test_activity_of_another_user_is_not_found() {
+        let activity = make_activity();
+
+        let error = owned_activity(Some(activity), Uuid::new_v4()).unwrap_err();
+
+        assert_eq!(error.into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_missing_activity_is_not_found() {
+        let error = owned_activity(None, Uuid::new_v4()).unwrap_err();
+
+        assert_eq!(error.into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_listened_track_is_returned() {
+        let track = make_track();
+
+        let result = listened_track(Some(track.clone()), true);
+
+        assert_eq!(result.unwrap(), track);
+    }
+
+    #[test]
+    fn test_track_never_listened_to_is_not_found() {
+        let error = listened_track(Some(make_track()), false).unwrap_err();
+
+        assert_eq!(error.into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    /// Mock database answering the track lookup, then the listen check
+    fn track_db(track: Option<track::Model>, listened: bool) -> DatabaseConnection {
+        let first_listen = if listened {
+            vec![BTreeMap::from([("id", Value::from(Uuid::new_v4()))])]
+        } else {
+            Vec::new()
+        };
+        MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([track.into_iter().collect::<Vec<_>>()])
+            .append_query_results([first_listen])
+            .into_connection()
+    }
+
+    #[tokio::test]
+    async fn test_track_listened_to_is_loaded_from_the_database() {
+        let track = make_track();
+        let db = track_db(Some(track.clone()), true);
+
+        let loaded = load_listened_track(&db, Uuid::new_v4(), track.id)
+            .await
+            .unwrap();
+
+        assert_eq!(loaded, track);
+        assert_eq!(db.into_transaction_log().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_track_without_listens_of_the_user_is_not_loaded() {
+        let track = make_track();
+        let db = track_db(Some(track.clone()), false);
+
+        let error = load_listened_track(&db, Uuid::new_v4(), track.id)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_missing_track_is_not_found_without_checking_listens() {
+        let db = track_db(None, true);
+
+        let error = load_listened_track(&db, Uuid::new_v4(), Uuid::new_v4())
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.into_response().status(), StatusCode::NOT_FOUND);
+        assert_eq!(db.into_transaction_log().len(), 1);
+    }
+}
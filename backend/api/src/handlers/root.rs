@@ -1,18 +1,21 @@
-use axum::{
-    http::StatusCode,
-    response::{IntoResponse, Json},
-};
+use axum::response::Json;
 use serde_json::{json, Value};
 
+use crate::responses::ApiError;
+
 pub async fn root() -> Json<Value> {
     Json(json!({
         "message": "Welcome to Run Sous BPM API",
         "version": "0.1.0",
         "endpoints": {
-            "health": "/health",
+            "health": {
+                "live": "/health/live",
+                "ready": "/health/ready (alias: /health)"
+            },
             "auth": {
                 "register": "POST /api/auth/register",
                 "login": "POST /api/auth/login",
+                "refresh": "POST /api/auth/refresh",
                 "logout": "POST /api/auth/logout (requires auth)"
             },
             "oauth": {
@@ -24,14 +27,7 @@ pub async fn root() -> Json<Value> {
     }))
 }
 
-pub async fn handler_404() -> impl IntoResponse {
-    (
-        StatusCode::NOT_FOUND,
-        Json(json!({
-            "error": "Not Found",
-            "status": 404,
-            "message": "The requested resource was not found",
-            "hint": "Visit GET / for available endpoints"
-        })),
-    )
+/// Fallback for unknown routes, answering with the structured error schema
+pub async fn handler_404() -> ApiError {
+    ApiError::route_not_found()
 }
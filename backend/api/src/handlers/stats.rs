@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use axum_login::AuthSession;
+use chrono::FixedOffset;
+use run_sous_bpm_core::{
+    auth::AuthBackend,
+    services::{get_training_load, TrainingLoadPeriod},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    responses::{ApiError, ErrorCode},
+    AppState,
+};
+
+/// Query parameters for the training load endpoint
+#[derive(Debug, Deserialize)]
+pub struct TrainingLoadQuery {
+    /// `week` or `month` (default: week)
+    pub period: Option<TrainingLoadPeriod>,
+    /// User's offset from UTC in minutes, east positive (default: 0)
+    ///
+    /// Decides which week or month an activity near midnight falls into.
+    pub utc_offset_minutes: Option<i32>,
+}
+
+/// Converts a UTC offset in minutes into a timezone, rejecting offsets of a day or more
+fn parse_utc_offset(minutes: i32) -> Option<FixedOffset> {
+    minutes.checked_mul(60).and_then(FixedOffset::east_opt)
+}
+
+/// Aggregates the user's activities into weekly or monthly training load
+///
+/// # Returns
+///
+/// - `200 OK`: `{ period, buckets }`, each with distance, elapsed time, elevation and load
+/// - `400 Bad Request`: Unknown period or out-of-range UTC offset
+/// - `401 Unauthorized`: User not authenticated
+/// - `500 Internal Server Error`: Database query failed
+pub async fn get_training_load_stats(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Query(params): Query<TrainingLoadQuery>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user_id = auth_session.user.ok_or_else(ApiError::unauthorized)?.id;
+
+    let period = params.period.unwrap_or_default();
+    let offset = parse_utc_offset(params.utc_offset_minutes.unwrap_or(0)).ok_or_else(|| {
+        ApiError::bad_request(
+            ErrorCode::InvalidInput,
+            "utc_offset_minutes must be within one day of UTC",
+        )
+    })?;
+
+    let buckets = get_training_load(&state.db_connection, user_id, period, offset)
+        .await
+        .map_err(ApiError::database)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "period": period,
+            "buckets": buckets
+        })),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utc_offset_is_east_positive() {
+        assert_eq!(
+            parse_utc_offset(60),
+            Some(FixedOffset::east_opt(3600).unwrap())
+        );
+        assert_eq!(
+            parse_utc_offset(-300),
+            Some(FixedOffset::west_opt(5 * 3600).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_utc_offset_of_a_day_is_rejected() {
+        assert_eq!(parse_utc_offset(24 * 60), None);
+        assert_eq!(parse_utc_offset(i32::MAX), None);
+    }
+}
@@ -1,62 +1,97 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Json,
 };
 use axum_login::AuthSession;
-use run_sous_bpm_core::auth::AuthBackend;
+use run_sous_bpm_core::{
+    auth::AuthBackend,
+    database::{activity_repository, clamp_page_size},
+    models::{parse_stream_keys, ActivityCategory},
+    services::{
+        analytics_service, get_activity_summary, ActivityOwnedByAnotherUserError,
+        ActivityStreamSyncResult, StreamFetchOptions, StreamSyncOutcome,
+        DEFAULT_SIMILAR_ACTIVITIES_LIMIT, MAX_ROUTE_CANDIDATES,
+    },
+};
+use run_sous_bpm_integrations::strava::{StreamResolution, StreamSeriesType};
 use sea_orm::prelude::Uuid;
+use serde::Deserialize;
 use serde_json::{json, Value};
 use tracing::info;
 
-use crate::AppState;
+use super::load_owned_activity;
+use crate::{
+    responses::{ApiError, ErrorCode, Paginated},
+    AppState,
+};
 
 /// Syncs user's Strava activities from the Strava API to the local database
 ///
-/// Fetches all activities for the authenticated user from Strava and stores them locally.
+/// Fetches the authenticated user's activities newer than the latest stored one from
+/// Strava and stores them locally, up to `ACTIVITY_SYNC_MAX_ACTIVITIES` per call.
 /// Updates existing activities if they already exist (based on `external_id`).
 ///
 /// # Returns
 ///
 /// - `200 OK`: Successfully synced activities with count
 /// - `401 Unauthorized`: User not authenticated
+/// - `403 Forbidden`: Strava token lacks a required scope, reconnect Strava
 /// - `502 Bad Gateway`: Failed to retrieve OAuth token or Strava API error
 pub async fn sync_strava_activities(
     State(state): State<Arc<AppState>>,
     auth_session: AuthSession<AuthBackend>,
-) -> (StatusCode, Json<Value>) {
-    let Some(user) = auth_session.user else {
-        return (
-            StatusCode::UNAUTHORIZED,
-            Json(json!({
-                "error": "Unauthorized",
-                "message": "You must be logged in to access this resource"
-            })),
-        );
-    };
-    let user_id = user.id;
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user_id = auth_session.user.ok_or_else(ApiError::unauthorized)?.id;
 
-    match run_sous_bpm_core::services::sync_strava_activities(
+    let synced = run_sous_bpm_core::services::sync_strava_activities(
         user_id,
         &state.strava_client,
         &state.db_connection,
-        &state.encryption_service,
+        state.encryption_service.as_ref(),
+        &state.sync_events,
     )
     .await
-    {
-        Ok(activities) => (
-            StatusCode::OK,
-            Json(json!(
-                { "message": format!("Successfully synced {} activities", activities.len())}
-            )),
-        ),
-        Err(err) => (
-            StatusCode::BAD_GATEWAY,
-            Json(json!({"error": format!("Failed to sync Strava activities: {}", err)})),
-        ),
-    }
+    .map_err(|err| strava_error(err.as_ref(), "sync Strava activities"))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!(
+            { "message": format!("Successfully synced {synced} activities")}
+        )),
+    ))
+}
+
+/// Maps a Strava sync failure to an API error
+///
+/// A token missing a required scope gets a dedicated 403 so the client can ask the
+/// user to reconnect Strava, and requests held back by the rate limit tracker a 429;
+/// anything else is reported as a Strava failure.
+fn strava_error(err: &(dyn std::error::Error + 'static), action: &str) -> ApiError {
+    ApiError::reconnect_required(err)
+        .or_else(|| ApiError::strava_rate_limited(err))
+        .unwrap_or_else(|| {
+            ApiError::bad_gateway(ErrorCode::StravaError, format!("Failed to {action}: {err}"))
+        })
+}
+
+/// Query parameters for the activity streams sync endpoint
+#[derive(Debug, Deserialize)]
+pub struct StreamSyncQuery {
+    /// Strava sampling resolution: `low`, `medium` or `high` (default: every point)
+    pub resolution: Option<StreamResolution>,
+    /// Series the points are aligned on: `distance` or `time`
+    /// (default: time for activities without distance, distance otherwise)
+    pub series_type: Option<StreamSeriesType>,
+    /// Only add points newer than the ones already stored, for live activities (default: false)
+    pub append: Option<bool>,
+    /// Comma-separated stream types to sync, e.g. `latlng` for map-only use (default: all).
+    /// `time` is always synced.
+    pub keys: Option<String>,
+    /// Sync an activity previously found without streams again (default: false)
+    pub force: Option<bool>,
 }
 
 /// Syncs detailed activity stream data for a specific Strava activity
@@ -67,153 +102,415 @@ pub async fn sync_strava_activities(
 /// # Arguments
 ///
 /// * `id` - The activity's internal UUID
+/// * `resolution` - Optional Strava sampling resolution for cheaper previews
+/// * `append` - Only add points recorded after the latest stored one
+/// * `keys` - Subset of stream types to sync, the others are stored empty
+/// * `force` - Ask Strava again for an activity previously found without streams
 ///
 /// # Returns
 ///
-/// - `200 OK`: Successfully synced activity streams
-/// - `400 Bad Request`: Invalid activity ID format
+/// - `200 OK`: Successfully synced activity streams, or none available (manual activity)
+/// - `400 Bad Request`: Invalid activity ID format or unknown stream key
 /// - `401 Unauthorized`: User not authenticated
-/// - `502 Bad Gateway`: Failed to retrieve activity or Strava API error
+/// - `403 Forbidden`: Strava token lacks a required scope, reconnect Strava
+/// - `404 Not Found`: Activity not found or not owned by the user
+/// - `502 Bad Gateway`: Strava API error
 pub async fn sync_strava_activity_streams(
     State(state): State<Arc<AppState>>,
     auth_session: AuthSession<AuthBackend>,
     Path(id): Path<String>,
-) -> (StatusCode, Json<Value>) {
-    let Some(user) = auth_session.user else {
-        return (
-            StatusCode::UNAUTHORIZED,
-            Json(json!({
-                "error": "Unauthorized",
-                "message": "You must be logged in to access this resource"
-            })),
-        );
-    };
-    let user_id = user.id;
+    Query(params): Query<StreamSyncQuery>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user_id = auth_session.user.ok_or_else(ApiError::unauthorized)?.id;
 
-    let Ok(activity_id) = id.parse::<Uuid>() else {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Invalid activity ID format"})),
-        );
-    };
+    let activity_id = id
+        .parse::<Uuid>()
+        .map_err(|_| ApiError::invalid_activity_id())?;
+    let keys = params
+        .keys
+        .as_deref()
+        .map(parse_stream_keys)
+        .transpose()
+        .map_err(|e| ApiError::bad_request(ErrorCode::InvalidInput, e.to_string()))?;
 
     info!(user_id = %user_id, activity_id = %activity_id, "Starting sync of Strava activity streams");
 
     // First get the activity to get its external_id
-    match run_sous_bpm_core::database::activity_repository::get_activity_by_id(
+    let activity = load_owned_activity(&state.db_connection, user_id, activity_id).await?;
+
+    let external_id = activity.external_id;
+    info!(user_id = %user_id, activity_id = %activity_id, external_id = %external_id, "Syncing Strava activity streams");
+
+    let outcome = run_sous_bpm_core::services::sync_strava_activity_streams(
+        user_id,
+        external_id,
+        StreamFetchOptions {
+            resolution: params.resolution,
+            series_type: params.series_type,
+            append: params.append.unwrap_or(false),
+            keys,
+            force: params.force.unwrap_or(false),
+        },
+        &state.strava_client,
         &state.db_connection,
-        activity_id,
+        state.encryption_service.as_ref(),
+        &state.sync_events,
     )
     .await
-    {
-        Ok(Some(activity)) if activity.user_id == user_id => {
-            let external_id = activity.external_id;
-            info!(user_id = %user_id, activity_id = %activity_id, external_id = %external_id, "Syncing Strava activity streams");
-
-            match run_sous_bpm_core::services::sync_strava_activity_streams(
-                user_id,
-                external_id,
-                &state.strava_client,
-                &state.db_connection,
-                &state.encryption_service,
-            )
-            .await
-            {
-                Ok(()) => (
-                    StatusCode::OK,
-                    Json(json!({"message": "Successfully synced activity streams"})),
-                ),
-                Err(err) => (
-                    StatusCode::BAD_GATEWAY,
-                    Json(
-                        json!({"error": format!("Failed to sync Strava activity streams: {}", err)}),
-                    ),
-                ),
-            }
-        }
-        Ok(Some(_) | None) => (
-            StatusCode::NOT_FOUND,
-            Json(json!({"error": "Activity not found"})),
-        ),
-        Err(err) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Database error: {}", err)})),
-        ),
+    .map_err(|err| strava_error(err.as_ref(), "sync Strava activity streams"))?;
+
+    Ok((StatusCode::OK, Json(stream_sync_response(outcome))))
+}
+
+/// JSON body describing what a stream sync stored
+fn stream_sync_response(outcome: StreamSyncOutcome) -> Value {
+    match outcome {
+        StreamSyncOutcome::Synced {
+            points,
+            original_points,
+        } => json!({
+            "message": "Successfully synced activity streams",
+            "points": points,
+            "original_points": original_points,
+            "streams_unavailable": false
+        }),
+        StreamSyncOutcome::Unavailable => json!({
+            "message": "No streams available for this activity",
+            "points": 0,
+            "original_points": 0,
+            "streams_unavailable": true
+        }),
     }
 }
 
+/// Imports a single Strava activity and its streams in one call
+///
+/// Fetches the activity detail from Strava, stores it, then syncs its streams.
+/// Safe to retry: importing an activity again refreshes it without duplicating
+/// the activity or its points.
+///
+/// # Arguments
+///
+/// * `id` - The Strava activity ID (not the internal UUID)
+///
+/// # Returns
+///
+/// - `200 OK`: `{ activity, streams }` with the stored activity and the stream sync result
+/// - `400 Bad Request`: Invalid Strava activity ID format
+/// - `401 Unauthorized`: User not authenticated
+/// - `403 Forbidden`: Strava token lacks a required scope, reconnect Strava
+/// - `404 Not Found`: Activity is stored for another user
+/// - `502 Bad Gateway`: Failed to retrieve OAuth token or Strava API error
+pub async fn import_strava_activity(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user_id = auth_session.user.ok_or_else(ApiError::unauthorized)?.id;
+
+    let external_id = id.parse::<i64>().map_err(|_| {
+        ApiError::bad_request(ErrorCode::InvalidInput, "Invalid Strava activity ID format")
+    })?;
+
+    info!(user_id = %user_id, external_id = external_id, "Importing Strava activity");
+
+    let imported = run_sous_bpm_core::services::import_strava_activity(
+        user_id,
+        external_id,
+        &state.strava_client,
+        &state.db_connection,
+        state.encryption_service.as_ref(),
+        &state.sync_events,
+    )
+    .await
+    .map_err(|err| {
+        if err.is::<ActivityOwnedByAnotherUserError>() {
+            ApiError::activity_not_found()
+        } else {
+            strava_error(err.as_ref(), "import Strava activity")
+        }
+    })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "activity": imported.activity,
+            "streams": stream_sync_response(imported.streams)
+        })),
+    ))
+}
+
+/// Previews which stream channels Strava has for an activity, without importing them
+///
+/// # Returns
+///
+/// - `200 OK`: Available channels with their full-resolution sizes
+/// - `400 Bad Request`: Invalid activity ID format
+/// - `401 Unauthorized`: User not authenticated
+/// - `404 Not Found`: Activity not found or not owned by the user
+/// - `502 Bad Gateway`: Failed to retrieve OAuth token or Strava API error
+pub async fn preview_strava_activity_streams(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user_id = auth_session.user.ok_or_else(ApiError::unauthorized)?.id;
+
+    let activity_id = id
+        .parse::<Uuid>()
+        .map_err(|_| ApiError::invalid_activity_id())?;
+
+    let activity = load_owned_activity(&state.db_connection, user_id, activity_id).await?;
+
+    let channels = run_sous_bpm_core::services::preview_strava_activity_streams(
+        user_id,
+        activity.external_id,
+        &state.strava_client,
+        &state.db_connection,
+        state.encryption_service.as_ref(),
+    )
+    .await
+    .map_err(|err| strava_error(err.as_ref(), "preview Strava activity streams"))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "activity_id": activity_id,
+            "channels": channels
+        })),
+    ))
+}
+
+/// Syncs the streams of every activity of the user
+///
+/// Limited to one bulk sync per user per `BULK_SYNC_COOLDOWN_MINUTES`, shared with
+/// the range sync, since each activity costs a Strava request.
+///
+/// # Returns
+///
+/// - `200 OK`: Streams of every activity synced
+/// - `401 Unauthorized`: User not authenticated
+/// - `403 Forbidden`: Strava token lacks a required scope, reconnect Strava
+/// - `429 Too Many Requests`: A bulk sync ran within the cooldown, see `Retry-After`
+/// - `502 Bad Gateway`: Failed to retrieve OAuth token or Strava API error
 pub async fn sync_all_strava_activity_streams(
     State(state): State<Arc<AppState>>,
     auth_session: AuthSession<AuthBackend>,
-) -> (StatusCode, Json<Value>) {
-    let Some(user) = auth_session.user else {
-        return (
-            StatusCode::UNAUTHORIZED,
-            Json(json!({
-                "error": "Unauthorized",
-                "message": "You must be logged in to access this resource"
-            })),
-        );
-    };
-    let user_id = user.id;
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user_id = auth_session.user.ok_or_else(ApiError::unauthorized)?.id;
+    start_bulk_sync(&state, user_id)?;
 
-    match run_sous_bpm_core::services::sync_all_strava_activity_streams(
+    run_sous_bpm_core::services::sync_all_strava_activity_streams(
         user_id,
         &state.strava_client,
         &state.db_connection,
-        &state.encryption_service,
+        state.encryption_service.as_ref(),
+        &state.sync_events,
     )
     .await
-    {
-        Ok(()) => (
-            StatusCode::OK,
-            Json(json!({"message": "Successfully synced all activity streams"})),
-        ),
-        Err(err) => (
-            StatusCode::BAD_GATEWAY,
-            Json(json!({"error": format!("Failed to sync all Strava activity streams: {}", err)})),
-        ),
+    .map_err(|err| strava_error(err.as_ref(), "sync all Strava activity streams"))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({"message": "Successfully synced all activity streams"})),
+    ))
+}
+
+/// Starts the user's bulk sync cooldown, rejecting the sync if it is still running
+fn start_bulk_sync(state: &AppState, user_id: Uuid) -> Result<(), ApiError> {
+    state
+        .bulk_sync_cooldown
+        .try_start(user_id, chrono::Utc::now())
+        .map_err(ApiError::sync_cooldown)
+}
+
+/// Query parameters for the range stream sync endpoint
+#[derive(Debug, Deserialize)]
+pub struct StreamSyncRangeQuery {
+    /// Unix timestamp (seconds), inclusive start of the activity start time window
+    pub start: i64,
+    /// Unix timestamp (seconds), exclusive end of the window
+    pub end: i64,
+}
+
+/// Syncs the streams of the user's activities started within a time window
+///
+/// A targeted alternative to syncing every activity, e.g. for last week's runs.
+///
+/// # Returns
+///
+/// - `200 OK`: One result per activity in the window (`synced`, `unavailable` or `failed`)
+/// - `400 Bad Request`: Timestamps out of range or `start` not before `end`
+/// - `401 Unauthorized`: User not authenticated
+/// - `403 Forbidden`: Strava token lacks a required scope, reconnect Strava
+/// - `429 Too Many Requests`: A bulk sync ran within the cooldown, see `Retry-After`
+/// - `502 Bad Gateway`: Failed to load activities or retrieve the OAuth token
+///
+/// # Example
+/// POST /api/strava/activities/streams/sync/range?start=1762128000&end=1762732800
+pub async fn sync_strava_activity_streams_in_range(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Query(params): Query<StreamSyncRangeQuery>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user_id = auth_session.user.ok_or_else(ApiError::unauthorized)?.id;
+
+    let (Some(start), Some(end)) = (
+        chrono::DateTime::from_timestamp(params.start, 0),
+        chrono::DateTime::from_timestamp(params.end, 0),
+    ) else {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidInput,
+            "Timestamp is out of range",
+        ));
+    };
+    if start >= end {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidInput,
+            "start must be before end",
+        ));
     }
+    start_bulk_sync(&state, user_id)?;
+
+    let results = run_sous_bpm_core::services::sync_strava_activity_streams_in_range(
+        user_id,
+        start,
+        end,
+        state.strava_client.clone(),
+        Arc::clone(&state.db_connection),
+        state.encryption_service.clone(),
+        state.sync_events.clone(),
+    )
+    .await
+    .map_err(|err| strava_error(err.as_ref(), "sync Strava activity streams in range"))?;
+
+    let activities: Vec<Value> = results.iter().map(stream_sync_result).collect();
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "start": start,
+            "end": end,
+            "synced": results
+                .iter()
+                .filter(|r| matches!(r.outcome, Ok(StreamSyncOutcome::Synced { .. })))
+                .count(),
+            "failed": results.iter().filter(|r| r.outcome.is_err()).count(),
+            "activities": activities
+        })),
+    ))
+}
+
+fn stream_sync_result(result: &ActivityStreamSyncResult) -> Value {
+    let (status, points, error) = match &result.outcome {
+        Ok(StreamSyncOutcome::Synced { points, .. }) => ("synced", Some(*points), None),
+        Ok(StreamSyncOutcome::Unavailable) => ("unavailable", None, None),
+        Err(error) => ("failed", None, Some(error.as_str())),
+    };
+
+    json!({
+        "activity_id": result.activity_id,
+        "external_id": result.external_id,
+        "start_time": result.start_time,
+        "status": status,
+        "points": points,
+        "error": error
+    })
+}
+
+/// Query parameters for the activities list endpoint
+#[derive(Debug, Deserialize)]
+pub struct ActivitiesQuery {
+    /// Zero-based page index (default: 0), only valid along with `per_page`
+    pub page: Option<u64>,
+    /// Page size, 0 meaning `DEFAULT_PAGE_SIZE` and capped to `MAX_PAGE_SIZE`
+    /// (default: all activities in a single page)
+    pub per_page: Option<u64>,
+    /// Only return activities of this type (e.g. `Run`)
+    #[serde(rename = "type")]
+    pub activity_type: Option<String>,
+    /// Only return activities of this sport: `run`, `ride`, `swim` or `other`
+    pub sport: Option<ActivityCategory>,
 }
 
 /// Retrieves user's Strava activities from the local database
 ///
-/// Returns all activities that have been synced to the database, ordered by start time (most recent first).
+/// Returns activities that have been synced to the database, ordered by start time (most recent first),
+/// wrapped in a pagination envelope with the total number of matching activities.
+///
+/// # Arguments
+///
+/// * `page` - Zero-based page index, requires `per_page`
+/// * `per_page` - Optional page size, clamped to `MAX_PAGE_SIZE` (0 uses `DEFAULT_PAGE_SIZE`);
+///   without it every activity is returned in a single page
+/// * `type` - Optional activity type filter
+/// * `sport` - Optional sport category filter, covering every type aliased to it
 ///
 /// # Returns
 ///
-/// - `200 OK`: JSON array of activities from database
+/// - `200 OK`: `{ items, total, page, per_page }`, `per_page` being the page size applied
+/// - `400 Bad Request`: `page` given without `per_page`
 /// - `401 Unauthorized`: User not authenticated
 /// - `500 Internal Server Error`: Database query failed
 pub async fn get_strava_activities(
     State(state): State<Arc<AppState>>,
     auth_session: AuthSession<AuthBackend>,
-) -> (StatusCode, Json<Value>) {
-    let Some(user) = auth_session.user else {
-        return (
-            StatusCode::UNAUTHORIZED,
-            Json(json!({
-                "error": "Unauthorized",
-                "message": "You must be logged in to access this resource"
-            })),
-        );
-    };
-    let user_id = user.id;
+    Query(params): Query<ActivitiesQuery>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user_id = auth_session.user.ok_or_else(ApiError::unauthorized)?.id;
+    let (page, per_page) = page_bounds(params.page, params.per_page)?;
 
-    match run_sous_bpm_core::database::activity_repository::get_activities_by_user(
+    let activity_type = params.activity_type.as_deref();
+    let total = activity_repository::count_activities_by_user(
         &state.db_connection,
         user_id,
+        activity_type,
+        params.sport,
     )
     .await
-    {
-        Ok(activities) => (StatusCode::OK, Json(json!(activities))),
-        Err(err) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Failed to retrieve activities: {}", err)})),
-        ),
+    .map_err(ApiError::database)?;
+
+    let items = activity_repository::get_activities_page_by_user(
+        &state.db_connection,
+        user_id,
+        activity_type,
+        params.sport,
+        page,
+        per_page,
+    )
+    .await
+    .map_err(ApiError::database)?;
+
+    let response = Paginated {
+        items,
+        total,
+        page,
+        per_page,
+    };
+
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+/// Page index and clamped page size requested from a list endpoint
+///
+/// Without a page size the whole list fits in page 0, so asking for another
+/// page without one is rejected rather than silently ignored.
+fn page_bounds(page: Option<u64>, per_page: Option<u64>) -> Result<(u64, Option<u64>), ApiError> {
+    match (page, per_page) {
+        (Some(_), None) => Err(ApiError::bad_request(
+            ErrorCode::InvalidInput,
+            "`page` requires `per_page`",
+        )),
+        (page, per_page) => Ok((page.unwrap_or(0), per_page.map(clamp_page_size))),
     }
 }
 
+/// Query parameters for activity streams endpoint
+#[derive(Debug, Deserialize)]
+pub struct ActivityStreamsQuery {
+    /// Maximum number of points to return (default: all points)
+    pub max_points: Option<usize>,
+}
+
 /// Retrieves detailed stream data for a specific activity from the local database
 ///
 /// Returns time-series data (GPS coordinates, heart rate, cadence, etc.) that has been synced to the database.
@@ -221,11 +518,12 @@ pub async fn get_strava_activities(
 /// # Arguments
 ///
 /// * `id` - The activity's internal UUID
+/// * `max_points` - Optional point budget; the stream is simplified down to it before serialization
 ///
 /// # Returns
 ///
 /// - `200 OK`: JSON array containing activity stream data points
-/// - `400 Bad Request`: Invalid activity ID format
+/// - `400 Bad Request`: Invalid activity ID format or `max_points` lower than 2
 /// - `401 Unauthorized`: User not authenticated
 /// - `404 Not Found`: Activity not found or not owned by user
 /// - `500 Internal Server Error`: Database query failed
@@ -233,64 +531,507 @@ pub async fn get_strava_activity_streams(
     State(state): State<Arc<AppState>>,
     auth_session: AuthSession<AuthBackend>,
     Path(id): Path<String>,
-) -> (StatusCode, Json<Value>) {
-    let Some(user) = auth_session.user else {
-        return (
-            StatusCode::UNAUTHORIZED,
-            Json(json!({
-                "error": "Unauthorized",
-                "message": "You must be logged in to access this resource"
-            })),
-        );
+    Query(params): Query<ActivityStreamsQuery>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user_id = auth_session.user.ok_or_else(ApiError::unauthorized)?.id;
+
+    let activity_id = id
+        .parse::<Uuid>()
+        .map_err(|_| ApiError::invalid_activity_id())?;
+
+    if params.max_points.is_some_and(|max| max < 2) {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidInput,
+            "max_points must be at least 2",
+        ));
+    }
+
+    // First verify the activity exists and belongs to the user
+    load_owned_activity(&state.db_connection, user_id, activity_id).await?;
+
+    let streams = run_sous_bpm_core::database::activity_stream_repository::get_activity_streams(
+        &state.db_connection,
+        activity_id,
+    )
+    .await
+    .map_err(ApiError::database)?;
+
+    let streams = match params.max_points {
+        Some(max_points) => analytics_service::downsample_streams(streams, max_points),
+        None => streams,
     };
-    let user_id = user.id;
+    Ok((StatusCode::OK, Json(json!(streams))))
+}
 
-    let Ok(activity_id) = id.parse::<sea_orm::prelude::Uuid>() else {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Invalid activity ID format"})),
-        );
+/// Summarizes each numeric stream channel of an activity for quick diagnostics
+///
+/// # Returns
+///
+/// - `200 OK`: Point count and min/max/avg/count per channel, nulls for unrecorded channels
+/// - `400 Bad Request`: Invalid activity ID format
+/// - `401 Unauthorized`: User not authenticated
+/// - `404 Not Found`: Activity not found or not owned by user
+/// - `500 Internal Server Error`: Database query failed
+pub async fn get_strava_activity_stream_stats(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user_id = auth_session.user.ok_or_else(ApiError::unauthorized)?.id;
+
+    let activity_id = id
+        .parse::<Uuid>()
+        .map_err(|_| ApiError::invalid_activity_id())?;
+
+    load_owned_activity(&state.db_connection, user_id, activity_id).await?;
+
+    let streams = run_sous_bpm_core::database::activity_stream_repository::get_activity_streams(
+        &state.db_connection,
+        activity_id,
+    )
+    .await
+    .map_err(ApiError::database)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "activity_id": activity_id,
+            "points": streams.len(),
+            "channels": analytics_service::calculate_stream_stats(&streams)
+        })),
+    ))
+}
+
+/// Returns an activity's summary: average/max heart rate, elevation gain and moving time
+///
+/// Reads the values stored by the last stream sync, computing them from the streams
+/// for activities synced before summaries were stored.
+///
+/// # Returns
+///
+/// - `200 OK`: `{ activity_id, summary }`, `summary.computed_at` being `null` when computed on the fly
+/// - `400 Bad Request`: Invalid activity ID format
+/// - `401 Unauthorized`: User not authenticated
+/// - `404 Not Found`: Activity not found or not owned by user
+/// - `500 Internal Server Error`: Database query failed
+pub async fn get_strava_activity_summary(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user_id = auth_session.user.ok_or_else(ApiError::unauthorized)?.id;
+
+    let activity_id = id
+        .parse::<Uuid>()
+        .map_err(|_| ApiError::invalid_activity_id())?;
+
+    let activity = load_owned_activity(&state.db_connection, user_id, activity_id).await?;
+
+    let summary = get_activity_summary(&state.db_connection, &activity)
+        .await
+        .map_err(ApiError::database)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "activity_id": activity_id,
+            "summary": summary
+        })),
+    ))
+}
+
+/// Returns the best efforts (fastest 400m, 1k, 5k...) Strava computed for a run
+///
+/// Best efforts are fetched from Strava's detailed activity on first request and stored.
+/// Activities that aren't runs have none.
+///
+/// # Returns
+///
+/// - `200 OK`: `{ activity_id, best_efforts }`, shortest distance first
+/// - `400 Bad Request`: Invalid activity ID format
+/// - `401 Unauthorized`: User not authenticated
+/// - `403 Forbidden`: Strava token lacks a required scope, reconnect Strava
+/// - `404 Not Found`: Activity not found or not owned by user
+/// - `502 Bad Gateway`: Failed to retrieve OAuth token or Strava API error
+pub async fn get_strava_activity_best_efforts(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user_id = auth_session.user.ok_or_else(ApiError::unauthorized)?.id;
+
+    let activity_id = id
+        .parse::<Uuid>()
+        .map_err(|_| ApiError::invalid_activity_id())?;
+
+    let activity = load_owned_activity(&state.db_connection, user_id, activity_id).await?;
+
+    let best_efforts = run_sous_bpm_core::services::get_strava_activity_best_efforts(
+        user_id,
+        &activity,
+        &state.strava_client,
+        &state.db_connection,
+        state.encryption_service.as_ref(),
+    )
+    .await
+    .map_err(|err| strava_error(err.as_ref(), "fetch Strava best efforts"))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "activity_id": activity_id,
+            "best_efforts": best_efforts
+        })),
+    ))
+}
+
+/// Lists which stream channels have data for an activity, without loading the points
+///
+/// Lets the frontend pick which charts to draw before downloading the streams.
+///
+/// # Returns
+///
+/// - `200 OK`: `{ activity_id, channels: { has_hr, has_watts, has_latlng, has_cadence, has_altitude, has_temperature } }`
+/// - `400 Bad Request`: Invalid activity ID format
+/// - `401 Unauthorized`: User not authenticated
+/// - `404 Not Found`: Activity not found or not owned by user
+/// - `500 Internal Server Error`: Database query failed
+pub async fn get_strava_activity_stream_channels(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user_id = auth_session.user.ok_or_else(ApiError::unauthorized)?.id;
+
+    let activity_id = id
+        .parse::<Uuid>()
+        .map_err(|_| ApiError::invalid_activity_id())?;
+
+    load_owned_activity(&state.db_connection, user_id, activity_id).await?;
+
+    let channels = run_sous_bpm_core::database::activity_stream_repository::get_stream_channels(
+        &state.db_connection,
+        activity_id,
+    )
+    .await
+    .map_err(ApiError::database)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "activity_id": activity_id,
+            "channels": channels
+        })),
+    ))
+}
+
+/// Query parameters for the pause detection endpoint
+#[derive(Debug, Deserialize)]
+pub struct PauseQuery {
+    /// Shortest stop reported, in seconds (default: 15)
+    pub min_duration: Option<i64>,
+    /// Speed in m/s under which the athlete counts as stopped (default: 0.5)
+    pub min_speed: Option<f64>,
+}
+
+/// Lists the intervals where the athlete stopped during an activity
+///
+/// A stop is a stretch where the speed implied by the distance stream stayed
+/// below `min_speed` for at least `min_duration` seconds (water, photos, lights).
+///
+/// # Returns
+///
+/// - `200 OK`: `{ activity_id, pauses }`, each with start/end time, duration and location
+/// - `400 Bad Request`: Invalid activity ID or non-positive thresholds
+/// - `401 Unauthorized`: User not authenticated
+/// - `404 Not Found`: Activity not found or not owned by user
+/// - `500 Internal Server Error`: Database query failed
+pub async fn get_strava_activity_pauses(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Path(id): Path<String>,
+    Query(params): Query<PauseQuery>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user_id = auth_session.user.ok_or_else(ApiError::unauthorized)?.id;
+
+    let activity_id = id
+        .parse::<Uuid>()
+        .map_err(|_| ApiError::invalid_activity_id())?;
+
+    let min_duration = chrono::Duration::try_seconds(
+        params
+            .min_duration
+            .unwrap_or(analytics_service::DEFAULT_PAUSE_MIN_DURATION_SECONDS),
+    )
+    .filter(|duration| *duration > chrono::Duration::zero());
+    let min_speed = params
+        .min_speed
+        .unwrap_or(analytics_service::DEFAULT_PAUSE_MAX_SPEED);
+    let Some(min_duration) = min_duration.filter(|_| min_speed.is_finite() && min_speed > 0.0)
+    else {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidInput,
+            "min_duration and min_speed must be positive",
+        ));
     };
 
-    // First verify the activity exists and belongs to the user
-    match run_sous_bpm_core::database::activity_repository::get_activity_by_id(
+    load_owned_activity(&state.db_connection, user_id, activity_id).await?;
+
+    let streams = run_sous_bpm_core::database::activity_stream_repository::get_activity_streams(
         &state.db_connection,
         activity_id,
     )
     .await
-    {
-        Ok(Some(activity)) if activity.user_id == user_id => {
-            // Activity found and belongs to user, get streams
-            match run_sous_bpm_core::database::activity_stream_repository::get_activity_streams(
-                &state.db_connection,
-                activity_id,
-            )
-            .await
-            {
-                Ok(streams) => (StatusCode::OK, Json(json!(streams))),
-                Err(err) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": format!("Failed to retrieve activity streams: {}", err)})),
-                ),
-            }
-        }
-        Ok(Some(_)) => {
-            // Activity exists but doesn't belong to user
-            (
-                StatusCode::NOT_FOUND,
-                Json(json!({"error": "Activity not found"})),
-            )
-        }
-        Ok(None) => {
-            // Activity doesn't exist
-            (
-                StatusCode::NOT_FOUND,
-                Json(json!({"error": "Activity not found"})),
-            )
-        }
-        Err(err) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Database error: {}", err)})),
-        ),
+    .map_err(ApiError::database)?;
+
+    let pauses = analytics_service::detect_pauses(&streams, min_duration, min_speed);
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "activity_id": activity_id,
+            "pauses": pauses
+        })),
+    ))
+}
+
+/// Lists pairs of the user's activities that overlap in time
+///
+/// Helps spot a session logged twice, e.g. by a watch and a treadmill app. Both
+/// activities already show the music of their common window, so nothing is merged.
+///
+/// # Returns
+///
+/// - `200 OK`: `{ overlaps }`, each with both activity IDs and the overlapping window
+/// - `401 Unauthorized`: User not authenticated
+/// - `500 Internal Server Error`: Database query failed
+pub async fn get_strava_activity_overlaps(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user_id = auth_session.user.ok_or_else(ApiError::unauthorized)?.id;
+
+    let overlaps = analytics_service::get_activity_overlaps(&state.db_connection, user_id)
+        .await
+        .map_err(ApiError::database)?;
+
+    Ok((StatusCode::OK, Json(json!({ "overlaps": overlaps }))))
+}
+
+/// Query parameters for the similar activities endpoint
+#[derive(Debug, Deserialize)]
+pub struct SimilarActivitiesQuery {
+    /// Most activities returned (default: 5, maximum: 20)
+    pub limit: Option<usize>,
+}
+
+/// Lists the user's activities run on the same course as an activity, closest first
+///
+/// Routes are compared approximately: bounding-box overlap, then the Fréchet
+/// distance between the routes resampled to a few dozen points. Activities
+/// without GPS points have no similar activities.
+///
+/// # Returns
+///
+/// - `200 OK`: `{ activity_id, similar }`, each with the activity, `bounds_overlap` and `frechet_meters`
+/// - `400 Bad Request`: Invalid activity ID or `limit` of 0
+/// - `401 Unauthorized`: User not authenticated
+/// - `404 Not Found`: Activity not found or not owned by user
+/// - `500 Internal Server Error`: Database query failed
+pub async fn get_strava_similar_activities(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Path(id): Path<String>,
+    Query(params): Query<SimilarActivitiesQuery>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user_id = auth_session.user.ok_or_else(ApiError::unauthorized)?.id;
+
+    let activity_id = id
+        .parse::<Uuid>()
+        .map_err(|_| ApiError::invalid_activity_id())?;
+
+    let limit = params.limit.unwrap_or(DEFAULT_SIMILAR_ACTIVITIES_LIMIT);
+    if limit == 0 {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidInput,
+            "limit must be positive",
+        ));
+    }
+
+    load_owned_activity(&state.db_connection, user_id, activity_id).await?;
+
+    let similar = run_sous_bpm_core::services::find_similar_activities(
+        &state.db_connection,
+        user_id,
+        activity_id,
+        limit.min(MAX_ROUTE_CANDIDATES),
+    )
+    .await
+    .map_err(ApiError::database)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "activity_id": activity_id,
+            "similar": similar
+        })),
+    ))
+}
+
+/// Query parameters for the elevation profile endpoint
+#[derive(Debug, Deserialize)]
+pub struct ElevationQuery {
+    /// Most points returned (default: 500, minimum: 2)
+    pub max_points: Option<usize>,
+}
+
+/// Returns the (distance, altitude) profile of an activity, downsampled for charting
+///
+/// Points missing altitude are skipped. The profile is reduced with the same
+/// simplifier as routes, so climbs and descents survive the point budget.
+///
+/// # Returns
+///
+/// - `200 OK`: `{ activity_id, points }`, each with distance (m) and altitude (m)
+/// - `400 Bad Request`: Invalid activity ID or `max_points` lower than 2
+/// - `401 Unauthorized`: User not authenticated
+/// - `404 Not Found`: Activity not found or not owned by user
+/// - `500 Internal Server Error`: Database query failed
+pub async fn get_strava_activity_elevation(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+    Path(id): Path<String>,
+    Query(params): Query<ElevationQuery>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user_id = auth_session.user.ok_or_else(ApiError::unauthorized)?.id;
+
+    let activity_id = id
+        .parse::<Uuid>()
+        .map_err(|_| ApiError::invalid_activity_id())?;
+
+    let max_points = params
+        .max_points
+        .unwrap_or(analytics_service::DEFAULT_ELEVATION_PROFILE_POINTS);
+    if max_points < 2 {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidInput,
+            "max_points must be at least 2",
+        ));
+    }
+
+    load_owned_activity(&state.db_connection, user_id, activity_id).await?;
+
+    let streams = run_sous_bpm_core::database::activity_stream_repository::get_activity_streams(
+        &state.db_connection,
+        activity_id,
+    )
+    .await
+    .map_err(ApiError::database)?;
+
+    let points = analytics_service::elevation_profile(&streams, max_points)
+        .map_err(|e| ApiError::bad_request(ErrorCode::InvalidInput, e.to_string()))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "activity_id": activity_id,
+            "points": points
+        })),
+    ))
+}
+
+/// Reports the shared Strava request budget
+///
+/// Usage comes from the rate limit headers of the last Strava response; requests
+/// are paused (`paused_until` set) once a window is nearly used up.
+///
+/// # Returns
+///
+/// - `200 OK`: `{ short_term, daily, observed_at, paused_until }`, windows are `null`
+///   until Strava has been called
+/// - `401 Unauthorized`: User not authenticated
+pub async fn get_strava_rate_limit(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    auth_session.user.ok_or_else(ApiError::unauthorized)?;
+
+    let status = state.strava_rate_limit.status(chrono::Utc::now());
+
+    Ok((StatusCode::OK, Json(json!(status))))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::response::IntoResponse;
+    use run_sous_bpm_core::{
+        config::OAuthProvider,
+        database::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE},
+        services::ReconnectRequiredError,
+    };
+    use run_sous_bpm_integrations::common::IntegrationError;
+
+    use super::*;
+
+    #[test]
+    fn test_missing_scope_surfaces_reconnect_required() {
+        let err: Box<dyn std::error::Error> = Box::new(ReconnectRequiredError {
+            provider: OAuthProvider::Strava,
+            missing_scope: "activity:read_all".to_string(),
+        });
+
+        let api_error = strava_error(err.as_ref(), "sync Strava activities");
+
+        assert_eq!(api_error.code, ErrorCode::ReconnectRequired);
+        assert!(api_error.message.contains("reconnect strava"));
+        assert_eq!(api_error.into_response().status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_other_sync_failures_are_bad_gateway() {
+        let err: Box<dyn std::error::Error> = "connection reset".into();
+
+        let api_error = strava_error(err.as_ref(), "sync Strava activities");
+
+        assert_eq!(api_error.code, ErrorCode::StravaError);
+        assert_eq!(
+            api_error.message,
+            "Failed to sync Strava activities: connection reset"
+        );
+    }
+
+    #[test]
+    fn test_paused_requests_are_too_many_requests() {
+        let resume_at = chrono::DateTime::from_timestamp(1_762_769_700, 0).unwrap();
+        let err: Box<dyn std::error::Error> = Box::new(IntegrationError::RateLimited(resume_at));
+
+        let api_error = strava_error(err.as_ref(), "sync Strava activity streams");
+
+        assert_eq!(api_error.code, ErrorCode::StravaRateLimited);
+        assert_eq!(
+            api_error.into_response().status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+
+    #[test]
+    fn test_page_without_page_size_is_rejected() {
+        let api_error = page_bounds(Some(0), None).unwrap_err();
+
+        assert_eq!(api_error.code, ErrorCode::InvalidInput);
+        assert_eq!(api_error.into_response().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_page_bounds_default_to_first_page_and_clamp_size() {
+        assert_eq!(page_bounds(None, None).unwrap(), (0, None));
+        assert_eq!(
+            page_bounds(None, Some(0)).unwrap(),
+            (0, Some(DEFAULT_PAGE_SIZE))
+        );
+        assert_eq!(
+            page_bounds(Some(3), Some(MAX_PAGE_SIZE + 1)).unwrap(),
+            (3, Some(MAX_PAGE_SIZE))
+        );
     }
 }
@@ -2,70 +2,107 @@ use std::sync::Arc;
 
 use axum::{extract::State, http::StatusCode, Json};
 use axum_login::AuthSession;
-use run_sous_bpm_core::auth::AuthBackend;
+use run_sous_bpm_core::{auth::AuthBackend, services::ListenPadding};
 use serde::Deserialize;
 use serde_json::{json, Value};
 
-use crate::AppState;
+use crate::{
+    responses::{ApiError, ErrorCode},
+    AppState,
+};
 
 #[derive(Deserialize)]
 pub struct UpdateUserRequest {
     pub lastfm_username: Option<String>,
+    /// Default seconds before an activity in which listens are matched
+    pub listen_padding_before_seconds: Option<u32>,
+    /// Default seconds after an activity in which listens are matched
+    pub listen_padding_after_seconds: Option<u32>,
 }
 
 pub async fn patch_user(
     State(state): State<Arc<AppState>>,
     auth_session: AuthSession<AuthBackend>,
     Json(payload): Json<UpdateUserRequest>,
-) -> (StatusCode, Json<Value>) {
-    let Some(user) = auth_session.user else {
-        return (
-            StatusCode::UNAUTHORIZED,
-            Json(json!({
-                "error": "Unauthorized",
-                "message": "You must be logged in to access this resource"
-            })),
-        );
-    };
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
 
-    let Some(lastfm_username) = payload.lastfm_username else {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "Bad Request",
-                "message": "lastfm_username is required"
-            })),
-        );
-    };
+    let padding_changed = payload.listen_padding_before_seconds.is_some()
+        || payload.listen_padding_after_seconds.is_some();
+    if payload.lastfm_username.is_none() && !padding_changed {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidInput,
+            "lastfm_username or a listen padding is required",
+        ));
+    }
+
+    // Validate everything before writing anything
+    let padding = ListenPadding::from_user(&user)
+        .with_overrides(
+            payload.listen_padding_before_seconds,
+            payload.listen_padding_after_seconds,
+        )
+        .map_err(|e| ApiError::bad_request(ErrorCode::InvalidInput, e.to_string()))?;
 
-    match run_sous_bpm_core::services::user_service::update_valid_user_lastfm_username(
+    run_sous_bpm_core::services::user_service::update_user_settings(
         user.id,
-        lastfm_username,
+        payload.lastfm_username,
+        padding_changed.then_some(padding),
         &state.db_connection,
     )
     .await
-    {
-        Ok(()) => (
-            StatusCode::OK,
-            Json(json!({
-                "message": "Last.fm username updated successfully"
-            })),
-        ),
-        Err(e) => {
-            let error_msg = e.to_string();
-            let status = if error_msg.contains("Invalid Last.fm username") {
-                StatusCode::BAD_REQUEST
-            } else {
-                StatusCode::INTERNAL_SERVER_ERROR
-            };
+    .map_err(|e| {
+        if let Some(api_error) = ApiError::music_not_configured(e.as_ref())
+            .or_else(|| ApiError::lastfm_failure(e.as_ref()))
+        {
+            return api_error;
+        }
 
-            (
-                status,
-                Json(json!({
-                    "error": status.canonical_reason().unwrap_or("Error"),
-                    "message": error_msg
-                })),
-            )
+        let error_msg = e.to_string();
+        if error_msg.contains("Invalid Last.fm username") {
+            ApiError::bad_request(ErrorCode::InvalidLastfmUsername, error_msg)
+        } else {
+            ApiError::internal(ErrorCode::InternalError, error_msg)
         }
-    }
+    })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "message": "User updated successfully",
+            "listen_padding_before_seconds": padding.before_seconds,
+            "listen_padding_after_seconds": padding.after_seconds,
+        })),
+    ))
+}
+
+/// Unlinks the user's Last.fm account
+///
+/// Listens already stored are kept. Music endpoints that need to sync then answer
+/// `409 lastfm_not_configured` until a username is set again.
+///
+/// # Returns
+///
+/// - `200 OK`: Last.fm unlinked (also when none was linked)
+/// - `401 Unauthorized`: User not authenticated
+/// - `500 Internal Server Error`: Database operation failed
+///
+/// # Example
+/// DELETE /api/user/lastfm
+pub async fn unlink_lastfm(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession<AuthBackend>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let user = auth_session.user.ok_or_else(ApiError::unauthorized)?;
+
+    run_sous_bpm_core::services::user_service::unlink_user_lastfm(user.id, &state.db_connection)
+        .await
+        .map_err(ApiError::database)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "message": "Last.fm unlinked successfully",
+        })),
+    ))
 }
@@ -1,30 +1,49 @@
+mod admin;
 mod handlers;
 mod middleware;
+mod redirect_allowlist;
 mod responses;
+mod session_config;
+mod sync_cooldown;
 mod tracing_config;
 
-use axum::extract::MatchedPath;
+use axum::extract::{DefaultBodyLimit, MatchedPath};
 use axum::http::{HeaderValue, Method, Request, Response};
 use axum::{
-    middleware::from_fn,
-    routing::{get, patch, post},
+    middleware::{from_fn, from_fn_with_state},
+    routing::{delete, get, patch, post},
     Router,
 };
 use axum_login::{login_required, AuthManagerLayerBuilder};
 use handlers::{
-    get_activity_music, get_current_user, get_strava_activities, get_strava_activity_streams,
-    handler_404, health, login_user, logout_user, oauth_callback, oauth_process_callback,
-    register_user, root, sync_all_strava_activity_streams, sync_strava_activities,
-    sync_strava_activity_streams,
+    enrich_spotify_tracks, export_activity_music_csv, get_activity_listen_density,
+    get_activity_music, get_activity_music_timeline, get_activity_track_at, get_crypto_status,
+    get_current_user, get_lastfm_range, get_listen_range, get_strava_activities,
+    get_strava_activity_best_efforts, get_strava_activity_elevation, get_strava_activity_pauses,
+    get_strava_activity_stream_channels, get_strava_activity_stream_stats,
+    get_strava_activity_streams, get_strava_activity_summary, get_strava_rate_limit,
+    get_strava_similar_activities, get_track, get_training_load_stats, handler_404,
+    health_integrations, health_live, health_ready, import_lastfm_listens, import_strava_activity,
+    login_user, logout_user, merge_duplicate_tracks, oauth_callback, oauth_process_callback,
+    preview_strava_activity_streams, reencrypt_tokens, refresh_session, register_user, root,
+    set_activity_time_offset, sync_all_strava_activity_streams, sync_events,
+    sync_strava_activities, sync_strava_activity_streams, sync_strava_activity_streams_in_range,
 };
-use run_sous_bpm_core::config::read_secret;
+use run_sous_bpm_core::config::{read_optional_secret, OAuthProvider};
 use run_sous_bpm_core::crypto::EncryptionService;
 use run_sous_bpm_core::{
-    auth::AuthBackend, database::establish_db_connection, services::OAuthSessionManager,
+    auth::{AuthBackend, BearerTokenService},
+    database::establish_db_connection,
+    services::{OAuthSessionManager, SyncEventBus},
 };
 use run_sous_bpm_integrations::{
-    common::{AuthenticatedClient, IntegrationClient},
-    strava::StravaApiClient,
+    common::{
+        reachability::DEFAULT_PROBE_TIMEOUT, AuthenticatedClient, IntegrationClient,
+        ReachabilityChecker,
+    },
+    lastfm::last_fm_api_url,
+    spotify::SpotifyApiClient,
+    strava::{StravaApiClient, StravaRateLimiter},
 };
 use sea_orm::DatabaseConnection;
 use std::net::SocketAddr;
@@ -38,21 +57,30 @@ use tower_http::classify::ServerErrorsFailureClass;
 use tower_http::cors::AllowOrigin;
 use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
-use tower_sessions::{
-    cookie::{time, SameSite},
-    Expiry, SessionManagerLayer,
-};
+use tower_sessions::{Expiry, SessionManagerLayer};
 use tower_sessions_redis_store::{fred::prelude::*, RedisStore};
-use tracing::{info, info_span, Span};
+use tracing::{info, info_span, warn, Span};
 
-use crate::handlers::{patch_user, remove_oauth_provider};
+use crate::admin::AdminAllowlist;
+use crate::handlers::{patch_user, remove_oauth_provider, unlink_lastfm};
+use crate::redirect_allowlist::RedirectAllowlist;
+use crate::session_config::{SessionSettings, SESSION_COOKIE_NAME};
+use crate::sync_cooldown::SyncCooldown;
 
 #[derive(Clone)]
 struct AppState {
-    db_connection: DatabaseConnection,
+    db_connection: Arc<DatabaseConnection>,
     oauth_session_store: Arc<OAuthSessionManager>,
     strava_client: Arc<StravaApiClient>,
+    strava_rate_limit: Arc<StravaRateLimiter>,
+    spotify_client: Arc<SpotifyApiClient>,
     encryption_service: Arc<EncryptionService>,
+    bearer_tokens: Option<Arc<BearerTokenService>>,
+    redirect_allowlist: Arc<RedirectAllowlist>,
+    sync_events: SyncEventBus,
+    bulk_sync_cooldown: Arc<SyncCooldown>,
+    admins: Arc<AdminAllowlist>,
+    integration_health: Option<Arc<ReachabilityChecker>>,
 }
 
 #[tokio::main]
@@ -62,22 +90,35 @@ async fn main() -> anyhow::Result<()> {
 
     // Inject LAST_FM_API_KEY into process env so the lastfm-client crate can find it via env::var.
     // The crate reads the key directly from env; this bridges the *_FILE pattern for Docker Secrets.
-    std::env::set_var("LAST_FM_API_KEY", read_secret("LAST_FM_API_KEY"));
+    // Without a key the music endpoints answer 503 instead of the server refusing to start.
+    let lastfm_api_key = read_optional_secret("LAST_FM_API_KEY");
+    if let Some(key) = &lastfm_api_key {
+        std::env::set_var("LAST_FM_API_KEY", key);
+    }
 
     tracing_config::init_tracing();
 
+    if lastfm_api_key.is_none() {
+        warn!("LAST_FM_API_KEY is not set, music integration is disabled");
+    }
+
     let oauth_session_store = Arc::new(OAuthSessionManager::new());
-    let db_connection = establish_db_connection().await?;
+    let db_connection = Arc::new(establish_db_connection().await?);
 
     let http_client = Arc::new(AuthenticatedClient::new());
 
     let strava_base_url = std::env::var("STRAVA_API_URL")
         .unwrap_or_else(|_| "https://www.strava.com/api/v3".to_string());
+    let integration_health = integration_health_checker(&strava_base_url, lastfm_api_key.is_some());
     let strava_integration_client = IntegrationClient::new(http_client.clone());
-    let strava_client = Arc::new(StravaApiClient::new(
-        strava_integration_client,
-        strava_base_url,
-    ));
+    let strava_rate_limit = Arc::new(StravaRateLimiter::new());
+    let strava_client = Arc::new(
+        StravaApiClient::new(strava_integration_client, strava_base_url)
+            .with_rate_limiter(Arc::clone(&strava_rate_limit)),
+    );
+    let spotify_client = Arc::new(SpotifyApiClient::from_env(IntegrationClient::new(
+        http_client.clone(),
+    )));
 
     let encryption_key_path =
         std::env::var("ENCRYPTION_KEY_FILE").expect("ENCRYPTION_KEY_FILE must be set in .env");
@@ -87,11 +128,29 @@ async fn main() -> anyhow::Result<()> {
     );
     info!("Encryption service initialized successfully");
 
+    let bearer_tokens = BearerTokenService::from_env().map(Arc::new);
+    if bearer_tokens.is_some() {
+        info!("Bearer token auth enabled");
+    }
+
+    let admins = AdminAllowlist::from_env();
+    if admins.is_enabled() {
+        info!("Admin endpoints enabled");
+    }
+
     let state = AppState {
-        db_connection: db_connection.clone(),
+        db_connection: Arc::clone(&db_connection),
         oauth_session_store: oauth_session_store.clone(),
         strava_client,
+        strava_rate_limit,
+        spotify_client,
         encryption_service,
+        bearer_tokens: bearer_tokens.clone(),
+        redirect_allowlist: Arc::new(RedirectAllowlist::from_env()),
+        sync_events: SyncEventBus::new(),
+        bulk_sync_cooldown: Arc::new(SyncCooldown::from_env()),
+        admins: Arc::new(admins),
+        integration_health,
     };
 
     let redis_url = std::env::var("REDIS_URL").unwrap_or_else(|_| {
@@ -118,27 +177,35 @@ async fn main() -> anyhow::Result<()> {
     let session_store = RedisStore::new(redis_pool);
     info!("Redis session store initialized");
 
-    // Session configuration with security best practices
-    // - HttpOnly: prevents JavaScript access to cookies (default in tower_sessions)
-    // - Secure: only send cookie over HTTPS (configurable via COOKIE_SECURE env var)
-    // - SameSite::Strict: only send cookie for same-site requests (prevents CSRF)
-    let cookie_secure = std::env::var("COOKIE_SECURE")
-        .map(|v| v.to_lowercase() == "true")
-        .unwrap_or(false); // Default to false for local development
+    let allowed_origin = std::env::var("FRONTEND_URL").expect("FRONTEND_URL must be set");
+
+    // Fail fast on cookie settings that would silently break authentication
+    let session_settings = SessionSettings::from_env();
+    session_settings.log();
+    session_settings.validate(&allowed_origin)?;
 
     let session_layer = SessionManagerLayer::new(session_store)
-        .with_name("run_sous_bpm_session")
-        .with_secure(cookie_secure)
-        .with_same_site(SameSite::Strict) // Changed from Lax to Strict for better security
-        .with_http_only(true) // Explicitly set HttpOnly (prevents XSS attacks)
-        .with_expiry(Expiry::OnInactivity(time::Duration::hours(1)));
-    let auth_backend = AuthBackend::new(db_connection.clone());
+        .with_name(SESSION_COOKIE_NAME)
+        .with_secure(session_settings.secure)
+        .with_same_site(session_settings.same_site)
+        .with_http_only(session_settings.http_only)
+        .with_expiry(Expiry::OnInactivity(session_settings.inactivity_expiry));
+    let auth_backend = AuthBackend::new(db_connection);
     let auth_layer = AuthManagerLayerBuilder::new(auth_backend, session_layer).build();
 
     let oauth_callback_route =
         std::env::var("REDIRECT_ENDPOINT").unwrap_or_else(|_| "/api/oauth/callback".to_string());
+    // Providers with their own redirect URI get their callback path served as well
+    let mut oauth_callback_routes = vec![oauth_callback_route];
+    for path in OAuthProvider::ALL
+        .into_iter()
+        .filter_map(OAuthProvider::specific_redirect_path)
+    {
+        if !oauth_callback_routes.contains(&path) {
+            oauth_callback_routes.push(path);
+        }
+    }
 
-    let allowed_origin = std::env::var("FRONTEND_URL").expect("FRONTEND_URL must be set");
     let allowed_header: HeaderValue = allowed_origin
         .parse()
         .expect("FRONTEND_URL must be a valid HTTP origin header value");
@@ -159,7 +226,9 @@ async fn main() -> anyhow::Result<()> {
         .allow_headers([
             axum::http::header::CONTENT_TYPE,
             axum::http::header::AUTHORIZATION,
-        ]);
+            middleware::REQUEST_ID_HEADER.clone(),
+        ])
+        .expose_headers([middleware::REQUEST_ID_HEADER.clone()]);
 
     // Auth-specific rate limit: 5 attempts/minute, burst 5 (blocks brute-force on login/register)
     let auth_rate_config = {
@@ -174,41 +243,128 @@ async fn main() -> anyhow::Result<()> {
     let auth_routes = Router::new()
         .route("/api/auth/register", post(register_user))
         .route("/api/auth/login", post(login_user))
+        .route("/api/auth/refresh", post(refresh_session))
         .layer(GovernorLayer::new(auth_rate_config));
 
-    let public_routes = Router::new()
+    let public_routes = oauth_callback_routes
+        .iter()
+        .fold(Router::new(), |router, route| {
+            router.route(route, get(oauth_process_callback))
+        })
         .route("/", get(root))
-        .route("/health", get(health))
-        .route(&oauth_callback_route, get(oauth_process_callback))
+        .route("/health", get(health_ready))
+        .route("/health/live", get(health_live))
+        .route("/health/ready", get(health_ready))
+        .route("/health/integrations", get(health_integrations))
         .merge(auth_routes);
 
     let protected_routes = Router::new()
         .route("/api/auth/me", get(get_current_user))
         .route("/api/auth/logout", post(logout_user))
         .route("/api/user", patch(patch_user))
+        .route("/api/user/lastfm", delete(unlink_lastfm))
+        .route("/api/events", get(sync_events))
+        .route("/api/admin/crypto-status", get(get_crypto_status))
+        .route("/api/admin/reencrypt-tokens", post(reencrypt_tokens))
         .route("/api/oauth/{provider}/authorize", get(oauth_callback))
         .route(
             "/api/oauth/{provider}/disconnect",
             post(remove_oauth_provider),
         )
+        .route("/api/stats/training-load", get(get_training_load_stats))
         .route("/api/strava/activities", get(get_strava_activities))
+        .route(
+            "/api/strava/activities/overlaps",
+            get(get_strava_activity_overlaps),
+        )
+        .route("/api/strava/ratelimit", get(get_strava_rate_limit))
         .route(
             "/api/strava/activities/{id}/streams",
             get(get_strava_activity_streams),
         )
         .route("/api/strava/activities/sync", post(sync_strava_activities))
+        .route(
+            "/api/strava/activities/{id}/streams/stats",
+            get(get_strava_activity_stream_stats),
+        )
+        .route(
+            "/api/strava/activities/{id}/summary",
+            get(get_strava_activity_summary),
+        )
+        .route(
+            "/api/strava/activities/{id}/best-efforts",
+            get(get_strava_activity_best_efforts),
+        )
+        .route(
+            "/api/strava/activities/{id}/streams/channels",
+            get(get_strava_activity_stream_channels),
+        )
+        .route(
+            "/api/strava/activities/{id}/elevation",
+            get(get_strava_activity_elevation),
+        )
+        .route(
+            "/api/strava/activities/{id}/pauses",
+            get(get_strava_activity_pauses),
+        )
+        .route(
+            "/api/strava/activities/{id}/similar",
+            get(get_strava_similar_activities),
+        )
+        .route(
+            "/api/strava/activities/{id}/streams/preview",
+            get(preview_strava_activity_streams),
+        )
         .route(
             "/api/strava/activities/{id}/streams/sync",
             post(sync_strava_activity_streams),
         )
+        .route(
+            "/api/strava/activities/{id}/import",
+            post(import_strava_activity),
+        )
         .route(
             "/api/strava/activities/streams/sync",
             post(sync_all_strava_activity_streams),
         )
+        .route(
+            "/api/strava/activities/streams/sync/range",
+            post(sync_strava_activity_streams_in_range),
+        )
+        .route(
+            "/api/music/import",
+            post(import_lastfm_listens)
+                .layer(DefaultBodyLimit::max(handlers::MAX_LASTFM_EXPORT_BYTES)),
+        )
+        .route("/api/music/range", get(get_listen_range))
+        .route("/api/music/enrich", post(enrich_spotify_tracks))
+        .route("/api/music/tracks/merge", post(merge_duplicate_tracks))
+        .route("/api/music/tracks/{track_id}", get(get_track))
         .route(
             "/api/activities/{activity_id}/music",
             get(get_activity_music),
         )
+        .route(
+            "/api/activities/{activity_id}/music/timeline",
+            get(get_activity_music_timeline),
+        )
+        .route(
+            "/api/activities/{activity_id}/music/density",
+            get(get_activity_listen_density),
+        )
+        .route(
+            "/api/activities/{activity_id}/music/at",
+            get(get_activity_track_at),
+        )
+        .route(
+            "/api/activities/{activity_id}/music/offset",
+            patch(set_activity_time_offset),
+        )
+        .route(
+            "/api/activities/{activity_id}/music.csv",
+            get(export_activity_music_csv),
+        )
+        .merge(debug_routes())
         .route_layer(login_required!(AuthBackend))
         .with_state(state.clone().into());
 
@@ -233,6 +389,7 @@ async fn main() -> anyhow::Result<()> {
                 uri = %request.uri(),
                 version = ?request.version(),
                 user_id,
+                request_id = tracing::field::Empty,
                 status = tracing::field::Empty,
                 latency_ms = tracing::field::Empty,
             )
@@ -244,6 +401,11 @@ async fn main() -> anyhow::Result<()> {
             |response: &Response<axum::body::Body>, latency: Duration, span: &Span| {
                 let status = response.status();
                 let latency_ms = latency.as_millis();
+                let request_id = response
+                    .headers()
+                    .get(&middleware::REQUEST_ID_HEADER)
+                    .and_then(|id| id.to_str().ok())
+                    .unwrap_or("unknown");
 
                 span.record("status", status.as_u16());
                 span.record("latency_ms", latency_ms);
@@ -253,20 +415,41 @@ async fn main() -> anyhow::Result<()> {
                         tracing::info!(
                             status = status.as_u16(),
                             latency_ms,
+                            request_id,
                             "Request completed successfully"
                         );
                     }
                     300..=399 => {
-                        tracing::info!(status = status.as_u16(), latency_ms, "Request redirected");
+                        tracing::info!(
+                            status = status.as_u16(),
+                            latency_ms,
+                            request_id,
+                            "Request redirected"
+                        );
                     }
                     400..=499 => {
-                        tracing::warn!(status = status.as_u16(), latency_ms, "Client error");
+                        tracing::warn!(
+                            status = status.as_u16(),
+                            latency_ms,
+                            request_id,
+                            "Client error"
+                        );
                     }
                     500..=599 => {
-                        tracing::error!(status = status.as_u16(), latency_ms, "Server error");
+                        tracing::error!(
+                            status = status.as_u16(),
+                            latency_ms,
+                            request_id,
+                            "Server error"
+                        );
                     }
                     _ => {
-                        tracing::info!(status = status.as_u16(), latency_ms, "Request completed");
+                        tracing::info!(
+                            status = status.as_u16(),
+                            latency_ms,
+                            request_id,
+                            "Request completed"
+                        );
                     }
                 }
             },
@@ -280,15 +463,25 @@ async fn main() -> anyhow::Result<()> {
             },
         );
 
-    let app = Router::new()
+    let mut app = Router::new()
         .merge(public_routes)
         .merge(protected_routes)
         .with_state(state)
         .layer(from_fn(middleware::handle_errors))
+        // Inside the trace layer so the id lands on its span and in its response log
+        .layer(from_fn(middleware::request_id))
         .layer(trace_layer)
-        .layer(cors)
-        .layer(auth_layer)
-        .fallback(handler_404);
+        .layer(cors);
+
+    // Bearer auth must run inside the auth manager so it can populate the AuthSession
+    if let Some(tokens) = bearer_tokens {
+        app = app.layer(from_fn_with_state(
+            tokens,
+            middleware::bearer_auth::<AuthBackend>,
+        ));
+    }
+
+    let app = app.layer(auth_layer).fallback(handler_404);
 
     let port = std::env::var("PORT").unwrap_or_else(|_| "3000".to_string());
     let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{port}")).await?;
@@ -314,3 +507,39 @@ async fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Routes for investigating sync issues, only mounted when `ENABLE_DEBUG_ENDPOINTS=true`
+fn debug_routes() -> Router<Arc<AppState>> {
+    let enabled = std::env::var("ENABLE_DEBUG_ENDPOINTS")
+        .map(|v| v.to_lowercase() == "true")
+        .unwrap_or(false);
+    if !enabled {
+        return Router::new();
+    }
+
+    info!("Debug endpoints enabled");
+    Router::new().route("/api/music/lastfm/range", get(get_lastfm_range))
+}
+
+/// Builds the integration reachability checker when `HEALTH_CHECK_INTEGRATIONS=true`
+///
+/// Only configured providers are probed: Strava always, Last.fm when its API key is set.
+fn integration_health_checker(
+    strava_base_url: &str,
+    lastfm_enabled: bool,
+) -> Option<Arc<ReachabilityChecker>> {
+    let enabled = std::env::var("HEALTH_CHECK_INTEGRATIONS")
+        .map(|v| v.to_lowercase() == "true")
+        .unwrap_or(false);
+    if !enabled {
+        return None;
+    }
+
+    let mut checker =
+        ReachabilityChecker::new(DEFAULT_PROBE_TIMEOUT).with_provider("strava", strava_base_url);
+    if lastfm_enabled {
+        checker = checker.with_provider("lastfm", last_fm_api_url());
+    }
+    info!("Integration health checks enabled");
+    Some(Arc::new(checker))
+}
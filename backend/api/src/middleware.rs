@@ -1,18 +1,28 @@
+use std::sync::Arc;
+
 use axum::{
     body::Body,
-    http::{Request, StatusCode},
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap, HeaderName, HeaderValue, Request, StatusCode},
     middleware::Next,
-    response::{IntoResponse, Json, Response},
+    response::{IntoResponse, Response},
 };
-use serde_json::json;
-use tracing::{debug, error, warn};
+use axum_login::{AuthSession, AuthUser, AuthnBackend};
+use run_sous_bpm_core::auth::BearerTokenService;
+use sea_orm::prelude::Uuid;
+use tracing::{debug, error, warn, Span};
+
+use crate::responses::{ApiError, ErrorCode, ErrorMessage};
 
 /// Error handling middleware that converts error responses to JSON
 /// and logs them with appropriate severity levels
 ///
 /// This middleware:
 /// - Lets successful/redirect responses pass through unchanged
-/// - Converts 4xx/5xx responses to structured JSON errors
+/// - Lets client errors built from an `ApiError` pass through unchanged
+/// - Keeps the code of server errors built from an `ApiError`, but logs their
+///   message and replaces it with a generic one
+/// - Converts other 4xx/5xx responses, including bare 404s, to structured JSON errors
 /// - Logs errors with context from the tracing span
 #[allow(clippy::too_many_lines)]
 pub async fn handle_errors(req: Request<Body>, next: Next) -> Response {
@@ -28,6 +38,33 @@ pub async fn handle_errors(req: Request<Body>, next: Next) -> Response {
         return response;
     }
 
+    // Handlers already produced a structured error with a specific code
+    if let Some(&code) = response.extensions().get::<ErrorCode>() {
+        if !status.is_server_error() {
+            debug!(method = %method, path = %path, code = %code, "Request rejected");
+            return response;
+        }
+
+        // Server error details stay in the logs: only the code reaches the client
+        let detail = response
+            .extensions()
+            .get::<ErrorMessage>()
+            .map(|message| message.0.clone())
+            .unwrap_or_default();
+        error!(
+            method = %method,
+            path = %path,
+            code = %code,
+            detail = %detail,
+            "Request failed"
+        );
+        let (parts, _) = response.into_parts();
+        let body = ApiError::new(status, code, server_error_message(status))
+            .into_response()
+            .into_body();
+        return Response::from_parts(parts, body);
+    }
+
     // From here, we're dealing with errors (4xx or 5xx)
     // The status code is already logged by TraceLayer, so we just add contextual details
 
@@ -38,16 +75,12 @@ pub async fn handle_errors(req: Request<Body>, next: Next) -> Response {
                 path = %path,
                 "Bad request - invalid input from client"
             );
-            (
-                StatusCode::BAD_REQUEST,
-                Json(
-                    json!({
-                    "error": "Bad Request",
-                    "status": 400,
-                    "message": "The request could not be understood or was missing required parameters"
-                })
-                ),
-            ).into_response()
+            ApiError::new(
+                status,
+                ErrorCode::BadRequest,
+                "The request could not be understood or was missing required parameters",
+            )
+            .into_response()
         }
         StatusCode::UNAUTHORIZED => {
             warn!(
@@ -55,16 +88,12 @@ pub async fn handle_errors(req: Request<Body>, next: Next) -> Response {
                 path = %path,
                 "Unauthorized access attempt"
             );
-            (
-                StatusCode::UNAUTHORIZED,
-                Json(
-                    json!({
-                    "error": "Unauthorized",
-                    "status": 401,
-                    "message": "Authentication is required and has failed or has not yet been provided"
-                })
-                ),
-            ).into_response()
+            ApiError::new(
+                status,
+                ErrorCode::Unauthorized,
+                "Authentication is required and has failed or has not yet been provided",
+            )
+            .into_response()
         }
         StatusCode::FORBIDDEN => {
             warn!(
@@ -72,33 +101,33 @@ pub async fn handle_errors(req: Request<Body>, next: Next) -> Response {
                 path = %path,
                 "Forbidden - insufficient permissions"
             );
-            (
-                StatusCode::FORBIDDEN,
-                Json(json!({
-                    "error": "Forbidden",
-                    "status": 403,
-                    "message": "You don't have permission to access this resource"
-                })),
+            ApiError::new(
+                status,
+                ErrorCode::Forbidden,
+                "You don't have permission to access this resource",
             )
-                .into_response()
+            .into_response()
+        }
+        StatusCode::NOT_FOUND => {
+            debug!(
+                method = %method,
+                path = %path,
+                "Not found - no matching resource"
+            );
+            ApiError::route_not_found().into_response()
         }
-        // NOT_FOUND is handled by the fallback handler with custom hint message
-        StatusCode::NOT_FOUND => response,
         StatusCode::METHOD_NOT_ALLOWED => {
             warn!(
                 method = %method,
                 path = %path,
                 "Method not allowed for this endpoint"
             );
-            (
-                StatusCode::METHOD_NOT_ALLOWED,
-                Json(json!({
-                    "error": "Method Not Allowed",
-                    "status": 405,
-                    "message": "The requested HTTP method is not allowed for this endpoint"
-                })),
+            ApiError::new(
+                status,
+                ErrorCode::MethodNotAllowed,
+                "The requested HTTP method is not allowed for this endpoint",
             )
-                .into_response()
+            .into_response()
         }
         StatusCode::INTERNAL_SERVER_ERROR => {
             error!(
@@ -106,15 +135,12 @@ pub async fn handle_errors(req: Request<Body>, next: Next) -> Response {
                 path = %path,
                 "Internal server error occurred"
             );
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Internal Server Error",
-                    "status": 500,
-                    "message": "An unexpected error occurred. The issue has been logged."
-                })),
+            ApiError::new(
+                status,
+                ErrorCode::InternalError,
+                server_error_message(status),
             )
-                .into_response()
+            .into_response()
         }
         StatusCode::BAD_GATEWAY => {
             error!(
@@ -122,14 +148,7 @@ pub async fn handle_errors(req: Request<Body>, next: Next) -> Response {
                 path = %path,
                 "Bad gateway - external service error"
             );
-            (
-                StatusCode::BAD_GATEWAY,
-                Json(json!({
-                    "error": "Bad Gateway",
-                    "status": 502,
-                    "message": "Error communicating with external service"
-                })),
-            )
+            ApiError::new(status, ErrorCode::BadGateway, server_error_message(status))
                 .into_response()
         }
         StatusCode::SERVICE_UNAVAILABLE => {
@@ -138,15 +157,12 @@ pub async fn handle_errors(req: Request<Body>, next: Next) -> Response {
                 path = %path,
                 "Service temporarily unavailable"
             );
-            (
-                StatusCode::SERVICE_UNAVAILABLE,
-                Json(json!({
-                    "error": "Service Unavailable",
-                    "status": 503,
-                    "message": "The service is temporarily unavailable. Please try again later."
-                })),
+            ApiError::new(
+                status,
+                ErrorCode::ServiceUnavailable,
+                server_error_message(status),
             )
-                .into_response()
+            .into_response()
         }
         // For other errors, return a generic error response
         _ => {
@@ -156,15 +172,463 @@ pub async fn handle_errors(req: Request<Body>, next: Next) -> Response {
                 status = status.as_u16(),
                 "Unhandled error status code"
             );
-            (
+            ApiError::new(
                 status,
-                Json(json!({
-                    "error": status.canonical_reason().unwrap_or("Error"),
-                    "status": status.as_u16(),
-                    "message": status.canonical_reason().unwrap_or("An error occurred")
-                })),
+                ErrorCode::HttpError,
+                status.canonical_reason().unwrap_or("An error occurred"),
             )
-                .into_response()
+            .into_response()
+        }
+    }
+}
+
+/// Client-facing message for a server error status, which never carries internal details
+fn server_error_message(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::INTERNAL_SERVER_ERROR => {
+            "An unexpected error occurred. The issue has been logged."
+        }
+        StatusCode::BAD_GATEWAY => "Error communicating with external service",
+        StatusCode::SERVICE_UNAVAILABLE => {
+            "The service is temporarily unavailable. Please try again later."
         }
+        _ => status.canonical_reason().unwrap_or("An error occurred"),
+    }
+}
+
+/// Header carrying the request correlation id, in both directions
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest client-supplied request id that is trusted as is
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Request id middleware: reads or generates an `X-Request-Id` for every request
+///
+/// A client-supplied id is kept when it is short printable ASCII, otherwise a UUID
+/// is generated. The id is recorded on the current `http_request` span, so every
+/// log line of the request carries it, and echoed in the response header so a
+/// bug report can be matched to the logs.
+///
+/// Must be layered inside the `TraceLayer`.
+pub async fn request_id(mut req: Request<Body>, next: Next) -> Response {
+    let Some(id) = req
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .filter(|value| is_valid_request_id(value))
+        .cloned()
+        .or_else(|| HeaderValue::from_str(&Uuid::new_v4().to_string()).ok())
+    else {
+        return next.run(req).await;
+    };
+
+    if let Ok(id) = id.to_str() {
+        Span::current().record("request_id", id);
+    }
+    req.headers_mut()
+        .insert(REQUEST_ID_HEADER.clone(), id.clone());
+
+    let mut response = next.run(req).await;
+    response.headers_mut().insert(REQUEST_ID_HEADER.clone(), id);
+    response
+}
+
+fn is_valid_request_id(value: &HeaderValue) -> bool {
+    let bytes = value.as_bytes();
+    !bytes.is_empty()
+        && bytes.len() <= MAX_REQUEST_ID_LEN
+        && bytes.iter().all(|b| b.is_ascii_graphic())
+}
+
+/// Bearer token authentication middleware
+///
+/// Requests carrying `Authorization: Bearer <token>` are authenticated from the token
+/// instead of the session cookie: the user is loaded through the auth backend and set
+/// on the request's `AuthSession`, so `login_required!` and handlers work unchanged.
+/// Requests without a bearer token fall through to cookie session auth.
+///
+/// Must be layered inside the `AuthManagerLayer`.
+pub async fn bearer_auth<B>(
+    State(tokens): State<Arc<BearerTokenService>>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Response
+where
+    B: AuthnBackend + 'static,
+    B::User: AuthUser<Id = Uuid>,
+{
+    let Some(token) = bearer_token(req.headers()) else {
+        return next.run(req).await;
+    };
+
+    let claims = match tokens.verify(token) {
+        Ok(claims) => claims,
+        Err(e) => {
+            debug!(error = %e, "Rejected bearer token");
+            return unauthorized_bearer();
+        }
+    };
+
+    let Some(backend) = req
+        .extensions()
+        .get::<AuthSession<B>>()
+        .map(|auth_session| auth_session.backend.clone())
+    else {
+        error!("Bearer auth middleware is not layered inside the auth manager");
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+
+    let user = match backend.get_user(&claims.sub).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            warn!(user_id = %claims.sub, "Bearer token for unknown user");
+            return unauthorized_bearer();
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to load bearer token user");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    if let Some(auth_session) = req.extensions_mut().get_mut::<AuthSession<B>>() {
+        auth_session.user = Some(user);
+    }
+
+    next.run(req).await
+}
+
+/// Extracts the token from an `Authorization: Bearer <token>` header
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+fn unauthorized_bearer() -> Response {
+    ApiError::new(
+        StatusCode::UNAUTHORIZED,
+        ErrorCode::InvalidBearerToken,
+        "Invalid or expired bearer token",
+    )
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::header::RETRY_AFTER, middleware::from_fn_with_state, routing::get, Router};
+    use axum_login::{login_required, AuthManagerLayerBuilder};
+    use chrono::Duration;
+    use tower::ServiceExt;
+    use tower_sessions::{MemoryStore, SessionManagerLayer};
+
+    const SECRET: &[u8] = b"test-secret-that-is-at-least-32-bytes-long";
+
+    #[derive(Debug, Clone)]
+    struct TestUser {
+        id: Uuid,
+    }
+
+    impl AuthUser for TestUser {
+        type Id = Uuid;
+
+        fn id(&self) -> Self::Id {
+            self.id
+        }
+
+        fn session_auth_hash(&self) -> &[u8] {
+            &[]
+        }
+    }
+
+    /// In-memory stand-in for `AuthBackend` that knows a single user
+    #[derive(Debug, Clone)]
+    struct TestBackend {
+        known_user: Uuid,
+    }
+
+    impl AuthnBackend for TestBackend {
+        type User = TestUser;
+        type Credentials = ();
+        type Error = std::convert::Infallible;
+
+        async fn authenticate(&self, (): ()) -> Result<Option<TestUser>, Self::Error> {
+            Ok(None)
+        }
+
+        async fn get_user(&self, user_id: &Uuid) -> Result<Option<TestUser>, Self::Error> {
+            Ok((*user_id == self.known_user).then_some(TestUser { id: *user_id }))
+        }
+    }
+
+    fn app(known_user: Uuid, tokens: BearerTokenService) -> Router {
+        let session_layer = SessionManagerLayer::new(MemoryStore::default());
+        let auth_layer =
+            AuthManagerLayerBuilder::new(TestBackend { known_user }, session_layer).build();
+
+        Router::new()
+            .route(
+                "/protected",
+                get(|auth_session: AuthSession<TestBackend>| async move {
+                    auth_session
+                        .user
+                        .map(|u| u.id.to_string())
+                        .unwrap_or_default()
+                }),
+            )
+            .route_layer(login_required!(TestBackend))
+            .layer(from_fn_with_state(
+                Arc::new(tokens),
+                bearer_auth::<TestBackend>,
+            ))
+            .layer(auth_layer)
+    }
+
+    fn request(authorization: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder().uri("/protected");
+        if let Some(value) = authorization {
+            builder = builder.header(AUTHORIZATION, value);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    fn tokens() -> BearerTokenService {
+        BearerTokenService::new(SECRET, Duration::hours(1))
+    }
+
+    #[tokio::test]
+    async fn test_valid_bearer_token_authorizes_protected_route() {
+        let user_id = Uuid::new_v4();
+        let token = tokens().issue(user_id);
+
+        let response = app(user_id, tokens())
+            .oneshot(request(Some(&format!("Bearer {token}"))))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, user_id.to_string().as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_expired_bearer_token_rejected() {
+        let user_id = Uuid::new_v4();
+        let expired = BearerTokenService::new(SECRET, Duration::seconds(-1)).issue(user_id);
+
+        let response = app(user_id, tokens())
+            .oneshot(request(Some(&format!("Bearer {expired}"))))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_bearer_token_rejected() {
+        let user_id = Uuid::new_v4();
+
+        let response = app(user_id, tokens())
+            .oneshot(request(Some("Bearer not.a.token")))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body = body_json(response).await;
+        assert_eq!(body["code"], "invalid_bearer_token");
+    }
+
+    #[tokio::test]
+    async fn test_bearer_token_for_unknown_user_rejected() {
+        let token = tokens().issue(Uuid::new_v4());
+
+        let response = app(Uuid::new_v4(), tokens())
+            .oneshot(request(Some(&format!("Bearer {token}"))))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_missing_credentials_rejected_by_login_required() {
+        let response = app(Uuid::new_v4(), tokens())
+            .oneshot(request(None))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    async fn body_json(response: Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn error_app() -> Router {
+        Router::new()
+            .route(
+                "/structured",
+                get(|| async { ApiError::activity_not_found() }),
+            )
+            .route(
+                "/bare",
+                get(|| async { (StatusCode::BAD_GATEWAY, "upstream exploded") }),
+            )
+            .route("/gone", get(|| async { StatusCode::NOT_FOUND }))
+            .route(
+                "/failing",
+                get(|| async {
+                    ApiError::database("relation \"oauth_token\" does not exist")
+                        .with_retry_after(5)
+                }),
+            )
+            .layer(axum::middleware::from_fn(handle_errors))
+            .fallback(crate::handlers::handler_404)
+    }
+
+    #[tokio::test]
+    async fn test_unknown_route_returns_structured_error() {
+        let response = error_app()
+            .oneshot(Request::get("/no/such/route").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = body_json(response).await;
+        assert_eq!(body["error"], "Not Found");
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["status"], 404);
+        assert_eq!(
+            body["message"],
+            "The requested resource was not found. Visit GET / for available endpoints"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_errors_structures_bare_not_found() {
+        let response = error_app()
+            .oneshot(Request::get("/gone").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = body_json(response).await;
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["status"], 404);
+    }
+
+    #[tokio::test]
+    async fn test_handle_errors_keeps_structured_errors() {
+        let response = error_app()
+            .oneshot(Request::get("/structured").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = body_json(response).await;
+        assert_eq!(body["code"], "activity_not_found");
+        assert_eq!(body["message"], "Activity not found");
+    }
+
+    #[tokio::test]
+    async fn test_handle_errors_hides_server_error_details() {
+        let response = error_app()
+            .oneshot(Request::get("/failing").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.headers()[RETRY_AFTER], "5");
+        let body = body_json(response).await;
+        assert_eq!(body["code"], "database_error");
+        assert_eq!(body["status"], 500);
+        assert_eq!(
+            body["message"],
+            "An unexpected error occurred. The issue has been logged."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_errors_structures_bare_errors() {
+        let response = error_app()
+            .oneshot(Request::get("/bare").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        let body = body_json(response).await;
+        assert_eq!(body["code"], "bad_gateway");
+        assert_eq!(body["status"], 502);
+    }
+
+    fn request_id_app() -> Router {
+        Router::new()
+            .route(
+                "/echo",
+                get(|headers: HeaderMap| async move {
+                    headers
+                        .get(&REQUEST_ID_HEADER)
+                        .and_then(|v| v.to_str().ok())
+                        .unwrap_or_default()
+                        .to_string()
+                }),
+            )
+            .layer(axum::middleware::from_fn(request_id))
+    }
+
+    #[tokio::test]
+    async fn test_response_echoes_client_request_id() {
+        let response = request_id_app()
+            .oneshot(
+                Request::get("/echo")
+                    .header(&REQUEST_ID_HEADER, "bug-report-42")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.headers()[&REQUEST_ID_HEADER], "bug-report-42");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "bug-report-42".as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_missing_request_id_is_generated() {
+        let response = request_id_app()
+            .oneshot(Request::get("/echo").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        let id = response.headers()[&REQUEST_ID_HEADER].to_str().unwrap();
+        assert!(Uuid::parse_str(id).is_ok(), "{id}");
+    }
+
+    #[tokio::test]
+    async fn test_oversized_request_id_is_replaced() {
+        let oversized = "x".repeat(MAX_REQUEST_ID_LEN + 1);
+
+        let response = request_id_app()
+            .oneshot(
+                Request::get("/echo")
+                    .header(&REQUEST_ID_HEADER, oversized.as_str())
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let id = response.headers()[&REQUEST_ID_HEADER].to_str().unwrap();
+        assert!(Uuid::parse_str(id).is_ok(), "{id}");
     }
 }
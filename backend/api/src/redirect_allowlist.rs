@@ -0,0 +1,173 @@
+use tracing::warn;
+
+/// Frontend origins the OAuth callback may redirect to
+///
+/// Read from `FRONTEND_URLS` (comma-separated), falling back to `FRONTEND_URL`.
+/// The first origin is the safe default for rejected targets and for flows
+/// started without a recognized origin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectAllowlist {
+    origins: Vec<String>,
+}
+
+impl RedirectAllowlist {
+    /// Builds the allowlist from a list of frontend URLs, ignoring blank entries
+    ///
+    /// # Panics
+    ///
+    /// Panics if no URL is given
+    #[must_use]
+    pub fn new<'a>(urls: impl IntoIterator<Item = &'a str>) -> Self {
+        let origins: Vec<String> = urls
+            .into_iter()
+            .filter_map(origin)
+            .map(str::to_ascii_lowercase)
+            .collect();
+        assert!(
+            !origins.is_empty(),
+            "At least one frontend URL must be allowed"
+        );
+        Self { origins }
+    }
+
+    /// Reads the allowlist from `FRONTEND_URLS`, or `FRONTEND_URL` if unset
+    ///
+    /// # Panics
+    ///
+    /// Panics if neither variable holds a URL
+    #[must_use]
+    pub fn from_env() -> Self {
+        let urls = std::env::var("FRONTEND_URLS")
+            .ok()
+            .filter(|urls| !urls.trim().is_empty())
+            .or_else(|| std::env::var("FRONTEND_URL").ok())
+            .expect("FRONTEND_URLS or FRONTEND_URL must be set");
+        Self::new(urls.split(','))
+    }
+
+    /// The origin redirects fall back to
+    #[must_use]
+    pub fn default_origin(&self) -> &str {
+        &self.origins[0]
+    }
+
+    /// Whether `target` is an absolute URL on an allowed origin
+    #[must_use]
+    pub fn allows(&self, target: &str) -> bool {
+        origin(target).is_some_and(|origin| {
+            self.origins
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(origin))
+        })
+    }
+
+    /// The allowed origin `url` belongs to, normalized to lowercase
+    ///
+    /// Used to remember which frontend started an OAuth flow, from its `Origin`
+    /// or `Referer` header.
+    #[must_use]
+    pub fn allowed_origin(&self, url: &str) -> Option<String> {
+        self.allows(url)
+            .then(|| origin(url).map(str::to_ascii_lowercase))
+            .flatten()
+    }
+
+    /// Returns `target` if allowed, otherwise the default origin's root
+    #[must_use]
+    pub fn sanitize(&self, target: &str) -> String {
+        if self.allows(target) {
+            target.to_string()
+        } else {
+            warn!(target_url = %target, "Redirect target not in allowlist, using default origin");
+            format!("{}/", self.default_origin())
+        }
+    }
+}
+
+/// Extracts `scheme://authority` from an absolute http(s) URL
+///
+/// The authority runs up to the first `/`, `?`, `#` or `\`, so anything that
+/// smuggles another host (userinfo, backslashes) won't match an allowed origin.
+fn origin(url: &str) -> Option<&str> {
+    let url = url.trim();
+    let scheme_end = url.find("://")?;
+    let scheme = &url[..scheme_end];
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        return None;
+    }
+
+    let authority_start = scheme_end + 3;
+    let authority_end = url[authority_start..]
+        .find(['/', '?', '#', '\\'])
+        .map_or(url.len(), |i| authority_start + i);
+    (authority_end > authority_start).then(|| &url[..authority_end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowlist() -> RedirectAllowlist {
+        RedirectAllowlist::new([" https://app.example.com/ ", "http://127.0.0.1:5173"])
+    }
+
+    #[test]
+    fn test_allowed_targets_are_kept() {
+        let allowlist = allowlist();
+
+        for target in [
+            "https://app.example.com/oauth/callback?status=success&provider=strava",
+            "HTTPS://App.Example.com/oauth/callback",
+            "http://127.0.0.1:5173/oauth/callback?status=error&error=denied",
+        ] {
+            assert!(allowlist.allows(target), "{target} should be allowed");
+            assert_eq!(allowlist.sanitize(target), target);
+        }
+    }
+
+    #[test]
+    fn test_off_allowlist_targets_are_rejected() {
+        let allowlist = allowlist();
+
+        for target in [
+            "https://evil.example.com/oauth/callback",
+            "https://app.example.com.evil.com/oauth/callback",
+            "https://app.example.com@evil.com/oauth/callback",
+            "https://app.example.com\\@evil.com/",
+            "http://app.example.com/oauth/callback",
+            "http://127.0.0.1:8080/oauth/callback",
+            "javascript://app.example.com/%0aalert(1)",
+            "//evil.com/oauth/callback",
+            "/oauth/callback",
+        ] {
+            assert!(!allowlist.allows(target), "{target} should be rejected");
+            assert_eq!(allowlist.sanitize(target), "https://app.example.com/");
+        }
+    }
+
+    #[test]
+    fn test_allowed_origin_of_a_caller() {
+        let allowlist = allowlist();
+
+        assert_eq!(
+            allowlist
+                .allowed_origin("http://127.0.0.1:5173/settings/integrations")
+                .as_deref(),
+            Some("http://127.0.0.1:5173")
+        );
+        assert_eq!(
+            allowlist
+                .allowed_origin("HTTPS://App.Example.com")
+                .as_deref(),
+            Some("https://app.example.com")
+        );
+        assert_eq!(allowlist.allowed_origin("https://evil.example.com/"), None);
+    }
+
+    #[test]
+    fn test_blank_entries_are_ignored() {
+        let allowlist = RedirectAllowlist::new(["", " ", "https://app.example.com"]);
+
+        assert_eq!(allowlist.default_origin(), "https://app.example.com");
+    }
+}
@@ -1,12 +1,15 @@
 use chrono::{DateTime, Utc};
+use run_sous_bpm_core::{services::SegmentationMode, units::UnitSystem};
 use sea_orm::prelude::Uuid;
-use serde::{Deserialize, Serialize};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
 
 /// Response for GET /api/activities/{id}/music with GPS segments
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ActivityMusicResponse {
     pub activity_id: Uuid,
     pub has_gps: bool,
+    /// Unit system used for altitude (m/ft) and velocity (m/s or mph)
+    pub units: UnitSystem,
     pub segments: Vec<SegmentResponse>,
     pub stats: SimplificationStats,
 }
@@ -19,7 +22,89 @@ pub struct SegmentResponse {
     pub track: Option<TrackInfo>,
     pub start_time: DateTime<Utc>,
     pub end_time: DateTime<Utc>,
+    /// Whole seconds from `start_time` to `end_time`, never negative
+    pub duration_seconds: i64,
     pub points: Vec<GpsPointResponse>,
+    /// Mean temperature (°C) over the segment, `null` if no point has a reading
+    pub avg_temperature: Option<f64>,
+    /// Whether the segment's track also plays in another segment of the activity
+    pub repeated: bool,
+    /// Whether the segment continues the previous segment's play after a GPS gap
+    pub after_gap: bool,
+}
+
+/// Response for GET /api/activities/{id}/music?mode=distance with per-bucket dominant tracks
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActivityMusicDistanceResponse {
+    pub activity_id: Uuid,
+    pub mode: SegmentationMode,
+    /// Requested bucket size in meters
+    pub bucket_meters: f64,
+    /// Unit system used for bucket distances (m or mi)
+    pub units: UnitSystem,
+    pub buckets: Vec<DistanceBucketResponse>,
+}
+
+/// A distance bucket with the track that played longest within it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DistanceBucketResponse {
+    pub index: usize,
+    pub start_distance: f64,
+    pub end_distance: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track: Option<TrackInfo>,
+    pub dominant_seconds: f64,
+    pub total_seconds: f64,
+}
+
+/// Response for GET /api/activities/{id}/music/density: distinct tracks per time bucket
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActivityListenDensityResponse {
+    pub activity_id: Uuid,
+    /// Requested bucket size in seconds
+    pub bucket_seconds: u32,
+    pub buckets: Vec<DensityBucketResponse>,
+}
+
+/// A time bucket with how many distinct tracks played within it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DensityBucketResponse {
+    pub index: usize,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub distinct_tracks: usize,
+}
+
+/// Response for GET /api/activities/{id}/music/timeline: the track timeline without GPS points
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActivityMusicTimelineResponse {
+    pub activity_id: Uuid,
+    /// Fraction of the activity's elapsed time with a known track, in [0, 1]
+    pub music_coverage_ratio: f64,
+    pub segments: Vec<TimelineSegmentResponse>,
+}
+
+/// Response for GET /api/activities/{id}/music/at: the track playing and distance covered at a given moment
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActivityTrackAtResponse {
+    pub activity_id: Uuid,
+    /// The requested timestamp
+    pub at: DateTime<Utc>,
+    /// Track whose segment contains `at`, `null` if no music was playing
+    pub track: Option<TrackInfo>,
+    /// Meters covered at `at`, interpolated from the distance stream, `null` without one
+    pub distance: Option<f64>,
+}
+
+/// A segment of the track timeline, without its GPS points
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimelineSegmentResponse {
+    pub index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track: Option<TrackInfo>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub duration_seconds: i64,
 }
 
 /// Track information within a segment
@@ -30,24 +115,94 @@ pub struct TrackInfo {
     pub artist_name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub album_name: Option<String>,
+    /// Track tempo in beats per minute, when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bpm: Option<f32>,
 }
 
-/// GPS point with sensor data
+/// Full metadata of a track, with the IDs needed to link to `MusicBrainz` and Last.fm
 #[derive(Debug, Serialize, Deserialize)]
+pub struct TrackDetailsResponse {
+    pub id: Uuid,
+    pub track_name: String,
+    pub artist_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist_mbid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track_mbid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album_mbid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lastfm_url: Option<String>,
+    /// Track tempo in beats per minute, when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bpm: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spotify_id: Option<String>,
+}
+
+/// Which sensor fields of a GPS point are serialized
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PointFields {
+    /// Sensor values without a reading are omitted, keeping long activities small
+    #[default]
+    Compact,
+    /// Every sensor field is present, `null` without a reading, for fixed-schema clients
+    Full,
+}
+
+/// GPS point with sensor data
+///
+/// Coordinates are `null` for points recorded without GPS (treadmill, indoor).
+/// Sensor values without a reading are omitted unless `fields` is `Full`.
+#[derive(Debug, Deserialize)]
 pub struct GpsPointResponse {
     pub time: DateTime<Utc>,
-    pub latitude: f64,
-    pub longitude: f64,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
     pub altitude: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub heart_rate: Option<i32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub cadence: Option<i32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub watts: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub velocity: Option<f32>,
+    #[serde(skip)]
+    pub fields: PointFields,
+}
+
+impl Serialize for GpsPointResponse {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut point = serializer.serialize_struct("GpsPointResponse", 8)?;
+        point.serialize_field("time", &self.time)?;
+        point.serialize_field("latitude", &self.latitude)?;
+        point.serialize_field("longitude", &self.longitude)?;
+        serialize_sensor_field(&mut point, "altitude", self.altitude.as_ref(), self.fields)?;
+        serialize_sensor_field(
+            &mut point,
+            "heart_rate",
+            self.heart_rate.as_ref(),
+            self.fields,
+        )?;
+        serialize_sensor_field(&mut point, "cadence", self.cadence.as_ref(), self.fields)?;
+        serialize_sensor_field(&mut point, "watts", self.watts.as_ref(), self.fields)?;
+        serialize_sensor_field(&mut point, "velocity", self.velocity.as_ref(), self.fields)?;
+        point.end()
+    }
+}
+
+fn serialize_sensor_field<S: SerializeStruct, T: Serialize>(
+    point: &mut S,
+    key: &'static str,
+    value: Option<&T>,
+    fields: PointFields,
+) -> Result<(), S::Error> {
+    if value.is_none() && fields == PointFields::Compact {
+        point.skip_field(key)
+    } else {
+        point.serialize_field(key, &value)
+    }
 }
 
 /// Statistics about GPS simplification
@@ -59,4 +214,89 @@ pub struct SimplificationStats {
     pub original_points: usize,
     pub simplified_points: usize,
     pub reduction_ratio: f32,
+    /// Fraction of the activity's elapsed time with a known track, in [0, 1]
+    pub music_coverage_ratio: f64,
+    /// Duration-weighted average tempo of the music, `null` if no track tempo is known
+    pub avg_bpm: Option<f64>,
+    /// Mean temperature (°C) over the activity, `null` if no point has a reading
+    pub avg_temperature: Option<f64>,
+    /// Plays per distinct track, in order of first play
+    pub play_counts: Vec<TrackPlayCountResponse>,
+}
+
+/// How many times a track plays during an activity
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrackPlayCountResponse {
+    pub track: TrackInfo,
+    pub play_count: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gps_only_points(count: u32, fields: PointFields) -> Vec<GpsPointResponse> {
+        (0..count)
+            .map(|i| GpsPointResponse {
+                time: DateTime::from_timestamp(1_700_000_000 + i64::from(i), 0).unwrap(),
+                latitude: Some(48.0 + f64::from(i) * 0.0001),
+                longitude: Some(2.0),
+                altitude: None,
+                heart_rate: None,
+                cadence: None,
+                watts: None,
+                velocity: None,
+                fields,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_compact_points_omit_missing_sensor_values() {
+        let json = serde_json::to_value(&gps_only_points(1, PointFields::Compact)[0]).unwrap();
+
+        let keys: Vec<&str> = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(keys.len(), 3, "{json}");
+        assert!(json.get("heart_rate").is_none());
+    }
+
+    #[test]
+    fn test_full_points_keep_every_field() {
+        let json = serde_json::to_value(&gps_only_points(1, PointFields::Full)[0]).unwrap();
+
+        assert_eq!(json.as_object().unwrap().len(), 8, "{json}");
+        assert!(json["heart_rate"].is_null());
+        assert!(json["velocity"].is_null());
+    }
+
+    #[test]
+    fn test_compact_points_shrink_the_payload() {
+        let compact = serde_json::to_string(&gps_only_points(1000, PointFields::Compact)).unwrap();
+        let full = serde_json::to_string(&gps_only_points(1000, PointFields::Full)).unwrap();
+
+        // Five `"key":null,` pairs dropped per point
+        assert!(
+            compact.len() * 3 < full.len() * 2,
+            "{} vs {}",
+            compact.len(),
+            full.len()
+        );
+    }
+
+    #[test]
+    fn test_readings_are_serialized_in_both_modes() {
+        for fields in [PointFields::Compact, PointFields::Full] {
+            let point = GpsPointResponse {
+                heart_rate: Some(150),
+                ..gps_only_points(1, fields).remove(0)
+            };
+
+            assert_eq!(serde_json::to_value(&point).unwrap()["heart_rate"], 150);
+        }
+    }
 }
@@ -0,0 +1,391 @@
+use axum::{
+    http::{header::RETRY_AFTER, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use run_sous_bpm_core::services::{LastfmNotConfiguredError, ReconnectRequiredError};
+use run_sous_bpm_integrations::common::IntegrationError;
+use serde::Serialize;
+use serde_json::json;
+use strum::Display;
+
+/// Stable, machine-readable error codes returned in the `code` field of error responses
+///
+/// Clients should switch on these instead of the human-readable message,
+/// which may change wording at any time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ErrorCode {
+    // Generic HTTP failures, used by the error middleware
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    MethodNotAllowed,
+    InternalError,
+    BadGateway,
+    ServiceUnavailable,
+    HttpError,
+
+    // Authentication
+    InvalidCredentials,
+    InvalidBearerToken,
+    InvalidRefreshToken,
+    SessionError,
+
+    // Request validation
+    InvalidInput,
+    InvalidActivityId,
+    InvalidProvider,
+
+    // Resources
+    ActivityNotFound,
+    TrackNotFound,
+    UserNotFound,
+
+    // Integrations
+    MusicIntegrationNotConfigured,
+    LastfmNotConfigured,
+    InvalidLastfmUsername,
+    LastfmError,
+    LastfmRateLimited,
+    StravaError,
+    StravaRateLimited,
+    SpotifyNotConnected,
+    SpotifyError,
+    ReconnectRequired,
+    SyncCooldown,
+
+    // Domain failures
+    ActivityMusicFailed,
+    InvalidExport,
+    DatabaseError,
+}
+
+/// Structured error response: HTTP status, stable code and human-readable message
+///
+/// Serialized as `{"error": <reason>, "code": <code>, "status": <status>, "message": <message>}`.
+/// `handle_errors` passes client errors through unchanged and replaces the message of
+/// server errors with a generic one.
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: ErrorCode,
+    pub message: String,
+    /// Seconds sent in a `Retry-After` header, for errors the client can retry later
+    pub retry_after: Option<u64>,
+}
+
+impl ApiError {
+    #[must_use]
+    pub fn new(status: StatusCode, code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            retry_after: None,
+        }
+    }
+
+    /// Tells the client to retry after `seconds` with a `Retry-After` header
+    #[must_use]
+    pub fn with_retry_after(mut self, seconds: u64) -> Self {
+        self.retry_after = Some(seconds);
+        self
+    }
+
+    /// 401 for handlers reached without an authenticated user
+    #[must_use]
+    pub fn unauthorized() -> Self {
+        Self::new(
+            StatusCode::UNAUTHORIZED,
+            ErrorCode::Unauthorized,
+            "You must be logged in to access this resource",
+        )
+    }
+
+    #[must_use]
+    pub fn bad_request(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, code, message)
+    }
+
+    /// 403 for authenticated users without the admin flag
+    #[must_use]
+    pub fn admin_required() -> Self {
+        Self::new(
+            StatusCode::FORBIDDEN,
+            ErrorCode::Forbidden,
+            "Admin access is required for this resource",
+        )
+    }
+
+    #[must_use]
+    pub fn not_found(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, code, message)
+    }
+
+    #[must_use]
+    pub fn bad_gateway(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_GATEWAY, code, message)
+    }
+
+    #[must_use]
+    pub fn service_unavailable(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::SERVICE_UNAVAILABLE, code, message)
+    }
+
+    #[must_use]
+    pub fn internal(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, code, message)
+    }
+
+    /// 500 for a failed database operation
+    #[must_use]
+    pub fn database(err: impl std::fmt::Display) -> Self {
+        Self::internal(ErrorCode::DatabaseError, format!("Database error: {err}"))
+    }
+
+    /// 404 for requests matching no route, pointing clients at the endpoint list
+    #[must_use]
+    pub fn route_not_found() -> Self {
+        Self::not_found(
+            ErrorCode::NotFound,
+            "The requested resource was not found. Visit GET / for available endpoints",
+        )
+    }
+
+    #[must_use]
+    pub fn invalid_activity_id() -> Self {
+        Self::bad_request(ErrorCode::InvalidActivityId, "Invalid activity ID format")
+    }
+
+    #[must_use]
+    pub fn activity_not_found() -> Self {
+        Self::not_found(ErrorCode::ActivityNotFound, "Activity not found")
+    }
+
+    #[must_use]
+    pub fn track_not_found() -> Self {
+        Self::not_found(ErrorCode::TrackNotFound, "Track not found")
+    }
+
+    /// 503 when a service error comes from the unconfigured Last.fm integration
+    ///
+    /// Returns `None` for any other error so callers can fall back to their own mapping.
+    #[must_use]
+    pub fn music_not_configured(error: &(dyn std::error::Error + 'static)) -> Option<Self> {
+        match error.downcast_ref::<IntegrationError>() {
+            Some(IntegrationError::NotConfigured(_)) => Some(Self::service_unavailable(
+                ErrorCode::MusicIntegrationNotConfigured,
+                "Music integration not configured",
+            )),
+            _ => None,
+        }
+    }
+
+    /// Maps a typed Last.fm failure to its status
+    ///
+    /// An unknown username is a 400, a hit rate limit a 429 and an outage a 502.
+    /// Returns `None` for any other error so callers can fall back to their own mapping.
+    #[must_use]
+    pub fn lastfm_failure(error: &(dyn std::error::Error + 'static)) -> Option<Self> {
+        match error.downcast_ref::<IntegrationError>() {
+            Some(IntegrationError::NotFound(message)) => Some(Self::bad_request(
+                ErrorCode::InvalidLastfmUsername,
+                format!("Invalid Last.fm username: {message}"),
+            )),
+            Some(IntegrationError::RateLimited(resume_at)) => {
+                let seconds = (*resume_at - chrono::Utc::now()).num_seconds().max(1);
+                Some(
+                    Self::new(
+                        StatusCode::TOO_MANY_REQUESTS,
+                        ErrorCode::LastfmRateLimited,
+                        format!("Last.fm rate limit exceeded, retry after {resume_at}"),
+                    )
+                    .with_retry_after(seconds.unsigned_abs()),
+                )
+            }
+            Some(IntegrationError::Unavailable(message)) => Some(Self::bad_gateway(
+                ErrorCode::LastfmError,
+                format!("Last.fm is unavailable: {message}"),
+            )),
+            _ => None,
+        }
+    }
+
+    /// 409 when the user has no Last.fm account linked yet
+    ///
+    /// Clients switch on `lastfm_not_configured` to prompt for the account setup.
+    #[must_use]
+    pub fn lastfm_not_configured() -> Self {
+        Self::new(
+            StatusCode::CONFLICT,
+            ErrorCode::LastfmNotConfigured,
+            LastfmNotConfiguredError.to_string(),
+        )
+    }
+
+    /// 409 when the user has not connected Spotify yet
+    #[must_use]
+    pub fn spotify_not_connected() -> Self {
+        Self::new(
+            StatusCode::CONFLICT,
+            ErrorCode::SpotifyNotConnected,
+            "Spotify is not connected",
+        )
+    }
+
+    /// 429 when Strava requests are paused to stay under its rate limit
+    ///
+    /// Returns `None` for any other error so callers can fall back to their own mapping.
+    #[must_use]
+    pub fn strava_rate_limited(error: &(dyn std::error::Error + 'static)) -> Option<Self> {
+        match error.downcast_ref::<IntegrationError>() {
+            Some(IntegrationError::RateLimited(resume_at)) => Some(Self::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                ErrorCode::StravaRateLimited,
+                format!("Strava rate limit nearly reached, retry after {resume_at}"),
+            )),
+            _ => None,
+        }
+    }
+
+    /// 429 when the user ran a bulk sync too recently, retry after `wait`
+    #[must_use]
+    pub fn sync_cooldown(wait: chrono::Duration) -> Self {
+        // Round up so a client retrying on time is never rejected again
+        let seconds = (wait + chrono::Duration::milliseconds(999))
+            .num_seconds()
+            .max(1);
+        Self::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::SyncCooldown,
+            format!("A bulk sync ran recently, retry in {seconds} seconds"),
+        )
+        .with_retry_after(seconds.unsigned_abs())
+    }
+
+    /// 403 when a provider token lacks a required scope and the user must reconnect
+    ///
+    /// Returns `None` for any other error so callers can fall back to their own mapping.
+    #[must_use]
+    pub fn reconnect_required(error: &(dyn std::error::Error + 'static)) -> Option<Self> {
+        error.downcast_ref::<ReconnectRequiredError>().map(|e| {
+            Self::new(
+                StatusCode::FORBIDDEN,
+                ErrorCode::ReconnectRequired,
+                e.to_string(),
+            )
+        })
+    }
+}
+
+/// Message of an `ApiError` response, kept for the error middleware to log
+///
+/// Server error messages may carry internal details (SQL, connection errors), so
+/// `handle_errors` logs them from here and sends the client a generic message.
+#[derive(Debug, Clone)]
+pub struct ErrorMessage(pub String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(json!({
+            "error": self.status.canonical_reason().unwrap_or("Error"),
+            "code": self.code,
+            "status": self.status.as_u16(),
+            "message": self.message,
+        }));
+
+        let mut response = (self.status, body).into_response();
+        if let Some(seconds) = self.retry_after {
+            response.headers_mut().insert(RETRY_AFTER, seconds.into());
+        }
+        // Marks the body as already structured for the error middleware
+        response.extensions_mut().insert(self.code);
+        response.extensions_mut().insert(ErrorMessage(self.message));
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    async fn body_json(response: Response) -> Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_error_response_schema() {
+        let response = ApiError::activity_not_found().into_response();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.extensions().get::<ErrorCode>(),
+            Some(&ErrorCode::ActivityNotFound)
+        );
+
+        let body = body_json(response).await;
+        assert_eq!(body["error"], "Not Found");
+        assert_eq!(body["code"], "activity_not_found");
+        assert_eq!(body["status"], 404);
+        assert_eq!(body["message"], "Activity not found");
+    }
+
+    #[tokio::test]
+    async fn test_sync_cooldown_sets_retry_after() {
+        let response =
+            ApiError::sync_cooldown(chrono::Duration::milliseconds(90_500)).into_response();
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[RETRY_AFTER], "91");
+
+        let body = body_json(response).await;
+        assert_eq!(body["code"], "sync_cooldown");
+    }
+
+    #[test]
+    fn test_lastfm_failures_are_told_apart() {
+        let unknown_user = IntegrationError::NotFound("User not found".to_string());
+        let outage = IntegrationError::Unavailable("Service Offline".to_string());
+        let other = IntegrationError::Other("Invalid method signature".to_string());
+
+        let unknown_user = ApiError::lastfm_failure(&unknown_user).unwrap();
+        let outage = ApiError::lastfm_failure(&outage).unwrap();
+
+        assert_eq!(unknown_user.status, StatusCode::BAD_REQUEST);
+        assert_eq!(unknown_user.code, ErrorCode::InvalidLastfmUsername);
+        assert_eq!(outage.status, StatusCode::BAD_GATEWAY);
+        assert_eq!(outage.code, ErrorCode::LastfmError);
+        assert!(ApiError::lastfm_failure(&other).is_none());
+    }
+
+    #[test]
+    fn test_lastfm_rate_limit_sets_retry_after() {
+        let resume_at = chrono::Utc::now() + chrono::Duration::seconds(60);
+
+        let error = ApiError::lastfm_failure(&IntegrationError::RateLimited(resume_at)).unwrap();
+
+        assert_eq!(error.status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(error.code, ErrorCode::LastfmRateLimited);
+        assert!(error
+            .retry_after
+            .is_some_and(|seconds| (1..=60).contains(&seconds)));
+    }
+
+    #[test]
+    fn test_error_codes_are_snake_case() {
+        assert_eq!(
+            ErrorCode::LastfmNotConfigured.to_string(),
+            "lastfm_not_configured"
+        );
+        assert_eq!(
+            serde_json::to_value(ErrorCode::InvalidActivityId).unwrap(),
+            "invalid_activity_id"
+        );
+    }
+}
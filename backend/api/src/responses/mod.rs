@@ -1,5 +1,11 @@
 pub mod activity_music;
+pub mod error;
 pub mod lastfm_range;
+pub mod music_csv;
+pub mod pagination;
 
 pub use activity_music::*;
+pub use error::*;
 pub use lastfm_range::*;
+pub use music_csv::*;
+pub use pagination::*;
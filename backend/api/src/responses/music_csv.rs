@@ -0,0 +1,176 @@
+use run_sous_bpm_core::services::analytics_service::Segment;
+
+/// Header row of the activity music CSV export
+const CSV_HEADER: &str = "index,track,artist,start_time,end_time,duration_seconds,distance_meters,avg_pace_seconds_per_km,avg_heart_rate";
+
+/// Renders activity music segments as CSV, one row per segment
+///
+/// Distance, pace and heart rate are derived from the segment's stream points;
+/// cells are left empty when the underlying stream is missing.
+#[must_use]
+pub fn activity_music_csv(segments: &[Segment]) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    csv.push_str("\r\n");
+
+    for segment in segments {
+        let (track, artist) = segment.track.as_ref().map_or(("", ""), |t| {
+            (t.track_name.as_str(), t.artist_name.as_str())
+        });
+        let duration_seconds = segment.duration_seconds();
+        let distance = segment_distance(segment);
+        let pace = distance.and_then(|d| pace_seconds_per_km(duration_seconds, d));
+
+        let row = [
+            segment.index.to_string(),
+            csv_field(track),
+            csv_field(artist),
+            segment.start_time.to_rfc3339(),
+            segment.end_time.to_rfc3339(),
+            duration_seconds.to_string(),
+            optional_cell(distance),
+            optional_cell(pace),
+            optional_cell(average_heart_rate(segment)),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    }
+
+    csv
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn optional_cell(value: Option<f64>) -> String {
+    value.map(|v| format!("{v:.1}")).unwrap_or_default()
+}
+
+/// Meters covered within the segment, from the cumulative distance stream
+fn segment_distance(segment: &Segment) -> Option<f64> {
+    let mut distances = segment.points.iter().filter_map(|p| p.distance);
+    let first = distances.next()?;
+    let last = distances.last().unwrap_or(first);
+    Some(f64::from(last - first).max(0.0))
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn pace_seconds_per_km(duration_seconds: i64, distance_meters: f64) -> Option<f64> {
+    (distance_meters > 0.0).then(|| duration_seconds as f64 / (distance_meters / 1000.0))
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn average_heart_rate(segment: &Segment) -> Option<f64> {
+    let heart_rates: Vec<i32> = segment.points.iter().filter_map(|p| p.heart_rate).collect();
+    if heart_rates.is_empty() {
+        return None;
+    }
+    let sum: i64 = heart_rates.iter().map(|&hr| i64::from(hr)).sum();
+    Some(sum as f64 / heart_rates.len() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration, Utc};
+    use run_sous_bpm_core::database::{activity_stream, track};
+    use run_sous_bpm_core::test_support::make_track;
+    use sea_orm::prelude::Uuid;
+
+    fn start() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    }
+
+    /// A 300s segment with a point every 60s, 200m apart, heart rate rising from 140 to 160
+    fn make_segment(track: Option<track::Model>) -> Segment {
+        let activity_id = Uuid::new_v4();
+        let points = (0..=5)
+            .map(|i| activity_stream::Model {
+                activity_id,
+                time: (start() + Duration::seconds(i * 60)).into(),
+                latitude: None,
+                longitude: None,
+                altitude: None,
+                heart_rate: Some(140 + i32::try_from(i).unwrap() * 4),
+                cadence: None,
+                watts: None,
+                velocity: None,
+                distance: Some(1000.0 + i as f32 * 200.0),
+                temperature: None,
+                grade: None,
+                moving: None,
+            })
+            .collect();
+
+        Segment {
+            index: 0,
+            track,
+            start_time: start(),
+            end_time: start() + Duration::seconds(300),
+            points,
+            avg_temperature: None,
+            repeated: false,
+            after_gap: false,
+        }
+    }
+
+    #[test]
+    fn test_track_name_with_comma_is_quoted() {
+        let segment = make_segment(Some(track::Model {
+            artist_name: "The Beatles".to_string(),
+            track_name: "Hello, Goodbye".to_string(),
+            ..make_track()
+        }));
+
+        let csv = activity_music_csv(&[segment]);
+        let row = csv.lines().nth(1).unwrap();
+
+        assert!(
+            row.starts_with("0,\"Hello, Goodbye\",The Beatles,"),
+            "Unexpected row: {row}"
+        );
+    }
+
+    #[test]
+    fn test_quotes_are_escaped() {
+        assert_eq!(csv_field(r#"Say "Hi""#), r#""Say ""Hi""""#);
+        assert_eq!(csv_field("Plain"), "Plain");
+    }
+
+    #[test]
+    fn test_row_contains_derived_stats() {
+        let csv = activity_music_csv(&[make_segment(Some(track::Model {
+            artist_name: "Artist".to_string(),
+            track_name: "Song".to_string(),
+            ..make_track()
+        }))]);
+        let cells: Vec<&str> = csv.lines().nth(1).unwrap().split(',').collect();
+
+        assert_eq!(cells[5], "300", "duration_seconds");
+        assert_eq!(cells[6], "1000.0", "distance_meters");
+        assert_eq!(cells[7], "300.0", "avg_pace_seconds_per_km: 300s over 1km");
+        assert_eq!(cells[8], "150.0", "avg_heart_rate of 140..=160");
+    }
+
+    #[test]
+    fn test_segment_without_music_or_streams_has_empty_cells() {
+        let segment = Segment {
+            points: Vec::new(),
+            ..make_segment(None)
+        };
+
+        let csv = activity_music_csv(&[segment]);
+        let cells: Vec<&str> = csv.lines().nth(1).unwrap().split(',').collect();
+
+        assert_eq!(cells.len(), 9);
+        assert_eq!(cells[1], "");
+        assert_eq!(cells[2], "");
+        assert_eq!(&cells[6..], ["", "", ""]);
+        assert_eq!(csv.lines().next().unwrap(), CSV_HEADER);
+    }
+}
@@ -0,0 +1,17 @@
+use serde::Serialize;
+
+/// Envelope for list endpoints, carrying the total row count for pagination UIs
+#[derive(Debug, Serialize)]
+pub struct Paginated<T> {
+    /// Rows of the requested page
+    pub items: Vec<T>,
+
+    /// Total number of rows matching the filters, across all pages
+    pub total: u64,
+
+    /// Zero-based page index
+    pub page: u64,
+
+    /// Page size, `None` when the whole list was returned in one page
+    pub per_page: Option<u64>,
+}
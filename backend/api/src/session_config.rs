@@ -0,0 +1,138 @@
+use std::fmt;
+
+use tower_sessions::cookie::{time, SameSite};
+use tracing::info;
+
+/// Name of the session cookie
+pub const SESSION_COOKIE_NAME: &str = "run_sous_bpm_session";
+
+/// Effective session cookie settings, resolved once at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionSettings {
+    pub secure: bool,
+    pub same_site: SameSite,
+    pub http_only: bool,
+    /// Session lifetime after the last request
+    pub inactivity_expiry: time::Duration,
+}
+
+/// Session settings that would silently break authentication
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionConfigError {
+    /// The frontend is served over HTTPS but the cookie is not marked `Secure`
+    InsecureCookieForHttpsFrontend { frontend_url: String },
+}
+
+impl fmt::Display for SessionConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InsecureCookieForHttpsFrontend { frontend_url } => write!(
+                f,
+                "COOKIE_SECURE is false but FRONTEND_URL ({frontend_url}) uses https: \
+                 set COOKIE_SECURE=true or sessions will not survive in the browser"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SessionConfigError {}
+
+impl SessionSettings {
+    /// Reads the settings from the environment
+    ///
+    /// - `COOKIE_SECURE`: only send the cookie over HTTPS (default: false for local development)
+    #[must_use]
+    pub fn from_env() -> Self {
+        let secure = std::env::var("COOKIE_SECURE")
+            .map(|v| v.to_lowercase() == "true")
+            .unwrap_or(false);
+
+        Self {
+            secure,
+            // Only send cookie for same-site requests (prevents CSRF)
+            same_site: SameSite::Strict,
+            // Prevents JavaScript access to the cookie (XSS)
+            http_only: true,
+            inactivity_expiry: time::Duration::hours(1),
+        }
+    }
+
+    /// Checks the settings against the frontend origin
+    ///
+    /// # Errors
+    ///
+    /// Returns `SessionConfigError::InsecureCookieForHttpsFrontend` if the frontend
+    /// is served over HTTPS while the cookie is not `Secure`
+    pub fn validate(&self, frontend_url: &str) -> Result<(), SessionConfigError> {
+        let https_frontend = frontend_url
+            .trim()
+            .get(..8)
+            .is_some_and(|scheme| scheme.eq_ignore_ascii_case("https://"));
+
+        if https_frontend && !self.secure {
+            return Err(SessionConfigError::InsecureCookieForHttpsFrontend {
+                frontend_url: frontend_url.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Logs the effective settings at startup
+    pub fn log(&self) {
+        info!(
+            cookie_name = SESSION_COOKIE_NAME,
+            secure = self.secure,
+            same_site = %self.same_site,
+            http_only = self.http_only,
+            inactivity_expiry_seconds = self.inactivity_expiry.whole_seconds(),
+            "Session cookie settings"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(secure: bool) -> SessionSettings {
+        SessionSettings {
+            secure,
+            ..SessionSettings::from_env()
+        }
+    }
+
+    #[test]
+    fn test_insecure_cookie_with_https_frontend_is_rejected() {
+        let result = settings(false).validate("https://runsousbpm.example");
+
+        assert_eq!(
+            result,
+            Err(SessionConfigError::InsecureCookieForHttpsFrontend {
+                frontend_url: "https://runsousbpm.example".to_string()
+            })
+        );
+        assert!(result.unwrap_err().to_string().contains("COOKIE_SECURE"));
+    }
+
+    #[test]
+    fn test_scheme_check_is_case_insensitive() {
+        assert!(settings(false)
+            .validate("HTTPS://runsousbpm.example")
+            .is_err());
+    }
+
+    #[test]
+    fn test_secure_cookie_with_https_frontend_is_valid() {
+        assert_eq!(
+            settings(true).validate("https://runsousbpm.example"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_http_frontend_allows_insecure_cookie() {
+        assert_eq!(settings(false).validate("http://127.0.0.1:5173"), Ok(()));
+        assert_eq!(settings(true).validate("http://127.0.0.1:5173"), Ok(()));
+    }
+}
@@ -0,0 +1,119 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::prelude::Uuid;
+
+/// Default minutes between two bulk syncs of the same user
+const DEFAULT_COOLDOWN_MINUTES: i64 = 10;
+
+/// Per-user cooldown between bulk stream syncs
+///
+/// A bulk sync calls Strava once per activity, so a user re-triggering it could
+/// spend the Strava budget shared by everyone. Read from `BULK_SYNC_COOLDOWN_MINUTES`
+/// (default 10, 0 disables the cooldown). Last runs are kept in memory, so a
+/// restart resets them.
+#[derive(Debug)]
+pub struct SyncCooldown {
+    cooldown: Duration,
+    last_runs: Mutex<HashMap<Uuid, DateTime<Utc>>>,
+}
+
+impl SyncCooldown {
+    #[must_use]
+    pub fn new(cooldown: Duration) -> Self {
+        Self {
+            cooldown,
+            last_runs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Reads the cooldown from `BULK_SYNC_COOLDOWN_MINUTES`
+    #[must_use]
+    pub fn from_env() -> Self {
+        let minutes = std::env::var("BULK_SYNC_COOLDOWN_MINUTES")
+            .ok()
+            .and_then(|minutes| minutes.trim().parse::<i64>().ok())
+            .filter(|minutes| *minutes >= 0)
+            .unwrap_or(DEFAULT_COOLDOWN_MINUTES);
+        Self::new(Duration::minutes(minutes))
+    }
+
+    /// Records a bulk sync of `user_id` at `now`, unless the previous one is too recent
+    ///
+    /// # Errors
+    ///
+    /// Returns how long the user has to wait while still cooling down
+    pub fn try_start(&self, user_id: Uuid, now: DateTime<Utc>) -> Result<(), Duration> {
+        let mut last_runs = self
+            .last_runs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(next_allowed) = last_runs.get(&user_id).map(|last| *last + self.cooldown) {
+            if now < next_allowed {
+                return Err(next_allowed - now);
+            }
+        }
+
+        // Entries past their cooldown no longer block anyone
+        last_runs.retain(|_, last| now < *last + self.cooldown);
+        last_runs.insert(user_id, now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base_time() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    }
+
+    #[test]
+    fn test_second_sync_within_the_cooldown_is_rejected() {
+        let cooldown = SyncCooldown::new(Duration::minutes(10));
+        let user_id = Uuid::new_v4();
+
+        assert!(cooldown.try_start(user_id, base_time()).is_ok());
+        let wait = cooldown
+            .try_start(user_id, base_time() + Duration::minutes(4))
+            .unwrap_err();
+
+        assert_eq!(wait, Duration::minutes(6));
+    }
+
+    #[test]
+    fn test_sync_is_allowed_again_after_the_cooldown() {
+        let cooldown = SyncCooldown::new(Duration::minutes(10));
+        let user_id = Uuid::new_v4();
+
+        assert!(cooldown.try_start(user_id, base_time()).is_ok());
+        assert!(cooldown
+            .try_start(user_id, base_time() + Duration::minutes(10))
+            .is_ok());
+        // The allowed sync starts a new cooldown
+        assert!(cooldown
+            .try_start(user_id, base_time() + Duration::minutes(11))
+            .is_err());
+    }
+
+    #[test]
+    fn test_cooldown_is_per_user() {
+        let cooldown = SyncCooldown::new(Duration::minutes(10));
+
+        assert!(cooldown.try_start(Uuid::new_v4(), base_time()).is_ok());
+        assert!(cooldown.try_start(Uuid::new_v4(), base_time()).is_ok());
+    }
+
+    #[test]
+    fn test_zero_cooldown_never_rejects() {
+        let cooldown = SyncCooldown::new(Duration::zero());
+        let user_id = Uuid::new_v4();
+
+        assert!(cooldown.try_start(user_id, base_time()).is_ok());
+        assert!(cooldown.try_start(user_id, base_time()).is_ok());
+    }
+}
@@ -1,16 +1,53 @@
+use tracing::{warn, Subscriber};
+use tracing_subscriber::{fmt::MakeWriter, util::SubscriberInitExt, EnvFilter};
+
+/// Output format of the log lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One JSON object per line, for log aggregation
+    Json,
+    /// Multi-line human-readable output
+    Pretty,
+    /// Single-line human-readable output
+    Compact,
+}
+
+impl LogFormat {
+    /// Parses a `LOG_FORMAT` value (`json`, `pretty` or `compact`, case-insensitive)
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "pretty" => Some(Self::Pretty),
+            "compact" => Some(Self::Compact),
+            _ => None,
+        }
+    }
+
+    /// Pretty for debug builds (development), JSON for release builds (production)
+    fn build_default() -> Self {
+        if cfg!(debug_assertions) {
+            Self::Pretty
+        } else {
+            Self::Json
+        }
+    }
+}
+
 /// Initialize the tracing subscriber with configurable output format
 ///
+/// Uses `LOG_FORMAT` to pick the output (`json`, `pretty` or `compact`); when unset
+/// or invalid, debug builds log pretty and release builds log JSON.
+///
 /// Uses `RUST_LOG` environment variable for filtering:
 /// - `RUST_LOG=debug` - All debug logs
 /// - `RUST_LOG=run_sous_bpm_api=debug,tower_http=info` - Specific module levels
 /// - `RUST_LOG=error` - Only errors
 pub fn init_tracing() {
-    // Determine if we should use pretty or compact format based on environment
-    let use_pretty = std::env::var("LOG_FORMAT")
-        .map(|f| f.to_lowercase() == "pretty")
-        .unwrap_or(true); // Default to pretty for development
+    let configured = std::env::var("LOG_FORMAT").ok();
+    let parsed = configured.as_deref().and_then(LogFormat::parse);
+    let format = parsed.unwrap_or_else(LogFormat::build_default);
 
-    let env_filter = tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
         // Default filter configuration
         // - Your app at INFO level
         // - Database queries at DEBUG
@@ -19,16 +56,109 @@ pub fn init_tracing() {
         "run_sous_bpm_api=info,run_sous_bpm_core=info,run_sous_bpm_integrations=info,sqlx=debug,tower_http=info,axum::rejection=trace".into()
     });
 
-    let subscriber = tracing_subscriber::fmt()
+    build_subscriber(format, env_filter, std::io::stdout).init();
+
+    if let (Some(value), None) = (configured, parsed) {
+        warn!(
+            log_format = %value,
+            "Unknown LOG_FORMAT, expected json, pretty or compact; using {format:?}"
+        );
+    }
+}
+
+/// Builds the formatting subscriber for `format`, writing log lines to `writer`
+fn build_subscriber<W>(
+    format: LogFormat,
+    env_filter: EnvFilter,
+    writer: W,
+) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let builder = tracing_subscriber::fmt()
         .with_env_filter(env_filter)
+        .with_writer(writer)
         .with_target(true) // Show which module logged
         .with_line_number(true) // Show line numbers
         .with_thread_ids(false) // Don't show thread IDs (noisy)
         .with_file(false); // Don't show full file paths
 
-    if use_pretty {
-        subscriber.pretty().init();
-    } else {
-        subscriber.compact().init();
+    match format {
+        // Color codes would end up inside the aggregated JSON strings
+        LogFormat::Json => Box::new(builder.with_ansi(false).json().finish()),
+        LogFormat::Pretty => Box::new(builder.pretty().finish()),
+        LogFormat::Compact => Box::new(builder.compact().finish()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// Captures everything a subscriber writes, one buffer shared by all writers
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn log_with(format: LogFormat) -> String {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = build_subscriber(format, EnvFilter::new("info"), move || writer.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request", request_id = "abc-123");
+            let _entered = span.enter();
+            tracing::info!(points = 42, "Synced activity streams");
+        });
+
+        let bytes = logs.0.lock().unwrap().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_log_format_values_are_parsed() {
+        assert_eq!(LogFormat::parse("json"), Some(LogFormat::Json));
+        assert_eq!(LogFormat::parse(" Pretty "), Some(LogFormat::Pretty));
+        assert_eq!(LogFormat::parse("COMPACT"), Some(LogFormat::Compact));
+        assert_eq!(LogFormat::parse("yaml"), None);
+    }
+
+    #[test]
+    fn test_json_format_writes_one_object_per_line_with_span_fields() {
+        let output = log_with(LogFormat::Json);
+
+        let line: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(line["fields"]["message"], "Synced activity streams");
+        assert_eq!(line["fields"]["points"], 42);
+        assert_eq!(line["span"]["request_id"], "abc-123");
+    }
+
+    #[test]
+    fn test_compact_format_writes_a_single_plain_line() {
+        let output = log_with(LogFormat::Compact);
+
+        assert!(serde_json::from_str::<serde_json::Value>(output.trim()).is_err());
+        assert_eq!(output.trim_end().lines().count(), 1, "{output}");
+        assert!(output.contains("Synced activity streams"), "{output}");
+    }
+
+    #[test]
+    fn test_pretty_format_spreads_an_event_over_several_lines() {
+        let output = log_with(LogFormat::Pretty);
+
+        assert!(output.trim_end().lines().count() > 1, "{output}");
+        assert!(output.contains("Synced activity streams"), "{output}");
     }
 }
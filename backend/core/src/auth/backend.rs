@@ -2,6 +2,8 @@ use crate::{
     auth::verify_password,
     database::{entities::user, user::Entity},
 };
+use std::sync::Arc;
+
 use axum_login::{AuthnBackend, UserId};
 use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
 use serde::Deserialize;
@@ -13,16 +15,23 @@ pub struct Credentials {
     pub email: String,
     #[validate(length(min = 8))]
     pub password: String,
+    /// Issue a long-lived refresh token alongside the session
+    #[serde(default)]
+    pub remember_me: bool,
 }
 
+/// Session authentication against the user table
+///
+/// Shares the application's connection: `DatabaseConnection` itself is not `Clone`
+/// once the sea-orm `mock` feature is enabled for tests.
 #[derive(Clone)]
 pub struct AuthBackend {
-    db: DatabaseConnection,
+    db: Arc<DatabaseConnection>,
 }
 
 impl AuthBackend {
     #[must_use]
-    pub fn new(db: DatabaseConnection) -> Self {
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
         Self { db }
     }
 }
@@ -40,7 +49,7 @@ impl AuthnBackend for AuthBackend {
     ) -> Result<Option<Self::User>, Self::Error> {
         let user = Entity::find()
             .filter(user::Column::Email.eq(creds.email))
-            .one(&self.db)
+            .one(self.db.as_ref())
             .await?;
         if let Some(user) = user {
             match verify_password(
@@ -56,7 +65,7 @@ impl AuthnBackend for AuthBackend {
     }
 
     async fn get_user(&self, user_id: &UserId<Self>) -> Result<Option<Self::User>, Self::Error> {
-        let user = Entity::find_by_id(*user_id).one(&self.db).await?;
+        let user = Entity::find_by_id(*user_id).one(self.db.as_ref()).await?;
         Ok(user)
     }
 }
@@ -1,8 +1,10 @@
 pub mod backend;
 pub mod password;
+pub mod token;
 
 pub use backend::*;
 pub use password::*;
+pub use token::*;
 
 use axum_login::AuthUser;
 use uuid::Uuid;
@@ -0,0 +1,287 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use hkdf::hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+use crate::config::read_secret;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Fixed JOSE header: only HS256 is issued or accepted
+const TOKEN_HEADER: &str = r#"{"alg":"HS256","typ":"JWT"}"#;
+
+/// Default bearer token lifetime, matching the cookie session inactivity timeout
+pub const DEFAULT_BEARER_TOKEN_TTL_SECONDS: i64 = 3600;
+
+/// Minimum signing secret length in bytes
+const MIN_SECRET_LEN: usize = 32;
+
+/// Errors that can occur when validating a bearer token
+#[derive(Debug, thiserror::Error)]
+pub enum TokenError {
+    #[error("Malformed bearer token")]
+    Malformed,
+
+    #[error("Invalid bearer token signature")]
+    InvalidSignature,
+
+    #[error("Bearer token expired")]
+    Expired,
+}
+
+/// Claims carried by a bearer token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BearerClaims {
+    /// User ID the token was issued to
+    pub sub: Uuid,
+    /// Issued-at, Unix timestamp (seconds)
+    pub iat: i64,
+    /// Expiration, Unix timestamp (seconds)
+    pub exp: i64,
+}
+
+/// Issues and validates HS256-signed bearer tokens (JWT compact format)
+///
+/// Bearer tokens are an alternative to cookie sessions for scripts and mobile
+/// clients. They only carry the user ID; the user itself is still loaded through
+/// the `AuthBackend` on every request.
+pub struct BearerTokenService {
+    secret: Zeroizing<Vec<u8>>,
+    ttl: Duration,
+}
+
+impl BearerTokenService {
+    /// Creates a token service from a signing secret and token lifetime
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret is shorter than 32 bytes.
+    #[must_use]
+    pub fn new(secret: &[u8], ttl: Duration) -> Self {
+        assert!(
+            secret.len() >= MIN_SECRET_LEN,
+            "Bearer token secret must be at least {MIN_SECRET_LEN} bytes"
+        );
+        Self {
+            secret: Zeroizing::new(secret.to_vec()),
+            ttl,
+        }
+    }
+
+    /// Builds the token service from the environment, if bearer auth is enabled
+    ///
+    /// Returns `None` unless `BEARER_AUTH_ENABLED=true`. The signing secret is read
+    /// from `JWT_SECRET` (or `JWT_SECRET_FILE`), the lifetime from `JWT_TTL_SECONDS`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if bearer auth is enabled but the secret is missing or too short.
+    #[must_use]
+    pub fn from_env() -> Option<Self> {
+        let enabled = std::env::var("BEARER_AUTH_ENABLED")
+            .map(|v| v.to_lowercase() == "true")
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+
+        let ttl_seconds = std::env::var("JWT_TTL_SECONDS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_BEARER_TOKEN_TTL_SECONDS);
+
+        let secret = Zeroizing::new(read_secret("JWT_SECRET"));
+        Some(Self::new(secret.as_bytes(), Duration::seconds(ttl_seconds)))
+    }
+
+    /// Token lifetime in seconds
+    #[must_use]
+    pub fn ttl_seconds(&self) -> i64 {
+        self.ttl.num_seconds()
+    }
+
+    /// Issues a signed token for the given user, valid from now
+    #[must_use]
+    pub fn issue(&self, user_id: Uuid) -> String {
+        self.issue_at(user_id, Utc::now())
+    }
+
+    /// Validates a token's signature and expiry against the current time
+    ///
+    /// # Errors
+    ///
+    /// Returns `TokenError` if the token is malformed, has an invalid signature or is expired
+    pub fn verify(&self, token: &str) -> Result<BearerClaims, TokenError> {
+        self.verify_at(token, Utc::now())
+    }
+
+    fn issue_at(&self, user_id: Uuid, now: DateTime<Utc>) -> String {
+        let claims = BearerClaims {
+            sub: user_id,
+            iat: now.timestamp(),
+            exp: (now + self.ttl).timestamp(),
+        };
+        let payload = serde_json::to_vec(&claims).expect("Claims are always serializable");
+
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(TOKEN_HEADER),
+            URL_SAFE_NO_PAD.encode(payload)
+        );
+        let signature = URL_SAFE_NO_PAD.encode(self.sign(signing_input.as_bytes()));
+
+        format!("{signing_input}.{signature}")
+    }
+
+    fn verify_at(&self, token: &str, now: DateTime<Utc>) -> Result<BearerClaims, TokenError> {
+        let (signing_input, signature) = token.rsplit_once('.').ok_or(TokenError::Malformed)?;
+        let (header, payload) = signing_input.split_once('.').ok_or(TokenError::Malformed)?;
+
+        // Reject anything but our own header (e.g. "alg":"none") before checking the signature
+        let header = URL_SAFE_NO_PAD
+            .decode(header)
+            .map_err(|_| TokenError::Malformed)?;
+        if header != TOKEN_HEADER.as_bytes() {
+            return Err(TokenError::Malformed);
+        }
+
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| TokenError::Malformed)?;
+        let mut mac = self.mac();
+        mac.update(signing_input.as_bytes());
+        mac.verify_slice(&signature)
+            .map_err(|_| TokenError::InvalidSignature)?;
+
+        let payload = URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|_| TokenError::Malformed)?;
+        let claims: BearerClaims =
+            serde_json::from_slice(&payload).map_err(|_| TokenError::Malformed)?;
+
+        if claims.exp <= now.timestamp() {
+            return Err(TokenError::Expired);
+        }
+
+        Ok(claims)
+    }
+
+    fn sign(&self, data: &[u8]) -> Vec<u8> {
+        let mut mac = self.mac();
+        mac.update(data);
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts keys of any length")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"test-secret-that-is-at-least-32-bytes-long";
+
+    fn service() -> BearerTokenService {
+        BearerTokenService::new(SECRET, Duration::seconds(DEFAULT_BEARER_TOKEN_TTL_SECONDS))
+    }
+
+    #[test]
+    fn test_issue_and_verify_roundtrip() {
+        let tokens = service();
+        let user_id = Uuid::new_v4();
+        let now = Utc::now();
+
+        let token = tokens.issue_at(user_id, now);
+        let claims = tokens.verify_at(&token, now).unwrap();
+
+        assert_eq!(claims.sub, user_id);
+        assert_eq!(claims.iat, now.timestamp());
+        assert_eq!(
+            claims.exp,
+            now.timestamp() + DEFAULT_BEARER_TOKEN_TTL_SECONDS
+        );
+    }
+
+    #[test]
+    fn test_expired_token_rejected() {
+        let tokens = service();
+        let issued_at = Utc::now() - Duration::hours(2);
+        let token = tokens.issue_at(Uuid::new_v4(), issued_at);
+
+        assert!(matches!(tokens.verify(&token), Err(TokenError::Expired)));
+    }
+
+    #[test]
+    fn test_token_from_other_secret_rejected() {
+        let other = BearerTokenService::new(
+            b"another-secret-that-is-at-least-32-bytes",
+            Duration::seconds(60),
+        );
+        let token = other.issue(Uuid::new_v4());
+
+        assert!(matches!(
+            service().verify(&token),
+            Err(TokenError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn test_tampered_payload_rejected() {
+        let tokens = service();
+        let token = tokens.issue(Uuid::new_v4());
+        let forged_claims = BearerClaims {
+            sub: Uuid::new_v4(),
+            iat: 0,
+            exp: i64::MAX,
+        };
+        let forged_payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&forged_claims).unwrap());
+
+        let mut parts: Vec<&str> = token.split('.').collect();
+        parts[1] = &forged_payload;
+        let forged = parts.join(".");
+
+        assert!(matches!(
+            tokens.verify(&forged),
+            Err(TokenError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn test_unsigned_token_rejected() {
+        let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"none","typ":"JWT"}"#);
+        let payload = URL_SAFE_NO_PAD.encode(
+            serde_json::to_vec(&BearerClaims {
+                sub: Uuid::new_v4(),
+                iat: 0,
+                exp: i64::MAX,
+            })
+            .unwrap(),
+        );
+
+        let result = service().verify(&format!("{header}.{payload}."));
+        assert!(matches!(result, Err(TokenError::Malformed)));
+    }
+
+    #[test]
+    fn test_garbage_rejected() {
+        let tokens = service();
+        assert!(matches!(tokens.verify(""), Err(TokenError::Malformed)));
+        assert!(matches!(
+            tokens.verify("not-a-token"),
+            Err(TokenError::Malformed)
+        ));
+        assert!(matches!(tokens.verify("a.b.c"), Err(TokenError::Malformed)));
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 32 bytes")]
+    fn test_short_secret_panics() {
+        let _ = BearerTokenService::new(b"short", Duration::seconds(60));
+    }
+}
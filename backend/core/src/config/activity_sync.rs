@@ -0,0 +1,61 @@
+use chrono::Duration;
+
+use super::env_or_default;
+
+/// Environment variable setting how far back incremental activity syncs overlap the last stored activity
+pub const ACTIVITY_SYNC_OVERLAP_MINUTES_VAR: &str = "ACTIVITY_SYNC_OVERLAP_MINUTES";
+
+/// Overlap used when `ACTIVITY_SYNC_OVERLAP_MINUTES` is unset
+pub const DEFAULT_ACTIVITY_SYNC_OVERLAP_MINUTES: i64 = 60;
+
+/// Reads the incremental sync overlap from `ACTIVITY_SYNC_OVERLAP_MINUTES`
+///
+/// Activities re-fetched inside the overlap are deduplicated by the `external_id` upsert.
+/// Returns `DEFAULT_ACTIVITY_SYNC_OVERLAP_MINUTES` when the variable is unset or invalid.
+#[must_use]
+pub fn activity_sync_overlap() -> Duration {
+    Duration::minutes(env_or_default(
+        ACTIVITY_SYNC_OVERLAP_MINUTES_VAR,
+        DEFAULT_ACTIVITY_SYNC_OVERLAP_MINUTES,
+        is_valid_overlap_minutes,
+    ))
+}
+
+/// Capped at a year so the subtraction from a timestamp can't overflow
+fn is_valid_overlap_minutes(minutes: &i64) -> bool {
+    (0..=525_600).contains(minutes)
+}
+
+/// Environment variable capping how many activities a single sync stores
+pub const ACTIVITY_SYNC_MAX_ACTIVITIES_VAR: &str = "ACTIVITY_SYNC_MAX_ACTIVITIES";
+
+/// Activities stored per sync when `ACTIVITY_SYNC_MAX_ACTIVITIES` is unset
+///
+/// Above a decade of daily activities; larger histories are caught up by later syncs.
+pub const DEFAULT_ACTIVITY_SYNC_MAX_ACTIVITIES: usize = 5000;
+
+/// Reads the per-sync activity cap from `ACTIVITY_SYNC_MAX_ACTIVITIES`
+///
+/// Returns `DEFAULT_ACTIVITY_SYNC_MAX_ACTIVITIES` when the variable is unset or invalid.
+#[must_use]
+pub fn activity_sync_max_activities() -> usize {
+    env_or_default(
+        ACTIVITY_SYNC_MAX_ACTIVITIES_VAR,
+        DEFAULT_ACTIVITY_SYNC_MAX_ACTIVITIES,
+        |max| *max >= 1,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlap_accepts_zero_up_to_a_year() {
+        assert!(is_valid_overlap_minutes(&0));
+        assert!(is_valid_overlap_minutes(&15));
+        assert!(is_valid_overlap_minutes(&525_600));
+        assert!(!is_valid_overlap_minutes(&-5));
+        assert!(!is_valid_overlap_minutes(&9_999_999));
+    }
+}
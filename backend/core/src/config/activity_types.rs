@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use tracing::warn;
+
+use crate::models::ActivityCategory;
+
+/// Environment variable adding activity type aliases on top of Strava's built-in types
+///
+/// Comma-separated `Type=category` pairs, e.g. `Handcycle=ride,Canoeing=other`, where the
+/// category is one of `run`, `ride`, `swim` or `other`.
+pub const ACTIVITY_TYPE_ALIASES_VAR: &str = "ACTIVITY_TYPE_ALIASES";
+
+/// Reads the extra activity type aliases from `ACTIVITY_TYPE_ALIASES`
+///
+/// Returns no aliases when the variable is unset. Invalid pairs are skipped.
+#[must_use]
+pub fn activity_type_aliases() -> HashMap<String, ActivityCategory> {
+    parse_aliases(std::env::var(ACTIVITY_TYPE_ALIASES_VAR).ok().as_deref())
+}
+
+fn parse_aliases(value: Option<&str>) -> HashMap<String, ActivityCategory> {
+    let Some(value) = value else {
+        return HashMap::new();
+    };
+
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .filter_map(|pair| {
+            let parsed = pair.split_once('=').and_then(|(raw, category)| {
+                let raw = raw.trim();
+                let category = category.trim().parse::<ActivityCategory>().ok()?;
+                (!raw.is_empty()).then(|| (raw.to_string(), category))
+            });
+            if parsed.is_none() {
+                warn!(
+                    pair = pair,
+                    "Invalid {ACTIVITY_TYPE_ALIASES_VAR} entry, expected Type=run|ride|swim|other"
+                );
+            }
+            parsed
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aliases_empty_when_unset() {
+        assert!(parse_aliases(None).is_empty());
+        assert!(parse_aliases(Some("")).is_empty());
+    }
+
+    #[test]
+    fn test_aliases_parse_pairs_and_skip_invalid_ones() {
+        let aliases = parse_aliases(Some(
+            "Handcycle=ride, Canoeing = other,Rowing,=run,Skate=skate",
+        ));
+
+        assert_eq!(
+            aliases,
+            HashMap::from([
+                ("Handcycle".to_string(), ActivityCategory::Ride),
+                ("Canoeing".to_string(), ActivityCategory::Other),
+            ])
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use std::{fmt::Display, str::FromStr};
+
+use tracing::warn;
+
+/// Reads a setting from the environment variable `var`
+///
+/// Returns `default` when the variable is unset or blank, and logs a warning
+/// before falling back to it when the value doesn't parse or fails `valid`.
+#[must_use]
+pub fn env_or_default<T>(var: &str, default: T, valid: impl Fn(&T) -> bool) -> T
+where
+    T: FromStr + Display,
+{
+    parse_or_default(var, std::env::var(var).ok().as_deref(), default, valid)
+}
+
+fn parse_or_default<T>(var: &str, value: Option<&str>, default: T, valid: impl Fn(&T) -> bool) -> T
+where
+    T: FromStr + Display,
+{
+    let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) else {
+        return default;
+    };
+
+    match value.parse::<T>() {
+        Ok(parsed) if valid(&parsed) => parsed,
+        _ => {
+            warn!(value = value, "Invalid {var}, using the default {default}");
+            default
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_positive(value: Option<&str>) -> u32 {
+        parse_or_default("TEST_SETTING", value, 3, |v| *v >= 1)
+    }
+
+    #[test]
+    fn test_unset_or_blank_uses_default() {
+        assert_eq!(parse_positive(None), 3);
+        assert_eq!(parse_positive(Some("")), 3);
+        assert_eq!(parse_positive(Some("  ")), 3);
+    }
+
+    #[test]
+    fn test_value_is_trimmed_and_parsed() {
+        assert_eq!(parse_positive(Some("5")), 5);
+        assert_eq!(parse_positive(Some(" 250 ")), 250);
+        assert_eq!(parse_positive(Some("1")), 1);
+    }
+
+    #[test]
+    fn test_unparsable_or_rejected_value_uses_default() {
+        assert_eq!(parse_positive(Some("often")), 3);
+        assert_eq!(parse_positive(Some("-2")), 3);
+        assert_eq!(parse_positive(Some("0")), 3);
+    }
+
+    #[test]
+    fn test_unset_variable_uses_default() {
+        assert_eq!(
+            env_or_default("RUN_SOUS_BPM_UNSET_TEST_SETTING", 7_i64, |_| true),
+            7
+        );
+    }
+}
@@ -0,0 +1,20 @@
+use super::env_or_default;
+
+/// Environment variable setting the longest time range the Last.fm debug endpoint accepts
+pub const LASTFM_RANGE_MAX_SECONDS_VAR: &str = "LASTFM_RANGE_MAX_SECONDS";
+
+/// Longest Last.fm debug range when `LASTFM_RANGE_MAX_SECONDS` is unset (7 days)
+pub const DEFAULT_LASTFM_RANGE_MAX_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+/// Reads the longest Last.fm debug range, in seconds, from `LASTFM_RANGE_MAX_SECONDS`
+///
+/// Longer ranges would page through Last.fm for a long time. Returns
+/// `DEFAULT_LASTFM_RANGE_MAX_SECONDS` when the variable is unset or invalid.
+#[must_use]
+pub fn lastfm_range_max_seconds() -> i64 {
+    env_or_default(
+        LASTFM_RANGE_MAX_SECONDS_VAR,
+        DEFAULT_LASTFM_RANGE_MAX_SECONDS,
+        |seconds| *seconds >= 1,
+    )
+}
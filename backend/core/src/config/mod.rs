@@ -1,5 +1,19 @@
+pub mod activity_sync;
+pub mod activity_types;
+pub mod env;
+pub mod lastfm;
 pub mod oauth;
+pub mod retry;
 pub mod secret;
+pub mod simplification;
+pub mod streams;
 
+pub use activity_sync::*;
+pub use activity_types::*;
+pub use env::env_or_default;
+pub use lastfm::*;
 pub use oauth::*;
-pub use secret::read_secret;
+pub use retry::*;
+pub use secret::{read_optional_secret, read_secret};
+pub use simplification::*;
+pub use streams::*;
@@ -11,7 +11,16 @@ pub enum OAuthProvider {
     Spotify,
 }
 
+/// A scope requested when starting an OAuth flow is not in the provider's allowlist
+#[derive(Debug, thiserror::Error)]
+#[error("Scope '{scope}' cannot be requested from {provider}")]
+pub struct ScopeNotAllowedError {
+    pub provider: OAuthProvider,
+    pub scope: String,
+}
+
 pub struct ClientInfo {
+    pub(crate) provider: OAuthProvider,
     pub(crate) client_id: ClientId,
     pub(crate) client_secret: ClientSecret,
     pub(crate) auth_url: AuthUrl,
@@ -21,46 +30,178 @@ pub struct ClientInfo {
     pub(crate) auth_type: AuthType,
 }
 
+impl OAuthProvider {
+    /// Every supported provider
+    pub const ALL: [Self; 2] = [Self::Strava, Self::Spotify];
+
+    /// Environment variable overriding `REDIRECT_URI` for this provider, e.g. `STRAVA_REDIRECT_URI`
+    #[must_use]
+    pub fn redirect_uri_var(self) -> String {
+        format!("{}_REDIRECT_URI", self.to_string().to_uppercase())
+    }
+
+    /// Path of the provider-specific redirect URI, `None` when the provider uses `REDIRECT_URI`
+    ///
+    /// The server must answer OAuth callbacks on this path too.
+    #[must_use]
+    pub fn specific_redirect_path(self) -> Option<String> {
+        dotenv().ok();
+        let uri = non_empty(std::env::var(self.redirect_uri_var()).ok())?;
+        Some(oauth2::url::Url::parse(&uri).ok()?.path().to_string())
+    }
+
+    /// Provider's public authorization endpoint, used when `{PROVIDER}_AUTH_URL` is unset
+    #[must_use]
+    pub fn default_auth_url(self) -> &'static str {
+        match self {
+            OAuthProvider::Strava => "https://www.strava.com/oauth/authorize",
+            OAuthProvider::Spotify => "https://accounts.spotify.com/authorize",
+        }
+    }
+
+    /// Provider's public token endpoint, used when `{PROVIDER}_TOKEN_URL` is unset
+    #[must_use]
+    pub fn default_token_url(self) -> &'static str {
+        match self {
+            OAuthProvider::Strava => "https://www.strava.com/oauth/token",
+            OAuthProvider::Spotify => "https://accounts.spotify.com/api/token",
+        }
+    }
+
+    /// Scopes the integration needs, requested at authorization and checked on stored tokens
+    #[must_use]
+    pub fn required_scopes(self) -> &'static [&'static str] {
+        match self {
+            OAuthProvider::Strava => &["activity:read_all"],
+            OAuthProvider::Spotify => &["user-read-recently-played"],
+        }
+    }
+
+    /// Scopes that may be requested on top of the required ones when (re)authorizing
+    #[must_use]
+    pub fn allowed_scopes(self) -> &'static [&'static str] {
+        match self {
+            OAuthProvider::Strava => &[
+                "read",
+                "read_all",
+                "profile:read_all",
+                "activity:read",
+                "activity:read_all",
+            ],
+            OAuthProvider::Spotify => &[
+                "user-read-recently-played",
+                "user-read-currently-playing",
+                "user-read-playback-state",
+                "user-top-read",
+                "user-library-read",
+            ],
+        }
+    }
+
+    /// Separator between scopes in the authorize URL's `scope` parameter
+    ///
+    /// Strava expects a comma-separated list, Spotify follows RFC 6749's spaces.
+    #[must_use]
+    pub fn scope_separator(self) -> &'static str {
+        match self {
+            OAuthProvider::Strava => ",",
+            OAuthProvider::Spotify => " ",
+        }
+    }
+
+    fn default_scopes(self) -> Vec<Scope> {
+        self.required_scopes()
+            .iter()
+            .map(|scope| Scope::new((*scope).to_string()))
+            .collect()
+    }
+}
+
 impl ClientInfo {
     fn retrieve_env_var(var_name: &str) -> String {
         dotenv().ok();
         super::secret::read_secret(var_name)
     }
 
+    /// Reads an optional URL override, falling back to the given default
+    fn retrieve_env_url(var_name: &str, default: &str) -> String {
+        dotenv().ok();
+        std::env::var(var_name)
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| default.to_string())
+    }
+
+    /// Creates OAuth client configuration with explicit endpoint URLs
+    ///
+    /// Lets tests and local mocks point the OAuth flow at another server.
+    /// Scopes and auth type are the provider defaults.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `auth_url`, `token_url` or `redirect_url` is not a valid URL
+    pub fn new(
+        provider: OAuthProvider,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+        auth_url: &str,
+        token_url: &str,
+        redirect_url: &str,
+    ) -> Result<Self, oauth2::url::ParseError> {
+        Ok(ClientInfo {
+            provider,
+            client_id: ClientId::new(client_id.into()),
+            client_secret: ClientSecret::new(client_secret.into()),
+            auth_url: AuthUrl::new(auth_url.to_string())?,
+            token_url: TokenUrl::new(token_url.to_string())?,
+            redirect_url: RedirectUrl::new(redirect_url.to_string())?,
+            scopes: provider.default_scopes(),
+            auth_type: AuthType::RequestBody,
+        })
+    }
+
     /// Creates OAuth client configuration from provider type
     ///
+    /// Auth and token URLs default to the provider's public endpoints and can be
+    /// overridden with `{PROVIDER}_AUTH_URL` and `{PROVIDER}_TOKEN_URL`. The redirect
+    /// URI is `{PROVIDER}_REDIRECT_URI` when set, `REDIRECT_URI` otherwise.
+    ///
     /// # Panics
     ///
     /// Panics if:
-    /// - Required environment variables are not set (`CLIENT_ID`, `CLIENT_SECRET`, `REDIRECT_URI`, `AUTH_URL`, `TOKEN_URL`)
+    /// - Required environment variables are not set (`CLIENT_ID`, `CLIENT_SECRET`, and
+    ///   `REDIRECT_URI` when the provider has no redirect URI of its own)
     /// - URL parsing fails for `auth_url`, `token_url`, or `redirect_url`
     #[must_use]
     pub fn from_provider(provider: OAuthProvider) -> Self {
         let prefix = provider.to_string().to_uppercase();
-        let client_id = ClientId::new(Self::retrieve_env_var(&format!("{prefix}_CLIENT_ID")));
-        let client_secret =
-            ClientSecret::new(Self::retrieve_env_var(&format!("{prefix}_CLIENT_SECRET")));
-        let redirect_url = RedirectUrl::new(Self::retrieve_env_var("REDIRECT_URI"));
-        let auth_url = AuthUrl::new(Self::retrieve_env_var(&format!("{prefix}_AUTH_URL")));
-        let token_url = TokenUrl::new(Self::retrieve_env_var(&format!("{prefix}_TOKEN_URL")));
-        let scopes = match provider {
-            OAuthProvider::Strava => vec![Scope::new("activity:read_all".to_string())],
-            OAuthProvider::Spotify => vec![Scope::new("user-read-recently-played".to_string())],
-        };
-        let auth_type = AuthType::RequestBody;
-
-        ClientInfo {
+        let client_id = Self::retrieve_env_var(&format!("{prefix}_CLIENT_ID"));
+        let client_secret = Self::retrieve_env_var(&format!("{prefix}_CLIENT_SECRET"));
+        dotenv().ok();
+        let redirect_url = redirect_uri(provider, |var| std::env::var(var).ok())
+            .unwrap_or_else(|| Self::retrieve_env_var("REDIRECT_URI"));
+        let auth_url =
+            Self::retrieve_env_url(&format!("{prefix}_AUTH_URL"), provider.default_auth_url());
+        let token_url =
+            Self::retrieve_env_url(&format!("{prefix}_TOKEN_URL"), provider.default_token_url());
+
+        Self::new(
+            provider,
             client_id,
             client_secret,
-            auth_url: auth_url.expect("AuthUrl must be valid"),
-            token_url: token_url.expect("TokenUrl must be valid"),
-            redirect_url: redirect_url.expect("RedirectUrl must be valid"),
-            scopes,
-            auth_type,
-        }
+            &auth_url,
+            &token_url,
+            &redirect_url,
+        )
+        .expect("OAuth provider URLs must be valid")
     }
 
     // Public getters for accessing fields from outside the crate
+    #[must_use]
+    pub fn provider(&self) -> OAuthProvider {
+        self.provider
+    }
+
     #[must_use]
     pub fn client_id(&self) -> &ClientId {
         &self.client_id
@@ -96,3 +237,87 @@ impl ClientInfo {
         &self.auth_type
     }
 }
+
+/// Redirect URI for `provider`: its own `{PROVIDER}_REDIRECT_URI` first, then `REDIRECT_URI`
+///
+/// `lookup` reads a variable; blank values count as unset.
+fn redirect_uri(
+    provider: OAuthProvider,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Option<String> {
+    non_empty(lookup(&provider.redirect_uri_var())).or_else(|| non_empty(lookup("REDIRECT_URI")))
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn lookup(vars: &HashMap<&'static str, &'static str>) -> impl Fn(&str) -> Option<String> + '_ {
+        move |var| vars.get(var).map(|value| (*value).to_string())
+    }
+
+    #[test]
+    fn test_each_provider_picks_its_own_redirect_uri() {
+        let vars = HashMap::from([
+            ("REDIRECT_URI", "https://app.example.com/api/oauth/callback"),
+            (
+                "STRAVA_REDIRECT_URI",
+                "https://app.example.com/api/oauth/strava/callback",
+            ),
+            (
+                "SPOTIFY_REDIRECT_URI",
+                "https://app.example.com/api/oauth/spotify/callback",
+            ),
+        ]);
+
+        assert_eq!(
+            redirect_uri(OAuthProvider::Strava, lookup(&vars)).as_deref(),
+            Some("https://app.example.com/api/oauth/strava/callback")
+        );
+        assert_eq!(
+            redirect_uri(OAuthProvider::Spotify, lookup(&vars)).as_deref(),
+            Some("https://app.example.com/api/oauth/spotify/callback")
+        );
+    }
+
+    #[test]
+    fn test_redirect_uri_falls_back_to_the_shared_one() {
+        let vars = HashMap::from([
+            ("REDIRECT_URI", "https://app.example.com/api/oauth/callback"),
+            (
+                "STRAVA_REDIRECT_URI",
+                "https://app.example.com/api/oauth/strava/callback",
+            ),
+            ("SPOTIFY_REDIRECT_URI", "  "),
+        ]);
+
+        assert_eq!(
+            redirect_uri(OAuthProvider::Spotify, lookup(&vars)).as_deref(),
+            Some("https://app.example.com/api/oauth/callback")
+        );
+        assert_eq!(
+            redirect_uri(OAuthProvider::Spotify, lookup(&HashMap::new())),
+            None
+        );
+    }
+
+    #[test]
+    fn test_redirect_uri_variables_are_named_after_the_provider() {
+        assert_eq!(
+            OAuthProvider::Strava.redirect_uri_var(),
+            "STRAVA_REDIRECT_URI"
+        );
+        assert_eq!(
+            OAuthProvider::Spotify.redirect_uri_var(),
+            "SPOTIFY_REDIRECT_URI"
+        );
+    }
+}
@@ -0,0 +1,20 @@
+use super::env_or_default;
+
+/// Environment variable setting how many times a sync write is attempted on transient errors
+pub const SYNC_DB_RETRY_ATTEMPTS_VAR: &str = "SYNC_DB_RETRY_ATTEMPTS";
+
+/// Attempts per sync write when `SYNC_DB_RETRY_ATTEMPTS` is unset
+pub const DEFAULT_SYNC_DB_RETRY_ATTEMPTS: u32 = 3;
+
+/// Reads the number of attempts per sync write from `SYNC_DB_RETRY_ATTEMPTS`
+///
+/// Returns `DEFAULT_SYNC_DB_RETRY_ATTEMPTS` when the variable is unset or invalid.
+/// `1` disables retries.
+#[must_use]
+pub fn sync_db_retry_attempts() -> u32 {
+    env_or_default(
+        SYNC_DB_RETRY_ATTEMPTS_VAR,
+        DEFAULT_SYNC_DB_RETRY_ATTEMPTS,
+        |attempts| *attempts >= 1,
+    )
+}
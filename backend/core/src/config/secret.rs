@@ -14,3 +14,24 @@ pub fn read_secret(var: &str) -> String {
         std::env::var(var).unwrap_or_else(|_| panic!("Either {var} or {var}_FILE must be set"))
     }
 }
+
+/// Reads an optional secret from `{var}_FILE` (file path) if set, otherwise from `{var}` directly.
+///
+/// Returns `None` when neither is set or the value is empty, for integrations that
+/// degrade gracefully without their credentials.
+///
+/// # Panics
+///
+/// Panics if `{var}_FILE` is set but the file cannot be read.
+#[must_use]
+pub fn read_optional_secret(var: &str) -> Option<String> {
+    let value = if let Ok(path) = std::env::var(format!("{var}_FILE")) {
+        std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Failed to read {var}_FILE at {path}: {e}"))
+            .trim_end()
+            .to_string()
+    } else {
+        std::env::var(var).ok()?
+    };
+    Some(value).filter(|v| !v.is_empty())
+}
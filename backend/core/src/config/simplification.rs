@@ -0,0 +1,23 @@
+use super::env_or_default;
+
+/// Environment variable setting the smallest segment, in GPS points, that gets simplified
+pub const SIMPLIFY_MIN_POINTS_VAR: &str = "SIMPLIFY_MIN_POINTS";
+
+/// Smallest simplified segment when `SIMPLIFY_MIN_POINTS` is unset
+pub const DEFAULT_SIMPLIFY_MIN_POINTS: usize = 10;
+
+/// Fewest GPS points from which simplification can drop anything
+const MIN_SIMPLIFIABLE_POINTS: usize = 3;
+
+/// Reads the smallest simplified segment from `SIMPLIFY_MIN_POINTS`
+///
+/// Segments with fewer GPS points are returned unchanged. Returns
+/// `DEFAULT_SIMPLIFY_MIN_POINTS` when the variable is unset or invalid.
+#[must_use]
+pub fn simplify_min_points() -> usize {
+    env_or_default(
+        SIMPLIFY_MIN_POINTS_VAR,
+        DEFAULT_SIMPLIFY_MIN_POINTS,
+        |points| *points >= MIN_SIMPLIFIABLE_POINTS,
+    )
+}
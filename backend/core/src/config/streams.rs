@@ -0,0 +1,33 @@
+use super::env_or_default;
+
+/// Environment variable enabling ingestion-time stream downsampling
+///
+/// When set to `N > 1`, only every Nth stream point (plus the last one) is stored.
+pub const STREAM_INGEST_KEEP_EVERY_VAR: &str = "STREAM_INGEST_KEEP_EVERY";
+
+/// Reads the ingestion downsampling step from `STREAM_INGEST_KEEP_EVERY`
+///
+/// Returns 1 (store every point) when the variable is unset or invalid.
+#[must_use]
+pub fn stream_ingest_keep_every() -> usize {
+    env_or_default(STREAM_INGEST_KEEP_EVERY_VAR, 1, |step| *step >= 1)
+}
+
+/// Environment variable setting how many stream points are inserted per statement
+pub const STREAM_INSERT_BATCH_SIZE_VAR: &str = "STREAM_INSERT_BATCH_SIZE";
+
+/// Stream points inserted per statement when `STREAM_INSERT_BATCH_SIZE` is unset
+pub const DEFAULT_STREAM_INSERT_BATCH_SIZE: usize = 2000;
+
+/// Reads the stream insert batch size from `STREAM_INSERT_BATCH_SIZE`
+///
+/// Returns `DEFAULT_STREAM_INSERT_BATCH_SIZE` when the variable is unset or invalid.
+/// Values above what Postgres accepts in one statement are capped on insert.
+#[must_use]
+pub fn stream_insert_batch_size() -> usize {
+    env_or_default(
+        STREAM_INSERT_BATCH_SIZE_VAR,
+        DEFAULT_STREAM_INSERT_BATCH_SIZE,
+        |size| *size >= 1,
+    )
+}
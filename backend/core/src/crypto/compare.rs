@@ -0,0 +1,37 @@
+use std::hint::black_box;
+
+/// Compares two byte strings in constant time
+///
+/// Every byte is examined whatever the position of the first mismatch, so the
+/// time taken does not reveal how much of a secret an attacker guessed right.
+/// Only the lengths may leak, which is fine for fixed-length secrets.
+#[must_use]
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && black_box(diff_bits(a, b)) == 0
+}
+
+/// ORs together the differing bits of every byte pair, without branching on them
+fn diff_bits(a: &[u8], b: &[u8]) -> u8 {
+    a.iter()
+        .zip(b)
+        .fold(0, |acc, (x, y)| acc | black_box(x ^ y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equal_and_different_inputs() {
+        assert!(constant_time_eq(b"csrf-token", b"csrf-token"));
+        assert!(!constant_time_eq(b"csrf-token", b"csrf-tokeN"));
+        assert!(!constant_time_eq(b"csrf-token", b"csrf-toke"));
+        assert!(constant_time_eq(b"", b""));
+    }
+
+    #[test]
+    fn test_comparison_does_not_stop_at_first_mismatch() {
+        // Differences in the first and the last byte both reach the result
+        assert_eq!(diff_bits(&[0b01, 7, 7, 0], &[0, 7, 7, 0b10]), 0b11);
+    }
+}
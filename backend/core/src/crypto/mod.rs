@@ -1,14 +1,20 @@
 pub mod cipher;
+pub mod compare;
 pub mod error;
 pub mod key;
 pub mod payload;
 pub mod service;
+pub mod status;
+pub mod token_crypto;
 
 pub use cipher::*;
+pub use compare::*;
 pub use error::*;
 pub use key::*;
 pub use payload::*;
 pub use service::*;
+pub use status::*;
+pub use token_crypto::*;
 
 /// AES-256 key size in bytes
 pub const KEY_SIZE: usize = 32;
@@ -41,4 +41,12 @@ impl EncryptedPayload {
             ciphertext,
         })
     }
+
+    /// Reads the version byte of a stored payload without decrypting it
+    ///
+    /// # Errors
+    /// Returns `CryptoError` if the base64 string is invalid or the payload format is incorrect
+    pub fn version_of(s: &str) -> Result<u8, CryptoError> {
+        Self::from_base64(s).map(|payload| payload.version)
+    }
 }
@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::crypto::{EncryptedPayload, CURRENT_VERSION};
+
+/// Payload versions found in a sample of stored ciphertexts
+///
+/// Used to check that a key rotation has re-encrypted everything: once it has,
+/// `outdated` drops to zero.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PayloadVersionReport {
+    /// Version new payloads are written with
+    pub current_version: u8,
+    /// Number of payloads inspected
+    pub sampled: u64,
+    /// Payloads on a version other than `current_version`
+    pub outdated: u64,
+    /// Payloads whose version byte could not be read
+    pub unreadable: u64,
+    /// Payload count per version byte
+    pub by_version: BTreeMap<u8, u64>,
+}
+
+impl PayloadVersionReport {
+    /// Counts payload versions by parsing the version byte of each payload
+    #[must_use]
+    pub fn from_payloads<'a>(payloads: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut report = Self {
+            current_version: CURRENT_VERSION,
+            sampled: 0,
+            outdated: 0,
+            unreadable: 0,
+            by_version: BTreeMap::new(),
+        };
+
+        for payload in payloads {
+            report.sampled += 1;
+            match EncryptedPayload::version_of(payload) {
+                Ok(version) => {
+                    *report.by_version.entry(version).or_default() += 1;
+                    if version != CURRENT_VERSION {
+                        report.outdated += 1;
+                    }
+                }
+                Err(_) => report.unreadable += 1,
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::NONCE_SIZE;
+
+    fn payload(version: u8) -> String {
+        EncryptedPayload {
+            version,
+            nonce: [7; NONCE_SIZE],
+            ciphertext: vec![1, 2, 3, 4],
+        }
+        .to_base64()
+    }
+
+    #[test]
+    fn test_counts_mixed_versions() {
+        let current = CURRENT_VERSION;
+        let old = CURRENT_VERSION.wrapping_sub(1);
+        let payloads = [
+            payload(current),
+            payload(old),
+            payload(current),
+            payload(old),
+        ];
+
+        let report = PayloadVersionReport::from_payloads(payloads.iter().map(String::as_str));
+
+        assert_eq!(report.current_version, CURRENT_VERSION);
+        assert_eq!(report.sampled, 4);
+        assert_eq!(report.outdated, 2);
+        assert_eq!(report.unreadable, 0);
+        assert_eq!(report.by_version, BTreeMap::from([(old, 2), (current, 2)]));
+    }
+
+    #[test]
+    fn test_unreadable_payloads_are_counted_separately() {
+        let payloads = [
+            payload(CURRENT_VERSION),
+            "not base64!".to_string(),
+            "AAAA".to_string(),
+        ];
+
+        let report = PayloadVersionReport::from_payloads(payloads.iter().map(String::as_str));
+
+        assert_eq!(report.sampled, 3);
+        assert_eq!(report.outdated, 0);
+        assert_eq!(report.unreadable, 2);
+        assert_eq!(report.by_version, BTreeMap::from([(CURRENT_VERSION, 1)]));
+    }
+
+    #[test]
+    fn test_empty_sample() {
+        let report = PayloadVersionReport::from_payloads(std::iter::empty());
+
+        assert_eq!(report.sampled, 0);
+        assert_eq!(report.outdated, 0);
+        assert!(report.by_version.is_empty());
+    }
+}
@@ -0,0 +1,43 @@
+use crate::crypto::{CryptoError, EncryptionService};
+
+/// Encryption of OAuth tokens at rest
+///
+/// Services take `&dyn TokenCrypto` so sync paths can be exercised in tests
+/// with `PassthroughCrypto` instead of a real key file.
+pub trait TokenCrypto: Send + Sync {
+    /// # Errors
+    /// Returns `CryptoError` if encryption fails
+    fn encrypt(&self, plaintext: &str) -> Result<String, CryptoError>;
+
+    /// # Errors
+    /// Returns `CryptoError` if decryption fails or the encrypted string is invalid
+    fn decrypt(&self, encrypted: &str) -> Result<String, CryptoError>;
+}
+
+impl TokenCrypto for EncryptionService {
+    fn encrypt(&self, plaintext: &str) -> Result<String, CryptoError> {
+        EncryptionService::encrypt(self, plaintext)
+    }
+
+    fn decrypt(&self, encrypted: &str) -> Result<String, CryptoError> {
+        EncryptionService::decrypt(self, encrypted)
+    }
+}
+
+/// No-op `TokenCrypto` that stores tokens as plaintext
+///
+/// Compiled for tests only, so real provider tokens can never be persisted with it.
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PassthroughCrypto;
+
+#[cfg(test)]
+impl TokenCrypto for PassthroughCrypto {
+    fn encrypt(&self, plaintext: &str) -> Result<String, CryptoError> {
+        Ok(plaintext.to_string())
+    }
+
+    fn decrypt(&self, encrypted: &str) -> Result<String, CryptoError> {
+        Ok(encrypted.to_string())
+    }
+}
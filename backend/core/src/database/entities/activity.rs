@@ -24,6 +24,25 @@ pub struct Model {
     pub distance: f32,
     #[sea_orm(column_type = "Float")]
     pub total_elevation_gain: f32,
+    /// Strava has no streams for this activity (e.g. manually entered)
+    pub streams_unavailable: bool,
+    /// Seconds added to listen times to line them up with the activity's GPS clock
+    pub time_offset_seconds: i32,
+    /// Mean heart rate from the streams, `None` until computed or without a heart rate stream
+    #[sea_orm(column_type = "Double", nullable)]
+    pub avg_heart_rate: Option<f64>,
+    pub max_heart_rate: Option<i32>,
+    /// Sum of altitude increases over the streams, in meters
+    #[sea_orm(column_type = "Float", nullable)]
+    pub stream_elevation_gain: Option<f32>,
+    /// Seconds spent moving according to the streams
+    pub stream_moving_time: Option<i32>,
+    /// When the stream summary columns were last computed, `None` before any stream sync
+    pub summary_computed_at: Option<DateTimeWithTimeZone>,
+    /// Stream points fetched from Strava before ingestion downsampling, `None` before any stream sync
+    pub stream_original_size: Option<i32>,
+    /// When the best efforts were last fetched from Strava, `None` until first requested
+    pub best_efforts_fetched_at: Option<DateTimeWithTimeZone>,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
 }
@@ -32,6 +51,8 @@ pub struct Model {
 pub enum Relation {
     #[sea_orm(has_many = "super::activity_stream::Entity")]
     ActivityStream,
+    #[sea_orm(has_many = "super::best_effort::Entity")]
+    BestEffort,
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::UserId",
@@ -48,6 +69,12 @@ impl Related<super::activity_stream::Entity> for Entity {
     }
 }
 
+impl Related<super::best_effort::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BestEffort.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
@@ -26,6 +26,9 @@ pub struct Model {
     pub distance: Option<f32>,
     #[sea_orm(column_type = "Float", nullable)]
     pub temperature: Option<f32>,
+    #[sea_orm(column_type = "Float", nullable)]
+    pub grade: Option<f32>,
+    pub moving: Option<bool>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
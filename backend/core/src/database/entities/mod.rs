@@ -4,7 +4,9 @@ pub mod prelude;
 
 pub mod activity;
 pub mod activity_stream;
+pub mod best_effort;
 pub mod listen;
 pub mod oauth_token;
+pub mod refresh_token;
 pub mod track;
 pub mod user;
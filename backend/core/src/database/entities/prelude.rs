@@ -2,7 +2,9 @@
 
 pub use super::activity::Entity as Activity;
 pub use super::activity_stream::Entity as ActivityStream;
+pub use super::best_effort::Entity as BestEffort;
 pub use super::listen::Entity as Listen;
 pub use super::oauth_token::Entity as OauthToken;
+pub use super::refresh_token::Entity as RefreshToken;
 pub use super::track::Entity as Track;
 pub use super::user::Entity as User;
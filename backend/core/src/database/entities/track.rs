@@ -2,7 +2,7 @@
 
 use sea_orm::entity::prelude::*;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "track")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
@@ -21,6 +21,14 @@ pub struct Model {
     pub album_mbid: Option<String>,
     #[sea_orm(column_type = "Text", nullable)]
     pub lastfm_url: Option<String>,
+    #[sea_orm(column_type = "Float", nullable)]
+    pub bpm: Option<f32>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub spotify_id: Option<String>,
+    #[sea_orm(column_type = "Double", nullable)]
+    pub spotify_match_confidence: Option<f64>,
+    /// When the track was last searched on Spotify, matched or not; `None` until searched
+    pub spotify_searched_at: Option<DateTimeWithTimeZone>,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
 }
@@ -15,6 +15,8 @@ pub struct Model {
     pub password_hash: Option<String>,
     #[sea_orm(column_type = "Text", nullable)]
     pub lastfm_username: Option<String>,
+    pub listen_padding_before_seconds: i32,
+    pub listen_padding_after_seconds: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -25,6 +27,8 @@ pub enum Relation {
     Listen,
     #[sea_orm(has_many = "super::oauth_token::Entity")]
     OauthToken,
+    #[sea_orm(has_many = "super::refresh_token::Entity")]
+    RefreshToken,
 }
 
 impl Related<super::activity::Entity> for Entity {
@@ -45,4 +49,10 @@ impl Related<super::oauth_token::Entity> for Entity {
     }
 }
 
+impl Related<super::refresh_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RefreshToken.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -1,8 +1,16 @@
 pub mod connection;
 pub mod entities;
+pub mod pagination;
 pub mod repositories;
+pub mod retry;
+pub mod transaction;
+pub mod upsert;
 
 // Re-export connection utilities at module root
 pub use connection::*;
 pub use entities::*;
+pub use pagination::*;
 pub use repositories::*;
+pub use retry::*;
+pub use transaction::*;
+pub use upsert::*;
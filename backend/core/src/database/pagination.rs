@@ -0,0 +1,41 @@
+/// Page size used when a list endpoint is asked for a page size of zero
+pub const DEFAULT_PAGE_SIZE: u64 = 50;
+
+/// Largest page size a list endpoint returns when asked for a page size
+///
+/// List endpoints called without a page size return every row in a single page.
+pub const MAX_PAGE_SIZE: u64 = 200;
+
+/// Clamps a requested page size to what list endpoints serve
+///
+/// Zero falls back to `DEFAULT_PAGE_SIZE` and anything above `MAX_PAGE_SIZE`
+/// is capped to it, so every paginated query pages the same way.
+#[must_use]
+pub fn clamp_page_size(requested: u64) -> u64 {
+    match requested {
+        0 => DEFAULT_PAGE_SIZE,
+        size => size.min(MAX_PAGE_SIZE),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_size_above_max_is_clamped() {
+        assert_eq!(clamp_page_size(MAX_PAGE_SIZE + 1), MAX_PAGE_SIZE);
+        assert_eq!(clamp_page_size(u64::MAX), MAX_PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_zero_page_size_uses_default() {
+        assert_eq!(clamp_page_size(0), DEFAULT_PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_page_size_within_bounds_is_kept() {
+        assert_eq!(clamp_page_size(1), 1);
+        assert_eq!(clamp_page_size(MAX_PAGE_SIZE), MAX_PAGE_SIZE);
+    }
+}
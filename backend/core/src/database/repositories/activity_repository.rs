@@ -1,11 +1,17 @@
+use chrono::{DateTime, Utc};
 use sea_orm::{
-    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
-    QueryFilter, QueryOrder,
+    sea_query::{Expr, OnConflict, SimpleExpr},
+    ActiveModelTrait,
+    ActiveValue::Set,
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+    QueryTrait, Select, UpdateMany,
 };
 use uuid::Uuid;
 
-use crate::database::{activity, entities::prelude::Activity};
-use crate::models::CreateActivityDto;
+use crate::database::{
+    activity, clamp_page_size, entities::prelude::Activity, excluded, upsert_one,
+};
+use crate::models::{ActivityCategory, ActivitySummary, ActivityTypeAliases, CreateActivityDto};
 
 /// Creates a new activity from a DTO
 ///
@@ -23,6 +29,10 @@ pub async fn create_activity(
 /// Creates or updates an activity based on `external_id` (Strava ID)
 /// If an activity with the same `external_id` exists for this user, it updates it
 ///
+/// Runs as a single `INSERT ... ON CONFLICT`, so concurrent syncs of the same
+/// activity never race to insert it. An activity stored for another user is left
+/// untouched and reported as `DbErr::RecordNotInserted`.
+///
 /// # Errors
 ///
 /// Returns an error if database operation fails
@@ -30,31 +40,37 @@ pub async fn upsert_activity(
     db: &DatabaseConnection,
     dto: CreateActivityDto,
 ) -> Result<activity::Model, DbErr> {
-    // Check if activity already exists
-    let existing = get_activity_by_external_id(db, dto.user_id, dto.external_id).await?;
-
-    match existing {
-        Some(existing_activity) => {
-            // Update existing activity
-            let mut active_model: activity::ActiveModel = existing_activity.into();
-            active_model.name = Set(dto.name);
-            active_model.description = Set(dto.description);
-            active_model.r#type = Set(dto.activity_type);
-            active_model.start_time = Set(dto.start_time);
-            active_model.moving_time = Set(dto.moving_time);
-            active_model.elapsed_time = Set(dto.elapsed_time);
-            active_model.timezone = Set(dto.timezone);
-            active_model.distance = Set(dto.distance);
-            active_model.total_elevation_gain = Set(dto.total_elevation_gain);
-            active_model.updated_at = Set(chrono::Utc::now().into());
-
-            active_model.update(db).await
-        }
-        None => {
-            // Create new activity
-            create_activity(db, dto).await
-        }
-    }
+    upsert_one(db, dto.into_active_model(), activity_upsert_conflict()).await
+}
+
+/// Conflict on `external_id` refreshing the Strava fields of the user's activity
+///
+/// Fields set locally (time offset, stored summary) are kept.
+fn activity_upsert_conflict() -> OnConflict {
+    OnConflict::column(activity::Column::ExternalId)
+        .update_columns([
+            activity::Column::Name,
+            activity::Column::Description,
+            activity::Column::Type,
+            activity::Column::StartTime,
+            activity::Column::MovingTime,
+            activity::Column::ElapsedTime,
+            activity::Column::Timezone,
+            activity::Column::Distance,
+            activity::Column::TotalElevationGain,
+            activity::Column::UpdatedAt,
+        ])
+        // Only ever raise the flag: stream sync may have found none for a non-manual activity.
+        // A forced stream sync clears it once Strava has streams again.
+        .value(
+            activity::Column::StreamsUnavailable,
+            SimpleExpr::from(Expr::col((Activity, activity::Column::StreamsUnavailable)))
+                .or(excluded(activity::Column::StreamsUnavailable)),
+        )
+        .action_and_where(
+            Expr::col((Activity, activity::Column::UserId)).eq(excluded(activity::Column::UserId)),
+        )
+        .to_owned()
 }
 
 /// Retrieves an activity by `external_id` (Strava ID) for a specific user
@@ -98,13 +114,230 @@ pub async fn get_activities_by_user(
     db: &DatabaseConnection,
     user_id: Uuid,
 ) -> Result<Vec<activity::Model>, DbErr> {
-    Activity::find()
-        .filter(activity::Column::UserId.eq(user_id))
+    activities_by_user_query(user_id, None, None)
         .order_by_desc(activity::Column::StartTime)
         .all(db)
         .await
 }
 
+/// Retrieves one page of a user's activities, ordered by start time (descending)
+///
+/// # Arguments
+///
+/// * `activity_type` - Optional exact match on the activity type (e.g. `Run`)
+/// * `category` - Optional sport category the activity type must classify into
+/// * `page` - Zero-based page index, ignored without a page size
+/// * `per_page` - Page size, clamped with `clamp_page_size`; `None` returns every
+///   activity in a single page
+///
+/// # Errors
+///
+/// Returns an error if database query fails
+pub async fn get_activities_page_by_user(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    activity_type: Option<&str>,
+    category: Option<ActivityCategory>,
+    page: u64,
+    per_page: Option<u64>,
+) -> Result<Vec<activity::Model>, DbErr> {
+    let query = activities_by_user_query(user_id, activity_type, category)
+        .order_by_desc(activity::Column::StartTime)
+        .order_by_asc(activity::Column::Id);
+
+    match per_page {
+        Some(per_page) => {
+            query
+                .paginate(db, clamp_page_size(per_page))
+                .fetch_page(page)
+                .await
+        }
+        None => query.all(db).await,
+    }
+}
+
+/// Counts a user's activities without loading them
+///
+/// Uses the same filters as `get_activities_page_by_user`, so the total matches
+/// the rows that can be paged through.
+///
+/// # Errors
+///
+/// Returns an error if database query fails
+pub async fn count_activities_by_user(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    activity_type: Option<&str>,
+    category: Option<ActivityCategory>,
+) -> Result<u64, DbErr> {
+    activities_by_user_query(user_id, activity_type, category)
+        .count(db)
+        .await
+}
+
+fn activities_by_user_query(
+    user_id: Uuid,
+    activity_type: Option<&str>,
+    category: Option<ActivityCategory>,
+) -> Select<Activity> {
+    Activity::find()
+        .filter(activity::Column::UserId.eq(user_id))
+        .apply_if(activity_type, |query, activity_type| {
+            query.filter(activity::Column::Type.eq(activity_type))
+        })
+        .apply_if(category, |query, category| {
+            query.filter(category_filter(&ActivityTypeAliases::from_env(), category))
+        })
+}
+
+/// Matches the activity types classified into `category`
+///
+/// `Other` also holds every unknown type, so it excludes the known types
+/// instead of listing its own.
+fn category_filter(aliases: &ActivityTypeAliases, category: ActivityCategory) -> SimpleExpr {
+    match category {
+        ActivityCategory::Other => activity::Column::Type.is_not_in(
+            aliases
+                .known_types()
+                .into_iter()
+                .filter(|raw| aliases.classify(raw) != ActivityCategory::Other),
+        ),
+        _ => activity::Column::Type.is_in(aliases.types_in(category)),
+    }
+}
+
+/// Retrieves a user's activities started within `[start, end)`, ordered by start time
+///
+/// # Errors
+///
+/// Returns an error if database query fails
+pub async fn get_activities_by_user_in_range(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<activity::Model>, DbErr> {
+    activities_in_range_query(user_id, start, end).all(db).await
+}
+
+fn activities_in_range_query(
+    user_id: Uuid,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Select<Activity> {
+    activities_by_user_query(user_id, None, None)
+        .filter(activity::Column::StartTime.gte(start))
+        .filter(activity::Column::StartTime.lt(end))
+        .order_by_asc(activity::Column::StartTime)
+}
+
+/// Retrieves the start time of a user's most recent activity, the incremental sync cursor
+///
+/// # Errors
+///
+/// Returns an error if database query fails
+pub async fn get_latest_activity_start_by_user(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+) -> Result<Option<DateTime<Utc>>, DbErr> {
+    let latest = activities_by_user_query(user_id, None, None)
+        .order_by_desc(activity::Column::StartTime)
+        .one(db)
+        .await?;
+    Ok(latest.map(|activity| activity.start_time.with_timezone(&Utc)))
+}
+
+/// Sets whether an activity has no Strava streams, so stream syncs skip it
+///
+/// # Errors
+///
+/// Returns an error if database update fails
+pub async fn set_activity_streams_unavailable(
+    db: &DatabaseConnection,
+    id: Uuid,
+    unavailable: bool,
+) -> Result<(), DbErr> {
+    Activity::update_many()
+        .col_expr(
+            activity::Column::StreamsUnavailable,
+            Expr::value(unavailable),
+        )
+        .col_expr(
+            activity::Column::UpdatedAt,
+            Expr::value(chrono::Utc::now().fixed_offset()),
+        )
+        .filter(activity::Column::Id.eq(id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Stores the summary computed from an activity's streams in its summary columns
+///
+/// # Errors
+///
+/// Returns an error if database update fails
+pub async fn store_activity_summary(
+    db: &DatabaseConnection,
+    id: Uuid,
+    summary: &ActivitySummary,
+) -> Result<(), DbErr> {
+    activity_summary_update(id, summary).exec(db).await?;
+    Ok(())
+}
+
+fn activity_summary_update(id: Uuid, summary: &ActivitySummary) -> UpdateMany<Activity> {
+    Activity::update_many()
+        .col_expr(
+            activity::Column::AvgHeartRate,
+            Expr::value(summary.avg_heart_rate),
+        )
+        .col_expr(
+            activity::Column::MaxHeartRate,
+            Expr::value(summary.max_heart_rate),
+        )
+        .col_expr(
+            activity::Column::StreamElevationGain,
+            Expr::value(summary.elevation_gain),
+        )
+        .col_expr(
+            activity::Column::StreamMovingTime,
+            Expr::value(summary.moving_time_seconds),
+        )
+        .col_expr(
+            activity::Column::SummaryComputedAt,
+            Expr::value(summary.computed_at.map(|at| at.fixed_offset())),
+        )
+        .col_expr(
+            activity::Column::UpdatedAt,
+            Expr::value(chrono::Utc::now().fixed_offset()),
+        )
+        .filter(activity::Column::Id.eq(id))
+}
+
+/// Stores the offset applied to listen times when matching music to an activity
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Database update fails
+/// - Activity not found
+pub async fn update_activity_time_offset(
+    db: &DatabaseConnection,
+    id: Uuid,
+    time_offset_seconds: i32,
+) -> Result<activity::Model, DbErr> {
+    let activity = get_activity_by_id(db, id)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Activity not found".into()))?;
+
+    let mut active_model: activity::ActiveModel = activity.into();
+    active_model.time_offset_seconds = Set(time_offset_seconds);
+    active_model.updated_at = Set(chrono::Utc::now().into());
+    active_model.update(db).await
+}
+
 /// Deletes an activity by its internal UUID
 ///
 /// # Errors
@@ -124,3 +357,244 @@ pub async fn delete_activity(db: &DatabaseConnection, id: Uuid) -> Result<(), Db
         None => Err(DbErr::RecordNotFound("Activity not found".into())),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::upsert::upsert_many_query;
+    use crate::test_support::make_activity;
+    use sea_orm::{DbBackend, MockDatabase, Value};
+    use std::collections::BTreeMap;
+
+    fn make_dto() -> CreateActivityDto {
+        CreateActivityDto {
+            user_id: Uuid::new_v4(),
+            external_id: 42,
+            name: "Morning Run".to_string(),
+            description: None,
+            activity_type: "Run".to_string(),
+            start_time: DateTime::from_timestamp(1_700_000_000, 0)
+                .unwrap()
+                .fixed_offset(),
+            moving_time: 1800,
+            elapsed_time: 1900,
+            timezone: "UTC".to_string(),
+            distance: 5000.0,
+            total_elevation_gain: 40.0,
+            streams_unavailable: false,
+        }
+    }
+
+    #[test]
+    fn test_upsert_updates_the_users_activity_on_external_id_conflict() {
+        let sql = upsert_many_query(
+            vec![make_dto().into_active_model()],
+            activity_upsert_conflict(),
+        )
+        .build(DbBackend::Postgres)
+        .to_string();
+
+        assert!(
+            sql.contains(r#"ON CONFLICT ("external_id") DO UPDATE SET "name" = "excluded"."name""#),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(r#""updated_at" = "excluded"."updated_at""#),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(r#""streams_unavailable" = "activity"."streams_unavailable" OR "excluded"."streams_unavailable""#),
+            "{sql}"
+        );
+        // Another user's activity with the same Strava ID is never overwritten
+        assert!(
+            sql.ends_with(r#"WHERE "activity"."user_id" = "excluded"."user_id""#),
+            "{sql}"
+        );
+        // Locally set fields survive a re-sync
+        assert!(!sql.contains(r#""time_offset_seconds" ="#), "{sql}");
+        assert!(!sql.contains(r#""created_at" ="#), "{sql}");
+    }
+
+    #[test]
+    fn test_activity_type_filter_is_optional() {
+        let user_id = Uuid::new_v4();
+
+        let unfiltered = activities_by_user_query(user_id, None, None)
+            .build(DbBackend::Postgres)
+            .to_string();
+        let filtered = activities_by_user_query(user_id, Some("Run"), None)
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        assert!(!unfiltered.contains(r#""activity"."type""#), "{unfiltered}");
+        assert!(
+            filtered.contains(r#""activity"."type" = 'Run'"#),
+            "{filtered}"
+        );
+    }
+
+    fn activity_of_type(user_id: Uuid, activity_type: &str) -> activity::Model {
+        activity::Model {
+            user_id,
+            r#type: activity_type.to_string(),
+            ..make_activity()
+        }
+    }
+
+    /// Row returned by the `COUNT(*)` query of `PaginatorTrait::count`
+    fn count_row(count: usize) -> BTreeMap<&'static str, Value> {
+        BTreeMap::from([("num_items", i64::try_from(count).unwrap().into())])
+    }
+
+    fn where_clause(sql: &str) -> &str {
+        let start = sql.find(" WHERE ").expect(sql);
+        let end = sql[start..]
+            .find(" ORDER BY ")
+            .map_or(sql.len(), |end| start + end);
+        &sql[start..end]
+    }
+
+    #[tokio::test]
+    async fn test_counts_match_listed_rows_across_filters() {
+        let user_id = Uuid::new_v4();
+        let run = activity_of_type(user_id, "Run");
+        let trail_run = activity_of_type(user_id, "TrailRun");
+        let ride = activity_of_type(user_id, "Ride");
+
+        let cases = [
+            (
+                None,
+                None,
+                vec![run.clone(), trail_run.clone(), ride.clone()],
+            ),
+            (Some("Run"), None, vec![run.clone()]),
+            (
+                None,
+                Some(ActivityCategory::Run),
+                vec![run.clone(), trail_run],
+            ),
+            (Some("Ride"), Some(ActivityCategory::Ride), vec![ride]),
+            (Some("Swim"), None, Vec::new()),
+        ];
+        for (activity_type, category, rows) in cases {
+            let db = MockDatabase::new(DbBackend::Postgres)
+                .append_query_results([rows.clone()])
+                .append_query_results([[count_row(rows.len())]])
+                .into_connection();
+
+            let listed =
+                get_activities_page_by_user(&db, user_id, activity_type, category, 0, None)
+                    .await
+                    .unwrap();
+            let total = count_activities_by_user(&db, user_id, activity_type, category)
+                .await
+                .unwrap();
+
+            assert_eq!(listed, rows);
+            assert_eq!(total, rows.len() as u64, "{activity_type:?} {category:?}");
+            // The count runs on the listed rows' filters, not on a query of its own
+            let log = db.into_transaction_log();
+            let list_sql = &log[0].statements()[0].sql;
+            let count_sql = &log[1].statements()[0].sql;
+            assert!(count_sql.starts_with("SELECT COUNT(*)"), "{count_sql}");
+            assert!(
+                count_sql.contains(where_clause(list_sql)),
+                "{list_sql}\n{count_sql}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_category_filter_lists_the_category_types() {
+        let sql = Activity::find()
+            .filter(category_filter(
+                &ActivityTypeAliases::default(),
+                ActivityCategory::Run,
+            ))
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        assert!(
+            sql.contains(r#""activity"."type" IN ('Run', 'TrailRun', 'VirtualRun')"#),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_other_category_filter_excludes_known_types() {
+        let aliases = ActivityTypeAliases::new(std::collections::HashMap::from([(
+            "Velomobile".to_string(),
+            ActivityCategory::Other,
+        )]));
+
+        let sql = Activity::find()
+            .filter(category_filter(&aliases, ActivityCategory::Other))
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        assert!(sql.contains(r#""activity"."type" NOT IN ("#), "{sql}");
+        assert!(sql.contains("'Run'"), "{sql}");
+        assert!(sql.contains("'Swim'"), "{sql}");
+        // Aliased to other, so it must stay matched
+        assert!(!sql.contains("'Velomobile'"), "{sql}");
+    }
+
+    #[test]
+    fn test_summary_update_writes_every_summary_column() {
+        let id = Uuid::new_v4();
+        let summary = ActivitySummary {
+            avg_heart_rate: Some(142.5),
+            max_heart_rate: Some(171),
+            elevation_gain: Some(86.0),
+            moving_time_seconds: Some(1800),
+            computed_at: DateTime::from_timestamp(1_762_128_000, 0),
+        };
+
+        let sql = activity_summary_update(id, &summary)
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        assert!(sql.starts_with(r#"UPDATE "activity" SET"#), "{sql}");
+        assert!(sql.contains(r#""avg_heart_rate" = 142.5"#), "{sql}");
+        assert!(sql.contains(r#""max_heart_rate" = 171"#), "{sql}");
+        assert!(sql.contains(r#""stream_elevation_gain" = 86"#), "{sql}");
+        assert!(sql.contains(r#""stream_moving_time" = 1800"#), "{sql}");
+        assert!(
+            sql.contains(r#""summary_computed_at" = '2025-11-03 00:00:00"#),
+            "{sql}"
+        );
+        assert!(
+            sql.ends_with(&format!(r#"WHERE "activity"."id" = '{id}'"#)),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_range_query_selects_activities_started_in_window() {
+        let user_id = Uuid::new_v4();
+        let start = DateTime::from_timestamp(1_762_128_000, 0).unwrap(); // 2025-11-03 00:00 UTC
+        let end = DateTime::from_timestamp(1_762_732_800, 0).unwrap(); // 2025-11-10 00:00 UTC
+
+        let sql = activities_in_range_query(user_id, start, end)
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        assert!(
+            sql.contains(&format!(r#""activity"."user_id" = '{user_id}'"#)),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(r#""activity"."start_time" >= '2025-11-03 00:00:00"#),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(r#""activity"."start_time" < '2025-11-10 00:00:00"#),
+            "{sql}"
+        );
+        assert!(
+            sql.ends_with(r#"ORDER BY "activity"."start_time" ASC"#),
+            "{sql}"
+        );
+    }
+}
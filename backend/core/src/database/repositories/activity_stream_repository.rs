@@ -1,31 +1,151 @@
 use sea_orm::{
-    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, TransactionTrait,
+    prelude::DateTimeWithTimeZone,
+    sea_query::{Alias, Expr, Query, SelectStatement, SimpleExpr},
+    ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    FromQueryResult, Iterable, JoinType, QueryFilter, QueryOrder, QuerySelect, RelationTrait,
+    Select, TransactionTrait,
 };
+use serde::Serialize;
 use uuid::Uuid;
 
-use crate::database::activity_stream::{ActiveModel, Model};
+use crate::database::activity_stream::{self, ActiveModel, Model};
 use crate::database::entities::prelude::ActivityStream;
 
-/// Creates or updates activity streams in batch
+/// Most bind parameters Postgres accepts in a single statement
+const POSTGRES_MAX_BIND_PARAMETERS: usize = 65_535;
+
+/// Largest number of stream points that fit in one insert statement
+#[must_use]
+pub fn max_stream_insert_batch_size() -> usize {
+    POSTGRES_MAX_BIND_PARAMETERS / activity_stream::Column::iter().count()
+}
+
+/// Replaces an activity's stored stream points, all in one transaction
+///
+/// Points from an earlier sync are deleted first, so re-syncing at another
+/// downsampling step never leaves stale rows behind. New points are inserted in
+/// batches of `batch_size`, capped at `max_stream_insert_batch_size` so long
+/// activities never exceed the Postgres bind parameter limit. `original_size`,
+/// the point count before ingestion downsampling, is stored on the activity.
+/// Either every change is applied or none is.
+///
 /// # Errors
+///
 /// Returns an error if database operation fails
-pub async fn batch_upsert_activity_streams(
+pub async fn replace_activity_streams(
     db: &DatabaseConnection,
+    activity_id: Uuid,
     models: Vec<ActiveModel>,
+    original_size: usize,
+    batch_size: usize,
 ) -> Result<(), DbErr> {
-    const CHUNK_SIZE: usize = 5000;
+    let batch_size = batch_size.clamp(1, max_stream_insert_batch_size());
 
     let transaction = db.begin().await?;
-    for chunk in models.chunks(CHUNK_SIZE) {
+    ActivityStream::delete_many()
+        .filter(activity_stream::Column::ActivityId.eq(activity_id))
+        .exec(&transaction)
+        .await?;
+    for chunk in models.chunks(batch_size) {
         ActivityStream::insert_many(chunk.to_vec())
-            .exec(&transaction)
+            .exec_without_returning(&transaction)
             .await?;
     }
+    store_original_size(&transaction, activity_id, original_size).await?;
     transaction.commit().await?;
 
     Ok(())
 }
 
+/// Appends the stream points recorded after the latest stored one, in one transaction
+///
+/// Meant for activities still in progress: points at or before the latest stored
+/// time are skipped, so points already stored are never touched. Points are
+/// inserted in batches of `batch_size`, like `replace_activity_streams`, and
+/// `original_size` is stored on the activity when any point is new.
+///
+/// # Returns
+///
+/// The number of points appended
+///
+/// # Errors
+///
+/// Returns an error if database operation fails
+pub async fn append_activity_streams(
+    db: &DatabaseConnection,
+    activity_id: Uuid,
+    models: Vec<ActiveModel>,
+    original_size: usize,
+    batch_size: usize,
+) -> Result<usize, DbErr> {
+    let batch_size = batch_size.clamp(1, max_stream_insert_batch_size());
+
+    let transaction = db.begin().await?;
+    let latest = latest_stream_time_query(activity_id)
+        .into_tuple::<Option<DateTimeWithTimeZone>>()
+        .one(&transaction)
+        .await?
+        .flatten();
+    let new_points = points_after(models, latest);
+    for chunk in new_points.chunks(batch_size) {
+        ActivityStream::insert_many(chunk.to_vec())
+            .exec_without_returning(&transaction)
+            .await?;
+    }
+    // Strava's full stream only grows with new points, so the stored size is current otherwise
+    if !new_points.is_empty() {
+        store_original_size(&transaction, activity_id, original_size).await?;
+    }
+    transaction.commit().await?;
+
+    Ok(new_points.len())
+}
+
+/// Records on the activity how many stream points Strava returned before downsampling
+async fn store_original_size<C: ConnectionTrait>(
+    db: &C,
+    activity_id: Uuid,
+    original_size: usize,
+) -> Result<(), DbErr> {
+    use crate::database::activity;
+
+    activity::Entity::update_many()
+        .col_expr(
+            activity::Column::StreamOriginalSize,
+            Expr::value(i32::try_from(original_size).unwrap_or(i32::MAX)),
+        )
+        .filter(activity::Column::Id.eq(activity_id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+fn latest_stream_time_query(activity_id: Uuid) -> Select<ActivityStream> {
+    ActivityStream::find()
+        .select_only()
+        .column_as(activity_stream::Column::Time.max(), "latest_time")
+        .filter(activity_stream::Column::ActivityId.eq(activity_id))
+}
+
+/// Keeps the points strictly after `latest`, or all of them when nothing is stored yet
+fn points_after(
+    models: Vec<ActiveModel>,
+    latest: Option<DateTimeWithTimeZone>,
+) -> Vec<ActiveModel> {
+    let Some(latest) = latest else {
+        return models;
+    };
+
+    models
+        .into_iter()
+        .filter(|model| match &model.time {
+            ActiveValue::Set(time) | ActiveValue::Unchanged(time) => *time > latest,
+            ActiveValue::NotSet => false,
+        })
+        .collect()
+}
+
 /// Retrieves all activity streams for a specific activity, ordered by time
 ///
 /// # Errors
@@ -43,3 +163,588 @@ pub async fn get_activity_streams(
         .all(db)
         .await
 }
+
+/// Retrieves activity streams for an activity within `[start, end]` (both inclusive), ordered by time
+///
+/// Uses the `(activity_id, time)` primary key so only the requested window is read,
+/// instead of loading every point of a long activity and filtering in memory.
+///
+/// # Errors
+///
+/// Returns an error if database query fails
+pub async fn get_activity_streams_in_range(
+    db: &DatabaseConnection,
+    activity_id: Uuid,
+    start: DateTimeWithTimeZone,
+    end: DateTimeWithTimeZone,
+) -> Result<Vec<Model>, DbErr> {
+    activity_streams_in_range_query(activity_id, start, end)
+        .all(db)
+        .await
+}
+
+fn activity_streams_in_range_query(
+    activity_id: Uuid,
+    start: DateTimeWithTimeZone,
+    end: DateTimeWithTimeZone,
+) -> Select<ActivityStream> {
+    use crate::database::activity_stream;
+
+    ActivityStream::find()
+        .filter(activity_stream::Column::ActivityId.eq(activity_id))
+        .filter(activity_stream::Column::Time.gte(start))
+        .filter(activity_stream::Column::Time.lte(end))
+        .order_by_asc(activity_stream::Column::Time)
+}
+
+/// Retrieves the distance readings surrounding `at`, ordered by time
+///
+/// Returns the last point at or before `at` and the first one at or after it,
+/// both with a distance, so a scrubber reads two rows instead of the whole stream.
+/// Either is missing past the ends of the stream.
+///
+/// # Errors
+///
+/// Returns an error if database query fails
+pub async fn get_distance_points_around(
+    db: &DatabaseConnection,
+    activity_id: Uuid,
+    at: DateTimeWithTimeZone,
+) -> Result<Vec<Model>, DbErr> {
+    let (before, after) = distance_points_around_queries(activity_id, at);
+    let before = before.one(db).await?;
+    let after = after.one(db).await?;
+
+    let mut points: Vec<Model> = before.into_iter().collect();
+    if let Some(after) = after.filter(|after| points.first() != Some(after)) {
+        points.push(after);
+    }
+    Ok(points)
+}
+
+fn distance_points_around_queries(
+    activity_id: Uuid,
+    at: DateTimeWithTimeZone,
+) -> (Select<ActivityStream>, Select<ActivityStream>) {
+    let with_distance = || {
+        ActivityStream::find()
+            .filter(activity_stream::Column::ActivityId.eq(activity_id))
+            .filter(activity_stream::Column::Distance.is_not_null())
+    };
+
+    (
+        with_distance()
+            .filter(activity_stream::Column::Time.lte(at))
+            .order_by_desc(activity_stream::Column::Time),
+        with_distance()
+            .filter(activity_stream::Column::Time.gte(at))
+            .order_by_asc(activity_stream::Column::Time),
+    )
+}
+
+/// Latitude/longitude extent of each of a user's activities with GPS points
+///
+/// Rows are `(activity_id, min_lat, max_lat, min_lng, max_lng)`. Computed in the
+/// database, so only one row per activity is transferred.
+///
+/// # Errors
+///
+/// Returns an error if database query fails
+pub async fn get_route_bounds_by_user(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+) -> Result<Vec<(Uuid, f64, f64, f64, f64)>, DbErr> {
+    route_bounds_query(user_id)
+        .into_tuple::<(Uuid, f64, f64, f64, f64)>()
+        .all(db)
+        .await
+}
+
+fn route_bounds_query(user_id: Uuid) -> Select<ActivityStream> {
+    use crate::database::{activity, activity_stream::Column};
+
+    ActivityStream::find()
+        .select_only()
+        .column(Column::ActivityId)
+        .column_as(Column::Latitude.min(), "min_lat")
+        .column_as(Column::Latitude.max(), "max_lat")
+        .column_as(Column::Longitude.min(), "min_lng")
+        .column_as(Column::Longitude.max(), "max_lng")
+        .join(
+            JoinType::InnerJoin,
+            activity_stream::Relation::Activity.def(),
+        )
+        .filter(activity::Column::UserId.eq(user_id))
+        .filter(Column::Latitude.is_not_null())
+        .filter(Column::Longitude.is_not_null())
+        .group_by(Column::ActivityId)
+}
+
+/// Which stream channels hold at least one value for an activity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, FromQueryResult)]
+pub struct StreamChannels {
+    pub has_hr: bool,
+    pub has_watts: bool,
+    pub has_latlng: bool,
+    pub has_cadence: bool,
+    pub has_altitude: bool,
+    pub has_temperature: bool,
+}
+
+/// Reports which stream channels have data for an activity
+///
+/// Each channel is an `EXISTS` check, so no stream row is transferred and the
+/// scan stops at the first point carrying a value.
+///
+/// # Errors
+///
+/// Returns an error if database query fails
+pub async fn get_stream_channels(
+    db: &DatabaseConnection,
+    activity_id: Uuid,
+) -> Result<StreamChannels, DbErr> {
+    let statement = db
+        .get_database_backend()
+        .build(&stream_channels_query(activity_id));
+    StreamChannels::find_by_statement(statement)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Stream channel query returned no row".to_string()))
+}
+
+fn stream_channels_query(activity_id: Uuid) -> SelectStatement {
+    use crate::database::activity_stream::Column;
+
+    let has_values = |condition: SimpleExpr| {
+        Expr::exists(
+            Query::select()
+                .expr(Expr::val(1))
+                .from(ActivityStream)
+                .and_where(Column::ActivityId.eq(activity_id))
+                .and_where(condition)
+                .to_owned(),
+        )
+    };
+
+    Query::select()
+        .expr_as(
+            has_values(Column::HeartRate.is_not_null()),
+            Alias::new("has_hr"),
+        )
+        .expr_as(
+            has_values(Column::Watts.is_not_null()),
+            Alias::new("has_watts"),
+        )
+        .expr_as(
+            has_values(
+                Column::Latitude
+                    .is_not_null()
+                    .and(Column::Longitude.is_not_null()),
+            ),
+            Alias::new("has_latlng"),
+        )
+        .expr_as(
+            has_values(Column::Cadence.is_not_null()),
+            Alias::new("has_cadence"),
+        )
+        .expr_as(
+            has_values(Column::Altitude.is_not_null()),
+            Alias::new("has_altitude"),
+        )
+        .expr_as(
+            has_values(Column::Temperature.is_not_null()),
+            Alias::new("has_temperature"),
+        )
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_activity, insert_user, test_database};
+    use std::collections::BTreeMap;
+
+    use chrono::DateTime;
+    use sea_orm::{ActiveModelTrait, DbBackend, MockDatabase, MockExecResult, QueryTrait, Value};
+
+    #[test]
+    fn test_range_query_bounds_are_inclusive() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap().into();
+        let end = DateTime::from_timestamp(1_700_000_600, 0).unwrap().into();
+
+        let sql = activity_streams_in_range_query(Uuid::new_v4(), start, end)
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        // Must match the in-memory `time >= start && time <= end` filter it replaces
+        assert!(sql.contains(r#""activity_stream"."time" >= "#), "{sql}");
+        assert!(sql.contains(r#""activity_stream"."time" <= "#), "{sql}");
+        assert!(
+            sql.ends_with(r#"ORDER BY "activity_stream"."time" ASC"#),
+            "{sql}"
+        );
+    }
+
+    /// A point `seconds` into the activity without any reading
+    fn bare_point(activity_id: Uuid, seconds: i64) -> Model {
+        Model {
+            activity_id,
+            time: DateTime::from_timestamp(1_700_000_000 + seconds, 0)
+                .unwrap()
+                .into(),
+            latitude: None,
+            longitude: None,
+            altitude: None,
+            heart_rate: None,
+            cadence: None,
+            watts: None,
+            velocity: None,
+            distance: None,
+            temperature: None,
+            grade: None,
+            moving: None,
+        }
+    }
+
+    async fn insert_points(db: &DatabaseConnection, points: Vec<Model>) {
+        ActivityStream::insert_many(points.into_iter().map(ActiveModel::from))
+            .exec_without_returning(db)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_distance_points_around_read_the_nearest_reading_on_each_side() {
+        let Some(db) = test_database().await else {
+            return;
+        };
+        let user = insert_user(&db).await;
+        let run = insert_activity(&db, user.id).await;
+        let with_distance = |seconds, distance| Model {
+            distance: Some(distance),
+            ..bare_point(run.id, seconds)
+        };
+        insert_points(
+            &db,
+            vec![
+                with_distance(0, 0.0),
+                // No distance reading, skipped
+                bare_point(run.id, 10),
+                with_distance(20, 100.0),
+                with_distance(30, 200.0),
+            ],
+        )
+        .await;
+        let distances_around = |seconds| {
+            let db = &db;
+            let at = DateTime::from_timestamp(1_700_000_000 + seconds, 0)
+                .unwrap()
+                .into();
+            async move {
+                get_distance_points_around(db, run.id, at)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|point| point.distance)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(distances_around(15).await, vec![Some(0.0), Some(100.0)]);
+        // A reading at that exact moment is returned once
+        assert_eq!(distances_around(20).await, vec![Some(100.0)]);
+        assert_eq!(distances_around(-5).await, vec![Some(0.0)]);
+        assert_eq!(distances_around(45).await, vec![Some(200.0)]);
+    }
+
+    #[tokio::test]
+    async fn test_route_bounds_cover_the_gps_points_of_each_of_the_users_activities() {
+        let Some(db) = test_database().await else {
+            return;
+        };
+        let user = insert_user(&db).await;
+        let other_user = insert_user(&db).await;
+        let run = insert_activity(&db, user.id).await;
+        let treadmill_run = insert_activity(&db, user.id).await;
+        let other_run = insert_activity(&db, other_user.id).await;
+        let at = |activity_id, seconds, latitude, longitude| Model {
+            latitude: Some(latitude),
+            longitude: Some(longitude),
+            ..bare_point(activity_id, seconds)
+        };
+        insert_points(
+            &db,
+            vec![
+                at(run.id, 0, 48.85, 2.35),
+                at(run.id, 10, 48.87, 2.29),
+                // Points without a GPS fix leave the bounds alone
+                Model {
+                    latitude: Some(0.0),
+                    ..bare_point(run.id, 20)
+                },
+                bare_point(treadmill_run.id, 0),
+                at(other_run.id, 0, 45.76, 4.83),
+            ],
+        )
+        .await;
+
+        let bounds = get_route_bounds_by_user(&db, user.id).await.unwrap();
+
+        assert_eq!(bounds, vec![(run.id, 48.85, 48.87, 2.29, 2.35)]);
+    }
+
+    #[test]
+    fn test_stream_channels_query_uses_exists_per_channel() {
+        let activity_id = Uuid::new_v4();
+        let sql = DbBackend::Postgres
+            .build(&stream_channels_query(activity_id))
+            .to_string();
+
+        assert_eq!(sql.matches("EXISTS").count(), 6, "{sql}");
+        assert!(sql.contains(r#"SELECT 1 FROM "activity_stream""#), "{sql}");
+        assert_eq!(sql.matches(&activity_id.to_string()).count(), 6, "{sql}");
+        assert!(
+            sql.contains(r#""watts" IS NOT NULL) AS "has_watts""#),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(r#""activity_stream"."longitude" IS NOT NULL"#),
+            "{sql}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_channels_for_activity_without_watts_and_temperature() {
+        let row = BTreeMap::from([
+            ("has_hr", Value::Bool(Some(true))),
+            ("has_watts", Value::Bool(Some(false))),
+            ("has_latlng", Value::Bool(Some(true))),
+            ("has_cadence", Value::Bool(Some(true))),
+            ("has_altitude", Value::Bool(Some(true))),
+            ("has_temperature", Value::Bool(Some(false))),
+        ]);
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![row]])
+            .into_connection();
+
+        let channels = get_stream_channels(&db, Uuid::new_v4()).await.unwrap();
+
+        assert_eq!(
+            channels,
+            StreamChannels {
+                has_hr: true,
+                has_watts: false,
+                has_latlng: true,
+                has_cadence: true,
+                has_altitude: true,
+                has_temperature: false,
+            }
+        );
+        let log = format!("{:?}", db.into_transaction_log());
+        assert_eq!(log.matches("EXISTS").count(), 6, "{log}");
+    }
+
+    fn make_points(count: usize) -> Vec<ActiveModel> {
+        let activity_id = Uuid::new_v4();
+        (0..count)
+            .map(|i| {
+                let time = DateTime::from_timestamp(1_700_000_000 + i64::try_from(i).unwrap(), 0)
+                    .unwrap()
+                    .into();
+                ActiveModel::from(Model {
+                    activity_id,
+                    time,
+                    latitude: Some(48.85),
+                    longitude: Some(2.35),
+                    altitude: Some(35.0),
+                    heart_rate: Some(150),
+                    cadence: Some(170),
+                    watts: None,
+                    velocity: Some(3.2),
+                    distance: None,
+                    temperature: None,
+                    grade: None,
+                    moving: Some(true),
+                })
+                .reset_all()
+            })
+            .collect()
+    }
+
+    fn exec_results(batches: usize, rows: u64) -> Vec<MockExecResult> {
+        (0..batches)
+            .map(|_| MockExecResult {
+                last_insert_id: 0,
+                rows_affected: rows,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_max_batch_fits_postgres_parameter_limit() {
+        let columns = activity_stream::Column::iter().count();
+
+        assert_eq!(columns, 13);
+        assert!(max_stream_insert_batch_size() * columns <= POSTGRES_MAX_BIND_PARAMETERS);
+    }
+
+    /// Results of a replace: the delete, `batches` inserts and the original size update
+    fn replace_exec_results(batches: usize, rows: u64) -> Vec<MockExecResult> {
+        let mut results = exec_results(1, 0);
+        results.extend(exec_results(batches, rows));
+        results.extend(exec_results(1, 1));
+        results
+    }
+
+    #[tokio::test]
+    async fn test_large_activity_is_inserted_in_batches() {
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_exec_results(replace_exec_results(10, 2000))
+            .into_connection();
+
+        replace_activity_streams(&db, Uuid::new_v4(), make_points(20_000), 20_000, 2000)
+            .await
+            .unwrap();
+
+        let log = format!("{:?}", db.into_transaction_log());
+        assert_eq!(log.matches("INSERT INTO").count(), 10);
+        assert!(log.contains("COMMIT"));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_batch_is_capped_to_parameter_limit() {
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_exec_results(replace_exec_results(4, 5041))
+            .into_connection();
+
+        // 20k points need 260k parameters, far above a single statement's limit
+        replace_activity_streams(&db, Uuid::new_v4(), make_points(20_000), 20_000, usize::MAX)
+            .await
+            .unwrap();
+
+        let log = format!("{:?}", db.into_transaction_log());
+        assert_eq!(
+            log.matches("INSERT INTO").count(),
+            20_000usize.div_ceil(max_stream_insert_batch_size())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resync_replaces_stored_points_and_records_the_original_size() {
+        let activity_id = Uuid::new_v4();
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_exec_results(replace_exec_results(1, 10))
+            .into_connection();
+
+        // 10 points kept out of the 40 Strava returned
+        replace_activity_streams(&db, activity_id, make_points(10), 40, 2000)
+            .await
+            .unwrap();
+
+        let log = db.into_transaction_log();
+        assert_eq!(log.len(), 1, "One transaction: {log:?}");
+        let statements = log[0].statements();
+        let sql: Vec<&str> = statements
+            .iter()
+            .map(|statement| statement.sql.as_str())
+            .collect();
+        assert_eq!(sql.len(), 5, "{sql:?}");
+        assert_eq!(sql[0], "BEGIN");
+        assert!(
+            sql[1].starts_with(r#"DELETE FROM "activity_stream" WHERE"#),
+            "{sql:?}"
+        );
+        assert!(
+            sql[2].starts_with(r#"INSERT INTO "activity_stream""#),
+            "{sql:?}"
+        );
+        assert!(sql[3].starts_with(r#"UPDATE "activity""#), "{sql:?}");
+        assert_eq!(sql[4], "COMMIT");
+        let delete_values = &statements[1].values.as_ref().unwrap().0;
+        assert_eq!(*delete_values, vec![Value::from(activity_id)]);
+        let update_values = &statements[3].values.as_ref().unwrap().0;
+        assert_eq!(
+            *update_values,
+            vec![Value::from(40_i32), Value::from(activity_id)]
+        );
+    }
+
+    fn point_time(model: &ActiveModel) -> DateTimeWithTimeZone {
+        model.time.clone().unwrap()
+    }
+
+    #[test]
+    fn test_latest_time_query_takes_max_for_the_activity() {
+        let activity_id = Uuid::new_v4();
+        let sql = latest_stream_time_query(activity_id)
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        assert!(
+            sql.starts_with(
+                r#"SELECT MAX("activity_stream"."time") AS "latest_time" FROM "activity_stream""#
+            ),
+            "{sql}"
+        );
+        assert!(sql.contains(&activity_id.to_string()), "{sql}");
+    }
+
+    #[test]
+    fn test_points_after_keeps_only_newer_points() {
+        let points = make_points(10);
+        let latest = point_time(&points[5]);
+
+        let appended = points_after(points.clone(), Some(latest));
+
+        assert_eq!(appended.len(), 4);
+        assert!(appended.iter().all(|point| point_time(point) > latest));
+        assert_eq!(appended[0], points[6]);
+        assert_eq!(points_after(points.clone(), None).len(), points.len());
+    }
+
+    #[tokio::test]
+    async fn test_append_inserts_only_points_past_the_latest_stored() {
+        let points = make_points(10);
+        let latest = point_time(&points[6]);
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![BTreeMap::from([(
+                "latest_time",
+                Value::ChronoDateTimeWithTimeZone(Some(Box::new(latest))),
+            )])]])
+            .append_exec_results(exec_results(2, 3))
+            .into_connection();
+
+        let appended = append_activity_streams(&db, Uuid::new_v4(), points.clone(), 10, 2000)
+            .await
+            .unwrap();
+
+        assert_eq!(appended, 3);
+        let log = format!("{:?}", db.into_transaction_log());
+        assert_eq!(log.matches("INSERT INTO").count(), 1, "{log}");
+        assert!(!log.contains("DELETE"), "{log}");
+        // Only the activity's original size is updated, never a stored point
+        assert!(!log.contains(r#"UPDATE \"activity_stream\""#), "{log}");
+        assert!(log.contains(r#"UPDATE \"activity\""#), "{log}");
+        assert!(log.contains("COMMIT"), "{log}");
+    }
+
+    #[tokio::test]
+    async fn test_append_with_nothing_new_writes_nothing() {
+        let points = make_points(5);
+        let latest = point_time(&points[4]);
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![BTreeMap::from([(
+                "latest_time",
+                Value::ChronoDateTimeWithTimeZone(Some(Box::new(latest))),
+            )])]])
+            .into_connection();
+
+        let appended = append_activity_streams(&db, Uuid::new_v4(), points, 5, 2000)
+            .await
+            .unwrap();
+
+        assert_eq!(appended, 0);
+        let log = format!("{:?}", db.into_transaction_log());
+        assert!(!log.contains("INSERT INTO"), "{log}");
+    }
+}
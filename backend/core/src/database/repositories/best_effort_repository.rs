@@ -0,0 +1,158 @@
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    Select, TransactionTrait,
+};
+use uuid::Uuid;
+
+use crate::database::{activity, best_effort, entities::prelude::BestEffort};
+use crate::models::CreateBestEffortDto;
+
+/// Retrieves an activity's best efforts, shortest distance first
+///
+/// # Errors
+///
+/// Returns an error if database query fails
+pub async fn get_best_efforts_by_activity(
+    db: &DatabaseConnection,
+    activity_id: Uuid,
+) -> Result<Vec<best_effort::Model>, DbErr> {
+    best_efforts_by_activity_query(activity_id).all(db).await
+}
+
+fn best_efforts_by_activity_query(activity_id: Uuid) -> Select<BestEffort> {
+    BestEffort::find()
+        .filter(best_effort::Column::ActivityId.eq(activity_id))
+        .order_by_asc(best_effort::Column::Distance)
+        .order_by_asc(best_effort::Column::Name)
+}
+
+/// Replaces an activity's best efforts with the ones from its latest Strava details
+///
+/// Runs in a single transaction, so readers never see a partial set. The activity's
+/// `best_efforts_fetched_at` is set, even when Strava had none for it.
+///
+/// # Errors
+///
+/// Returns an error if a database query fails (nothing is changed)
+pub async fn replace_best_efforts(
+    db: &DatabaseConnection,
+    activity_id: Uuid,
+    efforts: Vec<CreateBestEffortDto>,
+) -> Result<Vec<best_effort::Model>, DbErr> {
+    let transaction = db.begin().await?;
+
+    BestEffort::delete_many()
+        .filter(best_effort::Column::ActivityId.eq(activity_id))
+        .exec(&transaction)
+        .await?;
+    if !efforts.is_empty() {
+        BestEffort::insert_many(
+            efforts
+                .into_iter()
+                .map(CreateBestEffortDto::into_active_model),
+        )
+        .exec_without_returning(&transaction)
+        .await?;
+    }
+    activity::Entity::update_many()
+        .col_expr(
+            activity::Column::BestEffortsFetchedAt,
+            Expr::value(chrono::Utc::now().fixed_offset()),
+        )
+        .filter(activity::Column::Id.eq(activity_id))
+        .exec(&transaction)
+        .await?;
+    let stored = best_efforts_by_activity_query(activity_id)
+        .all(&transaction)
+        .await?;
+
+    transaction.commit().await?;
+    Ok(stored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{DbBackend, MockDatabase, MockExecResult, QueryTrait};
+
+    fn make_effort(activity_id: Uuid, name: &str, distance: f32) -> best_effort::Model {
+        best_effort::Model {
+            id: Uuid::new_v4(),
+            activity_id,
+            name: name.to_string(),
+            elapsed_time: 300,
+            distance,
+            created_at: chrono::Utc::now().fixed_offset(),
+        }
+    }
+
+    #[test]
+    fn test_best_efforts_are_ordered_by_distance() {
+        let activity_id = Uuid::new_v4();
+
+        let sql = best_efforts_by_activity_query(activity_id)
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        assert!(
+            sql.contains(&format!(r#""best_effort"."activity_id" = '{activity_id}'"#)),
+            "{sql}"
+        );
+        assert!(
+            sql.ends_with(r#"ORDER BY "best_effort"."distance" ASC, "best_effort"."name" ASC"#),
+            "{sql}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replacing_best_efforts_deletes_previous_ones() {
+        let activity_id = Uuid::new_v4();
+        let stored = vec![make_effort(activity_id, "1k", 1000.0)];
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_exec_results([
+                MockExecResult {
+                    last_insert_id: 0,
+                    rows_affected: 2,
+                },
+                MockExecResult {
+                    last_insert_id: 0,
+                    rows_affected: 1,
+                },
+                MockExecResult {
+                    last_insert_id: 0,
+                    rows_affected: 1,
+                },
+            ])
+            .append_query_results([stored.clone()])
+            .into_connection();
+
+        let efforts = replace_best_efforts(
+            &db,
+            activity_id,
+            vec![CreateBestEffortDto {
+                activity_id,
+                name: "1k".to_string(),
+                elapsed_time: 300,
+                distance: 1000.0,
+            }],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(efforts, stored);
+        let sql = db
+            .into_transaction_log()
+            .iter()
+            .flat_map(|transaction| transaction.statements())
+            .map(|statement| statement.sql.clone())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(sql.contains(r#"DELETE FROM "best_effort" WHERE"#), "{sql}");
+        assert!(sql.contains(r#"INSERT INTO "best_effort""#), "{sql}");
+        assert!(
+            sql.contains(r#"UPDATE "activity" SET "best_efforts_fetched_at" = "#),
+            "{sql}"
+        );
+        assert!(sql.contains("COMMIT"), "{sql}");
+    }
+}
@@ -1,8 +1,9 @@
 use chrono::{DateTime, FixedOffset};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, InsertResult,
-    QueryFilter, QueryOrder,
+    sea_query::OnConflict, ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection,
+    DbErr, EntityTrait, FromQueryResult, Insert, QueryFilter, QueryOrder, QuerySelect, Select,
 };
+use serde::Serialize;
 use uuid::Uuid;
 
 use crate::database::{entities::prelude::Listen, listen};
@@ -21,17 +22,42 @@ pub async fn create_listen(
     active_model.insert(db).await
 }
 
-/// Batch inserts multiple listen records
-/// Uses `SeaORM`'s bulk insert for efficiency
+/// Batch inserts listen records, silently skipping ones that already exist
+///
+/// Duplicates are detected by the `(user_id, track_id, played_at)` unique index,
+/// so re-syncing an overlapping time range only inserts the genuinely new listens
+/// instead of aborting the whole batch.
+///
+/// # Returns
+///
+/// The number of listens actually inserted
 ///
 /// # Errors
 ///
 /// Returns an error if database insert fails
-pub async fn batch_create_listens(
-    db: &DatabaseConnection,
+pub async fn batch_create_listens<C: ConnectionTrait>(
+    db: &C,
     listens: Vec<listen::ActiveModel>,
-) -> Result<InsertResult<listen::ActiveModel>, DbErr> {
-    Listen::insert_many(listens).exec(db).await
+) -> Result<u64, DbErr> {
+    if listens.is_empty() {
+        return Ok(0);
+    }
+
+    batch_insert_listens_query(listens)
+        .exec_without_returning(db)
+        .await
+}
+
+fn batch_insert_listens_query(listens: Vec<listen::ActiveModel>) -> Insert<listen::ActiveModel> {
+    Listen::insert_many(listens).on_conflict(
+        OnConflict::columns([
+            listen::Column::UserId,
+            listen::Column::TrackId,
+            listen::Column::PlayedAt,
+        ])
+        .do_nothing()
+        .to_owned(),
+    )
 }
 
 /// Retrieves listens for a user within a specific time range
@@ -72,6 +98,72 @@ pub async fn get_listens_by_user(
         .await
 }
 
+/// Time span covered by a user's listening history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, FromQueryResult)]
+pub struct ListenRange {
+    /// Earliest `played_at`, `None` without listens
+    pub first_listen_at: Option<DateTime<FixedOffset>>,
+    /// Latest `played_at`, `None` without listens
+    pub last_listen_at: Option<DateTime<FixedOffset>>,
+    pub total_listens: i64,
+}
+
+/// Reports the first and last listen of a user, with their listen count
+///
+/// Computed with `MIN`/`MAX`/`COUNT` aggregates, so no listen row is transferred.
+///
+/// # Errors
+///
+/// Returns an error if database query fails
+pub async fn get_listen_range_by_user(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+) -> Result<ListenRange, DbErr> {
+    listen_range_query(user_id)
+        .into_model::<ListenRange>()
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Listen range query returned no row".to_string()))
+}
+
+fn listen_range_query(user_id: Uuid) -> Select<Listen> {
+    Listen::find()
+        .select_only()
+        .column_as(listen::Column::PlayedAt.min(), "first_listen_at")
+        .column_as(listen::Column::PlayedAt.max(), "last_listen_at")
+        .column_as(listen::Column::Id.count(), "total_listens")
+        .filter(listen::Column::UserId.eq(user_id))
+}
+
+/// Checks whether a user has at least one listen of a track
+///
+/// Tracks are shared between users, so this is what ties a track to a user.
+/// Only the ID of the first matching listen is read (`LIMIT 1`), however many
+/// times the user played the track.
+///
+/// # Errors
+///
+/// Returns an error if database query fails
+pub async fn has_user_listened_to_track(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    track_id: Uuid,
+) -> Result<bool, DbErr> {
+    let first_listen = user_track_listens_query(user_id, track_id)
+        .select_only()
+        .column(listen::Column::Id)
+        .into_tuple::<Uuid>()
+        .one(db)
+        .await?;
+    Ok(first_listen.is_some())
+}
+
+fn user_track_listens_query(user_id: Uuid, track_id: Uuid) -> Select<Listen> {
+    Listen::find()
+        .filter(listen::Column::UserId.eq(user_id))
+        .filter(listen::Column::TrackId.eq(track_id))
+}
+
 /// Retrieves a specific listen by its internal UUID
 ///
 /// # Errors
@@ -144,3 +236,154 @@ pub async fn delete_listens_by_user_time_range(
 
     Ok(result.rows_affected)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_track, insert_user, test_database};
+    use sea_orm::{DbBackend, MockDatabase, QueryTrait, Value};
+    use std::collections::BTreeMap;
+
+    #[tokio::test]
+    async fn test_overlapping_resync_skips_existing_listens() {
+        let Some(db) = test_database().await else {
+            return;
+        };
+        let user = insert_user(&db).await;
+        let track = insert_track(&db).await;
+        let listens = |timestamps: &[u32]| -> Vec<listen::ActiveModel> {
+            timestamps
+                .iter()
+                .map(|&uts| CreateListenDto::new(user.id, track.id, uts).into_active_model())
+                .collect()
+        };
+        batch_create_listens(&db, listens(&[1_700_000_000]))
+            .await
+            .unwrap();
+
+        // Second sync of a window overlapping the first: 1_700_000_000 is already stored
+        let inserted = batch_create_listens(&db, listens(&[1_700_000_000, 1_700_000_240]))
+            .await
+            .unwrap();
+
+        assert_eq!(inserted, 1);
+        let played_at: Vec<_> = get_listens_by_user(&db, user.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|listen| listen.played_at.timestamp())
+            .collect();
+        assert_eq!(played_at, vec![1_700_000_240, 1_700_000_000]);
+    }
+
+    #[tokio::test]
+    async fn test_listened_check_reads_a_single_listen_of_the_user() {
+        let user_id = Uuid::new_v4();
+        let track_id = Uuid::new_v4();
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![BTreeMap::from([("id", Value::from(Uuid::new_v4()))])]])
+            .append_query_results([Vec::<BTreeMap<&str, Value>>::new()])
+            .into_connection();
+
+        assert!(has_user_listened_to_track(&db, user_id, track_id)
+            .await
+            .unwrap());
+        assert!(!has_user_listened_to_track(&db, user_id, track_id)
+            .await
+            .unwrap());
+
+        let log = db.into_transaction_log();
+        let query = &log[0].statements()[0];
+        assert_eq!(
+            query.values.as_ref().unwrap().0,
+            vec![user_id.into(), track_id.into(), 1_u64.into()],
+            "Scoped to the user and track, stopping at the first listen: {query:?}"
+        );
+    }
+
+    #[test]
+    fn test_listen_range_query_aggregates_without_loading_rows() {
+        let user_id = Uuid::new_v4();
+
+        let sql = listen_range_query(user_id)
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        assert!(
+            sql.contains(r#"MIN("listen"."played_at") AS "first_listen_at""#),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(r#"MAX("listen"."played_at") AS "last_listen_at""#),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(r#"COUNT("listen"."id") AS "total_listens""#),
+            "{sql}"
+        );
+        assert!(!sql.contains(r#""listen"."track_id""#), "{sql}");
+        assert!(sql.contains(r#""listen"."user_id" = "#), "{sql}");
+        assert!(sql.contains(&user_id.to_string()), "{sql}");
+        assert!(!sql.contains("GROUP BY"), "{sql}");
+    }
+
+    fn range_row(
+        first: Option<DateTime<FixedOffset>>,
+        last: Option<DateTime<FixedOffset>>,
+        total: i64,
+    ) -> BTreeMap<&'static str, Value> {
+        BTreeMap::from([
+            (
+                "first_listen_at",
+                Value::ChronoDateTimeWithTimeZone(first.map(Box::new)),
+            ),
+            (
+                "last_listen_at",
+                Value::ChronoDateTimeWithTimeZone(last.map(Box::new)),
+            ),
+            ("total_listens", Value::BigInt(Some(total))),
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_listen_range_over_seeded_listens() {
+        let first = DateTime::from_timestamp(1_600_000_000, 0)
+            .unwrap()
+            .fixed_offset();
+        let last = DateTime::from_timestamp(1_700_000_000, 0)
+            .unwrap()
+            .fixed_offset();
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![range_row(Some(first), Some(last), 1234)]])
+            .into_connection();
+
+        let range = get_listen_range_by_user(&db, Uuid::new_v4()).await.unwrap();
+
+        assert_eq!(
+            range,
+            ListenRange {
+                first_listen_at: Some(first),
+                last_listen_at: Some(last),
+                total_listens: 1234,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_listen_range_for_empty_history_is_null() {
+        // Aggregates without GROUP BY still return one row: NULL bounds and a zero count
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![range_row(None, None, 0)]])
+            .into_connection();
+
+        let range = get_listen_range_by_user(&db, Uuid::new_v4()).await.unwrap();
+
+        assert_eq!(range.first_listen_at, None);
+        assert_eq!(range.last_listen_at, None);
+        assert_eq!(range.total_listens, 0);
+        assert_eq!(
+            serde_json::to_value(range).unwrap()["first_listen_at"],
+            serde_json::Value::Null
+        );
+    }
+}
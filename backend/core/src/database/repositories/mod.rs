@@ -1,13 +1,17 @@
 pub mod activity_repository;
 pub mod activity_stream_repository;
+pub mod best_effort_repository;
 pub mod listen_repository;
 pub mod oauth_token_repository;
+pub mod refresh_token_repository;
 pub mod track_repository;
 pub mod user_repository;
 
 pub use activity_repository::*;
 pub use activity_stream_repository::*;
+pub use best_effort_repository::*;
 pub use listen_repository::*;
 pub use oauth_token_repository::*;
+pub use refresh_token_repository::*;
 pub use track_repository::*;
 pub use user_repository::*;
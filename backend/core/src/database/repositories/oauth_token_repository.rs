@@ -1,8 +1,8 @@
 use sea_orm::{
-    prelude::DateTimeWithTimeZone, ActiveModelTrait, ActiveValue::Set, ColumnTrait,
-    DatabaseConnection, DbErr,
+    prelude::DateTimeWithTimeZone, sea_query::Expr, ActiveModelTrait, ActiveValue::Set,
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr,
 };
-use sea_orm::{EntityTrait, QueryFilter};
+use sea_orm::{EntityTrait, QueryFilter, QueryOrder, QuerySelect, Select};
 use uuid::Uuid;
 
 use crate::config::OAuthProvider;
@@ -117,3 +117,132 @@ pub async fn delete_oauth_token(
         None => Err(DbErr::RecordNotFound("OAuth token not found".into())),
     }
 }
+
+/// Samples stored access tokens, least recently updated first
+///
+/// Only the encrypted payloads are returned; refresh tokens are always written
+/// alongside their access token, so the access token's version stands for the row.
+///
+/// # Errors
+///
+/// Returns an error if database query fails
+pub async fn sample_access_token_payloads(
+    db: &DatabaseConnection,
+    limit: u64,
+) -> Result<Vec<String>, DbErr> {
+    access_token_sample_query(limit)
+        .into_tuple::<String>()
+        .all(db)
+        .await
+}
+
+fn access_token_sample_query(limit: u64) -> Select<OauthToken> {
+    OauthToken::find()
+        .select_only()
+        .column(oauth_token::Column::AccessToken)
+        .order_by_asc(oauth_token::Column::UpdatedAt)
+        .limit(limit)
+}
+
+/// Loads OAuth tokens ordered by id, starting after `after_id`
+///
+/// Meant to walk the whole table in batches: pass the id of the last row of the
+/// previous batch to get the next one.
+///
+/// # Errors
+///
+/// Returns an error if database query fails
+pub async fn get_oauth_tokens_after(
+    db: &DatabaseConnection,
+    after_id: Option<Uuid>,
+    limit: u64,
+) -> Result<Vec<oauth_token::Model>, DbErr> {
+    oauth_tokens_after_query(after_id, limit).all(db).await
+}
+
+fn oauth_tokens_after_query(after_id: Option<Uuid>, limit: u64) -> Select<OauthToken> {
+    let mut query = OauthToken::find();
+    if let Some(after_id) = after_id {
+        query = query.filter(oauth_token::Column::Id.gt(after_id));
+    }
+    query.order_by_asc(oauth_token::Column::Id).limit(limit)
+}
+
+/// Replaces the encrypted payloads of a token, unless it changed since it was read
+///
+/// The update only applies while the row still holds `current_access_token`, so
+/// a token refreshed in the meantime is not overwritten with stale values.
+///
+/// # Returns
+///
+/// Whether the row was updated
+///
+/// # Errors
+///
+/// Returns an error if database update fails
+pub async fn replace_oauth_token_payloads<C: ConnectionTrait>(
+    db: &C,
+    token_id: Uuid,
+    current_access_token: &str,
+    access_token: String,
+    refresh_token: Option<String>,
+) -> Result<bool, DbErr> {
+    let result = OauthToken::update_many()
+        .col_expr(oauth_token::Column::AccessToken, Expr::value(access_token))
+        .col_expr(
+            oauth_token::Column::RefreshToken,
+            Expr::value(refresh_token),
+        )
+        .col_expr(
+            oauth_token::Column::UpdatedAt,
+            Expr::value(DateTimeWithTimeZone::from(chrono::Utc::now())),
+        )
+        .filter(oauth_token::Column::Id.eq(token_id))
+        .filter(oauth_token::Column::AccessToken.eq(current_access_token))
+        .exec(db)
+        .await?;
+
+    Ok(result.rows_affected > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{DbBackend, QueryTrait};
+
+    #[test]
+    fn test_sample_query_selects_oldest_access_tokens_only() {
+        let sql = access_token_sample_query(500)
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        assert!(
+            sql.starts_with(r#"SELECT "oauth_token"."access_token" FROM "oauth_token""#),
+            "{sql}"
+        );
+        assert!(!sql.contains("refresh_token"), "{sql}");
+        assert!(
+            sql.contains(r#"ORDER BY "oauth_token"."updated_at" ASC LIMIT 500"#),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_tokens_after_query_pages_by_id() {
+        let first = oauth_tokens_after_query(None, 100)
+            .build(DbBackend::Postgres)
+            .to_string();
+        assert!(!first.contains("WHERE"), "{first}");
+        assert!(
+            first.ends_with(r#"ORDER BY "oauth_token"."id" ASC LIMIT 100"#),
+            "{first}"
+        );
+
+        let after = Uuid::new_v4();
+        let next = oauth_tokens_after_query(Some(after), 100)
+            .build(DbBackend::Postgres)
+            .to_string();
+        assert!(next.contains(r#"WHERE "oauth_token"."id" >"#), "{next}");
+        assert!(next.contains(&after.to_string()), "{next}");
+    }
+}
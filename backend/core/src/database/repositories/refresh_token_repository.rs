@@ -0,0 +1,96 @@
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, sea_query::Expr, ActiveModelTrait, ActiveValue::Set,
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+};
+use uuid::Uuid;
+
+use crate::database::entities::prelude::RefreshToken;
+use crate::database::refresh_token;
+
+/// Stores a new refresh token hash for a user
+///
+/// # Errors
+///
+/// Returns an error if database insert fails
+pub async fn create_refresh_token<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    token_hash: String,
+    expires_at: DateTimeWithTimeZone,
+) -> Result<refresh_token::Model, DbErr> {
+    let new_token = refresh_token::ActiveModel {
+        user_id: Set(user_id),
+        token_hash: Set(token_hash),
+        expires_at: Set(expires_at),
+        ..Default::default()
+    };
+
+    new_token.insert(db).await
+}
+
+/// Retrieves a refresh token by its hash, including revoked and expired ones
+///
+/// # Errors
+///
+/// Returns an error if database query fails
+pub async fn get_refresh_token_by_hash(
+    db: &DatabaseConnection,
+    token_hash: &str,
+) -> Result<Option<refresh_token::Model>, DbErr> {
+    RefreshToken::find()
+        .filter(refresh_token::Column::TokenHash.eq(token_hash))
+        .one(db)
+        .await
+}
+
+/// Revokes a single refresh token
+///
+/// Only a token that is not already revoked is updated, so concurrent redemptions
+/// of the same token cannot both succeed.
+///
+/// # Returns
+///
+/// `true` if the token was revoked by this call, `false` if it was already revoked
+///
+/// # Errors
+///
+/// Returns an error if database update fails
+pub async fn revoke_refresh_token<C: ConnectionTrait>(db: &C, id: Uuid) -> Result<bool, DbErr> {
+    let result = RefreshToken::update_many()
+        .col_expr(
+            refresh_token::Column::RevokedAt,
+            Expr::value(chrono::Utc::now()),
+        )
+        .filter(refresh_token::Column::Id.eq(id))
+        .filter(refresh_token::Column::RevokedAt.is_null())
+        .exec(db)
+        .await?;
+
+    Ok(result.rows_affected == 1)
+}
+
+/// Revokes every active refresh token of a user
+///
+/// # Returns
+///
+/// The number of tokens revoked
+///
+/// # Errors
+///
+/// Returns an error if database update fails
+pub async fn revoke_user_refresh_tokens(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+) -> Result<u64, DbErr> {
+    let result = RefreshToken::update_many()
+        .col_expr(
+            refresh_token::Column::RevokedAt,
+            Expr::value(chrono::Utc::now()),
+        )
+        .filter(refresh_token::Column::UserId.eq(user_id))
+        .filter(refresh_token::Column::RevokedAt.is_null())
+        .exec(db)
+        .await?;
+
+    Ok(result.rows_affected)
+}
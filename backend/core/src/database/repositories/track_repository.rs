@@ -1,7 +1,16 @@
-use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+use sea_orm::{
+    sea_query::{Alias, Condition, Expr, Func, OnConflict, Query, SimpleExpr},
+    ActiveModelTrait,
+    ActiveValue::Set,
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, DeleteMany, EntityTrait, QueryFilter,
+    QueryOrder, Select, TransactionTrait, UpdateMany,
+};
 use uuid::Uuid;
 
-use crate::database::{entities::prelude::Track, track};
+use crate::database::{
+    entities::prelude::{Listen, Track},
+    excluded, listen, track, upsert_one,
+};
 use crate::models::CreateTrackDto;
 
 /// Creates a new track from a DTO
@@ -9,8 +18,8 @@ use crate::models::CreateTrackDto;
 /// # Errors
 ///
 /// Returns an error if database insert fails
-pub async fn create_track(
-    db: &DatabaseConnection,
+pub async fn create_track<C: ConnectionTrait>(
+    db: &C,
     dto: CreateTrackDto,
 ) -> Result<track::Model, DbErr> {
     let active_model = dto.into_active_model();
@@ -18,30 +27,121 @@ pub async fn create_track(
 }
 
 /// Creates or updates a track based on `(artist_name, track_name)` unique constraint
-/// If a track with the same artist and name exists, it returns the existing track
+///
+/// If a track with the same artist and name exists, the MBIDs, album and Last.fm URL
+/// it is missing are filled from the DTO, so metadata is enriched as better scrobbles
+/// arrive. Values already set are never overwritten. Runs as a single
+/// `INSERT ... ON CONFLICT`, so concurrent syncs of the same track share one row.
 ///
 /// # Errors
 ///
 /// Returns an error if database operation fails
-pub async fn upsert_track(
-    db: &DatabaseConnection,
+pub async fn upsert_track<C: ConnectionTrait>(
+    db: &C,
     dto: CreateTrackDto,
 ) -> Result<track::Model, DbErr> {
-    // Check if track already exists
-    let existing = get_track_by_metadata(db, &dto.artist_name, &dto.track_name).await?;
-
-    match existing {
-        Some(existing_track) => {
-            // Track already exists, optionally update metadata if needed
-            // For now, just return the existing track
-            // Future: Could update MBIDs or URL if they were empty before
-            Ok(existing_track)
-        }
-        None => {
-            // Create new track
-            create_track(db, dto).await
-        }
+    upsert_one(db, dto.into_active_model(), track_upsert_conflict()).await
+}
+
+/// Metadata columns an existing track takes from a later upsert while they are empty
+const ENRICHED_COLUMNS: [track::Column; 5] = [
+    track::Column::AlbumName,
+    track::Column::ArtistMbid,
+    track::Column::TrackMbid,
+    track::Column::AlbumMbid,
+    track::Column::LastfmUrl,
+];
+
+/// Conflict on `(artist_name, track_name)` filling the metadata the track is missing
+///
+/// `updated_at` only moves when a column is actually filled in.
+fn track_upsert_conflict() -> OnConflict {
+    let mut on_conflict =
+        OnConflict::columns([track::Column::ArtistName, track::Column::TrackName]);
+    for column in ENRICHED_COLUMNS {
+        on_conflict.value(
+            column,
+            Expr::case(is_blank(existing(column)), excluded(column)).finally(existing(column)),
+        );
     }
+
+    let enriched = ENRICHED_COLUMNS
+        .into_iter()
+        .fold(Condition::any(), |condition, column| {
+            condition.add(
+                Condition::all()
+                    .add(is_blank(existing(column)))
+                    .add(is_blank(excluded(column)).not()),
+            )
+        });
+    on_conflict.value(
+        track::Column::UpdatedAt,
+        Expr::case(enriched, excluded(track::Column::UpdatedAt))
+            .finally(existing(track::Column::UpdatedAt)),
+    );
+    on_conflict
+}
+
+/// The value stored in the conflicting track row
+fn existing(column: track::Column) -> SimpleExpr {
+    Expr::col((Track, column)).into()
+}
+
+/// Whether an optional metadata value is missing (Last.fm sends empty strings)
+fn is_blank(value: SimpleExpr) -> SimpleExpr {
+    Expr::expr(
+        Func::cust(Alias::new("NULLIF"))
+            .arg(Func::cust(Alias::new("TRIM")).arg(value))
+            .arg(""),
+    )
+    .is_null()
+}
+
+/// Records the Spotify track a track was matched to, with the match confidence
+///
+/// The track is also marked as searched, like `mark_track_spotify_searched`.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Database query fails
+/// - Track not found
+pub async fn set_track_spotify_match(
+    db: &DatabaseConnection,
+    id: Uuid,
+    spotify_id: String,
+    confidence: f64,
+) -> Result<track::Model, DbErr> {
+    let track = get_track_by_id(db, id)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Track not found".into()))?;
+
+    let mut active_model: track::ActiveModel = track.into();
+    active_model.spotify_id = Set(Some(spotify_id));
+    active_model.spotify_match_confidence = Set(Some(confidence));
+    active_model.spotify_searched_at = Set(Some(chrono::Utc::now().into()));
+    active_model.updated_at = Set(chrono::Utc::now().into());
+    active_model.update(db).await
+}
+
+/// Marks a track as searched on Spotify without a confident match
+///
+/// Batch enrichment skips marked tracks, so their search isn't paid for again.
+///
+/// # Errors
+///
+/// Returns an error if database update fails
+pub async fn mark_track_spotify_searched(db: &DatabaseConnection, id: Uuid) -> Result<(), DbErr> {
+    Track::update_many()
+        .col_expr(
+            track::Column::SpotifySearchedAt,
+            Expr::value(chrono::Utc::now().fixed_offset()),
+        )
+        .filter(track::Column::Id.eq(id))
+        .exec(db)
+        .await?;
+
+    Ok(())
 }
 
 /// Retrieves a track by artist name and track name
@@ -49,8 +149,8 @@ pub async fn upsert_track(
 /// # Errors
 ///
 /// Returns an error if database query fails
-pub async fn get_track_by_metadata(
-    db: &DatabaseConnection,
+pub async fn get_track_by_metadata<C: ConnectionTrait>(
+    db: &C,
     artist_name: &str,
     track_name: &str,
 ) -> Result<Option<track::Model>, DbErr> {
@@ -73,6 +173,34 @@ pub async fn get_track_by_id(
     Track::find().filter(track::Column::Id.eq(id)).one(db).await
 }
 
+/// Retrieves the tracks a user listened to that were never searched on Spotify, oldest first
+///
+/// Tracks searched without a confident match are left out, like matched ones.
+///
+/// # Errors
+///
+/// Returns an error if database query fails
+pub async fn get_unenriched_tracks_by_user(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+) -> Result<Vec<track::Model>, DbErr> {
+    unenriched_tracks_query(user_id).all(db).await
+}
+
+fn unenriched_tracks_query(user_id: Uuid) -> Select<Track> {
+    let listened = Query::select()
+        .column(listen::Column::TrackId)
+        .from(Listen)
+        .and_where(listen::Column::UserId.eq(user_id))
+        .to_owned();
+
+    Track::find()
+        .filter(track::Column::SpotifyId.is_null())
+        .filter(track::Column::SpotifySearchedAt.is_null())
+        .filter(track::Column::Id.in_subquery(listened))
+        .order_by_asc(track::Column::CreatedAt)
+}
+
 /// Retrieves a track by `MusicBrainz` track ID
 ///
 /// # Errors
@@ -107,3 +235,321 @@ pub async fn delete_track(db: &DatabaseConnection, id: Uuid) -> Result<(), DbErr
         None => Err(DbErr::RecordNotFound("Track not found".into())),
     }
 }
+
+/// Merges a duplicate track into the track to keep
+///
+/// Listens of the duplicate are repointed to the kept track and the duplicate is
+/// deleted, in a single transaction. A duplicate listen that collides with a listen
+/// of the kept track (same user, same `played_at`) is dropped instead of repointed,
+/// so the `(user_id, track_id, played_at)` unique index still holds.
+///
+/// # Returns
+///
+/// The number of listens repointed to the kept track
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Both IDs are the same track
+/// - Either track does not exist
+/// - A database query fails (nothing is changed)
+pub async fn merge_tracks(
+    db: &DatabaseConnection,
+    keep_id: Uuid,
+    duplicate_id: Uuid,
+) -> Result<u64, DbErr> {
+    if keep_id == duplicate_id {
+        return Err(DbErr::Custom("Cannot merge a track into itself".into()));
+    }
+
+    let transaction = db.begin().await?;
+
+    for id in [keep_id, duplicate_id] {
+        if Track::find_by_id(id).one(&transaction).await?.is_none() {
+            return Err(DbErr::RecordNotFound(format!("Track {id} not found")));
+        }
+    }
+
+    delete_colliding_listens_query(keep_id, duplicate_id)
+        .exec(&transaction)
+        .await?;
+    let repointed = repoint_listens_query(keep_id, duplicate_id)
+        .exec(&transaction)
+        .await?
+        .rows_affected;
+    Track::delete_by_id(duplicate_id).exec(&transaction).await?;
+
+    transaction.commit().await?;
+    Ok(repointed)
+}
+
+/// Duplicate listens that would collide with a listen of the kept track once repointed
+fn delete_colliding_listens_query(keep_id: Uuid, duplicate_id: Uuid) -> DeleteMany<Listen> {
+    let kept_listens = Query::select()
+        .columns([listen::Column::UserId, listen::Column::PlayedAt])
+        .from(Listen)
+        .and_where(listen::Column::TrackId.eq(keep_id))
+        .to_owned();
+
+    Listen::delete_many()
+        .filter(listen::Column::TrackId.eq(duplicate_id))
+        .filter(
+            Expr::tuple([
+                Expr::col(listen::Column::UserId).into(),
+                Expr::col(listen::Column::PlayedAt).into(),
+            ])
+            .in_subquery(kept_listens),
+        )
+}
+
+fn repoint_listens_query(keep_id: Uuid, duplicate_id: Uuid) -> UpdateMany<Listen> {
+    Listen::update_many()
+        .col_expr(listen::Column::TrackId, Expr::value(keep_id))
+        .filter(listen::Column::TrackId.eq(duplicate_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CreateListenDto;
+    use crate::test_support::{insert_user, make_track, test_database};
+    use sea_orm::{DbBackend, MockDatabase, QueryTrait};
+
+    fn make_dto(album_name: Option<&str>) -> CreateTrackDto {
+        CreateTrackDto {
+            artist_name: "Daft Punk".to_string(),
+            track_name: "Around the World".to_string(),
+            album_name: album_name.map(str::to_string),
+            artist_mbid: None,
+            track_mbid: None,
+            album_mbid: None,
+            lastfm_url: None,
+        }
+    }
+
+    /// `make_dto` with a track name of its own, so database-backed tests never share a row
+    fn unique_dto(album_name: Option<&str>) -> CreateTrackDto {
+        CreateTrackDto {
+            track_name: format!("Around the World {}", Uuid::new_v4()),
+            ..make_dto(album_name)
+        }
+    }
+
+    /// A later scrobble of the same track carrying `album_name`
+    fn with_album(dto: &CreateTrackDto, album_name: Option<&str>) -> CreateTrackDto {
+        CreateTrackDto {
+            album_name: album_name.map(str::to_string),
+            ..dto.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upsert_fills_empty_album() {
+        let Some(db) = test_database().await else {
+            return;
+        };
+
+        for empty in [None, Some(""), Some("  ")] {
+            let dto = unique_dto(empty);
+            let stored = upsert_track(&db, dto.clone()).await.unwrap();
+
+            let track = upsert_track(&db, with_album(&dto, Some("Homework")))
+                .await
+                .unwrap();
+
+            assert_eq!(track.id, stored.id);
+            assert_eq!(track.album_name.as_deref(), Some("Homework"));
+            assert!(track.updated_at >= stored.updated_at);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upsert_keeps_set_album_without_writing() {
+        let Some(db) = test_database().await else {
+            return;
+        };
+        let dto = unique_dto(Some("Homework"));
+        let stored = upsert_track(&db, dto.clone()).await.unwrap();
+
+        for other in [Some("Around the World (Single)"), Some(""), None] {
+            let track = upsert_track(&db, with_album(&dto, other)).await.unwrap();
+
+            // Same row and album, with `updated_at` untouched
+            assert_eq!(track, stored);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upsert_fills_mbids_without_touching_set_fields() {
+        let Some(db) = test_database().await else {
+            return;
+        };
+        let dto = CreateTrackDto {
+            lastfm_url: Some("https://www.last.fm/music/Daft+Punk".to_string()),
+            ..unique_dto(Some("Homework"))
+        };
+        let stored = upsert_track(&db, dto.clone()).await.unwrap();
+
+        let track = upsert_track(
+            &db,
+            CreateTrackDto {
+                track_mbid: Some("track-mbid".to_string()),
+                lastfm_url: Some("https://www.last.fm/other".to_string()),
+                ..with_album(&dto, Some("Other Album"))
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(track.track_mbid.as_deref(), Some("track-mbid"));
+        assert_eq!(track.album_name, stored.album_name);
+        assert_eq!(track.lastfm_url, stored.lastfm_url);
+        assert_eq!(track.artist_mbid, None);
+    }
+
+    #[tokio::test]
+    async fn test_parallel_upserts_of_a_new_track_share_one_row() {
+        let Some(db) = test_database().await else {
+            return;
+        };
+        let dto = unique_dto(None);
+
+        let (first, second) = tokio::join!(
+            upsert_track(&db, dto.clone()),
+            upsert_track(&db, with_album(&dto, Some("Homework"))),
+        );
+
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert_eq!(first.id, second.id);
+        let stored = Track::find()
+            .filter(track::Column::ArtistName.eq(dto.artist_name.as_str()))
+            .filter(track::Column::TrackName.eq(dto.track_name.as_str()))
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 1, "{stored:?}");
+        // Whichever upsert ran first, the album ends up filled
+        assert_eq!(stored[0].album_name.as_deref(), Some("Homework"));
+    }
+
+    #[tokio::test]
+    async fn test_every_upsert_is_a_single_conflict_insert_without_lookup() {
+        // No SELECT an insert could race with: Postgres resolves a concurrent
+        // insert of the same track onto the existing row through the conflict clause
+        let stored = make_track();
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![stored.clone()], vec![stored.clone()]])
+            .into_connection();
+
+        upsert_track(&db, make_dto(None)).await.unwrap();
+        upsert_track(&db, make_dto(Some("Homework"))).await.unwrap();
+
+        let log = db.into_transaction_log();
+        assert_eq!(log.len(), 2, "{log:?}");
+        let log = format!("{log:?}");
+        assert_eq!(log.matches("ON CONFLICT").count(), 2, "{log}");
+        assert!(!log.contains("SELECT"), "{log}");
+    }
+
+    #[test]
+    fn test_unenriched_tracks_are_the_users_tracks_without_spotify_match() {
+        let user_id = Uuid::new_v4();
+
+        let sql = unenriched_tracks_query(user_id)
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        assert!(sql.contains(r#""track"."spotify_id" IS NULL"#), "{sql}");
+        assert!(
+            sql.contains(r#""track"."spotify_searched_at" IS NULL"#),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(&format!(
+                r#""track"."id" IN (SELECT "track_id" FROM "listen" WHERE "listen"."user_id" = '{user_id}')"#
+            )),
+            "{sql}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merging_repoints_listens_and_drops_colliding_ones() {
+        let Some(db) = test_database().await else {
+            return;
+        };
+        let runner = insert_user(&db).await;
+        let friend = insert_user(&db).await;
+        let keep = upsert_track(&db, unique_dto(Some("Homework")))
+            .await
+            .unwrap();
+        let duplicate = upsert_track(&db, unique_dto(None)).await.unwrap();
+        let listens = [
+            (runner.id, keep.id, 1_700_000_000),
+            // Same moment as a listen of the kept track: dropped
+            (runner.id, duplicate.id, 1_700_000_000),
+            (runner.id, duplicate.id, 1_700_000_240),
+            // Another user's listen at that moment does not collide
+            (friend.id, duplicate.id, 1_700_000_000),
+        ]
+        .map(|(user_id, track_id, uts)| {
+            CreateListenDto::new(user_id, track_id, uts).into_active_model()
+        });
+        Listen::insert_many(listens).exec(&db).await.unwrap();
+
+        let repointed = merge_tracks(&db, keep.id, duplicate.id).await.unwrap();
+
+        assert_eq!(repointed, 2);
+        assert_eq!(get_track_by_id(&db, duplicate.id).await.unwrap(), None);
+        let mut merged: Vec<_> = Listen::find()
+            .filter(listen::Column::TrackId.eq(keep.id))
+            .all(&db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|listen| (listen.user_id, listen.played_at.timestamp()))
+            .collect();
+        merged.sort_unstable();
+        let mut expected = vec![
+            (runner.id, 1_700_000_000),
+            (runner.id, 1_700_000_240),
+            (friend.id, 1_700_000_000),
+        ];
+        expected.sort_unstable();
+        assert_eq!(merged, expected);
+    }
+
+    #[tokio::test]
+    async fn test_merging_a_missing_track_changes_nothing() {
+        let Some(db) = test_database().await else {
+            return;
+        };
+        let runner = insert_user(&db).await;
+        let keep = upsert_track(&db, unique_dto(None)).await.unwrap();
+        let listen = CreateListenDto::new(runner.id, keep.id, 1_700_000_000)
+            .into_active_model()
+            .insert(&db)
+            .await
+            .unwrap();
+
+        let result = merge_tracks(&db, Uuid::new_v4(), keep.id).await;
+
+        assert!(matches!(result, Err(DbErr::RecordNotFound(_))));
+        assert!(get_track_by_id(&db, keep.id).await.unwrap().is_some());
+        assert_eq!(
+            Listen::find_by_id(listen.id).one(&db).await.unwrap(),
+            Some(listen)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merging_a_track_into_itself_is_rejected() {
+        let db = DatabaseConnection::Disconnected;
+        let id = Uuid::new_v4();
+
+        let result = merge_tracks(&db, id, id).await;
+
+        assert!(
+            matches!(result, Err(DbErr::Custom(ref message)) if message == "Cannot merge a track into itself")
+        );
+    }
+}
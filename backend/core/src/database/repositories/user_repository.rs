@@ -85,24 +85,62 @@ pub async fn update_user_email(
     }
 }
 
-/// Updates a user's lastfm username
+/// Updates a user's Last.fm username and default listen padding together
+///
+/// Fields left `None` are kept. Both are written by a single `UPDATE`, so a
+/// failure leaves neither changed.
+///
+/// # Arguments
+///
+/// * `lastfm_username` - New Last.fm username
+/// * `listen_padding` - New seconds of padding before and after activities
 ///
 /// # Errors
 ///
 /// Returns an error if:
 /// - Database query fails
 /// - User not found
-pub async fn update_user_lastfm_username(
+pub async fn update_user_settings(
     db: &DatabaseConnection,
     id: Uuid,
-    new_lastfm_username: String,
+    lastfm_username: Option<String>,
+    listen_padding: Option<(i32, i32)>,
 ) -> Result<user::Model, DbErr> {
     let user = get_user_by_id(db, id).await?;
 
     match user {
         Some(u) => {
             let mut active_model: user::ActiveModel = u.into();
-            active_model.lastfm_username = Set(Some(new_lastfm_username));
+            if let Some(lastfm_username) = lastfm_username {
+                active_model.lastfm_username = Set(Some(lastfm_username));
+            }
+            if let Some((before_seconds, after_seconds)) = listen_padding {
+                active_model.listen_padding_before_seconds = Set(before_seconds);
+                active_model.listen_padding_after_seconds = Set(after_seconds);
+            }
+            active_model.update(db).await
+        }
+        None => Err(DbErr::RecordNotFound("User not found".into())),
+    }
+}
+
+/// Clears a user's Last.fm username, unlinking their Last.fm account
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Database query fails
+/// - User not found
+pub async fn clear_user_lastfm_username(
+    db: &DatabaseConnection,
+    id: Uuid,
+) -> Result<user::Model, DbErr> {
+    let user = get_user_by_id(db, id).await?;
+
+    match user {
+        Some(u) => {
+            let mut active_model: user::ActiveModel = u.into();
+            active_model.lastfm_username = Set(None);
             active_model.update(db).await
         }
         None => Err(DbErr::RecordNotFound("User not found".into())),
@@ -128,3 +166,73 @@ pub async fn delete_user(db: &DatabaseConnection, id: Uuid) -> Result<(), DbErr>
         None => Err(DbErr::RecordNotFound("User not found".into())),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::make_user;
+    use sea_orm::{DbBackend, MockDatabase};
+
+    #[tokio::test]
+    async fn test_clearing_lastfm_username_stores_null() {
+        let linked = make_user();
+        let unlinked = user::Model {
+            lastfm_username: None,
+            ..linked.clone()
+        };
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![linked.clone()], vec![unlinked]])
+            .into_connection();
+
+        let updated = clear_user_lastfm_username(&db, linked.id).await.unwrap();
+
+        assert_eq!(updated.lastfm_username, None);
+        let log = format!("{:?}", db.into_transaction_log());
+        assert!(log.contains("UPDATE"), "{log}");
+        assert!(log.contains("lastfm_username"), "{log}");
+        assert!(log.contains("String(None)"), "{log}");
+    }
+
+    #[tokio::test]
+    async fn test_clearing_lastfm_username_of_unknown_user_fails() {
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([Vec::<user::Model>::new()])
+            .into_connection();
+
+        let result = clear_user_lastfm_username(&db, Uuid::new_v4()).await;
+
+        assert!(matches!(result, Err(DbErr::RecordNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_username_and_padding_are_written_by_one_update() {
+        let stored = user::Model {
+            lastfm_username: None,
+            ..make_user()
+        };
+        let updated = user::Model {
+            lastfm_username: Some("runner".to_string()),
+            listen_padding_before_seconds: 120,
+            listen_padding_after_seconds: 30,
+            ..stored.clone()
+        };
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![stored.clone()], vec![updated.clone()]])
+            .into_connection();
+
+        let result =
+            update_user_settings(&db, stored.id, Some("runner".to_string()), Some((120, 30)))
+                .await
+                .unwrap();
+
+        assert_eq!(result, updated);
+        let log = db.into_transaction_log();
+        assert_eq!(log.len(), 2, "One lookup, then one update: {log:?}");
+        let update = &log[1].statements()[0];
+        assert!(update.sql.starts_with(r#"UPDATE "user""#), "{update:?}");
+        let values = &update.values.as_ref().unwrap().0;
+        assert!(values.contains(&"runner".into()), "{values:?}");
+        assert!(values.contains(&120_i32.into()), "{values:?}");
+        assert!(values.contains(&30_i32.into()), "{values:?}");
+    }
+}
@@ -0,0 +1,242 @@
+use std::{future::Future, time::Duration};
+
+use sea_orm::{DbErr, RuntimeErr};
+use tracing::warn;
+
+use crate::config::sync_db_retry_attempts;
+
+/// Delay before the first retry, doubled on every further attempt
+pub const DEFAULT_RETRY_BASE_DELAY: Duration = Duration::from_millis(200);
+
+/// Postgres SQLSTATE codes worth retrying: the same statement may succeed on a later attempt
+///
+/// Class `08` (connection exceptions) is matched separately.
+const TRANSIENT_SQLSTATES: &[&str] = &[
+    "40001", // serialization_failure
+    "40P01", // deadlock_detected
+    "57P01", // admin_shutdown
+    "57P03", // cannot_connect_now
+];
+
+/// How often a database write is attempted before its error is returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first one (1 disables retries)
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled on every further attempt
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Policy with the attempts configured by `SYNC_DB_RETRY_ATTEMPTS`
+    #[must_use]
+    pub fn from_env() -> Self {
+        Self {
+            max_attempts: sync_db_retry_attempts(),
+            base_delay: DEFAULT_RETRY_BASE_DELAY,
+        }
+    }
+
+    fn delay_before_retry(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+    }
+}
+
+/// Whether a database error is a transient failure that a retry may get past
+///
+/// Dropped or unavailable connections, pool timeouts, serialization failures and
+/// deadlocks are transient. Constraint violations and every other error are not:
+/// retrying them would fail the same way.
+#[must_use]
+pub fn is_transient_db_error(err: &DbErr) -> bool {
+    match err {
+        DbErr::ConnectionAcquire(_) => true,
+        DbErr::Conn(RuntimeErr::SqlxError(err))
+        | DbErr::Exec(RuntimeErr::SqlxError(err))
+        | DbErr::Query(RuntimeErr::SqlxError(err)) => is_transient_sqlx_error(err),
+        _ => false,
+    }
+}
+
+fn is_transient_sqlx_error(err: &sqlx::Error) -> bool {
+    match err {
+        sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::WorkerCrashed => true,
+        sqlx::Error::Database(db_err) => db_err
+            .code()
+            .is_some_and(|code| code.starts_with("08") || TRANSIENT_SQLSTATES.contains(&&*code)),
+        _ => false,
+    }
+}
+
+/// Runs a database operation, retrying it on transient errors
+///
+/// `operation` is called again for each attempt, so it must rebuild its inputs
+/// every time. Non-transient errors are returned right away.
+///
+/// # Errors
+///
+/// Returns the operation's error if it is not transient or the last attempt fails
+pub async fn retry_transient<T, F, Fut>(policy: RetryPolicy, mut operation: F) -> Result<T, DbErr>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, DbErr>>,
+{
+    let mut attempt = 1;
+    loop {
+        match operation().await {
+            Err(err) if attempt < policy.max_attempts && is_transient_db_error(&err) => {
+                warn!(
+                    attempt = attempt,
+                    max_attempts = policy.max_attempts,
+                    error = %err,
+                    "Transient database error, retrying"
+                );
+                tokio::time::sleep(policy.delay_before_retry(attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Postgres error carrying only a SQLSTATE code
+    #[derive(Debug)]
+    struct PgError(&'static str);
+
+    impl std::fmt::Display for PgError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "SQLSTATE {}", self.0)
+        }
+    }
+
+    impl std::error::Error for PgError {}
+
+    impl sqlx::error::DatabaseError for PgError {
+        fn message(&self) -> &str {
+            self.0
+        }
+
+        fn code(&self) -> Option<Cow<'_, str>> {
+            Some(Cow::Borrowed(self.0))
+        }
+
+        fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+            self
+        }
+
+        fn kind(&self) -> sqlx::error::ErrorKind {
+            match self.0 {
+                "23505" => sqlx::error::ErrorKind::UniqueViolation,
+                _ => sqlx::error::ErrorKind::Other,
+            }
+        }
+    }
+
+    fn exec_error(code: &'static str) -> DbErr {
+        DbErr::Exec(RuntimeErr::SqlxError(sqlx::Error::Database(Box::new(
+            PgError(code),
+        ))))
+    }
+
+    fn connection_reset() -> DbErr {
+        DbErr::Exec(RuntimeErr::SqlxError(sqlx::Error::Io(
+            std::io::Error::from(std::io::ErrorKind::ConnectionReset),
+        )))
+    }
+
+    const NO_DELAY: RetryPolicy = RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::ZERO,
+    };
+
+    #[test]
+    fn test_connection_errors_are_transient() {
+        assert!(is_transient_db_error(&connection_reset()));
+        assert!(is_transient_db_error(&DbErr::Exec(RuntimeErr::SqlxError(
+            sqlx::Error::PoolTimedOut
+        ))));
+        assert!(is_transient_db_error(&exec_error("08006")));
+        assert!(is_transient_db_error(&exec_error("40P01")));
+    }
+
+    #[test]
+    fn test_constraint_violations_are_not_transient() {
+        assert!(!is_transient_db_error(&exec_error("23505")));
+        assert!(!is_transient_db_error(&exec_error("23503")));
+        assert!(!is_transient_db_error(&DbErr::RecordNotInserted));
+        assert!(!is_transient_db_error(&DbErr::Custom(
+            "connection reset".into()
+        )));
+    }
+
+    #[test]
+    fn test_retry_delay_doubles() {
+        let policy = RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(100),
+        };
+
+        assert_eq!(policy.delay_before_retry(1), Duration::from_millis(100));
+        assert_eq!(policy.delay_before_retry(3), Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn test_transient_error_is_retried() {
+        let calls = &AtomicU32::new(0);
+
+        let result = retry_transient(NO_DELAY, || async move {
+            if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                Err(connection_reset())
+            } else {
+                Ok("saved")
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), "saved");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_constraint_error_is_not_retried() {
+        let calls = &AtomicU32::new(0);
+
+        let result: Result<(), DbErr> = retry_transient(NO_DELAY, || async move {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(exec_error("23505"))
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_transient_error_stops_after_max_attempts() {
+        let calls = &AtomicU32::new(0);
+
+        let result: Result<(), DbErr> = retry_transient(NO_DELAY, || async move {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(connection_reset())
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), NO_DELAY.max_attempts);
+    }
+}
@@ -0,0 +1,30 @@
+use std::{future::Future, pin::Pin};
+
+use sea_orm::{DatabaseConnection, DatabaseTransaction, DbErr, TransactionError, TransactionTrait};
+
+/// Runs a multi-step operation inside a single database transaction
+///
+/// The transaction is committed when `operation` returns `Ok` and rolled back on
+/// any error, so a failure halfway through leaves no partial writes behind.
+/// Repository functions taking a generic `ConnectionTrait` can be called with the
+/// transaction handed to `operation`.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The transaction cannot be started or committed
+/// - `operation` fails (after rolling back)
+pub async fn run_in_transaction<F, T, E>(db: &DatabaseConnection, operation: F) -> Result<T, E>
+where
+    F: for<'c> FnOnce(
+            &'c DatabaseTransaction,
+        ) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'c>>
+        + Send,
+    T: Send,
+    E: std::error::Error + From<DbErr> + Send,
+{
+    db.transaction(operation).await.map_err(|err| match err {
+        TransactionError::Connection(db_err) => E::from(db_err),
+        TransactionError::Transaction(err) => err,
+    })
+}
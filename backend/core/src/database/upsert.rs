@@ -0,0 +1,146 @@
+use sea_orm::{
+    sea_query::{Alias, Expr, IntoIden, OnConflict, SimpleExpr},
+    ActiveModelTrait, ConnectionTrait, DbErr, EntityTrait, Insert, IntoActiveModel,
+};
+
+/// Inserts `models`, resolving rows that collide on a natural key with `on_conflict`
+///
+/// Everything happens in a single `INSERT ... ON CONFLICT` statement, so the
+/// database arbitrates concurrent writers: two syncs upserting the same row never
+/// both insert it, unlike selecting first and then deciding between insert and update.
+///
+/// # Returns
+///
+/// The inserted and updated rows. Rows skipped by a `DO NOTHING` action or by the
+/// conflict's `WHERE` clause are not returned.
+///
+/// # Errors
+///
+/// Returns an error if the statement fails
+pub async fn upsert_many<A, C>(
+    db: &C,
+    models: Vec<A>,
+    on_conflict: OnConflict,
+) -> Result<Vec<<A::Entity as EntityTrait>::Model>, DbErr>
+where
+    A: ActiveModelTrait,
+    <A::Entity as EntityTrait>::Model: IntoActiveModel<A>,
+    C: ConnectionTrait,
+{
+    if models.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    upsert_many_query(models, on_conflict)
+        .exec_with_returning_many(db)
+        .await
+}
+
+/// Upserts a single row with `upsert_many`, failing if the conflict skipped it
+///
+/// # Errors
+///
+/// Returns an error if the statement fails, or `DbErr::RecordNotInserted` if the
+/// row was neither inserted nor updated
+pub async fn upsert_one<A, C>(
+    db: &C,
+    model: A,
+    on_conflict: OnConflict,
+) -> Result<<A::Entity as EntityTrait>::Model, DbErr>
+where
+    A: ActiveModelTrait,
+    <A::Entity as EntityTrait>::Model: IntoActiveModel<A>,
+    C: ConnectionTrait,
+{
+    upsert_many(db, vec![model], on_conflict)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or(DbErr::RecordNotInserted)
+}
+
+pub(crate) fn upsert_many_query<A: ActiveModelTrait>(
+    models: Vec<A>,
+    on_conflict: OnConflict,
+) -> Insert<A> {
+    <A::Entity as EntityTrait>::insert_many(models).on_conflict(on_conflict)
+}
+
+/// The value the conflicting insert proposed for `column` (`"excluded"."column"`)
+#[must_use]
+pub fn excluded<C: IntoIden>(column: C) -> SimpleExpr {
+    Expr::col((Alias::new("excluded"), column)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::listen;
+    use sea_orm::{ActiveValue::Set, DbBackend, MockDatabase, QueryTrait};
+    use uuid::Uuid;
+
+    fn make_listen() -> listen::ActiveModel {
+        listen::ActiveModel {
+            id: Set(Uuid::nil()),
+            user_id: Set(Uuid::nil()),
+            track_id: Set(Uuid::nil()),
+            played_at: Set(chrono::DateTime::UNIX_EPOCH.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_upsert_query_resolves_conflicts_on_the_natural_key() {
+        let on_conflict = OnConflict::columns([listen::Column::UserId, listen::Column::PlayedAt])
+            .update_column(listen::Column::TrackId)
+            .to_owned();
+
+        let sql = upsert_many_query(vec![make_listen()], on_conflict)
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        assert!(sql.starts_with(r#"INSERT INTO "listen""#), "{sql}");
+        assert!(
+            sql.ends_with(
+                r#"ON CONFLICT ("user_id", "played_at") DO UPDATE SET "track_id" = "excluded"."track_id""#
+            ),
+            "{sql}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upserting_nothing_skips_the_database() {
+        let db = MockDatabase::new(DbBackend::Postgres).into_connection();
+
+        let rows = upsert_many(
+            &db,
+            Vec::<listen::ActiveModel>::new(),
+            OnConflict::column(listen::Column::Id)
+                .do_nothing()
+                .to_owned(),
+        )
+        .await
+        .unwrap();
+
+        assert!(rows.is_empty());
+        assert!(db.into_transaction_log().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_skipped_single_upsert_is_an_error() {
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([Vec::<listen::Model>::new()])
+            .into_connection();
+
+        let result = upsert_one(
+            &db,
+            make_listen(),
+            OnConflict::column(listen::Column::Id)
+                .do_nothing()
+                .to_owned(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(DbErr::RecordNotInserted)));
+    }
+}
@@ -1,3 +1,5 @@
+pub mod similarity;
 pub mod simplification;
 
+pub use similarity::*;
 pub use simplification::*;
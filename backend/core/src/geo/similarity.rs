@@ -0,0 +1,300 @@
+//! Approximate route similarity, to find activities run on the same course
+//!
+//! Routes are compared in two passes: a cheap bounding-box overlap that only needs
+//! each route's extent, then the discrete Fréchet distance between the routes
+//! resampled to a few dozen evenly spaced points. Resampling keeps the comparison
+//! fast and independent of the recording rate, at the cost of ignoring detours
+//! shorter than the sample spacing.
+
+use serde::Serialize;
+
+use super::simplification::{equirectangular_distance, GpsPoint};
+
+/// Points each route is resampled to before computing the Fréchet distance
+pub const ROUTE_SAMPLE_POINTS: usize = 64;
+
+/// Bounding-box overlap (intersection over union) from which routes are compared
+pub const MIN_BOUNDS_OVERLAP: f64 = 0.5;
+
+/// Fréchet distance in meters up to which two routes count as the same course
+pub const MAX_SIMILAR_FRECHET_METERS: f64 = 150.0;
+
+/// Margin added around bounding boxes before comparing them, in degrees (~100 m)
+///
+/// Absorbs GPS noise, and gives straight routes, whose boxes have no area, a
+/// box to overlap with.
+const BOUNDS_MARGIN_DEGREES: f64 = 0.001;
+
+/// Latitude/longitude extent of a route
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RouteBounds {
+    pub min_lat: f64,
+    pub max_lat: f64,
+    pub min_lng: f64,
+    pub max_lng: f64,
+}
+
+impl RouteBounds {
+    /// Extent of `(lat, lng)` points, `None` for an empty route
+    #[must_use]
+    pub fn from_points(points: &[(f64, f64)]) -> Option<Self> {
+        let (&(lat, lng), rest) = points.split_first()?;
+        let start = Self {
+            min_lat: lat,
+            max_lat: lat,
+            min_lng: lng,
+            max_lng: lng,
+        };
+        Some(rest.iter().fold(start, |bounds, &(lat, lng)| Self {
+            min_lat: bounds.min_lat.min(lat),
+            max_lat: bounds.max_lat.max(lat),
+            min_lng: bounds.min_lng.min(lng),
+            max_lng: bounds.max_lng.max(lng),
+        }))
+    }
+
+    /// Intersection over union of the two boxes: 1 for identical boxes, 0 for distant ones
+    #[must_use]
+    pub fn overlap(self, other: Self) -> f64 {
+        let (a, b) = (self.padded(), other.padded());
+        let lat = (a.max_lat.min(b.max_lat) - a.min_lat.max(b.min_lat)).max(0.0);
+        let lng = (a.max_lng.min(b.max_lng) - a.min_lng.max(b.min_lng)).max(0.0);
+        let intersection = lat * lng;
+        intersection / (a.area() + b.area() - intersection)
+    }
+
+    fn padded(self) -> Self {
+        Self {
+            min_lat: self.min_lat - BOUNDS_MARGIN_DEGREES,
+            max_lat: self.max_lat + BOUNDS_MARGIN_DEGREES,
+            min_lng: self.min_lng - BOUNDS_MARGIN_DEGREES,
+            max_lng: self.max_lng + BOUNDS_MARGIN_DEGREES,
+        }
+    }
+
+    fn area(self) -> f64 {
+        (self.max_lat - self.min_lat) * (self.max_lng - self.min_lng)
+    }
+}
+
+/// Resamples a `(lat, lng)` route to `count` points evenly spaced along its length
+///
+/// Routes with fewer than 2 points or no length, and counts below 2, return the
+/// route unchanged.
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn resample_route(points: &[(f64, f64)], count: usize) -> Vec<(f64, f64)> {
+    let mut cumulative = Vec::with_capacity(points.len());
+    let mut total = 0.0;
+    cumulative.push(0.0);
+    for pair in points.windows(2) {
+        total += equirectangular_distance(to_gps(pair[0]), to_gps(pair[1]));
+        cumulative.push(total);
+    }
+    if count < 2 || points.len() < 2 || total <= 0.0 {
+        return points.to_vec();
+    }
+
+    let mut segment = 0;
+    (0..count)
+        .map(|k| {
+            let target = total * k as f64 / (count - 1) as f64;
+            while segment + 2 < cumulative.len() && cumulative[segment + 1] < target {
+                segment += 1;
+            }
+            let (start, end) = (cumulative[segment], cumulative[segment + 1]);
+            let t = if end > start {
+                ((target - start) / (end - start)).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let (a, b) = (points[segment], points[segment + 1]);
+            (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+        })
+        .collect()
+}
+
+/// Discrete Fréchet distance between two `(lat, lng)` routes in meters
+///
+/// The shortest leash letting two walkers cover both routes from start to end
+/// without going back, so the same course run in opposite directions is far apart.
+/// Runs in `O(a.len() * b.len())`: resample long routes first.
+///
+/// Returns `None` if either route is empty.
+#[must_use]
+pub fn frechet_distance_meters(a: &[(f64, f64)], b: &[(f64, f64)]) -> Option<f64> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+
+    // Only the previous row of the coupling table is needed
+    let mut previous = vec![0.0; b.len()];
+    let mut current = vec![0.0; b.len()];
+    for (i, &point_a) in a.iter().enumerate() {
+        for (j, &point_b) in b.iter().enumerate() {
+            let distance = equirectangular_distance(to_gps(point_a), to_gps(point_b));
+            current[j] = match (i, j) {
+                (0, 0) => distance,
+                (0, _) => f64::max(current[j - 1], distance),
+                (_, 0) => f64::max(previous[0], distance),
+                _ => previous[j]
+                    .min(previous[j - 1])
+                    .min(current[j - 1])
+                    .max(distance),
+            };
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous.last().copied()
+}
+
+/// How close two routes are
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RouteSimilarity {
+    /// Bounding-box intersection over union, see [`RouteBounds::overlap`]
+    pub bounds_overlap: f64,
+    /// Fréchet distance between the resampled routes in meters, infinite when the
+    /// boxes overlap too little to be compared
+    pub frechet_meters: f64,
+}
+
+impl RouteSimilarity {
+    /// Whether both routes follow the same course
+    #[must_use]
+    pub fn is_similar(self) -> bool {
+        self.bounds_overlap >= MIN_BOUNDS_OVERLAP
+            && self.frechet_meters <= MAX_SIMILAR_FRECHET_METERS
+    }
+}
+
+/// Compares two `(lat, lng)` routes, `None` if either has no points
+///
+/// Routes whose boxes overlap less than [`MIN_BOUNDS_OVERLAP`] are not resampled.
+#[must_use]
+pub fn route_similarity(a: &[(f64, f64)], b: &[(f64, f64)]) -> Option<RouteSimilarity> {
+    let bounds_overlap = RouteBounds::from_points(a)?.overlap(RouteBounds::from_points(b)?);
+    let frechet_meters = if bounds_overlap < MIN_BOUNDS_OVERLAP {
+        f64::INFINITY
+    } else {
+        frechet_distance_meters(
+            &resample_route(a, ROUTE_SAMPLE_POINTS),
+            &resample_route(b, ROUTE_SAMPLE_POINTS),
+        )?
+    };
+
+    Some(RouteSimilarity {
+        bounds_overlap,
+        frechet_meters,
+    })
+}
+
+fn to_gps((lat, lng): (f64, f64)) -> GpsPoint {
+    GpsPoint::new(lat, lng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    /// Loop of roughly 800 m radius around a park, sampled at `count` points
+    #[allow(clippy::cast_precision_loss)]
+    fn loop_route(count: usize, noise_degrees: f64) -> Vec<(f64, f64)> {
+        (0..count)
+            .map(|i| {
+                let angle = TAU * i as f64 / (count - 1) as f64;
+                // Alternate the offset so the noise doesn't shift the whole loop
+                let noise = if i % 2 == 0 {
+                    noise_degrees
+                } else {
+                    -noise_degrees
+                };
+                (
+                    48.85 + 0.007 * angle.sin() + noise,
+                    2.35 + 0.0107 * angle.cos() - noise,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_near_identical_routes_are_similar() {
+        // Same loop recorded at another rate, with ~5 m of GPS noise
+        let similarity =
+            route_similarity(&loop_route(200, 0.0), &loop_route(150, 0.000_045)).unwrap();
+
+        assert!(similarity.bounds_overlap > 0.9, "{similarity:?}");
+        assert!(similarity.frechet_meters < 50.0, "{similarity:?}");
+        assert!(similarity.is_similar());
+    }
+
+    #[test]
+    fn test_route_across_the_same_area_is_not_similar() {
+        // Out and back along the diagonal of the loop's bounding box
+        let diagonal = [(48.843, 2.3393), (48.857, 2.3607), (48.843, 2.3393)];
+        let similarity = route_similarity(&loop_route(200, 0.0), &diagonal).unwrap();
+
+        assert!(
+            similarity.bounds_overlap >= MIN_BOUNDS_OVERLAP,
+            "{similarity:?}"
+        );
+        assert!(
+            similarity.frechet_meters > MAX_SIMILAR_FRECHET_METERS,
+            "{similarity:?}"
+        );
+        assert!(!similarity.is_similar());
+    }
+
+    #[test]
+    fn test_distant_routes_are_not_compared() {
+        let elsewhere: Vec<(f64, f64)> = loop_route(200, 0.0)
+            .into_iter()
+            .map(|(lat, lng)| (lat, lng + 0.05))
+            .collect();
+
+        let similarity = route_similarity(&loop_route(200, 0.0), &elsewhere).unwrap();
+
+        assert!(similarity.bounds_overlap < f64::EPSILON);
+        assert!(similarity.frechet_meters.is_infinite());
+        assert!(!similarity.is_similar());
+    }
+
+    #[test]
+    fn test_reversed_route_is_not_similar() {
+        let forward = [(48.85, 2.35), (48.86, 2.35), (48.86, 2.36)];
+        let backward: Vec<(f64, f64)> = forward.iter().rev().copied().collect();
+
+        assert!(!route_similarity(&forward, &backward).unwrap().is_similar());
+    }
+
+    #[test]
+    fn test_resampled_points_are_evenly_spaced() {
+        // 1.1 km north, sampled unevenly: one long step then two short ones
+        let route = [
+            (48.85, 2.35),
+            (48.859, 2.35),
+            (48.8595, 2.35),
+            (48.86, 2.35),
+        ];
+
+        let resampled = resample_route(&route, 5);
+
+        assert_eq!(resampled.len(), 5);
+        assert!((resampled[0].0 - 48.85).abs() < 1e-9);
+        assert!((resampled[4].0 - 48.86).abs() < 1e-9);
+        let steps: Vec<f64> = resampled
+            .windows(2)
+            .map(|pair| equirectangular_distance(to_gps(pair[0]), to_gps(pair[1])))
+            .collect();
+        assert!(
+            steps.iter().all(|step| (step - steps[0]).abs() < 1e-6),
+            "{steps:?}"
+        );
+    }
+
+    #[test]
+    fn test_empty_routes_have_no_similarity() {
+        assert!(route_similarity(&[], &[(48.85, 2.35)]).is_none());
+        assert!(frechet_distance_meters(&[(48.85, 2.35)], &[]).is_none());
+    }
+}
@@ -1,16 +1,25 @@
-//! GPS route simplification using the Ramer-Douglas-Peucker algorithm
+//! GPS route simplification using the Ramer-Douglas-Peucker or Visvalingam-Whyatt algorithm
 //!
 //! Reduces the number of points in a GPS track while preserving the overall
 //! route shape. Returns indices of points to keep rather than copying data,
 //! which preserves all metadata from the original activity stream.
 
 use crate::database::entities::activity_stream;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 use std::f64::consts::PI;
 
 /// Earth's mean radius approximation: meters per degree of latitude
 /// This value is constant globally (~111.32 km per degree)
 const METERS_PER_DEGREE_LAT: f64 = 111_320.0;
 
+/// Distances closer than this (in meters) are ties in the farthest-point search
+///
+/// Far below GPS precision, but wide enough to absorb floating-point noise
+/// that differs between platforms.
+const FARTHEST_POINT_TIE_EPSILON_METERS: f64 = 1e-6;
+
 /// Errors that can occur during route simplification
 #[derive(Debug, thiserror::Error)]
 pub enum SimplificationError {
@@ -19,17 +28,32 @@ pub enum SimplificationError {
 
     #[error("No valid GPS coordinates found in activity stream")]
     NoGpsCoordinates,
+
+    #[error("Point budget must be at least 2, got {0}")]
+    InvalidPointBudget(usize),
+}
+
+/// Line simplification algorithm applied to GPS routes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, strum::Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum SimplificationAlgorithm {
+    /// Ramer-Douglas-Peucker: drops points closer than the tolerance to the simplified line
+    #[default]
+    Rdp,
+    /// Visvalingam-Whyatt: drops points whose triangle area is below the tolerance squared
+    Vw,
 }
 
 /// Internal representation of a GPS coordinate for calculations
 #[derive(Debug, Clone, Copy)]
-struct GpsPoint {
+pub(super) struct GpsPoint {
     lat: f64,
     lng: f64,
 }
 
 impl GpsPoint {
-    fn new(lat: f64, lng: f64) -> Self {
+    pub(super) fn new(lat: f64, lng: f64) -> Self {
         Self { lat, lng }
     }
 }
@@ -51,6 +75,11 @@ impl GpsPoint {
 /// These indices can be used to filter the original slice while preserving all metadata
 /// (time, `heart_rate`, cadence, etc.).
 ///
+/// # Determinism
+///
+/// When several points are equally far from a segment (within a micrometer), the one
+/// with the lowest index is kept, so the output is the same on every platform.
+///
 /// # Errors
 ///
 /// Returns error if:
@@ -101,6 +130,357 @@ pub fn simplify_gps_route(
     Ok(result)
 }
 
+/// Simplifies a GPS route down to at most `max_points` points
+///
+/// Variant of [`simplify_gps_route`] driven by a point budget instead of a
+/// distance tolerance. Segments are split greedily at the point with the largest
+/// perpendicular distance until the budget is reached, so the most significant
+/// shape-defining points are always kept first.
+///
+/// # Arguments
+///
+/// * `points` - Slice of activity stream models with GPS coordinates
+/// * `max_points` - Maximum number of indices to return (must be at least 2)
+///
+/// # Returns
+///
+/// Vector of indices to keep from the original points slice, sorted in ascending order.
+/// If the route already has `max_points` GPS points or fewer, all of them are returned.
+///
+/// # Errors
+///
+/// Returns error if:
+/// - `max_points` is lower than 2
+/// - No valid GPS coordinates found in input (all lat/lng are None)
+pub fn simplify_gps_route_to_count(
+    points: &[activity_stream::Model],
+    max_points: usize,
+) -> Result<Vec<usize>, SimplificationError> {
+    if max_points < 2 {
+        return Err(SimplificationError::InvalidPointBudget(max_points));
+    }
+
+    let (gps_points, index_map) = extract_gps_points(points);
+
+    if gps_points.len() < 2 {
+        return Err(SimplificationError::NoGpsCoordinates);
+    }
+
+    if gps_points.len() <= max_points {
+        return Ok(index_map);
+    }
+
+    let keep = keep_most_significant(gps_points.len(), max_points, |start, end| {
+        find_farthest_point(&gps_points, start, end)
+    });
+
+    Ok(keep
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &keep)| if keep { Some(index_map[i]) } else { None })
+        .collect())
+}
+
+/// Reduces a profile series such as (distance, altitude) to at most `max_points` points
+///
+/// Same budget-driven split as `simplify_gps_route_to_count`, measuring each point
+/// by its vertical offset from the line between its kept neighbours. `x` values
+/// must be non-decreasing.
+///
+/// # Returns
+///
+/// Vector of indices to keep, sorted in ascending order. First and last points are
+/// always kept; a series of `max_points` points or fewer is returned whole.
+///
+/// # Errors
+///
+/// Returns error if `max_points` is lower than 2
+pub fn simplify_profile_to_count(
+    points: &[(f64, f64)],
+    max_points: usize,
+) -> Result<Vec<usize>, SimplificationError> {
+    if max_points < 2 {
+        return Err(SimplificationError::InvalidPointBudget(max_points));
+    }
+
+    if points.len() <= max_points {
+        return Ok((0..points.len()).collect());
+    }
+
+    let keep = keep_most_significant(points.len(), max_points, |start, end| {
+        find_farthest_profile_point(points, start, end)
+    });
+
+    Ok(keep
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &keep)| keep.then_some(i))
+        .collect())
+}
+
+/// Keeps the endpoints plus the points farthest from the simplified line, up to `max_points`
+///
+/// `farthest` returns the farthest point strictly between two kept indices with its
+/// distance, or the start index when none stands out.
+fn keep_most_significant(
+    n: usize,
+    max_points: usize,
+    farthest: impl Fn(usize, usize) -> (usize, f64),
+) -> Vec<bool> {
+    let mut keep = vec![false; n];
+    keep[0] = true;
+    keep[n - 1] = true;
+    let mut kept = 2;
+
+    let mut queue = BinaryHeap::new();
+    push_split_candidate(&mut queue, &farthest, 0, n - 1);
+
+    while kept < max_points {
+        let Some(candidate) = queue.pop() else {
+            break;
+        };
+        keep[candidate.index] = true;
+        kept += 1;
+        push_split_candidate(&mut queue, &farthest, candidate.start, candidate.index);
+        push_split_candidate(&mut queue, &farthest, candidate.index, candidate.end);
+    }
+
+    keep
+}
+
+/// Candidate split point for budget-driven simplification, ordered by distance
+struct SplitCandidate {
+    distance: f64,
+    index: usize,
+    start: usize,
+    end: usize,
+}
+
+impl PartialEq for SplitCandidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SplitCandidate {}
+
+impl PartialOrd for SplitCandidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SplitCandidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Larger distance first; on ties prefer the lower index for stable output
+        self.distance
+            .total_cmp(&other.distance)
+            .then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+/// Pushes the farthest point of the `(start, end)` segment onto the queue, if any
+fn push_split_candidate(
+    queue: &mut BinaryHeap<SplitCandidate>,
+    farthest: &impl Fn(usize, usize) -> (usize, f64),
+    start: usize,
+    end: usize,
+) {
+    if end - start <= 1 {
+        return;
+    }
+
+    let (index, distance) = farthest(start, end);
+    if index == start {
+        // All intermediate points lie exactly on the line: nothing worth keeping
+        return;
+    }
+
+    queue.push(SplitCandidate {
+        distance,
+        index,
+        start,
+        end,
+    });
+}
+
+/// Simplifies a GPS route with the selected algorithm
+///
+/// `epsilon` is the distance tolerance in meters for RDP; VW uses it as the
+/// side of the minimum triangle area (`epsilon²` square meters) so both
+/// algorithms accept the same `tolerance` parameter.
+///
+/// # Errors
+///
+/// Returns error if:
+/// - `epsilon` is negative, zero, or NaN
+/// - No valid GPS coordinates found in input (all lat/lng are None)
+pub fn simplify_gps_route_with(
+    points: &[activity_stream::Model],
+    epsilon: f64,
+    algorithm: SimplificationAlgorithm,
+) -> Result<Vec<usize>, SimplificationError> {
+    match algorithm {
+        SimplificationAlgorithm::Rdp => simplify_gps_route(points, epsilon),
+        SimplificationAlgorithm::Vw => simplify_gps_route_vw(points, epsilon),
+    }
+}
+
+/// Simplifies a GPS route using the Visvalingam-Whyatt algorithm
+///
+/// Repeatedly removes the point forming the smallest triangle with its current
+/// neighbours until every remaining triangle covers at least `epsilon²` square
+/// meters. Tends to keep smoother curves than RDP for the same point count.
+///
+/// # Arguments
+///
+/// * `points` - Slice of activity stream models with GPS coordinates
+/// * `epsilon` - Tolerance in meters; the minimum kept triangle area is `epsilon²`
+///
+/// # Returns
+///
+/// Vector of indices to keep from the original points slice, sorted in ascending order.
+/// First and last indices are always included.
+///
+/// # Errors
+///
+/// Returns error if:
+/// - `epsilon` is negative, zero, or NaN
+/// - No valid GPS coordinates found in input (all lat/lng are None)
+pub fn simplify_gps_route_vw(
+    points: &[activity_stream::Model],
+    epsilon: f64,
+) -> Result<Vec<usize>, SimplificationError> {
+    if epsilon <= 0.0 || epsilon.is_nan() {
+        return Err(SimplificationError::InvalidEpsilon(epsilon));
+    }
+
+    let (gps_points, index_map) = extract_gps_points(points);
+
+    if gps_points.len() < 2 {
+        return Err(SimplificationError::NoGpsCoordinates);
+    }
+
+    let keep_flags = vw_keep_flags(&gps_points, epsilon * epsilon);
+
+    Ok(keep_flags
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &keep)| if keep { Some(index_map[i]) } else { None })
+        .collect())
+}
+
+/// Candidate point for Visvalingam-Whyatt removal, smallest area first
+struct AreaCandidate {
+    area: f64,
+    index: usize,
+    /// Revision of the point's area when pushed; stale entries are skipped
+    revision: u32,
+}
+
+impl PartialEq for AreaCandidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for AreaCandidate {}
+
+impl PartialOrd for AreaCandidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for AreaCandidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed for a min-heap; on ties remove the lower index first for stable output
+        other
+            .area
+            .total_cmp(&self.area)
+            .then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+/// Visvalingam-Whyatt elimination over a doubly linked list of points
+///
+/// # Returns
+///
+/// Vector of boolean flags indicating which points to keep
+fn vw_keep_flags(points: &[GpsPoint], min_area: f64) -> Vec<bool> {
+    let n = points.len();
+    let mut keep = vec![true; n];
+    if n <= 2 {
+        return keep;
+    }
+
+    let mut prev: Vec<usize> = (0..n).map(|i| i.saturating_sub(1)).collect();
+    let mut next: Vec<usize> = (0..n).map(|i| (i + 1).min(n - 1)).collect();
+    let mut revisions = vec![0u32; n];
+
+    let mut queue: BinaryHeap<AreaCandidate> = (1..n - 1)
+        .map(|i| AreaCandidate {
+            area: triangle_area(points[i - 1], points[i], points[i + 1]),
+            index: i,
+            revision: 0,
+        })
+        .collect();
+
+    while let Some(candidate) = queue.pop() {
+        if candidate.revision != revisions[candidate.index] {
+            continue;
+        }
+        if candidate.area >= min_area {
+            break;
+        }
+
+        let index = candidate.index;
+        keep[index] = false;
+        let (before, after) = (prev[index], next[index]);
+        next[before] = after;
+        prev[after] = before;
+
+        for neighbor in [before, after] {
+            if neighbor == 0 || neighbor == n - 1 {
+                continue;
+            }
+            // A neighbour's effective area never drops below the removed point's,
+            // otherwise it would be eliminated out of order
+            let area = triangle_area(
+                points[prev[neighbor]],
+                points[neighbor],
+                points[next[neighbor]],
+            )
+            .max(candidate.area);
+            revisions[neighbor] += 1;
+            queue.push(AreaCandidate {
+                area,
+                index: neighbor,
+                revision: revisions[neighbor],
+            });
+        }
+    }
+
+    keep
+}
+
+/// Area in square meters of the triangle formed by three GPS points
+///
+/// Uses an equirectangular projection centred on the middle point.
+fn triangle_area(a: GpsPoint, b: GpsPoint, c: GpsPoint) -> f64 {
+    let meters_per_degree_lng = METERS_PER_DEGREE_LAT * (b.lat * PI / 180.0).cos();
+    let project = |p: GpsPoint| {
+        (
+            (p.lng - b.lng) * meters_per_degree_lng,
+            (p.lat - b.lat) * METERS_PER_DEGREE_LAT,
+        )
+    };
+
+    let (ax, ay) = project(a);
+    let (cx, cy) = project(c);
+    (ax * cy - ay * cx).abs() / 2.0
+}
+
 /// Extracts valid GPS points from activity stream models
 ///
 /// Returns a tuple of (GPS points, index mapping). The index mapping
@@ -178,7 +558,9 @@ fn rdp_iterative(points: &[GpsPoint], epsilon: f64) -> Vec<bool> {
 ///
 /// # Returns
 ///
-/// Tuple of (index of farthest point, distance in meters)
+/// Tuple of (index of farthest point, distance in meters). Points within
+/// `FARTHEST_POINT_TIE_EPSILON_METERS` of the current maximum are ties, won by
+/// the lowest index.
 fn find_farthest_point(points: &[GpsPoint], start: usize, end: usize) -> (usize, f64) {
     let mut max_dist = 0.0;
     let mut max_idx = start;
@@ -188,7 +570,32 @@ fn find_farthest_point(points: &[GpsPoint], start: usize, end: usize) -> (usize,
 
     for (i, &point) in points.iter().enumerate().take(end).skip(start + 1) {
         let dist = perpendicular_distance(point, line_start, line_end);
-        if dist > max_dist {
+        if dist > max_dist + FARTHEST_POINT_TIE_EPSILON_METERS {
+            max_dist = dist;
+            max_idx = i;
+        }
+    }
+
+    (max_idx, max_dist)
+}
+
+/// Finds the profile point between `start` and `end` farthest above or below their chord
+///
+/// Ties are broken like `find_farthest_point`: the lowest index wins.
+fn find_farthest_profile_point(points: &[(f64, f64)], start: usize, end: usize) -> (usize, f64) {
+    let (x_start, y_start) = points[start];
+    let (x_end, y_end) = points[end];
+    let slope = if x_end - x_start > f64::EPSILON {
+        (y_end - y_start) / (x_end - x_start)
+    } else {
+        0.0
+    };
+
+    let mut max_dist = 0.0;
+    let mut max_idx = start;
+    for (i, &(x, y)) in points.iter().enumerate().take(end).skip(start + 1) {
+        let dist = (y - (y_start + slope * (x - x_start))).abs();
+        if dist > max_dist + FARTHEST_POINT_TIE_EPSILON_METERS {
             max_dist = dist;
             max_idx = i;
         }
@@ -237,6 +644,26 @@ fn perpendicular_distance(point: GpsPoint, line_start: GpsPoint, line_end: GpsPo
     degrees_to_meters(dist_deg, avg_lat)
 }
 
+/// Total length of the GPS route in meters, skipping points without coordinates
+#[must_use]
+pub fn route_length_meters<'a>(
+    points: impl IntoIterator<Item = &'a activity_stream::Model>,
+) -> f64 {
+    let mut previous: Option<GpsPoint> = None;
+    let mut length = 0.0;
+    for point in points {
+        let (Some(lat), Some(lng)) = (point.latitude, point.longitude) else {
+            continue;
+        };
+        let current = GpsPoint::new(lat, lng);
+        if let Some(previous) = previous {
+            length += equirectangular_distance(previous, current);
+        }
+        previous = Some(current);
+    }
+    length
+}
+
 /// Calculates distance between two GPS points using equirectangular projection
 ///
 /// This is 30x faster than Haversine with <0.5% error for typical activity
@@ -251,7 +678,7 @@ fn perpendicular_distance(point: GpsPoint, line_start: GpsPoint, line_end: GpsPo
 /// # Returns
 ///
 /// Distance in meters
-fn equirectangular_distance(p1: GpsPoint, p2: GpsPoint) -> f64 {
+pub(super) fn equirectangular_distance(p1: GpsPoint, p2: GpsPoint) -> f64 {
     let avg_lat_rad = f64::midpoint(p1.lat, p2.lat) * PI / 180.0;
     let meters_per_degree_lng = METERS_PER_DEGREE_LAT * avg_lat_rad.cos();
 
@@ -298,9 +725,50 @@ mod tests {
             velocity: None,
             distance: None,
             temperature: None,
+            grade: None,
+            moving: None,
         }
     }
 
+    #[test]
+    fn test_farthest_point_tie_keeps_lowest_index() {
+        // Mirror images on both sides of a north-south segment: equally far from it
+        let points = [
+            GpsPoint::new(48.0, 2.0),
+            GpsPoint::new(48.001, 2.001),
+            GpsPoint::new(48.002, 1.999),
+            GpsPoint::new(48.003, 2.0),
+        ];
+
+        let (index, distance) = find_farthest_point(&points, 0, 3);
+
+        assert_eq!(index, 1);
+        assert!(distance > 0.0);
+    }
+
+    #[test]
+    fn test_farthest_point_noise_within_epsilon_is_a_tie() {
+        // The later point is farther only by floating-point-noise scale (~1e-8 m)
+        let points = [
+            GpsPoint::new(48.0, 2.0),
+            GpsPoint::new(48.001, 2.001),
+            GpsPoint::new(48.002, 1.999 - 1e-13),
+            GpsPoint::new(48.003, 2.0),
+        ];
+
+        assert_eq!(find_farthest_point(&points, 0, 3).0, 1);
+
+        // A clearly farther point still wins
+        let points = [
+            GpsPoint::new(48.0, 2.0),
+            GpsPoint::new(48.001, 2.001),
+            GpsPoint::new(48.002, 1.998),
+            GpsPoint::new(48.003, 2.0),
+        ];
+
+        assert_eq!(find_farthest_point(&points, 0, 3).0, 2);
+    }
+
     #[test]
     fn test_empty_slice() {
         let points: Vec<activity_stream::Model> = vec![];
@@ -447,6 +915,80 @@ mod tests {
         assert!((dist - 1000.0).abs() < 50.0, "Distance: {dist}");
     }
 
+    #[test]
+    fn test_route_length_skips_points_without_gps() {
+        // Two legs of ~1km east, with an indoor point in between
+        let mut points = vec![
+            make_point(48.8566, 2.3522),
+            make_point(48.8566, 2.3662),
+            make_point(48.8566, 2.3662),
+            make_point(48.8566, 2.3802),
+        ];
+        points[2].latitude = None;
+
+        let length = route_length_meters(&points);
+
+        assert!((length - 2000.0).abs() < 100.0, "Length: {length}");
+    }
+
+    #[test]
+    fn test_to_count_respects_budget() {
+        let points: Vec<activity_stream::Model> = (0..200)
+            .map(|i| {
+                let offset = f64::from(i) * 0.001;
+                let zigzag = if i % 2 == 0 { 0.0 } else { 0.0005 };
+                make_point(48.0 + offset, 2.0 + zigzag)
+            })
+            .collect();
+
+        let result = simplify_gps_route_to_count(&points, 25).unwrap();
+
+        assert_eq!(result.len(), 25);
+        assert_eq!(result[0], 0);
+        assert_eq!(result[result.len() - 1], 199);
+        assert!(
+            result.windows(2).all(|w| w[0] < w[1]),
+            "Indices should be strictly ascending"
+        );
+    }
+
+    #[test]
+    fn test_to_count_under_budget_keeps_all() {
+        let points = vec![
+            make_point(48.0, 2.0),
+            make_point(48.1, 2.0),
+            make_point(48.0, 2.1),
+        ];
+
+        let result = simplify_gps_route_to_count(&points, 10).unwrap();
+        assert_eq!(result, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_to_count_invalid_budget() {
+        let points = vec![make_point(48.0, 2.0), make_point(48.1, 2.1)];
+        let result = simplify_gps_route_to_count(&points, 1);
+        assert!(matches!(
+            result,
+            Err(SimplificationError::InvalidPointBudget(1))
+        ));
+    }
+
+    #[test]
+    fn test_to_count_keeps_most_significant_point() {
+        // A single sharp corner should be the first point kept after the endpoints
+        let points = vec![
+            make_point(48.0, 2.0),
+            make_point(48.0001, 2.0),
+            make_point(48.1, 2.0),
+            make_point(48.1, 2.05),
+            make_point(48.1, 2.1),
+        ];
+
+        let result = simplify_gps_route_to_count(&points, 3).unwrap();
+        assert_eq!(result, vec![0, 2, 4]);
+    }
+
     #[test]
     fn test_always_keeps_first_and_last() {
         let points = vec![
@@ -463,4 +1005,82 @@ mod tests {
         assert_eq!(result[0], 0);
         assert_eq!(result[result.len() - 1], 4);
     }
+
+    #[test]
+    fn test_vw_straight_line_keeps_endpoints() {
+        let points: Vec<_> = (0..10)
+            .map(|i| make_point(48.0 + f64::from(i) * 0.001, 2.0))
+            .collect();
+
+        let result = simplify_gps_route_vw(&points, 1.0).unwrap();
+        assert_eq!(result, vec![0, 9]);
+    }
+
+    #[test]
+    fn test_vw_zigzag_reduction() {
+        let points = vec![
+            make_point(48.0, 2.0),
+            make_point(48.01, 2.01),
+            make_point(48.02, 2.0),
+            make_point(48.03, 2.01),
+            make_point(48.04, 2.0),
+        ];
+
+        // Triangles here cover about 0.8 km², far above 10m × 10m
+        assert_eq!(simplify_gps_route_vw(&points, 10.0).unwrap().len(), 5);
+        assert_eq!(simplify_gps_route_vw(&points, 5000.0).unwrap(), vec![0, 4]);
+    }
+
+    #[test]
+    fn test_vw_invalid_epsilon() {
+        let points = vec![make_point(48.0, 2.0), make_point(48.1, 2.1)];
+        assert!(matches!(
+            simplify_gps_route_vw(&points, 0.0),
+            Err(SimplificationError::InvalidEpsilon(_))
+        ));
+    }
+
+    #[test]
+    fn test_algorithm_from_query_value() {
+        let vw: SimplificationAlgorithm = serde_json::from_str(r#""vw""#).unwrap();
+        assert_eq!(vw, SimplificationAlgorithm::Vw);
+        assert!(serde_json::from_str::<SimplificationAlgorithm>(r#""douglas""#).is_err());
+    }
+
+    #[test]
+    fn test_profile_keeps_peak_within_budget() {
+        // Flat, a single 50 m climb and descent, flat again
+        let profile: Vec<(f64, f64)> = (0..100)
+            .map(|i| {
+                let x = f64::from(i) * 10.0;
+                let y = if (40..=60).contains(&i) {
+                    100.0 + 50.0 - f64::from((i - 50).abs()) * 5.0
+                } else {
+                    100.0
+                };
+                (x, y)
+            })
+            .collect();
+
+        let kept = simplify_profile_to_count(&profile, 5).unwrap();
+
+        assert_eq!(kept.len(), 5);
+        assert_eq!(kept.first(), Some(&0));
+        assert_eq!(kept.last(), Some(&99));
+        assert!(kept.contains(&50), "{kept:?}");
+    }
+
+    #[test]
+    fn test_short_profile_is_kept_whole() {
+        let profile = vec![(0.0, 10.0), (5.0, 12.0), (10.0, 11.0)];
+
+        assert_eq!(
+            simplify_profile_to_count(&profile, 10).unwrap(),
+            vec![0, 1, 2]
+        );
+        assert!(matches!(
+            simplify_profile_to_count(&profile, 1),
+            Err(SimplificationError::InvalidPointBudget(1))
+        ));
+    }
 }
@@ -5,3 +5,6 @@ pub mod database;
 pub mod geo;
 pub mod models;
 pub mod services;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+pub mod units;
@@ -18,6 +18,8 @@ pub struct CreateActivityDto {
     pub timezone: String,
     pub distance: f32,
     pub total_elevation_gain: f32,
+    /// Strava has no streams for this activity (manual entry)
+    pub streams_unavailable: bool,
 }
 
 impl CreateActivityDto {
@@ -50,13 +52,14 @@ impl CreateActivityDto {
             timezone: response.timezone,
             distance: response.distance,
             total_elevation_gain: response.total_elevation_gain,
+            streams_unavailable: response.manual,
         })
     }
 
     /// Converts the DTO into a `SeaORM` `ActiveModel` for insertion
     #[must_use]
     pub fn into_active_model(self) -> activity::ActiveModel {
-        use sea_orm::ActiveValue::Set;
+        use sea_orm::ActiveValue::{NotSet, Set};
 
         activity::ActiveModel {
             id: Set(Uuid::new_v4()),
@@ -71,6 +74,16 @@ impl CreateActivityDto {
             timezone: Set(self.timezone),
             distance: Set(self.distance),
             total_elevation_gain: Set(self.total_elevation_gain),
+            streams_unavailable: Set(self.streams_unavailable),
+            // Set by the user when realigning music, never by a sync
+            time_offset_seconds: NotSet,
+            avg_heart_rate: NotSet,
+            max_heart_rate: NotSet,
+            stream_elevation_gain: NotSet,
+            stream_moving_time: NotSet,
+            summary_computed_at: NotSet,
+            stream_original_size: NotSet,
+            best_efforts_fetched_at: NotSet,
             created_at: Set(chrono::Utc::now().into()),
             updated_at: Set(chrono::Utc::now().into()),
         }
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+use crate::config::activity_type_aliases;
+
+/// Canonical sport an activity type belongs to
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize, Display, EnumString,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum ActivityCategory {
+    Run,
+    Ride,
+    Swim,
+    /// Every type without a known category
+    #[default]
+    Other,
+}
+
+/// Strava activity types with a known category
+const BUILT_IN_ALIASES: &[(&str, ActivityCategory)] = &[
+    ("Run", ActivityCategory::Run),
+    ("TrailRun", ActivityCategory::Run),
+    ("VirtualRun", ActivityCategory::Run),
+    ("Ride", ActivityCategory::Ride),
+    ("VirtualRide", ActivityCategory::Ride),
+    ("MountainBikeRide", ActivityCategory::Ride),
+    ("GravelRide", ActivityCategory::Ride),
+    ("EBikeRide", ActivityCategory::Ride),
+    ("EMountainBikeRide", ActivityCategory::Ride),
+    ("Velomobile", ActivityCategory::Ride),
+    ("Swim", ActivityCategory::Swim),
+];
+
+/// Mapping from raw activity type strings to their canonical category
+///
+/// Starts from Strava's types and can be extended (or overridden) through
+/// `ACTIVITY_TYPE_ALIASES`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActivityTypeAliases {
+    aliases: HashMap<String, ActivityCategory>,
+}
+
+impl ActivityTypeAliases {
+    /// Built-in Strava mapping with `extra` aliases taking precedence
+    #[must_use]
+    pub fn new(extra: HashMap<String, ActivityCategory>) -> Self {
+        let mut aliases: HashMap<String, ActivityCategory> = BUILT_IN_ALIASES
+            .iter()
+            .map(|&(raw, category)| (raw.to_string(), category))
+            .collect();
+        aliases.extend(extra);
+        Self { aliases }
+    }
+
+    /// Built-in mapping extended with the aliases configured in `ACTIVITY_TYPE_ALIASES`
+    #[must_use]
+    pub fn from_env() -> Self {
+        Self::new(activity_type_aliases())
+    }
+
+    /// Category of a raw activity type, `Other` when it is unknown
+    #[must_use]
+    pub fn classify(&self, activity_type: &str) -> ActivityCategory {
+        self.aliases
+            .get(activity_type.trim())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Raw types known to belong to `category`, sorted
+    ///
+    /// Unknown types also classify as `Other` but cannot be listed here.
+    #[must_use]
+    pub fn types_in(&self, category: ActivityCategory) -> Vec<&str> {
+        let mut types: Vec<&str> = self
+            .aliases
+            .iter()
+            .filter(|&(_, &c)| c == category)
+            .map(|(raw, _)| raw.as_str())
+            .collect();
+        types.sort_unstable();
+        types
+    }
+
+    /// Every raw type with a configured alias, sorted
+    #[must_use]
+    pub fn known_types(&self) -> Vec<&str> {
+        let mut types: Vec<&str> = self.aliases.keys().map(String::as_str).collect();
+        types.sort_unstable();
+        types
+    }
+}
+
+impl Default for ActivityTypeAliases {
+    fn default() -> Self {
+        Self::new(HashMap::new())
+    }
+}
+
+/// Classifies a raw activity type with the configured aliases
+#[must_use]
+pub fn activity_category(activity_type: &str) -> ActivityCategory {
+    ActivityTypeAliases::from_env().classify(activity_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strava_types_classify_into_categories() {
+        let aliases = ActivityTypeAliases::default();
+
+        assert_eq!(aliases.classify("Run"), ActivityCategory::Run);
+        assert_eq!(aliases.classify("TrailRun"), ActivityCategory::Run);
+        assert_eq!(aliases.classify("VirtualRun"), ActivityCategory::Run);
+        assert_eq!(aliases.classify("Ride"), ActivityCategory::Ride);
+        assert_eq!(aliases.classify("GravelRide"), ActivityCategory::Ride);
+        assert_eq!(aliases.classify("VirtualRide"), ActivityCategory::Ride);
+        assert_eq!(aliases.classify("Swim"), ActivityCategory::Swim);
+    }
+
+    #[test]
+    fn test_unknown_types_default_to_other() {
+        let aliases = ActivityTypeAliases::default();
+
+        assert_eq!(aliases.classify("Yoga"), ActivityCategory::Other);
+        assert_eq!(aliases.classify("WeightTraining"), ActivityCategory::Other);
+        assert_eq!(aliases.classify(""), ActivityCategory::Other);
+        // Strava's type strings are matched exactly
+        assert_eq!(aliases.classify("run"), ActivityCategory::Other);
+    }
+
+    #[test]
+    fn test_extra_aliases_extend_and_override_built_ins() {
+        let aliases = ActivityTypeAliases::new(HashMap::from([
+            ("Handcycle".to_string(), ActivityCategory::Ride),
+            ("Velomobile".to_string(), ActivityCategory::Other),
+        ]));
+
+        assert_eq!(aliases.classify("Handcycle"), ActivityCategory::Ride);
+        assert_eq!(aliases.classify("Velomobile"), ActivityCategory::Other);
+        assert_eq!(aliases.classify("Run"), ActivityCategory::Run);
+    }
+
+    #[test]
+    fn test_types_in_category_are_listed() {
+        let aliases = ActivityTypeAliases::default();
+
+        assert_eq!(
+            aliases.types_in(ActivityCategory::Run),
+            vec!["Run", "TrailRun", "VirtualRun"]
+        );
+        assert_eq!(aliases.types_in(ActivityCategory::Swim), vec!["Swim"]);
+        assert!(aliases.types_in(ActivityCategory::Other).is_empty());
+    }
+}
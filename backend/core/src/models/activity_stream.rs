@@ -1,30 +1,85 @@
 use std::collections::HashMap;
 
 use run_sous_bpm_integrations::strava::{StravaActivityStreamResponse, StreamData};
-use sea_orm::{prelude::DateTimeWithTimeZone, ActiveValue::Set};
-use tracing::info;
+use sea_orm::{prelude::DateTimeWithTimeZone, ActiveModelTrait};
+use serde::Serialize;
+use tracing::{info, warn};
 use uuid::Uuid;
 
 use crate::database::activity_stream;
+use crate::models::{activity_category, ActivityCategory};
+use crate::units::StreamUnits;
+
+/// Stream types a sync requests from Strava when the caller doesn't pick a subset
+pub const SYNC_STREAM_KEYS: &[&str] = &[
+    "time",
+    "distance",
+    "latlng",
+    "altitude",
+    "heart_rate",
+    "cadence",
+    "watts",
+    "velocity_smooth",
+    "temperature",
+    "grade_smooth",
+    "moving",
+];
+
+/// A requested stream type is not one a sync can store
+#[derive(Debug, thiserror::Error)]
+#[error("Unknown stream key '{0}', expected a subset of: {keys}", keys = SYNC_STREAM_KEYS.join(", "))]
+pub struct UnknownStreamKeyError(pub String);
+
+/// Parses a comma-separated subset of [`SYNC_STREAM_KEYS`], e.g. `latlng` for map-only use
+///
+/// `time` is always included since every point is timed from it. Blank entries
+/// and duplicates are ignored.
+///
+/// # Errors
+///
+/// Returns an error if a key is not in [`SYNC_STREAM_KEYS`]
+pub fn parse_stream_keys(keys: &str) -> Result<Vec<&'static str>, UnknownStreamKeyError> {
+    let mut selected = vec!["time"];
+    for key in keys.split(',').map(str::trim).filter(|key| !key.is_empty()) {
+        let known = SYNC_STREAM_KEYS
+            .iter()
+            .find(|known| **known == key)
+            .ok_or_else(|| UnknownStreamKeyError(key.to_string()))?;
+        if !selected.contains(known) {
+            selected.push(known);
+        }
+    }
+    Ok(selected)
+}
 
 /// DTO for creating an activity stream from Strava API response
 #[derive(Debug, Clone)]
 pub struct ValidatedActivityStreams {
     pub activity_id: Uuid,
     pub time: Vec<f32>,
-    pub distance: Vec<f32>,
-    pub latlng: Option<Vec<(f32, f32)>>,
+    /// Cumulative distance in meters, `None` when Strava has no distance stream
+    /// (treadmill, or a sync restricted to other keys)
+    pub distance: Option<Vec<f32>>,
+    /// `(lat, lng)` per point, `None` where the recorded coordinates were out of range
+    pub latlng: Option<Vec<Option<(f32, f32)>>>,
     pub altitude: Option<Vec<f32>>,
     pub heart_rate: Option<Vec<i32>>,
     pub cadence: Option<Vec<i32>>,
     pub watts: Option<Vec<f32>>,
     pub velocity: Option<Vec<f32>>,
     pub temperature: Option<Vec<f32>>,
+    /// Smoothed grade in percent (Strava `grade_smooth`)
+    pub grade: Option<Vec<f32>>,
+    /// Whether the athlete was moving at each point, as detected by Strava
+    pub moving: Option<Vec<bool>>,
 }
 
 impl ValidatedActivityStreams {
     /// Creates a DTO from Strava API response
     ///
+    /// Point timing always comes from the `time` stream, so streams aligned on
+    /// either `series_type` convert the same way.
+    ///
     /// # Errors
     ///
     /// Returns an error if:
@@ -40,7 +95,7 @@ impl ValidatedActivityStreams {
             "Validating Strava activity streams for activity"
         );
         let time = extract_required_f32(&response.0, "time")?;
-        let distance = extract_required_f32(&response.0, "distance")?;
+        let distance = extract_optional_f32(&response.0, "distance");
         info!(
             activity_id = %activity_id,
             points = time.len(),
@@ -57,7 +112,8 @@ impl ValidatedActivityStreams {
                         if arr.len() == 2 {
                             let lat = arr[0].as_f64()? as f32;
                             let lng = arr[1].as_f64()? as f32;
-                            Some((lat, lng))
+                            // Keep the point aligned with the time stream, without coordinates
+                            Some(is_valid_coordinate(lat, lng).then_some((lat, lng)))
                         } else {
                             None
                         }
@@ -66,6 +122,15 @@ impl ValidatedActivityStreams {
                     }
                 })
                 .collect::<Vec<_>>();
+            let out_of_range = latlng_data.iter().filter(|point| point.is_none()).count();
+            if out_of_range > 0 {
+                warn!(
+                    activity_id = %activity_id,
+                    out_of_range = out_of_range,
+                    "Dropped {} out-of-range GPS coordinates",
+                    out_of_range
+                );
+            }
             Some(latlng_data)
         } else {
             None
@@ -76,10 +141,12 @@ impl ValidatedActivityStreams {
         let watts = extract_optional_f32(&response.0, "watts");
         let velocity = extract_optional_f32(&response.0, "velocity_smooth");
         let temperature = extract_optional_f32(&response.0, "temperature");
+        let grade = extract_optional_f32(&response.0, "grade_smooth");
+        let moving = extract_optional_bool(&response.0, "moving");
 
         let lengths = [
             time.len(),
-            distance.len(),
+            distance.as_ref().map_or(time.len(), Vec::len),
             latlng.as_ref().map_or(0, Vec::len),
             altitude.as_ref().map_or(time.len(), Vec::len),
             heart_rate.as_ref().map_or(time.len(), Vec::len),
@@ -87,6 +154,8 @@ impl ValidatedActivityStreams {
             watts.as_ref().map_or(time.len(), Vec::len),
             velocity.as_ref().map_or(time.len(), Vec::len),
             temperature.as_ref().map_or(time.len(), Vec::len),
+            grade.as_ref().map_or(time.len(), Vec::len),
+            moving.as_ref().map_or(time.len(), Vec::len),
         ];
         if !lengths.iter().all(|&len| len == time.len()) {
             return Err("Inconsistent stream data lengths".into());
@@ -103,16 +172,85 @@ impl ValidatedActivityStreams {
             watts,
             velocity,
             temperature,
-        })
+            grade,
+            moving,
+        }
+        .with_canonical_units(StreamUnits::STRAVA))
+    }
+
+    /// Converts velocity and altitude from the provider's units to m/s and meters
+    ///
+    /// Every provider's streams go through this once at validation, so stored
+    /// values share one unit per column whatever their source.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn with_canonical_units(mut self, units: StreamUnits) -> Self {
+        if units.is_canonical() {
+            return self;
+        }
+
+        if let Some(velocity) = self.velocity.as_mut() {
+            for value in velocity.iter_mut() {
+                *value = units.velocity.to_meters_per_second(*value);
+            }
+        }
+        if let Some(altitude) = self.altitude.as_mut() {
+            for value in altitude.iter_mut() {
+                *value = units.altitude.to_meters(f64::from(*value)) as f32;
+            }
+        }
+        self
     }
 
-    /// Converts the DTO into a `SeaORM` `ActiveModel` for insertion
+    /// Normalizes cadence to full steps/revolutions per minute for the activity type
+    ///
+    /// Strava reports running cadence per leg (~90 for a 180 spm runner) but cycling
+    /// cadence as whole crank rpm, so running cadence is doubled to compare like with like.
+    /// The raw value is recoverable by dividing by [`cadence_multiplier`].
+    #[must_use]
+    pub fn with_normalized_cadence(mut self, activity_type: &str) -> Self {
+        let multiplier = cadence_multiplier(activity_type);
+        if multiplier != 1 {
+            if let Some(cadence) = self.cadence.as_mut() {
+                for value in cadence.iter_mut() {
+                    *value = value.saturating_mul(multiplier);
+                }
+            }
+        }
+        self
+    }
+
+    /// Keeps every `keep_every`th point, plus the last one, to cap stored rows
+    ///
+    /// Points are only dropped, never reordered or interpolated, so time and distance
+    /// stay monotonic and the activity keeps its exact start, end and total distance.
+    /// A step of 0 or 1 leaves the streams unchanged.
+    #[must_use]
+    pub fn downsample(mut self, keep_every: usize) -> Self {
+        let len = self.time.len();
+        if keep_every <= 1 || len <= 2 {
+            return self;
+        }
+
+        let keep = |i: usize| i % keep_every == 0 || i == len - 1;
+        self.time = keep_points(self.time, keep);
+        self.distance = self.distance.map(|v| keep_points(v, keep));
+        self.latlng = self.latlng.map(|v| keep_points(v, keep));
+        self.altitude = self.altitude.map(|v| keep_points(v, keep));
+        self.heart_rate = self.heart_rate.map(|v| keep_points(v, keep));
+        self.cadence = self.cadence.map(|v| keep_points(v, keep));
+        self.watts = self.watts.map(|v| keep_points(v, keep));
+        self.velocity = self.velocity.map(|v| keep_points(v, keep));
+        self.temperature = self.temperature.map(|v| keep_points(v, keep));
+        self.grade = self.grade.map(|v| keep_points(v, keep));
+        self.moving = self.moving.map(|v| keep_points(v, keep));
+        self
+    }
+
+    /// Converts the DTO into stream points timed from the activity's `start_time`
     #[must_use]
     #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-    pub fn into_active_models(
-        self,
-        start_time: DateTimeWithTimeZone,
-    ) -> Vec<activity_stream::ActiveModel> {
+    pub fn into_models(self, start_time: DateTimeWithTimeZone) -> Vec<activity_stream::Model> {
         let len = self.time.len();
         let mut models = Vec::with_capacity(len);
         info!(
@@ -122,28 +260,103 @@ impl ValidatedActivityStreams {
             len
         );
         for i in 0..len {
-            models.push(activity_stream::ActiveModel {
-                activity_id: Set(self.activity_id),
-                time: Set(start_time + chrono::Duration::seconds(self.time[i] as i64)),
-                distance: Set(Some(self.distance[i])),
-                latitude: Set(self
+            models.push(activity_stream::Model {
+                activity_id: self.activity_id,
+                time: start_time + chrono::Duration::seconds(self.time[i] as i64),
+                distance: self.distance.as_ref().and_then(|d| d.get(i).copied()),
+                latitude: self
                     .latlng
                     .as_ref()
-                    .and_then(|ll| ll.get(i).map(|(lat, _)| f64::from(*lat)))),
-                longitude: Set(self
+                    .and_then(|ll| ll.get(i).copied().flatten())
+                    .map(|(lat, _)| f64::from(lat)),
+                longitude: self
                     .latlng
                     .as_ref()
-                    .and_then(|ll| ll.get(i).map(|(_, lng)| f64::from(*lng)))),
-                altitude: Set(self.altitude.as_ref().and_then(|alt| alt.get(i).copied())),
-                heart_rate: Set(self.heart_rate.as_ref().and_then(|hr| hr.get(i).copied())),
-                cadence: Set(self.cadence.as_ref().and_then(|cad| cad.get(i).copied())),
-                watts: Set(self.watts.as_ref().and_then(|w| w.get(i).copied())),
-                velocity: Set(self.velocity.as_ref().and_then(|v| v.get(i).copied())),
-                temperature: Set(self.temperature.as_ref().and_then(|t| t.get(i).copied())),
+                    .and_then(|ll| ll.get(i).copied().flatten())
+                    .map(|(_, lng)| f64::from(lng)),
+                altitude: self.altitude.as_ref().and_then(|alt| alt.get(i).copied()),
+                heart_rate: self.heart_rate.as_ref().and_then(|hr| hr.get(i).copied()),
+                cadence: self.cadence.as_ref().and_then(|cad| cad.get(i).copied()),
+                watts: self.watts.as_ref().and_then(|w| w.get(i).copied()),
+                velocity: self.velocity.as_ref().and_then(|v| v.get(i).copied()),
+                temperature: self.temperature.as_ref().and_then(|t| t.get(i).copied()),
+                grade: self.grade.as_ref().and_then(|g| g.get(i).copied()),
+                moving: self.moving.as_ref().and_then(|m| m.get(i).copied()),
             });
         }
         models
     }
+
+    /// Converts the DTO into `SeaORM` `ActiveModel`s for insertion
+    #[must_use]
+    pub fn into_active_models(
+        self,
+        start_time: DateTimeWithTimeZone,
+    ) -> Vec<activity_stream::ActiveModel> {
+        self.into_models(start_time)
+            .into_iter()
+            .map(into_insertable)
+            .collect()
+    }
+}
+
+/// Stream point ready for insertion, with every column set
+#[must_use]
+pub fn into_insertable(point: activity_stream::Model) -> activity_stream::ActiveModel {
+    activity_stream::ActiveModel::from(point).reset_all()
+}
+
+/// Whether a latitude/longitude pair lies within [-90, 90] / [-180, 180]
+fn is_valid_coordinate(lat: f32, lng: f32) -> bool {
+    (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lng)
+}
+
+fn keep_points<T>(values: Vec<T>, keep: impl Fn(usize) -> bool) -> Vec<T> {
+    values
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, value)| keep(i).then_some(value))
+        .collect()
+}
+
+/// A stream channel available on Strava for an activity
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StreamChannelPreview {
+    /// Strava stream key (e.g. `heartrate`, `latlng`)
+    pub key: String,
+    /// Number of points Strava recorded for this channel at full resolution
+    pub original_size: u32,
+}
+
+/// Lists the channels present in a Strava streams response, sorted by key
+///
+/// Only metadata is kept: `original_size` reflects the full recording even when
+/// the streams were fetched at a reduced resolution.
+#[must_use]
+pub fn preview_stream_channels(
+    response: &StravaActivityStreamResponse,
+) -> Vec<StreamChannelPreview> {
+    let mut channels: Vec<StreamChannelPreview> = response
+        .0
+        .iter()
+        .map(|(key, stream)| StreamChannelPreview {
+            key: key.clone(),
+            original_size: stream.original_size,
+        })
+        .collect();
+    channels.sort_by(|a, b| a.key.cmp(&b.key));
+    channels
+}
+
+/// Factor applied to Strava's raw cadence stream to get full cadence for an activity type
+///
+/// Running sports report one leg only and are doubled; everything else is left as is.
+#[must_use]
+pub fn cadence_multiplier(activity_type: &str) -> i32 {
+    match activity_category(activity_type) {
+        ActivityCategory::Run => 2,
+        _ => 1,
+    }
 }
 
 #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
@@ -192,3 +405,332 @@ fn extract_optional_i32(map: &HashMap<String, StreamData>, key: &str) -> Option<
             .collect::<Vec<_>>()
     })
 }
+
+fn extract_optional_bool(map: &HashMap<String, StreamData>, key: &str) -> Option<Vec<bool>> {
+    map.get(key).map(|stream| {
+        stream
+            .data
+            .iter()
+            .filter_map(serde_json::Value::as_bool)
+            .collect::<Vec<_>>()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::{LengthUnit, SpeedUnit};
+    use sea_orm::ActiveValue::Set;
+
+    fn make_streams(cadence: Option<Vec<i32>>) -> ValidatedActivityStreams {
+        ValidatedActivityStreams {
+            activity_id: Uuid::new_v4(),
+            time: vec![0.0, 1.0, 2.0],
+            distance: Some(vec![0.0, 3.0, 6.0]),
+            latlng: None,
+            altitude: None,
+            heart_rate: None,
+            cadence,
+            watts: None,
+            velocity: None,
+            temperature: None,
+            grade: None,
+            moving: None,
+        }
+    }
+
+    #[test]
+    fn test_parses_grade_and_moving_streams() {
+        let response: StravaActivityStreamResponse = serde_json::from_value(serde_json::json!({
+            "time": { "data": [0, 1, 2], "original_size": 3, "series_type": "distance", "resolution": "high" },
+            "distance": { "data": [0.0, 2.5, 5.0], "original_size": 3, "series_type": "distance", "resolution": "high" },
+            "grade_smooth": { "data": [0.0, 1.5, -2], "original_size": 3, "series_type": "distance", "resolution": "high" },
+            "moving": { "data": [false, true, true], "original_size": 3, "series_type": "distance", "resolution": "high" }
+        }))
+        .unwrap();
+
+        let streams =
+            ValidatedActivityStreams::from_strava_response(response, Uuid::new_v4()).unwrap();
+
+        assert_eq!(streams.grade, Some(vec![0.0, 1.5, -2.0]));
+        assert_eq!(streams.moving, Some(vec![false, true, true]));
+
+        let models = streams.into_active_models(chrono::Utc::now().into());
+        assert_eq!(models[1].grade, Set(Some(1.5)));
+        assert_eq!(models[0].moving, Set(Some(false)));
+    }
+
+    #[test]
+    fn test_missing_grade_and_moving_streams_are_none() {
+        let response: StravaActivityStreamResponse = serde_json::from_value(serde_json::json!({
+            "time": { "data": [0, 1], "original_size": 2, "series_type": "distance", "resolution": "high" },
+            "distance": { "data": [0.0, 2.5], "original_size": 2, "series_type": "distance", "resolution": "high" }
+        }))
+        .unwrap();
+
+        let streams =
+            ValidatedActivityStreams::from_strava_response(response, Uuid::new_v4()).unwrap();
+
+        assert_eq!(streams.grade, None);
+        assert_eq!(streams.moving, None);
+
+        let models = streams.into_active_models(chrono::Utc::now().into());
+        assert_eq!(models[0].grade, Set(None));
+        assert_eq!(models[0].moving, Set(None));
+    }
+
+    #[test]
+    fn test_stream_keys_are_validated_against_the_known_set() {
+        assert_eq!(
+            parse_stream_keys("latlng, altitude,latlng").unwrap(),
+            vec!["time", "latlng", "altitude"]
+        );
+        assert_eq!(parse_stream_keys("").unwrap(), vec!["time"]);
+
+        let error = parse_stream_keys("latlng,power").unwrap_err();
+        assert_eq!(error.0, "power");
+    }
+
+    #[test]
+    fn test_time_and_latlng_only_leave_sensor_columns_empty() {
+        let response: StravaActivityStreamResponse = serde_json::from_value(serde_json::json!({
+            "time": { "data": [0, 1], "original_size": 2, "series_type": "distance", "resolution": "high" },
+            "latlng": { "data": [[48.85, 2.35], [48.86, 2.36]], "original_size": 2, "series_type": "distance", "resolution": "high" }
+        }))
+        .unwrap();
+
+        let streams =
+            ValidatedActivityStreams::from_strava_response(response, Uuid::new_v4()).unwrap();
+        let models = streams.into_active_models(chrono::Utc::now().into());
+
+        assert_eq!(models.len(), 2);
+        assert!(matches!(models[1].latitude, Set(Some(_))));
+        assert_eq!(models[1].heart_rate, Set(None));
+        assert_eq!(models[1].watts, Set(None));
+        assert_eq!(models[1].distance, Set(None));
+    }
+
+    #[test]
+    fn test_out_of_range_coordinates_are_nulled() {
+        let response: StravaActivityStreamResponse = serde_json::from_value(serde_json::json!({
+            "time": { "data": [0, 1, 2], "original_size": 3, "series_type": "distance", "resolution": "high" },
+            "distance": { "data": [0.0, 2.5, 5.0], "original_size": 3, "series_type": "distance", "resolution": "high" },
+            "latlng": { "data": [[48.85, 2.35], [148.85, 2.35], [48.86, -182.0]], "original_size": 3, "series_type": "distance", "resolution": "high" }
+        }))
+        .unwrap();
+
+        let streams =
+            ValidatedActivityStreams::from_strava_response(response, Uuid::new_v4()).unwrap();
+
+        assert_eq!(streams.latlng, Some(vec![Some((48.85, 2.35)), None, None]));
+        // Time and distance keep every point
+        assert_eq!(streams.time, vec![0.0, 1.0, 2.0]);
+        assert_eq!(streams.distance, Some(vec![0.0, 2.5, 5.0]));
+
+        let models = streams.into_active_models(chrono::Utc::now().into());
+        assert_eq!(models.len(), 3);
+        assert!(matches!(models[0].latitude, Set(Some(_))));
+        assert_eq!(models[1].latitude, Set(None));
+        assert_eq!(models[1].longitude, Set(None));
+        assert_eq!(models[2].longitude, Set(None));
+        assert_eq!(models[2].distance, Set(Some(5.0)));
+    }
+
+    #[test]
+    fn test_preview_reports_available_channels() {
+        let response: StravaActivityStreamResponse = serde_json::from_value(serde_json::json!({
+            "time": { "data": [0, 1, 2], "original_size": 3600, "series_type": "distance", "resolution": "low" },
+            "heartrate": { "data": [120, 125, 130], "original_size": 3600, "series_type": "distance", "resolution": "low" },
+            "latlng": { "data": [[48.0, 2.0], [48.1, 2.1], [48.2, 2.2]], "original_size": 3598, "series_type": "distance", "resolution": "low" }
+        }))
+        .unwrap();
+
+        let channels = preview_stream_channels(&response);
+
+        assert_eq!(
+            channels,
+            vec![
+                StreamChannelPreview {
+                    key: "heartrate".to_string(),
+                    original_size: 3600
+                },
+                StreamChannelPreview {
+                    key: "latlng".to_string(),
+                    original_size: 3598
+                },
+                StreamChannelPreview {
+                    key: "time".to_string(),
+                    original_size: 3600
+                },
+            ]
+        );
+        assert!(!channels.iter().any(|c| c.key == "watts"));
+    }
+
+    #[test]
+    fn test_kilometers_per_hour_velocity_is_converted_to_meters_per_second() {
+        let streams = ValidatedActivityStreams {
+            velocity: Some(vec![0.0, 18.0, 36.0]),
+            altitude: Some(vec![100.0, 1000.0, 0.0]),
+            ..make_streams(None)
+        };
+        let units = StreamUnits {
+            velocity: SpeedUnit::KilometersPerHour,
+            altitude: LengthUnit::Feet,
+        };
+
+        let streams = streams.with_canonical_units(units);
+
+        let velocity = streams.velocity.unwrap();
+        assert!((velocity[1] - 5.0).abs() < 1e-5, "{velocity:?}");
+        assert!((velocity[2] - 10.0).abs() < 1e-5, "{velocity:?}");
+        let altitude = streams.altitude.unwrap();
+        assert!((altitude[1] - 304.8).abs() < 1e-3, "{altitude:?}");
+        // Distance is already in meters for every provider
+        assert_eq!(streams.distance, Some(vec![0.0, 3.0, 6.0]));
+    }
+
+    #[test]
+    fn test_canonical_units_leave_streams_unchanged() {
+        let streams = ValidatedActivityStreams {
+            velocity: Some(vec![3.2, 3.4]),
+            ..make_streams(None)
+        }
+        .with_canonical_units(StreamUnits::STRAVA);
+
+        assert_eq!(streams.velocity, Some(vec![3.2, 3.4]));
+    }
+
+    #[test]
+    fn test_run_cadence_is_doubled() {
+        let streams = make_streams(Some(vec![88, 90, 92])).with_normalized_cadence("Run");
+        assert_eq!(streams.cadence, Some(vec![176, 180, 184]));
+
+        let trail = make_streams(Some(vec![85])).with_normalized_cadence("TrailRun");
+        assert_eq!(trail.cadence, Some(vec![170]));
+    }
+
+    #[test]
+    fn test_ride_cadence_is_unchanged() {
+        let streams = make_streams(Some(vec![80, 85, 90])).with_normalized_cadence("Ride");
+        assert_eq!(streams.cadence, Some(vec![80, 85, 90]));
+    }
+
+    #[test]
+    fn test_missing_cadence_stays_missing() {
+        let streams = make_streams(None).with_normalized_cadence("Run");
+        assert_eq!(streams.cadence, None);
+    }
+
+    #[test]
+    fn test_normalized_cadence_is_stored() {
+        let models = make_streams(Some(vec![90, 91, 92]))
+            .with_normalized_cadence("Run")
+            .into_active_models(chrono::Utc::now().into());
+
+        assert_eq!(models[0].cadence, Set(Some(180)));
+        assert_eq!(models[2].cadence, Set(Some(184)));
+    }
+
+    fn make_long_streams(len: usize) -> ValidatedActivityStreams {
+        #[allow(clippy::cast_precision_loss)]
+        let time: Vec<f32> = (0..len).map(|i| i as f32).collect();
+        ValidatedActivityStreams {
+            time: time.clone(),
+            distance: Some(time.iter().map(|t| t * 3.0).collect()),
+            heart_rate: Some(
+                (0..len)
+                    .map(|i| 120 + i32::try_from(i % 40).unwrap())
+                    .collect(),
+            ),
+            ..make_streams(None)
+        }
+    }
+
+    #[test]
+    fn test_downsample_reduces_rows_and_keeps_endpoints() {
+        let streams = make_long_streams(11_000).downsample(5);
+
+        assert_eq!(streams.time.len(), 2_201);
+        assert_eq!(
+            streams.distance.as_ref().map(Vec::len),
+            Some(streams.time.len())
+        );
+        assert_eq!(streams.heart_rate.as_ref().map(Vec::len), Some(2_201));
+        assert_eq!(streams.time.first(), Some(&0.0));
+        assert_eq!(streams.time.last(), Some(&10_999.0));
+        assert_eq!(
+            streams.distance.as_ref().and_then(|d| d.last()),
+            Some(&32_997.0)
+        );
+    }
+
+    #[test]
+    fn test_downsample_preserves_monotonicity() {
+        let streams = make_long_streams(1_003).downsample(10);
+
+        assert!(streams.time.windows(2).all(|w| w[0] < w[1]));
+        assert!(streams.distance.unwrap().windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn test_downsample_step_one_keeps_everything() {
+        assert_eq!(make_long_streams(100).downsample(1).time.len(), 100);
+        assert_eq!(make_long_streams(100).downsample(0).time.len(), 100);
+    }
+
+    #[test]
+    fn test_downsampled_streams_convert_to_rows() {
+        let start: DateTimeWithTimeZone = chrono::Utc::now().into();
+        let models = make_long_streams(100)
+            .downsample(10)
+            .into_active_models(start);
+
+        assert_eq!(models.len(), 11);
+        assert_eq!(models[0].time, Set(start));
+        assert_eq!(models[10].time, Set(start + chrono::Duration::seconds(99)));
+    }
+
+    #[test]
+    fn test_treadmill_streams_without_distance_keep_increasing_timestamps() {
+        let response: StravaActivityStreamResponse = serde_json::from_value(serde_json::json!({
+            "time": { "data": [0, 5, 10, 15], "original_size": 4, "series_type": "time", "resolution": "high" },
+            "heart_rate": { "data": [110, 120, 130, 135], "original_size": 4, "series_type": "time", "resolution": "high" }
+        }))
+        .unwrap();
+
+        let streams =
+            ValidatedActivityStreams::from_strava_response(response, Uuid::new_v4()).unwrap();
+        assert_eq!(streams.distance, None);
+
+        let start: DateTimeWithTimeZone = chrono::Utc::now().into();
+        let models = streams.into_active_models(start);
+        let times: Vec<DateTimeWithTimeZone> = models
+            .iter()
+            .map(|model| model.time.clone().unwrap())
+            .collect();
+        assert!(times.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(times[3], start + chrono::Duration::seconds(15));
+        assert!(
+            models.iter().all(|model| model.distance == Set(None)),
+            "A missing distance stream is stored as NULL, not zero"
+        );
+    }
+
+    #[test]
+    fn test_zero_distance_streams_keep_increasing_timestamps() {
+        let streams = ValidatedActivityStreams {
+            time: vec![0.0, 1.0, 2.0, 3.0],
+            distance: Some(vec![0.0; 4]),
+            ..make_streams(None)
+        };
+
+        let start: DateTimeWithTimeZone = chrono::Utc::now().into();
+        let models = streams.into_active_models(start);
+
+        assert!(models
+            .windows(2)
+            .all(|w| w[0].time.as_ref() < w[1].time.as_ref()));
+        assert_eq!(models[3].distance, Set(Some(0.0)));
+    }
+}
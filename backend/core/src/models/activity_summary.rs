@@ -0,0 +1,167 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::database::{activity, activity_stream};
+
+/// Speed in m/s from which the athlete counts as moving when there is no moving stream
+const MIN_MOVING_SPEED: f64 = 0.5;
+
+/// Activity-level statistics computed from the streams
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ActivitySummary {
+    /// Mean heart rate, `None` without a heart rate stream
+    pub avg_heart_rate: Option<f64>,
+    pub max_heart_rate: Option<i32>,
+    /// Sum of altitude increases in meters, `None` without an altitude stream
+    pub elevation_gain: Option<f32>,
+    /// Seconds spent moving, `None` with fewer than two points
+    pub moving_time_seconds: Option<i32>,
+    /// When the summary was stored on the activity, `None` if computed on the fly
+    pub computed_at: Option<DateTime<Utc>>,
+}
+
+impl ActivitySummary {
+    /// Computes the summary of streams ordered by time
+    ///
+    /// Moving time follows Strava's moving stream when recorded, otherwise the speed
+    /// implied by the distance stream.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    pub fn from_streams(streams: &[activity_stream::Model]) -> Self {
+        let heart_rates: Vec<i32> = streams.iter().filter_map(|p| p.heart_rate).collect();
+        let avg_heart_rate = (!heart_rates.is_empty()).then(|| {
+            heart_rates.iter().map(|&hr| f64::from(hr)).sum::<f64>() / heart_rates.len() as f64
+        });
+
+        let altitudes: Vec<f32> = streams.iter().filter_map(|p| p.altitude).collect();
+        let elevation_gain = (!altitudes.is_empty()).then(|| {
+            altitudes
+                .windows(2)
+                .map(|pair| f64::from((pair[1] - pair[0]).max(0.0)))
+                .sum::<f64>() as f32
+        });
+
+        let has_moving_stream = streams.iter().any(|p| p.moving.is_some());
+        let moving_time_seconds = (streams.len() >= 2).then(|| {
+            let seconds: i64 = streams
+                .windows(2)
+                .filter(|pair| {
+                    if has_moving_stream {
+                        pair[1].moving == Some(true)
+                    } else {
+                        is_moving(&pair[0], &pair[1])
+                    }
+                })
+                .map(|pair| (pair[1].time - pair[0].time).num_seconds().max(0))
+                .sum();
+            i32::try_from(seconds).unwrap_or(i32::MAX)
+        });
+
+        Self {
+            avg_heart_rate,
+            max_heart_rate: heart_rates.into_iter().max(),
+            elevation_gain,
+            moving_time_seconds,
+            computed_at: None,
+        }
+    }
+
+    /// The summary stored on an activity by the last stream sync, if any
+    #[must_use]
+    pub fn stored(activity: &activity::Model) -> Option<Self> {
+        activity.summary_computed_at.map(|computed_at| Self {
+            avg_heart_rate: activity.avg_heart_rate,
+            max_heart_rate: activity.max_heart_rate,
+            elevation_gain: activity.stream_elevation_gain,
+            moving_time_seconds: activity.stream_moving_time,
+            computed_at: Some(computed_at.into()),
+        })
+    }
+}
+
+/// Whether the distance covered between two points implies the athlete was moving
+#[allow(clippy::cast_precision_loss)]
+fn is_moving(from: &activity_stream::Model, to: &activity_stream::Model) -> bool {
+    let elapsed = (to.time - from.time).num_milliseconds();
+    match (from.distance, to.distance) {
+        (Some(from), Some(to)) if elapsed > 0 => {
+            f64::from(to - from) / (elapsed as f64 / 1000.0) >= MIN_MOVING_SPEED
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn make_point(
+        seconds: i64,
+        heart_rate: Option<i32>,
+        altitude: Option<f32>,
+        distance: f32,
+    ) -> activity_stream::Model {
+        activity_stream::Model {
+            activity_id: Uuid::nil(),
+            time: DateTime::from_timestamp(1_700_000_000 + seconds, 0)
+                .unwrap()
+                .into(),
+            latitude: None,
+            longitude: None,
+            altitude,
+            heart_rate,
+            cadence: None,
+            watts: None,
+            velocity: None,
+            distance: Some(distance),
+            temperature: None,
+            grade: None,
+            moving: None,
+        }
+    }
+
+    #[test]
+    fn test_summary_of_streams() {
+        let streams = vec![
+            make_point(0, Some(120), Some(100.0), 0.0),
+            make_point(10, Some(140), Some(105.0), 30.0),
+            make_point(20, Some(160), Some(103.0), 60.0),
+            // Stopped at a crossing
+            make_point(50, None, Some(110.0), 60.0),
+        ];
+
+        let summary = ActivitySummary::from_streams(&streams);
+
+        assert_eq!(summary.avg_heart_rate, Some(140.0));
+        assert_eq!(summary.max_heart_rate, Some(160));
+        assert_eq!(summary.elevation_gain, Some(12.0));
+        assert_eq!(summary.moving_time_seconds, Some(20));
+        assert_eq!(summary.computed_at, None);
+    }
+
+    #[test]
+    fn test_moving_stream_takes_precedence_over_speed() {
+        let mut streams = vec![
+            make_point(0, None, None, 0.0),
+            make_point(10, None, None, 0.0),
+            make_point(20, None, None, 50.0),
+        ];
+        streams[1].moving = Some(true);
+        streams[2].moving = Some(false);
+
+        let summary = ActivitySummary::from_streams(&streams);
+
+        assert_eq!(summary.moving_time_seconds, Some(10));
+        assert_eq!(summary.avg_heart_rate, None);
+        assert_eq!(summary.elevation_gain, None);
+    }
+
+    #[test]
+    fn test_empty_streams_have_no_summary_values() {
+        assert_eq!(
+            ActivitySummary::from_streams(&[]),
+            ActivitySummary::default()
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use run_sous_bpm_integrations::strava::StravaBestEffort;
+use uuid::Uuid;
+
+use crate::database::best_effort;
+
+/// DTO for creating a best effort from a detailed Strava activity
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateBestEffortDto {
+    pub activity_id: Uuid,
+    pub name: String,
+    pub elapsed_time: i32,
+    pub distance: f32,
+}
+
+impl CreateBestEffortDto {
+    /// Creates a DTO from one of Strava's `best_efforts` entries
+    #[must_use]
+    pub fn from_strava_effort(effort: StravaBestEffort, activity_id: Uuid) -> Self {
+        Self {
+            activity_id,
+            name: effort.name,
+            elapsed_time: effort.elapsed_time,
+            distance: effort.distance,
+        }
+    }
+
+    /// Converts the DTO into a `SeaORM` `ActiveModel` for insertion
+    #[must_use]
+    pub fn into_active_model(self) -> best_effort::ActiveModel {
+        use sea_orm::ActiveValue::Set;
+
+        best_effort::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            activity_id: Set(self.activity_id),
+            name: Set(self.name),
+            elapsed_time: Set(self.elapsed_time),
+            distance: Set(self.distance),
+            created_at: Set(chrono::Utc::now().into()),
+        }
+    }
+}
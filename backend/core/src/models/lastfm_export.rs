@@ -0,0 +1,356 @@
+//! Parser for Last.fm scrobble exports
+//!
+//! Supports the two common export formats:
+//! - CSV rows of `artist,album,track,date` (optional header), where `date` is either a
+//!   Unix timestamp or Last.fm's `31 Jan 2021 14:05` UTC format
+//! - JSON in the Last.fm API `recenttracks` shape: an array of tracks, or an array of
+//!   pages each holding a `track` array
+
+use chrono::NaiveDateTime;
+use serde_json::Value;
+
+use crate::models::CreateTrackDto;
+
+/// Date format used by Last.fm's own exports and lastfm-to-csv
+const LASTFM_EXPORT_DATE_FORMAT: &str = "%d %b %Y %H:%M";
+
+/// A scrobble parsed from an export, ready to be stored as a track + listen
+#[derive(Debug, Clone)]
+pub struct ExportedScrobble {
+    pub track: CreateTrackDto,
+    /// Unix timestamp (seconds) when the track was played
+    pub played_at: u32,
+}
+
+/// A row of the export that could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedRow {
+    /// 1-based line (CSV) or item (JSON) number
+    pub row: usize,
+    pub reason: String,
+}
+
+/// Result of parsing a whole export
+#[derive(Debug, Clone, Default)]
+pub struct ParsedLastFmExport {
+    pub scrobbles: Vec<ExportedScrobble>,
+    pub skipped: Vec<SkippedRow>,
+}
+
+impl ParsedLastFmExport {
+    /// Number of rows seen, valid or not
+    #[must_use]
+    pub fn total_rows(&self) -> usize {
+        self.scrobbles.len() + self.skipped.len()
+    }
+}
+
+/// Parses a Last.fm export, detecting JSON or CSV from its content
+///
+/// Malformed rows are skipped and reported instead of failing the whole import.
+///
+/// # Errors
+///
+/// Returns an error if the input looks like JSON but is not valid JSON
+pub fn parse_lastfm_export(input: &str) -> Result<ParsedLastFmExport, String> {
+    let trimmed = input.trim_start_matches('\u{feff}').trim();
+    if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        parse_json_export(trimmed)
+    } else {
+        Ok(parse_csv_export(trimmed))
+    }
+}
+
+fn parse_csv_export(input: &str) -> ParsedLastFmExport {
+    let mut parsed = ParsedLastFmExport::default();
+
+    for (index, line) in input.lines().enumerate() {
+        let row = index + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let fields = split_csv_line(line);
+        if row == 1 && is_header(&fields) {
+            continue;
+        }
+
+        let [artist, album, track, date] = fields.as_slice() else {
+            parsed.skipped.push(SkippedRow {
+                row,
+                reason: format!("Expected 4 columns, got {}", fields.len()),
+            });
+            continue;
+        };
+
+        match build_scrobble(artist, album, track, date) {
+            Ok(scrobble) => parsed.scrobbles.push(scrobble),
+            Err(reason) => parsed.skipped.push(SkippedRow { row, reason }),
+        }
+    }
+
+    parsed
+}
+
+fn parse_json_export(input: &str) -> Result<ParsedLastFmExport, String> {
+    let value: Value = serde_json::from_str(input).map_err(|e| format!("Invalid JSON: {e}"))?;
+
+    // Accept a bare track, an array of tracks, or an array of pages with `track` arrays
+    let items: Vec<&Value> = match &value {
+        Value::Array(items) => items
+            .iter()
+            .flat_map(|item| match item.get("track") {
+                Some(Value::Array(tracks)) => tracks.iter().collect(),
+                _ => vec![item],
+            })
+            .collect(),
+        other => vec![other],
+    };
+
+    let mut parsed = ParsedLastFmExport::default();
+    for (index, item) in items.into_iter().enumerate() {
+        let row = index + 1;
+        let artist = text_field(item, "artist");
+        let album = text_field(item, "album");
+        let track = text_field(item, "name");
+        let date = date_field(item);
+
+        let result = match (artist, track, date) {
+            (Some(artist), Some(track), Some(date)) => {
+                build_scrobble(&artist, album.as_deref().unwrap_or_default(), &track, &date)
+            }
+            // Tracks still playing have no date in Last.fm responses
+            (_, _, None) => Err("Missing play date".to_string()),
+            _ => Err("Missing artist or track name".to_string()),
+        };
+
+        match result {
+            Ok(scrobble) => parsed.scrobbles.push(scrobble),
+            Err(reason) => parsed.skipped.push(SkippedRow { row, reason }),
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn build_scrobble(
+    artist: &str,
+    album: &str,
+    track: &str,
+    date: &str,
+) -> Result<ExportedScrobble, String> {
+    let artist = artist.trim();
+    let track = track.trim();
+    if artist.is_empty() || track.is_empty() {
+        return Err("Missing artist or track name".to_string());
+    }
+
+    let played_at = parse_played_at(date.trim())?;
+    let album = album.trim();
+
+    Ok(ExportedScrobble {
+        track: CreateTrackDto {
+            artist_name: artist.to_string(),
+            track_name: track.to_string(),
+            album_name: (!album.is_empty()).then(|| album.to_string()),
+            artist_mbid: None,
+            track_mbid: None,
+            album_mbid: None,
+            lastfm_url: None,
+        },
+        played_at,
+    })
+}
+
+/// Parses a play date as Unix seconds or Last.fm's export format (UTC)
+fn parse_played_at(date: &str) -> Result<u32, String> {
+    let timestamp = match date.parse::<i64>() {
+        Ok(timestamp) => timestamp,
+        Err(_) => NaiveDateTime::parse_from_str(date, LASTFM_EXPORT_DATE_FORMAT)
+            .map_err(|_| format!("Unrecognized date: {date}"))?
+            .and_utc()
+            .timestamp(),
+    };
+
+    // Timestamp 0 is what Last.fm exports for scrobbles with an unknown date
+    match u32::try_from(timestamp) {
+        Ok(timestamp) if timestamp > 0 => Ok(timestamp),
+        _ => Err(format!("Date out of range: {date}")),
+    }
+}
+
+/// Reads a Last.fm text field, either a plain string or an object with `#text`
+fn text_field(item: &Value, key: &str) -> Option<String> {
+    let value = item.get(key)?;
+    let text = match value {
+        Value::String(text) => text.as_str(),
+        Value::Object(_) => value.get("#text").or_else(|| value.get("name"))?.as_str()?,
+        _ => return None,
+    };
+    Some(text.to_string())
+}
+
+/// Reads the play date: `{"date": {"uts": "..."}}`, `{"date": 123}` or `{"timestamp": 123}`
+fn date_field(item: &Value) -> Option<String> {
+    let value = item.get("date").or_else(|| item.get("timestamp"))?;
+    match value {
+        Value::Object(_) => value
+            .get("uts")
+            .and_then(scalar_to_string)
+            .or_else(|| value.get("#text").and_then(scalar_to_string)),
+        other => scalar_to_string(other),
+    }
+}
+
+fn scalar_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+fn is_header(fields: &[String]) -> bool {
+    fields
+        .first()
+        .is_some_and(|field| field.trim().eq_ignore_ascii_case("artist"))
+}
+
+/// Splits a CSV line, honoring double-quoted fields with `""` escapes
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_CSV: &str = "\
+artist,album,track,date
+Daft Punk,Discovery,One More Time,31 Jan 2021 14:05
+\"Earth, Wind & Fire\",,September,1612101960
+
+Broken Row Without Date,Album,Track
+Radiohead,OK Computer,Airbag,not a date
+,Unknown,No Artist,31 Jan 2021 14:20
+\"The \"\"Quoted\"\" Band\",Live,Song,0
+";
+
+    #[test]
+    fn test_csv_export_parses_valid_rows() {
+        let parsed = parse_lastfm_export(SAMPLE_CSV).unwrap();
+
+        assert_eq!(parsed.scrobbles.len(), 2);
+
+        let first = &parsed.scrobbles[0];
+        assert_eq!(first.track.artist_name, "Daft Punk");
+        assert_eq!(first.track.track_name, "One More Time");
+        assert_eq!(first.track.album_name.as_deref(), Some("Discovery"));
+        assert_eq!(first.played_at, 1_612_101_900); // 2021-01-31 14:05 UTC
+
+        let second = &parsed.scrobbles[1];
+        assert_eq!(second.track.artist_name, "Earth, Wind & Fire");
+        assert_eq!(second.track.album_name, None);
+        assert_eq!(second.played_at, 1_612_101_960);
+    }
+
+    #[test]
+    fn test_csv_export_skips_malformed_rows() {
+        let parsed = parse_lastfm_export(SAMPLE_CSV).unwrap();
+
+        let skipped_rows: Vec<usize> = parsed.skipped.iter().map(|s| s.row).collect();
+        assert_eq!(skipped_rows, vec![5, 6, 7, 8]);
+        assert!(parsed.skipped[0].reason.contains("Expected 4 columns"));
+        assert!(parsed.skipped[1].reason.contains("Unrecognized date"));
+        assert!(parsed.skipped[2].reason.contains("Missing artist"));
+        assert!(parsed.skipped[3].reason.contains("out of range"));
+        assert_eq!(
+            parsed.total_rows(),
+            6,
+            "Header and blank lines are not rows"
+        );
+    }
+
+    #[test]
+    fn test_json_export_in_api_shape() {
+        let input = r##"[
+            {
+                "track": [
+                    {
+                        "artist": { "#text": "Daft Punk", "mbid": "" },
+                        "album": { "#text": "Discovery" },
+                        "name": "Aerodynamic",
+                        "date": { "uts": "1612101900", "#text": "31 Jan 2021, 14:05" }
+                    },
+                    {
+                        "artist": { "#text": "Daft Punk" },
+                        "name": "Now Playing",
+                        "@attr": { "nowplaying": "true" }
+                    }
+                ]
+            },
+            {
+                "track": [
+                    { "artist": "Justice", "album": "Cross", "name": "Genesis", "date": 1612102000 },
+                    { "album": "Nameless", "date": 1612102100 }
+                ]
+            }
+        ]"##;
+
+        let parsed = parse_lastfm_export(input).unwrap();
+
+        assert_eq!(parsed.scrobbles.len(), 2);
+        assert_eq!(parsed.scrobbles[0].track.track_name, "Aerodynamic");
+        assert_eq!(parsed.scrobbles[0].played_at, 1_612_101_900);
+        assert_eq!(parsed.scrobbles[1].track.artist_name, "Justice");
+        assert_eq!(
+            parsed.scrobbles[1].track.album_name.as_deref(),
+            Some("Cross")
+        );
+
+        assert_eq!(
+            parsed.skipped,
+            vec![
+                SkippedRow {
+                    row: 2,
+                    reason: "Missing play date".to_string()
+                },
+                SkippedRow {
+                    row: 4,
+                    reason: "Missing artist or track name".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_json_is_an_error() {
+        assert!(parse_lastfm_export("[{\"artist\": ").is_err());
+    }
+
+    #[test]
+    fn test_split_csv_line_handles_quotes() {
+        assert_eq!(
+            split_csv_line(r#"a,"b, c","say ""hi""",d"#),
+            vec!["a", "b, c", "say \"hi\"", "d"]
+        );
+    }
+}
@@ -1,9 +1,17 @@
 pub mod activity;
+pub mod activity_category;
 pub mod activity_stream;
+pub mod activity_summary;
+pub mod best_effort;
+pub mod lastfm_export;
 pub mod listen;
 pub mod track;
 
 pub use activity::*;
+pub use activity_category::*;
 pub use activity_stream::*;
+pub use activity_summary::*;
+pub use best_effort::*;
+pub use lastfm_export::*;
 pub use listen::*;
 pub use track::*;
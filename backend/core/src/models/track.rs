@@ -65,7 +65,7 @@ impl CreateTrackDto {
     /// Converts the DTO into a `SeaORM` `ActiveModel` for insertion
     #[must_use]
     pub fn into_active_model(self) -> track::ActiveModel {
-        use sea_orm::ActiveValue::Set;
+        use sea_orm::ActiveValue::{NotSet, Set};
 
         track::ActiveModel {
             id: Set(Uuid::new_v4()),
@@ -76,6 +76,12 @@ impl CreateTrackDto {
             track_mbid: Set(self.track_mbid),
             album_mbid: Set(self.album_mbid),
             lastfm_url: Set(self.lastfm_url),
+            // Tempo is not part of Last.fm data
+            bpm: NotSet,
+            // Filled in by Spotify enrichment
+            spotify_id: NotSet,
+            spotify_match_confidence: NotSet,
+            spotify_searched_at: NotSet,
             created_at: Set(chrono::Utc::now().into()),
             updated_at: Set(chrono::Utc::now().into()),
         }
@@ -0,0 +1,159 @@
+use chrono::Utc;
+use sea_orm::{DatabaseConnection, DbErr};
+use uuid::Uuid;
+
+use crate::database::{activity, activity_stream, get_activity_streams, store_activity_summary};
+use crate::models::ActivitySummary;
+
+/// Computes an activity's summary from its stream points and saves it on the activity
+///
+/// Called by the stream sync with the points it just stored, so the summary endpoint
+/// does not have to load every point on each request.
+///
+/// # Errors
+///
+/// Returns an error if updating the activity fails
+pub async fn refresh_activity_summary(
+    db: &DatabaseConnection,
+    activity_id: Uuid,
+    points: &[activity_stream::Model],
+) -> Result<ActivitySummary, DbErr> {
+    let summary = ActivitySummary {
+        computed_at: Some(Utc::now()),
+        ..ActivitySummary::from_streams(points)
+    };
+    store_activity_summary(db, activity_id, &summary).await?;
+    Ok(summary)
+}
+
+/// Returns the summary stored on an activity, computing it from the streams if absent
+///
+/// Activities synced before summaries were stored have none until their next stream
+/// sync; their summary is computed on the fly without being saved.
+///
+/// # Errors
+///
+/// Returns an error if the summary is absent and loading the streams fails
+pub async fn get_activity_summary(
+    db: &DatabaseConnection,
+    activity: &activity::Model,
+) -> Result<ActivitySummary, DbErr> {
+    if let Some(summary) = ActivitySummary::stored(activity) {
+        return Ok(summary);
+    }
+
+    let streams = get_activity_streams(db, activity.id).await?;
+    Ok(ActivitySummary::from_streams(&streams))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::make_activity;
+    use chrono::DateTime;
+    use sea_orm::{DbBackend, MockDatabase, MockExecResult};
+
+    fn make_streams(activity: &activity::Model) -> Vec<activity_stream::Model> {
+        [
+            (0, 130, 100.0, 0.0),
+            (10, 150, 104.0, 30.0),
+            (20, 170, 101.0, 60.0),
+        ]
+        .into_iter()
+        .map(
+            |(seconds, heart_rate, altitude, distance)| activity_stream::Model {
+                activity_id: activity.id,
+                time: activity.start_time + chrono::Duration::seconds(seconds),
+                latitude: None,
+                longitude: None,
+                altitude: Some(altitude),
+                heart_rate: Some(heart_rate),
+                cadence: None,
+                watts: None,
+                velocity: None,
+                distance: Some(distance),
+                temperature: None,
+                grade: None,
+                moving: None,
+            },
+        )
+        .collect()
+    }
+
+    #[tokio::test]
+    async fn test_refresh_stores_the_summary_of_the_given_points() {
+        let activity = make_activity();
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_exec_results([MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 1,
+            }])
+            .into_connection();
+
+        let summary = refresh_activity_summary(&db, activity.id, &make_streams(&activity))
+            .await
+            .unwrap();
+
+        assert_eq!(summary.avg_heart_rate, Some(150.0));
+        assert_eq!(summary.max_heart_rate, Some(170));
+        assert_eq!(summary.elevation_gain, Some(4.0));
+        assert_eq!(summary.moving_time_seconds, Some(20));
+        assert!(summary.computed_at.is_some());
+
+        let sql = db
+            .into_transaction_log()
+            .iter()
+            .flat_map(|transaction| transaction.statements())
+            .map(|statement| statement.sql.clone())
+            .collect::<Vec<_>>()
+            .join("\n");
+        // The points are not read back from the database
+        assert!(sql.starts_with(r#"UPDATE "activity" SET"#), "{sql}");
+        assert!(sql.contains(r#""summary_computed_at" = "#), "{sql}");
+        assert!(sql.contains(r#""avg_heart_rate" = "#), "{sql}");
+    }
+
+    #[tokio::test]
+    async fn test_summary_reads_precomputed_columns_without_loading_streams() {
+        let computed_at = DateTime::from_timestamp(1_700_003_600, 0).unwrap();
+        let activity = activity::Model {
+            avg_heart_rate: Some(148.0),
+            max_heart_rate: Some(181),
+            stream_elevation_gain: Some(52.0),
+            stream_moving_time: Some(1750),
+            summary_computed_at: Some(computed_at.fixed_offset()),
+            ..make_activity()
+        };
+        // Any query would fail: the mock has no results
+        let db = MockDatabase::new(DbBackend::Postgres).into_connection();
+
+        let summary = get_activity_summary(&db, &activity).await.unwrap();
+
+        assert_eq!(
+            summary,
+            ActivitySummary {
+                avg_heart_rate: Some(148.0),
+                max_heart_rate: Some(181),
+                elevation_gain: Some(52.0),
+                moving_time_seconds: Some(1750),
+                computed_at: Some(computed_at),
+            }
+        );
+        assert!(db.into_transaction_log().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_summary_falls_back_to_streams_when_absent() {
+        let activity = make_activity();
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([make_streams(&activity)])
+            .into_connection();
+
+        let summary = get_activity_summary(&db, &activity).await.unwrap();
+
+        assert_eq!(summary.max_heart_rate, Some(170));
+        assert_eq!(summary.computed_at, None);
+        let log = format!("{:?}", db.into_transaction_log());
+        assert!(!log.contains("UPDATE"), "{log}");
+    }
+}
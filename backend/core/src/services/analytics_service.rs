@@ -1,17 +1,30 @@
-use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, FixedOffset, Utc};
 use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+use tracing::warn;
 use uuid::Uuid;
 
 use crate::{
+    config::simplify_min_points,
     database::{
+        activity,
         activity_stream::Model,
         entities::prelude::{Listen, Track},
-        get_activity_by_id, get_activity_streams, get_listens_by_user_time_range, get_user_by_id,
+        get_activities_by_user, get_activity_by_id, get_activity_streams_in_range,
+        get_distance_points_around, get_listens_by_user_time_range, get_user_by_id,
         listen::{self},
         track::{self},
+        user,
+    },
+    geo::{
+        route_length_meters, simplify_gps_route_to_count, simplify_gps_route_with,
+        simplify_profile_to_count, SimplificationAlgorithm, SimplificationError,
     },
-    geo::simplify_gps_route,
     services::sync_lastfm_for_time_range,
+    units::LengthUnit,
 };
 
 /// Default GPS simplification tolerance in meters
@@ -22,6 +35,86 @@ use crate::{
 /// - Map rendering (smooth lines at typical zoom levels)
 const DEFAULT_SIMPLIFICATION_TOLERANCE_METERS: f32 = 10.0;
 
+/// How `build_activity_segments` simplifies the GPS points of each segment
+#[derive(Debug, Clone, Copy)]
+struct SegmentSimplification {
+    /// Tolerance in meters (default: 10.0)
+    tolerance: Option<f64>,
+    algorithm: SimplificationAlgorithm,
+    /// Segments with fewer GPS points are returned unchanged
+    min_points: usize,
+}
+
+/// Route length is divided by this to get the `auto` tolerance
+///
+/// Routes of the same shape then keep the same number of points whatever their
+/// length. RDP keeps fewer points than this on smooth stretches, so it acts as an
+/// upper bound rather than an exact count.
+pub const AUTO_TOLERANCE_TARGET_POINTS: f64 = 500.0;
+
+/// Smallest `auto` tolerance in meters, below GPS precision nothing is gained
+pub const AUTO_TOLERANCE_MIN_METERS: f64 = 2.0;
+
+/// Largest `auto` tolerance in meters, so ultra routes keep their turns
+pub const AUTO_TOLERANCE_MAX_METERS: f64 = 200.0;
+
+/// GPS simplification tolerance requested by the client
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum SimplificationTolerance {
+    /// Fixed tolerance in meters
+    Meters(f64),
+    /// Tolerance scaled with the route length, see `auto_tolerance_meters`
+    Auto,
+}
+
+impl TryFrom<String> for SimplificationTolerance {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if value.trim().eq_ignore_ascii_case("auto") {
+            return Ok(Self::Auto);
+        }
+        value
+            .trim()
+            .parse::<f64>()
+            .map(Self::Meters)
+            .map_err(|_| format!("Tolerance must be a number of meters or `auto`, got `{value}`"))
+    }
+}
+
+impl SimplificationTolerance {
+    /// Reads a fixed tolerance as given in `unit`, converting it to meters
+    ///
+    /// `Auto` is computed in meters and is left unchanged.
+    #[must_use]
+    pub fn in_unit(self, unit: LengthUnit) -> Self {
+        match self {
+            Self::Meters(value) => Self::Meters(unit.to_meters(value)),
+            Self::Auto => Self::Auto,
+        }
+    }
+
+    /// Tolerance in meters for a route of `route_length` meters
+    #[must_use]
+    pub fn meters(self, route_length: f64) -> f64 {
+        match self {
+            Self::Meters(meters) => meters,
+            Self::Auto => auto_tolerance_meters(route_length),
+        }
+    }
+}
+
+/// Tolerance that keeps roughly the same point count for routes of any length
+///
+/// `route_length / AUTO_TOLERANCE_TARGET_POINTS`, clamped to
+/// `[AUTO_TOLERANCE_MIN_METERS, AUTO_TOLERANCE_MAX_METERS]`.
+#[must_use]
+pub fn auto_tolerance_meters(route_length: f64) -> f64 {
+    (route_length / AUTO_TOLERANCE_TARGET_POINTS)
+        .clamp(AUTO_TOLERANCE_MIN_METERS, AUTO_TOLERANCE_MAX_METERS)
+}
+
 // Split each stream by tracks
 /*
     {
@@ -71,6 +164,28 @@ pub struct Segment {
     pub start_time: chrono::DateTime<chrono::Utc>,
     pub end_time: chrono::DateTime<chrono::Utc>,
     pub points: Vec<Model>,
+    /// Mean temperature (°C) over the segment's points, before simplification
+    pub avg_temperature: Option<f64>,
+    /// Whether the segment's track also plays in another segment of the activity
+    pub repeated: bool,
+    /// Whether the segment starts after a GPS gap, continuing the previous segment's play
+    pub after_gap: bool,
+}
+
+impl Segment {
+    /// Whole seconds between the segment's start and end instants, never negative
+    #[must_use]
+    pub fn duration_seconds(&self) -> i64 {
+        (self.end_time - self.start_time).num_seconds().max(0)
+    }
+}
+
+/// How many times a track plays during an activity
+#[derive(Debug, Clone)]
+pub struct TrackPlayCount {
+    pub track: track::Model,
+    /// Number of segments playing the track
+    pub play_count: usize,
 }
 
 /// Statistics about GPS simplification
@@ -82,6 +197,285 @@ pub struct SimplificationStats {
     pub original_points: usize,
     pub simplified_points: usize,
     pub reduction_ratio: f32,
+    /// Fraction of the activity's elapsed time with a known track, in [0, 1]
+    pub music_coverage_ratio: f64,
+    /// Duration-weighted average tempo of the music, `None` if no track tempo is known
+    pub avg_bpm: Option<f64>,
+    /// Mean temperature (°C) over the activity's points, `None` if none has a reading
+    pub avg_temperature: Option<f64>,
+    /// Plays per distinct track, in order of first play
+    pub play_counts: Vec<TrackPlayCount>,
+}
+
+/// How activity music is segmented
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, Display, EnumString,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum SegmentationMode {
+    /// One segment per listen, split at track changes
+    #[default]
+    Time,
+    /// Fixed-size distance buckets with their dominant track
+    Distance,
+}
+
+/// Which activity duration bounds the window listens are matched in
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, Display, EnumString,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum ActivityWindow {
+    /// Ends at `start_time + elapsed_time`, pauses included
+    #[default]
+    Elapsed,
+    /// Ends at `start_time + moving_time`, leaving out cooldown scrobbles after a paused run
+    Moving,
+}
+
+impl ActivityWindow {
+    /// Duration in seconds the window spans, see `activity_duration_seconds`
+    ///
+    /// `Moving` falls back to the elapsed time when the moving time is not positive.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if neither duration is positive
+    pub fn duration_seconds(
+        self,
+        elapsed_time: i32,
+        moving_time: i32,
+    ) -> Result<i32, InvalidActivityDurationError> {
+        match self {
+            Self::Moving if moving_time > 0 => Ok(moving_time),
+            _ => activity_duration_seconds(elapsed_time, moving_time),
+        }
+    }
+}
+
+/// Smallest accepted distance bucket, in meters
+///
+/// Bounds the number of buckets allocated for an activity: a tiny bucket over a
+/// marathon would otherwise mean millions of them.
+pub const MIN_DISTANCE_BUCKET_METERS: f64 = 10.0;
+
+/// A fixed-size distance bucket of an activity with its dominant track
+#[derive(Debug, Clone)]
+pub struct DistanceBucket {
+    pub index: usize,
+    /// Bucket start, meters from activity start
+    pub start_distance: f64,
+    /// Bucket end, meters from activity start (the last bucket ends at the total distance)
+    pub end_distance: f64,
+    /// Track that played longest within the bucket, `None` if mostly silent
+    pub track: Option<track::Model>,
+    /// Seconds the dominant track (or silence) played within the bucket
+    pub dominant_seconds: f64,
+    /// Seconds spent within the bucket
+    pub total_seconds: f64,
+}
+
+/// Default width of a listen density bucket, in seconds (5 minutes)
+pub const DEFAULT_LISTEN_DENSITY_BUCKET_SECONDS: u32 = 300;
+
+/// Smallest accepted listen density bucket, in seconds
+///
+/// Shorter than any track, so finer buckets add nothing but allocations on long activities.
+pub const MIN_LISTEN_DENSITY_BUCKET_SECONDS: u32 = 30;
+
+/// A fixed-size time bucket of an activity with how many distinct tracks played in it
+///
+/// Many tracks in one bucket point at skipping, a single one at a track played through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DensityBucket {
+    pub index: usize,
+    pub start_time: DateTime<Utc>,
+    /// Bucket end (the last bucket ends with the activity)
+    pub end_time: DateTime<Utc>,
+    /// Distinct tracks playing at some point within the bucket
+    pub distinct_tracks: usize,
+}
+
+/// Longest padding allowed on either side of an activity, in seconds (15 minutes)
+pub const MAX_LISTEN_PADDING_SECONDS: u32 = 900;
+
+/// Error returned for a listen padding outside the allowed bounds
+#[derive(Debug, thiserror::Error)]
+#[error("Listen padding must be between 0 and {MAX_LISTEN_PADDING_SECONDS} seconds, got {0}")]
+pub struct ListenPaddingError(pub u32);
+
+/// Extra time around an activity in which listens are matched
+///
+/// Widens the window used to load and sync listens, so a track started just
+/// before the activity covers its start and late scrobbles are fetched from
+/// Last.fm. Segments stay within the activity: listens after its end are dropped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ListenPadding {
+    pub before_seconds: u32,
+    pub after_seconds: u32,
+}
+
+impl ListenPadding {
+    /// Builds a padding, checking both sides are within bounds
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either side exceeds `MAX_LISTEN_PADDING_SECONDS`
+    pub fn new(before_seconds: u32, after_seconds: u32) -> Result<Self, ListenPaddingError> {
+        for seconds in [before_seconds, after_seconds] {
+            if seconds > MAX_LISTEN_PADDING_SECONDS {
+                return Err(ListenPaddingError(seconds));
+            }
+        }
+        Ok(Self {
+            before_seconds,
+            after_seconds,
+        })
+    }
+
+    /// The default padding stored in a user's profile
+    #[must_use]
+    pub fn from_user(user: &user::Model) -> Self {
+        let clamp =
+            |seconds: i32| u32::try_from(seconds).map_or(0, |s| s.min(MAX_LISTEN_PADDING_SECONDS));
+        Self {
+            before_seconds: clamp(user.listen_padding_before_seconds),
+            after_seconds: clamp(user.listen_padding_after_seconds),
+        }
+    }
+
+    /// Replaces each side with its override, when given
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an override exceeds `MAX_LISTEN_PADDING_SECONDS`
+    pub fn with_overrides(
+        self,
+        before_seconds: Option<u32>,
+        after_seconds: Option<u32>,
+    ) -> Result<Self, ListenPaddingError> {
+        Self::new(
+            before_seconds.unwrap_or(self.before_seconds),
+            after_seconds.unwrap_or(self.after_seconds),
+        )
+    }
+}
+
+/// The user has not linked a Last.fm account, so listens cannot be synced
+///
+/// Kept distinct from other failures so clients can prompt for the account setup.
+#[derive(Debug, thiserror::Error)]
+#[error("User does not have a Last.fm username configured")]
+pub struct LastfmNotConfiguredError;
+
+/// The activity has no positive elapsed or moving time to build a window from
+///
+/// A corrupt duration would put the activity end before its start and silently
+/// match no listens, so it is reported instead.
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "Activity has no usable duration: elapsed_time is {elapsed_time}s and moving_time is {moving_time}s"
+)]
+pub struct InvalidActivityDurationError {
+    pub elapsed_time: i32,
+    pub moving_time: i32,
+}
+
+/// Duration of an activity in seconds for window math
+///
+/// Uses `elapsed_time`, falling back to `moving_time` when the elapsed time is
+/// zero or negative.
+///
+/// # Errors
+///
+/// Returns an error if neither duration is positive
+pub fn activity_duration_seconds(
+    elapsed_time: i32,
+    moving_time: i32,
+) -> Result<i32, InvalidActivityDurationError> {
+    if elapsed_time > 0 {
+        Ok(elapsed_time)
+    } else if moving_time > 0 {
+        Ok(moving_time)
+    } else {
+        Err(InvalidActivityDurationError {
+            elapsed_time,
+            moving_time,
+        })
+    }
+}
+
+/// Largest listen time offset allowed in either direction, in seconds (1 hour)
+pub const MAX_TIME_OFFSET_SECONDS: i32 = 3600;
+
+/// Error returned for a listen time offset outside the allowed bounds
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "Time offset must be between -{MAX_TIME_OFFSET_SECONDS} and {MAX_TIME_OFFSET_SECONDS} seconds, got {0}"
+)]
+pub struct TimeOffsetError(pub i32);
+
+/// Checks a listen time offset is within `MAX_TIME_OFFSET_SECONDS` of zero
+///
+/// # Errors
+///
+/// Returns an error if the offset is out of bounds
+pub fn validate_time_offset(seconds: i32) -> Result<i32, TimeOffsetError> {
+    if (-MAX_TIME_OFFSET_SECONDS..=MAX_TIME_OFFSET_SECONDS).contains(&seconds) {
+        Ok(seconds)
+    } else {
+        Err(TimeOffsetError(seconds))
+    }
+}
+
+/// How listens are matched to an activity's timeline
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ListenMatchOptions {
+    /// Extra time around the activity in which listens are matched
+    pub padding: ListenPadding,
+    /// Music segments shorter than this are merged into the previous segment (0 keeps all)
+    ///
+    /// Filters out skipped tracks, which otherwise show as segments of a few seconds.
+    pub min_segment_seconds: u32,
+    /// Use only the listens already stored, never syncing Last.fm
+    ///
+    /// Recomputes segments after manual listen edits or an offset change
+    /// without any external call.
+    pub no_fetch: bool,
+    /// Activity duration listens are matched over (streams always span the elapsed time)
+    pub window: ActivityWindow,
+    /// Segments are split where GPS fixes are more than this many seconds apart (0 never splits)
+    ///
+    /// Lets the map draw separate polylines across tunnels or paused recordings.
+    pub split_gap_seconds: u32,
+    /// Match the Last.fm "now playing" track as a listen at the current time when syncing
+    ///
+    /// For activities still in progress, whose current track has no play date yet.
+    /// The listen is never stored, so later syncs don't duplicate it.
+    pub include_now_playing: bool,
+}
+
+impl ListenMatchOptions {
+    /// Matching with a padding, no minimum segment duration and a Last.fm sync when needed
+    #[must_use]
+    pub fn with_padding(padding: ListenPadding) -> Self {
+        Self {
+            padding,
+            min_segment_seconds: 0,
+            no_fetch: false,
+            window: ActivityWindow::Elapsed,
+            split_gap_seconds: 0,
+            include_now_playing: false,
+        }
+    }
+
+    /// The GPS gap segments are split at, `None` when splitting is off
+    fn split_gap(&self) -> Option<chrono::Duration> {
+        (self.split_gap_seconds > 0)
+            .then(|| chrono::Duration::seconds(i64::from(self.split_gap_seconds)))
+    }
 }
 
 /// Retrieves music tracks played during a specific activity with GPS segments
@@ -91,7 +485,10 @@ pub struct SimplificationStats {
 /// * `user_id` - ID of the user
 /// * `activity_id` - ID of the activity
 /// * `simplify` - Whether to apply GPS simplification
-/// * `tolerance` - Simplification tolerance in meters (default: 10.0)
+/// * `tolerance` - Simplification tolerance in meters or `auto` (default: 10.0 meters)
+/// * `algorithm` - Simplification algorithm, RDP or VW
+/// * `matching` - Listen padding and window, minimum music segment duration, GPS gap
+///   splitting and whether Last.fm may be synced
 ///
 /// # Returns
 ///
@@ -101,8 +498,9 @@ pub struct SimplificationStats {
 ///
 /// Returns an error if:
 /// - Activity is not found in the database
+/// - Activity has neither a positive elapsed nor moving time (`InvalidActivityDurationError`)
 /// - User is not found in the database
-/// - User does not have a Last.fm username configured
+/// - User does not have a Last.fm username configured (`LastfmNotConfiguredError`)
 /// - Last.fm API request fails
 /// - Database query fails
 /// - GPS simplification fails
@@ -111,8 +509,216 @@ pub async fn get_activity_music(
     user_id: Uuid,
     activity_id: Uuid,
     simplify: bool,
-    tolerance: Option<f64>,
+    tolerance: Option<SimplificationTolerance>,
+    algorithm: SimplificationAlgorithm,
+    matching: ListenMatchOptions,
 ) -> Result<(Vec<Segment>, SimplificationStats), Box<dyn std::error::Error>> {
+    let inputs = load_activity_music_inputs(db, user_id, activity_id, matching).await?;
+    let listens = drop_skipped_listens(
+        inputs.listens,
+        inputs.activity_end,
+        matching.min_segment_seconds,
+    );
+
+    // Count only GPS points within activity time range for accurate statistics.
+    // Indoor activities have no GPS at all, so every point in range counts instead.
+    let points_in_range: Vec<&Model> = inputs
+        .streams
+        .iter()
+        .filter(|s| s.time >= inputs.activity_start && s.time <= inputs.activity_end)
+        .collect();
+    let activity_has_gps = points_in_range.iter().any(|s| has_gps_coordinates(s));
+    let original_points = points_in_range
+        .iter()
+        .filter(|s| !activity_has_gps || has_gps_coordinates(s))
+        .count();
+
+    // Segments are simplified one by one, but share the tolerance of the whole route
+    let tolerance = tolerance
+        .map(|tolerance| tolerance.meters(route_length_meters(points_in_range.iter().copied())));
+
+    let segments = build_activity_segments(
+        &inputs.streams,
+        &listens,
+        inputs.activity_start,
+        inputs.activity_end,
+        simplify.then(|| SegmentSimplification {
+            tolerance,
+            algorithm,
+            min_points: simplify_min_points(),
+        }),
+        matching.split_gap(),
+    )?;
+
+    let stats = SimplificationStats {
+        music_coverage_ratio: calculate_music_coverage(
+            &segments,
+            inputs.activity_start,
+            inputs.activity_end,
+        ),
+        avg_bpm: activity_avg_bpm(&segments),
+        avg_temperature: average_temperature(points_in_range.iter().copied()),
+        ..calculate_stats(&segments, original_points)
+    };
+
+    Ok((segments, stats))
+}
+
+/// Retrieves the dominant music track for each distance bucket of an activity
+///
+/// Answers "which song played in which kilometer" using the distance stream:
+/// each bucket reports the track that played for the longest time within it.
+///
+/// # Arguments
+/// * `db` - Database connection
+/// * `user_id` - ID of the user
+/// * `activity_id` - ID of the activity
+/// * `bucket_meters` - Bucket size in meters (e.g. 1000.0 for per-kilometer)
+/// * `matching` - Listen padding and window, and whether Last.fm may be synced
+///   (`min_segment_seconds` does not apply to buckets)
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Bucket size is below `MIN_DISTANCE_BUCKET_METERS` or not a number
+/// - Activity is not found or does not belong to the user
+/// - Last.fm sync or a database query fails
+pub async fn get_activity_music_by_distance(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    activity_id: Uuid,
+    bucket_meters: f64,
+    matching: ListenMatchOptions,
+) -> Result<Vec<DistanceBucket>, Box<dyn std::error::Error>> {
+    if !is_valid_distance_bucket(bucket_meters) {
+        return Err(
+            format!("Bucket size must be at least {MIN_DISTANCE_BUCKET_METERS} meters").into(),
+        );
+    }
+
+    let inputs = load_activity_music_inputs(db, user_id, activity_id, matching).await?;
+
+    Ok(build_distance_buckets(
+        &inputs.streams,
+        &inputs.listens,
+        inputs.activity_start,
+        inputs.activity_end,
+        bucket_meters,
+    ))
+}
+
+/// Whether `bucket_meters` is a finite bucket size of at least `MIN_DISTANCE_BUCKET_METERS`
+#[must_use]
+pub fn is_valid_distance_bucket(bucket_meters: f64) -> bool {
+    bucket_meters.is_finite() && bucket_meters >= MIN_DISTANCE_BUCKET_METERS
+}
+
+/// Counts the distinct tracks played in each fixed-size time bucket of an activity
+///
+/// Only listens are loaded, not streams. A listen plays from its `played_at` until
+/// the next listen starts, like a music segment, and counts in every bucket it overlaps.
+///
+/// # Arguments
+/// * `db` - Database connection
+/// * `user_id` - ID of the user
+/// * `activity_id` - ID of the activity
+/// * `bucket_seconds` - Bucket size in seconds (e.g. 300 for five-minute buckets)
+/// * `matching` - Listen padding and window, and whether Last.fm may be synced
+///   (`min_segment_seconds` does not apply: skipped tracks are what this counts)
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Bucket size is below `MIN_LISTEN_DENSITY_BUCKET_SECONDS`
+/// - Activity is not found or does not belong to the user
+/// - Last.fm sync or a database query fails
+pub async fn get_activity_listen_density(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    activity_id: Uuid,
+    bucket_seconds: u32,
+    matching: ListenMatchOptions,
+) -> Result<Vec<DensityBucket>, Box<dyn std::error::Error>> {
+    if !is_valid_density_bucket(bucket_seconds) {
+        return Err(format!(
+            "Bucket size must be at least {MIN_LISTEN_DENSITY_BUCKET_SECONDS} seconds"
+        )
+        .into());
+    }
+
+    let inputs = load_activity_listens(db, user_id, activity_id, matching).await?;
+
+    Ok(build_listen_density(
+        &inputs.listens,
+        inputs.activity_start,
+        inputs.activity_end,
+        chrono::Duration::seconds(i64::from(bucket_seconds)),
+    ))
+}
+
+/// Whether `bucket_seconds` is at least `MIN_LISTEN_DENSITY_BUCKET_SECONDS`
+#[must_use]
+pub fn is_valid_density_bucket(bucket_seconds: u32) -> bool {
+    bucket_seconds >= MIN_LISTEN_DENSITY_BUCKET_SECONDS
+}
+
+/// Streams and listens of an activity window, shared by all segmentation modes
+struct ActivityMusicInputs {
+    activity_start: DateTime<Utc>,
+    activity_end: DateTime<Utc>,
+    streams: Vec<Model>,
+    listens: Vec<(listen::Model, Option<track::Model>)>,
+}
+
+/// Listens of an activity window, ordered by `played_at`
+struct ActivityListens {
+    activity_start: DateTime<Utc>,
+    activity_end: DateTime<Utc>,
+    listens: Vec<(listen::Model, Option<track::Model>)>,
+}
+
+/// Loads an activity's streams and listens, syncing Last.fm first if no listens are stored
+/// (unless `matching.no_fetch` is set)
+async fn load_activity_music_inputs(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    activity_id: Uuid,
+    matching: ListenMatchOptions,
+) -> Result<ActivityMusicInputs, Box<dyn std::error::Error>> {
+    let ActivityListens {
+        activity_start,
+        activity_end,
+        listens,
+    } = load_activity_listens(db, user_id, activity_id, matching).await?;
+
+    // Retrieve only the activity window, matching the in-memory boundaries
+    let streams = get_activity_streams_in_range(
+        db,
+        activity_id,
+        activity_start.fixed_offset(),
+        activity_end.fixed_offset(),
+    )
+    .await?;
+
+    Ok(ActivityMusicInputs {
+        activity_start,
+        activity_end,
+        streams,
+        listens,
+    })
+}
+
+/// Loads an activity's listens with their tracks, syncing Last.fm first if none are stored
+///
+/// With `matching.no_fetch`, only stored listens are used and Last.fm is never contacted.
+/// `matching.window` only bounds the listens matched: the returned activity always
+/// ends at its elapsed time, so no stream point is cut off.
+async fn load_activity_listens(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    activity_id: Uuid,
+    matching: ListenMatchOptions,
+) -> Result<ActivityListens, Box<dyn std::error::Error>> {
     let activity = get_activity_by_id(db, activity_id)
         .await?
         .ok_or("Activity not found")?;
@@ -121,67 +727,270 @@ pub async fn get_activity_music(
         return Err("Activity does not belong to the user".into());
     }
 
-    let end_time =
-        activity.start_time + chrono::Duration::seconds(i64::from(activity.elapsed_time));
-    let wide_start_time = activity.start_time;
-
-    let listens = get_listens_by_user_time_range(db, user_id, wide_start_time, end_time).await?;
-
-    if listens.is_empty() {
+    let elapsed_seconds = activity_duration_seconds(activity.elapsed_time, activity.moving_time)?;
+    let matched_seconds = matching
+        .window
+        .duration_seconds(activity.elapsed_time, activity.moving_time)?;
+    let window = listen_window(
+        activity.start_time,
+        matched_seconds,
+        matching.padding,
+        activity.time_offset_seconds,
+    );
+
+    let listens =
+        get_listens_by_user_time_range(db, user_id, window.wide_start, window.wide_end).await?;
+
+    let mut now_playing = None;
+    if listens.is_empty() && !matching.no_fetch {
         // Fetch user to get Last.fm username
         let user = get_user_by_id(db, user_id).await?.ok_or("User not found")?;
 
-        let lastfm_username = user
-            .lastfm_username
-            .ok_or("User does not have a Last.fm username configured")?;
+        let lastfm_username = user.lastfm_username.ok_or(LastfmNotConfiguredError)?;
 
-        sync_lastfm_for_time_range(
+        now_playing = sync_lastfm_for_time_range(
             user_id,
             &lastfm_username,
-            wide_start_time.timestamp(),
-            end_time.timestamp(),
+            window.wide_start.timestamp(),
+            window.wide_end.timestamp(),
+            matching.include_now_playing,
             db,
         )
-        .await?;
+        .await?
+        .now_playing;
     }
 
-    // Retrieve Activity Streams
-    let streams = get_activity_streams(db, activity_id).await?;
-
-    let listens_with_tracks = Listen::find()
+    // Listens in the trailing padding started after the activity and never overlap it
+    let mut listens_with_tracks = Listen::find()
         .filter(listen::Column::UserId.eq(user_id))
-        .filter(listen::Column::PlayedAt.gte(wide_start_time))
-        .filter(listen::Column::PlayedAt.lte(end_time))
+        .filter(listen::Column::PlayedAt.gte(window.wide_start))
+        .filter(listen::Column::PlayedAt.lte(window.matched_end))
         .order_by_asc(listen::Column::PlayedAt)
         .find_also_related(Track)
         .all(db)
         .await?;
+    // The unsaved "now playing" listen is the latest one, after every stored listen
+    if let Some((listen, track)) = now_playing
+        .filter(|(listen, _)| (window.wide_start..=window.matched_end).contains(&listen.played_at))
+    {
+        listens_with_tracks.push((listen, Some(track)));
+    }
+    let listens_with_tracks = apply_time_offset(listens_with_tracks, activity.time_offset_seconds);
+
+    let activity_start: DateTime<Utc> = activity.start_time.into();
+    let activity_end = activity.start_time + chrono::Duration::seconds(i64::from(elapsed_seconds));
+    Ok(ActivityListens {
+        activity_start,
+        activity_end: activity_end.into(),
+        listens: skip_listens_ended_before(listens_with_tracks, activity_start),
+    })
+}
+
+/// Bounds of the listen query for an activity, on the listening device's clock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ListenWindow {
+    /// Earliest listen loaded or synced, including the leading padding
+    wide_start: DateTime<FixedOffset>,
+    /// Latest listen synced from Last.fm, including the trailing padding
+    wide_end: DateTime<FixedOffset>,
+    /// Latest listen matched to the activity: listens after its end never overlap it
+    matched_end: DateTime<FixedOffset>,
+}
+
+/// Maps an activity's time span (with padding) onto the listening device's clock
+///
+/// A positive `time_offset_seconds` means scrobbles are stamped that much earlier
+/// than the GPS clock, so the window moves back by the same amount.
+fn listen_window(
+    start_time: DateTime<FixedOffset>,
+    elapsed_time: i32,
+    padding: ListenPadding,
+    time_offset_seconds: i32,
+) -> ListenWindow {
+    let offset = chrono::Duration::seconds(i64::from(time_offset_seconds));
+    let end_time = start_time + chrono::Duration::seconds(i64::from(elapsed_time));
+
+    ListenWindow {
+        wide_start: start_time
+            - chrono::Duration::seconds(i64::from(padding.before_seconds))
+            - offset,
+        wide_end: end_time + chrono::Duration::seconds(i64::from(padding.after_seconds)) - offset,
+        matched_end: end_time - offset,
+    }
+}
+
+/// Moves listen times onto the GPS clock by adding the activity's time offset
+fn apply_time_offset(
+    mut listens: Vec<(listen::Model, Option<track::Model>)>,
+    time_offset_seconds: i32,
+) -> Vec<(listen::Model, Option<track::Model>)> {
+    if time_offset_seconds != 0 {
+        let offset = chrono::Duration::seconds(i64::from(time_offset_seconds));
+        for (listen, _) in &mut listens {
+            listen.played_at += offset;
+        }
+    }
+    listens
+}
 
-    // Count only GPS points within activity time range for accurate statistics
-    let activity_start_utc: DateTime<Utc> = activity.start_time.into();
-    let end_time_utc: DateTime<Utc> = end_time.into();
-    let original_gps_points = streams
+/// Drops listens from the leading padding that a later listen replaced before the start
+///
+/// Only the last listen started at or before `activity_start` can still be playing
+/// when the activity begins; earlier ones would become segments without points.
+fn skip_listens_ended_before(
+    mut listens: Vec<(listen::Model, Option<track::Model>)>,
+    activity_start: DateTime<Utc>,
+) -> Vec<(listen::Model, Option<track::Model>)> {
+    let started_before = listens
         .iter()
-        .filter(|s| {
-            s.latitude.is_some()
-                && s.longitude.is_some()
-                && s.time >= activity_start_utc
-                && s.time <= end_time_utc
-        })
+        .take_while(|(listen, _)| listen.played_at <= activity_start)
         .count();
+    if started_before > 1 {
+        listens.drain(..started_before - 1);
+    }
+    listens
+}
 
-    let segments = build_activity_segments(
-        &streams,
-        &listens_with_tracks,
-        activity.start_time.into(),
-        end_time.into(),
-        simplify,
-        tolerance,
-    )?;
+/// Drops listens whose music segment would last less than `min_segment_seconds`
+///
+/// A listen's segment runs until the next listen (or the activity end), so dropping
+/// it merges its time and points into the previous segment. Segments are then built
+/// from the remaining listens, which keeps their indices contiguous.
+fn drop_skipped_listens(
+    listens: Vec<(listen::Model, Option<track::Model>)>,
+    activity_end: DateTime<Utc>,
+    min_segment_seconds: u32,
+) -> Vec<(listen::Model, Option<track::Model>)> {
+    if min_segment_seconds == 0 {
+        return listens;
+    }
+    let min_duration = chrono::Duration::seconds(i64::from(min_segment_seconds));
 
-    let stats = calculate_stats(&segments, original_gps_points);
+    let ends: Vec<DateTime<Utc>> = listens
+        .iter()
+        .skip(1)
+        .map(|(listen, _)| listen.played_at.into())
+        .chain(std::iter::once(activity_end))
+        .collect();
+    listens
+        .into_iter()
+        .zip(ends)
+        .filter(|((listen, _), end)| *end - DateTime::<Utc>::from(listen.played_at) >= min_duration)
+        .map(|(listen, _)| listen)
+        .collect()
+}
 
-    Ok((segments, stats))
+/// What was happening at a given moment of an activity
+#[derive(Debug, Clone)]
+pub struct ActivityMoment {
+    /// Track playing at that moment, `None` if no music was playing
+    pub track: Option<track::Model>,
+    /// Meters covered at that moment, `None` without a distance stream
+    pub distance: Option<f64>,
+}
+
+/// Finds the track that was playing and the distance covered at a given moment of an activity
+///
+/// Loads only the activity's stored listens and the two distance readings around
+/// `at`, never its streams nor Last.fm, so it is cheap enough to call repeatedly
+/// from a map scrubber.
+///
+/// # Returns
+///
+/// The track whose segment contains `at` and the distance interpolated with `distance_at`
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - `at` falls outside the activity window
+/// - Activity is not found or does not belong to the user
+/// - A database query fails
+pub async fn get_activity_track_at(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    activity_id: Uuid,
+    at: DateTime<Utc>,
+    padding: ListenPadding,
+) -> Result<ActivityMoment, Box<dyn std::error::Error>> {
+    let inputs = load_activity_listens(
+        db,
+        user_id,
+        activity_id,
+        ListenMatchOptions {
+            no_fetch: true,
+            ..ListenMatchOptions::with_padding(padding)
+        },
+    )
+    .await?;
+
+    let track = track_at(
+        &inputs.listens,
+        inputs.activity_start,
+        inputs.activity_end,
+        at,
+    )?
+    .cloned();
+    let points = get_distance_points_around(db, activity_id, at.into()).await?;
+
+    Ok(ActivityMoment {
+        track,
+        distance: distance_at(&points, at),
+    })
+}
+
+/// Interpolates the cumulative distance (meters) covered at `at`
+///
+/// Uses the two points with a distance reading surrounding `at`, linearly in time.
+/// Before the first reading or after the last one the distance is clamped to it.
+///
+/// # Returns
+///
+/// The distance, or `None` if no point has a distance reading
+#[allow(clippy::cast_precision_loss)]
+pub fn distance_at(streams: &[Model], at: DateTime<Utc>) -> Option<f64> {
+    let points: Vec<(DateTime<Utc>, f64)> = streams
+        .iter()
+        .filter_map(|s| s.distance.map(|d| (s.time.into(), f64::from(d))))
+        .collect();
+
+    let after = points.partition_point(|&(time, _)| time <= at);
+    let Some(&(before_time, before_distance)) = after.checked_sub(1).map(|i| &points[i]) else {
+        return points.first().map(|&(_, distance)| distance);
+    };
+    let Some(&(after_time, after_distance)) = points.get(after) else {
+        return Some(before_distance);
+    };
+
+    let span = (after_time - before_time).num_milliseconds();
+    if span <= 0 {
+        return Some(before_distance);
+    }
+    let elapsed = (at - before_time).num_milliseconds();
+    Some(before_distance + (after_distance - before_distance) * elapsed as f64 / span as f64)
+}
+
+/// Binary searches the listens (ordered by `played_at`) for the one playing at `at`
+///
+/// Uses the same boundaries as the music segments: a listen plays from its
+/// `played_at` until the next listen starts, and nothing plays before the first one.
+///
+/// # Errors
+///
+/// Returns an error if `at` falls outside `[activity_start, activity_end]`
+pub fn track_at(
+    listens: &[(listen::Model, Option<track::Model>)],
+    activity_start: DateTime<Utc>,
+    activity_end: DateTime<Utc>,
+    at: DateTime<Utc>,
+) -> Result<Option<&track::Model>, Box<dyn std::error::Error>> {
+    if at < activity_start || at > activity_end {
+        return Err("Timestamp is outside the activity window".into());
+    }
+
+    Ok(listens
+        .partition_point(|(listen, _)| listen.played_at <= at)
+        .checked_sub(1)
+        .and_then(|i| listens[i].1.as_ref()))
 }
 
 fn build_activity_segments(
@@ -189,34 +998,28 @@ fn build_activity_segments(
     listens: &[(listen::Model, Option<track::Model>)],
     activity_start: DateTime<Utc>,
     activity_end: DateTime<Utc>,
-    simplify: bool,
-    tolerance: Option<f64>,
+    simplification: Option<SegmentSimplification>,
+    split_gap: Option<chrono::Duration>,
 ) -> Result<Vec<Segment>, Box<dyn std::error::Error>> {
     let mut segments = Vec::new();
 
     // If no music listens, return the entire activity as a single segment
     if listens.is_empty() {
-        let mut all_points: Vec<Model> = streams
+        let all_points: Vec<Model> = streams
             .iter()
             .filter(|s| s.time >= activity_start && s.time <= activity_end)
             .cloned()
             .collect();
+        push_segments(
+            &mut segments,
+            None,
+            (activity_start, activity_end),
+            all_points,
+            simplification,
+            split_gap,
+        )?;
 
-        if simplify && all_points.len() >= 2 {
-            let tolerance_meters =
-                tolerance.unwrap_or(f64::from(DEFAULT_SIMPLIFICATION_TOLERANCE_METERS));
-            let indices = simplify_gps_route(&all_points, tolerance_meters)?;
-            all_points = indices.iter().map(|&i| all_points[i].clone()).collect();
-        }
-
-        segments.push(Segment {
-            index: 0,
-            track: None,
-            start_time: activity_start,
-            end_time: activity_end,
-            points: all_points,
-        });
-
+        ensure_full_coverage(&mut segments, activity_start, activity_end);
         return Ok(segments);
     }
 
@@ -228,20 +1031,14 @@ fn build_activity_segments(
             .filter(|s| s.time >= activity_start && s.time < listens[0].0.played_at)
             .cloned()
             .collect();
-        let mut segment_points = pre_music_points;
-        if simplify && segment_points.len() >= 2 {
-            let tolerance_meters =
-                tolerance.unwrap_or(f64::from(DEFAULT_SIMPLIFICATION_TOLERANCE_METERS));
-            let indices = simplify_gps_route(&segment_points, tolerance_meters)?;
-            segment_points = indices.iter().map(|&i| segment_points[i].clone()).collect();
-        }
-        segments.push(Segment {
-            index: 0,
-            track: None,
-            start_time: activity_start,
-            end_time: listens[0].0.played_at.into(),
-            points: segment_points,
-        });
+        push_segments(
+            &mut segments,
+            None,
+            (activity_start, listens[0].0.played_at.into()),
+            pre_music_points,
+            simplification,
+            split_gap,
+        )?;
     }
 
     // Music segments
@@ -251,32 +1048,568 @@ fn build_activity_segments(
             .get(i + 1)
             .map_or(activity_end.into(), |(l, _)| l.played_at);
 
-        let mut segment_points: Vec<Model> = streams
+        let segment_points: Vec<Model> = streams
             .iter()
             .filter(|s| s.time >= start_time && s.time < end_time)
             .cloned()
             .collect();
+        push_segments(
+            &mut segments,
+            track.as_ref(),
+            (start_time.into(), end_time.into()),
+            segment_points,
+            simplification,
+            split_gap,
+        )?;
+    }
 
-        if simplify && segment_points.len() >= 2 {
-            let tolerance_meters =
-                tolerance.unwrap_or(f64::from(DEFAULT_SIMPLIFICATION_TOLERANCE_METERS));
+    ensure_full_coverage(&mut segments, activity_start, activity_end);
+    mark_repeated_tracks(&mut segments);
+    Ok(segments)
+}
+
+/// Repairs segments so they tile the activity without gaps or overlaps
+///
+/// Each segment must end where the next one starts, the first must start no later
+/// than `activity_start` (a track already playing may start earlier) and the last
+/// must end at `activity_end`. Boundaries are moved to satisfy this, never points.
+///
+/// A segment the next one starts before is left with nothing to cover: it is
+/// dropped and its points move to the next segment, then segments are renumbered.
+///
+/// # Returns
+///
+/// The number of corrected boundaries and dropped segments, each logged as a warning
+fn ensure_full_coverage(
+    segments: &mut Vec<Segment>,
+    activity_start: DateTime<Utc>,
+    activity_end: DateTime<Utc>,
+) -> usize {
+    let mut corrections = drop_swallowed_segments(segments);
+
+    if let Some(first) = segments.first_mut() {
+        if first.start_time > activity_start {
+            warn!(
+                segment = first.index,
+                start = %first.start_time,
+                %activity_start,
+                "First segment starts after the activity, extending it back"
+            );
+            first.start_time = activity_start;
+            corrections += 1;
+        }
+    }
+
+    for i in 1..segments.len() {
+        let next_start = segments[i].start_time;
+        let previous = &mut segments[i - 1];
+        if previous.end_time != next_start {
+            warn!(
+                segment = previous.index,
+                end = %previous.end_time,
+                %next_start,
+                "Segment does not end where the next one starts, moving its end"
+            );
+            previous.end_time = next_start;
+            corrections += 1;
+        }
+    }
+
+    if let Some(last) = segments.last_mut() {
+        if last.end_time != activity_end {
+            warn!(
+                segment = last.index,
+                end = %last.end_time,
+                %activity_end,
+                "Last segment does not end with the activity, moving its end"
+            );
+            last.end_time = activity_end;
+            corrections += 1;
+        }
+    }
+
+    corrections
+}
 
-            // Get indices of points to keep
-            let indices = simplify_gps_route(&segment_points, tolerance_meters)?;
+/// Drops the segments that start after the segment following them, merging their points into it
+///
+/// Moving such a segment's end to the next start would leave it ending before it starts.
+fn drop_swallowed_segments(segments: &mut Vec<Segment>) -> usize {
+    let mut dropped = 0;
+    let mut kept: Vec<Segment> = Vec::with_capacity(segments.len());
+
+    for mut segment in segments.drain(..) {
+        while let Some(swallowed) = kept.pop_if(|previous| segment.start_time < previous.start_time)
+        {
+            warn!(
+                segment = swallowed.index,
+                start = %swallowed.start_time,
+                next_start = %segment.start_time,
+                "Segment starts after the next one, merging it into the next one"
+            );
+            segment.points.extend(swallowed.points);
+            segment.points.sort_by_key(|point| point.time);
+            dropped += 1;
+        }
+        kept.push(segment);
+    }
 
-            // Filter points using indices
-            segment_points = indices.iter().map(|&i| segment_points[i].clone()).collect();
+    if dropped > 0 {
+        for (index, segment) in kept.iter_mut().enumerate() {
+            segment.index = index;
         }
+    }
+    *segments = kept;
+    dropped
+}
+
+/// Appends the segment covering `(start, end)`, split at GPS gaps longer than `split_gap`
+///
+/// Sub-segments share the track and stay contiguous in time: each one after the
+/// first starts at its first point and is flagged `after_gap`. Temperature is
+/// averaged per sub-segment before simplification.
+///
+/// # Errors
+///
+/// Returns an error if simplifying a sub-segment fails.
+fn push_segments(
+    segments: &mut Vec<Segment>,
+    track: Option<&track::Model>,
+    (start_time, end_time): (DateTime<Utc>, DateTime<Utc>),
+    points: Vec<Model>,
+    simplification: Option<SegmentSimplification>,
+    split_gap: Option<chrono::Duration>,
+) -> Result<(), SimplificationError> {
+    let runs = match split_gap {
+        Some(min_gap) => split_at_gps_gaps(points, min_gap),
+        None => vec![points],
+    };
+    let run_starts: Vec<DateTime<Utc>> = runs
+        .iter()
+        .enumerate()
+        .map(|(i, run)| match run.first() {
+            Some(first) if i > 0 => first.time.into(),
+            _ => start_time,
+        })
+        .collect();
+
+    for (i, run) in runs.into_iter().enumerate() {
+        let avg_temperature = average_temperature(&run);
+        let points = simplify_segment_points(run, simplification)?;
         segments.push(Segment {
             index: segments.len(),
-            track: track.clone(),
-            start_time: start_time.into(),
-            end_time: end_time.into(),
-            points: segment_points,
+            track: track.cloned(),
+            start_time: run_starts[i],
+            end_time: run_starts.get(i + 1).copied().unwrap_or(end_time),
+            points,
+            avg_temperature,
+            repeated: false,
+            after_gap: i > 0,
         });
     }
+    Ok(())
+}
+
+/// Splits time-ordered points wherever consecutive GPS fixes are more than `min_gap` apart
+///
+/// Points without coordinates never start a new run; they stay with the run of the
+/// last fix. Always returns at least one run.
+fn split_at_gps_gaps(points: Vec<Model>, min_gap: chrono::Duration) -> Vec<Vec<Model>> {
+    let mut runs = Vec::new();
+    let mut current = Vec::new();
+    let mut last_fix: Option<DateTime<FixedOffset>> = None;
+
+    for point in points {
+        if has_gps_coordinates(&point) {
+            if last_fix.is_some_and(|last| point.time - last > min_gap) {
+                runs.push(std::mem::take(&mut current));
+            }
+            last_fix = Some(point.time);
+        }
+        current.push(point);
+    }
+    runs.push(current);
+    runs
+}
 
-    Ok(segments)
+/// Flags the segments whose track plays more than once in the activity
+fn mark_repeated_tracks(segments: &mut [Segment]) {
+    let mut plays: HashMap<Uuid, usize> = HashMap::new();
+    // Sub-segments split at a GPS gap belong to the same play
+    for track in segments
+        .iter()
+        .filter(|s| !s.after_gap)
+        .filter_map(|s| s.track.as_ref())
+    {
+        *plays.entry(track.id).or_default() += 1;
+    }
+
+    for segment in segments {
+        segment.repeated = segment
+            .track
+            .as_ref()
+            .is_some_and(|track| plays[&track.id] > 1);
+    }
+}
+
+/// Counts the plays of each distinct track, in order of first play
+#[must_use]
+pub fn track_play_counts(segments: &[Segment]) -> Vec<TrackPlayCount> {
+    let mut counts: Vec<TrackPlayCount> = Vec::new();
+    for track in segments
+        .iter()
+        .filter(|s| !s.after_gap)
+        .filter_map(|s| s.track.as_ref())
+    {
+        match counts.iter_mut().find(|count| count.track.id == track.id) {
+            Some(count) => count.play_count += 1,
+            None => counts.push(TrackPlayCount {
+                track: track.clone(),
+                play_count: 1,
+            }),
+        }
+    }
+    counts
+}
+
+/// Assigns listens to fixed-size distance buckets using the distance stream
+///
+/// Each interval between consecutive stream points is attributed to the bucket of its
+/// starting distance and to the track playing at its start. Buckets are reported for the
+/// whole covered distance, including ones without music.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+fn build_distance_buckets(
+    streams: &[Model],
+    listens: &[(listen::Model, Option<track::Model>)],
+    activity_start: DateTime<Utc>,
+    activity_end: DateTime<Utc>,
+    bucket_meters: f64,
+) -> Vec<DistanceBucket> {
+    let points: Vec<(DateTime<Utc>, f64)> = streams
+        .iter()
+        .filter(|s| s.time >= activity_start && s.time <= activity_end)
+        .filter_map(|s| s.distance.map(|d| (s.time.into(), f64::from(d))))
+        .collect();
+
+    if points.len() < 2 {
+        return Vec::new();
+    }
+
+    let total_distance = points.iter().map(|&(_, d)| d).fold(0.0, f64::max);
+    let bucket_count = ((total_distance / bucket_meters).ceil() as usize).max(1);
+
+    // Per bucket: seconds per track ID (None = no music), in first-heard order
+    let mut durations: Vec<Vec<(Option<Uuid>, f64)>> = vec![Vec::new(); bucket_count];
+    let mut bucket_seconds = vec![0.0; bucket_count];
+
+    for pair in points.windows(2) {
+        let (start_time, start_distance) = pair[0];
+        let (end_time, _) = pair[1];
+        let seconds = (end_time - start_time).num_milliseconds() as f64 / 1000.0;
+        if seconds <= 0.0 {
+            continue;
+        }
+
+        let bucket = ((start_distance.max(0.0) / bucket_meters) as usize).min(bucket_count - 1);
+        let track_id = listens
+            .partition_point(|(listen, _)| listen.played_at <= start_time)
+            .checked_sub(1)
+            .map(|i| listens[i].0.track_id);
+
+        bucket_seconds[bucket] += seconds;
+        match durations[bucket].iter_mut().find(|(id, _)| *id == track_id) {
+            Some((_, total)) => *total += seconds,
+            None => durations[bucket].push((track_id, seconds)),
+        }
+    }
+
+    durations
+        .into_iter()
+        .zip(bucket_seconds)
+        .enumerate()
+        .map(|(index, (tracks, total_seconds))| {
+            // Strictly greater keeps the earliest heard track on ties
+            let (track_id, dominant_seconds) =
+                tracks.into_iter().fold((None, 0.0), |best, candidate| {
+                    if candidate.1 > best.1 {
+                        candidate
+                    } else {
+                        best
+                    }
+                });
+            let track = track_id.and_then(|id| {
+                listens
+                    .iter()
+                    .find_map(|(_, track)| track.as_ref().filter(|t| t.id == id).cloned())
+            });
+
+            DistanceBucket {
+                index,
+                start_distance: index as f64 * bucket_meters,
+                end_distance: ((index + 1) as f64 * bucket_meters).min(total_distance),
+                track,
+                dominant_seconds,
+                total_seconds,
+            }
+        })
+        .collect()
+}
+
+/// Assigns listens (ordered by `played_at`) to fixed-size time buckets of the activity
+///
+/// Buckets cover the whole activity, including ones without music. A listen
+/// overlapping several buckets counts once in each of them.
+fn build_listen_density(
+    listens: &[(listen::Model, Option<track::Model>)],
+    activity_start: DateTime<Utc>,
+    activity_end: DateTime<Utc>,
+    bucket: chrono::Duration,
+) -> Vec<DensityBucket> {
+    let duration_ms =
+        u64::try_from((activity_end - activity_start).num_milliseconds()).unwrap_or(0);
+    let bucket_ms = u64::try_from(bucket.num_milliseconds()).unwrap_or(1).max(1);
+    let bucket_count = usize::try_from(duration_ms.div_ceil(bucket_ms))
+        .unwrap_or(1)
+        .max(1);
+    let bucket_bounds = |index: usize| {
+        let start = activity_start + bucket * i32::try_from(index).unwrap_or(i32::MAX);
+        (start, (start + bucket).min(activity_end))
+    };
+    // Bucket holding the millisecond `offset_ms` after the activity start
+    let bucket_index = |offset_ms: i64| {
+        usize::try_from(u64::try_from(offset_ms).unwrap_or(0) / bucket_ms)
+            .unwrap_or(usize::MAX)
+            .min(bucket_count - 1)
+    };
+
+    let mut tracks: Vec<HashSet<Uuid>> = vec![HashSet::new(); bucket_count];
+    let ends = listens
+        .iter()
+        .skip(1)
+        .map(|(listen, _)| listen.played_at.into())
+        .chain(std::iter::once(activity_end));
+    for ((listen, _), end) in listens.iter().zip(ends) {
+        let start = DateTime::<Utc>::from(listen.played_at).max(activity_start);
+        let end: DateTime<Utc> = end.min(activity_end);
+        if end <= start {
+            continue;
+        }
+
+        // The end is exclusive: a listen ending on a bucket boundary stays out of the next one
+        let first = bucket_index((start - activity_start).num_milliseconds());
+        let last = bucket_index((end - activity_start).num_milliseconds() - 1).max(first);
+        for bucket_tracks in &mut tracks[first..=last] {
+            bucket_tracks.insert(listen.track_id);
+        }
+    }
+
+    tracks
+        .into_iter()
+        .enumerate()
+        .map(|(index, bucket_tracks)| {
+            let (start_time, end_time) = bucket_bounds(index);
+            DensityBucket {
+                index,
+                start_time,
+                end_time,
+                distinct_tracks: bucket_tracks.len(),
+            }
+        })
+        .collect()
+}
+
+/// Applies GPS simplification to a segment's points when requested
+///
+/// Segments with fewer than `min_points` GPS coordinates (indoor activities, short
+/// GPS dropouts, tracks skipped after a few seconds) are not worth simplifying, so
+/// their points are returned unchanged instead of failing the whole request.
+///
+/// # Errors
+///
+/// Returns an error if the tolerance is not a positive number.
+fn simplify_segment_points(
+    points: Vec<Model>,
+    simplification: Option<SegmentSimplification>,
+) -> Result<Vec<Model>, SimplificationError> {
+    let Some(simplification) = simplification else {
+        return Ok(points);
+    };
+    if points.iter().filter(|p| has_gps_coordinates(p)).count() < simplification.min_points {
+        return Ok(points);
+    }
+
+    let tolerance_meters = simplification
+        .tolerance
+        .unwrap_or(f64::from(DEFAULT_SIMPLIFICATION_TOLERANCE_METERS));
+
+    // Get indices of points to keep, then filter points using them
+    let indices = simplify_gps_route_with(&points, tolerance_meters, simplification.algorithm)?;
+    Ok(indices.iter().map(|&i| points[i].clone()).collect())
+}
+
+/// Whether a stream point carries both latitude and longitude
+fn has_gps_coordinates(point: &Model) -> bool {
+    point.latitude.is_some() && point.longitude.is_some()
+}
+
+/// Reduces an activity stream to at most `max_points` points, preserving time order
+///
+/// GPS points are reduced with budget-driven RDP so the route keeps its shape.
+/// Points without usable GPS coordinates (indoor activities, tunnels, signal
+/// gaps) are kept with evenly spaced sampling. A mixed stream splits the budget
+/// between the two in proportion to their counts.
+///
+/// # Arguments
+/// * `streams` - Activity stream points ordered by time
+/// * `max_points` - Maximum number of points to keep
+///
+/// # Returns
+///
+/// The reduced stream, still ordered by time. Returned unchanged if already within budget.
+#[must_use]
+pub fn downsample_streams(streams: Vec<Model>, max_points: usize) -> Vec<Model> {
+    if streams.len() <= max_points {
+        return streams;
+    }
+
+    let without_gps: Vec<usize> = (0..streams.len())
+        .filter(|&i| !has_gps_coordinates(&streams[i]))
+        .collect();
+    let gps_count = streams.len() - without_gps.len();
+    let gps_budget = if gps_count < 2 || max_points < 2 {
+        0
+    } else {
+        (max_points * gps_count / streams.len()).max(2)
+    };
+
+    let mut keep = vec![false; streams.len()];
+    match simplify_gps_route_to_count(&streams, gps_budget) {
+        Ok(indices) => {
+            for index in indices {
+                keep[index] = true;
+            }
+            for i in evenly_spaced_indices(without_gps.len(), max_points - gps_budget) {
+                keep[without_gps[i]] = true;
+            }
+        }
+        Err(_) => {
+            for index in evenly_spaced_indices(streams.len(), max_points) {
+                keep[index] = true;
+            }
+        }
+    }
+
+    streams
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(point, keep)| keep.then_some(point))
+        .collect()
+}
+
+/// Min, max and mean of one numeric stream channel
+///
+/// All fields but `count` are `None` when no point has a value for the channel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ChannelStats {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub avg: Option<f64>,
+    /// Number of points with a value
+    pub count: usize,
+}
+
+/// Per-channel statistics of an activity's streams
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct StreamStats {
+    pub heart_rate: ChannelStats,
+    pub watts: ChannelStats,
+    pub velocity: ChannelStats,
+    pub altitude: ChannelStats,
+    pub cadence: ChannelStats,
+    pub temperature: ChannelStats,
+}
+
+/// Running min/max/sum of a channel
+#[derive(Debug, Default)]
+struct ChannelAccumulator {
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: usize,
+}
+
+impl ChannelAccumulator {
+    fn push(&mut self, value: Option<f64>) {
+        let Some(value) = value else {
+            return;
+        };
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn finish(self) -> ChannelStats {
+        if self.count == 0 {
+            return ChannelStats::default();
+        }
+        ChannelStats {
+            min: Some(self.min),
+            max: Some(self.max),
+            avg: Some(self.sum / self.count as f64),
+            count: self.count,
+        }
+    }
+}
+
+/// Calculates min/max/avg of each numeric channel in a single pass over the streams
+///
+/// Missing values are skipped, so a channel the device never recorded reports nulls.
+#[must_use]
+pub fn calculate_stream_stats(streams: &[Model]) -> StreamStats {
+    let mut heart_rate = ChannelAccumulator::default();
+    let mut watts = ChannelAccumulator::default();
+    let mut velocity = ChannelAccumulator::default();
+    let mut altitude = ChannelAccumulator::default();
+    let mut cadence = ChannelAccumulator::default();
+    let mut temperature = ChannelAccumulator::default();
+
+    for point in streams {
+        heart_rate.push(point.heart_rate.map(f64::from));
+        watts.push(point.watts.map(f64::from));
+        velocity.push(point.velocity.map(f64::from));
+        altitude.push(point.altitude.map(f64::from));
+        cadence.push(point.cadence.map(f64::from));
+        temperature.push(point.temperature.map(f64::from));
+    }
+
+    StreamStats {
+        heart_rate: heart_rate.finish(),
+        watts: watts.finish(),
+        velocity: velocity.finish(),
+        altitude: altitude.finish(),
+        cadence: cadence.finish(),
+        temperature: temperature.finish(),
+    }
+}
+
+/// Picks `count` evenly spaced indices in `0..len`, always including both ends
+fn evenly_spaced_indices(len: usize, count: usize) -> Vec<usize> {
+    match count {
+        0 => Vec::new(),
+        1 => vec![0],
+        _ => (0..count).map(|i| i * (len - 1) / (count - 1)).collect(),
+    }
 }
 
 /// Calculate simplification statistics from segments
@@ -307,7 +1640,279 @@ fn calculate_stats(segments: &[Segment], original_points: usize) -> Simplificati
         original_points,
         simplified_points,
         reduction_ratio,
+        music_coverage_ratio: 0.0,
+        avg_bpm: None,
+        avg_temperature: None,
+        play_counts: track_play_counts(segments),
+    }
+}
+
+/// Shortest stop reported as a pause by default, in seconds
+pub const DEFAULT_PAUSE_MIN_DURATION_SECONDS: i64 = 15;
+/// Implied speed below which the athlete counts as stationary by default, in m/s
+pub const DEFAULT_PAUSE_MAX_SPEED: f64 = 0.5;
+
+/// An interval where the athlete stayed (nearly) still
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Pause {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub duration_seconds: i64,
+    /// Where the pause started, `None` without GPS
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+/// Finds intervals where the implied speed stayed below `min_speed` for at least `min_duration`
+///
+/// The implied speed between two consecutive points is their distance delta over
+/// their time delta, so recording gaps from auto-pause count as stationary too.
+/// Pairs without a distance reading end the current interval.
+///
+/// # Arguments
+/// * `streams` - Activity stream points ordered by time
+/// * `min_duration` - Shortest interval reported
+/// * `min_speed` - Speed in m/s under which the athlete is considered stopped
+#[must_use]
+pub fn detect_pauses(
+    streams: &[Model],
+    min_duration: chrono::Duration,
+    min_speed: f64,
+) -> Vec<Pause> {
+    let mut pauses = Vec::new();
+    // First point of the current stationary run and the last point seen in it
+    let mut run: Option<(&Model, &Model)> = None;
+
+    for pair in streams.windows(2) {
+        let (from, to) = (&pair[0], &pair[1]);
+        if implied_speed(from, to).is_some_and(|speed| speed < min_speed) {
+            run = Some((run.map_or(from, |(start, _)| start), to));
+        } else if let Some((start, end)) = run.take() {
+            pauses.extend(pause_between(start, end, min_duration));
+        }
+    }
+    if let Some((start, end)) = run {
+        pauses.extend(pause_between(start, end, min_duration));
+    }
+
+    pauses
+}
+
+/// Distance covered between two points over the time between them, in m/s
+fn implied_speed(from: &Model, to: &Model) -> Option<f64> {
+    let elapsed = (to.time - from.time).num_milliseconds();
+    if elapsed <= 0 {
+        return None;
+    }
+    let covered = f64::from(to.distance? - from.distance?);
+    #[allow(clippy::cast_precision_loss)]
+    Some(covered / (elapsed as f64 / 1000.0))
+}
+
+fn pause_between(start: &Model, end: &Model, min_duration: chrono::Duration) -> Option<Pause> {
+    let duration = end.time - start.time;
+    (duration >= min_duration).then(|| Pause {
+        start_time: start.time.into(),
+        end_time: end.time.into(),
+        duration_seconds: duration.num_seconds(),
+        latitude: start.latitude,
+        longitude: start.longitude,
+    })
+}
+
+/// Calculates the fraction of an activity covered by segments with a known track
+///
+/// Music segment durations are clipped to the activity window and summed, then divided
+/// by the activity's elapsed time. The ratio is clamped to [0, 1]; a zero-duration
+/// activity has no coverage.
+///
+/// # Arguments
+/// * `segments` - Segments of the activity, as built by `get_activity_music`
+/// * `activity_start` - Activity start time
+/// * `activity_end` - Activity start time plus elapsed time
+#[allow(clippy::cast_precision_loss)]
+pub fn calculate_music_coverage(
+    segments: &[Segment],
+    activity_start: DateTime<Utc>,
+    activity_end: DateTime<Utc>,
+) -> f64 {
+    let elapsed_seconds = (activity_end - activity_start).num_seconds();
+    if elapsed_seconds <= 0 {
+        return 0.0;
+    }
+
+    let music_seconds: i64 = segments
+        .iter()
+        .filter(|s| s.track.is_some())
+        .map(|s| {
+            let start = s.start_time.max(activity_start);
+            let end = s.end_time.min(activity_end);
+            (end - start).num_seconds().max(0)
+        })
+        .sum();
+
+    (music_seconds as f64 / elapsed_seconds as f64).clamp(0.0, 1.0)
+}
+
+/// Calculates the average tempo of the music played during an activity
+///
+/// Each music segment's track BPM is weighted by the segment's duration.
+/// Segments without a track or without a known tempo are ignored.
+///
+/// # Returns
+///
+/// The duration-weighted mean BPM, or `None` if no segment has a known tempo
+#[allow(clippy::cast_precision_loss)]
+pub fn activity_avg_bpm(segments: &[Segment]) -> Option<f64> {
+    let (weighted_bpm, total_seconds) = segments
+        .iter()
+        .filter_map(|s| {
+            let bpm = s.track.as_ref()?.bpm?;
+            let seconds = (s.end_time - s.start_time).num_seconds();
+            (seconds > 0).then_some((f64::from(bpm), seconds as f64))
+        })
+        .fold((0.0, 0.0), |(weighted, total), (bpm, seconds)| {
+            (weighted + bpm * seconds, total + seconds)
+        });
+
+    (total_seconds > 0.0).then(|| weighted_bpm / total_seconds)
+}
+
+/// Calculates the mean temperature of stream points
+///
+/// Only points with a temperature reading are counted, so sensors that drop out
+/// part-way through don't drag the mean towards zero.
+///
+/// # Returns
+///
+/// The mean temperature in °C, or `None` if no point has a reading
+pub fn average_temperature<'a>(points: impl IntoIterator<Item = &'a Model>) -> Option<f64> {
+    let (sum, count) = points
+        .into_iter()
+        .filter_map(|p| p.temperature)
+        .fold((0.0, 0_u32), |(sum, count), t| {
+            (sum + f64::from(t), count + 1)
+        });
+
+    (count > 0).then(|| sum / f64::from(count))
+}
+
+/// Default number of points in an elevation profile, plenty for a chart
+pub const DEFAULT_ELEVATION_PROFILE_POINTS: usize = 500;
+
+/// A point of an elevation profile
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ElevationPoint {
+    /// Meters from the activity start
+    pub distance: f64,
+    /// Altitude in meters
+    pub altitude: f64,
+}
+
+/// Projects an activity's streams onto a (distance, altitude) profile for charting
+///
+/// Points missing distance or altitude are skipped, as are points whose distance
+/// goes backwards, so the series is non-decreasing in distance. The profile is then
+/// reduced to at most `max_points`, keeping the climbs and descents that stand out.
+///
+/// # Errors
+///
+/// Returns an error if `max_points` is lower than 2
+pub fn elevation_profile(
+    streams: &[Model],
+    max_points: usize,
+) -> Result<Vec<ElevationPoint>, SimplificationError> {
+    let mut profile: Vec<(f64, f64)> = Vec::with_capacity(streams.len());
+    for point in streams {
+        let (Some(distance), Some(altitude)) = (point.distance, point.altitude) else {
+            continue;
+        };
+        let distance = f64::from(distance);
+        if profile.last().is_some_and(|&(last, _)| distance < last) {
+            continue;
+        }
+        profile.push((distance, f64::from(altitude)));
+    }
+
+    Ok(simplify_profile_to_count(&profile, max_points)?
+        .into_iter()
+        .map(|i| ElevationPoint {
+            distance: profile[i].0,
+            altitude: profile[i].1,
+        })
+        .collect())
+}
+
+/// Two of a user's activities whose time windows overlap
+///
+/// Usually the same session logged twice (e.g. a watch and a treadmill app).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ActivityOverlap {
+    /// Activity that started first
+    pub first_activity_id: Uuid,
+    pub second_activity_id: Uuid,
+    pub overlap_start: DateTime<Utc>,
+    pub overlap_end: DateTime<Utc>,
+    pub overlap_seconds: i64,
+}
+
+/// Lists the pairs of a user's activities that overlap in time
+///
+/// Listens are matched to activities by time, so overlapping activities already
+/// share the scrobbles of their common window: a GPS-less duplicate of a run
+/// shows the same music without copying anything.
+///
+/// # Errors
+///
+/// Returns an error if the database query fails
+pub async fn get_activity_overlaps(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+) -> Result<Vec<ActivityOverlap>, sea_orm::DbErr> {
+    let activities = get_activities_by_user(db, user_id).await?;
+    Ok(find_activity_overlaps(&activities))
+}
+
+/// Finds every pair of activities whose `[start, start + duration)` windows overlap
+///
+/// Activities touching end to start do not overlap. Activities without a positive
+/// duration are ignored. Pairs are ordered by the start of their first activity.
+#[must_use]
+pub fn find_activity_overlaps(activities: &[activity::Model]) -> Vec<ActivityOverlap> {
+    let mut windows: Vec<(Uuid, DateTime<Utc>, DateTime<Utc>)> = activities
+        .iter()
+        .filter_map(|activity| {
+            let duration =
+                activity_duration_seconds(activity.elapsed_time, activity.moving_time).ok()?;
+            let start = activity.start_time.with_timezone(&Utc);
+            Some((
+                activity.id,
+                start,
+                start + chrono::Duration::seconds(i64::from(duration)),
+            ))
+        })
+        .collect();
+    windows.sort_by_key(|&(_, start, _)| start);
+
+    let mut overlaps = Vec::new();
+    for (i, &(first_id, _, first_end)) in windows.iter().enumerate() {
+        // Sorted by start: later windows can only overlap while they start before this one ends
+        for &(second_id, second_start, second_end) in windows[i + 1..]
+            .iter()
+            .take_while(|&&(_, start, _)| start < first_end)
+        {
+            let overlap_end = first_end.min(second_end);
+            overlaps.push(ActivityOverlap {
+                first_activity_id: first_id,
+                second_activity_id: second_id,
+                overlap_start: second_start,
+                overlap_end,
+                overlap_seconds: (overlap_end - second_start).num_seconds(),
+            });
+        }
     }
+
+    overlaps
 }
 
 #[cfg(test)]
@@ -315,7 +1920,9 @@ fn calculate_stats(segments: &[Segment], original_points: usize) -> Simplificati
 mod tests {
     use super::*;
     use crate::database::activity_stream;
+    use crate::test_support::{make_activity, make_track, make_user};
     use chrono::{DateTime, Duration, Utc};
+    use sea_orm::{DbBackend, MockDatabase};
     use uuid::Uuid;
 
     // ==================== Test Fixtures ====================
@@ -354,6 +1961,8 @@ mod tests {
             velocity: Some(5.5),
             distance: Some(1000.0),
             temperature: Some(20.0),
+            grade: None,
+            moving: None,
         }
     }
 
@@ -378,12 +1987,7 @@ mod tests {
             artist_name: artist_name.to_string(),
             track_name: track_name.to_string(),
             album_name: Some("Test Album".to_string()),
-            artist_mbid: None,
-            track_mbid: None,
-            album_mbid: None,
-            lastfm_url: None,
-            created_at: Utc::now().into(),
-            updated_at: Utc::now().into(),
+            ..make_track()
         });
 
         (listen, track)
@@ -419,10 +2023,25 @@ mod tests {
             track,
             start_time,
             end_time,
+            avg_temperature: average_temperature(&points),
             points,
+            repeated: false,
+            after_gap: false,
         }
     }
 
+    /// Segment simplification skipping segments below the default minimum size
+    fn simplification(
+        tolerance: Option<f64>,
+        algorithm: SimplificationAlgorithm,
+    ) -> Option<SegmentSimplification> {
+        Some(SegmentSimplification {
+            tolerance,
+            algorithm,
+            min_points: crate::config::DEFAULT_SIMPLIFY_MIN_POINTS,
+        })
+    }
+
     // ==================== Group A: Pure Function Tests - calculate_stats() ====================
 
     #[test]
@@ -437,12 +2056,7 @@ mod tests {
                     artist_name: "Artist A".to_string(),
                     track_name: "Track A".to_string(),
                     album_name: Some("Album A".to_string()),
-                    artist_mbid: None,
-                    track_mbid: None,
-                    album_mbid: None,
-                    lastfm_url: None,
-                    created_at: Utc::now().into(),
-                    updated_at: Utc::now().into(),
+                    ..make_track()
                 }),
                 minutes_after(3),
                 minutes_after(6),
@@ -455,12 +2069,7 @@ mod tests {
                     artist_name: "Artist B".to_string(),
                     track_name: "Track B".to_string(),
                     album_name: Some("Album B".to_string()),
-                    artist_mbid: None,
-                    track_mbid: None,
-                    album_mbid: None,
-                    lastfm_url: None,
-                    created_at: Utc::now().into(),
-                    updated_at: Utc::now().into(),
+                    ..make_track()
                 }),
                 minutes_after(6),
                 minutes_after(10),
@@ -527,12 +2136,7 @@ mod tests {
             artist_name: "Artist".to_string(),
             track_name: "Track".to_string(),
             album_name: Some("Album".to_string()),
-            artist_mbid: None,
-            track_mbid: None,
-            album_mbid: None,
-            lastfm_url: None,
-            created_at: Utc::now().into(),
-            updated_at: Utc::now().into(),
+            ..make_track()
         });
 
         let segments = vec![
@@ -644,14 +2248,8 @@ mod tests {
         let activity_start = base_time();
         let activity_end = minutes_after(10);
 
-        let result = build_activity_segments(
-            &streams,
-            &listens,
-            activity_start,
-            activity_end,
-            false,
-            None,
-        );
+        let result =
+            build_activity_segments(&streams, &listens, activity_start, activity_end, None, None);
 
         assert!(result.is_ok(), "Should successfully build segments");
         let segments = result.unwrap();
@@ -706,14 +2304,8 @@ mod tests {
         let activity_start = base_time();
         let activity_end = minutes_after(10);
 
-        let result = build_activity_segments(
-            &streams,
-            &listens,
-            activity_start,
-            activity_end,
-            false,
-            None,
-        );
+        let result =
+            build_activity_segments(&streams, &listens, activity_start, activity_end, None, None);
 
         assert!(result.is_ok(), "Should successfully build segments");
         let segments = result.unwrap();
@@ -783,14 +2375,8 @@ mod tests {
         let activity_start = base_time();
         let activity_end = minutes_after(10);
 
-        let result = build_activity_segments(
-            &streams,
-            &listens,
-            activity_start,
-            activity_end,
-            false,
-            None,
-        );
+        let result =
+            build_activity_segments(&streams, &listens, activity_start, activity_end, None, None);
 
         assert!(result.is_ok(), "Should successfully build segments");
         let segments = result.unwrap();
@@ -843,14 +2429,8 @@ mod tests {
         let activity_start = base_time();
         let activity_end = minutes_after(10);
 
-        let result = build_activity_segments(
-            &streams,
-            &listens,
-            activity_start,
-            activity_end,
-            false,
-            None,
-        );
+        let result =
+            build_activity_segments(&streams, &listens, activity_start, activity_end, None, None);
 
         assert!(result.is_ok(), "Should successfully build segments");
         let segments = result.unwrap();
@@ -928,8 +2508,8 @@ mod tests {
             &listens,
             activity_start,
             activity_end,
-            true, // Enable simplification
-            Some(10.0),
+            simplification(Some(10.0), SimplificationAlgorithm::Rdp),
+            None,
         );
 
         assert!(
@@ -1012,8 +2592,8 @@ mod tests {
             &listens,
             activity_start,
             activity_end,
-            true,
-            Some(10.0),
+            simplification(Some(10.0), SimplificationAlgorithm::Rdp),
+            None,
         );
 
         assert!(
@@ -1052,14 +2632,8 @@ mod tests {
         let activity_start = base_time();
         let activity_end = minutes_after(10);
 
-        let result = build_activity_segments(
-            &streams,
-            &listens,
-            activity_start,
-            activity_end,
-            false,
-            None,
-        );
+        let result =
+            build_activity_segments(&streams, &listens, activity_start, activity_end, None, None);
 
         assert!(result.is_ok(), "Should successfully build segments");
         let segments = result.unwrap();
@@ -1100,14 +2674,8 @@ mod tests {
         let activity_start = base_time();
         let activity_end = minutes_after(10);
 
-        let result = build_activity_segments(
-            &streams,
-            &listens,
-            activity_start,
-            activity_end,
-            false,
-            None,
-        );
+        let result =
+            build_activity_segments(&streams, &listens, activity_start, activity_end, None, None);
 
         assert!(result.is_ok(), "Should successfully build segments");
         let segments = result.unwrap();
@@ -1147,14 +2715,8 @@ mod tests {
         let activity_start = base_time();
         let activity_end = minutes_after(10);
 
-        let result = build_activity_segments(
-            &streams,
-            &listens,
-            activity_start,
-            activity_end,
-            false,
-            None,
-        );
+        let result =
+            build_activity_segments(&streams, &listens, activity_start, activity_end, None, None);
 
         assert!(result.is_ok(), "Should handle empty streams without panic");
         let segments = result.unwrap();
@@ -1206,14 +2768,8 @@ mod tests {
         let activity_start = base_time();
         let activity_end = minutes_after(10);
 
-        let result = build_activity_segments(
-            &streams,
-            &listens,
-            activity_start,
-            activity_end,
-            false,
-            None,
-        );
+        let result =
+            build_activity_segments(&streams, &listens, activity_start, activity_end, None, None);
 
         assert!(result.is_ok(), "Should successfully build segments");
         let segments = result.unwrap();
@@ -1237,6 +2793,145 @@ mod tests {
         );
     }
 
+    /// Asserts the segments are contiguous and cover `[activity_start, activity_end]`
+    fn assert_tiles(
+        segments: &[Segment],
+        activity_start: DateTime<Utc>,
+        activity_end: DateTime<Utc>,
+    ) {
+        assert!(segments[0].start_time <= activity_start, "{segments:?}");
+        for pair in segments.windows(2) {
+            assert_eq!(pair[0].end_time, pair[1].start_time, "{segments:?}");
+        }
+        assert_eq!(segments.last().unwrap().end_time, activity_end);
+    }
+
+    #[test]
+    fn test_listens_on_the_activity_boundaries_cover_the_activity() {
+        let activity_id = Uuid::new_v4();
+        let user_id = Uuid::new_v4();
+        let streams: Vec<activity_stream::Model> = (0..21)
+            .map(|i| make_stream_point(activity_id, seconds_after(i * 30), Some(48.0), Some(2.0)))
+            .collect();
+        let activity_start = base_time();
+        let activity_end = minutes_after(10);
+
+        // Listens exactly at the start, one second before the end and exactly at the end
+        let listens = vec![
+            make_listen_with_track(user_id, Uuid::new_v4(), activity_start, "A", "Artist"),
+            make_listen_with_track(
+                user_id,
+                Uuid::new_v4(),
+                activity_end - Duration::seconds(1),
+                "B",
+                "Artist",
+            ),
+            make_listen_with_track(user_id, Uuid::new_v4(), activity_end, "C", "Artist"),
+        ];
+
+        let segments =
+            build_activity_segments(&streams, &listens, activity_start, activity_end, None, None)
+                .unwrap();
+
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].start_time, activity_start);
+        assert_tiles(&segments, activity_start, activity_end);
+    }
+
+    #[test]
+    fn test_off_by_one_segment_boundaries_are_repaired() {
+        let activity_start = base_time();
+        let activity_end = minutes_after(10);
+        // One second late start, one second gap, one second overlap, one second short end
+        let mut segments = vec![
+            make_segment(0, None, seconds_after(1), minutes_after(3), 2),
+            make_segment(
+                1,
+                None,
+                minutes_after(3) + Duration::seconds(1),
+                minutes_after(6),
+                2,
+            ),
+            make_segment(
+                2,
+                None,
+                minutes_after(6) - Duration::seconds(1),
+                activity_end - Duration::seconds(1),
+                2,
+            ),
+        ];
+
+        let corrections = ensure_full_coverage(&mut segments, activity_start, activity_end);
+
+        assert_eq!(corrections, 4);
+        assert_tiles(&segments, activity_start, activity_end);
+        assert_eq!(segments[0].start_time, activity_start);
+        assert_eq!(
+            segments[0].end_time,
+            minutes_after(3) + Duration::seconds(1)
+        );
+        assert_eq!(
+            segments[1].end_time,
+            minutes_after(6) - Duration::seconds(1)
+        );
+    }
+
+    #[test]
+    fn test_segment_starting_after_the_next_one_is_merged_into_it() {
+        let activity_start = base_time();
+        let activity_end = minutes_after(10);
+        // The second segment starts after the third: nothing is left for it to cover
+        let mut segments = vec![
+            make_segment(0, None, activity_start, minutes_after(3), 2),
+            make_segment(1, None, minutes_after(5), minutes_after(6), 2),
+            make_segment(2, None, minutes_after(4), activity_end, 2),
+        ];
+
+        let corrections = ensure_full_coverage(&mut segments, activity_start, activity_end);
+
+        // One dropped segment, one moved end
+        assert_eq!(corrections, 2);
+        assert_eq!(segments.len(), 2);
+        assert_tiles(&segments, activity_start, activity_end);
+        assert_eq!(segments[1].index, 1);
+        assert_eq!(segments[1].start_time, minutes_after(4));
+        assert!(segments
+            .iter()
+            .all(|segment| segment.end_time >= segment.start_time));
+        // The dropped segment's points are kept, in time order
+        assert_eq!(segments[1].points.len(), 4);
+        assert!(segments[1]
+            .points
+            .windows(2)
+            .all(|pair| pair[0].time <= pair[1].time));
+    }
+
+    #[test]
+    fn test_contiguous_segments_are_left_untouched() {
+        let activity_start = base_time();
+        let activity_end = minutes_after(10);
+        // A track already playing before the activity may start earlier
+        let mut segments = vec![
+            make_segment(
+                0,
+                None,
+                activity_start - Duration::seconds(30),
+                minutes_after(4),
+                2,
+            ),
+            make_segment(1, None, minutes_after(4), activity_end, 2),
+        ];
+
+        assert_eq!(
+            ensure_full_coverage(&mut segments, activity_start, activity_end),
+            0
+        );
+        assert_eq!(
+            segments[0].start_time,
+            activity_start - Duration::seconds(30)
+        );
+    }
+
     #[test]
     fn test_tolerance_none_uses_default() {
         let activity_id = Uuid::new_v4();
@@ -1264,8 +2959,8 @@ mod tests {
             &listens,
             activity_start,
             activity_end,
-            true, // simplify=true
-            None, // tolerance=None should use default
+            simplification(None, SimplificationAlgorithm::Rdp),
+            None,
         );
 
         assert!(result.is_ok(), "Should successfully build segments");
@@ -1298,14 +2993,8 @@ mod tests {
         let activity_start = base_time();
         let activity_end = minutes_after(10);
 
-        let result = build_activity_segments(
-            &streams,
-            &listens,
-            activity_start,
-            activity_end,
-            false,
-            None,
-        );
+        let result =
+            build_activity_segments(&streams, &listens, activity_start, activity_end, None, None);
 
         assert!(result.is_ok(), "Should successfully build segments");
         let segments = result.unwrap();
@@ -1320,6 +3009,60 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_indoor_activity_without_gps() {
+        let activity_id = Uuid::new_v4();
+        let user_id = Uuid::new_v4();
+
+        // Treadmill run: sensor data every 30s but no latlng stream at all
+        let streams: Vec<activity_stream::Model> = (0..20)
+            .map(|i| make_stream_point(activity_id, seconds_after(i * 30), None, None))
+            .collect();
+
+        let listens = vec![make_listen_with_track(
+            user_id,
+            Uuid::new_v4(),
+            minutes_after(3),
+            "Track A",
+            "Artist A",
+        )];
+
+        let activity_start = base_time();
+        let activity_end = minutes_after(10);
+
+        let result = build_activity_segments(
+            &streams,
+            &listens,
+            activity_start,
+            activity_end,
+            simplification(None, SimplificationAlgorithm::Rdp),
+            None,
+        );
+
+        assert!(
+            result.is_ok(),
+            "Indoor activity should not surface a SimplificationError"
+        );
+        let segments = result.unwrap();
+
+        assert_eq!(segments.len(), 2, "Should have pre-music + 1 music segment");
+        assert_eq!(segments[0].points.len(), 6, "Pre-music: 0-2:30 (6 points)");
+        assert_eq!(segments[1].points.len(), 14, "Music: 3:00-9:30 (14 points)");
+        assert!(
+            segments
+                .iter()
+                .flat_map(|s| &s.points)
+                .all(|p| p.latitude.is_none() && p.longitude.is_none()),
+            "Points should keep null coordinates"
+        );
+
+        let stats = calculate_stats(&segments, streams.len());
+        assert_eq!(stats.total_segments, 2);
+        assert_eq!(stats.segments_with_music, 1);
+        assert_eq!(stats.simplified_points, 20);
+        assert!((stats.reduction_ratio - 1.0).abs() < f32::EPSILON);
+    }
+
     // ==================== Group D: Statistics Validation Tests ====================
 
     #[test]
@@ -1381,14 +3124,8 @@ mod tests {
             "Should count only the 20 points within activity time range"
         );
 
-        let result = build_activity_segments(
-            &streams,
-            &listens,
-            activity_start,
-            activity_end,
-            false,
-            None,
-        );
+        let result =
+            build_activity_segments(&streams, &listens, activity_start, activity_end, None, None);
 
         assert!(result.is_ok(), "Should successfully build segments");
         let segments = result.unwrap();
@@ -1449,8 +3186,8 @@ mod tests {
                 &listens,
                 activity_start,
                 activity_end,
-                true, // simplify=true
-                Some(10.0),
+                simplification(Some(10.0), SimplificationAlgorithm::Rdp),
+                None,
             );
 
             assert!(result.is_ok(), "Should build segments for {pattern_name}");
@@ -1498,8 +3235,8 @@ mod tests {
                 &listens,
                 activity_start,
                 activity_end,
-                true,
-                Some(tolerance),
+                simplification(Some(tolerance), SimplificationAlgorithm::Rdp),
+                None,
             );
 
             assert!(
@@ -1531,14 +3268,8 @@ mod tests {
         let activity_start = base_time();
         let activity_end = minutes_after(10);
 
-        let result = build_activity_segments(
-            &streams,
-            &listens,
-            activity_start,
-            activity_end,
-            false, // simplify=false
-            None,
-        );
+        let result =
+            build_activity_segments(&streams, &listens, activity_start, activity_end, None, None);
 
         assert!(result.is_ok(), "Should successfully build segments");
         let segments = result.unwrap();
@@ -1555,4 +3286,2011 @@ mod tests {
             "Reduction ratio should be 1.0 when simplify=false"
         );
     }
+
+    /// Straight 100m-spaced route with ±1.5m lateral jitter
+    ///
+    /// Every jitter stays within a 10m RDP tolerance, while each triangle covers
+    /// far more than the 100m² VW threshold, so the algorithms disagree.
+    fn noisy_route(activity_id: Uuid) -> Vec<activity_stream::Model> {
+        (0..=20)
+            .map(|i| {
+                let jitter = if i % 2 == 0 { 0.00002 } else { -0.00002 };
+                make_stream_point(
+                    activity_id,
+                    seconds_after(i * 30),
+                    Some(48.0 + f64::from(i) * 0.0009),
+                    Some(2.0 + jitter),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_algorithm_selects_simplification_implementation() {
+        let streams = noisy_route(Uuid::new_v4());
+        let activity_end = seconds_after(20 * 30);
+
+        let point_count = |algorithm| {
+            build_activity_segments(
+                &streams,
+                &[],
+                base_time(),
+                activity_end,
+                simplification(Some(10.0), algorithm),
+                None,
+            )
+            .unwrap()[0]
+                .points
+                .len()
+        };
+
+        let rdp_points = point_count(SimplificationAlgorithm::Rdp);
+        let vw_points = point_count(SimplificationAlgorithm::Vw);
+
+        assert_eq!(
+            rdp_points,
+            crate::geo::simplify_gps_route(&streams, 10.0)
+                .unwrap()
+                .len()
+        );
+        assert_eq!(
+            vw_points,
+            crate::geo::simplify_gps_route_vw(&streams, 10.0)
+                .unwrap()
+                .len()
+        );
+        assert_eq!(rdp_points, 2, "RDP drops jitter below the tolerance");
+        assert_eq!(vw_points, streams.len(), "VW keeps wide jitter triangles");
+    }
+
+    #[test]
+    fn test_algorithm_defaults_to_rdp() {
+        assert_eq!(
+            SimplificationAlgorithm::default(),
+            SimplificationAlgorithm::Rdp
+        );
+    }
+
+    // ==================== Group E: Stream Downsampling ====================
+
+    #[test]
+    fn test_downsample_streams_respects_budget_and_order() {
+        let activity_id = Uuid::new_v4();
+        let streams: Vec<activity_stream::Model> = (0..500)
+            .map(|i| {
+                let offset = (i as f64) / 500.0;
+                let zigzag = if i % 2 == 0 { 0.0 } else { 0.0005 };
+                make_stream_point(
+                    activity_id,
+                    seconds_after(i),
+                    Some(48.0 + offset * 0.1),
+                    Some(2.0 + zigzag),
+                )
+            })
+            .collect();
+
+        let reduced = downsample_streams(streams.clone(), 50);
+
+        assert!(reduced.len() <= 50, "Should respect the point budget");
+        assert_eq!(reduced[0].time, streams[0].time, "Should keep first point");
+        assert_eq!(
+            reduced.last().unwrap().time,
+            streams.last().unwrap().time,
+            "Should keep last point"
+        );
+        assert!(
+            reduced.windows(2).all(|w| w[0].time < w[1].time),
+            "Points should stay in time order"
+        );
+    }
+
+    #[test]
+    fn test_downsample_streams_without_gps_uses_even_sampling() {
+        let activity_id = Uuid::new_v4();
+        let streams: Vec<activity_stream::Model> = (0..100)
+            .map(|i| make_stream_point(activity_id, seconds_after(i), None, None))
+            .collect();
+
+        let reduced = downsample_streams(streams.clone(), 10);
+
+        assert_eq!(reduced.len(), 10, "Should sample exactly the budget");
+        assert_eq!(reduced[0].time, streams[0].time);
+        assert_eq!(reduced[9].time, streams[99].time);
+        assert!(reduced.windows(2).all(|w| w[0].time < w[1].time));
+    }
+
+    #[test]
+    fn test_downsample_streams_keeps_points_without_gps() {
+        let activity_id = Uuid::new_v4();
+        // A tunnel in the middle of the run: 100 points without GPS between two GPS stretches
+        let streams: Vec<activity_stream::Model> = (0..400)
+            .map(|i| {
+                let gps = !(150..250).contains(&i);
+                let offset = f64::from(i) / 400.0;
+                let zigzag = if i % 2 == 0 { 0.0 } else { 0.0005 };
+                make_stream_point(
+                    activity_id,
+                    seconds_after(i64::from(i)),
+                    gps.then_some(48.0 + offset * 0.1),
+                    gps.then_some(2.0 + zigzag),
+                )
+            })
+            .collect();
+
+        let reduced = downsample_streams(streams, 40);
+
+        let without_gps = reduced.iter().filter(|p| p.latitude.is_none()).count();
+        assert!(reduced.len() <= 40, "Should respect the point budget");
+        assert_eq!(
+            without_gps, 10,
+            "Points without GPS should get their share of the budget"
+        );
+        assert!(reduced.windows(2).all(|w| w[0].time < w[1].time));
+    }
+
+    #[test]
+    fn test_downsample_streams_within_budget_unchanged() {
+        let activity_id = Uuid::new_v4();
+        let streams: Vec<activity_stream::Model> = (0..5)
+            .map(|i| make_stream_point(activity_id, seconds_after(i), Some(48.0), Some(2.0)))
+            .collect();
+
+        let reduced = downsample_streams(streams.clone(), 10);
+        assert_eq!(reduced, streams);
+    }
+
+    // ==================== Group F: Distance Buckets ====================
+
+    /// Helper to create a stream point at a given distance, without GPS
+    fn make_distance_point(
+        activity_id: Uuid,
+        time: DateTime<Utc>,
+        distance: f32,
+    ) -> activity_stream::Model {
+        activity_stream::Model {
+            distance: Some(distance),
+            ..make_stream_point(activity_id, time, None, None)
+        }
+    }
+
+    #[test]
+    fn test_distance_buckets_dominant_track() {
+        let activity_id = Uuid::new_v4();
+        let user_id = Uuid::new_v4();
+
+        // 20 points every 30s at 100m per point: 0m..1900m
+        let streams: Vec<activity_stream::Model> = (0..20)
+            .map(|i| make_distance_point(activity_id, seconds_after(i * 30), i as f32 * 100.0))
+            .collect();
+
+        let listens = vec![
+            make_listen_with_track(user_id, Uuid::new_v4(), base_time(), "Track A", "Artist"),
+            make_listen_with_track(
+                user_id,
+                Uuid::new_v4(),
+                minutes_after(4),
+                "Track B",
+                "Artist",
+            ),
+        ];
+
+        let buckets =
+            build_distance_buckets(&streams, &listens, base_time(), minutes_after(10), 1000.0);
+
+        assert_eq!(buckets.len(), 2, "1900m in 1000m buckets: 2 buckets");
+
+        // Bucket 0 (0-1000m): A for 0:00-4:00 (240s), B for 4:00-5:00 (60s)
+        assert_eq!(buckets[0].index, 0);
+        assert_eq!(buckets[0].track.as_ref().unwrap().track_name, "Track A");
+        assert!((buckets[0].dominant_seconds - 240.0).abs() < 0.001);
+        assert!((buckets[0].total_seconds - 300.0).abs() < 0.001);
+        assert!((buckets[0].start_distance - 0.0).abs() < 0.001);
+        assert!((buckets[0].end_distance - 1000.0).abs() < 0.001);
+
+        // Bucket 1 (1000-1900m): B only, last interval ends at 9:30 (270s)
+        assert_eq!(buckets[1].track.as_ref().unwrap().track_name, "Track B");
+        assert!((buckets[1].dominant_seconds - 270.0).abs() < 0.001);
+        assert!(
+            (buckets[1].end_distance - 1900.0).abs() < 0.001,
+            "Last bucket ends at total distance"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tiny_distance_bucket_rejected_before_loading() {
+        let db = MockDatabase::new(DbBackend::Postgres).into_connection();
+
+        for bucket_meters in [1e-9, 0.0, MIN_DISTANCE_BUCKET_METERS - 0.1, f64::NAN] {
+            let result = get_activity_music_by_distance(
+                &db,
+                Uuid::new_v4(),
+                Uuid::new_v4(),
+                bucket_meters,
+                ListenMatchOptions::default(),
+            )
+            .await;
+            assert!(result.is_err(), "bucket of {bucket_meters} m accepted");
+        }
+        assert!(is_valid_distance_bucket(MIN_DISTANCE_BUCKET_METERS));
+        assert!(db.into_transaction_log().is_empty());
+    }
+
+    #[test]
+    fn test_distance_buckets_repeated_track_accumulates() {
+        let activity_id = Uuid::new_v4();
+        let user_id = Uuid::new_v4();
+        let track_a = Uuid::new_v4();
+
+        // 10 points every 30s at 100m per point, single 1000m bucket
+        let streams: Vec<activity_stream::Model> = (0..10)
+            .map(|i| make_distance_point(activity_id, seconds_after(i * 30), i as f32 * 100.0))
+            .collect();
+
+        // A (60s), B (90s), A again (120s): A wins with 180s total
+        let listens = vec![
+            make_listen_with_track(user_id, track_a, base_time(), "Track A", "Artist"),
+            make_listen_with_track(
+                user_id,
+                Uuid::new_v4(),
+                seconds_after(60),
+                "Track B",
+                "Artist",
+            ),
+            make_listen_with_track(user_id, track_a, seconds_after(150), "Track A", "Artist"),
+        ];
+
+        let buckets =
+            build_distance_buckets(&streams, &listens, base_time(), minutes_after(10), 1000.0);
+
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].track.as_ref().unwrap().id, track_a);
+        assert!((buckets[0].dominant_seconds - 180.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_distance_buckets_without_music() {
+        let activity_id = Uuid::new_v4();
+
+        let streams: Vec<activity_stream::Model> = (0..10)
+            .map(|i| make_distance_point(activity_id, seconds_after(i * 30), i as f32 * 250.0))
+            .collect();
+
+        let buckets = build_distance_buckets(&streams, &[], base_time(), minutes_after(10), 1000.0);
+
+        assert_eq!(buckets.len(), 3, "2250m in 1000m buckets: 3 buckets");
+        assert!(buckets.iter().all(|b| b.track.is_none()));
+    }
+
+    #[test]
+    fn test_distance_buckets_require_distance_stream() {
+        let activity_id = Uuid::new_v4();
+
+        let streams: Vec<activity_stream::Model> = (0..10)
+            .map(|i| activity_stream::Model {
+                distance: None,
+                ..make_stream_point(activity_id, seconds_after(i * 30), None, None)
+            })
+            .collect();
+
+        let buckets = build_distance_buckets(&streams, &[], base_time(), minutes_after(10), 1000.0);
+
+        assert!(buckets.is_empty());
+    }
+
+    // ==================== Group G: Music Coverage ====================
+
+    #[test]
+    fn test_music_coverage_full() {
+        let (_, track) = make_listen_with_track(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            base_time(),
+            "Track A",
+            "Artist",
+        );
+        // Last segment runs past the activity end and is clipped
+        let segments = vec![
+            make_segment(0, track.clone(), base_time(), minutes_after(4), 5),
+            make_segment(1, track, minutes_after(4), minutes_after(12), 5),
+        ];
+
+        let coverage = calculate_music_coverage(&segments, base_time(), minutes_after(10));
+
+        assert!((coverage - 1.0).abs() < 0.001, "Coverage should be 1.0");
+    }
+
+    #[test]
+    fn test_music_coverage_half() {
+        let (_, track) = make_listen_with_track(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            base_time(),
+            "Track A",
+            "Artist",
+        );
+        let segments = vec![
+            make_segment(0, None, base_time(), minutes_after(5), 5),
+            make_segment(1, track, minutes_after(5), minutes_after(10), 5),
+        ];
+
+        let coverage = calculate_music_coverage(&segments, base_time(), minutes_after(10));
+
+        assert!((coverage - 0.5).abs() < 0.001, "Coverage should be 0.5");
+    }
+
+    #[test]
+    fn test_music_coverage_no_music() {
+        let segments = vec![make_segment(0, None, base_time(), minutes_after(10), 5)];
+
+        let coverage = calculate_music_coverage(&segments, base_time(), minutes_after(10));
+
+        assert!(coverage.abs() < f64::EPSILON, "Coverage should be 0.0");
+    }
+
+    #[test]
+    fn test_music_coverage_zero_duration_activity() {
+        let (_, track) = make_listen_with_track(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            base_time(),
+            "Track A",
+            "Artist",
+        );
+        let segments = vec![make_segment(0, track, base_time(), minutes_after(3), 5)];
+
+        let coverage = calculate_music_coverage(&segments, base_time(), base_time());
+
+        assert!(
+            coverage.abs() < f64::EPSILON,
+            "Zero-duration activity has no coverage"
+        );
+    }
+
+    // ==================== Group H: Average BPM ====================
+
+    fn track_with_bpm(bpm: Option<f32>) -> Option<track::Model> {
+        let (_, track) = make_listen_with_track(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            base_time(),
+            "Track",
+            "Artist",
+        );
+        track.map(|track| track::Model { bpm, ..track })
+    }
+
+    #[test]
+    fn test_avg_bpm_weighted_by_duration() {
+        // 120 BPM for 3 minutes, 180 BPM for 1 minute: (120*180 + 180*60) / 240 = 135
+        let segments = vec![
+            make_segment(
+                0,
+                track_with_bpm(Some(120.0)),
+                base_time(),
+                minutes_after(3),
+                5,
+            ),
+            make_segment(
+                1,
+                track_with_bpm(Some(180.0)),
+                minutes_after(3),
+                minutes_after(4),
+                5,
+            ),
+        ];
+
+        let avg_bpm = activity_avg_bpm(&segments).unwrap();
+
+        assert!(
+            (avg_bpm - 135.0).abs() < 0.001,
+            "Expected 135, got {avg_bpm}"
+        );
+    }
+
+    #[test]
+    fn test_avg_bpm_ignores_segments_without_tempo() {
+        let segments = vec![
+            make_segment(0, None, base_time(), minutes_after(2), 5),
+            make_segment(
+                1,
+                track_with_bpm(Some(160.0)),
+                minutes_after(2),
+                minutes_after(5),
+                5,
+            ),
+            make_segment(
+                2,
+                track_with_bpm(None),
+                minutes_after(5),
+                minutes_after(10),
+                5,
+            ),
+        ];
+
+        let avg_bpm = activity_avg_bpm(&segments).unwrap();
+
+        assert!((avg_bpm - 160.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_avg_bpm_none_without_known_tempo() {
+        let segments = vec![
+            make_segment(0, None, base_time(), minutes_after(2), 5),
+            make_segment(
+                1,
+                track_with_bpm(None),
+                minutes_after(2),
+                minutes_after(5),
+                5,
+            ),
+        ];
+
+        assert_eq!(activity_avg_bpm(&segments), None);
+    }
+
+    // ==================== Group I: Track At Timestamp ====================
+
+    /// Two listens at 2 and 5 minutes of a 10 minute activity, nothing before 2 minutes
+    fn listens_for_track_at() -> Vec<(listen::Model, Option<track::Model>)> {
+        let user_id = Uuid::new_v4();
+        vec![
+            make_listen_with_track(user_id, Uuid::new_v4(), minutes_after(2), "First", "Artist"),
+            make_listen_with_track(
+                user_id,
+                Uuid::new_v4(),
+                minutes_after(5),
+                "Second",
+                "Artist",
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_track_at_inside_music_segment() {
+        let listens = listens_for_track_at();
+
+        let track_name = |at| {
+            track_at(&listens, base_time(), minutes_after(10), at)
+                .unwrap()
+                .map(|t| t.track_name.clone())
+        };
+
+        assert_eq!(track_name(minutes_after(3)).as_deref(), Some("First"));
+        // Segment boundaries match build_activity_segments: the next track starts at its played_at
+        assert_eq!(track_name(minutes_after(5)).as_deref(), Some("Second"));
+        // The last track plays until the end of the activity
+        assert_eq!(track_name(minutes_after(10)).as_deref(), Some("Second"));
+    }
+
+    #[test]
+    fn test_track_at_in_gap_before_music() {
+        let listens = listens_for_track_at();
+
+        let result = track_at(&listens, base_time(), minutes_after(10), minutes_after(1));
+
+        assert!(result.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_track_at_out_of_range_is_rejected() {
+        let listens = listens_for_track_at();
+
+        for at in [minutes_after(-1), minutes_after(11)] {
+            let result = track_at(&listens, base_time(), minutes_after(10), at);
+            assert_eq!(
+                result.unwrap_err().to_string(),
+                "Timestamp is outside the activity window"
+            );
+        }
+    }
+
+    /// Readings at 0s, 30s and 60s, plus a point without distance in between
+    fn distance_points() -> Vec<activity_stream::Model> {
+        let activity_id = Uuid::new_v4();
+        vec![
+            make_distance_point(activity_id, seconds_after(0), 0.0),
+            make_distance_point(activity_id, seconds_after(30), 100.0),
+            activity_stream::Model {
+                distance: None,
+                ..make_stream_point(activity_id, seconds_after(45), None, None)
+            },
+            make_distance_point(activity_id, seconds_after(60), 250.0),
+        ]
+    }
+
+    #[test]
+    fn test_distance_at_an_exact_point() {
+        let distance = distance_at(&distance_points(), seconds_after(30)).unwrap();
+
+        assert!((distance - 100.0).abs() < 0.001, "{distance}");
+    }
+
+    #[test]
+    fn test_distance_between_two_points_is_interpolated() {
+        let points = distance_points();
+
+        let between = distance_at(&points, seconds_after(15)).unwrap();
+        // The point without distance is skipped: 45s lies halfway between 30s and 60s
+        let skipping = distance_at(&points, seconds_after(45)).unwrap();
+
+        assert!((between - 50.0).abs() < 0.001, "{between}");
+        assert!((skipping - 175.0).abs() < 0.001, "{skipping}");
+    }
+
+    #[test]
+    fn test_distance_outside_the_stream_is_clamped() {
+        let points = distance_points();
+
+        let before = distance_at(&points, seconds_after(-10)).unwrap();
+        let after = distance_at(&points, seconds_after(90)).unwrap();
+
+        assert!(before.abs() < 0.001, "{before}");
+        assert!((after - 250.0).abs() < 0.001, "{after}");
+    }
+
+    #[test]
+    fn test_distance_at_without_distance_stream() {
+        let without_distance = activity_stream::Model {
+            distance: None,
+            ..make_stream_point(Uuid::new_v4(), base_time(), Some(48.0), Some(2.0))
+        };
+
+        assert!(distance_at(&[], base_time()).is_none());
+        assert!(distance_at(&[without_distance], base_time()).is_none());
+    }
+
+    // ==================== Group J: Average Temperature ====================
+
+    #[test]
+    fn test_segment_avg_temperature_from_points_with_readings() {
+        let activity_id = Uuid::new_v4();
+        let streams: Vec<activity_stream::Model> = [Some(18.0), None, Some(22.0), Some(23.0)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, temperature)| activity_stream::Model {
+                temperature,
+                ..make_stream_point(
+                    activity_id,
+                    seconds_after(i as i64 * 10),
+                    Some(48.0 + i as f64 * 0.001),
+                    Some(2.0),
+                )
+            })
+            .collect();
+
+        let segments = build_activity_segments(
+            &streams,
+            &[],
+            base_time(),
+            seconds_after(30),
+            Some(SegmentSimplification {
+                tolerance: Some(1000.0),
+                algorithm: SimplificationAlgorithm::Rdp,
+                min_points: 3,
+            }),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(segments.len(), 1);
+        assert!(
+            segments[0].points.len() < streams.len(),
+            "Route should be simplified"
+        );
+        // Computed over all points with a reading, not just the simplified ones
+        assert_eq!(segments[0].avg_temperature, Some(21.0));
+        assert_eq!(average_temperature(&streams), Some(21.0));
+    }
+
+    #[test]
+    fn test_segment_avg_temperature_without_readings_is_none() {
+        let activity_id = Uuid::new_v4();
+        let streams: Vec<activity_stream::Model> = (0..3)
+            .map(|i| activity_stream::Model {
+                temperature: None,
+                ..make_stream_point(activity_id, seconds_after(i * 10), Some(48.0), Some(2.0))
+            })
+            .collect();
+
+        let segments =
+            build_activity_segments(&streams, &[], base_time(), seconds_after(20), None, None)
+                .unwrap();
+
+        assert_eq!(segments[0].avg_temperature, None);
+        assert_eq!(average_temperature(&streams), None);
+    }
+
+    // ==================== Group K: Stream Statistics ====================
+
+    #[test]
+    fn test_stream_stats_over_mixed_channels() {
+        let activity_id = Uuid::new_v4();
+        let streams = vec![
+            activity_stream::Model {
+                heart_rate: Some(140),
+                watts: None,
+                cadence: Some(80),
+                temperature: None,
+                ..make_stream_point(activity_id, seconds_after(0), Some(48.0), Some(2.0))
+            },
+            activity_stream::Model {
+                heart_rate: None,
+                watts: None,
+                cadence: Some(90),
+                temperature: None,
+                ..make_stream_point(activity_id, seconds_after(1), Some(48.0), Some(2.0))
+            },
+            activity_stream::Model {
+                heart_rate: Some(160),
+                watts: None,
+                cadence: Some(85),
+                temperature: None,
+                ..make_stream_point(activity_id, seconds_after(2), Some(48.0), Some(2.0))
+            },
+        ];
+
+        let stats = calculate_stream_stats(&streams);
+
+        assert_eq!(
+            stats.heart_rate,
+            ChannelStats {
+                min: Some(140.0),
+                max: Some(160.0),
+                avg: Some(150.0),
+                count: 2,
+            },
+            "Points without a heart rate are skipped"
+        );
+        assert_eq!(stats.cadence.min, Some(80.0));
+        assert_eq!(stats.cadence.max, Some(90.0));
+        assert_eq!(stats.cadence.avg, Some(85.0));
+        assert_eq!(stats.cadence.count, 3);
+        // Every fixture point has the same altitude and velocity
+        assert_eq!(stats.altitude.avg, Some(100.0));
+        assert_eq!(stats.velocity.count, 3);
+    }
+
+    #[test]
+    fn test_stream_stats_all_null_channel_reports_nulls() {
+        let activity_id = Uuid::new_v4();
+        let streams: Vec<activity_stream::Model> = (0..3)
+            .map(|i| activity_stream::Model {
+                watts: None,
+                temperature: None,
+                ..make_stream_point(activity_id, seconds_after(i), None, None)
+            })
+            .collect();
+
+        let stats = calculate_stream_stats(&streams);
+
+        assert_eq!(stats.watts, ChannelStats::default());
+        assert_eq!(stats.temperature.min, None);
+        assert_eq!(stats.temperature.max, None);
+        assert_eq!(stats.temperature.avg, None);
+        assert_eq!(stats.temperature.count, 0);
+    }
+
+    #[test]
+    fn test_stream_stats_of_empty_streams() {
+        assert_eq!(calculate_stream_stats(&[]), StreamStats::default());
+    }
+
+    // ==================== Group L: Listen Padding ====================
+
+    #[test]
+    fn test_padding_within_bounds() {
+        let padding = ListenPadding::new(0, MAX_LISTEN_PADDING_SECONDS).unwrap();
+        assert_eq!(padding.after_seconds, MAX_LISTEN_PADDING_SECONDS);
+
+        assert!(ListenPadding::new(MAX_LISTEN_PADDING_SECONDS + 1, 0).is_err());
+        assert!(ListenPadding::new(0, u32::MAX).is_err());
+    }
+
+    #[test]
+    fn test_stored_padding_is_the_default() {
+        let padding = ListenPadding::from_user(&user::Model {
+            listen_padding_before_seconds: 120,
+            listen_padding_after_seconds: 30,
+            ..make_user()
+        })
+        .with_overrides(None, None)
+        .unwrap();
+
+        assert_eq!(
+            padding,
+            ListenPadding {
+                before_seconds: 120,
+                after_seconds: 30
+            }
+        );
+    }
+
+    #[test]
+    fn test_explicit_padding_overrides_stored_default() {
+        let stored = ListenPadding::from_user(&user::Model {
+            listen_padding_before_seconds: 120,
+            listen_padding_after_seconds: 30,
+            ..make_user()
+        });
+
+        let padding = stored.with_overrides(Some(0), None).unwrap();
+        assert_eq!(padding.before_seconds, 0);
+        assert_eq!(padding.after_seconds, 30);
+
+        assert!(stored.with_overrides(None, Some(3600)).is_err());
+    }
+
+    #[test]
+    fn test_out_of_range_stored_padding_is_clamped() {
+        let padding = ListenPadding::from_user(&user::Model {
+            listen_padding_before_seconds: -5,
+            listen_padding_after_seconds: 100_000,
+            ..make_user()
+        });
+
+        assert_eq!(padding.before_seconds, 0);
+        assert_eq!(padding.after_seconds, MAX_LISTEN_PADDING_SECONDS);
+    }
+
+    #[test]
+    fn test_only_last_listen_before_start_is_kept() {
+        let user_id = Uuid::new_v4();
+        let listens = vec![
+            make_listen_with_track(user_id, Uuid::new_v4(), minutes_after(-8), "Old", "A"),
+            make_listen_with_track(user_id, Uuid::new_v4(), minutes_after(-3), "Intro", "A"),
+            make_listen_with_track(user_id, Uuid::new_v4(), minutes_after(2), "Main", "A"),
+        ];
+
+        let kept = skip_listens_ended_before(listens, base_time());
+
+        let names: Vec<&str> = kept
+            .iter()
+            .filter_map(|(_, track)| track.as_ref().map(|t| t.track_name.as_str()))
+            .collect();
+        assert_eq!(names, vec!["Intro", "Main"]);
+    }
+
+    #[test]
+    fn test_listens_without_leading_padding_are_unchanged() {
+        let user_id = Uuid::new_v4();
+        let listens = vec![
+            make_listen_with_track(user_id, Uuid::new_v4(), minutes_after(0), "First", "A"),
+            make_listen_with_track(user_id, Uuid::new_v4(), minutes_after(4), "Second", "A"),
+        ];
+
+        assert_eq!(skip_listens_ended_before(listens, base_time()).len(), 2);
+    }
+
+    // ==================== Group M: Pause Detection ====================
+
+    /// One point per second at 3 m/s, standing still between `stop_from` and `stop_to`
+    fn make_run_with_stop(len: i64, stop_from: i64, stop_to: i64) -> Vec<activity_stream::Model> {
+        let activity_id = Uuid::new_v4();
+        let mut distance = 0.0_f32;
+        (0..len)
+            .map(|i| {
+                if i > 0 && !(stop_from < i && i <= stop_to) {
+                    distance += 3.0;
+                }
+                activity_stream::Model {
+                    distance: Some(distance),
+                    ..make_stream_point(
+                        activity_id,
+                        seconds_after(i),
+                        Some(48.0 + i as f64 * 0.0001),
+                        Some(2.0),
+                    )
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_one_clear_stop() {
+        let streams = make_run_with_stop(120, 40, 70);
+
+        let pauses = detect_pauses(&streams, Duration::seconds(15), 0.5);
+
+        assert_eq!(pauses.len(), 1);
+        assert_eq!(pauses[0].start_time, seconds_after(40));
+        assert_eq!(pauses[0].end_time, seconds_after(70));
+        assert_eq!(pauses[0].duration_seconds, 30);
+        assert_eq!(pauses[0].latitude, Some(48.0 + 40.0 * 0.0001));
+        assert_eq!(pauses[0].longitude, Some(2.0));
+    }
+
+    #[test]
+    fn test_short_stop_is_not_a_pause() {
+        let streams = make_run_with_stop(120, 40, 45);
+
+        assert!(detect_pauses(&streams, Duration::seconds(15), 0.5).is_empty());
+    }
+
+    #[test]
+    fn test_stop_at_activity_end_is_reported() {
+        let streams = make_run_with_stop(60, 30, 59);
+
+        let pauses = detect_pauses(&streams, Duration::seconds(15), 0.5);
+
+        assert_eq!(pauses.len(), 1);
+        assert_eq!(pauses[0].end_time, seconds_after(59));
+    }
+
+    #[test]
+    fn test_auto_pause_gap_counts_as_stop() {
+        let activity_id = Uuid::new_v4();
+        // Recording resumes five minutes later, two meters further
+        let streams = vec![
+            activity_stream::Model {
+                distance: Some(500.0),
+                ..make_stream_point(activity_id, seconds_after(0), None, None)
+            },
+            activity_stream::Model {
+                distance: Some(502.0),
+                ..make_stream_point(activity_id, seconds_after(300), None, None)
+            },
+        ];
+
+        let pauses = detect_pauses(&streams, Duration::seconds(15), 0.5);
+
+        assert_eq!(pauses.len(), 1);
+        assert_eq!(pauses[0].duration_seconds, 300);
+        assert_eq!(pauses[0].latitude, None);
+    }
+
+    #[test]
+    fn test_no_pauses_without_distance_stream() {
+        let activity_id = Uuid::new_v4();
+        let streams: Vec<activity_stream::Model> = (0..60)
+            .map(|i| activity_stream::Model {
+                distance: None,
+                ..make_stream_point(activity_id, seconds_after(i), None, None)
+            })
+            .collect();
+
+        assert!(detect_pauses(&streams, Duration::seconds(15), 0.5).is_empty());
+    }
+
+    // ==================== Group N: Minimum Segment Duration ====================
+
+    fn listens_with_skip() -> Vec<(listen::Model, Option<track::Model>)> {
+        let user_id = Uuid::new_v4();
+        vec![
+            make_listen_with_track(user_id, Uuid::new_v4(), base_time(), "Track A", "Artist"),
+            // Skipped after 3 seconds
+            make_listen_with_track(
+                user_id,
+                Uuid::new_v4(),
+                minutes_after(3),
+                "Skipped",
+                "Artist",
+            ),
+            make_listen_with_track(
+                user_id,
+                Uuid::new_v4(),
+                minutes_after(3) + Duration::seconds(3),
+                "Track C",
+                "Artist",
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_short_skip_segment_is_merged_into_previous() {
+        let activity_id = Uuid::new_v4();
+        let streams: Vec<activity_stream::Model> = (0..41)
+            .map(|i| make_stream_point(activity_id, seconds_after(i * 15), Some(48.0), Some(2.0)))
+            .collect();
+
+        let listens = drop_skipped_listens(listens_with_skip(), minutes_after(10), 10);
+        let segments = build_activity_segments(
+            &streams,
+            &listens,
+            base_time(),
+            minutes_after(10),
+            None,
+            None,
+        )
+        .unwrap();
+
+        let names: Vec<&str> = segments
+            .iter()
+            .filter_map(|s| s.track.as_ref().map(|t| t.track_name.as_str()))
+            .collect();
+        assert_eq!(names, vec!["Track A", "Track C"]);
+        assert_eq!(
+            segments[0].end_time,
+            minutes_after(3) + Duration::seconds(3)
+        );
+        // The point at 3:00 belonged to the skipped track and now joins Track A
+        assert!(segments[0]
+            .points
+            .iter()
+            .any(|p| p.time == minutes_after(3)));
+        for (i, segment) in segments.iter().enumerate() {
+            assert_eq!(segment.index, i, "Indices must stay sequential");
+        }
+    }
+
+    #[test]
+    fn test_zero_min_segment_keeps_every_listen() {
+        let listens = drop_skipped_listens(listens_with_skip(), minutes_after(10), 0);
+
+        assert_eq!(listens.len(), 3);
+    }
+
+    #[test]
+    fn test_short_last_listen_is_merged_until_activity_end() {
+        let user_id = Uuid::new_v4();
+        let listens = vec![
+            make_listen_with_track(user_id, Uuid::new_v4(), base_time(), "Track A", "Artist"),
+            make_listen_with_track(
+                user_id,
+                Uuid::new_v4(),
+                minutes_after(10) - Duration::seconds(4),
+                "Cut off",
+                "Artist",
+            ),
+        ];
+
+        let listens = drop_skipped_listens(listens, minutes_after(10), 5);
+
+        assert_eq!(listens.len(), 1);
+    }
+
+    // ==================== Group O: Listen Time Offset ====================
+
+    /// Listens matched to a 10-minute activity, on the GPS clock
+    fn matched_listen_names(
+        listens: Vec<(listen::Model, Option<track::Model>)>,
+        time_offset_seconds: i32,
+    ) -> Vec<String> {
+        let window = listen_window(
+            base_time().into(),
+            600,
+            ListenPadding::default(),
+            time_offset_seconds,
+        );
+        let in_window: Vec<_> = listens
+            .into_iter()
+            .filter(|(listen, _)| {
+                listen.played_at >= window.wide_start && listen.played_at <= window.matched_end
+            })
+            .collect();
+
+        apply_time_offset(in_window, time_offset_seconds)
+            .into_iter()
+            .filter_map(|(listen, track)| {
+                track.map(|t| format!("{}@{}", t.track_name, listen.played_at.timestamp()))
+            })
+            .collect()
+    }
+
+    fn listens_around_activity() -> Vec<(listen::Model, Option<track::Model>)> {
+        let user_id = Uuid::new_v4();
+        vec![
+            // Device clock runs 2 minutes behind: played 1 minute into the activity
+            make_listen_with_track(user_id, Uuid::new_v4(), seconds_after(-60), "Early", "A"),
+            make_listen_with_track(user_id, Uuid::new_v4(), minutes_after(5), "Middle", "A"),
+            // Played 1 minute after the activity ended
+            make_listen_with_track(user_id, Uuid::new_v4(), seconds_after(540), "Late", "A"),
+        ]
+    }
+
+    #[test]
+    fn test_zero_offset_matches_listens_inside_activity() {
+        let names = matched_listen_names(listens_around_activity(), 0);
+
+        let middle = minutes_after(5).timestamp();
+        let late = seconds_after(540).timestamp();
+        assert_eq!(
+            names,
+            vec![format!("Middle@{middle}"), format!("Late@{late}")]
+        );
+    }
+
+    #[test]
+    fn test_positive_offset_shifts_matched_listens() {
+        let names = matched_listen_names(listens_around_activity(), 120);
+
+        // "Early" now starts 1 minute in, "Late" is pushed past the end
+        let early = seconds_after(60).timestamp();
+        let middle = seconds_after(420).timestamp();
+        assert_eq!(
+            names,
+            vec![format!("Early@{early}"), format!("Middle@{middle}")]
+        );
+    }
+
+    #[test]
+    fn test_offset_moves_whole_query_window() {
+        let start: DateTime<FixedOffset> = base_time().into();
+        let padding = ListenPadding::new(60, 30).unwrap();
+
+        let window = listen_window(start, 600, padding, 120);
+
+        assert_eq!(window.wide_start, start - Duration::seconds(180));
+        assert_eq!(window.wide_end, start + Duration::seconds(510));
+        assert_eq!(window.matched_end, start + Duration::seconds(480));
+    }
+
+    #[test]
+    fn test_time_offset_bounds() {
+        assert!(validate_time_offset(MAX_TIME_OFFSET_SECONDS).is_ok());
+        assert!(validate_time_offset(-MAX_TIME_OFFSET_SECONDS).is_ok());
+        assert!(validate_time_offset(MAX_TIME_OFFSET_SECONDS + 1).is_err());
+        assert!(validate_time_offset(i32::MIN).is_err());
+    }
+
+    // ==================== Group P: Auto Tolerance ====================
+
+    /// Closed loop of `points` samples with wiggles at several scales, `radius` meters wide
+    ///
+    /// Loops with different radii are scaled copies of each other.
+    fn make_wiggly_loop(radius: f64, points: usize) -> Vec<Model> {
+        let activity_id = Uuid::new_v4();
+        let meters_per_degree_lng = 111_320.0 * 48.85_f64.to_radians().cos();
+        (0..=points)
+            .map(|i| {
+                #[allow(clippy::cast_precision_loss)]
+                let angle = std::f64::consts::TAU * i as f64 / points as f64;
+                let r = radius
+                    * (1.0
+                        + 0.25 * (5.0 * angle).sin()
+                        + 0.08 * (17.0 * angle).sin()
+                        + 0.03 * (41.0 * angle).sin()
+                        + 0.01 * (97.0 * angle).sin()
+                        + 0.004 * (233.0 * angle).sin());
+                make_stream_point(
+                    activity_id,
+                    seconds_after(i64::try_from(i).unwrap()),
+                    Some(48.85 + r * angle.sin() / 111_320.0),
+                    Some(2.35 + r * angle.cos() / meters_per_degree_lng),
+                )
+            })
+            .collect()
+    }
+
+    fn simplified_count(points: &[Model], tolerance: SimplificationTolerance) -> usize {
+        let tolerance = tolerance.meters(route_length_meters(points));
+        simplify_segment_points(
+            points.to_vec(),
+            simplification(Some(tolerance), SimplificationAlgorithm::Rdp),
+        )
+        .unwrap()
+        .len()
+    }
+
+    #[test]
+    fn test_auto_tolerance_keeps_short_and_long_routes_near_budget() {
+        // ~2km park loop and ~50km ultra, both sampled every ~5m
+        let park_loop = make_wiggly_loop(175.0, 400);
+        let ultra = make_wiggly_loop(4375.0, 10_000);
+
+        let park_auto = simplified_count(&park_loop, SimplificationTolerance::Auto);
+        let ultra_auto = simplified_count(&ultra, SimplificationTolerance::Auto);
+        let park_fixed = simplified_count(&park_loop, SimplificationTolerance::Meters(10.0));
+        let ultra_fixed = simplified_count(&ultra, SimplificationTolerance::Meters(10.0));
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let budget = AUTO_TOLERANCE_TARGET_POINTS as usize;
+        for count in [park_auto, ultra_auto] {
+            assert!(
+                (budget / 10..=budget).contains(&count),
+                "Auto tolerance kept {count} points, budget {budget}"
+            );
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let ratio = ultra_auto as f64 / park_auto as f64;
+        assert!(
+            (0.8..1.25).contains(&ratio),
+            "Auto tolerance kept {park_auto} vs {ultra_auto} points"
+        );
+        assert!(
+            ultra_fixed > 5 * park_fixed,
+            "A fixed tolerance keeps far more points on the long route: {park_fixed} vs {ultra_fixed}"
+        );
+    }
+
+    #[test]
+    fn test_auto_tolerance_is_clamped() {
+        assert!((auto_tolerance_meters(0.0) - AUTO_TOLERANCE_MIN_METERS).abs() < f64::EPSILON);
+        assert!((auto_tolerance_meters(10_000.0) - 20.0).abs() < 1e-9);
+        assert!(
+            (auto_tolerance_meters(1_000_000.0) - AUTO_TOLERANCE_MAX_METERS).abs() < f64::EPSILON
+        );
+    }
+
+    #[test]
+    fn test_tolerance_parses_meters_or_auto() {
+        assert_eq!(
+            SimplificationTolerance::try_from("15.5".to_string()),
+            Ok(SimplificationTolerance::Meters(15.5))
+        );
+        assert_eq!(
+            SimplificationTolerance::try_from("AUTO".to_string()),
+            Ok(SimplificationTolerance::Auto)
+        );
+        assert!(SimplificationTolerance::try_from("fine".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_tolerance_in_feet_matches_the_same_length_in_meters() {
+        let streams = noisy_route(Uuid::new_v4());
+        let count = |tolerance: SimplificationTolerance| {
+            simplify_segment_points(
+                streams.clone(),
+                simplification(Some(tolerance.meters(0.0)), SimplificationAlgorithm::Rdp),
+            )
+            .unwrap()
+            .len()
+        };
+
+        let feet = SimplificationTolerance::Meters(33.0).in_unit(LengthUnit::Feet);
+
+        assert_eq!(count(feet), count(SimplificationTolerance::Meters(10.0)));
+        assert!(count(feet) < streams.len());
+        assert_eq!(
+            SimplificationTolerance::Auto.in_unit(LengthUnit::Feet),
+            SimplificationTolerance::Auto
+        );
+    }
+
+    // ==================== Group Q: Track Play Counts ====================
+
+    /// Activity where Track A plays, then Track B, then Track A again
+    fn build_segments_with_repeat(track_a: Uuid) -> Vec<Segment> {
+        let activity_id = Uuid::new_v4();
+        let user_id = Uuid::new_v4();
+        let streams: Vec<activity_stream::Model> = (0..10)
+            .map(|i| {
+                make_stream_point(
+                    activity_id,
+                    minutes_after(i),
+                    Some(48.0 + i as f64 * 0.001),
+                    Some(2.0),
+                )
+            })
+            .collect();
+        let listens = vec![
+            make_listen_with_track(user_id, track_a, minutes_after(1), "Track A", "Artist"),
+            make_listen_with_track(
+                user_id,
+                Uuid::new_v4(),
+                minutes_after(4),
+                "Track B",
+                "Artist",
+            ),
+            make_listen_with_track(user_id, track_a, minutes_after(7), "Track A", "Artist"),
+        ];
+
+        build_activity_segments(
+            &streams,
+            &listens,
+            base_time(),
+            minutes_after(10),
+            None,
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_segments_of_a_track_playing_twice_are_repeated() {
+        let track_a = Uuid::new_v4();
+        let segments = build_segments_with_repeat(track_a);
+
+        let flags: Vec<(Option<&str>, bool)> = segments
+            .iter()
+            .map(|s| (s.track.as_ref().map(|t| t.track_name.as_str()), s.repeated))
+            .collect();
+        assert_eq!(
+            flags,
+            vec![
+                (None, false),
+                (Some("Track A"), true),
+                (Some("Track B"), false),
+                (Some("Track A"), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_play_counts_per_distinct_track() {
+        let track_a = Uuid::new_v4();
+        let segments = build_segments_with_repeat(track_a);
+
+        let stats = calculate_stats(&segments, 10);
+
+        let counts: Vec<(&str, usize)> = stats
+            .play_counts
+            .iter()
+            .map(|c| (c.track.track_name.as_str(), c.play_count))
+            .collect();
+        assert_eq!(counts, vec![("Track A", 2), ("Track B", 1)]);
+        assert_eq!(stats.play_counts[0].track.id, track_a);
+    }
+
+    // ==================== Group R: Recompute Without Fetching ====================
+
+    /// A 10-minute run of the user started at `base_time`
+    fn run_at_base_time(user_id: Uuid) -> crate::database::activity::Model {
+        crate::database::activity::Model {
+            user_id,
+            start_time: base_time().into(),
+            moving_time: 600,
+            elapsed_time: 600,
+            ..make_activity()
+        }
+    }
+
+    fn no_fetch() -> ListenMatchOptions {
+        ListenMatchOptions {
+            no_fetch: true,
+            ..ListenMatchOptions::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_no_fetch_uses_stored_listens_without_lastfm() {
+        let user_id = Uuid::new_v4();
+        let activity = run_at_base_time(user_id);
+        let (listen, track) =
+            make_listen_with_track(user_id, Uuid::new_v4(), minutes_after(1), "Song", "Artist");
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![activity.clone()]])
+            .append_query_results([vec![listen.clone()]])
+            .append_query_results([vec![(listen, track.unwrap())]])
+            .into_connection();
+
+        let loaded = load_activity_listens(&db, user_id, activity.id, no_fetch())
+            .await
+            .unwrap();
+
+        assert_eq!(loaded.listens.len(), 1);
+        // Activity, stored listens, listens with tracks: no user lookup for a Last.fm sync
+        let log = db.into_transaction_log();
+        assert_eq!(log.len(), 3, "{log:?}");
+    }
+
+    #[tokio::test]
+    async fn test_no_fetch_never_syncs_even_without_stored_listens() {
+        let user_id = Uuid::new_v4();
+        let activity = run_at_base_time(user_id);
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![activity.clone()]])
+            .append_query_results([Vec::<listen::Model>::new(), Vec::new()])
+            .into_connection();
+
+        let loaded = load_activity_listens(&db, user_id, activity.id, no_fetch())
+            .await
+            .unwrap();
+
+        assert!(loaded.listens.is_empty());
+        let log = db.into_transaction_log();
+        assert_eq!(log.len(), 3, "{log:?}");
+        assert!(!format!("{log:?}").contains("lastfm_username"), "{log:?}");
+    }
+
+    #[tokio::test]
+    async fn test_missing_listens_trigger_user_lookup_by_default() {
+        let user_id = Uuid::new_v4();
+        let activity = run_at_base_time(user_id);
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![activity.clone()]])
+            .append_query_results([Vec::<listen::Model>::new()])
+            .into_connection();
+
+        let result =
+            load_activity_listens(&db, user_id, activity.id, ListenMatchOptions::default()).await;
+
+        // The mock has no user row, so the sync stops at the user lookup
+        assert!(result.is_err());
+        let log = db.into_transaction_log();
+        assert!(
+            format!("{:?}", log[2]).contains("lastfm_username"),
+            "{log:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unlinked_lastfm_reports_not_configured() {
+        let user_id = Uuid::new_v4();
+        let activity = run_at_base_time(user_id);
+        let unlinked = user::Model {
+            id: user_id,
+            lastfm_username: None,
+            ..make_user()
+        };
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![activity.clone()]])
+            .append_query_results([Vec::<listen::Model>::new()])
+            .append_query_results([vec![unlinked]])
+            .into_connection();
+
+        let error = load_activity_listens(&db, user_id, activity.id, ListenMatchOptions::default())
+            .await
+            .err()
+            .expect("Syncing without a Last.fm username must fail");
+
+        assert!(error.downcast_ref::<LastfmNotConfiguredError>().is_some());
+    }
+
+    // ==================== Group S: Activity Duration Guard ====================
+
+    #[test]
+    fn test_positive_elapsed_time_is_used() {
+        assert_eq!(activity_duration_seconds(600, 500).unwrap(), 600);
+    }
+
+    #[test]
+    fn test_non_positive_elapsed_time_falls_back_to_moving_time() {
+        assert_eq!(activity_duration_seconds(0, 500).unwrap(), 500);
+        assert_eq!(activity_duration_seconds(-30, 500).unwrap(), 500);
+    }
+
+    #[tokio::test]
+    async fn test_activity_without_duration_returns_guard_error() {
+        let user_id = Uuid::new_v4();
+        let activity = crate::database::activity::Model {
+            elapsed_time: -60,
+            moving_time: 0,
+            ..run_at_base_time(user_id)
+        };
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![activity.clone()]])
+            .into_connection();
+
+        let Err(error) = load_activity_listens(&db, user_id, activity.id, no_fetch()).await else {
+            panic!("expected the duration guard to reject the activity");
+        };
+
+        assert!(error.is::<InvalidActivityDurationError>(), "{error}");
+        // Rejected before any listen query
+        assert_eq!(db.into_transaction_log().len(), 1);
+    }
+
+    // ==================== Group T: Moving Time Window ====================
+
+    /// Loads the listens of a run with a 30-minute pause under `window`
+    ///
+    /// Moving for 30 minutes, elapsed 60 minutes. Returns the loaded listens and the
+    /// bind values of the query matching them.
+    async fn load_with_window(window: ActivityWindow) -> (ActivityListens, Vec<sea_orm::Value>) {
+        let user_id = Uuid::new_v4();
+        let activity = crate::database::activity::Model {
+            moving_time: 1800,
+            elapsed_time: 3600,
+            ..run_at_base_time(user_id)
+        };
+        let (warmup, warmup_track) =
+            make_listen_with_track(user_id, Uuid::new_v4(), minutes_after(5), "Warmup", "A");
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![activity.clone()]])
+            .append_query_results([vec![warmup.clone()]])
+            .append_query_results([vec![(warmup, warmup_track.unwrap())]])
+            .into_connection();
+        let matching = ListenMatchOptions {
+            window,
+            ..no_fetch()
+        };
+
+        let loaded = load_activity_listens(&db, user_id, activity.id, matching)
+            .await
+            .unwrap();
+
+        let log = db.into_transaction_log();
+        let values = log[2].statements()[0]
+            .values
+            .clone()
+            .map(|values| values.0)
+            .unwrap_or_default();
+        (loaded, values)
+    }
+
+    #[tokio::test]
+    async fn test_elapsed_window_matches_listens_after_a_pause() {
+        let (loaded, values) = load_with_window(ActivityWindow::Elapsed).await;
+
+        let matched_end = sea_orm::Value::from(minutes_after(60).fixed_offset());
+        assert!(values.contains(&matched_end), "{values:?}");
+        assert_eq!(loaded.activity_end, minutes_after(60));
+    }
+
+    #[tokio::test]
+    async fn test_moving_window_drops_cooldown_listens_but_keeps_the_whole_activity() {
+        let (loaded, values) = load_with_window(ActivityWindow::Moving).await;
+
+        let matched_end = sea_orm::Value::from(minutes_after(30).fixed_offset());
+        assert!(values.contains(&matched_end), "{values:?}");
+        assert_eq!(
+            loaded.activity_end,
+            minutes_after(60),
+            "Streams after the moving time must still be loaded"
+        );
+    }
+
+    #[test]
+    fn test_moving_window_falls_back_to_elapsed_time() {
+        assert_eq!(
+            ActivityWindow::Moving.duration_seconds(600, 0).unwrap(),
+            600
+        );
+        assert_eq!(
+            ActivityWindow::Moving.duration_seconds(600, 480).unwrap(),
+            480
+        );
+        assert_eq!(
+            ActivityWindow::Elapsed.duration_seconds(600, 480).unwrap(),
+            600
+        );
+    }
+
+    #[test]
+    fn test_window_parses_from_query_value() {
+        assert_eq!(
+            "moving".parse::<ActivityWindow>().unwrap(),
+            ActivityWindow::Moving
+        );
+        assert_eq!(ActivityWindow::default(), ActivityWindow::Elapsed);
+    }
+
+    // ==================== Group U: Elevation Profile ====================
+
+    /// One point every 5 m over rolling hills, with a GPS glitch and altitude dropouts
+    fn make_hilly_run(len: usize) -> Vec<activity_stream::Model> {
+        let activity_id = Uuid::new_v4();
+        (0..len)
+            .map(|i| {
+                let distance = i as f32 * 5.0;
+                activity_stream::Model {
+                    // Distance briefly jumps back at i = 100
+                    distance: Some(if i == 100 { distance - 50.0 } else { distance }),
+                    altitude: (i % 17 != 0).then(|| 100.0 + 30.0 * (distance / 400.0).sin()),
+                    ..make_stream_point(
+                        activity_id,
+                        seconds_after(i64::try_from(i).unwrap()),
+                        None,
+                        None,
+                    )
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_elevation_profile_is_monotonic_in_distance() {
+        let profile = elevation_profile(&make_hilly_run(2000), 2000).unwrap();
+
+        assert!(profile
+            .windows(2)
+            .all(|pair| pair[0].distance <= pair[1].distance));
+        assert!(profile.len() < 2000, "points without altitude are skipped");
+    }
+
+    #[test]
+    fn test_elevation_profile_honors_point_budget() {
+        let streams = make_hilly_run(2000);
+
+        let profile = elevation_profile(&streams, 100).unwrap();
+
+        assert_eq!(profile.len(), 100);
+        assert!((profile[0].distance - 5.0).abs() < f64::EPSILON);
+        assert!((profile[99].distance - 1999.0 * 5.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_elevation_profile_skips_points_without_altitude() {
+        let activity_id = Uuid::new_v4();
+        let streams = vec![activity_stream::Model {
+            altitude: None,
+            ..make_stream_point(activity_id, base_time(), None, None)
+        }];
+
+        assert!(elevation_profile(&streams, 10).unwrap().is_empty());
+        assert!(elevation_profile(&streams, 1).is_err());
+    }
+
+    // ==================== Group V: Minimum Points To Simplify ====================
+
+    /// Zigzag route whose every point lies well outside a 10m tolerance
+    fn make_zigzag(activity_id: Uuid, len: i64) -> Vec<activity_stream::Model> {
+        (0..len)
+            .map(|i| {
+                let zigzag = if i % 2 == 0 { 0.0 } else { 0.001 };
+                make_stream_point(
+                    activity_id,
+                    seconds_after(i * 10),
+                    Some(48.0 + i as f64 * 0.0001),
+                    Some(2.0 + zigzag),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_segment_below_min_points_passes_through_unchanged() {
+        // Collinear, so any simplification would drop the middle points
+        let streams: Vec<activity_stream::Model> = (0..9)
+            .map(|i| {
+                make_stream_point(
+                    Uuid::new_v4(),
+                    seconds_after(i * 10),
+                    Some(48.0 + i as f64 * 0.001),
+                    Some(2.0),
+                )
+            })
+            .collect();
+
+        let points = simplify_segment_points(
+            streams.clone(),
+            simplification(Some(10.0), SimplificationAlgorithm::Rdp),
+        )
+        .unwrap();
+
+        assert_eq!(points, streams);
+    }
+
+    #[test]
+    fn test_segment_at_min_points_is_simplified() {
+        let streams: Vec<activity_stream::Model> = (0..10)
+            .map(|i| {
+                make_stream_point(
+                    Uuid::new_v4(),
+                    seconds_after(i * 10),
+                    Some(48.0 + i as f64 * 0.001),
+                    Some(2.0),
+                )
+            })
+            .collect();
+
+        let points = simplify_segment_points(
+            streams,
+            simplification(Some(10.0), SimplificationAlgorithm::Rdp),
+        )
+        .unwrap();
+
+        assert_eq!(points.len(), 2, "Straight line keeps only its endpoints");
+    }
+
+    #[test]
+    fn test_short_track_segment_skips_simplification() {
+        let activity_id = Uuid::new_v4();
+        let user_id = Uuid::new_v4();
+        // Track skipped after 30 seconds: 3 points, then a long straight stretch
+        let mut streams = make_zigzag(activity_id, 3);
+        streams.extend((3..60).map(|i| {
+            make_stream_point(
+                activity_id,
+                seconds_after(i * 10),
+                Some(48.0 + i as f64 * 0.0001),
+                Some(2.0),
+            )
+        }));
+        let listens = vec![
+            make_listen_with_track(user_id, Uuid::new_v4(), base_time(), "Skipped", "A"),
+            make_listen_with_track(user_id, Uuid::new_v4(), seconds_after(30), "Kept", "B"),
+        ];
+
+        let segments = build_activity_segments(
+            &streams,
+            &listens,
+            base_time(),
+            seconds_after(590),
+            simplification(Some(10.0), SimplificationAlgorithm::Rdp),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].points, streams[..3]);
+        assert!(segments[1].points.len() < 57, "Long segment is simplified");
+    }
+
+    #[test]
+    fn test_min_points_applies_to_gps_points_only() {
+        let activity_id = Uuid::new_v4();
+        // 12 points, but only 6 with coordinates (GPS dropout)
+        let streams: Vec<activity_stream::Model> = make_zigzag(activity_id, 12)
+            .into_iter()
+            .enumerate()
+            .map(|(i, point)| {
+                if i % 2 == 0 {
+                    point
+                } else {
+                    activity_stream::Model {
+                        latitude: None,
+                        longitude: None,
+                        ..point
+                    }
+                }
+            })
+            .collect();
+
+        let points = simplify_segment_points(
+            streams.clone(),
+            simplification(Some(10.0), SimplificationAlgorithm::Rdp),
+        )
+        .unwrap();
+
+        assert_eq!(points, streams);
+    }
+
+    // ==================== Group W: Activity Overlaps ====================
+
+    fn make_activity_at(start_minutes: i64, elapsed_time: i32) -> activity::Model {
+        activity::Model {
+            start_time: minutes_after(start_minutes).into(),
+            elapsed_time,
+            moving_time: elapsed_time,
+            ..make_activity()
+        }
+    }
+
+    #[test]
+    fn test_overlapping_activities_are_paired() {
+        // Treadmill app logged 5 minutes after the watch, both 30 minutes long
+        let watch = make_activity_at(0, 1800);
+        let treadmill = make_activity_at(5, 1800);
+
+        let overlaps = find_activity_overlaps(&[treadmill.clone(), watch.clone()]);
+
+        assert_eq!(
+            overlaps,
+            vec![ActivityOverlap {
+                first_activity_id: watch.id,
+                second_activity_id: treadmill.id,
+                overlap_start: minutes_after(5),
+                overlap_end: minutes_after(30),
+                overlap_seconds: 1500,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_disjoint_and_touching_activities_do_not_overlap() {
+        let morning = make_activity_at(0, 1800);
+        // Starts exactly when the morning run ends
+        let touching = make_activity_at(30, 600);
+        let evening = make_activity_at(600, 3600);
+
+        assert!(find_activity_overlaps(&[morning, touching, evening]).is_empty());
+    }
+
+    #[test]
+    fn test_activity_inside_another_overlaps_for_its_whole_duration() {
+        let long_ride = make_activity_at(0, 7200);
+        let short_run = make_activity_at(30, 600);
+        let later_run = make_activity_at(100, 1200);
+
+        let overlaps = find_activity_overlaps(&[short_run.clone(), long_ride.clone(), later_run]);
+
+        assert_eq!(overlaps.len(), 2);
+        assert_eq!(overlaps[0].first_activity_id, long_ride.id);
+        assert_eq!(overlaps[0].second_activity_id, short_run.id);
+        assert_eq!(overlaps[0].overlap_seconds, 600);
+        assert_eq!(overlaps[1].overlap_seconds, 1200);
+    }
+
+    #[test]
+    fn test_activity_without_duration_is_ignored() {
+        let run = make_activity_at(0, 1800);
+        let manual_entry = make_activity_at(5, 0);
+
+        assert!(find_activity_overlaps(&[run, manual_entry]).is_empty());
+    }
+
+    // ==================== Group X: Splitting Segments At GPS Gaps ====================
+
+    /// One track over 10 minutes with fixes every 30s, except a 5-minute gap after 2 minutes
+    fn track_with_gps_gap() -> (
+        Vec<activity_stream::Model>,
+        Vec<(listen::Model, Option<track::Model>)>,
+    ) {
+        let activity_id = Uuid::new_v4();
+        let streams = (0..=4)
+            .chain(14..20)
+            .map(|i| {
+                let offset = f64::from(i) * 0.0001;
+                make_stream_point(
+                    activity_id,
+                    seconds_after(i64::from(i) * 30),
+                    Some(48.0 + offset),
+                    Some(2.0 + offset),
+                )
+            })
+            .collect();
+        let listens = vec![make_listen_with_track(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            base_time(),
+            "Tunnel Song",
+            "Artist",
+        )];
+        (streams, listens)
+    }
+
+    #[test]
+    fn test_track_segment_with_gps_gap_splits_into_sub_segments() {
+        let (streams, listens) = track_with_gps_gap();
+
+        let segments = build_activity_segments(
+            &streams,
+            &listens,
+            base_time(),
+            minutes_after(10),
+            None,
+            Some(Duration::seconds(60)),
+        )
+        .unwrap();
+
+        assert_eq!(segments.len(), 2);
+        let track_ids: Vec<Uuid> = segments
+            .iter()
+            .map(|s| s.track.as_ref().unwrap().id)
+            .collect();
+        assert_eq!(track_ids[0], track_ids[1]);
+        assert_eq!(segments[0].points.len(), 5);
+        assert_eq!(segments[1].points.len(), 6);
+        assert_eq!(segments[1].index, 1);
+
+        // Contiguous in time: the second part starts at the first fix after the gap
+        assert_eq!(segments[0].start_time, base_time());
+        assert_eq!(segments[0].end_time, seconds_after(14 * 30));
+        assert_eq!(segments[1].start_time, seconds_after(14 * 30));
+        assert_eq!(segments[1].end_time, minutes_after(10));
+
+        assert!(!segments[0].after_gap);
+        assert!(segments[1].after_gap);
+    }
+
+    #[test]
+    fn test_split_track_still_counts_as_a_single_play() {
+        let (streams, listens) = track_with_gps_gap();
+
+        let segments = build_activity_segments(
+            &streams,
+            &listens,
+            base_time(),
+            minutes_after(10),
+            None,
+            Some(Duration::seconds(60)),
+        )
+        .unwrap();
+
+        assert!(segments.iter().all(|s| !s.repeated));
+        let counts = track_play_counts(&segments);
+        assert_eq!(counts.len(), 1);
+        assert_eq!(counts[0].play_count, 1);
+    }
+
+    #[test]
+    fn test_gps_gaps_are_kept_without_a_split_threshold() {
+        let (streams, listens) = track_with_gps_gap();
+
+        let segments = build_activity_segments(
+            &streams,
+            &listens,
+            base_time(),
+            minutes_after(10),
+            None,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].points.len(), 11);
+        assert!(!segments[0].after_gap);
+    }
+
+    #[test]
+    fn test_gap_shorter_than_threshold_does_not_split() {
+        let (streams, listens) = track_with_gps_gap();
+
+        let segments = build_activity_segments(
+            &streams,
+            &listens,
+            base_time(),
+            minutes_after(10),
+            None,
+            Some(Duration::seconds(600)),
+        )
+        .unwrap();
+
+        assert_eq!(segments.len(), 1);
+    }
+
+    #[test]
+    fn test_points_without_coordinates_do_not_close_a_gap() {
+        let activity_id = Uuid::new_v4();
+        let points = vec![
+            make_stream_point(activity_id, seconds_after(0), Some(48.0), Some(2.0)),
+            make_stream_point(activity_id, seconds_after(100), None, None),
+            make_stream_point(activity_id, seconds_after(200), Some(48.001), Some(2.001)),
+        ];
+
+        let runs = split_at_gps_gaps(points, Duration::seconds(150));
+
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].len(), 2);
+        assert_eq!(runs[1].len(), 1);
+    }
+
+    #[test]
+    fn test_split_gap_is_off_by_default() {
+        assert_eq!(ListenMatchOptions::default().split_gap(), None);
+        let matching = ListenMatchOptions {
+            split_gap_seconds: 90,
+            ..ListenMatchOptions::default()
+        };
+        assert_eq!(matching.split_gap(), Some(Duration::seconds(90)));
+    }
+
+    // ==================== Group Y: Listen Density ====================
+
+    #[test]
+    fn test_listen_density_counts_distinct_tracks_per_bucket() {
+        let user_id = Uuid::new_v4();
+        let track_a = Uuid::new_v4();
+        // A played through, then B, C and D skipped within a minute, then A again
+        let listens = vec![
+            make_listen_with_track(user_id, track_a, base_time(), "Track A", "Artist"),
+            make_listen_with_track(
+                user_id,
+                Uuid::new_v4(),
+                minutes_after(6),
+                "Track B",
+                "Artist",
+            ),
+            make_listen_with_track(
+                user_id,
+                Uuid::new_v4(),
+                seconds_after(380),
+                "Track C",
+                "Artist",
+            ),
+            make_listen_with_track(
+                user_id,
+                Uuid::new_v4(),
+                seconds_after(400),
+                "Track D",
+                "Artist",
+            ),
+            make_listen_with_track(user_id, track_a, minutes_after(8), "Track A", "Artist"),
+        ];
+
+        let buckets = build_listen_density(
+            &listens,
+            base_time(),
+            minutes_after(14),
+            Duration::minutes(5),
+        );
+
+        let counts: Vec<usize> = buckets.iter().map(|b| b.distinct_tracks).collect();
+        // 0-5: A; 5-10: A, B, C, D and A again (4 distinct); 10-14: A
+        assert_eq!(counts, vec![1, 4, 1]);
+        assert_eq!(buckets[1].start_time, minutes_after(5));
+        assert_eq!(
+            buckets[2].end_time,
+            minutes_after(14),
+            "Last bucket ends with the activity"
+        );
+    }
+
+    #[test]
+    fn test_listen_density_covers_silence_and_the_leading_padding() {
+        let user_id = Uuid::new_v4();
+        // Started before the activity, then nothing until the last bucket
+        let listens = vec![
+            make_listen_with_track(
+                user_id,
+                Uuid::new_v4(),
+                base_time() - Duration::minutes(2),
+                "Track A",
+                "Artist",
+            ),
+            make_listen_with_track(
+                user_id,
+                Uuid::new_v4(),
+                minutes_after(2),
+                "Track B",
+                "Artist",
+            ),
+            make_listen_with_track(
+                user_id,
+                Uuid::new_v4(),
+                minutes_after(2),
+                "Track C",
+                "Artist",
+            ),
+        ];
+
+        let buckets = build_listen_density(
+            &listens,
+            base_time(),
+            minutes_after(6),
+            Duration::minutes(2),
+        );
+
+        // A covers 0-2; B was replaced at once and never played; C covers 2-6
+        let counts: Vec<usize> = buckets.iter().map(|b| b.distinct_tracks).collect();
+        assert_eq!(counts, vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn test_listen_density_without_music_still_reports_buckets() {
+        let buckets =
+            build_listen_density(&[], base_time(), minutes_after(10), Duration::minutes(3));
+
+        assert_eq!(
+            buckets.len(),
+            4,
+            "10 minutes in 3 minute buckets: 4 buckets"
+        );
+        assert!(buckets.iter().all(|b| b.distinct_tracks == 0));
+    }
+
+    #[test]
+    fn test_listen_density_listen_ending_on_a_boundary_stays_in_its_buckets() {
+        let user_id = Uuid::new_v4();
+        let listens = vec![
+            make_listen_with_track(user_id, Uuid::new_v4(), base_time(), "Track A", "Artist"),
+            make_listen_with_track(
+                user_id,
+                Uuid::new_v4(),
+                minutes_after(4),
+                "Track B",
+                "Artist",
+            ),
+        ];
+
+        let buckets = build_listen_density(
+            &listens,
+            base_time(),
+            minutes_after(6),
+            Duration::minutes(2),
+        );
+
+        // A spans 0-4 exactly, B 4-6
+        let counts: Vec<usize> = buckets.iter().map(|b| b.distinct_tracks).collect();
+        assert_eq!(counts, vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn test_listen_density_rejects_buckets_below_the_minimum() {
+        assert!(!is_valid_density_bucket(0));
+        assert!(!is_valid_density_bucket(
+            MIN_LISTEN_DENSITY_BUCKET_SECONDS - 1
+        ));
+        assert!(is_valid_density_bucket(MIN_LISTEN_DENSITY_BUCKET_SECONDS));
+    }
+
+    // ==================== Group Z: Segment Duration ====================
+
+    #[test]
+    fn test_segment_duration_matches_end_minus_start() {
+        let start = base_time();
+        let segment = make_segment(0, None, start, minutes_after(3), 3);
+
+        assert_eq!(
+            segment.duration_seconds(),
+            (segment.end_time - segment.start_time).num_seconds()
+        );
+        assert_eq!(segment.duration_seconds(), 180);
+    }
+
+    #[test]
+    fn test_segment_duration_is_never_negative() {
+        let start = base_time();
+        let mut segment = make_segment(0, None, start, start, 0);
+        assert_eq!(segment.duration_seconds(), 0, "empty segment");
+
+        // End before start, e.g. after a clock adjustment
+        segment.end_time = start - Duration::seconds(30);
+        assert_eq!(segment.duration_seconds(), 0, "reversed segment");
+
+        // Sub-second segments round down instead of up
+        segment.end_time = start + Duration::milliseconds(999);
+        assert_eq!(segment.duration_seconds(), 0, "sub-second segment");
+    }
 }
@@ -1,13 +1,27 @@
+pub mod activity_summary;
 pub mod analytics_service;
 pub mod music_service;
 pub mod oauth;
 pub mod oauth_session;
+pub mod refresh_token_service;
+pub mod similar_routes;
+pub mod spotify_enrichment;
+pub mod sync_events;
+pub mod token_reencryption;
+pub mod training_load;
 pub mod user_service;
 pub mod workout;
 
+pub use activity_summary::*;
 pub use analytics_service::*;
 pub use music_service::*;
 pub use oauth::*;
 pub use oauth_session::*;
+pub use refresh_token_service::*;
+pub use similar_routes::*;
+pub use spotify_enrichment::*;
+pub use sync_events::*;
+pub use token_reencryption::*;
+pub use training_load::*;
 pub use user_service::*;
 pub use workout::*;
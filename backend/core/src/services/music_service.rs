@@ -1,14 +1,48 @@
+use std::collections::HashMap;
+
 use chrono::TimeZone;
 use lastfm_client::types::RecentTrack;
 use run_sous_bpm_integrations::lastfm::LastFmClient;
-use sea_orm::DatabaseConnection;
+use sea_orm::{DatabaseConnection, DbErr};
 use tracing::info;
+use uuid::Uuid;
 
 use crate::{
-    database::{batch_create_listens, listen, upsert_track},
-    models::{CreateListenDto, CreateTrackDto},
+    database::{
+        batch_create_listens, listen, retry_transient, run_in_transaction, track, upsert_track,
+        RetryPolicy,
+    },
+    models::{parse_lastfm_export, CreateListenDto, CreateTrackDto, SkippedRow},
 };
 
+/// Listens inserted per statement when importing, well under Postgres' parameter limit
+const IMPORT_BATCH_SIZE: usize = 1000;
+
+/// Outcome of a Last.fm export import
+#[derive(Debug, Clone)]
+pub struct LastFmImportReport {
+    /// Rows found in the export, valid or not
+    pub total_rows: usize,
+    /// Listens newly stored
+    pub inserted: u64,
+    /// Valid rows already stored (or repeated within the export)
+    pub duplicates: u64,
+    /// Rows that could not be parsed, with the reason
+    pub skipped: Vec<SkippedRow>,
+}
+
+/// Outcome of a Last.fm sync for a time range
+#[derive(Debug, Clone)]
+pub struct LastFmSync {
+    /// Listens stored in the synced range
+    pub listens: Vec<listen::Model>,
+    /// Track playing at sync time, as an unsaved listen stamped with the current time
+    ///
+    /// Never stored: Last.fm scrobbles the track with its real play date once it
+    /// ends, and a stored copy would be duplicated by every later sync.
+    pub now_playing: Option<(listen::Model, track::Model)>,
+}
+
 /// Syncs Last.fm listening history for a specific time range (e.g., during an activity)
 ///
 /// # Arguments
@@ -16,12 +50,14 @@ use crate::{
 /// * `lastfm_username` - Last.fm username to fetch data for
 /// * `start_timestamp` - Unix timestamp (seconds) for start of range
 /// * `end_timestamp` - Unix timestamp (seconds) for end of range
-/// * `lastfm_client` - Last.fm API client instance
+/// * `include_now_playing` - Return the "now playing" track as a listen at the current
+///   time, for activities still in progress (only its track is stored)
 /// * `db_connection` - Database connection
 ///
 /// # Errors
 ///
 /// Returns an error if:
+/// - The Last.fm integration is not configured (`LAST_FM_API_KEY` unset)
 /// - Last.fm API request fails
 /// - Track/listen DTO conversion fails
 /// - Database insertion fails
@@ -30,18 +66,24 @@ use crate::{
 /// Panics if the provided timestamps are out of valid date range
 ///
 /// # Returns
-/// Vector of saved listen records
+/// Saved listen records and the unsaved "now playing" listen, if requested
 pub async fn sync_lastfm_for_time_range(
     user_id: uuid::Uuid,
     lastfm_username: &str,
     start_timestamp: i64,
     end_timestamp: i64,
+    include_now_playing: bool,
     db_connection: &DatabaseConnection,
-) -> Result<Vec<listen::Model>, Box<dyn std::error::Error>> {
-    let lastfm_client = LastFmClient::new();
+) -> Result<LastFmSync, Box<dyn std::error::Error>> {
+    let lastfm_client = LastFmClient::try_new()?;
 
     let lastfm_tracks = lastfm_client
-        .get_tracks_in_time_range(lastfm_username, start_timestamp, end_timestamp)
+        .get_tracks_in_time_range(
+            lastfm_username,
+            start_timestamp,
+            end_timestamp,
+            include_now_playing,
+        )
         .await?;
 
     info!(
@@ -55,31 +97,45 @@ pub async fn sync_lastfm_for_time_range(
 
     if lastfm_tracks.is_empty() {
         info!("No tracks found in time range");
-        return Ok(Vec::new());
+        return Ok(LastFmSync {
+            listens: Vec::new(),
+            now_playing: None,
+        });
     }
 
-    let mut listen_models = Vec::new();
-
-    for lastfm_track in lastfm_tracks {
-        let Some(date) = &lastfm_track.date else {
-            continue;
-        };
-
-        // Create track DTO and upsert (deduplicates by artist+track name)
-        let track_dto = CreateTrackDto::from_lastfm_track(&lastfm_track);
-        let saved_track = upsert_track(db_connection, track_dto).await?;
-
-        let listen_dto = CreateListenDto::new(user_id, saved_track.id, date.uts);
+    // Create track DTOs, upserted below (deduplicates by artist+track name)
+    let (scrobbles, now_playing_track) =
+        split_now_playing(lastfm_tracks.iter().map(|lastfm_track| {
+            (
+                CreateTrackDto::from_lastfm_track(lastfm_track),
+                lastfm_track.date.as_ref().map(|date| date.uts),
+            )
+        }));
 
-        listen_models.push(listen_dto.into_active_model());
-    }
+    let fetched_count = scrobbles.len();
+    let retry = RetryPolicy::from_env();
+    // The whole save is one transaction, so a retry starts again from a clean state
+    let inserted_count = retry_transient(retry, || {
+        save_lastfm_scrobbles(db_connection, user_id, scrobbles.clone())
+    })
+    .await?;
 
-    let insert_count = listen_models.len();
-    batch_create_listens(db_connection, listen_models).await?;
+    let now_playing = match now_playing_track.filter(|_| include_now_playing) {
+        Some(track_dto) => {
+            let saved_track =
+                retry_transient(retry, || upsert_track(db_connection, track_dto.clone())).await?;
+            Some((
+                now_playing_listen(user_id, saved_track.id, chrono::Utc::now()),
+                saved_track,
+            ))
+        }
+        None => None,
+    };
 
     info!(
         user_id = %user_id,
-        listens_saved = insert_count,
+        listens_fetched = fetched_count,
+        listens_saved = inserted_count,
         "Successfully synced Last.fm listening history"
     );
 
@@ -99,7 +155,68 @@ pub async fn sync_lastfm_for_time_range(
     )
     .await?;
 
-    Ok(saved_listens)
+    Ok(LastFmSync {
+        listens: saved_listens,
+        now_playing,
+    })
+}
+
+/// Splits dated scrobbles from the undated "now playing" track, if any
+fn split_now_playing(
+    tracks: impl IntoIterator<Item = (CreateTrackDto, Option<u32>)>,
+) -> (Vec<(CreateTrackDto, u32)>, Option<CreateTrackDto>) {
+    let mut scrobbles = Vec::new();
+    let mut now_playing = None;
+    for (track, played_at) in tracks {
+        match played_at {
+            Some(played_at) => scrobbles.push((track, played_at)),
+            None => now_playing = now_playing.or(Some(track)),
+        }
+    }
+    (scrobbles, now_playing)
+}
+
+/// An in-memory listen of `track_id` started at `played_at`, never stored
+fn now_playing_listen(
+    user_id: Uuid,
+    track_id: Uuid,
+    played_at: chrono::DateTime<chrono::Utc>,
+) -> listen::Model {
+    listen::Model {
+        id: Uuid::new_v4(),
+        user_id,
+        track_id,
+        played_at: played_at.fixed_offset(),
+        created_at: played_at.fixed_offset(),
+    }
+}
+
+/// Upserts the tracks of Last.fm scrobbles and inserts their listens in one transaction
+///
+/// Either every track and listen is stored or, on any error, none of them are,
+/// so a failure can't leave tracks without the listens that referenced them.
+///
+/// # Returns
+///
+/// The number of listens actually inserted (existing ones are skipped)
+async fn save_lastfm_scrobbles(
+    db_connection: &DatabaseConnection,
+    user_id: Uuid,
+    scrobbles: Vec<(CreateTrackDto, u32)>,
+) -> Result<u64, DbErr> {
+    run_in_transaction(db_connection, move |transaction| {
+        Box::pin(async move {
+            let mut listen_models = Vec::with_capacity(scrobbles.len());
+            for (track_dto, played_at) in scrobbles {
+                let saved_track = upsert_track(transaction, track_dto).await?;
+                listen_models.push(
+                    CreateListenDto::new(user_id, saved_track.id, played_at).into_active_model(),
+                );
+            }
+            batch_create_listens(transaction, listen_models).await
+        })
+    })
+    .await
 }
 
 /// Fetches Last.fm tracks for a time range WITHOUT saving to database
@@ -114,7 +231,7 @@ pub async fn sync_lastfm_for_time_range(
 ///
 /// # Errors
 ///
-/// Returns an error if Last.fm API request fails
+/// Returns an error if the Last.fm integration is not configured or the API request fails
 ///
 /// # Returns
 /// Vector of raw `RecentTrack` objects from Last.fm API
@@ -123,10 +240,10 @@ pub async fn get_lastfm_tracks_raw(
     start_timestamp: i64,
     end_timestamp: i64,
 ) -> Result<Vec<RecentTrack>, Box<dyn std::error::Error>> {
-    let lastfm_client = LastFmClient::new();
+    let lastfm_client = LastFmClient::try_new()?;
 
     let lastfm_tracks = lastfm_client
-        .get_tracks_in_time_range(lastfm_username, start_timestamp, end_timestamp)
+        .get_tracks_in_time_range(lastfm_username, start_timestamp, end_timestamp, false)
         .await?;
 
     info!(
@@ -139,3 +256,160 @@ pub async fn get_lastfm_tracks_raw(
 
     Ok(lastfm_tracks)
 }
+
+/// Backfills listening history from a Last.fm export file (CSV or JSON)
+///
+/// Tracks are upserted by artist + track name and listens are inserted in batches;
+/// listens that already exist are skipped via the unique listen constraint, so
+/// importing the same export twice is harmless.
+///
+/// # Arguments
+/// * `user_id` - UUID of the user importing the export
+/// * `export` - Raw export file contents
+/// * `db_connection` - Database connection
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The export is not valid JSON while looking like JSON
+/// - Database insertion fails
+pub async fn import_lastfm_export(
+    user_id: Uuid,
+    export: &str,
+    db_connection: &DatabaseConnection,
+) -> Result<LastFmImportReport, Box<dyn std::error::Error>> {
+    let parsed = parse_lastfm_export(export)?;
+    let total_rows = parsed.total_rows();
+    let valid_rows = parsed.scrobbles.len() as u64;
+
+    // Same track usually appears many times in an export, upsert each only once
+    let mut track_ids: HashMap<(String, String), Uuid> = HashMap::new();
+    let mut listen_models = Vec::with_capacity(parsed.scrobbles.len());
+    let retry = RetryPolicy::from_env();
+
+    for scrobble in parsed.scrobbles {
+        let key = (
+            scrobble.track.artist_name.clone(),
+            scrobble.track.track_name.clone(),
+        );
+        let track_id = match track_ids.get(&key) {
+            Some(id) => *id,
+            None => {
+                let saved_track = retry_transient(retry, || {
+                    upsert_track(db_connection, scrobble.track.clone())
+                })
+                .await?;
+                track_ids.insert(key, saved_track.id);
+                saved_track.id
+            }
+        };
+
+        listen_models
+            .push(CreateListenDto::new(user_id, track_id, scrobble.played_at).into_active_model());
+    }
+
+    let mut inserted = 0;
+    while !listen_models.is_empty() {
+        let batch: Vec<_> = listen_models
+            .drain(..IMPORT_BATCH_SIZE.min(listen_models.len()))
+            .collect();
+        inserted +=
+            retry_transient(retry, || batch_create_listens(db_connection, batch.clone())).await?;
+    }
+
+    info!(
+        user_id = %user_id,
+        total_rows = total_rows,
+        inserted = inserted,
+        skipped = parsed.skipped.len(),
+        tracks = track_ids.len(),
+        "Imported Last.fm export"
+    );
+
+    Ok(LastFmImportReport {
+        total_rows,
+        inserted,
+        duplicates: valid_rows.saturating_sub(inserted),
+        skipped: parsed.skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::make_track;
+    use sea_orm::{DbBackend, MockDatabase};
+
+    fn make_track_dto(artist_name: &str, track_name: &str) -> CreateTrackDto {
+        CreateTrackDto {
+            artist_name: artist_name.to_string(),
+            track_name: track_name.to_string(),
+            album_name: None,
+            artist_mbid: None,
+            track_mbid: None,
+            album_mbid: None,
+            lastfm_url: None,
+        }
+    }
+
+    #[test]
+    fn test_now_playing_track_is_kept_apart_from_scrobbles() {
+        let (scrobbles, now_playing) = split_now_playing([
+            (
+                make_track_dto("Daft Punk", "Aerodynamic"),
+                Some(1_612_101_900),
+            ),
+            (make_track_dto("Daft Punk", "Digital Love"), None),
+        ]);
+
+        let played_at: Vec<(&str, u32)> = scrobbles
+            .iter()
+            .map(|(track, played_at)| (track.track_name.as_str(), *played_at))
+            .collect();
+        assert_eq!(played_at, vec![("Aerodynamic", 1_612_101_900)]);
+        assert_eq!(
+            now_playing.map(|track| track.track_name),
+            Some("Digital Love".to_string())
+        );
+    }
+
+    #[test]
+    fn test_scrobbles_without_now_playing_track() {
+        let (scrobbles, now_playing) = split_now_playing([(
+            make_track_dto("Daft Punk", "Aerodynamic"),
+            Some(1_612_101_900),
+        )]);
+
+        assert_eq!(scrobbles.len(), 1);
+        assert!(now_playing.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failure_mid_batch_rolls_back_every_write() {
+        let first = make_track_dto("Daft Punk", "One More Time");
+        let second = make_track_dto("Justice", "D.A.N.C.E.");
+        let db = MockDatabase::new(DbBackend::Postgres)
+            // First track's upsert returns the row
+            .append_query_results([vec![track::Model {
+                artist_name: first.artist_name.clone(),
+                track_name: first.track_name.clone(),
+                ..make_track()
+            }]])
+            // Second track's upsert fails before any listen is written
+            .append_query_errors([DbErr::Custom("connection reset".into())])
+            .into_connection();
+
+        let result = save_lastfm_scrobbles(
+            &db,
+            Uuid::new_v4(),
+            vec![(first, 1_700_000_000), (second, 1_700_000_240)],
+        )
+        .await;
+
+        assert!(matches!(result, Err(DbErr::Custom(ref message)) if message == "connection reset"));
+        let log = format!("{:?}", db.into_transaction_log());
+        assert!(!log.contains("played_at"), "{log}");
+        assert!(log.contains("ROLLBACK"), "{log}");
+        assert!(!log.contains("COMMIT"), "{log}");
+    }
+}
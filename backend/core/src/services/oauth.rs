@@ -1,12 +1,16 @@
-use super::oauth_session::OAuthSessionManager;
+use super::oauth_session::{OAuthSessionManager, CSRF_TOKEN_BYTES};
 use axum_login::tracing::info;
 use oauth2::basic::{BasicClient, BasicTokenResponse};
 use oauth2::{reqwest, RefreshToken};
-use oauth2::{AuthorizationCode, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, TokenResponse};
+use oauth2::{
+    AuthorizationCode, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, Scope, TokenResponse,
+};
+use run_sous_bpm_integrations::common::http_client::{connect_timeout, request_timeout};
+use run_sous_bpm_integrations::common::SecretToken;
 use sea_orm::DatabaseConnection;
 
-use crate::config::{ClientInfo, OAuthProvider};
-use crate::crypto::EncryptionService;
+use crate::config::{ClientInfo, OAuthProvider, ScopeNotAllowedError};
+use crate::crypto::TokenCrypto;
 use crate::database::repositories::oauth_token_repository::upsert_oauth_token;
 use crate::database::{get_oauth_token_by_provider, oauth_token};
 use crate::services::OAuthState;
@@ -38,37 +42,140 @@ fn build_oauth_client(client_info: &ClientInfo) -> ConfiguredClient {
         .set_auth_type(client_info.auth_type.clone())
 }
 
-#[must_use]
+/// HTTP client for token exchanges, with the same timeouts as the integration clients
+fn build_http_client() -> reqwest::Client {
+    reqwest::ClientBuilder::new()
+        // Following redirects opens the client up to SSRF vulnerabilities.
+        .redirect(reqwest::redirect::Policy::none())
+        .connect_timeout(connect_timeout())
+        .timeout(request_timeout())
+        .build()
+        .expect("Client should build")
+}
+
+/// Starts an OAuth flow using the provider configuration from the environment
+///
+/// `extra_scopes` are requested on top of the provider defaults, e.g. to widen
+/// the access of an already connected provider. `return_origin` is the already
+/// validated frontend origin the callback should send the user back to.
+///
+/// # Returns
+///
+/// The provider authorization URL the user should be redirected to
+///
+/// # Errors
+///
+/// Returns an error if an extra scope is not in the provider's allowlist
 pub fn start_oauth_flow(
     provider: OAuthProvider,
+    extra_scopes: &[String],
     session_store: &OAuthSessionManager,
     user_id: uuid::Uuid,
-) -> String {
-    let client_info = ClientInfo::from_provider(provider);
-    let client = build_oauth_client(&client_info);
+    return_origin: Option<String>,
+) -> Result<String, ScopeNotAllowedError> {
+    start_oauth_flow_with_client(
+        &ClientInfo::from_provider(provider),
+        extra_scopes,
+        session_store,
+        user_id,
+        return_origin,
+    )
+}
+
+/// Starts an OAuth flow against an explicit client configuration
+///
+/// Stores the PKCE verifier, the requested scopes and the return origin under
+/// the generated CSRF token until the callback.
+///
+/// # Returns
+///
+/// The provider authorization URL the user should be redirected to
+///
+/// # Errors
+///
+/// Returns an error if an extra scope is not in the provider's allowlist
+pub fn start_oauth_flow_with_client(
+    client_info: &ClientInfo,
+    extra_scopes: &[String],
+    session_store: &OAuthSessionManager,
+    user_id: uuid::Uuid,
+    return_origin: Option<String>,
+) -> Result<String, ScopeNotAllowedError> {
+    let scopes = requested_scopes(client_info, extra_scopes)?;
+    let client = build_oauth_client(client_info);
 
     // Generate a PKCE challenge.
     let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
 
     // Generate the full authorization URL.
     let (auth_url, csrf_token) = client
-        .authorize_url(CsrfToken::new_random)
-        .add_scopes(client_info.scopes.clone())
+        .authorize_url(|| CsrfToken::new_random_len(CSRF_TOKEN_BYTES))
+        // Joined by hand: the oauth2 crate always separates scopes with spaces
+        .add_scope(Scope::new(
+            scopes.join(client_info.provider.scope_separator()),
+        ))
         // Set the PKCE code challenge.
         .set_pkce_challenge(pkce_challenge)
         .url();
 
     let state = OAuthState {
         pkce_verifier: pkce_verifier.secret().clone(),
-        provider,
+        provider: client_info.provider,
         user_id,
+        scopes,
+        return_origin,
     };
     session_store.store(csrf_token.secret().clone(), state);
-    auth_url.to_string()
+    Ok(auth_url.to_string())
+}
+
+/// The client's scopes followed by the extra ones it doesn't already include
+fn requested_scopes(
+    client_info: &ClientInfo,
+    extra_scopes: &[String],
+) -> Result<Vec<String>, ScopeNotAllowedError> {
+    let provider = client_info.provider;
+    let mut scopes: Vec<String> = client_info
+        .scopes
+        .iter()
+        .map(|s| s.as_ref().to_string())
+        .collect();
+
+    for scope in extra_scopes {
+        if !provider.allowed_scopes().contains(&scope.as_str()) {
+            return Err(ScopeNotAllowedError {
+                provider,
+                scope: scope.clone(),
+            });
+        }
+        if !scopes.contains(scope) {
+            scopes.push(scope.clone());
+        }
+    }
+    Ok(scopes)
+}
+
+/// Token obtained from an OAuth callback, not yet persisted
+pub struct ExchangedOAuthToken {
+    pub token: BasicTokenResponse,
+    pub provider: OAuthProvider,
+    pub user_id: uuid::Uuid,
+    /// Scopes that were requested when the flow started
+    pub scopes: Vec<String>,
+    /// Frontend origin recorded when the flow started
+    pub return_origin: Option<String>,
 }
 
 /// Handles OAuth callback by exchanging authorization code for access token
 ///
+/// `granted_scope` is the `scope` parameter of the callback, which Strava uses to
+/// report the scopes the user actually accepted. The stored token records those,
+/// falling back to the requested scopes when the provider does not report them.
+///
+/// # Returns
+///
+/// The exchanged token, with the provider and the return origin of its flow
+///
 /// # Errors
 ///
 /// Returns an error if:
@@ -85,10 +192,69 @@ pub fn start_oauth_flow(
 pub async fn handle_oauth_callback(
     code: String,
     state: String,
+    granted_scope: Option<&str>,
     session_store: &OAuthSessionManager,
     db_connection: &DatabaseConnection,
-    encryption: &EncryptionService,
-) -> Result<(BasicTokenResponse, OAuthProvider), Box<dyn std::error::Error>> {
+    encryption: &dyn TokenCrypto,
+) -> Result<ExchangedOAuthToken, Box<dyn std::error::Error>> {
+    let exchanged =
+        exchange_oauth_callback(code, state, session_store, ClientInfo::from_provider).await?;
+
+    let encrypted_access_token = encryption.encrypt(exchanged.token.access_token().secret())?;
+    let encrypted_refresh_token = exchanged
+        .token
+        .refresh_token()
+        .map(|r| encryption.encrypt(r.secret()))
+        .transpose()?;
+
+    upsert_oauth_token(
+        db_connection,
+        exchanged.user_id,
+        exchanged.provider,
+        encrypted_access_token,
+        encrypted_refresh_token,
+        exchanged.token.expires_in().map(|dur| {
+            let expiry = chrono::Utc::now()
+                + chrono::Duration::from_std(dur).expect("Token expiry duration out of range");
+            expiry.into()
+        }),
+        Some(granted_scope.map_or_else(|| exchanged.scopes.clone(), parse_scopes)),
+    )
+    .await?;
+
+    Ok(exchanged)
+}
+
+/// Splits a `scope` list, comma-separated (Strava) or space-separated (RFC 6749)
+#[must_use]
+pub fn parse_scopes(scope: &str) -> Vec<String> {
+    scope
+        .split([',', ' '])
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Validates an OAuth callback and exchanges its authorization code, without persisting
+///
+/// The client configuration is resolved from the provider stored with the CSRF
+/// state, so tests can point the exchange at a mock token endpoint.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - CSRF token is invalid or expired
+/// - Token exchange request fails
+///
+/// # Panics
+///
+/// Panics if the HTTP client fails to build (should never happen with default config)
+pub async fn exchange_oauth_callback(
+    code: String,
+    state: String,
+    session_store: &OAuthSessionManager,
+    client_info_for: impl FnOnce(OAuthProvider) -> ClientInfo,
+) -> Result<ExchangedOAuthToken, Box<dyn std::error::Error>> {
     info!(
         "Handling OAuth callback with code: {}, state: {}",
         code, state
@@ -99,61 +265,35 @@ pub async fn handle_oauth_callback(
     };
 
     let provider = session_state.provider;
-    let client_info = ClientInfo::from_provider(provider);
+    let client_info = client_info_for(provider);
     let oauth_client = build_oauth_client(&client_info);
 
-    let http_client = reqwest::ClientBuilder::new()
-        // Following redirects opens the client up to SSRF vulnerabilities.
-        .redirect(reqwest::redirect::Policy::none())
-        .build()
-        .expect("Client should build");
-
-    let token_result = oauth_client
+    let token = oauth_client
         .exchange_code(AuthorizationCode::new(code))
         // Set the PKCE code verifier.
         .set_pkce_verifier(PkceCodeVerifier::new(session_state.pkce_verifier))
-        .request_async(&http_client)
+        .request_async(&build_http_client())
         .await?;
 
-    let encrypted_access_token = encryption.encrypt(token_result.access_token().secret())?;
-    let encrypted_refresh_token = token_result
-        .refresh_token()
-        .map(|r| encryption.encrypt(r.secret()))
-        .transpose()?;
-
-    upsert_oauth_token(
-        db_connection,
-        // You would typically get the user ID from your session or context.
-        session_state.user_id,
+    Ok(ExchangedOAuthToken {
+        token,
         provider,
-        encrypted_access_token,
-        encrypted_refresh_token,
-        token_result.expires_in().map(|dur| {
-            let expiry = chrono::Utc::now()
-                + chrono::Duration::from_std(dur).expect("Token expiry duration out of range");
-            expiry.into()
-        }),
-        Some(
-            client_info
-                .scopes
-                .iter()
-                .map(|s| s.as_ref().to_string())
-                .collect(),
-        ),
-    )
-    .await?;
-
-    Ok((token_result, provider))
+        user_id: session_state.user_id,
+        scopes: session_state.scopes,
+        return_origin: session_state.return_origin,
+    })
 }
 
 /// Gets a valid OAuth access token for a user and provider
 ///
-/// If the token is expired, it will automatically refresh it
+/// If the token is expired, it will automatically refresh it.
+/// The decrypted token is wiped from memory once the returned value is dropped.
 ///
 /// # Errors
 ///
 /// Returns an error if:
 /// - Token not found in database
+/// - Token lacks a required scope (`ReconnectRequiredError`)
 /// - Token refresh fails
 /// - Decryption fails
 /// - Database operation fails
@@ -161,16 +301,75 @@ pub async fn get_valid_token(
     db_connection: &DatabaseConnection,
     user_id: uuid::Uuid,
     provider: OAuthProvider,
-    encryption: &EncryptionService,
-) -> Result<String, Box<dyn std::error::Error>> {
+    encryption: &dyn TokenCrypto,
+) -> Result<SecretToken, Box<dyn std::error::Error>> {
     let token = get_oauth_token_by_provider(db_connection, user_id, provider).await?;
 
     let token = token.ok_or("OAuth token not found for user and provider")?;
+    check_granted_scopes(&token, provider)?;
+
+    match unexpired_access_token(&token, encryption)? {
+        Some(access_token) => Ok(access_token),
+        None => refresh_token(db_connection, &token, provider, encryption).await,
+    }
+}
+
+/// The stored token was granted fewer scopes than the integration needs
+///
+/// Happens when the user unticked a permission on the provider's consent screen.
+/// Only reconnecting the provider grants the missing scope.
+#[derive(Debug, thiserror::Error)]
+#[error("{provider} authorization is missing the '{missing_scope}' scope, reconnect {provider} to grant it")]
+pub struct ReconnectRequiredError {
+    pub provider: OAuthProvider,
+    pub missing_scope: String,
+}
+
+/// Checks a stored token was granted every scope the provider integration needs
+///
+/// Tokens stored before scopes were recorded carry none and are let through.
+///
+/// # Errors
+///
+/// Returns `ReconnectRequiredError` naming the first missing scope
+fn check_granted_scopes(
+    token: &oauth_token::Model,
+    provider: OAuthProvider,
+) -> Result<(), ReconnectRequiredError> {
+    let Some(granted) = &token.scopes else {
+        return Ok(());
+    };
+
+    match provider
+        .required_scopes()
+        .iter()
+        .find(|required| !granted.iter().any(|scope| scope == *required))
+    {
+        Some(missing) => Err(ReconnectRequiredError {
+            provider,
+            missing_scope: (*missing).to_string(),
+        }),
+        None => Ok(()),
+    }
+}
 
+/// Decrypts a stored access token if it has not expired yet
+///
+/// Returns `None` when the token is expired but can be refreshed.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Token expired and no refresh token is available
+/// - Decryption fails
+fn unexpired_access_token(
+    token: &oauth_token::Model,
+    encryption: &dyn TokenCrypto,
+) -> Result<Option<SecretToken>, Box<dyn std::error::Error>> {
     if let Some(expires_at) = token.expires_at {
         if expires_at < chrono::Utc::now() {
             if token.refresh_token.is_some() {
-                return refresh_token(db_connection, &token, provider, encryption).await;
+                return Ok(None);
             }
             // Token expired and no refresh token available
             return Err("Token expired and no refresh token available".into());
@@ -178,8 +377,9 @@ pub async fn get_valid_token(
     }
 
     // Decrypt the access token before returning
-    let decrypted_token = encryption.decrypt(&token.access_token)?;
-    Ok(decrypted_token)
+    Ok(Some(SecretToken::new(
+        encryption.decrypt(&token.access_token)?,
+    )))
 }
 
 /// Refreshes an expired OAuth token
@@ -201,8 +401,8 @@ pub async fn refresh_token(
     db_connection: &DatabaseConnection,
     token: &oauth_token::Model,
     provider: OAuthProvider,
-    encryption: &EncryptionService,
-) -> Result<String, Box<dyn std::error::Error>> {
+    encryption: &dyn TokenCrypto,
+) -> Result<SecretToken, Box<dyn std::error::Error>> {
     let encrypted_refresh_token_str = token
         .refresh_token
         .as_ref()
@@ -213,17 +413,7 @@ pub async fn refresh_token(
     let refresh_token = RefreshToken::new(decrypted_refresh_token);
 
     let client_info = ClientInfo::from_provider(provider);
-    let oauth_client = build_oauth_client(&client_info);
-    let http_client = reqwest::ClientBuilder::new()
-        .redirect(reqwest::redirect::Policy::none())
-        .build()
-        .expect("Client should build");
-
-    let token_result = oauth_client
-        .exchange_refresh_token(&refresh_token)
-        .request_async(&http_client)
-        .await
-        .map_err(|e| format!("Token refresh request failed: {e}"))?;
+    let token_result = request_token_refresh(&client_info, &refresh_token).await?;
 
     // Encrypt the new tokens before storing
     let encrypted_access_token = encryption.encrypt(token_result.access_token().secret())?;
@@ -243,18 +433,45 @@ pub async fn refresh_token(
                 + chrono::Duration::from_std(dur).expect("Token expiry duration out of range");
             expiry.into()
         }),
-        Some(
-            client_info
-                .scopes
-                .iter()
-                .map(|s| s.as_ref().to_string())
-                .collect(),
-        ),
+        // A refresh keeps the scopes granted at authorization
+        token.scopes.clone().or_else(|| {
+            Some(
+                client_info
+                    .scopes
+                    .iter()
+                    .map(|s| s.as_ref().to_string())
+                    .collect(),
+            )
+        }),
     )
     .await?;
 
     // Return the decrypted access token
-    Ok(token_result.access_token().secret().clone())
+    Ok(SecretToken::new(
+        token_result.access_token().secret().clone(),
+    ))
+}
+
+/// Exchanges a refresh token at the provider's token endpoint
+///
+/// # Errors
+///
+/// Returns an error if the token refresh request fails
+///
+/// # Panics
+///
+/// Panics if the HTTP client fails to build (should never happen with default config)
+pub async fn request_token_refresh(
+    client_info: &ClientInfo,
+    refresh_token: &RefreshToken,
+) -> Result<BasicTokenResponse, Box<dyn std::error::Error>> {
+    let token_result = build_oauth_client(client_info)
+        .exchange_refresh_token(refresh_token)
+        .request_async(&build_http_client())
+        .await
+        .map_err(|e| format!("Token refresh request failed: {e}"))?;
+
+    Ok(token_result)
 }
 
 /// Checks if a user has connected an OAuth provider
@@ -275,4 +492,150 @@ pub async fn is_oauth_provider_connected(
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+    use crate::crypto::PassthroughCrypto;
+
+    fn make_token(
+        expires_in: Option<chrono::Duration>,
+        refresh_token: Option<&str>,
+    ) -> oauth_token::Model {
+        let now = chrono::Utc::now();
+        oauth_token::Model {
+            id: uuid::Uuid::new_v4(),
+            user_id: uuid::Uuid::new_v4(),
+            provider: OAuthProvider::Strava.to_string(),
+            access_token: "plain-access-token".to_string(),
+            refresh_token: refresh_token.map(str::to_string),
+            expires_at: expires_in.map(|d| (now + d).into()),
+            scopes: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+        }
+    }
+
+    #[test]
+    fn test_unexpired_token_is_decrypted_without_key_file() {
+        let token = make_token(Some(chrono::Duration::hours(1)), Some("refresh"));
+
+        let access_token = unexpired_access_token(&token, &PassthroughCrypto).unwrap();
+
+        assert_eq!(access_token.as_deref(), Some("plain-access-token"));
+    }
+
+    #[test]
+    fn test_token_without_expiry_is_used_as_is() {
+        let token = make_token(None, None);
+
+        let access_token = unexpired_access_token(&token, &PassthroughCrypto).unwrap();
+
+        assert_eq!(access_token.as_deref(), Some("plain-access-token"));
+    }
+
+    #[test]
+    fn test_expired_token_with_refresh_token_needs_refresh() {
+        let token = make_token(Some(chrono::Duration::minutes(-5)), Some("refresh"));
+
+        let access_token = unexpired_access_token(&token, &PassthroughCrypto).unwrap();
+
+        assert_eq!(access_token, None);
+    }
+
+    #[test]
+    fn test_expired_token_without_refresh_token_fails() {
+        let token = make_token(Some(chrono::Duration::minutes(-5)), None);
+
+        let result = unexpired_access_token(&token, &PassthroughCrypto);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Token expired and no refresh token available"
+        );
+    }
+
+    #[test]
+    fn test_passthrough_crypto_round_trips() {
+        let crypto: &dyn TokenCrypto = &PassthroughCrypto;
+        let encrypted = crypto.encrypt("secret").unwrap();
+
+        assert_eq!(crypto.decrypt(&encrypted).unwrap(), "secret");
+    }
+
+    #[test]
+    fn test_token_lacking_required_scope_requires_reconnect() {
+        let token = oauth_token::Model {
+            scopes: Some(vec!["read".to_string(), "activity:read".to_string()]),
+            ..make_token(Some(chrono::Duration::hours(1)), Some("refresh"))
+        };
+
+        let error = check_granted_scopes(&token, OAuthProvider::Strava).unwrap_err();
+
+        assert_eq!(error.missing_scope, "activity:read_all");
+        assert_eq!(
+            error.to_string(),
+            "strava authorization is missing the 'activity:read_all' scope, reconnect strava to grant it"
+        );
+    }
+
+    #[test]
+    fn test_token_with_required_scope_or_unknown_scopes_is_accepted() {
+        let granted = oauth_token::Model {
+            scopes: Some(vec!["read".to_string(), "activity:read_all".to_string()]),
+            ..make_token(None, None)
+        };
+        let legacy = make_token(None, None);
+
+        assert!(check_granted_scopes(&granted, OAuthProvider::Strava).is_ok());
+        assert!(check_granted_scopes(&legacy, OAuthProvider::Strava).is_ok());
+    }
+
+    #[test]
+    fn test_parse_scopes() {
+        assert_eq!(
+            parse_scopes("read,activity:read"),
+            vec!["read", "activity:read"]
+        );
+        assert_eq!(
+            parse_scopes("user-read-recently-played user-read-email"),
+            vec!["user-read-recently-played", "user-read-email"]
+        );
+        assert!(parse_scopes("").is_empty());
+    }
+
+    fn client_info(provider: OAuthProvider) -> ClientInfo {
+        ClientInfo::new(
+            provider,
+            "client-id",
+            "client-secret",
+            "https://provider.test/authorize",
+            "https://provider.test/token",
+            "http://localhost:3000/api/oauth/callback",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_extra_scopes_are_merged_after_the_defaults() {
+        let extra = ["read".to_string(), "activity:read_all".to_string()];
+
+        let scopes = requested_scopes(&client_info(OAuthProvider::Strava), &extra).unwrap();
+
+        assert_eq!(scopes, vec!["activity:read_all", "read"]);
+    }
+
+    #[test]
+    fn test_scope_outside_the_allowlist_is_rejected() {
+        let extra = ["activity:write".to_string()];
+
+        let error = requested_scopes(&client_info(OAuthProvider::Strava), &extra).unwrap_err();
+
+        assert_eq!(error.scope, "activity:write");
+        assert_eq!(
+            error.to_string(),
+            "Scope 'activity:write' cannot be requested from strava"
+        );
+        // Allowlists are per provider
+        let extra = ["read".to_string()];
+        assert!(requested_scopes(&client_info(OAuthProvider::Spotify), &extra).is_err());
+    }
+}
@@ -1,18 +1,42 @@
 use moka::sync::Cache;
+use sha2::{Digest, Sha256};
 use std::time::Duration;
 use uuid::Uuid;
 
 use crate::config::OAuthProvider;
+use crate::crypto::constant_time_eq;
+
+/// Random bytes in an OAuth CSRF `state` token
+///
+/// 32 bytes give 256 bits of entropy, base64url-encoded into 43 characters, far
+/// beyond what can be guessed within the 10 minute session lifetime.
+pub const CSRF_TOKEN_BYTES: u32 = 32;
 
 #[derive(Clone)]
 pub struct OAuthState {
     pub pkce_verifier: String,
     pub provider: OAuthProvider,
     pub user_id: Uuid,
+    /// Scopes requested when the flow started
+    pub scopes: Vec<String>,
+    /// Allowed frontend origin that started the flow, `None` for the default one
+    pub return_origin: Option<String>,
 }
 
+/// Pending OAuth session, kept with the CSRF token it was issued for
+#[derive(Clone)]
+struct PendingSession {
+    csrf_token: String,
+    state: OAuthState,
+}
+
+/// Pending OAuth flows, keyed by the SHA-256 digest of their CSRF token
+///
+/// Keying by digest keeps the cache lookup from comparing the secret itself; the
+/// token is then checked with `constant_time_eq`, so a callback's timing does not
+/// reveal how close a forged `state` came to a real one.
 pub struct OAuthSessionManager {
-    cache: Cache<String, OAuthState>,
+    cache: Cache<[u8; 32], PendingSession>,
 }
 
 impl Default for OAuthSessionManager {
@@ -33,11 +57,59 @@ impl OAuthSessionManager {
     }
 
     pub fn store(&self, csrf_token: String, state: OAuthState) {
-        self.cache.insert(csrf_token, state);
+        self.cache.insert(
+            token_digest(&csrf_token),
+            PendingSession { csrf_token, state },
+        );
     }
 
+    /// Removes and returns the session issued for `csrf_token`, if any
     #[must_use]
     pub fn consume(&self, csrf_token: &str) -> Option<OAuthState> {
-        self.cache.remove(csrf_token)
+        let session = self.cache.remove(&token_digest(csrf_token))?;
+        constant_time_eq(session.csrf_token.as_bytes(), csrf_token.as_bytes())
+            .then_some(session.state)
+    }
+}
+
+fn token_digest(csrf_token: &str) -> [u8; 32] {
+    Sha256::digest(csrf_token.as_bytes()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> OAuthState {
+        OAuthState {
+            pkce_verifier: "verifier".to_string(),
+            provider: OAuthProvider::Strava,
+            user_id: Uuid::new_v4(),
+            scopes: vec!["activity:read_all".to_string()],
+            return_origin: None,
+        }
+    }
+
+    #[test]
+    fn test_consume_returns_session_once() {
+        let sessions = OAuthSessionManager::new();
+        let issued = state();
+        sessions.store("token-abc".to_string(), issued.clone());
+
+        let consumed = sessions.consume("token-abc").unwrap();
+
+        assert_eq!(consumed.user_id, issued.user_id);
+        assert!(sessions.consume("token-abc").is_none());
+    }
+
+    #[test]
+    fn test_consume_rejects_near_miss_token() {
+        let sessions = OAuthSessionManager::new();
+        sessions.store("token-abc".to_string(), state());
+
+        assert!(sessions.consume("token-abd").is_none());
+        assert!(sessions.consume("token-ab").is_none());
+        // A failed guess does not burn the real session
+        assert!(sessions.consume("token-abc").is_some());
     }
 }
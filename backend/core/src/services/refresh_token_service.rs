@@ -0,0 +1,277 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use rand::{rng, RngCore};
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbErr};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::config::env_or_default;
+use crate::database::{
+    create_refresh_token, get_refresh_token_by_hash, refresh_token, revoke_refresh_token,
+    run_in_transaction,
+};
+
+/// Default "remember me" lifetime of a refresh token
+pub const DEFAULT_REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+/// Random bytes per refresh token (256 bits)
+const REFRESH_TOKEN_BYTES: usize = 32;
+
+/// Errors that can occur when redeeming a refresh token
+#[derive(Debug, thiserror::Error)]
+pub enum RefreshTokenError {
+    #[error("Invalid refresh token")]
+    NotFound,
+
+    #[error("Refresh token has been revoked")]
+    Revoked,
+
+    #[error("Refresh token expired")]
+    Expired,
+
+    #[error("Database error: {0}")]
+    Database(#[from] DbErr),
+}
+
+/// A freshly issued refresh token; the plaintext is only ever returned to the client
+#[derive(Debug, Clone)]
+pub struct IssuedRefreshToken {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Issues a new long-lived refresh token for a user ("remember me")
+///
+/// Only the SHA-256 hash of the token is stored. Lifetime is read from
+/// `REFRESH_TOKEN_TTL_DAYS` (default: 30 days).
+///
+/// # Errors
+///
+/// Returns an error if the database insert fails
+pub async fn issue_refresh_token<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+) -> Result<IssuedRefreshToken, DbErr> {
+    let token = generate_refresh_token();
+    let expires_at = Utc::now() + refresh_token_ttl();
+
+    create_refresh_token(db, user_id, hash_refresh_token(&token), expires_at.into()).await?;
+
+    info!(user_id = %user_id, "Issued refresh token");
+
+    Ok(IssuedRefreshToken { token, expires_at })
+}
+
+/// Redeems a refresh token, rotating it for a new one
+///
+/// The redeemed token is revoked, so each refresh token can be used exactly once.
+/// Revoking it and issuing the replacement run in one transaction, so a failed
+/// insert doesn't leave the user without any valid token.
+///
+/// # Returns
+///
+/// The owning user ID and the replacement refresh token
+///
+/// # Errors
+///
+/// Returns `RefreshTokenError` if the token is unknown, revoked or expired,
+/// or if a database operation fails
+pub async fn redeem_refresh_token(
+    db: &DatabaseConnection,
+    token: &str,
+) -> Result<(Uuid, IssuedRefreshToken), RefreshTokenError> {
+    let record = get_refresh_token_by_hash(db, &hash_refresh_token(token))
+        .await?
+        .ok_or(RefreshTokenError::NotFound)?;
+
+    check_refresh_token(&record, Utc::now()).inspect_err(|e| {
+        warn!(user_id = %record.user_id, error = %e, "Rejected refresh token");
+    })?;
+
+    let (token_id, user_id) = (record.id, record.user_id);
+    let replacement = run_in_transaction(db, move |transaction| {
+        Box::pin(async move {
+            // Guards against two concurrent requests redeeming the same token
+            if !revoke_refresh_token(transaction, token_id).await? {
+                return Err(RefreshTokenError::Revoked);
+            }
+            Ok(issue_refresh_token(transaction, user_id).await?)
+        })
+    })
+    .await?;
+
+    Ok((user_id, replacement))
+}
+
+/// Checks that a stored refresh token is neither revoked nor expired
+fn check_refresh_token(
+    record: &refresh_token::Model,
+    now: DateTime<Utc>,
+) -> Result<(), RefreshTokenError> {
+    if record.revoked_at.is_some() {
+        return Err(RefreshTokenError::Revoked);
+    }
+    if record.expires_at <= now {
+        return Err(RefreshTokenError::Expired);
+    }
+    Ok(())
+}
+
+fn generate_refresh_token() -> String {
+    let mut bytes = [0u8; REFRESH_TOKEN_BYTES];
+    rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Refresh tokens are high-entropy random values, so a fast unsalted hash is enough
+fn hash_refresh_token(token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
+
+fn refresh_token_ttl() -> Duration {
+    Duration::days(env_or_default(
+        "REFRESH_TOKEN_TTL_DAYS",
+        DEFAULT_REFRESH_TOKEN_TTL_DAYS,
+        |days| *days > 0,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{DbBackend, MockDatabase, MockExecResult};
+
+    use super::*;
+
+    fn make_record(
+        expires_at: DateTime<Utc>,
+        revoked_at: Option<DateTime<Utc>>,
+    ) -> refresh_token::Model {
+        refresh_token::Model {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            token_hash: hash_refresh_token(&generate_refresh_token()),
+            expires_at: expires_at.into(),
+            revoked_at: revoked_at.map(Into::into),
+            created_at: Utc::now().into(),
+        }
+    }
+
+    #[test]
+    fn test_active_token_accepted() {
+        let now = Utc::now();
+        let record = make_record(now + Duration::days(30), None);
+
+        assert!(check_refresh_token(&record, now).is_ok());
+    }
+
+    #[test]
+    fn test_revoked_token_rejected() {
+        let now = Utc::now();
+        let record = make_record(now + Duration::days(30), Some(now - Duration::minutes(1)));
+
+        assert!(matches!(
+            check_refresh_token(&record, now),
+            Err(RefreshTokenError::Revoked)
+        ));
+    }
+
+    #[test]
+    fn test_expired_token_rejected() {
+        let now = Utc::now();
+        let record = make_record(now - Duration::seconds(1), None);
+
+        assert!(matches!(
+            check_refresh_token(&record, now),
+            Err(RefreshTokenError::Expired)
+        ));
+    }
+
+    #[test]
+    fn test_token_expires_exactly_at_expiry() {
+        let now = Utc::now();
+        let record = make_record(now, None);
+
+        assert!(matches!(
+            check_refresh_token(&record, now),
+            Err(RefreshTokenError::Expired)
+        ));
+    }
+
+    #[test]
+    fn test_generated_tokens_are_unique() {
+        let a = generate_refresh_token();
+        let b = generate_refresh_token();
+
+        assert_ne!(a, b);
+        // 32 bytes -> 43 base64url chars without padding
+        assert_eq!(a.len(), 43);
+    }
+
+    #[test]
+    fn test_hash_is_stable_and_not_plaintext() {
+        let token = generate_refresh_token();
+
+        assert_eq!(hash_refresh_token(&token), hash_refresh_token(&token));
+        assert_ne!(hash_refresh_token(&token), token);
+        assert_ne!(
+            hash_refresh_token(&token),
+            hash_refresh_token(&generate_refresh_token())
+        );
+    }
+
+    fn statements_of_rotation(db: DatabaseConnection) -> Vec<String> {
+        let log = db.into_transaction_log();
+        assert_eq!(log.len(), 2, "Token lookup, then one rotation transaction");
+        log[1]
+            .statements()
+            .iter()
+            .map(|statement| statement.sql.clone())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_rotation_revokes_and_issues_in_one_transaction() {
+        let token = generate_refresh_token();
+        let record = make_record(Utc::now() + Duration::days(30), None);
+        let user_id = record.user_id;
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![record.clone()]])
+            .append_exec_results([MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 1,
+            }])
+            .append_query_results([vec![record]])
+            .into_connection();
+
+        let (owner, _) = redeem_refresh_token(&db, &token).await.unwrap();
+
+        assert_eq!(owner, user_id);
+        let statements = statements_of_rotation(db);
+        assert_eq!(statements.len(), 4, "{statements:?}");
+        assert_eq!(statements[0], "BEGIN");
+        assert!(statements[1].starts_with(r#"UPDATE "refresh_token""#));
+        assert!(statements[2].starts_with(r#"INSERT INTO "refresh_token""#));
+        assert_eq!(statements[3], "COMMIT");
+    }
+
+    #[tokio::test]
+    async fn test_token_redeemed_concurrently_rolls_back_without_a_replacement() {
+        let token = generate_refresh_token();
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![make_record(Utc::now() + Duration::days(30), None)]])
+            .append_exec_results([MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 0,
+            }])
+            .into_connection();
+
+        let result = redeem_refresh_token(&db, &token).await;
+
+        assert!(matches!(result, Err(RefreshTokenError::Revoked)));
+        let statements = statements_of_rotation(db);
+        assert_eq!(statements.len(), 3, "{statements:?}");
+        assert!(statements[1].starts_with(r#"UPDATE "refresh_token""#));
+        assert_eq!(statements[2], "ROLLBACK");
+    }
+}
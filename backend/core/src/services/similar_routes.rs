@@ -0,0 +1,157 @@
+use sea_orm::{DatabaseConnection, DbErr};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::database::{activity, activity_repository, activity_stream_repository};
+use crate::geo::{route_similarity, RouteBounds, RouteSimilarity, MIN_BOUNDS_OVERLAP};
+
+/// Most candidate routes loaded and compared per request, by decreasing box overlap
+pub const MAX_ROUTE_CANDIDATES: usize = 20;
+
+/// Number of similar activities returned by default
+pub const DEFAULT_SIMILAR_ACTIVITIES_LIMIT: usize = 5;
+
+/// An activity run on the same course as another
+#[derive(Debug, Clone, Serialize)]
+pub struct SimilarActivity {
+    pub activity: activity::Model,
+    #[serde(flatten)]
+    pub similarity: RouteSimilarity,
+}
+
+/// Finds the user's activities run on the same course as `activity_id`, closest first
+///
+/// Candidates are picked from route extents computed in the database; only the
+/// `MAX_ROUTE_CANDIDATES` with the most overlapping boxes have their points loaded
+/// and compared. Activities without GPS points are never similar.
+///
+/// # Errors
+///
+/// Returns an error if a database query fails
+pub async fn find_similar_activities(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    activity_id: Uuid,
+    limit: usize,
+) -> Result<Vec<SimilarActivity>, DbErr> {
+    let route = load_route(db, activity_id).await?;
+    let Some(bounds) = RouteBounds::from_points(&route) else {
+        return Ok(Vec::new());
+    };
+    let all_bounds = activity_stream_repository::get_route_bounds_by_user(db, user_id).await?;
+
+    let mut matches = Vec::new();
+    for candidate_id in route_candidates(bounds, activity_id, all_bounds) {
+        let candidate = load_route(db, candidate_id).await?;
+        if let Some(similarity) =
+            route_similarity(&route, &candidate).filter(|similarity| similarity.is_similar())
+        {
+            matches.push((candidate_id, similarity));
+        }
+    }
+    matches.sort_by(|a, b| a.1.frechet_meters.total_cmp(&b.1.frechet_meters));
+    matches.truncate(limit);
+
+    let mut similar = Vec::with_capacity(matches.len());
+    for (candidate_id, similarity) in matches {
+        if let Some(activity) = activity_repository::get_activity_by_id(db, candidate_id).await? {
+            similar.push(SimilarActivity {
+                activity,
+                similarity,
+            });
+        }
+    }
+    Ok(similar)
+}
+
+/// Other activities whose route extent overlaps `bounds` enough to compare, most overlapping first
+fn route_candidates(
+    bounds: RouteBounds,
+    activity_id: Uuid,
+    all_bounds: Vec<(Uuid, f64, f64, f64, f64)>,
+) -> Vec<Uuid> {
+    let mut candidates: Vec<(Uuid, f64)> = all_bounds
+        .into_iter()
+        .filter(|(id, ..)| *id != activity_id)
+        .map(|(id, min_lat, max_lat, min_lng, max_lng)| {
+            let other = RouteBounds {
+                min_lat,
+                max_lat,
+                min_lng,
+                max_lng,
+            };
+            (id, bounds.overlap(other))
+        })
+        .filter(|(_, overlap)| *overlap >= MIN_BOUNDS_OVERLAP)
+        .collect();
+    candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+    candidates.truncate(MAX_ROUTE_CANDIDATES);
+    candidates.into_iter().map(|(id, _)| id).collect()
+}
+
+/// `(lat, lng)` points of an activity in time order, skipping points without coordinates
+async fn load_route(db: &DatabaseConnection, activity_id: Uuid) -> Result<Vec<(f64, f64)>, DbErr> {
+    Ok(
+        activity_stream_repository::get_activity_streams(db, activity_id)
+            .await?
+            .into_iter()
+            .filter_map(|point| Some((point.latitude?, point.longitude?)))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARK: RouteBounds = RouteBounds {
+        min_lat: 48.843,
+        max_lat: 48.857,
+        min_lng: 2.339,
+        max_lng: 2.361,
+    };
+
+    fn bounds_row(id: Uuid, lat_shift: f64) -> (Uuid, f64, f64, f64, f64) {
+        (
+            id,
+            PARK.min_lat + lat_shift,
+            PARK.max_lat + lat_shift,
+            PARK.min_lng,
+            PARK.max_lng,
+        )
+    }
+
+    #[test]
+    fn test_candidates_exclude_the_activity_and_distant_routes() {
+        let activity_id = Uuid::new_v4();
+        let same = Uuid::new_v4();
+        let shifted = Uuid::new_v4();
+        let elsewhere = Uuid::new_v4();
+
+        let candidates = route_candidates(
+            PARK,
+            activity_id,
+            vec![
+                bounds_row(activity_id, 0.0),
+                bounds_row(shifted, 0.003),
+                bounds_row(elsewhere, 0.05),
+                bounds_row(same, 0.0),
+            ],
+        );
+
+        // Most overlapping first
+        assert_eq!(candidates, vec![same, shifted]);
+    }
+
+    #[test]
+    fn test_candidates_are_capped() {
+        let rows = (0..MAX_ROUTE_CANDIDATES + 5)
+            .map(|_| bounds_row(Uuid::new_v4(), 0.0))
+            .collect();
+
+        assert_eq!(
+            route_candidates(PARK, Uuid::new_v4(), rows).len(),
+            MAX_ROUTE_CANDIDATES
+        );
+    }
+}
@@ -0,0 +1,588 @@
+use run_sous_bpm_integrations::{
+    common::IntegrationError,
+    spotify::{SpotifyApiClient, SpotifySearchParams, SpotifyTrack},
+};
+use sea_orm::{DatabaseConnection, DbErr};
+use serde::Serialize;
+use strsim::jaro_winkler;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::database::{
+    get_unenriched_tracks_by_user, mark_track_spotify_searched, set_track_spotify_match, track,
+};
+
+/// Lowest similarity, in [0, 1], at which a Spotify candidate is accepted
+///
+/// Below this the track is left unenriched for manual review rather than
+/// risk attaching the wrong tempo to it.
+pub const MIN_MATCH_CONFIDENCE: f64 = 0.9;
+
+/// Words introducing a featured artist
+const FEATURING_MARKERS: &[&str] = &["feat.", "feat", "ft.", "featuring"];
+
+/// Times a throttled search is retried before a batch enrichment stops
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+
+/// Longest wait in seconds honored for a single `Retry-After`, longer ones stop the batch
+const MAX_RATE_LIMIT_WAIT_SECONDS: i64 = 60;
+
+/// A Spotify candidate accepted for a track
+#[derive(Debug, Clone, Copy)]
+pub struct SpotifyMatch<'a> {
+    pub track: &'a SpotifyTrack,
+    /// Similarity of the weaker of title and artist, in [0, 1]
+    pub confidence: f64,
+}
+
+/// Result of enriching one track against Spotify
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnrichmentOutcome {
+    /// A candidate was accepted and recorded on the track
+    Matched { spotify_id: String, confidence: f64 },
+    /// No candidate reached `MIN_MATCH_CONFIDENCE`; the track is only marked as searched
+    Unmatched { best_confidence: Option<f64> },
+}
+
+/// Normalizes a track title for comparison
+///
+/// Lowercases, drops remaster and featured-artist annotations, whether
+/// bracketed (`(Remastered 2011)`, `[feat. X]`) or dash suffixes
+/// (`- Remastered 2009`), and collapses whitespace. Other brackets are kept,
+/// as in `(I Can't Get No) Satisfaction`.
+#[must_use]
+pub fn normalize_title(title: &str) -> String {
+    let lower = title.to_lowercase();
+    let mut stripped = strip_noise_brackets(&lower);
+    if let Some(dash) = stripped.find(" - ") {
+        if is_noise(&stripped[dash + 3..]) {
+            stripped.truncate(dash);
+        }
+    }
+    collapse_before_featuring(&stripped)
+}
+
+/// Normalizes an artist name for comparison, keeping only the main artist
+#[must_use]
+pub fn normalize_artist(artist: &str) -> String {
+    collapse_before_featuring(&strip_noise_brackets(&artist.to_lowercase()))
+}
+
+/// Scores a Spotify candidate against a track's artist and title
+///
+/// Both must match: the score is the lower of the title similarity and the
+/// best similarity among the candidate's artists (Jaro-Winkler on normalized text).
+#[must_use]
+pub fn match_confidence(artist_name: &str, track_name: &str, candidate: &SpotifyTrack) -> f64 {
+    let title = jaro_winkler(
+        &normalize_title(track_name),
+        &normalize_title(&candidate.name),
+    );
+    let artist = normalize_artist(artist_name);
+    let artist_score = candidate
+        .artists
+        .iter()
+        .map(|a| jaro_winkler(&artist, &normalize_artist(&a.name)))
+        .fold(0.0, f64::max);
+    title.min(artist_score)
+}
+
+/// Picks the most similar candidate, if it reaches `MIN_MATCH_CONFIDENCE`
+///
+/// On equal confidence the earlier candidate (Spotify's ranking) wins.
+#[must_use]
+pub fn best_spotify_match<'a>(
+    artist_name: &str,
+    track_name: &str,
+    candidates: &'a [SpotifyTrack],
+) -> Option<SpotifyMatch<'a>> {
+    best_candidate(artist_name, track_name, candidates)
+        .filter(|m| m.confidence >= MIN_MATCH_CONFIDENCE)
+}
+
+fn best_candidate<'a>(
+    artist_name: &str,
+    track_name: &str,
+    candidates: &'a [SpotifyTrack],
+) -> Option<SpotifyMatch<'a>> {
+    candidates
+        .iter()
+        .map(|candidate| SpotifyMatch {
+            track: candidate,
+            confidence: match_confidence(artist_name, track_name, candidate),
+        })
+        .fold(None, |best: Option<SpotifyMatch<'a>>, m| match best {
+            Some(b) if b.confidence >= m.confidence => Some(b),
+            _ => Some(m),
+        })
+}
+
+/// Searches Spotify for a track and records the best match on it
+///
+/// Low-confidence results are not recorded, so the track stays unenriched
+/// and can be reviewed by hand. Either way the track is marked as searched.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Spotify search request fails
+/// - Database update fails
+pub async fn enrich_track_from_spotify(
+    db: &DatabaseConnection,
+    spotify_client: &SpotifyApiClient,
+    access_token: &str,
+    track: &track::Model,
+) -> Result<EnrichmentOutcome, Box<dyn std::error::Error>> {
+    let candidates = spotify_client
+        .search_tracks(access_token, &search_params(track))
+        .await?
+        .tracks
+        .items;
+
+    Ok(record_best_match(db, track, &candidates).await?)
+}
+
+/// Counts of a batch enrichment run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct EnrichmentReport {
+    /// Tracks matched on Spotify and updated
+    pub matched: u32,
+    /// Tracks searched without a confident match, left for manual review and not searched again
+    pub unmatched: u32,
+    /// Tracks not searched because Spotify kept throttling; running again resumes with them
+    pub remaining: u32,
+}
+
+/// Enriches every track the user listened to that was never searched on Spotify
+///
+/// Searched tracks are marked, matched or not, so running it again is harmless,
+/// picks up where a previous run stopped and never pays for the same search twice. Throttled searches wait for Spotify's
+/// `Retry-After` and are retried; when Spotify keeps throttling, the run stops and
+/// the tracks left are reported as `remaining`.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Database query or update fails
+/// - A Spotify search fails for another reason than throttling
+pub async fn enrich_unenriched_tracks(
+    db: &DatabaseConnection,
+    spotify_client: &SpotifyApiClient,
+    access_token: &str,
+    user_id: Uuid,
+) -> Result<EnrichmentReport, Box<dyn std::error::Error>> {
+    let tracks = get_unenriched_tracks_by_user(db, user_id).await?;
+    let mut report = EnrichmentReport::default();
+
+    for (index, track) in tracks.iter().enumerate() {
+        let Some(candidates) = search_with_backoff(spotify_client, access_token, track).await?
+        else {
+            report.remaining = u32::try_from(tracks.len() - index).unwrap_or(u32::MAX);
+            warn!(
+                user_id = %user_id,
+                remaining = report.remaining,
+                "Spotify keeps throttling, stopping enrichment"
+            );
+            break;
+        };
+        match record_best_match(db, track, &candidates).await? {
+            EnrichmentOutcome::Matched { .. } => report.matched += 1,
+            EnrichmentOutcome::Unmatched { .. } => report.unmatched += 1,
+        }
+    }
+
+    info!(
+        user_id = %user_id,
+        matched = report.matched,
+        unmatched = report.unmatched,
+        remaining = report.remaining,
+        "Finished Spotify enrichment"
+    );
+    Ok(report)
+}
+
+fn search_params(track: &track::Model) -> SpotifySearchParams {
+    SpotifySearchParams::track(
+        &normalize_artist(&track.artist_name),
+        &normalize_title(&track.track_name),
+    )
+}
+
+/// Searches candidates for a track, waiting out Spotify's rate limit
+///
+/// Returns `None` when Spotify is still throttling after `MAX_RATE_LIMIT_RETRIES`
+/// retries, or asks to wait longer than `MAX_RATE_LIMIT_WAIT_SECONDS`.
+async fn search_with_backoff(
+    spotify_client: &SpotifyApiClient,
+    access_token: &str,
+    track: &track::Model,
+) -> Result<Option<Vec<SpotifyTrack>>, IntegrationError> {
+    let params = search_params(track);
+    let mut retries = 0;
+    loop {
+        match spotify_client.search_tracks(access_token, &params).await {
+            Ok(response) => return Ok(Some(response.tracks.items)),
+            Err(IntegrationError::RateLimited(resume_at)) => {
+                let wait = (resume_at - chrono::Utc::now()).max(chrono::Duration::zero());
+                if retries == MAX_RATE_LIMIT_RETRIES
+                    || wait > chrono::Duration::seconds(MAX_RATE_LIMIT_WAIT_SECONDS)
+                {
+                    return Ok(None);
+                }
+                retries += 1;
+                info!(
+                    track_id = %track.id,
+                    wait_seconds = wait.num_seconds(),
+                    retry = retries,
+                    "Spotify throttled the search, waiting before retrying"
+                );
+                tokio::time::sleep(wait.to_std().unwrap_or_default()).await;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Records the best of `candidates` on the track if it reaches `MIN_MATCH_CONFIDENCE`
+async fn record_best_match(
+    db: &DatabaseConnection,
+    track: &track::Model,
+    candidates: &[SpotifyTrack],
+) -> Result<EnrichmentOutcome, DbErr> {
+    let best = best_candidate(&track.artist_name, &track.track_name, candidates);
+    let Some(accepted) = best.filter(|m| m.confidence >= MIN_MATCH_CONFIDENCE) else {
+        info!(
+            track_id = %track.id,
+            candidates = candidates.len(),
+            best_confidence = ?best.map(|m| m.confidence),
+            "No confident Spotify match, leaving track for manual review"
+        );
+        mark_track_spotify_searched(db, track.id).await?;
+        return Ok(EnrichmentOutcome::Unmatched {
+            best_confidence: best.map(|m| m.confidence),
+        });
+    };
+
+    let spotify_id = accepted.track.id.clone();
+    set_track_spotify_match(db, track.id, spotify_id.clone(), accepted.confidence).await?;
+
+    info!(
+        track_id = %track.id,
+        spotify_id = %spotify_id,
+        confidence = accepted.confidence,
+        "Matched track on Spotify"
+    );
+    Ok(EnrichmentOutcome::Matched {
+        spotify_id,
+        confidence: accepted.confidence,
+    })
+}
+
+/// Whether bracket or suffix content is a remaster or featured-artist annotation
+fn is_noise(annotation: &str) -> bool {
+    let annotation = annotation.trim();
+    annotation.contains("remaster")
+        || annotation
+            .split_whitespace()
+            .next()
+            .is_some_and(|word| FEATURING_MARKERS.contains(&word))
+}
+
+/// Removes `(...)` and `[...]` groups holding remaster or featured-artist annotations
+fn strip_noise_brackets(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find(['(', '[']) {
+        let close_char = if rest[open..].starts_with('(') {
+            ')'
+        } else {
+            ']'
+        };
+        let Some(close) = rest[open..].find(close_char).map(|i| open + i) else {
+            break;
+        };
+        out.push_str(&rest[..open]);
+        let group = &rest[open..=close];
+        if !is_noise(&group[1..group.len() - 1]) {
+            out.push_str(group);
+        }
+        rest = &rest[close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Drops an unbracketed featuring clause and collapses whitespace
+fn collapse_before_featuring(text: &str) -> String {
+    text.split_whitespace()
+        .take_while(|word| !FEATURING_MARKERS.contains(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    use run_sous_bpm_integrations::common::{AuthenticatedClient, IntegrationClient};
+    use run_sous_bpm_integrations::spotify::{SpotifyAlbum, SpotifyArtist, SpotifyExternalUrls};
+    use run_sous_bpm_integrations::test_support::{
+        json_response, spawn_mock_server, SPOTIFY_SEARCH_EMPTY, SPOTIFY_SEARCH_QUEEN,
+    };
+    use sea_orm::{DbBackend, MockDatabase, MockExecResult};
+
+    use super::*;
+    use crate::test_support::make_track;
+
+    /// Mock Spotify search: finds Queen only, throttling the first `throttled` requests
+    async fn spawn_mock_spotify(throttled: u32) -> (SpotifyApiClient, Arc<AtomicU32>) {
+        let requests = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&requests);
+        let base_url = spawn_mock_server(move |request| {
+            if counter.fetch_add(1, Ordering::SeqCst) < throttled {
+                return "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string();
+            }
+            if request.to_lowercase().contains("queen") {
+                json_response(SPOTIFY_SEARCH_QUEEN)
+            } else {
+                json_response(SPOTIFY_SEARCH_EMPTY)
+            }
+        })
+        .await;
+
+        let client = SpotifyApiClient::new(
+            IntegrationClient::new(Arc::new(AuthenticatedClient::new())),
+            format!("{base_url}/v1"),
+        );
+        (client, requests)
+    }
+
+    fn stored_track(artist_name: &str, track_name: &str) -> track::Model {
+        track::Model {
+            artist_name: artist_name.to_string(),
+            track_name: track_name.to_string(),
+            ..make_track()
+        }
+    }
+
+    fn candidate(id: &str, name: &str, artists: &[&str]) -> SpotifyTrack {
+        SpotifyTrack {
+            id: id.to_string(),
+            name: name.to_string(),
+            artists: artists
+                .iter()
+                .map(|artist| SpotifyArtist {
+                    id: format!("artist-{artist}"),
+                    name: (*artist).to_string(),
+                })
+                .collect(),
+            album: SpotifyAlbum {
+                id: "album".to_string(),
+                name: "Album".to_string(),
+                images: Vec::new(),
+            },
+            external_urls: SpotifyExternalUrls {
+                spotify: format!("https://open.spotify.com/track/{id}"),
+            },
+            duration_ms: 200_000,
+        }
+    }
+
+    #[test]
+    fn test_normalize_strips_remaster_annotations() {
+        assert_eq!(
+            normalize_title("Don't Stop Me Now - Remastered 2011"),
+            "don't stop me now"
+        );
+        assert_eq!(
+            normalize_title("Bohemian Rhapsody [Remastered 2011]"),
+            "bohemian rhapsody"
+        );
+        assert_eq!(normalize_title("Heroes (2017 Remaster)  "), "heroes");
+    }
+
+    #[test]
+    fn test_normalize_strips_featured_artists() {
+        assert_eq!(
+            normalize_title("Get Lucky (feat. Pharrell Williams & Nile Rodgers)"),
+            "get lucky"
+        );
+        assert_eq!(normalize_title("Stay ft. Justin Bieber"), "stay");
+        assert_eq!(
+            normalize_artist("Daft Punk featuring Pharrell Williams"),
+            "daft punk"
+        );
+    }
+
+    #[test]
+    fn test_normalize_keeps_meaningful_brackets_and_dashes() {
+        assert_eq!(
+            normalize_title("(I Can't Get No) Satisfaction"),
+            "(i can't get no) satisfaction"
+        );
+        assert_eq!(
+            normalize_title("Symphony No. 9 - Ode to Joy"),
+            "symphony no. 9 - ode to joy"
+        );
+    }
+
+    #[test]
+    fn test_messy_title_matches_clean_candidate() {
+        let candidates = [
+            candidate("cover", "Get Lucky", &["Karaoke Stars"]),
+            candidate("original", "Get Lucky", &["Daft Punk", "Pharrell Williams"]),
+        ];
+
+        let best = best_spotify_match(
+            "Daft Punk feat. Pharrell Williams",
+            "Get Lucky (feat. Pharrell Williams & Nile Rodgers)",
+            &candidates,
+        )
+        .expect("original should be accepted");
+
+        assert_eq!(best.track.id, "original");
+    }
+
+    #[test]
+    fn test_near_identical_title_is_accepted_below_full_confidence() {
+        let candidates = [candidate("queen", "Don't Stop Me Now", &["Queen"])];
+
+        let best = best_spotify_match("Queen", "Dont Stop Me Now", &candidates)
+            .expect("missing apostrophe should still match");
+
+        assert!(best.confidence >= MIN_MATCH_CONFIDENCE);
+        assert!(best.confidence < 1.0);
+    }
+
+    #[test]
+    fn test_remaster_suffix_matches_exactly() {
+        let candidates = [candidate(
+            "queen",
+            "Don't Stop Me Now - Remastered 2011",
+            &["Queen"],
+        )];
+
+        let best = best_spotify_match("Queen", "Don't Stop Me Now", &candidates).unwrap();
+
+        assert_eq!(best.track.id, "queen");
+        assert!((best.confidence - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_low_confidence_candidates_are_rejected() {
+        let candidates = [
+            candidate("other-song", "Somebody to Love", &["Queen"]),
+            candidate("other-artist", "Don't Stop Me Now", &["McFly"]),
+        ];
+
+        assert!(best_spotify_match("Queen", "Don't Stop Me Now", &candidates).is_none());
+        assert!(best_spotify_match("Queen", "Don't Stop Me Now", &[]).is_none());
+    }
+
+    #[test]
+    fn test_equal_confidence_keeps_spotify_ranking() {
+        let candidates = [
+            candidate("first", "Heroes", &["David Bowie"]),
+            candidate("second", "Heroes - 2017 Remaster", &["David Bowie"]),
+        ];
+
+        let best = best_spotify_match("David Bowie", "Heroes", &candidates).unwrap();
+
+        assert_eq!(best.track.id, "first");
+    }
+
+    #[tokio::test]
+    async fn test_batch_enrichment_counts_matched_and_unmatched_tracks() {
+        let (client, requests) = spawn_mock_spotify(0).await;
+        let queen = stored_track("Queen", "Don't Stop Me Now");
+        let obscure = stored_track("The Basement Tapes", "Demo 3");
+        let matched = track::Model {
+            spotify_id: Some("7hQJA50XrCWABAu5v6QZ4i".to_string()),
+            spotify_match_confidence: Some(1.0),
+            ..queen.clone()
+        };
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([
+                // Unenriched tracks, then the matched track's lookup and update
+                vec![queen.clone(), obscure],
+                vec![queen],
+                vec![matched],
+            ])
+            // The unmatched track is marked as searched
+            .append_exec_results([MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 1,
+            }])
+            .into_connection();
+
+        let report = enrich_unenriched_tracks(&db, &client, "token", Uuid::new_v4())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            report,
+            EnrichmentReport {
+                matched: 1,
+                unmatched: 1,
+                remaining: 0,
+            }
+        );
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        let log = format!("{:?}", db.into_transaction_log());
+        assert_eq!(log.matches("UPDATE").count(), 2, "{log}");
+        assert_eq!(
+            log.matches(r#"\"spotify_searched_at\" ="#).count(),
+            2,
+            "{log}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_throttled_search_is_retried() {
+        let (client, requests) = spawn_mock_spotify(1).await;
+        let queen = stored_track("Queen", "Don't Stop Me Now");
+        let matched = track::Model {
+            spotify_id: Some("7hQJA50XrCWABAu5v6QZ4i".to_string()),
+            ..queen.clone()
+        };
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![queen.clone()], vec![queen], vec![matched]])
+            .into_connection();
+
+        let report = enrich_unenriched_tracks(&db, &client, "token", Uuid::new_v4())
+            .await
+            .unwrap();
+
+        assert_eq!(report.matched, 1);
+        assert_eq!(report.remaining, 0);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_persistent_throttling_leaves_tracks_for_the_next_run() {
+        let (client, requests) = spawn_mock_spotify(u32::MAX).await;
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![
+                stored_track("Queen", "Don't Stop Me Now"),
+                stored_track("Queen", "Somebody to Love"),
+            ]])
+            .into_connection();
+
+        let report = enrich_unenriched_tracks(&db, &client, "token", Uuid::new_v4())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            report,
+            EnrichmentReport {
+                matched: 0,
+                unmatched: 0,
+                remaining: 2,
+            }
+        );
+        assert_eq!(requests.load(Ordering::SeqCst), MAX_RATE_LIMIT_RETRIES + 1);
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use serde::Serialize;
+use strum::IntoStaticStr;
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+/// Events buffered per subscriber before slow ones start missing events
+const SYNC_EVENT_CAPACITY: usize = 64;
+
+/// Notification that a sync finished, pushed to the user's open event streams
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, IntoStaticStr)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum SyncEvent {
+    /// Activities were synced from Strava
+    ActivitySynced { count: usize },
+    /// Stream points of an activity were stored
+    StreamsSynced { activity_id: Uuid, points: usize },
+}
+
+impl SyncEvent {
+    /// Event name, e.g. `activity_synced`
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        self.into()
+    }
+}
+
+/// Broadcasts sync events from the sync services to subscribed clients
+///
+/// Each user gets a channel of their own, created on first subscription and
+/// dropped once nobody listens, so a user's events never wake another user's
+/// subscribers nor make them lag.
+#[derive(Debug, Clone, Default)]
+pub struct SyncEventBus {
+    channels: Arc<Mutex<HashMap<Uuid, broadcast::Sender<SyncEvent>>>>,
+}
+
+impl SyncEventBus {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publishes an event for a user; dropped if nobody is listening
+    pub fn publish(&self, user_id: Uuid, event: SyncEvent) {
+        let mut channels = self.channels.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(sender) = channels.get(&user_id) {
+            // Sending only fails once every subscriber of the user is gone
+            if sender.send(event).is_err() {
+                channels.remove(&user_id);
+            }
+        }
+    }
+
+    /// Subscribes to the events of a user, starting from now
+    #[must_use]
+    pub fn subscribe(&self, user_id: Uuid) -> UserSyncEvents {
+        let mut channels = self.channels.lock().unwrap_or_else(PoisonError::into_inner);
+        let receiver = channels
+            .entry(user_id)
+            .or_insert_with(|| broadcast::channel(SYNC_EVENT_CAPACITY).0)
+            .subscribe();
+        UserSyncEvents { receiver }
+    }
+}
+
+/// Receiving end of a user's sync events
+#[derive(Debug)]
+pub struct UserSyncEvents {
+    receiver: broadcast::Receiver<SyncEvent>,
+}
+
+impl UserSyncEvents {
+    /// Waits for the next event of the user
+    ///
+    /// Events missed by a lagging subscriber are skipped. Returns `None` once
+    /// the bus is dropped.
+    pub async fn recv(&mut self) -> Option<SyncEvent> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) => return Some(event),
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_subscriber_receives_own_events_only() {
+        let bus = SyncEventBus::new();
+        let user_id = Uuid::new_v4();
+        let mut events = bus.subscribe(user_id);
+
+        bus.publish(Uuid::new_v4(), SyncEvent::ActivitySynced { count: 3 });
+        bus.publish(user_id, SyncEvent::ActivitySynced { count: 5 });
+
+        assert_eq!(
+            events.recv().await,
+            Some(SyncEvent::ActivitySynced { count: 5 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_closed_bus_ends_subscription() {
+        let bus = SyncEventBus::new();
+        let mut events = bus.subscribe(Uuid::new_v4());
+
+        drop(bus);
+
+        assert_eq!(events.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_users_get_separate_channels() {
+        let bus = SyncEventBus::new();
+        let user_id = Uuid::new_v4();
+        let mut events = bus.subscribe(user_id);
+
+        // Far more events for someone else than a channel buffers
+        let other_user_id = Uuid::new_v4();
+        let _other_events = bus.subscribe(other_user_id);
+        for count in 0..SYNC_EVENT_CAPACITY * 2 {
+            bus.publish(other_user_id, SyncEvent::ActivitySynced { count });
+        }
+        bus.publish(user_id, SyncEvent::ActivitySynced { count: 1 });
+
+        assert_eq!(
+            events.receiver.len(),
+            1,
+            "Only the user's own event is queued"
+        );
+        assert_eq!(
+            events.recv().await,
+            Some(SyncEvent::ActivitySynced { count: 1 })
+        );
+    }
+
+    #[test]
+    fn test_channel_is_dropped_once_its_subscribers_are_gone() {
+        let bus = SyncEventBus::new();
+        let user_id = Uuid::new_v4();
+        drop(bus.subscribe(user_id));
+
+        bus.publish(user_id, SyncEvent::ActivitySynced { count: 1 });
+
+        assert!(bus.channels.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_publishing_without_subscribers_is_ignored() {
+        SyncEventBus::new().publish(Uuid::new_v4(), SyncEvent::ActivitySynced { count: 1 });
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let activity_id = Uuid::new_v4();
+        let event = SyncEvent::StreamsSynced {
+            activity_id,
+            points: 120,
+        };
+
+        assert_eq!(event.name(), "streams_synced");
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({
+                "type": "streams_synced",
+                "activity_id": activity_id,
+                "points": 120
+            })
+        );
+    }
+}
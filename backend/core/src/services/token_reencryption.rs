@@ -0,0 +1,288 @@
+use run_sous_bpm_integrations::common::SecretToken;
+use sea_orm::{DatabaseConnection, DbErr};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::crypto::{CryptoError, EncryptedPayload, TokenCrypto, CURRENT_VERSION};
+use crate::database::{
+    get_oauth_tokens_after, oauth_token, replace_oauth_token_payloads, run_in_transaction,
+};
+
+/// OAuth tokens read per batch; each batch is written in its own transaction
+pub const REENCRYPT_BATCH_SIZE: usize = 100;
+
+/// Outcome of a re-encryption run over every stored OAuth token
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ReencryptionReport {
+    /// Tokens inspected
+    pub scanned: u64,
+    /// Tokens rewritten with the current payload version
+    pub migrated: u64,
+    /// Tokens already on the current version, or refreshed while the run was going
+    pub up_to_date: u64,
+    /// Tokens that could not be decrypted or re-encrypted, left untouched
+    pub failed: u64,
+}
+
+/// Re-encrypts every stored OAuth token that is not on the current payload version
+///
+/// Tokens are read in batches of `REENCRYPT_BATCH_SIZE` and each batch is written
+/// in one transaction. A token refreshed between the read and the write is left
+/// alone, since the refresh already stored it with the current version. Tokens
+/// `encryption` cannot read are counted in `failed` and logged, so one bad row
+/// does not stop the run.
+///
+/// # Errors
+///
+/// Returns an error if a database query or transaction fails. Batches written
+/// before the failure stay migrated, so the run can simply be repeated.
+pub async fn reencrypt_all_oauth_tokens(
+    db: &DatabaseConnection,
+    encryption: &dyn TokenCrypto,
+) -> Result<ReencryptionReport, DbErr> {
+    let mut report = ReencryptionReport::default();
+    let mut after_id = None;
+
+    loop {
+        let batch = get_oauth_tokens_after(db, after_id, REENCRYPT_BATCH_SIZE as u64).await?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        after_id = Some(last.id);
+        let is_last_batch = batch.len() < REENCRYPT_BATCH_SIZE;
+        report.scanned += batch.len() as u64;
+
+        let mut updates = Vec::new();
+        for token in batch {
+            if is_on_current_version(&token) {
+                report.up_to_date += 1;
+                continue;
+            }
+            match reencrypt_token(&token, encryption) {
+                Ok((access_token, refresh_token)) => {
+                    updates.push((token.id, token.access_token, access_token, refresh_token));
+                }
+                Err(error) => {
+                    warn!(
+                        token_id = %token.id,
+                        user_id = %token.user_id,
+                        provider = %token.provider,
+                        error = %error,
+                        "Failed to re-encrypt OAuth token"
+                    );
+                    report.failed += 1;
+                }
+            }
+        }
+
+        if !updates.is_empty() {
+            let attempted = updates.len() as u64;
+            let migrated = run_in_transaction(db, move |transaction| {
+                Box::pin(async move {
+                    let mut migrated = 0;
+                    for (token_id, current_access_token, access_token, refresh_token) in updates {
+                        if replace_oauth_token_payloads(
+                            transaction,
+                            token_id,
+                            &current_access_token,
+                            access_token,
+                            refresh_token,
+                        )
+                        .await?
+                        {
+                            migrated += 1;
+                        }
+                    }
+                    Ok::<u64, DbErr>(migrated)
+                })
+            })
+            .await?;
+            report.migrated += migrated;
+            report.up_to_date += attempted - migrated;
+        }
+
+        if is_last_batch {
+            break;
+        }
+    }
+
+    info!(
+        scanned = report.scanned,
+        migrated = report.migrated,
+        up_to_date = report.up_to_date,
+        failed = report.failed,
+        "Re-encrypted OAuth tokens"
+    );
+
+    Ok(report)
+}
+
+/// Whether both payloads of a token are already on `CURRENT_VERSION`
+fn is_on_current_version(token: &oauth_token::Model) -> bool {
+    std::iter::once(&token.access_token)
+        .chain(token.refresh_token.as_ref())
+        .all(|payload| EncryptedPayload::version_of(payload).is_ok_and(|v| v == CURRENT_VERSION))
+}
+
+/// Decrypts a token's payloads and encrypts them again with the current version
+fn reencrypt_token(
+    token: &oauth_token::Model,
+    encryption: &dyn TokenCrypto,
+) -> Result<(String, Option<String>), CryptoError> {
+    let reencrypt = |payload: &str| -> Result<String, CryptoError> {
+        let plaintext = SecretToken::new(encryption.decrypt(payload)?);
+        encryption.encrypt(&plaintext)
+    };
+
+    let access_token = reencrypt(&token.access_token)?;
+    let refresh_token = token.refresh_token.as_deref().map(reencrypt).transpose()?;
+    Ok((access_token, refresh_token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::NONCE_SIZE;
+    use sea_orm::{DbBackend, MockDatabase, MockExecResult};
+    use uuid::Uuid;
+
+    /// Crypto that reads payloads of any version and writes the current one
+    ///
+    /// The "ciphertext" is the plaintext itself, so payloads stay predictable.
+    struct VersionedCrypto;
+
+    fn payload(version: u8, plaintext: &str) -> String {
+        EncryptedPayload {
+            version,
+            nonce: [0; NONCE_SIZE],
+            ciphertext: plaintext.as_bytes().to_vec(),
+        }
+        .to_base64()
+    }
+
+    impl TokenCrypto for VersionedCrypto {
+        fn encrypt(&self, plaintext: &str) -> Result<String, CryptoError> {
+            Ok(payload(CURRENT_VERSION, plaintext))
+        }
+
+        fn decrypt(&self, encrypted: &str) -> Result<String, CryptoError> {
+            let payload = EncryptedPayload::from_base64(encrypted)?;
+            String::from_utf8(payload.ciphertext).map_err(|_| CryptoError::InvalidUtf8)
+        }
+    }
+
+    fn old_version() -> u8 {
+        CURRENT_VERSION.wrapping_sub(1)
+    }
+
+    fn make_token(access_token: String, refresh_token: Option<String>) -> oauth_token::Model {
+        let now = chrono::Utc::now().fixed_offset();
+        oauth_token::Model {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            provider: "strava".to_string(),
+            access_token,
+            refresh_token,
+            expires_at: None,
+            scopes: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn updated(rows_affected: u64) -> MockExecResult {
+        MockExecResult {
+            last_insert_id: 0,
+            rows_affected,
+        }
+    }
+
+    #[test]
+    fn test_current_version_requires_both_payloads() {
+        let current = payload(CURRENT_VERSION, "a");
+        let old = payload(old_version(), "r");
+
+        assert!(is_on_current_version(&make_token(current.clone(), None)));
+        assert!(is_on_current_version(&make_token(
+            current.clone(),
+            Some(current.clone())
+        )));
+        assert!(!is_on_current_version(&make_token(current, Some(old))));
+        assert!(!is_on_current_version(&make_token("plain".into(), None)));
+    }
+
+    #[tokio::test]
+    async fn test_old_version_tokens_are_upgraded() {
+        let old = make_token(
+            payload(old_version(), "old-access"),
+            Some(payload(old_version(), "old-refresh")),
+        );
+        let current = make_token(payload(CURRENT_VERSION, "fresh-access"), None);
+        let unreadable = make_token("not a payload".into(), None);
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![old.clone(), current, unreadable]])
+            .append_exec_results([updated(1)])
+            .into_connection();
+
+        let report = reencrypt_all_oauth_tokens(&db, &VersionedCrypto)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            report,
+            ReencryptionReport {
+                scanned: 3,
+                migrated: 1,
+                up_to_date: 1,
+                failed: 1,
+            }
+        );
+
+        let log = format!("{:?}", db.into_transaction_log());
+        assert_eq!(log.matches("UPDATE").count(), 1, "{log}");
+        assert!(
+            log.contains(&payload(CURRENT_VERSION, "old-access")),
+            "{log}"
+        );
+        assert!(
+            log.contains(&payload(CURRENT_VERSION, "old-refresh")),
+            "{log}"
+        );
+        // Guarded on the payload that was read
+        assert!(log.contains(&old.access_token), "{log}");
+        assert!(log.contains("COMMIT"), "{log}");
+    }
+
+    #[tokio::test]
+    async fn test_token_refreshed_meanwhile_is_not_counted_as_migrated() {
+        let old = make_token(payload(old_version(), "old-access"), None);
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![old]])
+            .append_exec_results([updated(0)])
+            .into_connection();
+
+        let report = reencrypt_all_oauth_tokens(&db, &VersionedCrypto)
+            .await
+            .unwrap();
+
+        assert_eq!(report.migrated, 0);
+        assert_eq!(report.up_to_date, 1);
+    }
+
+    #[tokio::test]
+    async fn test_up_to_date_tokens_are_not_written() {
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![make_token(payload(CURRENT_VERSION, "a"), None)]])
+            .into_connection();
+
+        let report = reencrypt_all_oauth_tokens(&db, &VersionedCrypto)
+            .await
+            .unwrap();
+
+        assert_eq!(report.up_to_date, 1);
+        let log = format!("{:?}", db.into_transaction_log());
+        assert!(!log.contains("UPDATE"), "{log}");
+    }
+}
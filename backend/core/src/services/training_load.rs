@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Days, FixedOffset, NaiveDate};
+use sea_orm::{DatabaseConnection, DbErr};
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+use uuid::Uuid;
+
+use crate::database::{activity, activity_repository};
+
+/// Heart rate taken as 100% effort when scoring training load, in bpm
+///
+/// Users have no stored max heart rate yet, so a typical adult value is used.
+pub const REFERENCE_MAX_HEART_RATE: f64 = 190.0;
+
+/// Calendar period that training load is aggregated over
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, Display, EnumString,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum TrainingLoadPeriod {
+    /// ISO weeks, starting on Monday
+    #[default]
+    Week,
+    /// Calendar months
+    Month,
+}
+
+impl TrainingLoadPeriod {
+    /// First day of the period containing `date`
+    #[must_use]
+    pub fn start_of(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Week => date - Days::new(u64::from(date.weekday().num_days_from_monday())),
+            Self::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+}
+
+/// Training totals for one week or month
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrainingLoadBucket {
+    /// First day of the period, in the requested timezone
+    pub period_start: NaiveDate,
+    pub activities: usize,
+    /// Total distance in meters
+    pub distance: f64,
+    /// Total elapsed time in seconds
+    pub elapsed_time: i64,
+    /// Total elevation gain in meters
+    pub elevation_gain: f64,
+    /// Sum of moving minutes × average heart rate / `REFERENCE_MAX_HEART_RATE`
+    ///
+    /// Activities without a stored heart rate summary (no heart rate stream, or
+    /// not stream-synced since summaries were stored) add nothing.
+    pub load: f64,
+}
+
+impl TrainingLoadBucket {
+    fn empty(period_start: NaiveDate) -> Self {
+        Self {
+            period_start,
+            activities: 0,
+            distance: 0.0,
+            elapsed_time: 0,
+            elevation_gain: 0.0,
+            load: 0.0,
+        }
+    }
+}
+
+/// Load score of a single activity: moving minutes weighted by heart rate intensity
+#[must_use]
+pub fn activity_load(moving_time_seconds: i32, avg_heart_rate: Option<f64>) -> f64 {
+    avg_heart_rate.map_or(0.0, |heart_rate| {
+        f64::from(moving_time_seconds) / 60.0 * (heart_rate / REFERENCE_MAX_HEART_RATE)
+    })
+}
+
+/// Groups activities into weekly or monthly training totals
+///
+/// An activity belongs to the period of its start date in `offset`, so a Sunday
+/// evening run stays in its week for users east or west of UTC.
+/// Only periods with at least one activity are returned, oldest first.
+/// Load uses the average heart rate stored on each activity by its stream sync.
+#[must_use]
+pub fn bucket_training_load(
+    activities: &[activity::Model],
+    period: TrainingLoadPeriod,
+    offset: FixedOffset,
+) -> Vec<TrainingLoadBucket> {
+    let mut buckets: BTreeMap<NaiveDate, TrainingLoadBucket> = BTreeMap::new();
+
+    for activity in activities {
+        let local_date = activity.start_time.with_timezone(&offset).date_naive();
+        let period_start = period.start_of(local_date);
+        let bucket = buckets
+            .entry(period_start)
+            .or_insert_with(|| TrainingLoadBucket::empty(period_start));
+
+        bucket.activities += 1;
+        bucket.distance += f64::from(activity.distance);
+        bucket.elapsed_time += i64::from(activity.elapsed_time);
+        bucket.elevation_gain += f64::from(activity.total_elevation_gain);
+        bucket.load += activity_load(activity.moving_time, activity.avg_heart_rate);
+    }
+
+    buckets.into_values().collect()
+}
+
+/// Computes a user's weekly or monthly training load over all their activities
+///
+/// # Errors
+///
+/// Returns an error if database query fails
+pub async fn get_training_load(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    period: TrainingLoadPeriod,
+    offset: FixedOffset,
+) -> Result<Vec<TrainingLoadBucket>, DbErr> {
+    let activities = activity_repository::get_activities_by_user(db, user_id).await?;
+
+    Ok(bucket_training_load(&activities, period, offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::make_activity;
+    use chrono::DateTime;
+
+    fn make_run(start_time: &str, distance: f32, moving_time: i32) -> activity::Model {
+        activity::Model {
+            start_time: DateTime::parse_from_rfc3339(start_time).unwrap(),
+            moving_time,
+            elapsed_time: moving_time + 60,
+            distance,
+            ..make_activity()
+        }
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_week_starts_on_monday() {
+        // 2025-11-09 is a Sunday, 2025-11-10 a Monday
+        assert_eq!(
+            TrainingLoadPeriod::Week.start_of(date("2025-11-09")),
+            date("2025-11-03")
+        );
+        assert_eq!(
+            TrainingLoadPeriod::Week.start_of(date("2025-11-10")),
+            date("2025-11-10")
+        );
+        assert_eq!(
+            TrainingLoadPeriod::Month.start_of(date("2025-11-09")),
+            date("2025-11-01")
+        );
+    }
+
+    #[test]
+    fn test_activities_are_bucketed_across_a_week_boundary() {
+        let activities = vec![
+            make_run("2025-11-08T09:00:00Z", 5000.0, 1500),
+            make_run("2025-11-09T18:00:00Z", 10000.0, 3000),
+            make_run("2025-11-10T07:00:00Z", 8000.0, 2400),
+        ];
+
+        let buckets = bucket_training_load(
+            &activities,
+            TrainingLoadPeriod::Week,
+            FixedOffset::east_opt(0).unwrap(),
+        );
+
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].period_start, date("2025-11-03"));
+        assert_eq!(buckets[0].activities, 2);
+        assert!((buckets[0].distance - 15000.0).abs() < f64::EPSILON);
+        assert_eq!(buckets[0].elapsed_time, 1560 + 3060);
+        assert_eq!(buckets[1].period_start, date("2025-11-10"));
+        assert_eq!(buckets[1].activities, 1);
+    }
+
+    #[test]
+    fn test_week_boundary_follows_the_requested_offset() {
+        // Sunday 23:30 in UTC-5, already Monday in UTC
+        let activities = vec![make_run("2025-11-10T04:30:00Z", 5000.0, 1500)];
+
+        let utc = bucket_training_load(
+            &activities,
+            TrainingLoadPeriod::Week,
+            FixedOffset::east_opt(0).unwrap(),
+        );
+        let new_york = bucket_training_load(
+            &activities,
+            TrainingLoadPeriod::Week,
+            FixedOffset::west_opt(5 * 3600).unwrap(),
+        );
+
+        assert_eq!(utc[0].period_start, date("2025-11-10"));
+        assert_eq!(new_york[0].period_start, date("2025-11-03"));
+    }
+
+    #[test]
+    fn test_load_uses_heart_rate_fraction() {
+        let activity = activity::Model {
+            avg_heart_rate: Some(REFERENCE_MAX_HEART_RATE / 2.0),
+            ..make_run("2025-11-10T07:00:00Z", 10000.0, 3600)
+        };
+
+        let buckets = bucket_training_load(
+            &[activity],
+            TrainingLoadPeriod::Month,
+            FixedOffset::east_opt(0).unwrap(),
+        );
+
+        // 60 minutes at half of max heart rate
+        assert!((buckets[0].load - 30.0).abs() < 1e-9);
+        assert_eq!(buckets[0].period_start, date("2025-11-01"));
+    }
+
+    #[test]
+    fn test_activity_without_heart_rate_adds_no_load() {
+        assert!(activity_load(3600, None).abs() < f64::EPSILON);
+    }
+}
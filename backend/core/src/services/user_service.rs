@@ -2,37 +2,80 @@ use sea_orm::DatabaseConnection;
 use tracing::info;
 
 use crate::database::user_repository;
+use crate::services::ListenPadding;
 
-/// Updates a user's Last.fm username after validating it exists
+/// Updates a user's Last.fm username and default listen padding
+///
+/// A new username is checked against Last.fm before anything is written, then
+/// both settings are stored together, so a rejected username leaves the padding
+/// unchanged too.
 ///
 /// # Arguments
 /// * `user_id` - UUID of the user to update
-/// * `lastfm_username` - Last.fm username to set for the user
+/// * `lastfm_username` - Last.fm username to set for the user, if changed
+/// * `padding` - Validated padding applied when a request gives no override, if changed
 /// * `db_connection` - Database connection reference
 ///
 /// # Errors
 /// Returns an error if:
+/// - The Last.fm integration is not configured
 /// - The Last.fm username does not exist
+/// - Last.fm can't be reached to check the username
 /// - Database update fails
-pub async fn update_valid_user_lastfm_username(
+pub async fn update_user_settings(
     user_id: uuid::Uuid,
-    lastfm_username: String,
+    lastfm_username: Option<String>,
+    padding: Option<ListenPadding>,
     db_connection: &DatabaseConnection,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let last_fm_client = run_sous_bpm_integrations::lastfm::LastFmClient::new();
-    let is_valid = last_fm_client.is_username_valid(&lastfm_username).await;
-    if !is_valid {
-        return Err("Invalid Last.fm username".into());
+    if let Some(lastfm_username) = &lastfm_username {
+        let last_fm_client = run_sous_bpm_integrations::lastfm::LastFmClient::try_new()?;
+        if !last_fm_client.is_username_valid(lastfm_username).await? {
+            return Err("Invalid Last.fm username".into());
+        }
     }
 
-    user_repository::update_user_lastfm_username(db_connection, user_id, lastfm_username.clone())
-        .await?;
+    // Bounded by MAX_LISTEN_PADDING_SECONDS, far below i32::MAX
+    let listen_padding = padding
+        .map(|padding| {
+            Ok::<_, std::num::TryFromIntError>((
+                i32::try_from(padding.before_seconds)?,
+                i32::try_from(padding.after_seconds)?,
+            ))
+        })
+        .transpose()?;
+
+    user_repository::update_user_settings(
+        db_connection,
+        user_id,
+        lastfm_username.clone(),
+        listen_padding,
+    )
+    .await?;
 
     info!(
         user_id = %user_id,
         lastfm_username = ?lastfm_username,
-        "Updated user's Last.fm username"
+        listen_padding = ?padding,
+        "Updated user's settings"
     );
 
     Ok(())
 }
+
+/// Unlinks a user's Last.fm account by clearing their username
+///
+/// Music endpoints then answer as if Last.fm had never been set up.
+///
+/// # Errors
+/// Returns an error if the user does not exist or the database update fails
+pub async fn unlink_user_lastfm(
+    user_id: uuid::Uuid,
+    db_connection: &DatabaseConnection,
+) -> Result<(), Box<dyn std::error::Error>> {
+    user_repository::clear_user_lastfm_username(db_connection, user_id).await?;
+
+    info!(user_id = %user_id, "Unlinked user's Last.fm account");
+
+    Ok(())
+}
@@ -1,17 +1,50 @@
-use run_sous_bpm_integrations::strava::{StravaActivityStreamsParams, StravaApiClient};
-use sea_orm::DatabaseConnection;
-use tracing::info;
+use std::{future::Future, sync::Arc};
+
+use chrono::{DateTime, Duration, Utc};
+use run_sous_bpm_integrations::strava::{
+    StravaActivitiesParams, StravaActivityResponse, StravaActivityStreamsParams, StravaApiClient,
+    StreamResolution, StreamSeriesType,
+};
+use sea_orm::{DatabaseConnection, DbErr};
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+use uuid::Uuid;
 
 use crate::{
-    config::OAuthProvider,
-    crypto::EncryptionService,
-    database::{activity, activity_repository, batch_upsert_activity_streams, upsert_activity},
-    models::{CreateActivityDto, ValidatedActivityStreams},
-    services::get_valid_token,
+    config::{
+        activity_sync_max_activities, activity_sync_overlap, stream_ingest_keep_every,
+        stream_insert_batch_size, OAuthProvider,
+    },
+    crypto::TokenCrypto,
+    database::{
+        activity, activity_repository, append_activity_streams, best_effort,
+        get_best_efforts_by_activity, get_latest_activity_start_by_user, replace_activity_streams,
+        replace_best_efforts, retry_transient, upsert_activity, RetryPolicy,
+    },
+    models::{
+        activity_category, into_insertable, preview_stream_channels, ActivityCategory,
+        CreateActivityDto, CreateBestEffortDto, StreamChannelPreview, ValidatedActivityStreams,
+        SYNC_STREAM_KEYS,
+    },
+    services::{get_valid_token, refresh_activity_summary, SyncEvent, SyncEventBus},
 };
 
+/// Activities requested per Strava page, the most its API returns at once
+pub const STRAVA_ACTIVITIES_PAGE_SIZE: u32 = 200;
+
 /// Syncs Strava activities for a user and stores them in the database
 ///
+/// Only activities started after the latest stored one, minus the
+/// `ACTIVITY_SYNC_OVERLAP_MINUTES` overlap, are fetched. Activities re-fetched
+/// inside the overlap are deduplicated by the `external_id` upsert.
+/// Pages are stored as they arrive, so memory stays bounded by one page, and at
+/// most `ACTIVITY_SYNC_MAX_ACTIVITIES` are stored; the next sync resumes after them.
+/// Publishes `SyncEvent::ActivitySynced` once stored.
+///
+/// # Returns
+///
+/// The number of activities stored
+///
 /// # Errors
 ///
 /// Returns an error if:
@@ -23,29 +56,178 @@ pub async fn sync_strava_activities(
     user_id: uuid::Uuid,
     strava_client: &StravaApiClient,
     db_connection: &DatabaseConnection,
-    encryption: &EncryptionService,
-) -> Result<Vec<activity::Model>, Box<dyn std::error::Error>> {
+    encryption: &dyn TokenCrypto,
+    events: &SyncEventBus,
+) -> Result<usize, Box<dyn std::error::Error>> {
     let token = get_valid_token(db_connection, user_id, OAuthProvider::Strava, encryption).await?;
 
-    let strava_activities = strava_client.get_athlete_activities(&token, None).await?;
+    let latest_start = get_latest_activity_start_by_user(db_connection, user_id).await?;
+    // Always sent: with `after` Strava lists oldest first, so a capped sync can resume
+    let after = sync_after(latest_start, activity_sync_overlap()).unwrap_or(0);
+    let retry = RetryPolicy::from_env();
 
-    let mut saved_activities = Vec::new();
+    let synced = sync_in_pages(
+        STRAVA_ACTIVITIES_PAGE_SIZE,
+        activity_sync_max_activities(),
+        |page| {
+            let query = StravaActivitiesParams {
+                before: None,
+                after: Some(after),
+                per_page: Some(STRAVA_ACTIVITIES_PAGE_SIZE),
+                page: Some(page),
+            };
+            let token = &token;
+            async move {
+                let activities = strava_client
+                    .get_athlete_activities(token, Some(query))
+                    .await?;
+                Ok::<_, Box<dyn std::error::Error>>(activities)
+            }
+        },
+        |activities| store_activity_page(db_connection, user_id, activities, retry),
+    )
+    .await?;
 
-    // Convert and save each activity
-    for strava_activity in strava_activities {
-        // Convert Strava response to DTO
-        let dto = CreateActivityDto::from_strava_response(strava_activity, user_id)?;
+    events.publish(user_id, SyncEvent::ActivitySynced { count: synced });
+    Ok(synced)
+}
 
-        // Save or update activity in database
-        let saved_activity = upsert_activity(db_connection, dto).await?;
-        saved_activities.push(saved_activity);
+/// Fetches pages (numbered from 1) and hands each one to `store` before the next
+///
+/// Stops after a short page or once `max_items` were handed over, truncating the
+/// last page to the cap.
+///
+/// # Returns
+///
+/// The total reported by `store`
+async fn sync_in_pages<T, Fetch, FetchFut, Store, StoreFut>(
+    page_size: u32,
+    max_items: usize,
+    mut fetch: Fetch,
+    mut store: Store,
+) -> Result<usize, Box<dyn std::error::Error>>
+where
+    Fetch: FnMut(u32) -> FetchFut,
+    FetchFut: Future<Output = Result<Vec<T>, Box<dyn std::error::Error>>>,
+    Store: FnMut(Vec<T>) -> StoreFut,
+    StoreFut: Future<Output = Result<usize, Box<dyn std::error::Error>>>,
+{
+    let mut handed_over = 0;
+    let mut stored = 0;
+    let mut page = 1;
+
+    while handed_over < max_items {
+        let mut items = fetch(page).await?;
+        let last_page = items.len() < page_size as usize;
+        items.truncate(max_items - handed_over);
+        if items.is_empty() {
+            break;
+        }
+
+        handed_over += items.len();
+        stored += store(items).await?;
+        if last_page {
+            break;
+        }
+        page += 1;
     }
 
-    Ok(saved_activities)
+    if handed_over >= max_items {
+        info!(
+            max_items = max_items,
+            "Activity sync reached its cap, later syncs resume"
+        );
+    }
+    Ok(stored)
+}
+
+/// Upserts one page of Strava activities, retrying dropped connections
+///
+/// # Returns
+///
+/// The number of activities stored
+async fn store_activity_page(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    activities: Vec<StravaActivityResponse>,
+    retry: RetryPolicy,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let count = activities.len();
+    for strava_activity in activities {
+        let dto = CreateActivityDto::from_strava_response(strava_activity, user_id)?;
+        retry_transient(retry, || upsert_activity(db, dto.clone())).await?;
+    }
+    Ok(count)
+}
+
+/// Epoch `after` cursor of an incremental sync, `None` on a first sync
+///
+/// Strava's `after` is exclusive, so the cursor is moved back by `overlap`
+/// to also return activities starting on the same second as the latest one.
+fn sync_after(latest_start: Option<DateTime<Utc>>, overlap: Duration) -> Option<u64> {
+    let after = latest_start?.checked_sub_signed(overlap)?;
+    Some(u64::try_from(after.timestamp()).unwrap_or(0))
+}
+
+/// Result of syncing the streams of a single activity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamSyncOutcome {
+    /// Stream points were stored, possibly downsampled from `original_points`
+    Synced {
+        points: usize,
+        original_points: usize,
+    },
+    /// Strava has no streams for the activity (e.g. manually entered); it is flagged and skipped
+    Unavailable,
+}
+
+/// How an activity's streams are requested from Strava
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StreamFetchOptions {
+    /// Reduced sampling resolution (`None` keeps every point)
+    pub resolution: Option<StreamResolution>,
+    /// Series the points are aligned on, picked from the activity when `None`
+    pub series_type: Option<StreamSeriesType>,
+    /// Only store points newer than the latest stored one (activities still in progress)
+    pub append: bool,
+    /// Stream types to request, from `parse_stream_keys` (`None` requests `SYNC_STREAM_KEYS`)
+    pub keys: Option<Vec<&'static str>>,
+    /// Sync an activity flagged `streams_unavailable` anyway, clearing the flag
+    /// when Strava has streams for it
+    pub force: bool,
+}
+
+/// Series an activity's streams are aligned on when the caller doesn't choose one
+///
+/// Activities without distance (treadmill, indoor trainer) are aligned on time,
+/// since their distance stream is missing or flat.
+#[must_use]
+pub fn default_series_type(activity: &activity::Model) -> StreamSeriesType {
+    if activity.distance > 0.0 {
+        StreamSeriesType::Distance
+    } else {
+        StreamSeriesType::Time
+    }
 }
 
 /// Syncs activity stream data for a specific Strava activity
 ///
+/// `options.resolution` requests a reduced sampling from Strava (`None` keeps every point).
+/// Timestamps come from the time stream whichever series the points are aligned on.
+/// Points are further downsampled before storage when `STREAM_INGEST_KEEP_EVERY` is set;
+/// the point count before downsampling is kept as the activity's `stream_original_size`.
+/// The activity's stored points are replaced in one transaction. With `options.append`,
+/// only points recorded after the latest stored one are inserted instead and stored
+/// points are left untouched.
+/// `options.keys` restricts the streams requested, e.g. to `latlng` for map-only use;
+/// columns of the streams left out are stored empty.
+/// Activities without streams (manual entries) are flagged as `streams_unavailable`
+/// instead of failing, and skipped without calling Strava once flagged unless
+/// `options.force` is set.
+/// The activity summary (heart rate, elevation gain, moving time) is recomputed from
+/// the fetched points afterwards, without reading them back.
+/// Publishes `SyncEvent::StreamsSynced` once points are stored.
+///
 /// # Errors
 ///
 /// Returns an error if:
@@ -57,46 +239,306 @@ pub async fn sync_strava_activities(
 pub async fn sync_strava_activity_streams(
     user_id: uuid::Uuid,
     external_id: i64,
+    options: StreamFetchOptions,
     strava_client: &StravaApiClient,
     db_connection: &DatabaseConnection,
-    encryption: &EncryptionService,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let token = get_valid_token(db_connection, user_id, OAuthProvider::Strava, encryption).await?;
-    let keys = &[
-        "time",
-        "distance",
-        "latlng",
-        "altitude",
-        "heart_rate",
-        "cadence",
-        "watts",
-        "velocity_smooth",
-        "temperature",
-    ];
-    let params = StravaActivityStreamsParams::new(keys);
-    let streams = strava_client
-        .get_activity_streams(&token, external_id, params)
-        .await?;
-
+    encryption: &dyn TokenCrypto,
+    events: &SyncEventBus,
+) -> Result<StreamSyncOutcome, Box<dyn std::error::Error>> {
     let activity =
         activity_repository::get_activity_by_external_id(db_connection, user_id, external_id)
             .await?
             .ok_or("Activity not found")?;
-    let dto = ValidatedActivityStreams::from_strava_response(streams, activity.id)?;
 
-    let models = dto.into_active_models(activity.start_time);
-    let count = models.len();
+    if activity.streams_unavailable && !options.force {
+        return Ok(StreamSyncOutcome::Unavailable);
+    }
 
-    batch_upsert_activity_streams(db_connection, models).await?;
+    let token = get_valid_token(db_connection, user_id, OAuthProvider::Strava, encryption).await?;
+    sync_activity_streams_with_token(
+        &activity,
+        &token,
+        options,
+        strava_client,
+        db_connection,
+        events,
+    )
+    .await
+}
+
+/// Fetches and stores the streams of a stored activity with an already valid token
+///
+/// Clears the activity's `streams_unavailable` flag when Strava has streams for it.
+async fn sync_activity_streams_with_token(
+    activity: &activity::Model,
+    token: &str,
+    options: StreamFetchOptions,
+    strava_client: &StravaApiClient,
+    db_connection: &DatabaseConnection,
+    events: &SyncEventBus,
+) -> Result<StreamSyncOutcome, Box<dyn std::error::Error>> {
+    let user_id = activity.user_id;
+    let external_id = activity.external_id;
+    let keys = options.keys.as_deref().unwrap_or(SYNC_STREAM_KEYS);
+    let series_type = options
+        .series_type
+        .unwrap_or_else(|| default_series_type(&activity));
+    let params = StravaActivityStreamsParams::new(keys)
+        .with_resolution(options.resolution)
+        .with_series_type(series_type);
+    let streams = strava_client
+        .get_activity_streams(token, external_id, params)
+        .await?;
+
+    if streams.is_empty() {
+        activity_repository::set_activity_streams_unavailable(db_connection, activity.id, true)
+            .await?;
+        info!(
+            user_id = %user_id,
+            activity_id = %activity.id,
+            external_id = external_id,
+            "No streams available for activity, flagged to skip future syncs"
+        );
+        return Ok(StreamSyncOutcome::Unavailable);
+    }
+
+    if activity.streams_unavailable {
+        activity_repository::set_activity_streams_unavailable(db_connection, activity.id, false)
+            .await?;
+        info!(
+            user_id = %user_id,
+            activity_id = %activity.id,
+            external_id = external_id,
+            "Streams found for activity flagged without streams, flag cleared"
+        );
+    }
+
+    let dto = ValidatedActivityStreams::from_strava_response(streams, activity.id)?
+        .with_normalized_cadence(&activity.r#type);
+    let original_points = dto.time.len();
+
+    let points = dto
+        .downsample(stream_ingest_keep_every())
+        .into_models(activity.start_time);
+    let models: Vec<_> = points.iter().cloned().map(into_insertable).collect();
+
+    // Both writes run in one transaction, so a retry starts again from a clean state
+    let batch_size = stream_insert_batch_size();
+    let count = if options.append {
+        retry_transient(RetryPolicy::from_env(), || {
+            append_activity_streams(
+                db_connection,
+                activity.id,
+                models.clone(),
+                original_points,
+                batch_size,
+            )
+        })
+        .await?
+    } else {
+        let count = models.len();
+        retry_transient(RetryPolicy::from_env(), || {
+            replace_activity_streams(
+                db_connection,
+                activity.id,
+                models.clone(),
+                original_points,
+                batch_size,
+            )
+        })
+        .await?;
+        count
+    };
 
     info!(
         user_id = %user_id,
         activity_id = %activity.id,
         external_id = external_id,
         points = count,
+        original_points = original_points,
+        append = options.append,
         "Successfully synced activity streams"
     );
-    Ok(())
+    // The summary endpoint computes it on the fly when missing, so the sync still succeeds
+    if let Err(error) = refresh_activity_summary(db_connection, activity.id, &points).await {
+        warn!(
+            activity_id = %activity.id,
+            error = %error,
+            "Failed to store activity summary"
+        );
+    }
+    events.publish(
+        user_id,
+        SyncEvent::StreamsSynced {
+            activity_id: activity.id,
+            points: count,
+        },
+    );
+    Ok(StreamSyncOutcome::Synced {
+        points: count,
+        original_points,
+    })
+}
+
+/// The Strava activity is already stored for another user
+#[derive(Debug, thiserror::Error)]
+#[error("Activity belongs to another user")]
+pub struct ActivityOwnedByAnotherUserError;
+
+/// An activity imported from Strava together with its streams
+#[derive(Debug, Clone)]
+pub struct ImportedActivity {
+    pub activity: activity::Model,
+    pub streams: StreamSyncOutcome,
+}
+
+/// Imports a single Strava activity and its streams in one step
+///
+/// Fetches the activity detail, upserts it, then syncs its streams at full resolution.
+/// Every step is an upsert, so importing an activity again (or retrying after a
+/// failed stream sync) refreshes it instead of duplicating anything.
+/// Publishes `SyncEvent::ActivitySynced` once the activity is stored, and
+/// `SyncEvent::StreamsSynced` once its points are.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - OAuth token retrieval fails
+/// - Strava API request fails
+/// - The activity is stored for another user (`ActivityOwnedByAnotherUserError`)
+/// - Stream validation fails
+/// - Database insertion fails
+pub async fn import_strava_activity(
+    user_id: uuid::Uuid,
+    external_id: i64,
+    strava_client: &StravaApiClient,
+    db_connection: &DatabaseConnection,
+    encryption: &dyn TokenCrypto,
+    events: &SyncEventBus,
+) -> Result<ImportedActivity, Box<dyn std::error::Error>> {
+    let token = get_valid_token(db_connection, user_id, OAuthProvider::Strava, encryption).await?;
+    let details = strava_client
+        .get_activity_details(&token, external_id)
+        .await?;
+    let activity = store_strava_activity(db_connection, user_id, details).await?;
+    events.publish(user_id, SyncEvent::ActivitySynced { count: 1 });
+
+    let streams = sync_strava_activity_streams(
+        user_id,
+        activity.external_id,
+        StreamFetchOptions::default(),
+        strava_client,
+        db_connection,
+        encryption,
+        events,
+    )
+    .await?;
+
+    Ok(ImportedActivity { activity, streams })
+}
+
+/// Upserts a fetched Strava activity for the user, retrying dropped connections
+async fn store_strava_activity(
+    db_connection: &DatabaseConnection,
+    user_id: Uuid,
+    details: StravaActivityResponse,
+) -> Result<activity::Model, Box<dyn std::error::Error>> {
+    let dto = CreateActivityDto::from_strava_response(details, user_id)?;
+
+    match retry_transient(RetryPolicy::from_env(), || {
+        upsert_activity(db_connection, dto.clone())
+    })
+    .await
+    {
+        Ok(activity) => Ok(activity),
+        // The conflict only updates rows of the same user, so a skipped upsert means another owner
+        Err(DbErr::RecordNotInserted) => Err(Box::new(ActivityOwnedByAnotherUserError)),
+        Err(err) => Err(Box::new(err)),
+    }
+}
+
+/// Every stream type Strava can return, used to discover what an activity recorded
+const ALL_STRAVA_STREAM_KEYS: &[&str] = &[
+    "time",
+    "distance",
+    "latlng",
+    "altitude",
+    "velocity_smooth",
+    "heartrate",
+    "cadence",
+    "watts",
+    "temp",
+    "moving",
+    "grade_smooth",
+];
+
+/// Lists the stream channels Strava has for an activity, without storing anything
+///
+/// Streams are fetched at low resolution to keep the call cheap; the reported
+/// sizes are the full-resolution point counts.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - OAuth token retrieval fails
+/// - Strava API request fails
+pub async fn preview_strava_activity_streams(
+    user_id: uuid::Uuid,
+    external_id: i64,
+    strava_client: &StravaApiClient,
+    db_connection: &DatabaseConnection,
+    encryption: &dyn TokenCrypto,
+) -> Result<Vec<StreamChannelPreview>, Box<dyn std::error::Error>> {
+    let token = get_valid_token(db_connection, user_id, OAuthProvider::Strava, encryption).await?;
+    let params = StravaActivityStreamsParams::new(ALL_STRAVA_STREAM_KEYS)
+        .with_resolution(Some(StreamResolution::Low));
+    let streams = strava_client
+        .get_activity_streams(token, external_id, params)
+        .await?;
+
+    Ok(preview_stream_channels(&streams))
+}
+
+/// Returns the best efforts (fastest 400m, 1k, 5k...) Strava computed for a run
+///
+/// Best efforts only come with the detailed activity, which the activity list sync
+/// doesn't fetch, so they are loaded from Strava on first request and stored.
+/// The activity is marked as fetched, so a run Strava has none for is not fetched
+/// again. Activities that aren't runs have none and never call Strava.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - OAuth token retrieval fails
+/// - Strava API request fails
+/// - Database query fails
+pub async fn get_strava_activity_best_efforts(
+    user_id: uuid::Uuid,
+    activity: &activity::Model,
+    strava_client: &StravaApiClient,
+    db_connection: &DatabaseConnection,
+    encryption: &dyn TokenCrypto,
+) -> Result<Vec<best_effort::Model>, Box<dyn std::error::Error>> {
+    let stored = get_best_efforts_by_activity(db_connection, activity.id).await?;
+    // Efforts stored before fetches were marked count as fetched too
+    if activity.best_efforts_fetched_at.is_some()
+        || !stored.is_empty()
+        || activity_category(&activity.r#type) != ActivityCategory::Run
+    {
+        return Ok(stored);
+    }
+
+    let token = get_valid_token(db_connection, user_id, OAuthProvider::Strava, encryption).await?;
+    let details = strava_client
+        .get_activity_details(&token, activity.external_id)
+        .await?;
+    let efforts = details
+        .best_efforts
+        .into_iter()
+        .map(|effort| CreateBestEffortDto::from_strava_effort(effort, activity.id))
+        .collect();
+
+    Ok(replace_best_efforts(db_connection, activity.id, efforts).await?)
 }
 
 /// Syncs activity streams for all activities of a user
@@ -111,17 +553,24 @@ pub async fn sync_all_strava_activity_streams(
     user_id: uuid::Uuid,
     strava_client: &StravaApiClient,
     db_connection: &DatabaseConnection,
-    encryption: &EncryptionService,
+    encryption: &dyn TokenCrypto,
+    events: &SyncEventBus,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    // Fail once on a missing or under-scoped token instead of once per activity
+    get_valid_token(db_connection, user_id, OAuthProvider::Strava, encryption).await?;
+
     let activities = activity_repository::get_activities_by_user(db_connection, user_id).await?;
 
-    for activity in activities {
+    // Manual activities have no streams: skip them instead of logging a failure each time
+    for activity in activities.into_iter().filter(|a| !a.streams_unavailable) {
         if let Err(e) = sync_strava_activity_streams(
             user_id,
             activity.external_id,
+            StreamFetchOptions::default(),
             strava_client,
             db_connection,
             encryption,
+            events,
         )
         .await
         {
@@ -137,3 +586,640 @@ pub async fn sync_all_strava_activity_streams(
 
     Ok(())
 }
+
+/// Maximum number of activities whose streams are synced at the same time
+const STREAM_SYNC_CONCURRENCY: usize = 4;
+
+/// Result of syncing one activity's streams within a range sync
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActivityStreamSyncResult {
+    pub activity_id: Uuid,
+    pub external_id: i64,
+    pub start_time: DateTime<Utc>,
+    /// Sync outcome, or the error message if this activity failed
+    pub outcome: Result<StreamSyncOutcome, String>,
+}
+
+/// Syncs the streams of a user's activities started within `[start, end)`
+///
+/// Up to `STREAM_SYNC_CONCURRENCY` activities are synced concurrently, all with the
+/// token fetched once up front. A failing activity does not stop the others; its
+/// error is reported in its result. Activities flagged `streams_unavailable` are
+/// reported as such without calling Strava.
+///
+/// # Returns
+///
+/// One result per activity in the range, ordered by start time
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - OAuth token retrieval fails or the token lacks a required scope
+/// - The activities cannot be loaded
+/// - A sync task panics
+pub async fn sync_strava_activity_streams_in_range(
+    user_id: Uuid,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    strava_client: Arc<StravaApiClient>,
+    db_connection: Arc<DatabaseConnection>,
+    encryption: Arc<dyn TokenCrypto>,
+    events: SyncEventBus,
+) -> Result<Vec<ActivityStreamSyncResult>, Box<dyn std::error::Error>> {
+    // Fail once on a missing or under-scoped token, and refresh it at most once
+    let token = get_valid_token(
+        &db_connection,
+        user_id,
+        OAuthProvider::Strava,
+        encryption.as_ref(),
+    )
+    .await?;
+
+    let activities =
+        activity_repository::get_activities_by_user_in_range(&db_connection, user_id, start, end)
+            .await?;
+
+    let mut results = Vec::with_capacity(activities.len());
+    let mut tasks = JoinSet::new();
+
+    for activity in activities {
+        if tasks.len() >= STREAM_SYNC_CONCURRENCY {
+            if let Some(result) = tasks.join_next().await {
+                results.push(result?);
+            }
+        }
+
+        let strava_client = Arc::clone(&strava_client);
+        let db_connection = Arc::clone(&db_connection);
+        let token = token.clone();
+        let events = events.clone();
+        tasks.spawn(async move {
+            let outcome = if activity.streams_unavailable {
+                Ok(StreamSyncOutcome::Unavailable)
+            } else {
+                sync_activity_streams_with_token(
+                    &activity,
+                    &token,
+                    StreamFetchOptions::default(),
+                    &strava_client,
+                    &db_connection,
+                    &events,
+                )
+                .await
+                .map_err(|e| e.to_string())
+            };
+
+            ActivityStreamSyncResult {
+                activity_id: activity.id,
+                external_id: activity.external_id,
+                start_time: activity.start_time.with_timezone(&Utc),
+                outcome,
+            }
+        });
+    }
+
+    while let Some(result) = tasks.join_next().await {
+        results.push(result?);
+    }
+
+    results.sort_by_key(|result| result.start_time);
+    info!(
+        user_id = %user_id,
+        start = %start,
+        end = %end,
+        activities = results.len(),
+        failed = results.iter().filter(|r| r.outcome.is_err()).count(),
+        "Synced activity streams in range"
+    );
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    };
+
+    use super::*;
+    use crate::{crypto::PassthroughCrypto, database::oauth_token, test_support::make_activity};
+    use run_sous_bpm_integrations::common::{AuthenticatedClient, IntegrationClient};
+    use run_sous_bpm_integrations::test_support::{
+        closed_port_url, empty_response, json_response, spawn_mock_server,
+    };
+    use sea_orm::{DbBackend, MockDatabase, MockExecResult};
+
+    const EXTERNAL_ID: u64 = 42;
+
+    fn make_details() -> StravaActivityResponse {
+        StravaActivityResponse {
+            id: EXTERNAL_ID,
+            name: "Morning Run".to_string(),
+            description: None,
+            sport_type: "Run".to_string(),
+            start_date: "2023-11-14T22:13:20Z".to_string(),
+            moving_time: 1800,
+            elapsed_time: 1900,
+            timezone: "UTC".to_string(),
+            distance: 5000.0,
+            total_elevation_gain: 40.0,
+            manual: false,
+            best_efforts: Vec::new(),
+        }
+    }
+
+    /// Streams of activity 42: three points, 30 meters apart
+    const STREAMS_BODY: &str = r#"{
+        "time": { "data": [0, 10, 20], "original_size": 3 },
+        "distance": { "data": [0.0, 30.0, 60.0], "original_size": 3 },
+        "heart_rate": { "data": [130, 150, 170], "original_size": 3 }
+    }"#;
+
+    /// Mock Strava serving the detail and the streams of activity 42
+    ///
+    /// Records the path of every request it receives in `paths`.
+    async fn strava_serving_activity(paths: Arc<Mutex<Vec<String>>>) -> StravaApiClient {
+        let details = serde_json::to_string(&make_details()).unwrap();
+        let base_url = spawn_mock_server(move |request| {
+            let target = request.split_whitespace().nth(1).unwrap_or_default();
+            let path = target.split('?').next().unwrap_or_default().to_string();
+            paths.lock().unwrap().push(path.clone());
+            match path.as_str() {
+                "/activities/42" => json_response(&details),
+                "/activities/42/streams" => json_response(STREAMS_BODY),
+                _ => empty_response("404 Not Found"),
+            }
+        })
+        .await;
+        StravaApiClient::new(
+            IntegrationClient::new(Arc::new(AuthenticatedClient::new())),
+            base_url,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_importing_an_activity_stores_it_and_its_streams() {
+        let paths = Arc::new(Mutex::new(Vec::new()));
+        let strava_client = strava_serving_activity(Arc::clone(&paths)).await;
+
+        let user_id = Uuid::new_v4();
+        let stored = activity::Model {
+            user_id,
+            ..make_activity()
+        };
+        let db = MockDatabase::new(DbBackend::Postgres)
+            // Token, upserted activity, stream sync lookup and token again
+            .append_query_results([vec![make_strava_token(user_id)]])
+            .append_query_results([vec![stored.clone()], vec![stored.clone()]])
+            .append_query_results([vec![make_strava_token(user_id)]])
+            // Stream delete, insert and original size, then the summary
+            .append_exec_results([0, 3, 1, 1].map(|rows_affected| MockExecResult {
+                last_insert_id: 0,
+                rows_affected,
+            }))
+            .into_connection();
+        let events = SyncEventBus::new();
+        let mut received = events.subscribe(user_id);
+
+        let imported = import_strava_activity(
+            user_id,
+            42,
+            &strava_client,
+            &db,
+            &PassthroughCrypto,
+            &events,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(imported.activity, stored);
+        assert_eq!(
+            imported.streams,
+            StreamSyncOutcome::Synced {
+                points: 3,
+                original_points: 3,
+            }
+        );
+        assert_eq!(
+            *paths.lock().unwrap(),
+            vec!["/activities/42", "/activities/42/streams"]
+        );
+        assert_eq!(
+            received.recv().await,
+            Some(SyncEvent::ActivitySynced { count: 1 })
+        );
+        assert_eq!(
+            received.recv().await,
+            Some(SyncEvent::StreamsSynced {
+                activity_id: stored.id,
+                points: 3,
+            })
+        );
+
+        let log = db.into_transaction_log();
+        let summary_update = log
+            .iter()
+            .flat_map(|transaction| transaction.statements())
+            .find(|statement| statement.sql.contains(r#""avg_heart_rate" = "#))
+            .expect("The summary is stored");
+        let values = &summary_update.values.as_ref().unwrap().0;
+        assert!(values.contains(&150.0_f64.into()), "{values:?}");
+        assert!(values.contains(&170_i32.into()), "{values:?}");
+        let statements: Vec<&str> = log
+            .iter()
+            .flat_map(|transaction| transaction.statements())
+            .map(|statement| statement.sql.as_str())
+            .collect();
+        assert!(
+            !statements
+                .iter()
+                .any(|sql| sql.starts_with("SELECT") && sql.contains(r#"FROM "activity_stream""#)),
+            "The summary comes from the fetched points: {statements:?}"
+        );
+        assert!(
+            statements
+                .iter()
+                .any(|sql| sql.starts_with(r#"INSERT INTO "activity" "#)
+                    && sql.contains("ON CONFLICT")),
+            "{statements:?}"
+        );
+        assert!(
+            statements
+                .iter()
+                .any(|sql| sql.starts_with(r#"INSERT INTO "activity_stream" "#)),
+            "{statements:?}"
+        );
+        assert!(
+            statements
+                .iter()
+                .any(|sql| sql.contains(r#""stream_original_size" = "#)),
+            "{statements:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_importing_another_users_activity_is_rejected_before_its_streams() {
+        let paths = Arc::new(Mutex::new(Vec::new()));
+        let strava_client = strava_serving_activity(Arc::clone(&paths)).await;
+
+        let user_id = Uuid::new_v4();
+        // The conflict's WHERE clause skips rows of other users, so nothing is returned
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![make_strava_token(user_id)]])
+            .append_query_results([Vec::<activity::Model>::new()])
+            .into_connection();
+
+        let error = import_strava_activity(
+            user_id,
+            42,
+            &strava_client,
+            &db,
+            &PassthroughCrypto,
+            &SyncEventBus::new(),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(error.is::<ActivityOwnedByAnotherUserError>());
+        assert_eq!(*paths.lock().unwrap(), vec!["/activities/42"]);
+        assert_eq!(db.into_transaction_log().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_run_without_best_efforts_is_fetched_from_strava_once() {
+        let paths = Arc::new(Mutex::new(Vec::new()));
+        let strava_client = strava_serving_activity(Arc::clone(&paths)).await;
+
+        let user_id = Uuid::new_v4();
+        let run = activity::Model {
+            user_id,
+            ..make_activity()
+        };
+        let db = MockDatabase::new(DbBackend::Postgres)
+            // First request: nothing stored, the token, then the empty set read back
+            .append_query_results([Vec::<best_effort::Model>::new()])
+            .append_query_results([vec![make_strava_token(user_id)]])
+            .append_query_results([Vec::<best_effort::Model>::new()])
+            // Second request: still nothing stored
+            .append_query_results([Vec::<best_effort::Model>::new()])
+            // Delete of the previous efforts and the fetch marker
+            .append_exec_results([0, 1].map(|rows_affected| MockExecResult {
+                last_insert_id: 0,
+                rows_affected,
+            }))
+            .into_connection();
+
+        let first = get_strava_activity_best_efforts(
+            user_id,
+            &run,
+            &strava_client,
+            &db,
+            &PassthroughCrypto,
+        )
+        .await
+        .unwrap();
+        // The activity as reloaded by the next request
+        let fetched = activity::Model {
+            best_efforts_fetched_at: Some(Utc::now().fixed_offset()),
+            ..run
+        };
+        let second = get_strava_activity_best_efforts(
+            user_id,
+            &fetched,
+            &strava_client,
+            &db,
+            &PassthroughCrypto,
+        )
+        .await
+        .unwrap();
+
+        assert!(first.is_empty());
+        assert!(second.is_empty());
+        assert_eq!(*paths.lock().unwrap(), vec!["/activities/42"]);
+        let log = format!("{:?}", db.into_transaction_log());
+        assert!(log.contains("best_efforts_fetched_at"), "{log}");
+    }
+
+    #[test]
+    fn test_first_sync_fetches_every_activity() {
+        assert_eq!(sync_after(None, Duration::hours(1)), None);
+    }
+
+    #[test]
+    fn test_activity_on_the_sync_boundary_is_not_skipped() {
+        let latest = make_activity().start_time.with_timezone(&Utc);
+        let boundary = u64::try_from(latest.timestamp()).unwrap();
+
+        let after = sync_after(Some(latest), Duration::hours(1)).unwrap();
+
+        // Strava only returns activities strictly after the cursor
+        assert!(after < boundary);
+        assert_eq!(boundary - after, 3600);
+        assert_eq!(sync_after(Some(latest), Duration::zero()), Some(boundary));
+    }
+
+    #[test]
+    fn test_sync_cursor_is_clamped_at_the_epoch() {
+        let latest = DateTime::from_timestamp(600, 0).unwrap();
+
+        assert_eq!(sync_after(Some(latest), Duration::hours(1)), Some(0));
+    }
+
+    /// Runs `sync_in_pages` over `total` fake activities, returning the requested
+    /// pages, the sizes of the chunks handed to the store callback and the total
+    async fn page_through(total: u32, max_items: usize) -> (Vec<u32>, Vec<usize>, usize) {
+        let mut pages = Vec::new();
+        let mut chunks = Vec::new();
+
+        let stored = sync_in_pages(
+            STRAVA_ACTIVITIES_PAGE_SIZE,
+            max_items,
+            |page| {
+                pages.push(page);
+                let first = (page - 1) * STRAVA_ACTIVITIES_PAGE_SIZE;
+                let items: Vec<u32> =
+                    (first..total.min(first + STRAVA_ACTIVITIES_PAGE_SIZE)).collect();
+                async move { Ok::<_, Box<dyn std::error::Error>>(items) }
+            },
+            |chunk| {
+                chunks.push(chunk.len());
+                async move { Ok::<_, Box<dyn std::error::Error>>(chunk.len()) }
+            },
+        )
+        .await
+        .unwrap();
+
+        (pages, chunks, stored)
+    }
+
+    #[tokio::test]
+    async fn test_many_activities_are_stored_page_by_page() {
+        let (pages, chunks, stored) = page_through(450, 5000).await;
+
+        assert_eq!(pages, vec![1, 2, 3]);
+        // Never more than one page is held before being stored
+        assert_eq!(chunks, vec![200, 200, 50]);
+        assert_eq!(stored, 450);
+    }
+
+    #[tokio::test]
+    async fn test_sync_stops_at_the_activity_cap() {
+        let (pages, chunks, stored) = page_through(10_000, 250).await;
+
+        assert_eq!(pages, vec![1, 2]);
+        assert_eq!(chunks, vec![200, 50]);
+        assert_eq!(stored, 250);
+    }
+
+    #[tokio::test]
+    async fn test_full_last_page_ends_on_an_empty_page() {
+        let (pages, chunks, stored) = page_through(400, 5000).await;
+
+        assert_eq!(pages, vec![1, 2, 3]);
+        assert_eq!(chunks, vec![200, 200]);
+        assert_eq!(stored, 400);
+    }
+
+    #[tokio::test]
+    async fn test_activity_page_is_upserted_without_keeping_rows() {
+        let user_id = Uuid::new_v4();
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([
+                vec![activity::Model {
+                    user_id,
+                    ..make_activity()
+                }],
+                vec![activity::Model {
+                    user_id,
+                    ..make_activity()
+                }],
+            ])
+            .into_connection();
+        let retry = RetryPolicy {
+            max_attempts: 1,
+            base_delay: std::time::Duration::ZERO,
+        };
+
+        let stored = store_activity_page(&db, user_id, vec![make_details(), make_details()], retry)
+            .await
+            .unwrap();
+
+        assert_eq!(stored, 2);
+        assert_eq!(db.into_transaction_log().len(), 2);
+    }
+
+    fn make_strava_token(user_id: Uuid) -> oauth_token::Model {
+        let now = Utc::now();
+        oauth_token::Model {
+            id: Uuid::new_v4(),
+            user_id,
+            provider: OAuthProvider::Strava.to_string(),
+            access_token: "range-access-token".to_string(),
+            refresh_token: Some("refresh".to_string()),
+            expires_at: Some((now + Duration::hours(1)).into()),
+            scopes: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_range_sync_fetches_the_token_once_for_every_activity() {
+        // Mock Strava: no streams for any activity, only with the shared token
+        let requests = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&requests);
+        let base_url = spawn_mock_server(move |request| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            if request.contains("Bearer range-access-token") {
+                json_response("{}")
+            } else {
+                empty_response("401 Unauthorized")
+            }
+        })
+        .await;
+        let strava_client = Arc::new(StravaApiClient::new(
+            IntegrationClient::new(Arc::new(AuthenticatedClient::new())),
+            base_url,
+        ));
+
+        let user_id = Uuid::new_v4();
+        let mut activities: Vec<activity::Model> = (0..3)
+            .map(|i| activity::Model {
+                external_id: 100 + i,
+                user_id,
+                ..make_activity()
+            })
+            .collect();
+        activities.push(activity::Model {
+            external_id: 200,
+            streams_unavailable: true,
+            user_id,
+            ..make_activity()
+        });
+        let db = Arc::new(
+            MockDatabase::new(DbBackend::Postgres)
+                .append_query_results([vec![make_strava_token(user_id)]])
+                .append_query_results([activities])
+                .append_exec_results((0..3).map(|_| MockExecResult {
+                    last_insert_id: 0,
+                    rows_affected: 1,
+                }))
+                .into_connection(),
+        );
+
+        let start = Utc::now() - Duration::days(365 * 10);
+        let results = sync_strava_activity_streams_in_range(
+            user_id,
+            start,
+            Utc::now(),
+            strava_client,
+            Arc::clone(&db),
+            Arc::new(PassthroughCrypto),
+            SyncEventBus::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(results.len(), 4);
+        assert!(results
+            .iter()
+            .all(|result| result.outcome == Ok(StreamSyncOutcome::Unavailable)));
+        assert_eq!(
+            requests.load(Ordering::SeqCst),
+            3,
+            "Flagged activities are skipped without calling Strava"
+        );
+        let log = Arc::try_unwrap(db)
+            .ok()
+            .expect("Every sync task is done")
+            .into_transaction_log();
+        let token_lookups = log
+            .iter()
+            .flat_map(|transaction| transaction.statements())
+            .filter(|statement| statement.sql.contains(r#"FROM "oauth_token""#))
+            .count();
+        assert_eq!(token_lookups, 1, "{log:?}");
+    }
+
+    #[tokio::test]
+    async fn test_flagged_activity_is_skipped_without_a_token_or_strava_call() {
+        let user_id = Uuid::new_v4();
+        let strava_client = StravaApiClient::new(
+            IntegrationClient::new(Arc::new(AuthenticatedClient::new())),
+            closed_port_url().await,
+        );
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![activity::Model {
+                streams_unavailable: true,
+                user_id,
+                ..make_activity()
+            }]])
+            .into_connection();
+
+        let outcome = sync_strava_activity_streams(
+            user_id,
+            42,
+            StreamFetchOptions::default(),
+            &strava_client,
+            &db,
+            &PassthroughCrypto,
+            &SyncEventBus::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(outcome, StreamSyncOutcome::Unavailable);
+        assert_eq!(
+            db.into_transaction_log().len(),
+            1,
+            "Only the activity lookup"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forced_sync_asks_strava_again_for_a_flagged_activity() {
+        let requests = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&requests);
+        let base_url = spawn_mock_server(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            json_response("{}")
+        })
+        .await;
+        let strava_client = StravaApiClient::new(
+            IntegrationClient::new(Arc::new(AuthenticatedClient::new())),
+            base_url,
+        );
+
+        let user_id = Uuid::new_v4();
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![activity::Model {
+                streams_unavailable: true,
+                user_id,
+                ..make_activity()
+            }]])
+            .append_query_results([vec![make_strava_token(user_id)]])
+            .append_exec_results([MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 1,
+            }])
+            .into_connection();
+
+        let outcome = sync_strava_activity_streams(
+            user_id,
+            42,
+            StreamFetchOptions {
+                force: true,
+                ..StreamFetchOptions::default()
+            },
+            &strava_client,
+            &db,
+            &PassthroughCrypto,
+            &SyncEventBus::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(outcome, StreamSyncOutcome::Unavailable);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,170 @@
+//! Database model fixtures for tests
+//!
+//! Compiled for this crate's tests and, through the `test-support` feature,
+//! for the tests of the crates depending on it. Fixtures carry neutral defaults;
+//! tests override the fields they care about with struct update syntax.
+//!
+//! This crate's tests can also run against a real database with `test_database`.
+
+use chrono::{DateTime, Utc};
+#[cfg(test)]
+use sea_orm::{ActiveModelTrait, DatabaseConnection};
+use uuid::Uuid;
+
+use crate::database::{activity, track, user};
+
+/// A user linked to the Last.fm account `runner`, without listen padding
+#[must_use]
+pub fn make_user() -> user::Model {
+    let now = Utc::now().fixed_offset();
+    user::Model {
+        id: Uuid::new_v4(),
+        email: "runner@example.com".to_string(),
+        created_at: now,
+        updated_at: now,
+        password_hash: None,
+        lastfm_username: Some("runner".to_string()),
+        listen_padding_before_seconds: 0,
+        listen_padding_after_seconds: 0,
+    }
+}
+
+/// A Last.fm track without album, MusicBrainz IDs, BPM or Spotify search
+#[must_use]
+pub fn make_track() -> track::Model {
+    let now = Utc::now().fixed_offset();
+    track::Model {
+        id: Uuid::new_v4(),
+        artist_name: "Daft Punk".to_string(),
+        track_name: "One More Time".to_string(),
+        album_name: None,
+        artist_mbid: None,
+        track_mbid: None,
+        album_mbid: None,
+        lastfm_url: None,
+        bpm: None,
+        spotify_id: None,
+        spotify_match_confidence: None,
+        spotify_searched_at: None,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+/// A 5 km Strava run (external ID 42) started at 2023-11-14T22:13:20Z, without stored summary
+#[must_use]
+pub fn make_activity() -> activity::Model {
+    let start_time = DateTime::from_timestamp(1_700_000_000, 0)
+        .expect("timestamp is in range")
+        .fixed_offset();
+    activity::Model {
+        id: Uuid::new_v4(),
+        user_id: Uuid::new_v4(),
+        external_id: 42,
+        name: "Morning Run".to_string(),
+        description: None,
+        r#type: "Run".to_string(),
+        start_time,
+        moving_time: 1800,
+        elapsed_time: 1900,
+        timezone: "UTC".to_string(),
+        distance: 5000.0,
+        total_elevation_gain: 40.0,
+        streams_unavailable: false,
+        time_offset_seconds: 0,
+        avg_heart_rate: None,
+        max_heart_rate: None,
+        stream_elevation_gain: None,
+        stream_moving_time: None,
+        summary_computed_at: None,
+        stream_original_size: None,
+        best_efforts_fetched_at: None,
+        created_at: start_time,
+        updated_at: start_time,
+    }
+}
+
+/// Environment variable holding the URL of the database for database-backed tests
+#[cfg(test)]
+const TEST_DATABASE_URL: &str = "TEST_DATABASE_URL";
+
+#[cfg(test)]
+static TEST_DATABASE_MIGRATED: tokio::sync::OnceCell<()> = tokio::sync::OnceCell::const_new();
+
+/// Connection to the Postgres database named by `TEST_DATABASE_URL`, migrated to the latest schema
+///
+/// Returns `None` when the variable is unset, and the calling test returns early.
+/// The database needs the TimescaleDB extension, like the docker-compose one.
+/// Tests share it and run in parallel, so each one only works on rows it created
+/// with fresh IDs and names.
+///
+/// # Panics
+///
+/// Panics if the database is unreachable or cannot be migrated
+#[cfg(test)]
+pub async fn test_database() -> Option<DatabaseConnection> {
+    use migration::MigratorTrait;
+
+    let Ok(url) = std::env::var(TEST_DATABASE_URL) else {
+        eprintln!("{TEST_DATABASE_URL} is unset, skipping database-backed test");
+        return None;
+    };
+
+    let db = sea_orm::Database::connect(url)
+        .await
+        .expect("test database is reachable");
+    TEST_DATABASE_MIGRATED
+        .get_or_try_init(|| migration::Migrator::up(&db, None))
+        .await
+        .expect("test database is migrated");
+    Some(db)
+}
+
+/// Stores a `make_user` with an email of its own in the test database
+///
+/// # Panics
+///
+/// Panics if the user cannot be stored
+#[cfg(test)]
+pub async fn insert_user(db: &DatabaseConnection) -> user::Model {
+    user::ActiveModel::from(user::Model {
+        email: format!("runner-{}@example.com", Uuid::new_v4()),
+        ..make_user()
+    })
+    .insert(db)
+    .await
+    .expect("user is stored")
+}
+
+/// Stores a `make_track` with a track name of its own in the test database
+///
+/// # Panics
+///
+/// Panics if the track cannot be stored
+#[cfg(test)]
+pub async fn insert_track(db: &DatabaseConnection) -> track::Model {
+    track::ActiveModel::from(track::Model {
+        track_name: format!("One More Time {}", Uuid::new_v4()),
+        ..make_track()
+    })
+    .insert(db)
+    .await
+    .expect("track is stored")
+}
+
+/// Stores a `make_activity` of `user_id` with an external ID of its own in the test database
+///
+/// # Panics
+///
+/// Panics if the activity cannot be stored
+#[cfg(test)]
+pub async fn insert_activity(db: &DatabaseConnection, user_id: Uuid) -> activity::Model {
+    activity::ActiveModel::from(activity::Model {
+        user_id,
+        external_id: rand::random::<u32>().into(),
+        ..make_activity()
+    })
+    .insert(db)
+    .await
+    .expect("activity is stored")
+}
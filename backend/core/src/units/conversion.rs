@@ -0,0 +1,124 @@
+//! Metric/imperial unit conversions for API responses
+//!
+//! All values are stored in canonical metric units (meters, m/s, sec/km).
+//! Conversion happens only at the edges, when building response DTOs.
+
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+/// Meters in one international mile
+pub const METERS_PER_MILE: f64 = 1_609.344;
+
+/// Feet in one meter
+pub const FEET_PER_METER: f64 = 3.280_839_895;
+
+/// Unit system requested by the client
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, Display, EnumString,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum UnitSystem {
+    /// Meters, meters per second, seconds per kilometer
+    #[default]
+    Metric,
+    /// Miles, feet, miles per hour, seconds per mile
+    Imperial,
+}
+
+impl UnitSystem {
+    /// Converts a distance in meters
+    ///
+    /// Returns meters for metric and miles for imperial.
+    #[must_use]
+    pub fn distance(self, meters: f64) -> f64 {
+        match self {
+            Self::Metric => meters,
+            Self::Imperial => meters / METERS_PER_MILE,
+        }
+    }
+
+    /// Converts an elevation in meters
+    ///
+    /// Returns meters for metric and feet for imperial.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn elevation(self, meters: f32) -> f32 {
+        match self {
+            Self::Metric => meters,
+            Self::Imperial => (f64::from(meters) * FEET_PER_METER) as f32,
+        }
+    }
+
+    /// Converts a speed in meters per second
+    ///
+    /// Returns m/s for metric and miles per hour for imperial.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn speed(self, meters_per_second: f32) -> f32 {
+        match self {
+            Self::Metric => meters_per_second,
+            Self::Imperial => (f64::from(meters_per_second) * 3_600.0 / METERS_PER_MILE) as f32,
+        }
+    }
+
+    /// Converts a pace in seconds per kilometer
+    ///
+    /// Returns sec/km for metric and sec/mile for imperial.
+    #[must_use]
+    pub fn pace(self, seconds_per_km: f64) -> f64 {
+        match self {
+            Self::Metric => seconds_per_km,
+            Self::Imperial => seconds_per_km * METERS_PER_MILE / 1_000.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metric_is_identity() {
+        let units = UnitSystem::Metric;
+        assert!((units.distance(5_000.0) - 5_000.0).abs() < f64::EPSILON);
+        assert!((units.elevation(120.0) - 120.0).abs() < f32::EPSILON);
+        assert!((units.speed(3.5) - 3.5).abs() < f32::EPSILON);
+        assert!((units.pace(300.0) - 300.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_imperial_distance() {
+        let miles = UnitSystem::Imperial.distance(10_000.0);
+        assert!((miles - 6.213_712).abs() < 1e-5, "10 km = {miles} mi");
+    }
+
+    #[test]
+    fn test_imperial_elevation() {
+        let feet = UnitSystem::Imperial.elevation(100.0);
+        assert!((feet - 328.084).abs() < 1e-3, "100 m = {feet} ft");
+    }
+
+    #[test]
+    fn test_imperial_speed() {
+        let mph = UnitSystem::Imperial.speed(4.4704);
+        assert!((mph - 10.0).abs() < 1e-4, "4.4704 m/s = {mph} mph");
+    }
+
+    #[test]
+    fn test_imperial_pace() {
+        // 5:00 min/km is 8:02.8 min/mile
+        let sec_per_mile = UnitSystem::Imperial.pace(300.0);
+        assert!((sec_per_mile - 482.803_2).abs() < 1e-3, "{sec_per_mile}");
+    }
+
+    #[test]
+    fn test_parse_from_query_value() {
+        assert_eq!(
+            "imperial".parse::<UnitSystem>().unwrap(),
+            UnitSystem::Imperial
+        );
+        assert_eq!("metric".parse::<UnitSystem>().unwrap(), UnitSystem::Metric);
+        assert!("parsecs".parse::<UnitSystem>().is_err());
+    }
+}
@@ -0,0 +1,117 @@
+//! Source unit normalization for ingested streams
+//!
+//! Each provider reports streams in its own units. They are converted to the
+//! canonical metric units (meters, m/s) once, at validation, so everything
+//! downstream can rely on a single unit per column.
+
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+use crate::units::{FEET_PER_METER, METERS_PER_MILE};
+
+/// Unit of a speed stream as reported by a provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeedUnit {
+    MetersPerSecond,
+    KilometersPerHour,
+    MilesPerHour,
+}
+
+impl SpeedUnit {
+    /// Converts a speed in this unit to meters per second
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn to_meters_per_second(self, value: f32) -> f32 {
+        match self {
+            Self::MetersPerSecond => value,
+            Self::KilometersPerHour => (f64::from(value) / 3.6) as f32,
+            Self::MilesPerHour => (f64::from(value) * METERS_PER_MILE / 3_600.0) as f32,
+        }
+    }
+}
+
+/// Unit of a length: an altitude stream as reported by a provider, or a client-given
+/// length such as a simplification tolerance (`m` or `ft`)
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, Display, EnumString,
+)]
+pub enum LengthUnit {
+    #[default]
+    #[serde(rename = "m")]
+    #[strum(serialize = "m")]
+    Meters,
+    #[serde(rename = "ft")]
+    #[strum(serialize = "ft")]
+    Feet,
+}
+
+impl LengthUnit {
+    /// Converts a length in this unit to meters
+    #[must_use]
+    pub fn to_meters(self, value: f64) -> f64 {
+        match self {
+            Self::Meters => value,
+            Self::Feet => value / FEET_PER_METER,
+        }
+    }
+}
+
+/// Units a provider reports its velocity and altitude streams in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamUnits {
+    pub velocity: SpeedUnit,
+    pub altitude: LengthUnit,
+}
+
+impl StreamUnits {
+    /// Units streams are stored in
+    pub const CANONICAL: Self = Self {
+        velocity: SpeedUnit::MetersPerSecond,
+        altitude: LengthUnit::Meters,
+    };
+
+    /// Strava reports `velocity_smooth` in m/s and `altitude` in meters
+    pub const STRAVA: Self = Self::CANONICAL;
+
+    /// Whether values in these units can be stored without conversion
+    #[must_use]
+    pub fn is_canonical(self) -> bool {
+        self == Self::CANONICAL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kilometers_per_hour_to_meters_per_second() {
+        let speed = SpeedUnit::KilometersPerHour.to_meters_per_second(36.0);
+        assert!((speed - 10.0).abs() < 1e-5, "36 km/h = {speed} m/s");
+    }
+
+    #[test]
+    fn test_miles_per_hour_to_meters_per_second() {
+        let speed = SpeedUnit::MilesPerHour.to_meters_per_second(10.0);
+        assert!((speed - 4.4704).abs() < 1e-4, "10 mph = {speed} m/s");
+    }
+
+    #[test]
+    fn test_feet_to_meters() {
+        let meters = LengthUnit::Feet.to_meters(328.084);
+        assert!((meters - 100.0).abs() < 1e-3, "328.084 ft = {meters} m");
+    }
+
+    #[test]
+    fn test_length_unit_parses_abbreviations_only() {
+        assert_eq!("ft".parse::<LengthUnit>().unwrap(), LengthUnit::Feet);
+        assert_eq!("m".parse::<LengthUnit>().unwrap(), LengthUnit::Meters);
+        assert!("yd".parse::<LengthUnit>().is_err());
+        assert!("feet".parse::<LengthUnit>().is_err());
+    }
+
+    #[test]
+    fn test_strava_units_are_canonical() {
+        assert!(StreamUnits::STRAVA.is_canonical());
+    }
+}
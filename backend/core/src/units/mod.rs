@@ -0,0 +1,5 @@
+pub mod conversion;
+pub mod ingestion;
+
+pub use conversion::*;
+pub use ingestion::*;
@@ -0,0 +1,191 @@
+//! OAuth flow against a mock token endpoint
+//!
+//! Runs `start_oauth_flow_with_client` → `exchange_oauth_callback` end to end with
+//! explicit provider URLs, using a minimal HTTP server in place of the provider.
+
+use oauth2::url::Url;
+use oauth2::{RefreshToken, TokenResponse};
+use run_sous_bpm_core::config::{ClientInfo, OAuthProvider};
+use run_sous_bpm_core::services::{
+    exchange_oauth_callback, request_token_refresh, start_oauth_flow_with_client,
+    OAuthSessionManager,
+};
+use run_sous_bpm_integrations::test_support::{json_response, request_body, spawn_mock_server};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+const TOKEN_RESPONSE: &str = r#"{"access_token":"mock-access-token","token_type":"bearer","expires_in":21600,"refresh_token":"mock-refresh-token"}"#;
+
+/// Serves `TOKEN_RESPONSE` to every request, forwarding each request body to the returned channel
+async fn spawn_mock_token_endpoint() -> (String, mpsc::UnboundedReceiver<String>) {
+    let (requests_tx, requests_rx) = mpsc::unbounded_channel();
+    let base_url = spawn_mock_server(move |request| {
+        requests_tx.send(request_body(request).to_string()).ok();
+        json_response(TOKEN_RESPONSE)
+    })
+    .await;
+
+    (base_url, requests_rx)
+}
+
+fn mock_client_info(provider: OAuthProvider, base_url: &str) -> ClientInfo {
+    ClientInfo::new(
+        provider,
+        "test-client-id",
+        "test-client-secret",
+        &format!("{base_url}/oauth/authorize"),
+        &format!("{base_url}/oauth/token"),
+        "http://localhost:3000/api/oauth/callback",
+    )
+    .unwrap()
+}
+
+fn query_param(url: &Url, key: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.into_owned())
+}
+
+#[tokio::test]
+async fn test_oauth_flow_against_mock_token_endpoint() {
+    let (base_url, mut requests) = spawn_mock_token_endpoint().await;
+    let session_store = OAuthSessionManager::new();
+    let user_id = Uuid::new_v4();
+
+    let auth_url = start_oauth_flow_with_client(
+        &mock_client_info(OAuthProvider::Strava, &base_url),
+        &[],
+        &session_store,
+        user_id,
+        Some("http://127.0.0.1:5173".to_string()),
+    )
+    .unwrap();
+
+    let auth_url = Url::parse(&auth_url).unwrap();
+    assert_eq!(auth_url.path(), "/oauth/authorize");
+    assert_eq!(
+        query_param(&auth_url, "client_id").as_deref(),
+        Some("test-client-id")
+    );
+    assert_eq!(
+        query_param(&auth_url, "scope").as_deref(),
+        Some("activity:read_all")
+    );
+    assert!(query_param(&auth_url, "code_challenge").is_some());
+    let state = query_param(&auth_url, "state").expect("Authorize URL carries a CSRF state");
+
+    let exchanged = exchange_oauth_callback(
+        "mock-auth-code".to_string(),
+        state.clone(),
+        &session_store,
+        |provider| mock_client_info(provider, &base_url),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(exchanged.provider.to_string(), "strava");
+    assert_eq!(exchanged.user_id, user_id);
+    assert_eq!(exchanged.scopes, vec!["activity:read_all".to_string()]);
+    assert_eq!(
+        exchanged.return_origin.as_deref(),
+        Some("http://127.0.0.1:5173")
+    );
+    assert_eq!(exchanged.token.access_token().secret(), "mock-access-token");
+    assert_eq!(
+        exchanged.token.refresh_token().unwrap().secret(),
+        "mock-refresh-token"
+    );
+
+    let token_request = requests.recv().await.unwrap();
+    assert!(token_request.contains("grant_type=authorization_code"));
+    assert!(token_request.contains("code=mock-auth-code"));
+    assert!(
+        token_request.contains("code_verifier="),
+        "PKCE verifier must be sent to the token endpoint"
+    );
+    assert!(token_request.contains("client_secret=test-client-secret"));
+
+    // The CSRF state is single-use
+    let replay = exchange_oauth_callback(
+        "mock-auth-code".to_string(),
+        state,
+        &session_store,
+        |provider| mock_client_info(provider, &base_url),
+    )
+    .await;
+    assert!(replay.is_err());
+}
+
+#[tokio::test]
+async fn test_unknown_state_is_rejected_without_calling_provider() {
+    let (base_url, mut requests) = spawn_mock_token_endpoint().await;
+    let session_store = OAuthSessionManager::new();
+
+    let result = exchange_oauth_callback(
+        "mock-auth-code".to_string(),
+        "unknown-state".to_string(),
+        &session_store,
+        |provider| mock_client_info(provider, &base_url),
+    )
+    .await;
+
+    assert!(result.is_err());
+    assert!(
+        requests.try_recv().is_err(),
+        "Token endpoint must not be called"
+    );
+}
+
+#[tokio::test]
+async fn test_token_refresh_against_mock_token_endpoint() {
+    let (base_url, mut requests) = spawn_mock_token_endpoint().await;
+
+    let token = request_token_refresh(
+        &mock_client_info(OAuthProvider::Spotify, &base_url),
+        &RefreshToken::new("old-refresh-token".to_string()),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(token.access_token().secret(), "mock-access-token");
+
+    let token_request = requests.recv().await.unwrap();
+    assert!(token_request.contains("grant_type=refresh_token"));
+    assert!(token_request.contains("refresh_token=old-refresh-token"));
+}
+
+#[tokio::test]
+async fn test_authorize_url_includes_merged_extra_scopes() {
+    let (base_url, _requests) = spawn_mock_token_endpoint().await;
+    let session_store = OAuthSessionManager::new();
+
+    let auth_url = start_oauth_flow_with_client(
+        &mock_client_info(OAuthProvider::Strava, &base_url),
+        &["read".to_string(), "profile:read_all".to_string()],
+        &session_store,
+        Uuid::new_v4(),
+        None,
+    )
+    .unwrap();
+
+    let auth_url = Url::parse(&auth_url).unwrap();
+    assert_eq!(
+        query_param(&auth_url, "scope").as_deref(),
+        Some("activity:read_all,read,profile:read_all")
+    );
+
+    // Requested scopes are recorded for providers that don't report the granted ones
+    let state = query_param(&auth_url, "state").unwrap();
+    let exchanged = exchange_oauth_callback(
+        "mock-auth-code".to_string(),
+        state,
+        &session_store,
+        |provider| mock_client_info(provider, &base_url),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        exchanged.scopes,
+        vec!["activity:read_all", "read", "profile:read_all"]
+    );
+}
@@ -7,6 +7,14 @@ pub enum IntegrationError {
     TokenExpired,
     RefreshFailed(String),
     Deserialization(String),
+    /// The integration is missing its configuration (e.g. an unset API key)
+    NotConfigured(String),
+    /// Requests are held back until the given time to stay under the provider's rate limit
+    RateLimited(chrono::DateTime<chrono::Utc>),
+    /// The provider has no such resource (e.g. an unknown Last.fm username)
+    NotFound(String),
+    /// The provider is down or failed to handle the request, worth retrying later
+    Unavailable(String),
     Other(String),
 }
 
@@ -20,6 +28,15 @@ impl std::fmt::Display for IntegrationError {
             Self::TokenExpired => write!(f, "OAuth token expired and no refresh token available"),
             Self::RefreshFailed(msg) => write!(f, "Token refresh failed: {msg}"),
             Self::Deserialization(msg) => write!(f, "Failed to deserialize response: {msg}"),
+            Self::NotConfigured(msg) => write!(f, "Integration not configured: {msg}"),
+            Self::RateLimited(resume_at) => {
+                write!(
+                    f,
+                    "Rate limit nearly exhausted, requests paused until {resume_at}"
+                )
+            }
+            Self::NotFound(msg) => write!(f, "Not found: {msg}"),
+            Self::Unavailable(msg) => write!(f, "Integration unavailable: {msg}"),
             Self::Other(msg) => write!(f, "Integration error: {msg}"),
         }
     }
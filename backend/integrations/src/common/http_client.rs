@@ -1,3 +1,13 @@
+use std::time::Duration;
+
+/// Environment variable overriding the connect timeout, in seconds
+pub const HTTP_CONNECT_TIMEOUT_VAR: &str = "HTTP_CONNECT_TIMEOUT_SECS";
+/// Environment variable overriding the overall request timeout, in seconds
+pub const HTTP_REQUEST_TIMEOUT_VAR: &str = "HTTP_REQUEST_TIMEOUT_SECS";
+
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub struct AuthenticatedClient {
     http: reqwest::Client,
 }
@@ -11,13 +21,30 @@ impl Default for AuthenticatedClient {
 impl AuthenticatedClient {
     /// Creates a new authenticated HTTP client
     ///
+    /// Timeouts default to 5s to connect and 30s per request, overridable with
+    /// `HTTP_CONNECT_TIMEOUT_SECS` and `HTTP_REQUEST_TIMEOUT_SECS`.
+    ///
     /// # Panics
     ///
     /// Panics if the HTTP client fails to build (should never happen with default config)
     #[must_use]
     pub fn new() -> Self {
+        Self::with_timeouts(connect_timeout(), request_timeout())
+    }
+
+    /// Creates a client with explicit connect and overall request timeouts
+    ///
+    /// Redirects are never followed, so bearer tokens are not forwarded to another host.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the HTTP client fails to build (should never happen with default config)
+    #[must_use]
+    pub fn with_timeouts(connect_timeout: Duration, request_timeout: Duration) -> Self {
         let http = reqwest::ClientBuilder::new()
             .redirect(reqwest::redirect::Policy::none())
+            .connect_timeout(connect_timeout)
+            .timeout(request_timeout)
             .build()
             .expect("Client should build");
         Self { http }
@@ -75,3 +102,82 @@ impl AuthenticatedClient {
         request.send().await
     }
 }
+
+/// Connect timeout for outgoing HTTP clients: `HTTP_CONNECT_TIMEOUT_SECS`, 5s by default
+#[must_use]
+pub fn connect_timeout() -> Duration {
+    timeout_from_env(HTTP_CONNECT_TIMEOUT_VAR, DEFAULT_CONNECT_TIMEOUT)
+}
+
+/// Overall request timeout for outgoing HTTP clients: `HTTP_REQUEST_TIMEOUT_SECS`, 30s by default
+#[must_use]
+pub fn request_timeout() -> Duration {
+    timeout_from_env(HTTP_REQUEST_TIMEOUT_VAR, DEFAULT_REQUEST_TIMEOUT)
+}
+
+/// Reads a timeout in whole seconds, keeping the default when unset or not a positive number
+fn timeout_from_env(var: &str, default: Duration) -> Duration {
+    parse_timeout_secs(std::env::var(var).ok().as_deref()).unwrap_or(default)
+}
+
+fn parse_timeout_secs(value: Option<&str>) -> Option<Duration> {
+    value?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|&secs| secs > 0)
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{empty_response, spawn_mock_server, spawn_slow_mock_server};
+
+    #[tokio::test]
+    async fn test_slow_server_times_out() {
+        let base_url =
+            spawn_slow_mock_server(Duration::from_secs(30), |_| empty_response("200 OK")).await;
+        let client =
+            AuthenticatedClient::with_timeouts(Duration::from_secs(1), Duration::from_millis(200));
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            client.get_with_bearer(&base_url, "token"),
+        )
+        .await
+        .expect("Request should error out instead of hanging");
+
+        assert!(result.unwrap_err().is_timeout());
+    }
+
+    #[tokio::test]
+    async fn test_redirects_are_not_followed() {
+        let base_url = spawn_mock_server(|_| {
+            "HTTP/1.1 302 Found\r\nLocation: http://127.0.0.1:1/\r\nContent-Length: 0\r\n\r\n"
+                .to_string()
+        })
+        .await;
+        let client =
+            AuthenticatedClient::with_timeouts(Duration::from_secs(1), Duration::from_secs(5));
+
+        let response = client.get_with_bearer(&base_url, "token").await.unwrap();
+
+        assert_eq!(response.status(), reqwest::StatusCode::FOUND);
+    }
+
+    #[test]
+    fn test_parse_timeout_secs() {
+        assert_eq!(
+            parse_timeout_secs(Some("10")),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            parse_timeout_secs(Some(" 3 ")),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(parse_timeout_secs(Some("0")), None);
+        assert_eq!(parse_timeout_secs(Some("soon")), None);
+        assert_eq!(parse_timeout_secs(None), None);
+    }
+}
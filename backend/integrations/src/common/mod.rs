@@ -1,7 +1,11 @@
 pub mod error;
 pub mod http_client;
 pub mod integration_client;
+pub mod reachability;
+pub mod secret;
 
 pub use error::IntegrationError;
 pub use http_client::AuthenticatedClient;
 pub use integration_client::IntegrationClient;
+pub use reachability::{ProbeResult, ReachabilityChecker, ReachabilityStatus};
+pub use secret::SecretToken;
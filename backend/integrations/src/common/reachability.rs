@@ -0,0 +1,208 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::sync::Mutex;
+use tokio::task::JoinSet;
+
+/// Timeout for a single reachability probe, kept short so a health check never hangs
+pub const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long probe results are reused before the providers are probed again
+pub const DEFAULT_RESULT_TTL: Duration = Duration::from_secs(30);
+
+/// Whether a provider answered a reachability probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReachabilityStatus {
+    Up,
+    Down,
+}
+
+/// Outcome of probing one provider
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProbeResult {
+    pub name: String,
+    pub status: ReachabilityStatus,
+}
+
+/// Checks that third-party providers answer at all, without authenticating
+///
+/// A probe is a single `HEAD` request to the provider base URL. Any response
+/// below 500 counts as up: providers reject anonymous calls with 401 or 404,
+/// which still proves they are reachable.
+///
+/// Results are reused for a short TTL, so however often the health endpoint is
+/// called, each provider gets at most one probe per TTL.
+pub struct ReachabilityChecker {
+    http: reqwest::Client,
+    providers: Vec<(String, String)>,
+    result_ttl: Duration,
+    last_results: Mutex<Option<(Instant, Vec<ProbeResult>)>>,
+}
+
+impl ReachabilityChecker {
+    /// Creates a checker with no providers, each probe limited to `timeout`
+    ///
+    /// Results are reused for `DEFAULT_RESULT_TTL`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the HTTP client fails to build (should never happen with default config)
+    #[must_use]
+    pub fn new(timeout: Duration) -> Self {
+        let http = reqwest::ClientBuilder::new()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(timeout)
+            .build()
+            .expect("Client should build");
+        Self {
+            http,
+            providers: Vec::new(),
+            result_ttl: DEFAULT_RESULT_TTL,
+            last_results: Mutex::new(None),
+        }
+    }
+
+    /// Sets how long probe results are reused (`Duration::ZERO` probes on every check)
+    #[must_use]
+    pub fn with_result_ttl(mut self, ttl: Duration) -> Self {
+        self.result_ttl = ttl;
+        self
+    }
+
+    /// Adds a provider to probe at `base_url`
+    #[must_use]
+    pub fn with_provider(mut self, name: impl Into<String>, base_url: impl Into<String>) -> Self {
+        self.providers.push((name.into(), base_url.into()));
+        self
+    }
+
+    /// Probes every provider concurrently, results in registration order
+    ///
+    /// Returns the previous results instead while they are younger than the TTL.
+    /// Concurrent callers wait for a single round of probes.
+    pub async fn check_all(&self) -> Vec<ProbeResult> {
+        let mut last_results = self.last_results.lock().await;
+        if let Some((checked_at, results)) = last_results.as_ref() {
+            if checked_at.elapsed() < self.result_ttl {
+                return results.clone();
+            }
+        }
+
+        let results = self.probe_all().await;
+        *last_results = Some((Instant::now(), results.clone()));
+        results
+    }
+
+    async fn probe_all(&self) -> Vec<ProbeResult> {
+        let mut probes = JoinSet::new();
+        for (index, (_, base_url)) in self.providers.iter().enumerate() {
+            let request = self.http.head(base_url).send();
+            probes.spawn(async move {
+                let status = match request.await {
+                    Ok(response) if !response.status().is_server_error() => ReachabilityStatus::Up,
+                    _ => ReachabilityStatus::Down,
+                };
+                (index, status)
+            });
+        }
+
+        let mut statuses = vec![ReachabilityStatus::Down; self.providers.len()];
+        while let Some(joined) = probes.join_next().await {
+            if let Ok((index, status)) = joined {
+                statuses[index] = status;
+            }
+        }
+
+        self.providers
+            .iter()
+            .zip(statuses)
+            .map(|((name, _), status)| ProbeResult {
+                name: name.clone(),
+                status,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{closed_port_url, empty_response, spawn_mock_server};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_unauthorized_answer_counts_as_up() {
+        let base_url = spawn_mock_server(|_| empty_response("401 Unauthorized")).await;
+        let checker =
+            ReachabilityChecker::new(DEFAULT_PROBE_TIMEOUT).with_provider("strava", base_url);
+
+        let results = checker.check_all().await;
+
+        assert_eq!(results[0].status, ReachabilityStatus::Up);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_and_failing_providers_are_down() {
+        let failing = spawn_mock_server(|_| empty_response("503 Service Unavailable")).await;
+        let up = spawn_mock_server(|_| empty_response("200 OK")).await;
+        let checker = ReachabilityChecker::new(DEFAULT_PROBE_TIMEOUT)
+            .with_provider("strava", closed_port_url().await)
+            .with_provider("lastfm", failing)
+            .with_provider("spotify", up);
+
+        let results = checker.check_all().await;
+
+        let statuses: Vec<_> = results
+            .iter()
+            .map(|r| (r.name.as_str(), r.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("strava", ReachabilityStatus::Down),
+                ("lastfm", ReachabilityStatus::Down),
+                ("spotify", ReachabilityStatus::Up),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_results_are_reused_within_ttl() {
+        let probes = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&probes);
+        let base_url = spawn_mock_server(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            empty_response("200 OK")
+        })
+        .await;
+        let checker =
+            ReachabilityChecker::new(DEFAULT_PROBE_TIMEOUT).with_provider("strava", base_url);
+
+        let first = checker.check_all().await;
+        let second = checker.check_all().await;
+
+        assert_eq!(first, second);
+        assert_eq!(probes.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_results_expire_after_ttl() {
+        let probes = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&probes);
+        let base_url = spawn_mock_server(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            empty_response("200 OK")
+        })
+        .await;
+        let checker = ReachabilityChecker::new(DEFAULT_PROBE_TIMEOUT)
+            .with_result_ttl(Duration::ZERO)
+            .with_provider("strava", base_url);
+
+        checker.check_all().await;
+        checker.check_all().await;
+
+        assert_eq!(probes.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,66 @@
+use std::ops::Deref;
+
+use zeroize::Zeroizing;
+
+/// A decrypted OAuth token, wiped from memory when dropped
+///
+/// Dereferences to `str`, so `&token` can be passed straight to the client
+/// methods taking an access token. `Debug` is redacted and there is no
+/// `Display`, so the secret never ends up in logs by accident.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretToken(Zeroizing<String>);
+
+impl SecretToken {
+    #[must_use]
+    pub fn new(token: String) -> Self {
+        Self(Zeroizing::new(token))
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretToken {
+    fn from(token: String) -> Self {
+        Self::new(token)
+    }
+}
+
+impl Deref for SecretToken {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl std::fmt::Debug for SecretToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretToken(<redacted>)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_output_does_not_reveal_token() {
+        let token = SecretToken::new("strava-access-token".to_string());
+
+        let debug = format!("{token:?}");
+
+        assert!(!debug.contains("strava-access-token"), "{debug}");
+        assert_eq!(debug, "SecretToken(<redacted>)");
+    }
+
+    #[test]
+    fn test_derefs_to_the_token() {
+        let token = SecretToken::from("abc".to_string());
+
+        assert_eq!(&*token, "abc");
+        assert_eq!(token.as_str(), "abc");
+    }
+}
@@ -1,32 +1,132 @@
+use std::future::Future;
+
+use chrono::{DateTime, Duration, Utc};
 use lastfm_client::types::RecentTrack;
-use lastfm_client::LastFmClient as LastFmApiClient;
+use lastfm_client::{LastFmClient as LastFmApiClient, LastFmError};
 
-use crate::common::IntegrationError;
+use crate::common::{http_client::request_timeout, IntegrationError};
 
-/// Last.fm API client for fetching user listening history
-pub struct LastFmClient {
-    client: LastFmApiClient,
+/// Environment variable holding the Last.fm API key
+pub const LAST_FM_API_KEY_VAR: &str = "LAST_FM_API_KEY";
+
+/// Environment variable overriding the Last.fm API base URL
+///
+/// The `lastfm-client` crate builds its request URLs itself, so only the
+/// integration health probe follows this; API calls always reach Last.fm.
+pub const LAST_FM_API_URL_VAR: &str = "LAST_FM_API_URL";
+
+/// Public Last.fm API endpoint
+pub const DEFAULT_LAST_FM_API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+
+/// Reads the Last.fm API base URL from `LAST_FM_API_URL`, defaulting to the public API
+#[must_use]
+pub fn last_fm_api_url() -> String {
+    resolve_api_url(std::env::var(LAST_FM_API_URL_VAR).ok().as_deref())
+}
+
+fn resolve_api_url(configured: Option<&str>) -> String {
+    configured
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .unwrap_or(DEFAULT_LAST_FM_API_URL)
+        .to_string()
+}
+
+/// Seconds requests are held back after Last.fm reports its rate limit exceeded
+///
+/// Last.fm sends no `Retry-After`, so a fixed pause is used.
+pub const LAST_FM_RATE_LIMIT_BACKOFF_SECONDS: i64 = 60;
+
+/// Maps a `lastfm-client` error to the matching `IntegrationError` variant
+///
+/// API errors are told apart by their Last.fm error code, so an unknown
+/// username can be reported as a user error and an outage as a gateway error.
+fn map_lastfm_error(error: LastFmError) -> IntegrationError {
+    match error {
+        LastFmError::Api {
+            error_code,
+            message,
+            ..
+        } => map_api_error(error_code, message, Utc::now()),
+        LastFmError::Http(e) => IntegrationError::Unavailable(e.to_string()),
+        other => IntegrationError::Other(other.to_string()),
+    }
+}
+
+/// Maps a Last.fm API error code, see <https://www.last.fm/api/errorcodes>
+fn map_api_error(code: u32, message: String, now: DateTime<Utc>) -> IntegrationError {
+    match code {
+        // Invalid parameters, which Last.fm answers for unknown users
+        6 => IntegrationError::NotFound(message),
+        // Operation failed, service offline, temporarily unavailable
+        8 | 11 | 16 => IntegrationError::Unavailable(message),
+        // Invalid or suspended API key
+        10 | 26 => IntegrationError::NotConfigured(message),
+        29 => IntegrationError::RateLimited(
+            now + Duration::seconds(LAST_FM_RATE_LIMIT_BACKOFF_SECONDS),
+        ),
+        _ => IntegrationError::Other(format!("Last.fm error {code}: {message}")),
+    }
 }
 
-impl Default for LastFmClient {
-    fn default() -> Self {
-        Self::new()
+/// Checks that a Last.fm API key is configured (set and not blank)
+///
+/// # Errors
+///
+/// Returns `IntegrationError::NotConfigured` if `configured` is `None` or blank
+pub fn require_api_key(configured: Option<&str>) -> Result<(), IntegrationError> {
+    if configured.is_some_and(|key| !key.trim().is_empty()) {
+        Ok(())
+    } else {
+        Err(IntegrationError::NotConfigured(format!(
+            "{LAST_FM_API_KEY_VAR} is not set"
+        )))
     }
 }
 
+/// Runs a Last.fm call, giving up once `timeout` has elapsed
+///
+/// `lastfm-client` builds its own HTTP client, so the configured timeout is
+/// applied around each call instead.
+async fn with_timeout<T>(
+    timeout: std::time::Duration,
+    call: impl Future<Output = Result<T, LastFmError>>,
+) -> Result<T, IntegrationError> {
+    tokio::time::timeout(timeout, call)
+        .await
+        .map_err(|_| {
+            IntegrationError::Unavailable(format!(
+                "Last.fm did not answer within {}s",
+                timeout.as_secs_f32()
+            ))
+        })?
+        .map_err(map_lastfm_error)
+}
+
+/// Last.fm API client for fetching user listening history
+pub struct LastFmClient {
+    client: LastFmApiClient,
+    timeout: std::time::Duration,
+}
+
 impl LastFmClient {
     /// Creates a new Last.fm API client
     ///
     /// Reads the API key from the `LAST_FM_API_KEY` environment variable.
+    /// Each call gives up after `HTTP_REQUEST_TIMEOUT_SECS`, like the other integrations.
     ///
-    /// # Panics
-    /// Panics if the `LAST_FM_API_KEY` environment variable is not set
-    #[must_use]
-    pub fn new() -> Self {
-        Self {
-            client: LastFmApiClient::new()
-                .expect("LAST_FM_API_KEY environment variable must be set"),
-        }
+    /// # Errors
+    ///
+    /// Returns `IntegrationError::NotConfigured` if `LAST_FM_API_KEY` is unset or empty,
+    /// so music features can degrade gracefully instead of panicking
+    pub fn try_new() -> Result<Self, IntegrationError> {
+        require_api_key(std::env::var(LAST_FM_API_KEY_VAR).ok().as_deref())?;
+
+        let client = LastFmApiClient::new().map_err(map_lastfm_error)?;
+        Ok(Self {
+            client,
+            timeout: request_timeout(),
+        })
     }
 
     /// Validates if a Last.fm username exists
@@ -34,10 +134,18 @@ impl LastFmClient {
     /// # Arguments
     /// * `username` - Last.fm username to validate
     ///
+    /// # Errors
+    ///
+    /// Returns an error if Last.fm can't be reached or rejects the request
+    /// for another reason than an unknown user
+    ///
     /// # Returns
     /// `true` if the username exists, `false` otherwise
-    pub async fn is_username_valid(&self, username: &str) -> bool {
-        self.client.user_exists(username).await.unwrap_or(false)
+    pub async fn is_username_valid(&self, username: &str) -> Result<bool, IntegrationError> {
+        match with_timeout(self.timeout, self.client.user_exists(username)).await {
+            Err(IntegrationError::NotFound(_)) => Ok(false),
+            result => result,
+        }
     }
 
     /// Fetches tracks played within a specific time range
@@ -46,13 +154,17 @@ impl LastFmClient {
     /// * `username` - Last.fm username to fetch data for
     /// * `start_timestamp` - Unix timestamp (seconds) for start of range
     /// * `end_timestamp` - Unix timestamp (seconds) for end of range
+    /// * `include_now_playing` - Keep the "now playing" track, which has no date
     ///
     /// # Errors
     ///
-    /// Returns an error if the Last.fm API request fails
+    /// Returns `IntegrationError::NotFound` for an unknown username,
+    /// `IntegrationError::Unavailable` when Last.fm is down, or another variant
+    /// if the Last.fm API request fails
     ///
     /// # Returns
-    /// Vector of `RecentTrack` sorted chronologically, filtered to exclude "now playing" tracks
+    /// Vector of `RecentTrack` sorted chronologically, without the "now playing" track
+    /// unless `include_now_playing` is set
     ///
     /// # Note
     /// Uses Last.fm API's native `from` and `to` parameters for efficient server-side filtering
@@ -61,20 +173,22 @@ impl LastFmClient {
         username: &str,
         start_timestamp: i64,
         end_timestamp: i64,
+        include_now_playing: bool,
     ) -> Result<Vec<RecentTrack>, IntegrationError> {
         // Fetch tracks between timestamps using Last.fm API's native time range filtering
-        let tracks = self
-            .client
-            .recent_tracks(username)
-            .between(start_timestamp, end_timestamp)
-            .fetch()
-            .await
-            .map_err(|e| IntegrationError::Other(e.to_string()))?;
+        let tracks = with_timeout(
+            self.timeout,
+            self.client
+                .recent_tracks(username)
+                .between(start_timestamp, end_timestamp)
+                .fetch(),
+        )
+        .await?;
 
-        // Filter out "now playing" tracks (tracks without a timestamp)
+        // Filter out "now playing" tracks (tracks without a timestamp) unless requested
         let filtered_tracks: Vec<RecentTrack> = tracks
             .into_iter()
-            .filter(|track| track.date.is_some())
+            .filter(|track| include_now_playing || track.date.is_some())
             .collect();
 
         Ok(filtered_tracks)
@@ -88,17 +202,99 @@ impl LastFmClient {
     ///
     /// # Errors
     ///
-    /// Returns an error if the Last.fm API request fails
+    /// Returns `IntegrationError::NotFound` for an unknown username,
+    /// `IntegrationError::Unavailable` when Last.fm is down, or another variant
+    /// if the Last.fm API request fails
     pub async fn get_recent_tracks(
         &self,
         username: &str,
         limit: u32,
     ) -> Result<Vec<RecentTrack>, IntegrationError> {
-        self.client
-            .recent_tracks(username)
-            .limit(limit)
-            .fetch()
+        with_timeout(
+            self.timeout,
+            self.client.recent_tracks(username).limit(limit).fetch(),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_url_defaults_to_public_api() {
+        assert_eq!(resolve_api_url(None), DEFAULT_LAST_FM_API_URL);
+        assert_eq!(resolve_api_url(Some("")), DEFAULT_LAST_FM_API_URL);
+        assert_eq!(
+            resolve_api_url(Some(" http://localhost:9091/2.0/ ")),
+            "http://localhost:9091/2.0/"
+        );
+    }
+
+    fn at(timestamp: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(timestamp).unwrap().to_utc()
+    }
+
+    #[test]
+    fn test_missing_or_blank_api_key_is_not_configured() {
+        assert!(matches!(
+            require_api_key(None),
+            Err(IntegrationError::NotConfigured(_))
+        ));
+        assert!(matches!(
+            require_api_key(Some(" ")),
+            Err(IntegrationError::NotConfigured(_))
+        ));
+        assert!(require_api_key(Some("0123abcd")).is_ok());
+    }
+
+    #[test]
+    fn test_unknown_user_and_server_errors_map_to_different_variants() {
+        let now = at("2025-11-10T10:00:00Z");
+
+        let unknown_user = map_api_error(6, "User not found".to_string(), now);
+        let server_error = map_api_error(11, "Service Offline".to_string(), now);
+
+        assert!(
+            matches!(&unknown_user, IntegrationError::NotFound(msg) if msg == "User not found"),
+            "{unknown_user}"
+        );
+        assert!(
+            matches!(&server_error, IntegrationError::Unavailable(msg) if msg == "Service Offline"),
+            "{server_error}"
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_error_pauses_requests() {
+        let now = at("2025-11-10T10:00:00Z");
+
+        let error = map_api_error(29, "Rate Limit Exceeded".to_string(), now);
+
+        assert!(
+            matches!(error, IntegrationError::RateLimited(resume_at) if resume_at == at("2025-11-10T10:01:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_unexpected_api_error_keeps_its_code() {
+        let error = map_api_error(13, "Invalid method signature".to_string(), Utc::now());
+
+        assert!(
+            matches!(&error, IntegrationError::Other(msg) if msg == "Last.fm error 13: Invalid method signature"),
+            "{error}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_call_without_answer_times_out_as_unavailable() {
+        let call = std::future::pending::<Result<(), LastFmError>>();
+
+        let error = with_timeout(std::time::Duration::from_millis(20), call)
             .await
-            .map_err(|e| IntegrationError::Other(e.to_string()))
+            .unwrap_err();
+
+        assert!(matches!(error, IntegrationError::Unavailable(_)), "{error}");
     }
 }
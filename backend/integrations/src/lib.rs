@@ -2,3 +2,5 @@ pub mod common;
 pub mod lastfm;
 pub mod spotify;
 pub mod strava;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
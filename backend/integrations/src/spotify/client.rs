@@ -1,7 +1,7 @@
 use serde::Serialize;
 
 use crate::common::{IntegrationClient, IntegrationError};
-use crate::spotify::SpotifyRecentlyPlayedResponse;
+use crate::spotify::{SpotifyRecentlyPlayedResponse, SpotifySearchResponse};
 
 /// Query parameters for Spotify recently played endpoint
 ///
@@ -13,6 +13,38 @@ pub struct SpotifyRecentlyPlayedParams {
     pub before: Option<u64>,
 }
 
+/// Environment variable overriding the Spotify Web API base URL, e.g. to target a mock
+pub const SPOTIFY_API_URL_VAR: &str = "SPOTIFY_API_URL";
+
+/// Public Spotify Web API base URL
+pub const DEFAULT_SPOTIFY_API_URL: &str = "https://api.spotify.com/v1";
+
+/// Number of candidates requested per track search
+const SEARCH_CANDIDATE_LIMIT: u8 = 5;
+
+/// Seconds to wait after a 429 response without a usable `Retry-After` header
+const DEFAULT_RETRY_AFTER_SECONDS: i64 = 5;
+
+/// Query parameters for Spotify track search
+#[derive(Debug, Serialize)]
+pub struct SpotifySearchParams {
+    q: String,
+    r#type: &'static str,
+    limit: u8,
+}
+
+impl SpotifySearchParams {
+    /// Searches tracks by title and artist using Spotify field filters
+    #[must_use]
+    pub fn track(artist: &str, track: &str) -> Self {
+        Self {
+            q: format!("track:{track} artist:{artist}"),
+            r#type: "track",
+            limit: SEARCH_CANDIDATE_LIMIT,
+        }
+    }
+}
+
 /// Spotify API client for music enrichment
 ///
 /// Foundation for future Spotify integration to enrich Last.fm data
@@ -32,6 +64,16 @@ impl SpotifyApiClient {
             base_url,
         }
     }
+
+    /// Creates a client for the base URL in `SPOTIFY_API_URL`, or the public API when unset
+    #[must_use]
+    pub fn from_env(integration_client: IntegrationClient) -> Self {
+        Self::new(
+            integration_client,
+            spotify_api_url(std::env::var(SPOTIFY_API_URL_VAR).ok().as_deref()),
+        )
+    }
+
     /// Fetches the recently played tracks of the authenticated user from Spotify
     /// # Errors
     ///
@@ -51,4 +93,148 @@ impl SpotifyApiClient {
             response.json().await.map_err(IntegrationError::from)?;
         Ok(spotify_response)
     }
+
+    /// Searches Spotify's catalog for track candidates
+    ///
+    /// # Errors
+    ///
+    /// Returns `IntegrationError::RateLimited` with the time given by `Retry-After`
+    /// when Spotify throttles the request, or an error if the HTTP request fails,
+    /// Spotify answers with another error status or response deserialization fails
+    pub async fn search_tracks(
+        &self,
+        access_token: &str,
+        params: &SpotifySearchParams,
+    ) -> Result<SpotifySearchResponse, IntegrationError> {
+        let url = format!("{}/search", self.base_url);
+
+        let response = self
+            .integration_client
+            .get_with_query(&url, access_token, params)
+            .await?;
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(IntegrationError::RateLimited(retry_at(
+                response.headers(),
+                chrono::Utc::now(),
+            )));
+        }
+        response
+            .error_for_status()?
+            .json::<SpotifySearchResponse>()
+            .await
+            .map_err(|e| IntegrationError::Deserialization(e.to_string()))
+    }
+}
+
+/// Resolves the configured base URL, without trailing slash so paths can be appended
+fn spotify_api_url(configured: Option<&str>) -> String {
+    configured
+        .map(|url| url.trim().trim_end_matches('/'))
+        .filter(|url| !url.is_empty())
+        .unwrap_or(DEFAULT_SPOTIFY_API_URL)
+        .to_string()
+}
+
+/// When a throttled request may be retried, from the `Retry-After` header in seconds
+fn retry_at(
+    headers: &reqwest::header::HeaderMap,
+    now: chrono::DateTime<chrono::Utc>,
+) -> chrono::DateTime<chrono::Utc> {
+    let seconds = headers
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .filter(|seconds| *seconds >= 0)
+        .unwrap_or(DEFAULT_RETRY_AFTER_SECONDS);
+    now + chrono::Duration::seconds(seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::common::AuthenticatedClient;
+    use crate::test_support::{json_response, spawn_mock_server, SPOTIFY_SEARCH_QUEEN};
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_client_calls_configured_base_url() {
+        let (request_tx, mut requests) = mpsc::unbounded_channel();
+        let base_url = spawn_mock_server(move |request| {
+            request_tx.send(request.to_string()).ok();
+            json_response(SPOTIFY_SEARCH_QUEEN)
+        })
+        .await;
+        let client = SpotifyApiClient::new(
+            IntegrationClient::new(Arc::new(AuthenticatedClient::new())),
+            format!("{base_url}/v1"),
+        );
+
+        let response = client
+            .search_tracks(
+                "token",
+                &SpotifySearchParams::track("Queen", "Don't Stop Me Now"),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.tracks.items[0].name, "Don't Stop Me Now");
+        let request = requests.recv().await.unwrap();
+        assert!(request.starts_with("GET /v1/search?"), "{request}");
+        assert!(request.contains("authorization: Bearer token"), "{request}");
+    }
+
+    #[test]
+    fn test_throttled_search_waits_for_retry_after() {
+        let now = chrono::Utc::now();
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(
+            retry_at(&headers, now),
+            now + chrono::Duration::seconds(DEFAULT_RETRY_AFTER_SECONDS)
+        );
+
+        headers.insert(reqwest::header::RETRY_AFTER, "7".parse().unwrap());
+        assert_eq!(retry_at(&headers, now), now + chrono::Duration::seconds(7));
+
+        // HTTP dates are not sent by Spotify, fall back to the default wait
+        headers.insert(
+            reqwest::header::RETRY_AFTER,
+            "Wed, 21 Oct 2026 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(
+            retry_at(&headers, now),
+            now + chrono::Duration::seconds(DEFAULT_RETRY_AFTER_SECONDS)
+        );
+    }
+
+    #[test]
+    fn test_api_url_defaults_to_public_api() {
+        assert_eq!(spotify_api_url(None), DEFAULT_SPOTIFY_API_URL);
+        assert_eq!(spotify_api_url(Some("  ")), DEFAULT_SPOTIFY_API_URL);
+        assert_eq!(
+            spotify_api_url(Some("http://localhost:9090/v1/")),
+            "http://localhost:9090/v1"
+        );
+    }
+
+    #[test]
+    fn test_track_search_query_uses_field_filters() {
+        let query = reqwest::Client::new()
+            .get("http://localhost/search")
+            .query(&SpotifySearchParams::track("Queen", "Don't Stop Me Now"))
+            .build()
+            .expect("request should build")
+            .url()
+            .query()
+            .unwrap_or_default()
+            .to_string();
+
+        assert!(
+            query.contains("q=track%3ADon%27t+Stop+Me+Now+artist%3AQueen"),
+            "{query}"
+        );
+        assert!(query.contains("type=track"), "{query}");
+        assert!(query.contains("limit=5"), "{query}");
+    }
 }
@@ -24,6 +24,18 @@ pub struct SpotifyPlayedItem {
     pub track: SpotifyTrack,
 }
 
+/// Response of the Spotify search endpoint for `type=track`
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SpotifySearchResponse {
+    pub tracks: SpotifyTrackPage,
+}
+
+/// A page of tracks in a Spotify search response
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SpotifyTrackPage {
+    pub items: Vec<SpotifyTrack>,
+}
+
 #[allow(dead_code)]
 #[derive(Deserialize, Serialize, Debug)]
 pub struct SpotifyTrack {
@@ -1,8 +1,11 @@
+use std::sync::Arc;
+
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     common::{IntegrationClient, IntegrationError},
-    strava::{StravaActivityResponse, StravaActivityStreamResponse},
+    strava::{StravaActivityResponse, StravaActivityStreamResponse, StravaRateLimiter},
 };
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -13,50 +16,123 @@ pub struct StravaActivitiesParams {
     pub page: Option<u32>,
 }
 
+/// Sampling resolution for activity streams
+///
+/// When omitted Strava returns every recorded point; lower resolutions
+/// are cheaper to fetch and store for quick previews.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamResolution {
+    Low,
+    Medium,
+    High,
+}
+
+/// Series used by Strava to align points when a resolution is requested
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamSeriesType {
+    #[default]
+    Distance,
+    Time,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct StravaActivityStreamsParams {
     pub keys: String,
+    pub resolution: Option<StreamResolution>,
+    pub series_type: StreamSeriesType,
 }
 impl StravaActivityStreamsParams {
     /// Creates new activity streams parameters from a list of stream types
+    ///
+    /// Defaults to full resolution aligned on distance.
     #[must_use]
     pub fn new(keys: &[&str]) -> Self {
         Self {
             keys: keys.join(","),
+            resolution: None,
+            series_type: StreamSeriesType::default(),
+        }
+    }
+
+    /// Requests a reduced sampling resolution (`None` keeps every point)
+    #[must_use]
+    pub fn with_resolution(mut self, resolution: Option<StreamResolution>) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    /// Sets the series used to align the returned points
+    #[must_use]
+    pub fn with_series_type(mut self, series_type: StreamSeriesType) -> Self {
+        self.series_type = series_type;
+        self
+    }
+
+    fn to_query(&self) -> StravaActivityStreamsQuery<'_> {
+        StravaActivityStreamsQuery {
+            keys: &self.keys,
+            key_by_type: true,
+            series_type: self.series_type,
+            resolution: self.resolution,
         }
     }
 }
 
+/// Query string sent to `GET /activities/{id}/streams`
+#[derive(Serialize)]
+struct StravaActivityStreamsQuery<'a> {
+    keys: &'a str,
+    key_by_type: bool,
+    series_type: StreamSeriesType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resolution: Option<StreamResolution>,
+}
+
 pub struct StravaApiClient {
     pub integration_client: IntegrationClient,
     pub base_url: String,
+    /// Request budget, updated from every response and checked before every request
+    pub rate_limit: Arc<StravaRateLimiter>,
 }
 
 impl StravaApiClient {
-    /// Creates a new Strava API client
+    /// Creates a new Strava API client with its own rate limit tracker
     #[must_use]
     pub fn new(integration_client: IntegrationClient, base_url: String) -> Self {
         Self {
             integration_client,
             base_url,
+            rate_limit: Arc::new(StravaRateLimiter::new()),
         }
     }
 
+    /// Shares a rate limit tracker, e.g. one exposed through the application state
+    #[must_use]
+    pub fn with_rate_limiter(mut self, rate_limit: Arc<StravaRateLimiter>) -> Self {
+        self.rate_limit = rate_limit;
+        self
+    }
+
     /// Fetches the authenticated athlete's activities from Strava
     ///
     /// # Errors
     ///
-    /// Returns an error if the HTTP request fails or response deserialization fails
+    /// Returns an error if requests are paused near the rate limit, the HTTP request fails
+    /// or response deserialization fails
     pub async fn get_athlete_activities(
         &self,
         access_token: &str,
         query: Option<StravaActivitiesParams>,
     ) -> Result<Vec<StravaActivityResponse>, IntegrationError> {
         let url = format!("{}/athlete/activities", self.base_url);
+        self.rate_limit.check(Utc::now())?;
         let response = self
             .integration_client
             .get_with_query(&url, access_token, &query)
             .await?;
+        self.rate_limit.record(response.headers(), Utc::now());
         response
             .json::<Vec<StravaActivityResponse>>()
             .await
@@ -67,14 +143,17 @@ impl StravaApiClient {
     ///
     /// # Errors
     ///
-    /// Returns an error if the HTTP request fails or response deserialization fails
+    /// Returns an error if requests are paused near the rate limit, the HTTP request fails
+    /// or response deserialization fails
     pub async fn get_activity_details(
         &self,
         access_token: &str,
         external_id: i64,
     ) -> Result<StravaActivityResponse, IntegrationError> {
         let url = format!("{}/activities/{}", self.base_url, external_id);
+        self.rate_limit.check(Utc::now())?;
         let response = self.integration_client.get(&url, access_token).await?;
+        self.rate_limit.record(response.headers(), Utc::now());
         response
             .json::<StravaActivityResponse>()
             .await
@@ -85,7 +164,8 @@ impl StravaApiClient {
     ///
     /// # Errors
     ///
-    /// Returns an error if the HTTP request fails or response deserialization fails
+    /// Returns an error if requests are paused near the rate limit, the HTTP request fails
+    /// or response deserialization fails
     pub async fn get_activity_streams(
         &self,
         access_token: &str,
@@ -93,18 +173,54 @@ impl StravaApiClient {
         params: StravaActivityStreamsParams,
     ) -> Result<StravaActivityStreamResponse, IntegrationError> {
         let url = format!("{}/activities/{}/streams", self.base_url, external_id);
-        let query = serde_json::json!({
-            "keys": params.keys,
-            "key_by_type": true,
-            "series_type": "distance"
-        });
+        self.rate_limit.check(Utc::now())?;
         let response = self
             .integration_client
-            .get_with_query(&url, access_token, &query)
+            .get_with_query(&url, access_token, &params.to_query())
             .await?;
+        self.rate_limit.record(response.headers(), Utc::now());
         response
             .json::<StravaActivityStreamResponse>()
             .await
             .map_err(|e| IntegrationError::Deserialization(e.to_string()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query_string(params: &StravaActivityStreamsParams) -> String {
+        reqwest::Client::new()
+            .get("http://localhost/activities/1/streams")
+            .query(&params.to_query())
+            .build()
+            .expect("request should build")
+            .url()
+            .query()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    #[test]
+    fn test_default_query_keeps_current_behavior() {
+        let params = StravaActivityStreamsParams::new(&["time", "latlng"]);
+        let query = query_string(&params);
+
+        assert!(query.contains("keys=time%2Clatlng"), "{query}");
+        assert!(query.contains("key_by_type=true"), "{query}");
+        assert!(query.contains("series_type=distance"), "{query}");
+        assert!(!query.contains("resolution"), "{query}");
+    }
+
+    #[test]
+    fn test_query_carries_chosen_resolution() {
+        let params = StravaActivityStreamsParams::new(&["time"])
+            .with_resolution(Some(StreamResolution::Low))
+            .with_series_type(StreamSeriesType::Time);
+        let query = query_string(&params);
+
+        assert!(query.contains("resolution=low"), "{query}");
+        assert!(query.contains("series_type=time"), "{query}");
+    }
+}
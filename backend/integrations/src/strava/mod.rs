@@ -1,7 +1,9 @@
 // Strava API integration will be implemented here
 pub mod client;
+pub mod rate_limit;
 
 pub use client::*;
+pub use rate_limit::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -19,6 +21,23 @@ pub struct StravaActivityResponse {
     pub timezone: String,
     pub distance: f32,
     pub total_elevation_gain: f32,
+    /// Manually entered activity, recorded without a device and therefore without streams
+    #[serde(default)]
+    pub manual: bool,
+    /// Fastest efforts over standard distances (400m, 1k, 5k...), only on detailed runs
+    #[serde(default)]
+    pub best_efforts: Vec<StravaBestEffort>,
+}
+
+/// Fastest time over a standard distance within a run
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct StravaBestEffort {
+    /// Distance label, e.g. `1k`, `1 mile` or `Half-Marathon`
+    pub name: String,
+    /// Seconds taken to cover the distance
+    pub elapsed_time: i32,
+    /// Effort distance in meters
+    pub distance: f32,
 }
 
 /*
@@ -45,10 +64,129 @@ pub struct StravaActivityResponse {
   }
 } */
 #[derive(Deserialize, Serialize, Debug)]
+#[serde(try_from = "RawStreamResponse")]
 pub struct StravaActivityStreamResponse(pub HashMap<String, StreamData>);
 
+impl StravaActivityStreamResponse {
+    /// Whether Strava returned no time series at all, as for manually entered activities
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0
+            .get("time")
+            .is_none_or(|stream| stream.data.is_empty())
+    }
+}
+
+/// Wire format of the streams endpoint: keyed by type, or `[]` when the activity has none
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawStreamResponse {
+    Keyed(HashMap<String, StreamData>),
+    List(Vec<serde_json::Value>),
+}
+
+impl TryFrom<RawStreamResponse> for StravaActivityStreamResponse {
+    type Error = String;
+
+    fn try_from(raw: RawStreamResponse) -> Result<Self, Self::Error> {
+        match raw {
+            RawStreamResponse::Keyed(streams) => Ok(Self(streams)),
+            RawStreamResponse::List(streams) if streams.is_empty() => Ok(Self(HashMap::new())),
+            RawStreamResponse::List(_) => {
+                Err("Expected streams keyed by type (key_by_type=true)".to_string())
+            }
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct StreamData {
     pub data: Vec<serde_json::Value>,
     pub original_size: u32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_activity_has_no_streams() {
+        let activity: StravaActivityResponse = serde_json::from_str(
+            r#"{
+                "id": 12345,
+                "name": "Treadmill run",
+                "type": "Run",
+                "start_date": "2025-11-04T07:00:00Z",
+                "moving_time": 1800,
+                "elapsed_time": 1800,
+                "timezone": "(GMT+01:00) Europe/Paris",
+                "distance": 5000.0,
+                "total_elevation_gain": 0.0,
+                "manual": true
+            }"#,
+        )
+        .unwrap();
+        assert!(activity.manual);
+
+        for body in ["[]", "{}"] {
+            let streams: StravaActivityStreamResponse = serde_json::from_str(body).unwrap();
+            assert!(streams.is_empty(), "{body} should have no streams");
+        }
+    }
+
+    #[test]
+    fn test_detailed_run_includes_best_efforts() {
+        let activity: StravaActivityResponse =
+            serde_json::from_str(include_str!("fixtures/detailed_run.json")).unwrap();
+
+        assert_eq!(activity.id, 16_234_567_890);
+        assert_eq!(
+            activity.best_efforts,
+            vec![
+                StravaBestEffort {
+                    name: "400m".to_string(),
+                    elapsed_time: 118,
+                    distance: 400.0,
+                },
+                StravaBestEffort {
+                    name: "1k".to_string(),
+                    elapsed_time: 306,
+                    distance: 1000.0,
+                },
+                StravaBestEffort {
+                    name: "5k".to_string(),
+                    elapsed_time: 1671,
+                    distance: 5000.0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summary_activity_has_no_best_efforts() {
+        let activity: StravaActivityResponse = serde_json::from_str(
+            r#"{
+                "id": 12345,
+                "name": "Lunch Ride",
+                "type": "Ride",
+                "start_date": "2025-11-04T12:00:00Z",
+                "moving_time": 3600,
+                "elapsed_time": 3700,
+                "timezone": "(GMT+01:00) Europe/Paris",
+                "distance": 30000.0,
+                "total_elevation_gain": 250.0
+            }"#,
+        )
+        .unwrap();
+
+        assert!(activity.best_efforts.is_empty());
+    }
+
+    #[test]
+    fn test_recorded_activity_streams_are_not_empty() {
+        let streams: StravaActivityStreamResponse =
+            serde_json::from_str(r#"{"time": {"data": [0, 1, 2], "original_size": 3}}"#).unwrap();
+
+        assert!(!streams.is_empty());
+    }
+}
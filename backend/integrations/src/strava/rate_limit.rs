@@ -0,0 +1,259 @@
+use std::sync::{Mutex, PoisonError};
+
+use chrono::{DateTime, NaiveTime, Utc};
+use reqwest::header::HeaderMap;
+use serde::Serialize;
+
+use crate::common::IntegrationError;
+
+/// Header with the 15-minute and daily request limits, e.g. `200,2000`
+pub const RATE_LIMIT_LIMIT_HEADER: &str = "x-ratelimit-limit";
+/// Header with the requests used in the current 15-minute and daily windows, e.g. `34,340`
+pub const RATE_LIMIT_USAGE_HEADER: &str = "x-ratelimit-usage";
+
+/// Share of a window's limit, in percent, from which no new requests are issued
+///
+/// The remaining headroom absorbs requests already in flight.
+pub const PAUSE_AT_USAGE_PERCENT: u32 = 95;
+
+/// Strava resets the short-term window every quarter hour, on the clock
+const SHORT_TERM_WINDOW_SECONDS: i64 = 15 * 60;
+
+/// Usage of one Strava rate limit window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RateLimitWindow {
+    pub limit: u32,
+    pub usage: u32,
+}
+
+impl RateLimitWindow {
+    fn is_near_cap(self) -> bool {
+        u64::from(self.usage) * 100 >= u64::from(self.limit) * u64::from(PAUSE_AT_USAGE_PERCENT)
+    }
+
+    fn reset(self) -> Self {
+        Self {
+            limit: self.limit,
+            usage: 0,
+        }
+    }
+}
+
+/// Current Strava request budget, as last reported by Strava
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StravaRateLimitStatus {
+    /// 15-minute window, `None` until a Strava response has been seen
+    pub short_term: Option<RateLimitWindow>,
+    /// Daily window (resets at midnight UTC)
+    pub daily: Option<RateLimitWindow>,
+    /// When Strava last reported its usage
+    pub observed_at: Option<DateTime<Utc>>,
+    /// Set while requests are paused: when the exhausted window resets
+    pub paused_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Observation {
+    short_term: RateLimitWindow,
+    daily: RateLimitWindow,
+    observed_at: DateTime<Utc>,
+}
+
+/// Tracks Strava's rate limit headers and holds back requests near the cap
+///
+/// Shared by every Strava call of the process, so bulk syncs for one user
+/// cannot spend the budget of everyone else.
+#[derive(Debug, Default)]
+pub struct StravaRateLimiter {
+    last: Mutex<Option<Observation>>,
+}
+
+impl StravaRateLimiter {
+    /// Creates a tracker that allows requests until Strava reports its usage
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the usage reported in the headers of a Strava response
+    ///
+    /// Responses without (or with malformed) rate limit headers are ignored.
+    pub fn record(&self, headers: &HeaderMap, now: DateTime<Utc>) {
+        let (Some(limits), Some(usage)) = (
+            parse_window_pair(headers, RATE_LIMIT_LIMIT_HEADER),
+            parse_window_pair(headers, RATE_LIMIT_USAGE_HEADER),
+        ) else {
+            return;
+        };
+
+        *self.last.lock().unwrap_or_else(PoisonError::into_inner) = Some(Observation {
+            short_term: RateLimitWindow {
+                limit: limits.0,
+                usage: usage.0,
+            },
+            daily: RateLimitWindow {
+                limit: limits.1,
+                usage: usage.1,
+            },
+            observed_at: now,
+        });
+    }
+
+    /// Current budget, with usage of windows that reset since the last response cleared
+    #[must_use]
+    pub fn status(&self, now: DateTime<Utc>) -> StravaRateLimitStatus {
+        let last = *self.last.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(observation) = last else {
+            return StravaRateLimitStatus {
+                short_term: None,
+                daily: None,
+                observed_at: None,
+                paused_until: None,
+            };
+        };
+
+        let short_term_reset = next_short_term_reset(observation.observed_at);
+        let daily_reset = next_daily_reset(observation.observed_at);
+        let short_term = if now >= short_term_reset {
+            observation.short_term.reset()
+        } else {
+            observation.short_term
+        };
+        let daily = if now >= daily_reset {
+            observation.daily.reset()
+        } else {
+            observation.daily
+        };
+
+        let paused_until = if daily.is_near_cap() {
+            Some(daily_reset)
+        } else if short_term.is_near_cap() {
+            Some(short_term_reset)
+        } else {
+            None
+        };
+
+        StravaRateLimitStatus {
+            short_term: Some(short_term),
+            daily: Some(daily),
+            observed_at: Some(observation.observed_at),
+            paused_until,
+        }
+    }
+
+    /// Checks that a new Strava request may be issued
+    ///
+    /// # Errors
+    ///
+    /// Returns `IntegrationError::RateLimited` with the resume time while the
+    /// 15-minute or daily usage is at or above `PAUSE_AT_USAGE_PERCENT` of its limit
+    pub fn check(&self, now: DateTime<Utc>) -> Result<(), IntegrationError> {
+        match self.status(now).paused_until {
+            Some(resume_at) => Err(IntegrationError::RateLimited(resume_at)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parses a `<15-minute>,<daily>` rate limit header
+fn parse_window_pair(headers: &HeaderMap, name: &str) -> Option<(u32, u32)> {
+    let value = headers.get(name)?.to_str().ok()?;
+    let (short_term, daily) = value.split_once(',')?;
+    Some((short_term.trim().parse().ok()?, daily.trim().parse().ok()?))
+}
+
+fn next_short_term_reset(observed_at: DateTime<Utc>) -> DateTime<Utc> {
+    let next = (observed_at
+        .timestamp()
+        .div_euclid(SHORT_TERM_WINDOW_SECONDS)
+        + 1)
+        * SHORT_TERM_WINDOW_SECONDS;
+    DateTime::from_timestamp(next, 0).unwrap_or(observed_at)
+}
+
+fn next_daily_reset(observed_at: DateTime<Utc>) -> DateTime<Utc> {
+    observed_at
+        .date_naive()
+        .succ_opt()
+        .map_or(observed_at, |day| day.and_time(NaiveTime::MIN).and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339).unwrap().to_utc()
+    }
+
+    fn headers(limit: &'static str, usage: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RATE_LIMIT_LIMIT_HEADER, HeaderValue::from_static(limit));
+        headers.insert(RATE_LIMIT_USAGE_HEADER, HeaderValue::from_static(usage));
+        headers
+    }
+
+    #[test]
+    fn test_requests_allowed_before_any_response() {
+        let limiter = StravaRateLimiter::new();
+
+        assert!(limiter.check(at("2025-11-10T10:05:00Z")).is_ok());
+        assert_eq!(limiter.status(at("2025-11-10T10:05:00Z")).short_term, None);
+    }
+
+    #[test]
+    fn test_usage_near_short_term_cap_pauses_until_quarter_hour() {
+        let limiter = StravaRateLimiter::new();
+        let now = at("2025-11-10T10:05:00Z");
+
+        limiter.record(&headers("200,2000", "120,500"), now);
+        assert!(limiter.check(now).is_ok());
+
+        limiter.record(&headers("200,2000", "190,570"), now);
+        let result = limiter.check(now);
+
+        assert!(
+            matches!(result, Err(IntegrationError::RateLimited(resume_at)) if resume_at == at("2025-11-10T10:15:00Z"))
+        );
+        assert_eq!(
+            limiter.status(now).short_term,
+            Some(RateLimitWindow {
+                limit: 200,
+                usage: 190
+            })
+        );
+    }
+
+    #[test]
+    fn test_short_term_pause_lifts_once_window_resets() {
+        let limiter = StravaRateLimiter::new();
+        limiter.record(&headers("200,2000", "199,600"), at("2025-11-10T10:14:00Z"));
+
+        assert!(limiter.check(at("2025-11-10T10:14:59Z")).is_err());
+        assert!(limiter.check(at("2025-11-10T10:15:00Z")).is_ok());
+    }
+
+    #[test]
+    fn test_daily_cap_pauses_until_midnight_utc() {
+        let limiter = StravaRateLimiter::new();
+        limiter.record(&headers("200,2000", "10,1950"), at("2025-11-10T18:00:00Z"));
+
+        let status = limiter.status(at("2025-11-10T18:30:00Z"));
+
+        // The short-term window has reset but the daily one has not
+        assert_eq!(status.short_term.map(|w| w.usage), Some(0));
+        assert_eq!(status.paused_until, Some(at("2025-11-11T00:00:00Z")));
+    }
+
+    #[test]
+    fn test_malformed_headers_are_ignored() {
+        let limiter = StravaRateLimiter::new();
+        let now = at("2025-11-10T10:05:00Z");
+        limiter.record(&headers("200,2000", "199,1999"), now);
+
+        limiter.record(&headers("200", "lots"), now);
+
+        assert!(limiter.check(now).is_err(), "Previous usage must be kept");
+    }
+}
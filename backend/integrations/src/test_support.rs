@@ -0,0 +1,122 @@
+//! Local HTTP server and provider fixtures for tests
+//!
+//! Compiled for this crate's tests and, through the `test-support` feature,
+//! for the tests of the crates depending on it.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Spotify search result with a single track: "Don't Stop Me Now" by Queen
+pub const SPOTIFY_SEARCH_QUEEN: &str = r#"{"tracks":{"items":[{"id":"7hQJA50XrCWABAu5v6QZ4i","name":"Don't Stop Me Now","artists":[{"id":"1dfeR4HaWDbWqFHLkxsg1d","name":"Queen"}],"album":{"id":"6i6folBtxKV28WX3msQ4FE","name":"Jazz","images":[]},"external_urls":{"spotify":"https://open.spotify.com/track/7hQJA50XrCWABAu5v6QZ4i"},"duration_ms":209413}]}}"#;
+
+/// Spotify search result without any track
+pub const SPOTIFY_SEARCH_EMPTY: &str = r#"{"tracks":{"items":[]}}"#;
+
+/// Raw `HTTP/1.1` response with `status` (e.g. `200 OK`) and an empty body
+#[must_use]
+pub fn empty_response(status: &str) -> String {
+    format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+}
+
+/// Raw `HTTP/1.1 200 OK` response carrying `body` as JSON
+#[must_use]
+pub fn json_response(body: &str) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// Spawns a local HTTP server answering every request with `respond(request)`
+///
+/// `request` is the full raw request, head and body. Returns the server base URL
+/// (`http://127.0.0.1:<port>`).
+///
+/// # Panics
+///
+/// Panics if no local port can be bound
+pub async fn spawn_mock_server<F>(respond: F) -> String
+where
+    F: Fn(&str) -> String + Send + Sync + 'static,
+{
+    spawn_slow_mock_server(Duration::ZERO, respond).await
+}
+
+/// Like `spawn_mock_server`, waiting `delay` before answering each request
+///
+/// # Panics
+///
+/// Panics if no local port can be bound
+pub async fn spawn_slow_mock_server<F>(delay: Duration, respond: F) -> String
+where
+    F: Fn(&str) -> String + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let respond = Arc::new(respond);
+
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let respond = Arc::clone(&respond);
+            tokio::spawn(async move {
+                let request = read_request(&mut socket).await;
+                tokio::time::sleep(delay).await;
+                socket.write_all(respond(&request).as_bytes()).await.ok();
+            });
+        }
+    });
+
+    base_url
+}
+
+/// Base URL of a local port nothing listens on
+///
+/// # Panics
+///
+/// Panics if no local port can be bound
+pub async fn closed_port_url() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    base_url
+}
+
+/// Body of a raw HTTP request, empty without one
+#[must_use]
+pub fn request_body(request: &str) -> &str {
+    request.split_once("\r\n\r\n").map_or("", |(_, body)| body)
+}
+
+/// Reads one HTTP/1.1 request, up to the end of its `Content-Length` body
+async fn read_request(socket: &mut TcpStream) -> String {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    loop {
+        let read = socket.read(&mut chunk).await.unwrap_or(0);
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+
+        let request = String::from_utf8_lossy(&buffer);
+        if let Some(header_end) = request.find("\r\n\r\n") {
+            let content_length = request[..header_end]
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if buffer.len() >= header_end + 4 + content_length {
+                break;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&buffer).into_owned()
+}
@@ -6,6 +6,18 @@ mod m20251014_110258_add_password_to_user;
 mod m20251015_112925_create_table_activities;
 mod m20251023_222522_create_table_tracks_listens;
 mod m20251029_210155_add_lastfm_name_col_user;
+mod m20251102_090000_create_table_refresh_token;
+mod m20251103_090000_add_bpm_to_track;
+mod m20251104_090000_add_streams_unavailable_to_activity;
+mod m20251105_090000_add_grade_moving_to_activity_stream;
+mod m20251106_090000_add_listen_padding_to_user;
+mod m20251107_090000_add_spotify_match_to_track;
+mod m20251108_090000_add_time_offset_to_activity;
+mod m20251109_090000_add_summary_stats_to_activity;
+mod m20251110_090000_create_table_best_effort;
+mod m20251111_090000_add_stream_original_size_to_activity;
+mod m20251112_090000_add_best_efforts_fetched_at_to_activity;
+mod m20251113_090000_add_spotify_searched_at_to_track;
 
 pub struct Migrator;
 
@@ -19,6 +31,18 @@ impl MigratorTrait for Migrator {
             Box::new(m20251015_112925_create_table_activities::Migration),
             Box::new(m20251023_222522_create_table_tracks_listens::Migration),
             Box::new(m20251029_210155_add_lastfm_name_col_user::Migration),
+            Box::new(m20251102_090000_create_table_refresh_token::Migration),
+            Box::new(m20251103_090000_add_bpm_to_track::Migration),
+            Box::new(m20251104_090000_add_streams_unavailable_to_activity::Migration),
+            Box::new(m20251105_090000_add_grade_moving_to_activity_stream::Migration),
+            Box::new(m20251106_090000_add_listen_padding_to_user::Migration),
+            Box::new(m20251107_090000_add_spotify_match_to_track::Migration),
+            Box::new(m20251108_090000_add_time_offset_to_activity::Migration),
+            Box::new(m20251109_090000_add_summary_stats_to_activity::Migration),
+            Box::new(m20251110_090000_create_table_best_effort::Migration),
+            Box::new(m20251111_090000_add_stream_original_size_to_activity::Migration),
+            Box::new(m20251112_090000_add_best_efforts_fetched_at_to_activity::Migration),
+            Box::new(m20251113_090000_add_spotify_searched_at_to_track::Migration),
         ]
     }
 }
@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Add the tempo (beats per minute) column to the track table
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Track::Table)
+                    .add_column(ColumnDef::new(Track::Bpm).float().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Remove the tempo column from the track table
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Track::Table)
+                    .drop_column(Track::Bpm)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Track {
+    Table,
+    Bpm,
+}
@@ -0,0 +1,42 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Flag activities without Strava streams (e.g. manually entered ones)
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Activity::Table)
+                    .add_column(
+                        ColumnDef::new(Activity::StreamsUnavailable)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Remove the streams availability flag from the activity table
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Activity::Table)
+                    .drop_column(Activity::StreamsUnavailable)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Activity {
+    Table,
+    StreamsUnavailable,
+}
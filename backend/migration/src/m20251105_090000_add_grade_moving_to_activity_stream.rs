@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Store Strava's grade_smooth (%) and moving streams, both optional per activity
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ActivityStream::Table)
+                    .add_column(ColumnDef::new(ActivityStream::Grade).float())
+                    .add_column(ColumnDef::new(ActivityStream::Moving).boolean())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Remove the grade and moving columns from the activity stream table
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ActivityStream::Table)
+                    .drop_column(ActivityStream::Grade)
+                    .drop_column(ActivityStream::Moving)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ActivityStream {
+    Table,
+    Grade,
+    Moving,
+}
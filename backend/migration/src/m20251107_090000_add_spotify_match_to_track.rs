@@ -0,0 +1,44 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Record the Spotify track a track was matched to and how confident the match was
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Track::Table)
+                    .add_column(ColumnDef::new(Track::SpotifyId).text().null())
+                    .add_column(
+                        ColumnDef::new(Track::SpotifyMatchConfidence)
+                            .double()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Remove the Spotify match columns from the track table
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Track::Table)
+                    .drop_column(Track::SpotifyId)
+                    .drop_column(Track::SpotifyMatchConfidence)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Track {
+    Table,
+    SpotifyId,
+    SpotifyMatchConfidence,
+}
@@ -0,0 +1,42 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Per-activity shift (seconds) applied to listen times to correct clock drift
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Activity::Table)
+                    .add_column(
+                        ColumnDef::new(Activity::TimeOffsetSeconds)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Remove the listen time offset from the activity table
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Activity::Table)
+                    .drop_column(Activity::TimeOffsetSeconds)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Activity {
+    Table,
+    TimeOffsetSeconds,
+}
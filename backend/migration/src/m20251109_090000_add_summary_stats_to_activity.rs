@@ -0,0 +1,53 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Summary stats computed from the streams once they are synced
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Activity::Table)
+                    .add_column(ColumnDef::new(Activity::AvgHeartRate).double().null())
+                    .add_column(ColumnDef::new(Activity::MaxHeartRate).integer().null())
+                    .add_column(ColumnDef::new(Activity::StreamElevationGain).float().null())
+                    .add_column(ColumnDef::new(Activity::StreamMovingTime).integer().null())
+                    .add_column(
+                        ColumnDef::new(Activity::SummaryComputedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Remove the stream summary columns from the activity table
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Activity::Table)
+                    .drop_column(Activity::AvgHeartRate)
+                    .drop_column(Activity::MaxHeartRate)
+                    .drop_column(Activity::StreamElevationGain)
+                    .drop_column(Activity::StreamMovingTime)
+                    .drop_column(Activity::SummaryComputedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Activity {
+    Table,
+    AvgHeartRate,
+    MaxHeartRate,
+    StreamElevationGain,
+    StreamMovingTime,
+    SummaryComputedAt,
+}
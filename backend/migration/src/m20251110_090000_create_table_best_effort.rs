@@ -0,0 +1,87 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Strava's fastest efforts over standard distances within a run
+        manager
+            .create_table(
+                Table::create()
+                    .table(BestEffort::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(BestEffort::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default(Expr::cust("gen_random_uuid()")),
+                    )
+                    .col(ColumnDef::new(BestEffort::ActivityId).uuid().not_null())
+                    .col(ColumnDef::new(BestEffort::Name).text().not_null())
+                    .col(ColumnDef::new(BestEffort::ElapsedTime).integer().not_null())
+                    .col(ColumnDef::new(BestEffort::Distance).float().not_null())
+                    .col(
+                        ColumnDef::new(BestEffort::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-best_effort-activity_id")
+                            .from(BestEffort::Table, BestEffort::ActivityId)
+                            .to(Activity::Table, Activity::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-best_effort-activity_id")
+                    .table(BestEffort::Table)
+                    .col(BestEffort::ActivityId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BestEffort::Table).to_owned())
+            .await
+    }
+}
+
+/*
+CREATE TABLE best_effort (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    activity_id UUID NOT NULL REFERENCES activity(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    elapsed_time INTEGER NOT NULL,
+    distance REAL NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+*/
+
+#[derive(DeriveIden)]
+enum BestEffort {
+    Table,
+    Id,
+    ActivityId,
+    Name,
+    ElapsedTime,
+    Distance,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Activity {
+    Table,
+    Id,
+}
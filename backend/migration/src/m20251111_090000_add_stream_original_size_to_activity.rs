@@ -0,0 +1,41 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Stream points fetched from Strava, before ingestion downsampling dropped any
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Activity::Table)
+                    .add_column(
+                        ColumnDef::new(Activity::StreamOriginalSize)
+                            .integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Remove the original stream size from the activity table
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Activity::Table)
+                    .drop_column(Activity::StreamOriginalSize)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Activity {
+    Table,
+    StreamOriginalSize,
+}
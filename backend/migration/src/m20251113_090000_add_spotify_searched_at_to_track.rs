@@ -0,0 +1,41 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // When the track was last searched on Spotify, so tracks without a match aren't searched again
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Track::Table)
+                    .add_column(
+                        ColumnDef::new(Track::SpotifySearchedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Remove the Spotify search marker from the track table
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Track::Table)
+                    .drop_column(Track::SpotifySearchedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Track {
+    Table,
+    SpotifySearchedAt,
+}
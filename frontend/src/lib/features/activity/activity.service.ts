@@ -1,16 +1,20 @@
 import { apiClient } from "$lib/shared/api/client";
 import { API_ENDPOINTS } from "$lib/shared/api/endpoints";
-import type { ActivityStream, StravaActivity } from "$lib/shared/api/types";
+import type {
+  ActivityStream,
+  Paginated,
+  StravaActivity,
+} from "$lib/shared/api/types";
 
 class ActivitiesService {
   /**
    * Fetch all Strava activities for the authenticated user
    */
   async getActivities(): Promise<StravaActivity[]> {
-    const response = await apiClient.get<StravaActivity[]>(
+    const response = await apiClient.get<Paginated<StravaActivity>>(
       API_ENDPOINTS.strava.activities,
     );
-    return response;
+    return response.items;
   }
 
   /**
@@ -2,9 +2,18 @@
  * GeoJSON transformation utilities for converting activity data to map-ready formats.
  */
 
-import type { MusicSegment, ActivityStream } from "$lib/shared/api/types";
+import type {
+  GpsPoint,
+  MusicSegment,
+  ActivityStream,
+} from "$lib/shared/api/types";
 import { getTrackColor } from "./track-colors";
 
+/**
+ * GPS point with coordinates present (indoor points have null lat/lng).
+ */
+type LocatedPoint = GpsPoint & { latitude: number; longitude: number };
+
 /**
  * Represents bounding box coordinates for map viewport.
  */
@@ -39,7 +48,7 @@ export function createRouteGeoJSON(
         type: "LineString",
         coordinates: segment.points
           .filter(
-            (point) =>
+            (point): point is LocatedPoint =>
               typeof point.latitude === "number" &&
               typeof point.longitude === "number",
           )
@@ -1,4 +1,5 @@
 import { goto } from "$app/navigation";
+import { ApiRequestError } from "./errors";
 import type { ApiError } from "./types";
 
 class ApiClient {
@@ -67,7 +68,11 @@ class ApiClient {
     }
 
     // Throw l'erreur pour que le composant puisse la catch
-    throw new Error(errorData.message || errorData.error);
+    throw new ApiRequestError(
+      errorData.message || errorData.error,
+      errorData.status ?? response.status,
+      errorData.code,
+    );
   }
 
   /**
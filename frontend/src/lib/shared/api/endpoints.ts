@@ -8,22 +8,58 @@ export const API_ENDPOINTS = {
     me: "/api/auth/me",
   },
   oauth: {
-    authorize: (provider: OauthProvider) => `/api/oauth/${provider}/authorize`,
+    authorize: (provider: OauthProvider, scopes?: string[]) =>
+      scopes?.length
+        ? `/api/oauth/${provider}/authorize?scopes=${encodeURIComponent(scopes.join(","))}`
+        : `/api/oauth/${provider}/authorize`,
     disconnect: (provider: OauthProvider) =>
       `/api/oauth/${provider}/disconnect`,
   },
   user: {
     update: "/api/user",
+    lastfm: "/api/user/lastfm",
+  },
+  events: "/api/events",
+  stats: {
+    trainingLoad: "/api/stats/training-load",
   },
   strava: {
     activities: "/api/strava/activities",
+    activityOverlaps: "/api/strava/activities/overlaps",
     syncActivities: "/api/strava/activities/sync",
+    rateLimit: "/api/strava/ratelimit",
     activityStreams: (id: string) => `/api/strava/activities/${id}/streams`,
+    activityStreamStats: (id: string) =>
+      `/api/strava/activities/${id}/streams/stats`,
+    activitySummary: (id: string) => `/api/strava/activities/${id}/summary`,
+    activityBestEfforts: (id: string) =>
+      `/api/strava/activities/${id}/best-efforts`,
+    activityStreamChannels: (id: string) =>
+      `/api/strava/activities/${id}/streams/channels`,
+    activityPauses: (id: string) => `/api/strava/activities/${id}/pauses`,
+    similarActivities: (id: string) => `/api/strava/activities/${id}/similar`,
+    activityElevation: (id: string) =>
+      `/api/strava/activities/${id}/elevation`,
     syncActivityStreams: (id: string) =>
       `/api/strava/activities/${id}/streams/sync`,
     syncAllActivityStreams: "/api/strava/activities/streams/sync",
+    importActivity: (externalId: string) =>
+      `/api/strava/activities/${externalId}/import`,
+  },
+  music: {
+    range: "/api/music/range",
+    enrich: "/api/music/enrich",
+    track: (trackId: string) => `/api/music/tracks/${trackId}`,
   },
   activities: {
     music: (activityId: string) => `/api/activities/${activityId}/music`,
+    musicDensity: (activityId: string, bucketSeconds?: number) =>
+      bucketSeconds === undefined
+        ? `/api/activities/${activityId}/music/density`
+        : `/api/activities/${activityId}/music/density?bucket_seconds=${bucketSeconds}`,
+    musicAt: (activityId: string, timestamp: number) =>
+      `/api/activities/${activityId}/music/at?t=${timestamp}`,
+    musicOffset: (activityId: string) =>
+      `/api/activities/${activityId}/music/offset`,
   },
 } as const;
@@ -0,0 +1,15 @@
+/**
+ * Erreur levée par le client API pour toute réponse non-2xx.
+ * `code` est stable (ex. "activity_not_found") : préférer le tester plutôt que `message`.
+ */
+export class ApiRequestError extends Error {
+  readonly code?: string;
+  readonly status: number;
+
+  constructor(message: string, status: number, code?: string) {
+    super(message);
+    this.name = "ApiRequestError";
+    this.status = status;
+    this.code = code;
+  }
+}
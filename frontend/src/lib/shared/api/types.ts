@@ -1,6 +1,7 @@
 // API Error types
 export interface ApiError {
   error: string;
+  code?: string;
   message?: string;
   status: number;
 }
@@ -29,6 +30,8 @@ export interface User {
   id: string;
   email: string;
   lastfm_username?: string | null;
+  listen_padding_before_seconds?: number;
+  listen_padding_after_seconds?: number;
   oauth_connections?: OauthConnection;
 }
 
@@ -49,6 +52,39 @@ export interface StravaActivity {
   start_time: string;
   timezone: string;
   description?: string;
+  streams_unavailable: boolean;
+  time_offset_seconds: number;
+}
+
+export type TrainingLoadPeriod = "week" | "month";
+
+export interface ActivityOverlap {
+  first_activity_id: string;
+  second_activity_id: string;
+  overlap_start: string;
+  overlap_end: string;
+  overlap_seconds: number;
+}
+
+export interface TrainingLoadBucket {
+  period_start: string;
+  activities: number;
+  distance: number;
+  elapsed_time: number;
+  elevation_gain: number;
+  load: number;
+}
+
+export interface TrainingLoadResponse {
+  period: TrainingLoadPeriod;
+  buckets: TrainingLoadBucket[];
+}
+
+export interface Paginated<T> {
+  items: T[];
+  total: number;
+  page: number;
+  per_page: number | null;
 }
 
 // Music types
@@ -57,6 +93,27 @@ export interface TrackInfo {
   track_name: string;
   artist_name: string;
   album_name?: string;
+  bpm?: number;
+}
+
+export interface TrackDetails extends TrackInfo {
+  artist_mbid?: string;
+  track_mbid?: string;
+  album_mbid?: string;
+  lastfm_url?: string;
+  spotify_id?: string;
+}
+
+export interface ListenRange {
+  first_listen_at: string | null;
+  last_listen_at: string | null;
+  total_listens: number;
+}
+
+export interface EnrichmentReport {
+  matched: number;
+  unmatched: number;
+  remaining: number;
 }
 
 export interface TrackWithTimestamp {
@@ -70,13 +127,13 @@ export interface TrackWithTimestamp {
 
 export interface GpsPoint {
   time: string;
-  latitude: number;
-  longitude: number;
-  altitude?: number;
-  heart_rate?: number;
-  cadence?: number;
-  watts?: number;
-  velocity?: number;
+  latitude: number | null;
+  longitude: number | null;
+  altitude?: number | null;
+  heart_rate?: number | null;
+  cadence?: number | null;
+  watts?: number | null;
+  velocity?: number | null;
 }
 
 export interface MusicSegment {
@@ -84,7 +141,16 @@ export interface MusicSegment {
   track?: TrackInfo;
   start_time: string;
   end_time: string;
+  duration_seconds: number;
   points: GpsPoint[];
+  avg_temperature: number | null;
+  repeated: boolean;
+  after_gap: boolean;
+}
+
+export interface TrackPlayCount {
+  track: TrackInfo;
+  play_count: number;
 }
 
 export interface SimplificationStats {
@@ -94,6 +160,10 @@ export interface SimplificationStats {
   original_points: number;
   simplified_points: number;
   reduction_ratio: number;
+  music_coverage_ratio: number;
+  avg_bpm: number | null;
+  avg_temperature: number | null;
+  play_counts: TrackPlayCount[];
 }
 
 export interface ActivityMusicResponse {
@@ -103,6 +173,26 @@ export interface ActivityMusicResponse {
   stats: SimplificationStats;
 }
 
+export interface ActivityTrackAtResponse {
+  activity_id: string;
+  at: string;
+  track: TrackInfo | null;
+  distance: number | null; // Meters covered at `at`, null without a distance stream
+}
+
+export interface DensityBucket {
+  index: number;
+  start_time: string;
+  end_time: string;
+  distinct_tracks: number;
+}
+
+export interface ActivityListenDensityResponse {
+  activity_id: string;
+  bucket_seconds: number;
+  buckets: DensityBucket[];
+}
+
 export interface ActivityStreamPoint {
   activity_id: string;
   time: string;
@@ -115,6 +205,80 @@ export interface ActivityStreamPoint {
   cadence: number | null;
   watts: number | null;
   temperature: number | null;
+  grade: number | null;
+  moving: boolean | null;
 }
 
 export type ActivityStream = ActivityStreamPoint[];
+
+export interface ActivityStreamChannels {
+  activity_id: string;
+  channels: {
+    has_hr: boolean;
+    has_watts: boolean;
+    has_latlng: boolean;
+    has_cadence: boolean;
+    has_altitude: boolean;
+    has_temperature: boolean;
+  };
+}
+
+export interface ElevationPoint {
+  distance: number;
+  altitude: number;
+}
+
+export interface ActivityElevationResponse {
+  activity_id: string;
+  points: ElevationPoint[];
+}
+
+export interface ActivitySummary {
+  avg_heart_rate: number | null;
+  max_heart_rate: number | null;
+  elevation_gain: number | null;
+  moving_time_seconds: number | null;
+  computed_at: string | null;
+}
+
+export interface ActivitySummaryResponse {
+  activity_id: string;
+  summary: ActivitySummary;
+}
+
+export interface BestEffort {
+  id: string;
+  activity_id: string;
+  name: string;
+  elapsed_time: number;
+  distance: number;
+  created_at: string;
+}
+
+export interface ActivityBestEffortsResponse {
+  activity_id: string;
+  best_efforts: BestEffort[];
+}
+
+export interface SimilarActivity {
+  activity: StravaActivity;
+  bounds_overlap: number;
+  frechet_meters: number;
+}
+
+export interface SimilarActivitiesResponse {
+  activity_id: string;
+  similar: SimilarActivity[];
+}
+
+export interface StreamSyncResponse {
+  message: string;
+  points: number;
+  original_points: number;
+  streams_unavailable: boolean;
+}
+
+export interface ActivityImportResponse {
+  activity: StravaActivity;
+  streams: StreamSyncResponse;
+}